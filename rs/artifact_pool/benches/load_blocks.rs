@@ -12,7 +12,7 @@ use ic_test_utilities::{
     types::ids::{node_test_id, subnet_test_id},
     types::messages::SignedIngressBuilder,
 };
-use ic_types::batch::SelfValidatingPayload;
+use ic_types::batch::{CanisterHttpPayload, SelfValidatingPayload};
 use ic_types::{
     batch::{BatchPayload, IngressPayload, XNetPayload},
     consensus::{dkg, Block, BlockProposal, HasHeight, Payload, Rank},
@@ -58,7 +58,12 @@ fn prepare(pool: &mut ConsensusPoolImpl, num: usize) {
         block.payload = Payload::new(
             ic_crypto::crypto_hash,
             (
-                BatchPayload::new(ingress, xnet, self_validating),
+                BatchPayload::new(
+                    ingress,
+                    xnet,
+                    self_validating,
+                    CanisterHttpPayload::default(),
+                ),
                 dkg::Dealings::new_empty(parent.payload.as_ref().dkg_interval_start_height()),
             )
                 .into(),
@@ -1,21 +1,27 @@
 //! An agent to talk to the Internet Computer through the public endpoints.
 use crate::{
-    cbor::{parse_canister_query_response, parse_read_state_response, RequestStatus},
-    http_client::{HttpClient, HttpClientConfig},
+    cbor::{
+        parse_canister_query_response, parse_read_state_certificate, parse_request_status,
+        RequestStatus,
+    },
+    certification::verify_certificate,
+    http_client::{HttpClient, HttpClientConfig, HttpClientInterface},
 };
 use backoff::backoff::Backoff;
 use ed25519_dalek::{Keypair, Signer, KEYPAIR_LENGTH};
 use ic_crypto_sha::Sha256;
 use ic_crypto_tree_hash::Path;
-use ic_interfaces::crypto::DOMAIN_IC_REQUEST;
+use ic_interfaces::crypto::{Signable, DOMAIN_IC_REQUEST};
 use ic_protobuf::types::v1 as pb;
 use ic_types::{
     consensus::catchup::CatchUpPackageParam,
+    crypto::threshold_sig::ThresholdSigPublicKey,
+    ic00::{CanisterIdRecord, Payload, IC_00},
     messages::{
-        Blob, HttpQueryContent, HttpReadStateContent, HttpRequestEnvelope, HttpStatusResponse,
-        HttpSubmitContent, MessageId, ReplicaHealthStatus,
+        Blob, Delegation, HttpQueryContent, HttpReadStateContent, HttpRequestEnvelope,
+        HttpStatusResponse, HttpSubmitContent, MessageId, ReplicaHealthStatus, SignedDelegation,
     },
-    CanisterId, PrincipalId,
+    CanisterId, PrincipalId, Time,
 };
 use prost::Message;
 use serde_cbor::value::Value as CBOR;
@@ -113,6 +119,18 @@ pub enum Sender {
     Anonymous,
     /// Principal ID (no signature)
     PrincipalId(PrincipalId),
+    /// The sender is the identity of `pub_key`, which delegated signing
+    /// requests to the key of `session` through a chain of signed
+    /// delegations.
+    Delegated {
+        /// DER encoded public key of the delegating identity
+        pub_key: Vec<u8>,
+        /// The delegations, starting with the one signed with `pub_key` and
+        /// ending with the one to the public key of `session`.
+        delegations: Vec<SignedDelegation>,
+        /// The sender that signs the requests.
+        session: Box<Sender>,
+    },
 }
 
 impl Sender {
@@ -134,6 +152,55 @@ impl Sender {
         Sender::PrincipalId(principal_id)
     }
 
+    /// Delegates signing requests on behalf of this sender to `session` until
+    /// `expiration`. If `targets` is specified, the delegation is restricted
+    /// to requests to these canisters.
+    ///
+    /// Delegating from a `Delegated` sender extends its delegation chain.
+    pub fn delegate_to(
+        &self,
+        session: Sender,
+        expiration: Time,
+        targets: Option<Vec<CanisterId>>,
+    ) -> Result<Self, Box<dyn Error>> {
+        if let Self::Delegated { .. } = session {
+            return Err("cannot delegate to a delegated sender".into());
+        }
+        let session_pub_key = session
+            .sender_pubkey_der()
+            .ok_or("cannot delegate to a sender without a public key")?;
+        let delegation = match targets {
+            Some(targets) => Delegation::new_with_targets(session_pub_key, expiration, targets),
+            None => Delegation::new(session_pub_key, expiration),
+        };
+        let signature = self
+            .sign(&delegation.as_signed_bytes())?
+            .ok_or("cannot delegate from a sender that does not sign requests")?;
+        let signed_delegation = SignedDelegation::new(delegation, signature);
+
+        let (pub_key, delegations) = match self {
+            Self::Delegated {
+                pub_key,
+                delegations,
+                ..
+            } => {
+                let mut delegations = delegations.clone();
+                delegations.push(signed_delegation);
+                (pub_key.clone(), delegations)
+            }
+            _ => (
+                self.sender_pubkey_der()
+                    .ok_or("cannot delegate from a sender without a public key")?,
+                vec![signed_delegation],
+            ),
+        };
+        Ok(Self::Delegated {
+            pub_key,
+            delegations,
+            session: Box::new(session),
+        })
+    }
+
     pub fn get_principal_id(&self) -> PrincipalId {
         match self {
             Self::KeyPair(keypair) => PrincipalId::new_self_authenticating(
@@ -147,6 +214,7 @@ impl Sender {
             Self::ExternalHsm { pub_key, .. } => PrincipalId::new_self_authenticating(pub_key),
             Self::Anonymous => PrincipalId::new_anonymous(),
             Self::PrincipalId(id) => *id,
+            Self::Delegated { pub_key, .. } => PrincipalId::new_self_authenticating(pub_key),
         }
     }
 
@@ -177,6 +245,7 @@ impl Sender {
             Self::ExternalHsm { sign, .. } => sign(msg).map(Some),
             Self::Anonymous => Ok(None),
             Self::PrincipalId(_) => Ok(None),
+            Self::Delegated { session, .. } => session.sign(msg),
         }
     }
 
@@ -193,6 +262,16 @@ impl Sender {
             Self::ExternalHsm { pub_key, .. } => Some(pub_key.clone()),
             Self::Anonymous => None,
             Self::PrincipalId(_) => None,
+            Self::Delegated { pub_key, .. } => Some(pub_key.clone()),
+        }
+    }
+
+    /// The delegations to include in the request envelope, if the requests
+    /// are signed by a delegate of the sender.
+    pub fn sender_delegation(&self) -> Option<Vec<SignedDelegation>> {
+        match self {
+            Self::Delegated { delegations, .. } => Some(delegations.clone()),
+            _ => None,
         }
     }
 }
@@ -210,6 +289,14 @@ pub fn get_backoff_policy() -> backoff::ExponentialBackoff {
     }
 }
 
+/// Returns the next interval of a backoff policy created with
+/// [get_backoff_policy], which never runs out of intervals.
+pub(crate) fn next_backoff_interval(backoff: &mut backoff::ExponentialBackoff) -> Duration {
+    backoff.next_backoff().expect(
+        "Backoff interval MUST be available. If you see this error the backoff is misconfigured.",
+    )
+}
+
 /// An agent to talk to the Internet Computer through the public endpoints.
 #[derive(Clone)]
 pub struct Agent {
@@ -227,13 +314,17 @@ pub struct Agent {
 
     // Per reqwest document, cloning a client does not clone the actual connection pool inside.
     // Therefore directly owning a client as opposed to a reference is the standard way to go.
-    http_client: Arc<HttpClient>,
+    http_client: Arc<dyn HttpClientInterface>,
 
     pub sender: Sender,
 
     /// The values that any 'sender' field should have when issuing
     /// calls with the user corresponding to this Agent.
     pub sender_field: Blob,
+
+    /// The root public key of the Internet Computer. If set, the certificates
+    /// returned by `read_state` requests are verified against it.
+    root_key: Option<ThresholdSigPublicKey>,
 }
 
 impl fmt::Debug for Agent {
@@ -284,7 +375,11 @@ impl Agent {
     }
 
     /// Helper to create the agent
-    fn build_agent(url: Url, http_client: Arc<HttpClient>, sender: Sender) -> Self {
+    pub(crate) fn build_agent(
+        url: Url,
+        http_client: Arc<dyn HttpClientInterface>,
+        sender: Sender,
+    ) -> Self {
         let sender_field = Blob(sender.get_principal_id().into_vec());
         Self {
             url,
@@ -293,6 +388,7 @@ impl Agent {
             http_client,
             sender,
             sender_field,
+            root_key: None,
         }
     }
 
@@ -308,6 +404,13 @@ impl Agent {
         self
     }
 
+    /// Sets the root public key against which the certificates of the
+    /// statuses of update calls are verified.
+    pub fn with_root_key(mut self, root_key: ThresholdSigPublicKey) -> Self {
+        self.root_key = Some(root_key);
+        self
+    }
+
     /// Queries the cup endpoint given the provided CatchUpPackageParams.
     pub async fn query_cup_endpoint(
         &self,
@@ -385,6 +488,7 @@ impl Agent {
     ) -> Result<Option<Vec<u8>>, String> {
         let deadline = Instant::now() + self.ingress_timeout;
         let mut backoff = get_backoff_policy();
+        let effective_canister_id = effective_canister_id(canister_id, &arguments);
         let (http_body, request_id) = self
             .prepare_update(canister_id, method, arguments, nonce)
             .map_err(|err| format!("{}", err))?;
@@ -404,9 +508,9 @@ impl Agent {
         // will take at least the time between consensus blocks.
         while next_poll_time < deadline {
             sleep_until(tokio::time::Instant::from_std(next_poll_time)).await;
-            next_poll_time = Instant::now() + backoff.next_backoff().expect("Backoff interval MUST be available. If you see this error the backoff is misconfigured.");
+            next_poll_time = Instant::now() + next_backoff_interval(&mut backoff);
             match self
-                .wait_ingress(request_id.clone(), deadline, canister_id)
+                .wait_ingress(request_id.clone(), deadline, &effective_canister_id)
                 .await
             {
                 Ok(request_status) => match request_status.status.as_ref() {
@@ -460,19 +564,30 @@ impl Agent {
     }
 
     /// Requests the status of a pending canister update call request exactly
-    /// once using the `read_state` API.
+    /// once using the `read_state` API. If the agent has a root key, the
+    /// certificate containing the status is verified against it.
+    ///
+    /// `effective_canister_id` is the canister the call was executed on
+    /// behalf of, i.e. the canister in the arguments of a call to the
+    /// management canister. The subnet that certified the status must be
+    /// responsible for it.
     ///
     /// This is intended to be used in a loop until a final state is reached.
     pub async fn wait_ingress(
         &self,
         request_id: MessageId,
         deadline: Instant,
-        canister_id: &CanisterId,
+        effective_canister_id: &CanisterId,
     ) -> Result<RequestStatus, String> {
         let cbor = self
-            .request_status_once(request_id.clone(), deadline, canister_id)
+            .request_status_once(request_id.clone(), deadline, effective_canister_id)
             .await?;
-        parse_read_state_response(&request_id, cbor)
+        let certificate = parse_read_state_certificate(cbor)?;
+        if let Some(root_key) = &self.root_key {
+            verify_certificate(&certificate, effective_canister_id, root_key)
+                .map_err(|err| format!("invalid certificate: {}", err))?;
+        }
+        parse_request_status(&request_id, certificate)
     }
 
     async fn get_status(&self) -> Result<HttpStatusResponse, String> {
@@ -503,7 +618,7 @@ impl Agent {
         false
     }
 
-    pub fn http_client(&self) -> &HttpClient {
+    pub fn http_client(&self) -> &dyn HttpClientInterface {
         self.http_client.as_ref()
     }
 }
@@ -528,6 +643,18 @@ pub fn ed25519_public_key_to_der(mut key: Vec<u8>) -> Vec<u8> {
     encoded
 }
 
+/// Returns the canister that a call to `canister_id` with the given
+/// `arguments` is executed on behalf of. Calls to the management canister
+/// refer to the canister in their arguments, if there is one.
+pub(crate) fn effective_canister_id(canister_id: &CanisterId, arguments: &[u8]) -> CanisterId {
+    if *canister_id == IC_00 {
+        if let Ok(record) = CanisterIdRecord::decode(arguments) {
+            return record.get_canister_id();
+        }
+    }
+    *canister_id
+}
+
 /// Wraps the content into an envelope that contains the message signature.
 ///
 /// Prerequisite: `content` contains a `sender` field that is compatible with
@@ -551,7 +678,7 @@ pub fn sign_submit(
         content,
         sender_pubkey: pub_key_der,
         sender_sig,
-        sender_delegation: None,
+        sender_delegation: sender.sender_delegation(),
     };
     Ok((envelope, message_id))
 }
@@ -573,7 +700,7 @@ pub fn sign_read_state(
         content,
         sender_pubkey: pub_key_der,
         sender_sig,
-        sender_delegation: None,
+        sender_delegation: sender.sender_delegation(),
     })
 }
 
@@ -594,7 +721,7 @@ pub fn sign_query(
         content,
         sender_pubkey: pub_key_der,
         sender_sig,
-        sender_delegation: None,
+        sender_delegation: sender.sender_delegation(),
     })
 }
 
@@ -621,7 +748,7 @@ fn bytes_to_cbor(bytes: Vec<u8>) -> Result<CBOR, String> {
 mod tests {
     use super::*;
     use ic_test_utilities::crypto::temp_crypto_component_with_fake_registry;
-    use ic_test_utilities::types::ids::node_test_id;
+    use ic_test_utilities::types::ids::{canister_test_id, node_test_id};
     use ic_types::malicious_flags::MaliciousFlags;
     use ic_types::messages::{HttpCanisterUpdate, HttpRequest, HttpUserQuery, UserQuery};
     use ic_types::time::current_time;
@@ -742,6 +869,116 @@ mod tests {
         .contains(&request.content().canister_id()));
     }
 
+    fn delegated_update_content(sender: &Sender, canister_id: CanisterId) -> HttpSubmitContent {
+        HttpSubmitContent::Call {
+            update: HttpCanisterUpdate {
+                canister_id: Blob(canister_id.get().into_vec()),
+                method_name: "foo".to_string(),
+                arg: Blob(vec![12, 13, 99]),
+                nonce: None,
+                sender: Blob(sender.get_principal_id().into_vec()),
+                ingress_expiry: (current_time() + Duration::from_secs(4 * 60))
+                    .as_nanos_since_unix_epoch(),
+            },
+        }
+    }
+
+    /// Create an HttpRequest signed with a session key that the user
+    /// delegated to and then verify that `validate_message` authenticates it
+    /// for the delegation targets.
+    #[test]
+    fn sign_and_verify_submit_content_with_delegation() {
+        let test_start_time = current_time();
+        let mut rng = ChaChaRng::seed_from_u64(789_u64);
+        let identity = Sender::from_keypair(&ed25519_dalek::Keypair::generate(&mut rng));
+        let session = Sender::from_keypair(&ed25519_dalek::Keypair::generate(&mut rng));
+        let sender = identity
+            .delegate_to(
+                session,
+                test_start_time + Duration::from_secs(10 * 60),
+                Some(vec![canister_test_id(51)]),
+            )
+            .unwrap();
+        assert_eq!(sender.get_principal_id(), identity.get_principal_id());
+
+        let content = delegated_update_content(&sender, canister_test_id(51));
+        let (submit, id) = sign_submit(content.clone(), &sender).unwrap();
+
+        // The envelope carries the delegation chain
+        assert_eq!(submit.content, content);
+        assert_eq!(submit.sender_delegation, sender.sender_delegation());
+        assert_eq!(submit.sender_delegation.as_ref().unwrap().len(), 1);
+
+        // The envelope can be successfully authenticated for the target only
+        let request = HttpRequest::try_from(submit).unwrap();
+        assert_eq!(id, request.id());
+        let validator = temp_crypto_component_with_fake_registry(node_test_id(VALIDATOR_NODE_ID));
+        let authorized_canisters = get_authorized_canisters(
+            &request,
+            &validator,
+            test_start_time,
+            mock_registry_version(),
+            &MaliciousFlags::default(),
+        )
+        .unwrap();
+        assert!(authorized_canisters.contains(&canister_test_id(51)));
+        assert!(!authorized_canisters.contains(&canister_test_id(52)));
+    }
+
+    /// Create an HttpRequest signed at the end of a chain of two delegations
+    /// and then verify that `validate_message` authenticates it, unless the
+    /// chain has expired.
+    #[test]
+    fn sign_and_verify_submit_content_with_delegation_chain() {
+        let test_start_time = current_time();
+        let mut rng = ChaChaRng::seed_from_u64(89_u64);
+        let identity = Sender::from_keypair(&ed25519_dalek::Keypair::generate(&mut rng));
+        let intermediate = Sender::from_keypair(&ed25519_dalek::Keypair::generate(&mut rng));
+        let session = Sender::from_keypair(&ed25519_dalek::Keypair::generate(&mut rng));
+        // The delegations expire before the request does
+        let expiration = test_start_time + Duration::from_secs(60);
+        let sender = identity
+            .delegate_to(intermediate, expiration, None)
+            .unwrap()
+            .delegate_to(session, expiration, None)
+            .unwrap();
+        assert_eq!(sender.get_principal_id(), identity.get_principal_id());
+        assert_eq!(sender.sender_delegation().unwrap().len(), 2);
+
+        let content = delegated_update_content(&sender, canister_test_id(51));
+        let (submit, _) = sign_submit(content, &sender).unwrap();
+        let request = HttpRequest::try_from(submit).unwrap();
+        let validator = temp_crypto_component_with_fake_registry(node_test_id(VALIDATOR_NODE_ID));
+        assert!(get_authorized_canisters(
+            &request,
+            &validator,
+            test_start_time,
+            mock_registry_version(),
+            &MaliciousFlags::default(),
+        )
+        .unwrap()
+        .contains(&canister_test_id(51)));
+
+        // The delegations are rejected once they expired
+        assert!(get_authorized_canisters(
+            &request,
+            &validator,
+            expiration + Duration::from_secs(1),
+            mock_registry_version(),
+            &MaliciousFlags::default(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn cannot_delegate_from_anonymous_sender() {
+        let mut rng = ChaChaRng::seed_from_u64(89_u64);
+        let session = Sender::from_keypair(&ed25519_dalek::Keypair::generate(&mut rng));
+        assert!(Sender::Anonymous
+            .delegate_to(session, current_time(), None)
+            .is_err());
+    }
+
     /// Create an HttpRequest with an explicit anonymous user and then
     /// verify that `validate_message` manages to authenticate it.
     #[test]
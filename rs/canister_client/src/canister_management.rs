@@ -1,13 +1,553 @@
 //! Functions for clients to talk to the Management Canister, a.k.a ic:00.
-use crate::agent::Agent;
+//!
+//! Only the methods that users may call with ingress messages are available:
+//! `create_canister` and `raw_rand` can only be called by canisters.
+//! `deposit_cycles` is left out because it deposits the cycles attached to the
+//! call and ingress messages cannot attach cycles. Canisters are therefore
+//! created and topped up with the provisional methods instead.
+use crate::agent::{get_backoff_policy, next_backoff_interval, Agent};
 
-use ic_types::ic00::{InstallCodeArgs, Method, Payload, IC_00};
+use backoff::ExponentialBackoff;
+use ic_crypto_sha::Sha256;
+use ic_types::{
+    ic00::{
+        CanisterIdRecord, CanisterSettingsArgs, CanisterSnapshotArgs, CanisterSnapshotResponse,
+        CanisterStatusResultV2, InstallCodeArgs, Method, Payload,
+        ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs, SetControllerArgs,
+        TakeCanisterSnapshotArgs, UpdateSettingsArgs, IC_00,
+    },
+    CanisterId, CanisterStatusType, PrincipalId,
+};
+use std::time::Instant;
+use tokio::time::sleep;
 
 impl Agent {
+    /// Calls `method` of the management canister and returns the reply.
+    async fn execute_management(
+        &self,
+        method: Method,
+        payload: Vec<u8>,
+    ) -> Result<Vec<u8>, String> {
+        self.execute_update(&IC_00, method, payload, vec![])
+            .await
+            .map(|reply| reply.unwrap_or_default())
+    }
+
     // Ships a binary wasm module to a canister.
     pub async fn install_canister(&self, install_args: InstallCodeArgs) -> Result<(), String> {
         self.execute_update(&IC_00, Method::InstallCode, install_args.encode(), vec![])
             .await
             .map(|_| ())
     }
+
+    /// Ships a binary wasm module to a canister, making up to `max_attempts`
+    /// attempts.
+    ///
+    /// Large modules take long to be gossiped and installed, so an attempt
+    /// may fail even though the module got installed. The installation is
+    /// therefore considered successful as soon as the canister runs a module
+    /// with the hash of `install_args.wasm_module`.
+    pub async fn install_canister_with_retries(
+        &self,
+        install_args: InstallCodeArgs,
+        max_attempts: usize,
+    ) -> Result<(), String> {
+        let canister_id = install_args.get_canister_id();
+        let module_hash = Sha256::hash(&install_args.wasm_module).to_vec();
+        let mut backoff = get_backoff_policy();
+        let mut attempt = 1;
+        loop {
+            let install_err = match self.install_canister(install_args.clone()).await {
+                Ok(()) => return Ok(()),
+                Err(err) => err,
+            };
+            let err = match self.canister_status(canister_id).await {
+                Ok(status) if status.module_hash().as_ref() == Some(&module_hash) => return Ok(()),
+                Ok(_) => install_err,
+                Err(status_err) => format!(
+                    "{} (fetching the canister status failed: {})",
+                    install_err, status_err
+                ),
+            };
+            if attempt >= max_attempts {
+                return Err(format!(
+                    "Installing code on canister {} failed after {} attempts: {}",
+                    canister_id, attempt, err
+                ));
+            }
+            attempt += 1;
+            wait_for_next_backoff(&mut backoff).await;
+        }
+    }
+
+    /// Creates a canister with `cycles` cycles, or the default amount if not
+    /// specified, and the given settings. Requires the sender to be on the
+    /// provisional whitelist.
+    pub async fn provisional_create_canister_with_cycles(
+        &self,
+        cycles: Option<u64>,
+        settings: Option<CanisterSettingsArgs>,
+    ) -> Result<CanisterId, String> {
+        let mut args = ProvisionalCreateCanisterWithCyclesArgs::new(cycles);
+        args.settings = settings;
+        let reply = self
+            .execute_management(Method::ProvisionalCreateCanisterWithCycles, args.encode())
+            .await?;
+        CanisterIdRecord::decode(&reply)
+            .map(|record| record.get_canister_id())
+            .map_err(|err| format!("Failed to decode the created canister id: {}", err))
+    }
+
+    /// Adds `cycles` cycles to the balance of the canister. Requires the
+    /// sender to be on the provisional whitelist.
+    pub async fn provisional_top_up_canister(
+        &self,
+        canister_id: CanisterId,
+        cycles: u64,
+    ) -> Result<(), String> {
+        self.execute_management(
+            Method::ProvisionalTopUpCanister,
+            ProvisionalTopUpCanisterArgs::new(canister_id, cycles).encode(),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// Updates the settings of the canister. Settings that are not specified
+    /// are left unchanged.
+    pub async fn update_settings(
+        &self,
+        canister_id: CanisterId,
+        settings: CanisterSettingsArgs,
+    ) -> Result<(), String> {
+        let args = UpdateSettingsArgs {
+            canister_id: canister_id.get(),
+            settings,
+        };
+        self.execute_management(Method::UpdateSettings, args.encode())
+            .await
+            .map(|_| ())
+    }
+
+    /// Makes `controller` the only controller of the canister.
+    pub async fn set_controller(
+        &self,
+        canister_id: CanisterId,
+        controller: PrincipalId,
+    ) -> Result<(), String> {
+        self.execute_management(
+            Method::SetController,
+            SetControllerArgs::new(canister_id, controller).encode(),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// Returns the status of the canister.
+    pub async fn canister_status(
+        &self,
+        canister_id: CanisterId,
+    ) -> Result<CanisterStatusResultV2, String> {
+        let reply = self
+            .execute_management(
+                Method::CanisterStatus,
+                CanisterIdRecord::from(canister_id).encode(),
+            )
+            .await?;
+        CanisterStatusResultV2::decode(&reply)
+            .map_err(|err| format!("Failed to decode the canister status: {}", err))
+    }
+
+    pub async fn start_canister(&self, canister_id: CanisterId) -> Result<(), String> {
+        self.execute_management(
+            Method::StartCanister,
+            CanisterIdRecord::from(canister_id).encode(),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// Stops the canister and returns once it is stopped.
+    ///
+    /// A canister only stops once it has no outstanding calls, which can
+    /// take longer than the ingress timeout. If the stop request fails while
+    /// the canister is stopping, the status of the canister is polled until
+    /// it is stopped, for up to another ingress timeout.
+    pub async fn stop_canister(&self, canister_id: CanisterId) -> Result<(), String> {
+        let result = self
+            .execute_management(
+                Method::StopCanister,
+                CanisterIdRecord::from(canister_id).encode(),
+            )
+            .await;
+        let err = match result {
+            Ok(_) => return Ok(()),
+            Err(err) => err,
+        };
+
+        let deadline = Instant::now() + self.ingress_timeout;
+        let mut backoff = get_backoff_policy();
+        loop {
+            match self.canister_status(canister_id).await?.status() {
+                CanisterStatusType::Stopped => return Ok(()),
+                CanisterStatusType::Stopping if Instant::now() < deadline => {
+                    wait_for_next_backoff(&mut backoff).await
+                }
+                CanisterStatusType::Stopping => {
+                    return Err(format!(
+                        "Canister {} did not stop before the deadline: {}",
+                        canister_id, err
+                    ))
+                }
+                CanisterStatusType::Running => return Err(err),
+            }
+        }
+    }
+
+    /// Deletes the canister, which must be stopped.
+    pub async fn delete_canister(&self, canister_id: CanisterId) -> Result<(), String> {
+        self.execute_management(
+            Method::DeleteCanister,
+            CanisterIdRecord::from(canister_id).encode(),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// Removes the code and the state of the canister.
+    pub async fn uninstall_code(&self, canister_id: CanisterId) -> Result<(), String> {
+        self.execute_management(
+            Method::UninstallCode,
+            CanisterIdRecord::from(canister_id).encode(),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// Takes a snapshot of the canister, replacing the snapshot with id
+    /// `replace_snapshot` if specified.
+    pub async fn take_canister_snapshot(
+        &self,
+        canister_id: CanisterId,
+        replace_snapshot: Option<Vec<u8>>,
+    ) -> Result<CanisterSnapshotResponse, String> {
+        let reply = self
+            .execute_management(
+                Method::TakeCanisterSnapshot,
+                TakeCanisterSnapshotArgs::new(canister_id, replace_snapshot).encode(),
+            )
+            .await?;
+        CanisterSnapshotResponse::decode(&reply)
+            .map_err(|err| format!("Failed to decode the canister snapshot: {}", err))
+    }
+
+    /// Restores the canister to the snapshot with id `snapshot_id`. The
+    /// canister must be stopped.
+    pub async fn load_canister_snapshot(
+        &self,
+        canister_id: CanisterId,
+        snapshot_id: Vec<u8>,
+    ) -> Result<(), String> {
+        self.execute_management(
+            Method::LoadCanisterSnapshot,
+            CanisterSnapshotArgs::new(canister_id, snapshot_id).encode(),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    pub async fn list_canister_snapshots(
+        &self,
+        canister_id: CanisterId,
+    ) -> Result<Vec<CanisterSnapshotResponse>, String> {
+        let reply = self
+            .execute_management(
+                Method::ListCanisterSnapshots,
+                CanisterIdRecord::from(canister_id).encode(),
+            )
+            .await?;
+        candid::Decode!(&reply, Vec<CanisterSnapshotResponse>)
+            .map_err(|err| format!("Failed to decode the canister snapshots: {}", err))
+    }
+
+    pub async fn delete_canister_snapshot(
+        &self,
+        canister_id: CanisterId,
+        snapshot_id: Vec<u8>,
+    ) -> Result<(), String> {
+        self.execute_management(
+            Method::DeleteCanisterSnapshot,
+            CanisterSnapshotArgs::new(canister_id, snapshot_id).encode(),
+        )
+        .await
+        .map(|_| ())
+    }
+}
+
+async fn wait_for_next_backoff(backoff: &mut ExponentialBackoff) {
+    sleep(next_backoff_interval(backoff)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{agent::Sender, http_client::HttpClientInterface};
+    use async_trait::async_trait;
+    use ic_crypto_tree_hash::MixedHashTree;
+    use ic_test_utilities::types::ids::canister_test_id;
+    use ic_types::{
+        messages::{
+            Blob, CanisterInstallMode, Certificate, HttpReadStateResponse, HttpRequestEnvelope,
+            HttpSubmitContent, MessageId,
+        },
+        NumBytes,
+    };
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+    use url::Url;
+
+    /// A mock of the HTTP endpoints of a replica. Calls to the management
+    /// canister must arrive in the order of `calls`, each of which either
+    /// fails to be submitted or gets replied with the given bytes.
+    struct MockHttpClient {
+        calls: Mutex<VecDeque<(Method, Result<Vec<u8>, String>)>>,
+        /// The request id and the reply of the last call that was submitted.
+        last_call: Mutex<Option<(MessageId, Vec<u8>)>>,
+    }
+
+    impl MockHttpClient {
+        fn new(calls: Vec<(Method, Result<Vec<u8>, String>)>) -> Self {
+            Self {
+                calls: Mutex::new(calls.into()),
+                last_call: Mutex::new(None),
+            }
+        }
+
+        fn assert_all_calls_made(&self) {
+            assert_eq!(
+                self.calls
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .map(|(method, _)| method.to_string())
+                    .collect::<Vec<_>>(),
+                Vec::<String>::new()
+            );
+        }
+    }
+
+    #[async_trait]
+    impl HttpClientInterface for MockHttpClient {
+        async fn get_with_response(
+            &self,
+            _url: &Url,
+            end_point: &str,
+            _deadline: tokio::time::Instant,
+        ) -> Result<Vec<u8>, String> {
+            panic!("unexpected GET {}", end_point)
+        }
+
+        async fn post_with_response(
+            &self,
+            _url: &Url,
+            end_point: &str,
+            http_body: Vec<u8>,
+            _deadline: tokio::time::Instant,
+        ) -> Result<Vec<u8>, String> {
+            if end_point.ends_with("/read_state") {
+                let (request_id, reply) = self
+                    .last_call
+                    .lock()
+                    .unwrap()
+                    .clone()
+                    .expect("read_state before any call was submitted");
+                return Ok(read_state_response(&request_id, reply));
+            }
+
+            let envelope: HttpRequestEnvelope<HttpSubmitContent> =
+                serde_cbor::from_slice(&http_body).unwrap();
+            let HttpSubmitContent::Call { update } = envelope.content;
+            let (method, result) = self
+                .calls
+                .lock()
+                .unwrap()
+                .pop_front()
+                .unwrap_or_else(|| panic!("unexpected call to {}", update.method_name));
+            assert_eq!(update.method_name, method.to_string());
+            let reply = result?;
+            *self.last_call.lock().unwrap() = Some((update.id(), reply));
+            Ok(vec![])
+        }
+
+        async fn send_post_request(
+            &self,
+            url: &str,
+            _http_body: Vec<u8>,
+            _deadline: tokio::time::Instant,
+        ) -> Result<(Vec<u8>, hyper::StatusCode), String> {
+            panic!("unexpected POST {}", url)
+        }
+    }
+
+    /// Returns a `read_state` response certifying that the call with
+    /// `request_id` was replied with `reply`.
+    fn read_state_response(request_id: &MessageId, reply: Vec<u8>) -> Vec<u8> {
+        let tree = MixedHashTree::Labeled(
+            "request_status".into(),
+            Box::new(MixedHashTree::Labeled(
+                request_id.as_bytes().to_vec().into(),
+                Box::new(MixedHashTree::Fork(Box::new((
+                    MixedHashTree::Labeled("reply".into(), Box::new(MixedHashTree::Leaf(reply))),
+                    MixedHashTree::Labeled(
+                        "status".into(),
+                        Box::new(MixedHashTree::Leaf(b"replied".to_vec())),
+                    ),
+                )))),
+            )),
+        );
+        let certificate = Certificate {
+            tree,
+            signature: Blob(vec![]),
+            delegation: None,
+        };
+        serde_cbor::to_vec(&HttpReadStateResponse {
+            certificate: Blob(serde_cbor::to_vec(&certificate).unwrap()),
+        })
+        .unwrap()
+    }
+
+    fn canister_status(status: CanisterStatusType, module_hash: Option<Vec<u8>>) -> Vec<u8> {
+        CanisterStatusResultV2::new(
+            status,
+            module_hash,
+            PrincipalId::new_anonymous(),
+            vec![],
+            NumBytes::from(0),
+            0,
+            0,
+            None,
+            0,
+            0,
+            0,
+        )
+        .encode()
+    }
+
+    fn install_args() -> InstallCodeArgs {
+        InstallCodeArgs::new(
+            CanisterInstallMode::Install,
+            canister_test_id(1),
+            vec![0, 97, 115, 109],
+            vec![],
+            None,
+            None,
+            None,
+        )
+    }
+
+    fn agent(http_client: Arc<MockHttpClient>) -> Agent {
+        Agent::build_agent(
+            Url::parse("http://localhost").unwrap(),
+            http_client,
+            Sender::Anonymous,
+        )
+    }
+
+    #[tokio::test]
+    async fn install_canister_with_retries_retries_failed_installs() {
+        let http_client = Arc::new(MockHttpClient::new(vec![
+            (Method::InstallCode, Err("timeout".to_string())),
+            (
+                Method::CanisterStatus,
+                Ok(canister_status(CanisterStatusType::Running, None)),
+            ),
+            (Method::InstallCode, Ok(vec![])),
+        ]));
+
+        let result = agent(Arc::clone(&http_client))
+            .install_canister_with_retries(install_args(), 2)
+            .await;
+
+        assert_eq!(result, Ok(()));
+        http_client.assert_all_calls_made();
+    }
+
+    #[tokio::test]
+    async fn install_canister_with_retries_succeeds_if_the_module_got_installed() {
+        let module_hash = Sha256::hash(&install_args().wasm_module).to_vec();
+        let http_client = Arc::new(MockHttpClient::new(vec![
+            (Method::InstallCode, Err("timeout".to_string())),
+            (
+                Method::CanisterStatus,
+                Ok(canister_status(
+                    CanisterStatusType::Running,
+                    Some(module_hash),
+                )),
+            ),
+        ]));
+
+        let result = agent(Arc::clone(&http_client))
+            .install_canister_with_retries(install_args(), 2)
+            .await;
+
+        assert_eq!(result, Ok(()));
+        http_client.assert_all_calls_made();
+    }
+
+    #[tokio::test]
+    async fn install_canister_with_retries_reports_the_canister_status_error() {
+        let http_client = Arc::new(MockHttpClient::new(vec![
+            (Method::InstallCode, Err("install failed".to_string())),
+            (Method::CanisterStatus, Err("status failed".to_string())),
+        ]));
+
+        let err = agent(Arc::clone(&http_client))
+            .install_canister_with_retries(install_args(), 1)
+            .await
+            .unwrap_err();
+
+        assert!(err.contains("install failed"), "{}", err);
+        assert!(err.contains("status failed"), "{}", err);
+        http_client.assert_all_calls_made();
+    }
+
+    #[tokio::test]
+    async fn stop_canister_polls_the_status_until_stopped() {
+        let http_client = Arc::new(MockHttpClient::new(vec![
+            (Method::StopCanister, Err("timeout".to_string())),
+            (
+                Method::CanisterStatus,
+                Ok(canister_status(CanisterStatusType::Stopping, None)),
+            ),
+            (
+                Method::CanisterStatus,
+                Ok(canister_status(CanisterStatusType::Stopped, None)),
+            ),
+        ]));
+
+        let result = agent(Arc::clone(&http_client))
+            .stop_canister(canister_test_id(1))
+            .await;
+
+        assert_eq!(result, Ok(()));
+        http_client.assert_all_calls_made();
+    }
+
+    #[tokio::test]
+    async fn stop_canister_fails_if_the_canister_keeps_running() {
+        let http_client = Arc::new(MockHttpClient::new(vec![
+            (Method::StopCanister, Err("stop failed".to_string())),
+            (
+                Method::CanisterStatus,
+                Ok(canister_status(CanisterStatusType::Running, None)),
+            ),
+        ]));
+
+        let result = agent(Arc::clone(&http_client))
+            .stop_canister(canister_test_id(1))
+            .await;
+
+        assert_eq!(result, Err("stop failed".to_string()));
+        http_client.assert_all_calls_made();
+    }
 }
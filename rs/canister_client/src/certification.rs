@@ -0,0 +1,262 @@
+//! Verification of the certificates returned by `read_state` requests.
+use ic_crypto_tree_hash::{lookup_path, LabeledTree};
+use ic_crypto_utils_threshold_sig::{parse_threshold_sig_key_from_der, verify_combined};
+use ic_types::{
+    consensus::certification::CertificationContent,
+    crypto::{
+        threshold_sig::ThresholdSigPublicKey, CombinedThresholdSig, CombinedThresholdSigOf,
+        CryptoHash,
+    },
+    messages::{Certificate, CertificateDelegation},
+    CanisterId, CryptoHashOfPartialState, PrincipalId,
+};
+use std::convert::TryFrom;
+
+/// Verifies that `certificate` was issued by the Internet Computer whose root
+/// public key is `root_key`.
+///
+/// A certificate is either signed with the root key directly or by a subnet
+/// that the root subnet delegated to. In the latter case, the delegation must
+/// be signed with the root key and the subnet must be responsible for
+/// `effective_canister_id`.
+pub fn verify_certificate(
+    certificate: &Certificate,
+    effective_canister_id: &CanisterId,
+    root_key: &ThresholdSigPublicKey,
+) -> Result<(), String> {
+    let key = match &certificate.delegation {
+        None => *root_key,
+        Some(delegation) => verify_delegation(delegation, effective_canister_id, root_key)?,
+    };
+    verify_signature(certificate, &key)
+}
+
+/// Verifies the subnet delegation of a certificate and returns the public key
+/// of the subnet.
+fn verify_delegation(
+    delegation: &CertificateDelegation,
+    effective_canister_id: &CanisterId,
+    root_key: &ThresholdSigPublicKey,
+) -> Result<ThresholdSigPublicKey, String> {
+    let subnet_id = PrincipalId::try_from(delegation.subnet_id.as_slice())
+        .map_err(|err| format!("invalid subnet id in delegation: {}", err))?;
+    let certificate: Certificate = serde_cbor::from_slice(delegation.certificate.as_slice())
+        .map_err(|err| format!("decoding delegation certificate failed: {}", err))?;
+    if certificate.delegation.is_some() {
+        return Err(format!(
+            "the delegation certificate of subnet {} contains another delegation",
+            subnet_id
+        ));
+    }
+    verify_signature(&certificate, root_key)
+        .map_err(|err| format!("invalid delegation for subnet {}: {}", subnet_id, err))?;
+
+    let tree = LabeledTree::try_from(certificate.tree)
+        .map_err(|err| format!("parsing tree in delegation certificate failed: {:?}", err))?;
+    let lookup_leaf =
+        |label: &[u8]| match lookup_path(&tree, &[&b"subnet"[..], subnet_id.as_slice(), label]) {
+            Some(LabeledTree::Leaf(value)) => Ok(value),
+            _ => Err(format!(
+                "the delegation certificate does not contain /subnet/{}/{}",
+                subnet_id,
+                String::from_utf8_lossy(label)
+            )),
+        };
+
+    let canister_ranges: Vec<(PrincipalId, PrincipalId)> =
+        serde_cbor::from_slice(lookup_leaf(b"canister_ranges")?)
+            .map_err(|err| format!("decoding canister ranges failed: {}", err))?;
+    let canister_id = effective_canister_id.get();
+    if !canister_ranges
+        .iter()
+        .any(|(start, end)| *start <= canister_id && canister_id <= *end)
+    {
+        return Err(format!(
+            "subnet {} is not authorized to certify the state of canister {}",
+            subnet_id, effective_canister_id
+        ));
+    }
+
+    parse_threshold_sig_key_from_der(lookup_leaf(b"public_key")?)
+        .map_err(|err| format!("invalid public key of subnet {}: {}", subnet_id, err))
+}
+
+/// Verifies the signature on the root hash of the certificate tree.
+fn verify_signature(certificate: &Certificate, key: &ThresholdSigPublicKey) -> Result<(), String> {
+    let digest = CryptoHashOfPartialState::from(CryptoHash(certificate.tree.digest().to_vec()));
+    let signature =
+        CombinedThresholdSigOf::new(CombinedThresholdSig(certificate.signature.to_vec()));
+    verify_combined(&CertificationContent::new(digest), &signature, key)
+        .map_err(|err| format!("failed to verify threshold signature: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::effective_canister_id;
+    use ic_crypto_internal_threshold_sig_bls12381::{api as bls12_381, types::SecretKeyBytes};
+    use ic_crypto_tree_hash::MixedHashTree;
+    use ic_interfaces::crypto::Signable;
+    use ic_test_utilities::types::ids::{canister_test_id, subnet_test_id};
+    use ic_types::{
+        ic00::{CanisterIdRecord, Payload, IC_00},
+        messages::Blob,
+        NumberOfNodes, Randomness, SubnetId,
+    };
+    use serde::Serialize;
+
+    fn key_pair(seed: u8) -> (ThresholdSigPublicKey, SecretKeyBytes) {
+        let (public_coefficients, mut secret_keys) = bls12_381::keygen(
+            Randomness::from([seed; 32]),
+            NumberOfNodes::from(1),
+            &[true],
+        )
+        .unwrap();
+        let public_key = bls12_381::combined_public_key(&public_coefficients).unwrap();
+        (
+            ThresholdSigPublicKey::from(public_key),
+            secret_keys.remove(0).unwrap(),
+        )
+    }
+
+    fn sign_tree(tree: MixedHashTree, secret_key: &SecretKeyBytes) -> Certificate {
+        let digest = CryptoHashOfPartialState::from(CryptoHash(tree.digest().to_vec()));
+        let content = CertificationContent::new(digest);
+        let signature = bls12_381::sign_message(&content.as_signed_bytes(), secret_key).unwrap();
+        let signature =
+            bls12_381::combine_signatures(&[Some(signature)], NumberOfNodes::from(1)).unwrap();
+        Certificate {
+            tree,
+            signature: Blob(signature.0.to_vec()),
+            delegation: None,
+        }
+    }
+
+    fn time_tree() -> MixedHashTree {
+        MixedHashTree::Labeled("time".into(), Box::new(MixedHashTree::Leaf(vec![1])))
+    }
+
+    fn to_cbor<T: Serialize>(value: &T) -> Vec<u8> {
+        let mut serializer = serde_cbor::Serializer::new(vec![]);
+        serializer.self_describe().unwrap();
+        value.serialize(&mut serializer).unwrap();
+        serializer.into_inner()
+    }
+
+    /// Creates a delegation from the root subnet to `subnet_id`, which is
+    /// assigned the canister ids in `[start, end]`.
+    fn delegation(
+        subnet_id: SubnetId,
+        subnet_key: &ThresholdSigPublicKey,
+        (start, end): (CanisterId, CanisterId),
+        root_secret_key: &SecretKeyBytes,
+    ) -> CertificateDelegation {
+        let public_key = bls12_381::public_key_to_der((*subnet_key).into()).unwrap();
+        let canister_ranges = vec![(start.get(), end.get())];
+        let tree = MixedHashTree::Labeled(
+            "subnet".into(),
+            Box::new(MixedHashTree::Labeled(
+                subnet_id.get().into_vec().into(),
+                Box::new(MixedHashTree::Fork(Box::new((
+                    MixedHashTree::Labeled(
+                        "canister_ranges".into(),
+                        Box::new(MixedHashTree::Leaf(to_cbor(&canister_ranges))),
+                    ),
+                    MixedHashTree::Labeled(
+                        "public_key".into(),
+                        Box::new(MixedHashTree::Leaf(public_key)),
+                    ),
+                )))),
+            )),
+        );
+        CertificateDelegation {
+            subnet_id: Blob(subnet_id.get().into_vec()),
+            certificate: Blob(to_cbor(&sign_tree(tree, root_secret_key))),
+        }
+    }
+
+    #[test]
+    fn accepts_certificate_signed_by_root_key() {
+        let (root_key, root_secret_key) = key_pair(1);
+        let certificate = sign_tree(time_tree(), &root_secret_key);
+        assert_eq!(
+            verify_certificate(&certificate, &canister_test_id(1), &root_key),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rejects_certificate_signed_by_other_key() {
+        let (root_key, _) = key_pair(1);
+        let (_, other_secret_key) = key_pair(2);
+        let certificate = sign_tree(time_tree(), &other_secret_key);
+        assert!(verify_certificate(&certificate, &canister_test_id(1), &root_key).is_err());
+    }
+
+    #[test]
+    fn accepts_certificate_with_subnet_delegation() {
+        let (root_key, root_secret_key) = key_pair(1);
+        let (subnet_key, subnet_secret_key) = key_pair(2);
+        let mut certificate = sign_tree(time_tree(), &subnet_secret_key);
+        certificate.delegation = Some(delegation(
+            subnet_test_id(1),
+            &subnet_key,
+            (canister_test_id(0), canister_test_id(10)),
+            &root_secret_key,
+        ));
+        assert_eq!(
+            verify_certificate(&certificate, &canister_test_id(5), &root_key),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rejects_delegation_not_covering_the_canister() {
+        let (root_key, root_secret_key) = key_pair(1);
+        let (subnet_key, subnet_secret_key) = key_pair(2);
+        let mut certificate = sign_tree(time_tree(), &subnet_secret_key);
+        certificate.delegation = Some(delegation(
+            subnet_test_id(1),
+            &subnet_key,
+            (canister_test_id(0), canister_test_id(10)),
+            &root_secret_key,
+        ));
+        assert!(verify_certificate(&certificate, &canister_test_id(11), &root_key).is_err());
+    }
+
+    #[test]
+    fn rejects_delegation_not_signed_by_root_key() {
+        let (root_key, _) = key_pair(1);
+        let (subnet_key, subnet_secret_key) = key_pair(2);
+        let mut certificate = sign_tree(time_tree(), &subnet_secret_key);
+        certificate.delegation = Some(delegation(
+            subnet_test_id(1),
+            &subnet_key,
+            (canister_test_id(0), canister_test_id(10)),
+            &subnet_secret_key,
+        ));
+        assert!(verify_certificate(&certificate, &canister_test_id(5), &root_key).is_err());
+    }
+
+    #[test]
+    fn accepts_delegation_covering_the_target_of_a_management_call() {
+        let (root_key, root_secret_key) = key_pair(1);
+        let (subnet_key, subnet_secret_key) = key_pair(2);
+        let mut certificate = sign_tree(time_tree(), &subnet_secret_key);
+        certificate.delegation = Some(delegation(
+            subnet_test_id(1),
+            &subnet_key,
+            (canister_test_id(0), canister_test_id(10)),
+            &root_secret_key,
+        ));
+        let arguments = CanisterIdRecord::from(canister_test_id(5)).encode();
+        let effective_canister_id = effective_canister_id(&IC_00, &arguments);
+        assert_eq!(effective_canister_id, canister_test_id(5));
+        assert_eq!(
+            verify_certificate(&certificate, &effective_canister_id, &root_key),
+            Ok(())
+        );
+        // The management canister itself is not covered by the delegation.
+        assert!(verify_certificate(&certificate, &IC_00, &root_key).is_err());
+    }
+}
@@ -1,5 +1,6 @@
 //! The hyper based HTTP client
 
+use async_trait::async_trait;
 use hyper::client::HttpConnector as HyperConnector;
 use hyper::client::ResponseFuture as HyperFuture;
 use hyper::Client as HyperClient;
@@ -25,6 +26,33 @@ impl Default for HttpClientConfig {
     }
 }
 
+/// The HTTP requests that an [Agent](crate::Agent) sends to a replica.
+/// Implemented by [HttpClient]; tests replace the replica with a mock.
+#[async_trait]
+pub trait HttpClientInterface: Send + Sync {
+    async fn get_with_response(
+        &self,
+        url: &Url,
+        end_point: &str,
+        deadline: tokio::time::Instant,
+    ) -> Result<Vec<u8>, String>;
+
+    async fn post_with_response(
+        &self,
+        url: &Url,
+        end_point: &str,
+        http_body: Vec<u8>,
+        deadline: tokio::time::Instant,
+    ) -> Result<Vec<u8>, String>;
+
+    async fn send_post_request(
+        &self,
+        url: &str,
+        http_body: Vec<u8>,
+        deadline: tokio::time::Instant,
+    ) -> Result<(Vec<u8>, hyper::StatusCode), String>;
+}
+
 /// An HTTP Client to communicate with a replica.
 #[derive(Clone)]
 pub struct HttpClient {
@@ -121,8 +149,11 @@ impl HttpClient {
         }
         Ok(parsed_body)
     }
+}
 
-    pub(crate) async fn get_with_response(
+#[async_trait]
+impl HttpClientInterface for HttpClient {
+    async fn get_with_response(
         &self,
         url: &Url,
         end_point: &str,
@@ -133,7 +164,7 @@ impl HttpClient {
         Self::wait_for_one_http_request(uri, response_future, deadline).await
     }
 
-    pub(crate) async fn post_with_response(
+    async fn post_with_response(
         &self,
         url: &Url,
         end_point: &str,
@@ -145,7 +176,7 @@ impl HttpClient {
         Self::wait_for_one_http_request(uri, response_future, deadline).await
     }
 
-    pub async fn send_post_request(
+    async fn send_post_request(
         &self,
         url: &str,
         http_body: Vec<u8>,
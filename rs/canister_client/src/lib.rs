@@ -3,6 +3,7 @@ mod agent;
 mod canister_management;
 /// Asynchronous method to interact with canisters.
 mod cbor;
+mod certification;
 mod http_client;
 
 pub use agent::{
@@ -10,5 +11,6 @@ pub use agent::{
     Sender,
 };
 pub use cbor::parse_read_state_response;
-pub use http_client::{HttpClient, HttpClientConfig};
+pub use certification::verify_certificate;
+pub use http_client::{HttpClient, HttpClientConfig, HttpClientInterface};
 pub use hyper::StatusCode as HttpStatusCode;
@@ -23,6 +23,7 @@ use ic_canister_sandbox_common::protocol::sbxsvc::{
 use ic_canister_sandbox_common::protocol::structs::MemoryModifications;
 use ic_canister_sandbox_common::{controller_service::ControllerService, protocol};
 use ic_config::embedders::{Config, PersistenceType};
+use ic_config::feature_status::FeatureStatus;
 use ic_embedders::cow_memory_creator::CowMemoryCreator;
 use ic_embedders::{
     wasm_executor::compute_page_delta,
@@ -354,6 +355,10 @@ impl CanisterWasm {
         let log = ic_logger::replica_logger::no_op_logger();
         let mut config = Config::new();
         config.persistence_type = PersistenceType::Sigsegv;
+        // Installed code was validated against the subnet features when it
+        // was installed, so compiling it must accept all Wasm features that a
+        // subnet can enable.
+        config.feature_flags.wasm_simd = FeatureStatus::Enabled;
 
         let embedder = Arc::new(WasmtimeEmbedder::new(config.clone(), log));
         let instrumentation_output = validate_wasm_binary(&wasm, &config)
@@ -528,10 +533,15 @@ impl SandboxManager {
         wasm_binary: Vec<u8>,
         canister_root: PathBuf,
         canister_id: CanisterId,
+        wasm_simd: FeatureStatus,
     ) -> HypervisorResult<CreateExecutionStateSuccessReply> {
-        let embedder = WasmtimeEmbedder::new(Config::default(), no_op_logger());
+        // New code is validated against the Wasm features that are currently
+        // enabled on the subnet.
+        let mut config = Config::default();
+        config.feature_flags.wasm_simd = wasm_simd;
+        let embedder = WasmtimeEmbedder::new(config.clone(), no_op_logger());
         let wasm_binary = BinaryEncodedWasm::new(wasm_binary);
-        validate_wasm_binary(&wasm_binary, &Config::default())?;
+        validate_wasm_binary(&wasm_binary, &config)?;
         let instrumentation_output = instrument(&wasm_binary, &InstructionCostTable::new())?;
         let wasm_memory_pages = instrumentation_output.data.as_pages();
         let execution_state = ExecutionState::new(
@@ -6,6 +6,7 @@ use std::sync::Arc;
 use crate::sandbox_manager::SandboxManager;
 
 use ic_canister_sandbox_common::{protocol::sbxsvc::*, rpc, sandbox_service::SandboxService};
+use ic_config::feature_status::FeatureStatus;
 
 /// This is the implementation of the RPC interface exposed by the
 /// sandbox process and "binds everything together": All RPCs pass
@@ -79,6 +80,7 @@ impl SandboxService for SandboxServer {
             req.wasm_binary,
             req.canister_root,
             req.canister_id,
+            FeatureStatus::from(req.wasm_simd),
         );
         rpc::Call::new_resolved(Ok(CreateExecutionStateReply(result)))
     }
@@ -109,6 +111,7 @@ mod tests {
         methods::{FuncRef, WasmMethod},
         time::Time,
         ComputeAllocation, Cycles, NumBytes, NumInstructions, PrincipalId, SubnetId,
+        MAX_STABLE_MEMORY_IN_BYTES,
     };
     use mockall::*;
     use std::collections::BTreeMap;
@@ -120,6 +123,7 @@ mod tests {
         ExecutionParameters {
             instruction_limit: NumInstructions::new(1000),
             canister_memory_limit: NumBytes::new(4 << 30),
+            max_stable_memory_size: NumBytes::new(MAX_STABLE_MEMORY_IN_BYTES),
             subnet_available_memory: SubnetAvailableMemory::new(i64::MAX / 2),
             compute_allocation: ComputeAllocation::default(),
             subnet_type: SubnetType::Application,
@@ -1174,4 +1178,40 @@ mod tests {
             .unwrap();
         assert!(rep.success);
     }
+
+    /// Verifies that new code may only use SIMD instructions if the
+    /// `wasm_simd` subnet feature is enabled.
+    #[test]
+    fn test_create_execution_state_simd() {
+        let exec_finished_sync = Arc::new(SyncCell::<protocol::ctlsvc::ExecFinishedRequest>::new());
+
+        let srv = SandboxServer::new(SandboxManager::new(setup_mock_controller(
+            exec_finished_sync,
+        )));
+
+        let mut features = wabt::Features::new();
+        features.enable_simd();
+        let wasm_binary = wabt::wat2wasm_with_features(
+            r#"(module
+                (func (export "canister_query simd") (result i32)
+                    (i32x4.extract_lane 0 (i32x4.splat (i32.const 1)))))"#,
+            features,
+        )
+        .unwrap();
+        let tmpdir = tempfile::Builder::new().prefix("test").tempdir().unwrap();
+        let create_execution_state = |wasm_simd| {
+            srv.create_execution_state(CreateExecutionStateRequest {
+                wasm_binary: wasm_binary.clone(),
+                canister_root: tmpdir.path().to_path_buf(),
+                canister_id: canister_test_id(0),
+                wasm_simd,
+            })
+            .sync()
+            .unwrap()
+            .0
+        };
+
+        assert!(create_execution_state(false).is_err());
+        assert!(create_execution_state(true).is_ok());
+    }
 }
@@ -3,7 +3,7 @@ use ic_canister_sandbox_common::{
     protocol::{self, id::ExecId},
 };
 use ic_interfaces::execution_environment::HypervisorResult;
-use ic_replicated_state::StateError;
+use ic_replicated_state::{CanisterTimer, StateError};
 /// This module provides a way of accessing the canister system state
 /// via RPC. It implements the SystemStateAccessor interface that
 /// forms the back-end of the SystemApi (as far as it accesses system
@@ -12,7 +12,7 @@ use ic_system_api::SystemStateAccessor;
 use ic_types::{
     messages::{CallContextId, CallbackId},
     methods::Callback,
-    ComputeAllocation, Cycles, NumBytes, SubnetId,
+    ComputeAllocation, Cycles, NumBytes, SubnetId, Time,
 };
 
 use std::sync::Arc;
@@ -132,6 +132,26 @@ impl SystemStateAccessor for SystemStateAccessorRPC {
         }
     }
 
+    fn global_timer_set(&self, timer: CanisterTimer) -> CanisterTimer {
+        let reply = self.make_call(protocol::syscall::Request::GlobalTimerSet(
+            protocol::syscall::GlobalTimerSetRequest { timer },
+        ));
+        match reply {
+            protocol::syscall::Reply::GlobalTimerSet(rep) => rep.previous,
+            _ => unimplemented!(),
+        }
+    }
+
+    fn append_canister_log(&self, time: Time, content: Vec<u8>) {
+        let reply = self.make_call(protocol::syscall::Request::AppendCanisterLog(
+            protocol::syscall::AppendCanisterLogRequest { time, content },
+        ));
+        match reply {
+            protocol::syscall::Reply::AppendCanisterLog(_rep) => {}
+            _ => unimplemented!(),
+        }
+    }
+
     fn register_callback(&self, callback: Callback) -> CallbackId {
         let reply = self.make_call(protocol::syscall::Request::RegisterCallback(
             protocol::syscall::RegisterCallbackRequest { callback },
@@ -170,4 +190,17 @@ impl SystemStateAccessor for SystemStateAccessorRPC {
             _ => unimplemented!(),
         }
     }
+
+    fn push_output_query_request(
+        &self,
+        msg: ic_types::messages::Request,
+    ) -> Result<(), (StateError, ic_types::messages::Request)> {
+        let reply = self.make_call(protocol::syscall::Request::PushOutputQueryMessage(
+            protocol::syscall::PushOutputQueryMessageRequest { msg },
+        ));
+        match reply {
+            protocol::syscall::Reply::PushOutputQueryMessage(rep) => rep.result,
+            _ => unimplemented!(),
+        }
+    }
 }
@@ -239,6 +239,9 @@ pub struct CreateExecutionStateRequest {
     pub wasm_binary: Vec<u8>,
     pub canister_root: PathBuf,
     pub canister_id: CanisterId,
+    /// Whether the `wasm_simd` feature is enabled on the subnet, i.e. whether
+    /// the new code may use SIMD and bulk memory instructions.
+    pub wasm_simd: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -1,9 +1,12 @@
 use ic_interfaces::execution_environment::HypervisorResult;
-use ic_replicated_state::{canister_state::system_state::CanisterStatus, StateError};
+use ic_replicated_state::{
+    canister_state::system_state::{CanisterStatus, CanisterTimer},
+    StateError,
+};
 use ic_types::{
     messages::{CallContextId, CallbackId},
     methods::Callback,
-    CanisterId, ComputeAllocation, Cycles, NumBytes, NumInstructions, PrincipalId, SubnetId,
+    CanisterId, ComputeAllocation, Cycles, NumBytes, NumInstructions, PrincipalId, SubnetId, Time,
 };
 use serde::{Deserialize, Serialize};
 
@@ -154,6 +157,23 @@ pub struct SetCertifiedDataRequest {
 #[derive(Serialize, Deserialize, Clone)]
 pub struct SetCertifiedDataReply {}
 
+#[derive(Serialize, Deserialize, Clone)]
+pub struct GlobalTimerSetRequest {
+    pub timer: CanisterTimer,
+}
+#[derive(Serialize, Deserialize, Clone)]
+pub struct GlobalTimerSetReply {
+    pub previous: CanisterTimer,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AppendCanisterLogRequest {
+    pub time: Time,
+    pub content: Vec<u8>,
+}
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AppendCanisterLogReply {}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct RegisterCallbackRequest {
     pub callback: Callback,
@@ -187,6 +207,15 @@ pub struct PushOutputMessageReply {
     pub result: Result<(), (StateError, ic_types::messages::Request)>,
 }
 
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PushOutputQueryMessageRequest {
+    pub msg: ic_types::messages::Request,
+}
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PushOutputQueryMessageReply {
+    pub result: Result<(), (StateError, ic_types::messages::Request)>,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct CanisterStatusRequest {}
 #[derive(Serialize, Deserialize, Clone)]
@@ -205,9 +234,12 @@ pub enum Request {
     CanisterCyclesWithdraw(CanisterCyclesWithdrawRequest),
     CanisterCyclesRefund(CanisterCyclesRefundRequest),
     SetCertifiedData(SetCertifiedDataRequest),
+    GlobalTimerSet(GlobalTimerSetRequest),
+    AppendCanisterLog(AppendCanisterLogRequest),
     RegisterCallback(RegisterCallbackRequest),
     UnregisterCallback(UnregisterCallbackRequest),
     PushOutputMessage(PushOutputMessageRequest),
+    PushOutputQueryMessage(PushOutputQueryMessageRequest),
 }
 #[derive(Serialize, Deserialize, Clone)]
 pub enum Reply {
@@ -218,7 +250,10 @@ pub enum Reply {
     CanisterCyclesWithdraw(CanisterCyclesWithdrawReply),
     CanisterCyclesRefund(CanisterCyclesRefundReply),
     SetCertifiedData(SetCertifiedDataReply),
+    GlobalTimerSet(GlobalTimerSetReply),
+    AppendCanisterLog(AppendCanisterLogReply),
     RegisterCallback(RegisterCallbackReply),
     UnregisterCallback(UnregisterCallbackReply),
     PushOutputMessage(PushOutputMessageReply),
+    PushOutputQueryMessage(PushOutputQueryMessageReply),
 }
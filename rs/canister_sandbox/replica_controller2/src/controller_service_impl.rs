@@ -146,6 +146,14 @@ impl ControllerService for ControllerServiceImpl {
                             system_state_accessor.set_certified_data(req.data);
                             Reply::SetCertifiedData(SetCertifiedDataReply {})
                         }
+                        Request::GlobalTimerSet(req) => {
+                            let previous = system_state_accessor.global_timer_set(req.timer);
+                            Reply::GlobalTimerSet(GlobalTimerSetReply { previous })
+                        }
+                        Request::AppendCanisterLog(req) => {
+                            system_state_accessor.append_canister_log(req.time, req.content);
+                            Reply::AppendCanisterLog(AppendCanisterLogReply {})
+                        }
                         Request::RegisterCallback(req) => {
                             let result = system_state_accessor.register_callback(req.callback);
                             Reply::RegisterCallback(RegisterCallbackReply { result })
@@ -162,6 +170,10 @@ impl ControllerService for ControllerServiceImpl {
                             );
                             Reply::PushOutputMessage(PushOutputMessageReply { result })
                         }
+                        Request::PushOutputQueryMessage(req) => {
+                            let result = system_state_accessor.push_output_query_request(req.msg);
+                            Reply::PushOutputQueryMessage(PushOutputQueryMessageReply { result })
+                        }
                     };
 
                     Ok(protocol::ctlsvc::CanisterSystemCallReply { reply })
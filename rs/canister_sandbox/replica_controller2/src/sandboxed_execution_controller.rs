@@ -191,6 +191,8 @@ impl SandboxedExecutionController {
             func_ref,
             mut execution_state,
             cycles_account_manager,
+            // Executions in the sandbox are not sliced yet.
+            out_of_instructions_handler: _,
         }: WasmExecutionInput,
     ) -> WasmExecutionOutput {
         // Determine which process we want to run this on.
@@ -313,6 +315,7 @@ impl SandboxedExecutionController {
         wasm_binary: Vec<u8>,
         canister_root: PathBuf,
         canister_id: CanisterId,
+        wasm_simd: bool,
     ) -> HypervisorResult<ExecutionState> {
         let sandbox_process = self.get_sandbox_process(&canister_id);
         let reply = sandbox_process
@@ -321,6 +324,7 @@ impl SandboxedExecutionController {
                 wasm_binary: wasm_binary.clone(),
                 canister_root: canister_root.clone(),
                 canister_id,
+                wasm_simd,
             })
             .sync()
             .unwrap()
@@ -14,12 +14,17 @@ pub(crate) const MAX_FUNCTIONS: usize = 6000;
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
 pub struct FeatureFlags {
     pub api_cycles_u128_flag: FeatureStatus,
+    /// Enables the deterministic subset of Wasm SIMD and the bulk memory
+    /// instructions. When installing code, this is taken from the `wasm_simd`
+    /// feature of the subnet in the replicated state.
+    pub wasm_simd: FeatureStatus,
 }
 
 impl Default for FeatureFlags {
     fn default() -> Self {
         Self {
             api_cycles_u128_flag: FeatureStatus::Enabled,
+            wasm_simd: FeatureStatus::Disabled,
         }
     }
 }
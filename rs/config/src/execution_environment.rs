@@ -22,6 +22,15 @@ const GB: u64 = 1024 * 1024 * 1024;
 /// canister's data and the deltas.
 const SUBNET_MEMORY_CAPACITY: NumBytes = NumBytes::new(300 * GB);
 
+/// Once the memory or compute allocated on a subnet is above this percentage
+/// of its capacity, canisters have to reserve cycles up front for any increase
+/// of their allocations, so that a full subnet cannot starve them.
+const SUBNET_RESERVATION_THRESHOLD_PERCENT: u64 = 75;
+
+/// The default maximum amount of cycles a canister can have in its reserved
+/// balance if the controllers did not set a limit: 5T cycles.
+const DEFAULT_RESERVED_BALANCE_LIMIT: Cycles = Cycles::new(5_000_000_000_000);
+
 /// This is the upper limit on how big heap deltas all the canisters together
 /// can produce on a subnet in between checkpoints. Once, the total delta size
 /// is above this limit, no more canisters will be executed till the next
@@ -37,6 +46,28 @@ const SUBNET_MEMORY_CAPACITY: NumBytes = NumBytes::new(300 * GB);
 /// memory can succeed.
 pub(crate) const SUBNET_HEAP_DELTA_CAPACITY: NumBytes = NumBytes::new(200 * GB);
 
+const B: u64 = 1_000_000_000;
+
+/// The maximum number of instructions a single slice of a long-running
+/// execution can consume. The scheduler additionally caps every slice with
+/// the per-message limit it passes to the execution environment.
+const MAX_INSTRUCTIONS_PER_SLICE: NumInstructions = NumInstructions::new(2 * B);
+
+/// The total instruction limit of an update message when deterministic time
+/// slicing is enabled. The execution is spread over multiple rounds in slices
+/// of at most `MAX_INSTRUCTIONS_PER_SLICE` instructions.
+const MAX_INSTRUCTIONS_PER_SLICED_MESSAGE: NumInstructions = NumInstructions::new(20 * B);
+
+/// The total instruction limit of an `install_code` message when
+/// deterministic time slicing is enabled. This is large enough for
+/// `canister_post_upgrade` to migrate several GiB of stable memory.
+const MAX_INSTRUCTIONS_PER_SLICED_INSTALL_CODE: NumInstructions = NumInstructions::new(1_000 * B);
+
+/// The total number of instructions that all the executions triggered by a
+/// single composite query can consume. Every execution in the call graph is
+/// still capped by the per-message limit.
+const MAX_QUERY_CALL_GRAPH_INSTRUCTIONS: NumInstructions = NumInstructions::new(50 * B);
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
 #[serde(default)]
 pub struct Config {
@@ -53,6 +84,14 @@ pub struct Config {
     /// the subnet.
     pub subnet_memory_capacity: NumBytes,
 
+    /// The percentage of the memory and compute capacity of the subnet above
+    /// which increasing an allocation requires reserving cycles up front.
+    pub subnet_reservation_threshold_percent: u64,
+
+    /// The maximum amount of cycles in the reserved balance of a canister
+    /// whose controllers did not set `reserved_cycles_limit`.
+    pub default_reserved_balance_limit: Cycles,
+
     /// The maximum amount of memory that can be utilized by a single canister.
     pub max_canister_memory_size: NumBytes,
 
@@ -72,6 +111,26 @@ pub struct Config {
 
     /// Indicates whether canisters sandboxing is enabled or not.
     pub canister_sandboxing_flag: FeatureStatus,
+
+    /// Indicates whether long-running update and `install_code` executions
+    /// are paused at round boundaries and resumed in later rounds.
+    pub deterministic_time_slicing: FeatureStatus,
+
+    /// The maximum number of instructions a single slice of an execution can
+    /// consume when deterministic time slicing is enabled.
+    pub max_instructions_per_slice: NumInstructions,
+
+    /// The total instruction limit of an update message when deterministic
+    /// time slicing is enabled.
+    pub max_instructions_per_sliced_message: NumInstructions,
+
+    /// The total instruction limit of an `install_code` message when
+    /// deterministic time slicing is enabled.
+    pub max_instructions_per_sliced_install_code: NumInstructions,
+
+    /// The total number of instructions that the executions in the call graph
+    /// of a single query can consume.
+    pub max_query_call_graph_instructions: NumInstructions,
 }
 
 impl Default for Config {
@@ -81,6 +140,8 @@ impl Default for Config {
             create_funds_whitelist: String::default(),
             max_instructions_for_message_acceptance_calls: MAX_INSTRUCTIONS_PER_MESSAGE,
             subnet_memory_capacity: SUBNET_MEMORY_CAPACITY,
+            subnet_reservation_threshold_percent: SUBNET_RESERVATION_THRESHOLD_PERCENT,
+            default_reserved_balance_limit: DEFAULT_RESERVED_BALANCE_LIMIT,
             max_canister_memory_size: NumBytes::new(
                 MAX_STABLE_MEMORY_IN_BYTES + MAX_WASM_MEMORY_IN_BYTES,
             ),
@@ -96,6 +157,11 @@ impl Default for Config {
             max_controllers: 10,
             // Change this value to enable/disable canister sandboxing by default.
             canister_sandboxing_flag: FeatureStatus::Disabled,
+            deterministic_time_slicing: FeatureStatus::Disabled,
+            max_instructions_per_slice: MAX_INSTRUCTIONS_PER_SLICE,
+            max_instructions_per_sliced_message: MAX_INSTRUCTIONS_PER_SLICED_MESSAGE,
+            max_instructions_per_sliced_install_code: MAX_INSTRUCTIONS_PER_SLICED_INSTALL_CODE,
+            max_query_call_graph_instructions: MAX_QUERY_CALL_GRAPH_INSTRUCTIONS,
         }
     }
 }
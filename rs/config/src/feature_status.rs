@@ -6,3 +6,13 @@ pub enum FeatureStatus {
     Enabled,
     Disabled,
 }
+
+impl From<bool> for FeatureStatus {
+    fn from(enabled: bool) -> Self {
+        if enabled {
+            FeatureStatus::Enabled
+        } else {
+            FeatureStatus::Disabled
+        }
+    }
+}
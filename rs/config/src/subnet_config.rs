@@ -6,10 +6,11 @@ use std::time::Duration;
 use crate::execution_environment::SUBNET_HEAP_DELTA_CAPACITY;
 use ic_base_types::NumBytes;
 use ic_registry_subnet_type::SubnetType;
-use ic_types::{Cycles, NumInstructions};
+use ic_types::{Cycles, NumInstructions, MAX_STABLE_MEMORY_IN_BYTES};
 
 const B: u64 = 1_000_000_000;
 const M: u64 = 1_000_000;
+const GIB: u64 = 1024 * 1024 * 1024;
 
 // We assume 1 cycles unit ≅ 1 CPU cycle, so on a 2 GHz CPU one message has
 // approximately 2.5 seconds to be processed.
@@ -48,6 +49,14 @@ const SYSTEM_SUBNET_FACTOR: u64 = 10;
 // value of 200MB.
 const MAX_HEAP_DELTA_PER_ITERATION: NumBytes = NumBytes::new(200 * M);
 
+// The maximum stable memory size of a canister on application subnets. System
+// subnets allow up to `MAX_STABLE_MEMORY_IN_BYTES`.
+const MAX_STABLE_MEMORY_SIZE: NumBytes = NumBytes::new(32 * GIB);
+
+// Cycles reserved up front for an allocation increase cover the fees of that
+// increase for 30 days, which matches the default freezing threshold.
+const ALLOCATION_RESERVATION_DURATION: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
 // Log all messages that took more than this value to execute.
 pub const MAX_MESSAGE_DURATION_BEFORE_WARN_IN_SECONDS: f64 = 5.0;
 
@@ -113,6 +122,11 @@ pub struct SchedulerConfig {
     /// then not run for several rounds until they are back under the allowed
     /// rate.
     pub heap_delta_rate_limit: NumBytes,
+
+    /// The maximum size of the stable memory of a single canister. Growing the
+    /// stable memory beyond this size fails. Must not exceed
+    /// `MAX_STABLE_MEMORY_IN_BYTES`.
+    pub max_stable_memory_size: NumBytes,
 }
 
 impl SchedulerConfig {
@@ -128,6 +142,7 @@ impl SchedulerConfig {
                 MAX_MESSAGE_DURATION_BEFORE_WARN_IN_SECONDS,
             only_track_system_heartbeat_errors: true,
             heap_delta_rate_limit: NumBytes::from(100 * 1024 * 1024),
+            max_stable_memory_size: MAX_STABLE_MEMORY_SIZE,
         }
     }
 
@@ -146,6 +161,7 @@ impl SchedulerConfig {
             // This limit should be high enough (1000T) to effectively disable
             // rate-limiting for the system subnets.
             heap_delta_rate_limit: NumBytes::from(1_000_000_000_000_000),
+            max_stable_memory_size: NumBytes::new(MAX_STABLE_MEMORY_IN_BYTES),
         }
     }
 
@@ -161,6 +177,7 @@ impl SchedulerConfig {
                 MAX_MESSAGE_DURATION_BEFORE_WARN_IN_SECONDS,
             only_track_system_heartbeat_errors: true,
             heap_delta_rate_limit: NumBytes::from(100 * 1024 * 1024),
+            max_stable_memory_size: MAX_STABLE_MEMORY_SIZE,
         }
     }
 
@@ -200,6 +217,14 @@ pub struct CyclesAccountManagerConfig {
     /// Fee for every byte received in an ingress message.
     pub ingress_byte_reception_fee: Cycles,
 
+    /// Fee for every HTTP request made by a canister through the management
+    /// canister.
+    pub http_request_fee: Cycles,
+
+    /// Fee for every byte of an HTTP request made by a canister, including
+    /// the URL, the headers and the body.
+    pub http_request_byte_fee: Cycles,
+
     /// Fee for storing a GiB of data per second.
     pub gib_storage_per_second_fee: Cycles,
 
@@ -210,6 +235,10 @@ pub struct CyclesAccountManagerConfig {
 
     /// How often to charge canisters for memory and compute allocations.
     pub duration_between_allocation_charges: Duration,
+
+    /// For how long the cycles reserved up front for an allocation increase
+    /// must pay for that increase.
+    pub allocation_reservation_duration: Duration,
 }
 
 impl CyclesAccountManagerConfig {
@@ -231,9 +260,12 @@ impl CyclesAccountManagerConfig {
             xnet_byte_transmission_fee: Cycles::new(1_000),
             ingress_message_reception_fee: Cycles::new(1_200_000),
             ingress_byte_reception_fee: Cycles::new(2_000),
+            http_request_fee: Cycles::new(400_000_000),
+            http_request_byte_fee: Cycles::new(100_000),
             // 4 SDR per GiB per year => 4e12 Cycles per year
             gib_storage_per_second_fee: Cycles::new(127_000),
             duration_between_allocation_charges: Duration::from_secs(10),
+            allocation_reservation_duration: ALLOCATION_RESERVATION_DURATION,
         }
     }
 
@@ -248,8 +280,11 @@ impl CyclesAccountManagerConfig {
             xnet_byte_transmission_fee: Cycles::new(0),
             ingress_message_reception_fee: Cycles::new(0),
             ingress_byte_reception_fee: Cycles::new(0),
+            http_request_fee: Cycles::new(0),
+            http_request_byte_fee: Cycles::new(0),
             gib_storage_per_second_fee: Cycles::new(0),
             duration_between_allocation_charges: Duration::from_secs(10),
+            allocation_reservation_duration: ALLOCATION_RESERVATION_DURATION,
         }
     }
 }
@@ -21,6 +21,7 @@ use ic_consensus_message::ConsensusMessageHashable;
 use ic_execution_environment::IngressHistoryReaderImpl;
 use ic_ingress_manager::IngressManager;
 use ic_interfaces::{
+    canister_http::NoOpCanisterHttpPayloadBuilder,
     consensus::PayloadValidationError,
     consensus_pool::{ChangeAction, ChangeSet, ConsensusPool, MutableConsensusPool},
     state_manager::{CertificationScope, StateManager},
@@ -45,7 +46,10 @@ use ic_test_utilities::{
     FastForwardTimeSource,
 };
 use ic_types::{
-    batch::{BatchPayload, IngressPayload, SelfValidatingPayload, ValidationContext, XNetPayload},
+    batch::{
+        BatchPayload, CanisterHttpPayload, IngressPayload, SelfValidatingPayload,
+        ValidationContext, XNetPayload,
+    },
     consensus::certification::*,
     consensus::*,
     crypto::Signed,
@@ -146,6 +150,7 @@ where
             ingress_manager,
             Arc::new(FakeXNetPayloadBuilder::new()),
             Arc::new(FakeSelfValidatingPayloadBuilder::new()),
+            Arc::new(NoOpCanisterHttpPayloadBuilder {}),
             metrics_registry,
             no_op_logger(),
         ));
@@ -248,7 +253,12 @@ fn add_past_blocks(
         block.payload = Payload::new(
             ic_crypto::crypto_hash,
             (
-                BatchPayload::new(ingress, xnet, self_validating),
+                BatchPayload::new(
+                    ingress,
+                    xnet,
+                    self_validating,
+                    CanisterHttpPayload::default(),
+                ),
                 dkg::Dealings::new_empty(block.payload.as_ref().dkg_interval_start_height()),
             )
                 .into(),
@@ -312,7 +322,12 @@ fn validate_payload_benchmark(criterion: &mut Criterion) {
                 let payload = Payload::new(
                     ic_crypto::crypto_hash,
                     (
-                        BatchPayload::new(ingress, xnet, self_validating),
+                        BatchPayload::new(
+                            ingress,
+                            xnet,
+                            self_validating,
+                            CanisterHttpPayload::default(),
+                        ),
                         dkg::Dealings::new_empty(tip.payload.as_ref().dkg_interval_start_height()),
                     )
                         .into(),
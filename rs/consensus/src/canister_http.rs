@@ -0,0 +1,775 @@
+//! This module provides the component responsible for generating and validating
+//! the payloads that carry the responses to canister HTTP requests.
+//!
+//! Canisters make HTTP requests by calling the `http_request` method of the
+//! management canister, which records the request in the replicated state.
+//! The [CanisterHttpPayloadBuilderImpl] observes the requests in the certified
+//! state and hands them over to a [CanisterHttpAdapterClient] that performs the
+//! actual requests. Every replica signs a share for the response it got back
+//! and includes the share in the blocks it makes. Once the shares of enough
+//! replicas agree on a response, the response is included in a block
+//! together with these shares, and delivered to execution with the batch
+//! once the block is finalized.
+use crate::consensus::{crypto::SignVerify, ConsensusCrypto};
+use ic_crypto::crypto_hash;
+use ic_interfaces::{
+    canister_http::{
+        CanisterHttpAdapterClient, CanisterHttpPayloadBuilder, CanisterHttpPayloadValidationError,
+        CanisterHttpSendError, CanisterHttpTransientValidationError, InvalidCanisterHttpPayload,
+    },
+    crypto::ErrorReplication,
+    registry::RegistryClient,
+    state_manager::StateManager,
+    validation::ValidationError,
+};
+use ic_logger::{warn, ReplicaLogger};
+use ic_registry_client::helper::subnet::SubnetRegistry;
+use ic_replicated_state::ReplicatedState;
+use ic_types::{
+    batch::{CanisterHttpPayload, ValidationContext},
+    canister_http::{
+        CanisterHttpReject, CanisterHttpRequest, CanisterHttpRequestId, CanisterHttpResponse,
+        CanisterHttpResponseContent, CanisterHttpResponseMetadata, CanisterHttpResponseShare,
+        CanisterHttpResponseWithConsensus, MAX_CANISTER_HTTP_PAYLOAD_SIZE,
+    },
+    consensus::get_faults_tolerated,
+    crypto::Signed,
+    user_error::RejectCode,
+    CountBytes, NodeId, NumBytes, RegistryVersion, SubnetId,
+};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// Implementation of the [CanisterHttpPayloadBuilder].
+pub struct CanisterHttpPayloadBuilderImpl {
+    adapter_client: Arc<dyn CanisterHttpAdapterClient>,
+    state_manager: Arc<dyn StateManager<State = ReplicatedState>>,
+    crypto: Arc<dyn ConsensusCrypto>,
+    registry_client: Arc<dyn RegistryClient>,
+    node_id: NodeId,
+    subnet_id: SubnetId,
+    /// The requests that were handed over to the adapter.
+    requested: Mutex<BTreeSet<CanisterHttpRequestId>>,
+    /// The responses received from the adapter for requests that are still
+    /// open at the latest certified height.
+    responses: Mutex<BTreeMap<CanisterHttpRequestId, CanisterHttpResponse>>,
+    /// The shares of all nodes, including this one, for requests that are
+    /// still open at the latest certified height, as seen in past payloads.
+    shares: Mutex<BTreeMap<CanisterHttpRequestId, BTreeMap<NodeId, CanisterHttpResponseShare>>>,
+    log: ReplicaLogger,
+}
+
+impl CanisterHttpPayloadBuilderImpl {
+    /// Creates a new payload builder that uses the given adapter to make the
+    /// requests and signs the responses on behalf of `node_id`.
+    pub fn new(
+        adapter_client: Arc<dyn CanisterHttpAdapterClient>,
+        state_manager: Arc<dyn StateManager<State = ReplicatedState>>,
+        crypto: Arc<dyn ConsensusCrypto>,
+        registry_client: Arc<dyn RegistryClient>,
+        node_id: NodeId,
+        subnet_id: SubnetId,
+        log: ReplicaLogger,
+    ) -> Self {
+        Self {
+            adapter_client,
+            state_manager,
+            crypto,
+            registry_client,
+            node_id,
+            subnet_id,
+            requested: Mutex::new(BTreeSet::new()),
+            responses: Mutex::new(BTreeMap::new()),
+            shares: Mutex::new(BTreeMap::new()),
+            log,
+        }
+    }
+
+    /// Returns the nodes of the subnet at the given registry version.
+    fn get_subnet_members(&self, registry_version: RegistryVersion) -> Option<BTreeSet<NodeId>> {
+        match self
+            .registry_client
+            .get_node_ids_on_subnet(self.subnet_id, registry_version)
+        {
+            Ok(Some(nodes)) => Some(nodes.into_iter().collect()),
+            _ => None,
+        }
+    }
+
+    /// Returns this node's share for the given response, signing a new one if
+    /// none exists for the given registry version yet.
+    fn get_own_share(
+        &self,
+        shares: &mut BTreeMap<CanisterHttpRequestId, BTreeMap<NodeId, CanisterHttpResponseShare>>,
+        response: &CanisterHttpResponse,
+        registry_version: RegistryVersion,
+    ) -> Option<CanisterHttpResponseShare> {
+        let metadata = CanisterHttpResponseMetadata {
+            id: response.id,
+            content_hash: crypto_hash(response),
+            registry_version,
+        };
+        let node_shares = shares.entry(response.id).or_default();
+        if let Some(share) = node_shares.get(&self.node_id) {
+            if share.content == metadata {
+                return Some(share.clone());
+            }
+        }
+        match self.crypto.sign(&metadata, self.node_id, registry_version) {
+            Ok(signature) => {
+                let share = Signed {
+                    content: metadata,
+                    signature,
+                };
+                node_shares.insert(self.node_id, share.clone());
+                Some(share)
+            }
+            Err(err) => {
+                warn!(
+                    every_n_seconds => 5,
+                    self.log,
+                    "Failed to sign the response to canister http request {}: {:?}",
+                    response.id,
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    /// Checks that the given share was signed by a member of the subnet at
+    /// the given registry version.
+    fn validate_share(
+        &self,
+        share: &CanisterHttpResponseShare,
+        subnet_members: &BTreeSet<NodeId>,
+        registry_version: RegistryVersion,
+    ) -> Result<(), CanisterHttpPayloadValidationError> {
+        if share.content.registry_version != registry_version {
+            return Err(ValidationError::Permanent(
+                InvalidCanisterHttpPayload::RegistryVersionMismatch {
+                    expected: registry_version,
+                    received: share.content.registry_version,
+                },
+            ));
+        }
+        if !subnet_members.contains(&share.signature.signer) {
+            return Err(ValidationError::Permanent(
+                InvalidCanisterHttpPayload::SignerNotInSubnet(share.signature.signer),
+            ));
+        }
+        self.crypto.verify(share, registry_version).map_err(|err| {
+            if err.is_replicated() {
+                ValidationError::Permanent(InvalidCanisterHttpPayload::InvalidSignature(err))
+            } else {
+                ValidationError::Transient(CanisterHttpTransientValidationError::CryptoError(err))
+            }
+        })
+    }
+}
+
+impl CanisterHttpPayloadBuilder for CanisterHttpPayloadBuilderImpl {
+    fn get_canister_http_payload(
+        &self,
+        validation_context: &ValidationContext,
+        past_payloads: &[&CanisterHttpPayload],
+        byte_limit: NumBytes,
+    ) -> CanisterHttpPayload {
+        let state = match self
+            .state_manager
+            .get_state_at(validation_context.certified_height)
+        {
+            Ok(state) => state,
+            Err(err) => {
+                warn!(
+                    every_n_seconds => 5,
+                    self.log,
+                    "Failed to get the state at height {}: {:?}",
+                    validation_context.certified_height,
+                    err
+                );
+                return CanisterHttpPayload::default();
+            }
+        };
+        let registry_version = validation_context.registry_version;
+        let subnet_members = match self.get_subnet_members(registry_version) {
+            Some(subnet_members) => subnet_members,
+            None => {
+                warn!(
+                    every_n_seconds => 5,
+                    self.log,
+                    "Failed to get the subnet members at registry version {}",
+                    registry_version
+                );
+                return CanisterHttpPayload::default();
+            }
+        };
+        let threshold = get_faults_tolerated(subnet_members.len()) + 1;
+        let contexts = &state
+            .get_ref()
+            .metadata
+            .subnet_call_context_manager
+            .canister_http_request_contexts;
+
+        let mut requested = self.requested.lock().unwrap();
+        let mut responses = self.responses.lock().unwrap();
+        let mut shares = self.shares.lock().unwrap();
+        // Requests that are no longer open have been answered already.
+        requested.retain(|id| contexts.contains_key(id));
+        responses.retain(|id, _| contexts.contains_key(id));
+        shares.retain(|id, _| contexts.contains_key(id));
+
+        for (callback_id, context) in contexts.iter() {
+            if requested.contains(callback_id) {
+                continue;
+            }
+            match self.adapter_client.send(context.to_request(*callback_id)) {
+                Ok(()) => {
+                    requested.insert(*callback_id);
+                }
+                // The request is sent again when the next payload is built.
+                Err(CanisterHttpSendError::Full(_)) => break,
+                Err(CanisterHttpSendError::Disconnected(_)) => {
+                    warn!(
+                        every_n_seconds => 5,
+                        self.log,
+                        "The canister http adapter is disconnected"
+                    );
+                    break;
+                }
+            }
+        }
+        while let Some(response) = self.adapter_client.try_receive() {
+            if requested.contains(&response.id) {
+                responses.insert(response.id, response);
+            }
+        }
+
+        // Collect the shares of the other nodes from the past payloads. These
+        // were validated together with the blocks that contain them.
+        let mut published = BTreeSet::new();
+        for share in past_payloads
+            .iter()
+            .flat_map(|payload| payload.shares.iter())
+        {
+            if !contexts.contains_key(&share.content.id) {
+                continue;
+            }
+            published.insert((
+                share.content.id,
+                share.content.registry_version,
+                share.signature.signer,
+            ));
+            shares
+                .entry(share.content.id)
+                .or_default()
+                .entry(share.signature.signer)
+                .or_insert_with(|| share.clone());
+        }
+
+        let delivered = delivered_responses(past_payloads);
+        let mut payload = CanisterHttpPayload::default();
+        let mut payload_size = 0;
+        for (id, response) in responses.iter() {
+            if delivered.contains(id) {
+                continue;
+            }
+            let own_share = match self.get_own_share(&mut shares, response, registry_version) {
+                Some(share) => share,
+                None => continue,
+            };
+            let proof: Vec<_> = shares[id]
+                .values()
+                .filter(|share| {
+                    share.content == own_share.content
+                        && subnet_members.contains(&share.signature.signer)
+                })
+                .take(threshold)
+                .cloned()
+                .collect();
+            if proof.len() >= threshold {
+                let response = CanisterHttpResponseWithConsensus {
+                    content: response.clone(),
+                    proof,
+                };
+                let response_size = response.count_bytes();
+                if (payload_size + response_size) as u64 > byte_limit.get() {
+                    break;
+                }
+                payload_size += response_size;
+                payload.responses.push(response);
+            } else if !published.contains(&(*id, registry_version, self.node_id)) {
+                let share_size = own_share.count_bytes();
+                if (payload_size + share_size) as u64 > byte_limit.get() {
+                    break;
+                }
+                payload_size += share_size;
+                payload.shares.push(own_share);
+            }
+        }
+        payload
+    }
+
+    fn validate_canister_http_payload(
+        &self,
+        payload: &CanisterHttpPayload,
+        validation_context: &ValidationContext,
+        past_payloads: &[&CanisterHttpPayload],
+    ) -> Result<NumBytes, CanisterHttpPayloadValidationError> {
+        if payload.is_empty() {
+            return Ok(0.into());
+        }
+        let payload_size = payload.count_bytes();
+        if payload_size > MAX_CANISTER_HTTP_PAYLOAD_SIZE {
+            return Err(ValidationError::Permanent(
+                InvalidCanisterHttpPayload::PayloadTooBig {
+                    expected: NumBytes::from(MAX_CANISTER_HTTP_PAYLOAD_SIZE as u64),
+                    received: NumBytes::from(payload_size as u64),
+                },
+            ));
+        }
+
+        let state = self
+            .state_manager
+            .get_state_at(validation_context.certified_height)
+            .map_err(|_| {
+                ValidationError::Transient(CanisterHttpTransientValidationError::StateUnavailable(
+                    validation_context.certified_height,
+                ))
+            })?;
+        let registry_version = validation_context.registry_version;
+        let subnet_members =
+            self.get_subnet_members(registry_version)
+                .ok_or(ValidationError::Transient(
+                    CanisterHttpTransientValidationError::RegistryUnavailable(registry_version),
+                ))?;
+        let threshold = get_faults_tolerated(subnet_members.len()) + 1;
+        let contexts = &state
+            .get_ref()
+            .metadata
+            .subnet_call_context_manager
+            .canister_http_request_contexts;
+
+        let mut delivered = delivered_responses(past_payloads);
+        for response in payload.responses.iter() {
+            let id = response.content.id;
+            if !contexts.contains_key(&id) {
+                return Err(ValidationError::Permanent(
+                    InvalidCanisterHttpPayload::UnknownRequest(id),
+                ));
+            }
+            if !delivered.insert(id) {
+                return Err(ValidationError::Permanent(
+                    InvalidCanisterHttpPayload::DuplicateResponse(id),
+                ));
+            }
+            // The response is only accepted if enough nodes signed a share
+            // for exactly this response.
+            let content_hash = crypto_hash(&response.content);
+            let mut signers = BTreeSet::new();
+            for share in response.proof.iter() {
+                if share.content.id != id || share.content.content_hash != content_hash {
+                    return Err(ValidationError::Permanent(
+                        InvalidCanisterHttpPayload::ContentHashMismatch(id),
+                    ));
+                }
+                self.validate_share(share, &subnet_members, registry_version)?;
+                if !signers.insert(share.signature.signer) {
+                    return Err(ValidationError::Permanent(
+                        InvalidCanisterHttpPayload::DuplicateShare {
+                            id,
+                            signer: share.signature.signer,
+                        },
+                    ));
+                }
+            }
+            if signers.len() < threshold {
+                return Err(ValidationError::Permanent(
+                    InvalidCanisterHttpPayload::NotEnoughShares {
+                        id,
+                        expected: threshold,
+                        received: signers.len(),
+                    },
+                ));
+            }
+        }
+
+        let mut signers = BTreeSet::new();
+        for share in payload.shares.iter() {
+            let id = share.content.id;
+            if !contexts.contains_key(&id) {
+                return Err(ValidationError::Permanent(
+                    InvalidCanisterHttpPayload::UnknownRequest(id),
+                ));
+            }
+            self.validate_share(share, &subnet_members, registry_version)?;
+            if !signers.insert((id, share.signature.signer)) {
+                return Err(ValidationError::Permanent(
+                    InvalidCanisterHttpPayload::DuplicateShare {
+                        id,
+                        signer: share.signature.signer,
+                    },
+                ));
+            }
+        }
+        Ok(NumBytes::from(payload_size as u64))
+    }
+}
+
+/// Returns the ids of the requests answered in the given payloads.
+fn delivered_responses(past_payloads: &[&CanisterHttpPayload]) -> BTreeSet<CanisterHttpRequestId> {
+    past_payloads
+        .iter()
+        .flat_map(|payload| payload.responses.iter().map(|response| response.content.id))
+        .collect()
+}
+
+/// Produces the content of the response to a canister HTTP request.
+pub type CanisterHttpHandler =
+    Box<dyn Fn(&CanisterHttpRequest) -> CanisterHttpResponseContent + Send + Sync>;
+
+/// A [CanisterHttpAdapterClient] that answers requests in process by calling a
+/// handler instead of making network calls, e.g. for testing or on replicas
+/// that do not run an HTTP adapter.
+pub struct StubCanisterHttpAdapterClient {
+    handler: CanisterHttpHandler,
+    responses: Mutex<VecDeque<CanisterHttpResponse>>,
+}
+
+impl StubCanisterHttpAdapterClient {
+    /// Creates an adapter that answers every request with the result of
+    /// `handler`.
+    pub fn new(handler: CanisterHttpHandler) -> Self {
+        Self {
+            handler,
+            responses: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Creates an adapter that rejects every request.
+    pub fn reject_all() -> Self {
+        Self::new(Box::new(|_| {
+            CanisterHttpResponseContent::Reject(CanisterHttpReject {
+                reject_code: RejectCode::SysFatal,
+                message: "HTTP requests are not supported by this replica".to_string(),
+            })
+        }))
+    }
+}
+
+impl CanisterHttpAdapterClient for StubCanisterHttpAdapterClient {
+    fn send(&self, request: CanisterHttpRequest) -> Result<(), CanisterHttpSendError> {
+        let content = (self.handler)(&request);
+        self.responses
+            .lock()
+            .unwrap()
+            .push_back(CanisterHttpResponse {
+                id: request.id,
+                content,
+            });
+        Ok(())
+    }
+
+    fn try_receive(&self) -> Option<CanisterHttpResponse> {
+        self.responses.lock().unwrap().pop_front()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert_matches::assert_matches;
+    use ic_interfaces::state_manager::Labeled;
+    use ic_logger::replica_logger::no_op_logger;
+    use ic_replicated_state::metadata_state::subnet_call_context_manager::CanisterHttpRequestContext;
+    use ic_test_utilities::{
+        crypto::CryptoReturningOk,
+        mock_time,
+        registry::{setup_registry, SubnetRecordBuilder},
+        state::get_initial_state,
+        state_manager::MockStateManager,
+        types::{
+            ids::{node_test_id, subnet_test_id},
+            messages::RequestBuilder,
+        },
+    };
+    use ic_types::{canister_http::CanisterHttpMethod, Height};
+
+    /// The number of nodes on the test subnet, which tolerates one faulty
+    /// node and thus requires two matching shares.
+    const SUBNET_SIZE: u64 = 4;
+
+    /// Returns a payload builder for node 0 of a four node subnet, whose stub
+    /// adapter answers every request with its URL, on top of a state with
+    /// `num_requests` open requests.
+    fn make_payload_builder(num_requests: usize) -> CanisterHttpPayloadBuilderImpl {
+        let mut state = get_initial_state(0, 0);
+        for i in 0..num_requests {
+            state
+                .metadata
+                .subnet_call_context_manager
+                .push_http_request(CanisterHttpRequestContext {
+                    request: RequestBuilder::new().build(),
+                    url: format!("https://example.com/{}", i),
+                    http_method: CanisterHttpMethod::Get,
+                    headers: vec![],
+                    body: None,
+                    transform_method_name: None,
+                    time: mock_time(),
+                });
+        }
+        let mut state_manager = MockStateManager::new();
+        state_manager
+            .expect_get_state_at()
+            .return_const(Ok(Labeled::new(Height::new(0), Arc::new(state))));
+        let adapter_client = StubCanisterHttpAdapterClient::new(Box::new(|request| {
+            CanisterHttpResponseContent::Success(request.url.as_bytes().to_vec())
+        }));
+        let nodes: Vec<_> = (0..SUBNET_SIZE).map(node_test_id).collect();
+        let registry_client = setup_registry(
+            subnet_test_id(0),
+            vec![(1, SubnetRecordBuilder::from(&nodes).build())],
+        );
+        CanisterHttpPayloadBuilderImpl::new(
+            Arc::new(adapter_client),
+            Arc::new(state_manager),
+            Arc::new(CryptoReturningOk::default()),
+            registry_client,
+            node_test_id(0),
+            subnet_test_id(0),
+            no_op_logger(),
+        )
+    }
+
+    fn validation_context() -> ValidationContext {
+        ValidationContext {
+            registry_version: RegistryVersion::from(1),
+            certified_height: Height::new(0),
+            time: mock_time(),
+        }
+    }
+
+    fn response(id: u64) -> CanisterHttpResponse {
+        CanisterHttpResponse {
+            id: CanisterHttpRequestId::from(id),
+            content: CanisterHttpResponseContent::Success(
+                format!("https://example.com/{}", id).into_bytes(),
+            ),
+        }
+    }
+
+    /// Returns the share of the given node for the given response.
+    fn share(response: &CanisterHttpResponse, signer: u64) -> CanisterHttpResponseShare {
+        let metadata = CanisterHttpResponseMetadata {
+            id: response.id,
+            content_hash: crypto_hash(response),
+            registry_version: RegistryVersion::from(1),
+        };
+        let signature = CryptoReturningOk::default()
+            .sign(&metadata, node_test_id(signer), RegistryVersion::from(1))
+            .unwrap();
+        Signed {
+            content: metadata,
+            signature,
+        }
+    }
+
+    fn response_with_consensus(id: u64, signers: &[u64]) -> CanisterHttpResponseWithConsensus {
+        let content = response(id);
+        CanisterHttpResponseWithConsensus {
+            proof: signers
+                .iter()
+                .map(|signer| share(&content, *signer))
+                .collect(),
+            content,
+        }
+    }
+
+    #[test]
+    fn responses_are_only_included_once_enough_nodes_agree() {
+        let payload_builder = make_payload_builder(3);
+        let context = validation_context();
+        let limit = NumBytes::from(MAX_CANISTER_HTTP_PAYLOAD_SIZE as u64);
+
+        // Without the shares of other nodes, only this node's shares are
+        // included.
+        let payload = payload_builder.get_canister_http_payload(&context, &[], limit);
+        assert!(payload.responses.is_empty());
+        assert_eq!(
+            payload.shares,
+            (0..3).map(|id| share(&response(id), 0)).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            payload_builder.validate_canister_http_payload(&payload, &context, &[]),
+            Ok(NumBytes::from(payload.count_bytes() as u64))
+        );
+
+        // Shares are not included again once they are in a past payload.
+        let next_payload = payload_builder.get_canister_http_payload(&context, &[&payload], limit);
+        assert!(next_payload.is_empty());
+
+        // Once another node agrees on a response, the response is included.
+        let other_payload = CanisterHttpPayload {
+            responses: vec![],
+            shares: vec![share(&response(1), 2)],
+        };
+        let next_payload =
+            payload_builder.get_canister_http_payload(&context, &[&other_payload, &payload], limit);
+        assert_eq!(
+            next_payload.responses,
+            vec![response_with_consensus(1, &[0, 2])]
+        );
+        assert!(next_payload.shares.is_empty());
+        assert_eq!(
+            payload_builder.validate_canister_http_payload(&next_payload, &context, &[]),
+            Ok(NumBytes::from(next_payload.count_bytes() as u64))
+        );
+
+        // Responses included in past payloads are not included again.
+        let final_payload = payload_builder.get_canister_http_payload(
+            &context,
+            &[&next_payload, &other_payload, &payload],
+            limit,
+        );
+        assert!(final_payload.is_empty());
+    }
+
+    #[test]
+    fn shares_of_disagreeing_nodes_are_not_counted() {
+        let payload_builder = make_payload_builder(1);
+        let context = validation_context();
+        let limit = NumBytes::from(MAX_CANISTER_HTTP_PAYLOAD_SIZE as u64);
+
+        let mut other_response = response(0);
+        other_response.content = CanisterHttpResponseContent::Success(vec![1, 2, 3]);
+        let other_payload = CanisterHttpPayload {
+            responses: vec![],
+            shares: vec![share(&other_response, 1)],
+        };
+        let payload = payload_builder.get_canister_http_payload(&context, &[&other_payload], limit);
+        assert!(payload.responses.is_empty());
+        assert_eq!(payload.shares, vec![share(&response(0), 0)]);
+    }
+
+    #[test]
+    fn payload_respects_byte_limit() {
+        let payload_builder = make_payload_builder(3);
+        let limit = NumBytes::from(share(&response(0), 0).count_bytes() as u64 + 16);
+
+        let payload = payload_builder.get_canister_http_payload(&validation_context(), &[], limit);
+        assert_eq!(payload.shares.len(), 1);
+    }
+
+    #[test]
+    fn validation_rejects_unknown_and_duplicate_responses() {
+        let payload_builder = make_payload_builder(2);
+        let context = validation_context();
+
+        let payload = CanisterHttpPayload {
+            responses: vec![response_with_consensus(5, &[0, 1])],
+            shares: vec![],
+        };
+        assert_matches!(
+            payload_builder.validate_canister_http_payload(&payload, &context, &[]),
+            Err(ValidationError::Permanent(InvalidCanisterHttpPayload::UnknownRequest(id)))
+                if id == CanisterHttpRequestId::from(5)
+        );
+
+        let payload = CanisterHttpPayload {
+            responses: vec![
+                response_with_consensus(0, &[0, 1]),
+                response_with_consensus(0, &[0, 1]),
+            ],
+            shares: vec![],
+        };
+        assert_matches!(
+            payload_builder.validate_canister_http_payload(&payload, &context, &[]),
+            Err(ValidationError::Permanent(
+                InvalidCanisterHttpPayload::DuplicateResponse(_)
+            ))
+        );
+
+        let past_payload = CanisterHttpPayload {
+            responses: vec![response_with_consensus(1, &[0, 1])],
+            shares: vec![],
+        };
+        let payload = CanisterHttpPayload {
+            responses: vec![response_with_consensus(1, &[2, 3])],
+            shares: vec![],
+        };
+        assert_matches!(
+            payload_builder.validate_canister_http_payload(&payload, &context, &[&past_payload]),
+            Err(ValidationError::Permanent(
+                InvalidCanisterHttpPayload::DuplicateResponse(_)
+            ))
+        );
+    }
+
+    #[test]
+    fn validation_requires_enough_matching_shares() {
+        let payload_builder = make_payload_builder(1);
+        let context = validation_context();
+
+        let payload = CanisterHttpPayload {
+            responses: vec![response_with_consensus(0, &[1])],
+            shares: vec![],
+        };
+        assert_matches!(
+            payload_builder.validate_canister_http_payload(&payload, &context, &[]),
+            Err(ValidationError::Permanent(
+                InvalidCanisterHttpPayload::NotEnoughShares {
+                    expected: 2,
+                    received: 1,
+                    ..
+                }
+            ))
+        );
+
+        let payload = CanisterHttpPayload {
+            responses: vec![response_with_consensus(0, &[1, 1])],
+            shares: vec![],
+        };
+        assert_matches!(
+            payload_builder.validate_canister_http_payload(&payload, &context, &[]),
+            Err(ValidationError::Permanent(
+                InvalidCanisterHttpPayload::DuplicateShare { .. }
+            ))
+        );
+
+        // A share for a different response does not count.
+        let mut response = response_with_consensus(0, &[1, 2]);
+        response.content.content = CanisterHttpResponseContent::Success(vec![1, 2, 3]);
+        let payload = CanisterHttpPayload {
+            responses: vec![response],
+            shares: vec![],
+        };
+        assert_matches!(
+            payload_builder.validate_canister_http_payload(&payload, &context, &[]),
+            Err(ValidationError::Permanent(
+                InvalidCanisterHttpPayload::ContentHashMismatch(_)
+            ))
+        );
+
+        // Only members of the subnet can sign shares.
+        let payload = CanisterHttpPayload {
+            responses: vec![response_with_consensus(0, &[1, SUBNET_SIZE])],
+            shares: vec![],
+        };
+        assert_matches!(
+            payload_builder.validate_canister_http_payload(&payload, &context, &[]),
+            Err(ValidationError::Permanent(
+                InvalidCanisterHttpPayload::SignerNotInSubnet(signer)
+            )) if signer == node_test_id(SUBNET_SIZE)
+        );
+        let payload = CanisterHttpPayload {
+            responses: vec![],
+            shares: vec![share(&response(0), SUBNET_SIZE)],
+        };
+        assert_matches!(
+            payload_builder.validate_canister_http_payload(&payload, &context, &[]),
+            Err(ValidationError::Permanent(
+                InvalidCanisterHttpPayload::SignerNotInSubnet(_)
+            ))
+        );
+    }
+}
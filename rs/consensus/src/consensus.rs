@@ -49,6 +49,7 @@ use crate::consensus::{
 };
 use ic_config::consensus::ConsensusConfig;
 use ic_interfaces::{
+    canister_http::CanisterHttpPayloadBuilder,
     consensus::{Consensus, ConsensusGossip},
     consensus_pool::ConsensusPool,
     dkg::DkgPool,
@@ -133,6 +134,7 @@ impl ConsensusImpl {
         ingress_selector: Arc<dyn IngressSelector>,
         xnet_payload_builder: Arc<dyn XNetPayloadBuilder>,
         self_validating_payload_builder: Arc<dyn SelfValidatingPayloadBuilder>,
+        canister_http_payload_builder: Arc<dyn CanisterHttpPayloadBuilder>,
         dkg_pool: Arc<RwLock<dyn DkgPool>>,
         dkg_key_manager: Arc<Mutex<DkgKeyManager>>,
         message_routing: Arc<dyn MessageRouting>,
@@ -150,6 +152,7 @@ impl ConsensusImpl {
             ingress_selector.clone(),
             xnet_payload_builder,
             self_validating_payload_builder,
+            canister_http_payload_builder,
             metrics_registry.clone(),
             logger.clone(),
         ));
@@ -581,6 +584,7 @@ pub fn setup(
     ingress_selector: Arc<dyn IngressSelector>,
     xnet_payload_builder: Arc<dyn XNetPayloadBuilder>,
     self_validating_payload_builder: Arc<dyn SelfValidatingPayloadBuilder>,
+    canister_http_payload_builder: Arc<dyn CanisterHttpPayloadBuilder>,
     dkg_pool: Arc<RwLock<dyn DkgPool>>,
     dkg_key_manager: Arc<Mutex<DkgKeyManager>>,
     message_routing: Arc<dyn MessageRouting>,
@@ -613,6 +617,7 @@ pub fn setup(
             ingress_selector,
             xnet_payload_builder,
             self_validating_payload_builder,
+            canister_http_payload_builder,
             dkg_pool,
             dkg_key_manager,
             message_routing.clone(),
@@ -633,6 +638,7 @@ mod tests {
     use super::*;
     use crate::consensus::mocks::{dependencies_with_subnet_params, Dependencies};
     use ic_config::artifact_pool::ArtifactPoolConfig;
+    use ic_interfaces::canister_http::NoOpCanisterHttpPayloadBuilder;
     use ic_logger::replica_logger::no_op_logger;
     use ic_metrics::MetricsRegistry;
     use ic_protobuf::registry::subnet::v1::SubnetRecord;
@@ -702,6 +708,7 @@ mod tests {
             Arc::new(FakeIngressSelector::new()),
             Arc::new(FakeXNetPayloadBuilder::new()),
             Arc::new(FakeSelfValidatingPayloadBuilder::new()),
+            Arc::new(NoOpCanisterHttpPayloadBuilder {}),
             dkg_pool,
             Arc::new(Mutex::new(DkgKeyManager::new(
                 metrics_registry.clone(),
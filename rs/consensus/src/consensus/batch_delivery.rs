@@ -18,6 +18,7 @@ use ic_protobuf::log::consensus_log_entry::v1::ConsensusLogEntry;
 use ic_protobuf::registry::crypto::v1::PublicKey as PublicKeyProto;
 use ic_replicated_state::{metadata_state::subnet_call_context_manager::*, ReplicatedState};
 use ic_types::{
+    canister_http::CanisterHttpResponseContent,
     crypto::threshold_sig::ni_dkg::{
         NiDkgId, NiDkgTag, NiDkgTargetSubnet::Remote, NiDkgTranscript,
     },
@@ -128,14 +129,19 @@ pub fn deliver_batches(
                 // This flag can only be true, if we've called deliver_batches with a height
                 // limit.  In this case we also want to have a checkpoint for that last height.
                 let persist_batch = Some(h) == max_batch_height_to_deliver;
+                let requires_full_state_hash = block.payload.is_summary() || persist_batch;
+                let payload = if block.payload.is_summary() {
+                    BatchPayload::default()
+                } else {
+                    BlockPayload::from(block.payload).into_data().batch
+                };
+                consensus_responses.append(&mut generate_responses_to_canister_http_calls(
+                    &payload.canister_http,
+                ));
                 let batch = Batch {
                     batch_number: h,
-                    requires_full_state_hash: block.payload.is_summary() || persist_batch,
-                    payload: if block.payload.is_summary() {
-                        BatchPayload::default()
-                    } else {
-                        BlockPayload::from(block.payload).into_data().batch
-                    },
+                    requires_full_state_hash,
+                    payload,
                     randomness,
                     registry_version: block.context.registry_version,
                     time: block.context.time,
@@ -315,6 +321,36 @@ pub fn generate_responses_to_setup_initial_dkg_calls(
     consensus_responses
 }
 
+/// This function creates responses to the HttpRequest system calls from the
+/// canister http responses included in the block.
+pub fn generate_responses_to_canister_http_calls(
+    canister_http_payload: &CanisterHttpPayload,
+) -> Vec<Response> {
+    canister_http_payload
+        .responses
+        .iter()
+        .map(|response| {
+            let response = &response.content;
+            let response_payload = match &response.content {
+                CanisterHttpResponseContent::Success(data) => messages::Payload::Data(data.clone()),
+                CanisterHttpResponseContent::Reject(reject) => {
+                    messages::Payload::Reject(messages::RejectContext {
+                        code: reject.reject_code,
+                        message: reject.message.clone(),
+                    })
+                }
+            };
+            Response {
+                originator: CanisterId::ic_00(),
+                respondent: CanisterId::ic_00(),
+                originator_reply_callback: response.id,
+                refund: Cycles::zero(),
+                response_payload,
+            }
+        })
+        .collect()
+}
+
 const MOCK_ECDSA_DELAY_MILLIS: u64 = 30000;
 /// This function creates responses to the SignWithMockECDSA system calls with
 /// the computed MOCK(!) signature.
@@ -1,5 +1,6 @@
 use crate::consensus::prelude::*;
 use ic_interfaces::{crypto::*, validation::ValidationResult};
+use ic_types::canister_http::CanisterHttpResponseMetadata;
 use ic_types::consensus::ecdsa::EcdsaDealing;
 use ic_types::crypto::threshold_sig::ni_dkg::{DkgId, NiDkgId};
 use ic_types::crypto::CryptoError;
@@ -299,7 +300,11 @@ pub trait ConsensusCrypto:
         NiDkgId,
         ThresholdSignature<CatchUpContent>,
     > + SignVerify<dkg::DealingContent, BasicSignature<dkg::DealingContent>, RegistryVersion>
-    + Crypto
+    + SignVerify<
+        CanisterHttpResponseMetadata,
+        BasicSignature<CanisterHttpResponseMetadata>,
+        RegistryVersion,
+    > + Crypto
     + Send
     + Sync
 {
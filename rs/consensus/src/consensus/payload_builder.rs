@@ -2,6 +2,7 @@
 
 use crate::consensus::metrics::PayloadBuilderMetrics;
 use ic_interfaces::{
+    canister_http::CanisterHttpPayloadBuilder,
     consensus::{
         PayloadBuilderError, PayloadPermanentError, PayloadTransientError, PayloadValidationError,
     },
@@ -17,7 +18,10 @@ use ic_metrics::MetricsRegistry;
 use ic_registry_client::helper::subnet::SubnetRegistry;
 use ic_types::{
     artifact::IngressMessageId,
-    batch::{BatchPayload, SelfValidatingPayload, ValidationContext, XNetPayload},
+    batch::{
+        BatchPayload, CanisterHttpPayload, SelfValidatingPayload, ValidationContext, XNetPayload,
+    },
+    canister_http::MAX_CANISTER_HTTP_PAYLOAD_SIZE,
     consensus::{BlockPayload, Payload},
     crypto::CryptoHashOf,
     messages::MAX_XNET_PAYLOAD_IN_BYTES,
@@ -96,6 +100,7 @@ pub struct PayloadBuilderImpl {
     ingress_selector: Arc<dyn IngressSelector>,
     xnet_payload_builder: Arc<dyn XNetPayloadBuilder>,
     self_validating_payload_builder: Arc<dyn SelfValidatingPayloadBuilder>,
+    canister_http_payload_builder: Arc<dyn CanisterHttpPayloadBuilder>,
     metrics: PayloadBuilderMetrics,
     ingress_payload_cache: RwLock<IngressPayloadCache>,
     logger: ReplicaLogger,
@@ -109,6 +114,7 @@ impl PayloadBuilderImpl {
         ingress_selector: Arc<dyn IngressSelector>,
         xnet_payload_builder: Arc<dyn XNetPayloadBuilder>,
         self_validating_payload_builder: Arc<dyn SelfValidatingPayloadBuilder>,
+        canister_http_payload_builder: Arc<dyn CanisterHttpPayloadBuilder>,
         metrics: MetricsRegistry,
         logger: ReplicaLogger,
     ) -> Self {
@@ -118,6 +124,7 @@ impl PayloadBuilderImpl {
             ingress_selector,
             xnet_payload_builder,
             self_validating_payload_builder,
+            canister_http_payload_builder,
             metrics: PayloadBuilderMetrics::new(metrics),
             ingress_payload_cache: RwLock::new(BTreeMap::new()),
             logger,
@@ -147,7 +154,7 @@ impl PayloadBuilder for PayloadBuilderImpl {
             None => context.time,
             Some((_, time, _)) => *time,
         };
-        let (past_ingress, past_xnet, past_self_validating) =
+        let (past_ingress, past_xnet, past_self_validating, past_canister_http) =
             split_past_payloads(&mut ingress_payload_cache, past_payloads);
         self.metrics
             .past_payloads_length
@@ -195,10 +202,19 @@ impl PayloadBuilder for PayloadBuilderImpl {
             .self_validating_payload_builder
             .get_self_validating_payload(context, &past_self_validating, MAX_XNET_PAYLOAD_IN_BYTES);
 
+        let canister_http = self
+            .canister_http_payload_builder
+            .get_canister_http_payload(
+                context,
+                &past_canister_http,
+                NumBytes::new(MAX_CANISTER_HTTP_PAYLOAD_SIZE as u64),
+            );
+
         Ok(BatchPayload {
             ingress,
             xnet,
             self_validating,
+            canister_http,
         })
     }
 
@@ -218,7 +234,7 @@ impl PayloadBuilder for PayloadBuilderImpl {
             None => context.time,
             Some((_, time, _)) => *time,
         };
-        let (past_ingress, past_xnet, past_self_validating) =
+        let (past_ingress, past_xnet, past_self_validating, past_canister_http) =
             split_past_payloads(&mut ingress_payload_cache, past_payloads);
         self.metrics
             .ingress_payload_cache_size
@@ -262,6 +278,12 @@ impl PayloadBuilder for PayloadBuilderImpl {
                 context,
                 &past_self_validating,
             )?;
+        self.canister_http_payload_builder
+            .validate_canister_http_payload(
+                &batch_payload.canister_http,
+                context,
+                &past_canister_http,
+            )?;
 
         Ok(())
     }
@@ -306,7 +328,8 @@ impl PayloadBuilderImpl {
     }
 }
 
-/// Split past_payloads into past_ingress and past_xnet payloads. The
+/// Split past_payloads into past_ingress, past_xnet, past_self_validating and
+/// past_canister_http payloads. The
 /// past_ingress is actually a list of HashSet of MessageIds taken from the
 /// ingress_payload_cache.
 #[allow(clippy::type_complexity)]
@@ -317,6 +340,7 @@ fn split_past_payloads<'a, 'b>(
     Vec<Arc<HashSet<IngressMessageId>>>,
     Vec<&'b XNetPayload>,
     Vec<&'b SelfValidatingPayload>,
+    Vec<&'b CanisterHttpPayload>,
 ) {
     let past_xnet: Vec<_> = past_payloads
         .iter()
@@ -353,6 +377,16 @@ fn split_past_payloads<'a, 'b>(
             }
         })
         .collect();
+    let past_canister_http: Vec<_> = past_payloads
+        .iter()
+        .filter_map(|(_, _, payload)| {
+            if payload.is_summary() {
+                None
+            } else {
+                Some(&payload.as_ref().as_data().batch.canister_http)
+            }
+        })
+        .collect();
     // We assume that 'past_payloads' comes in descending heights, following the
     // block parent traversal order.
     if let Some((min_height, _, _)) = past_payloads.last() {
@@ -370,14 +404,22 @@ fn split_past_payloads<'a, 'b>(
             }
         }
     }
-    (past_ingress, past_xnet, past_self_validating)
+    (
+        past_ingress,
+        past_xnet,
+        past_self_validating,
+        past_canister_http,
+    )
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::consensus::mocks::{dependencies, dependencies_with_subnet_params, Dependencies};
-    use ic_interfaces::self_validating_payload::NoOpSelfValidatingPayloadBuilder;
+    use ic_interfaces::{
+        canister_http::NoOpCanisterHttpPayloadBuilder,
+        self_validating_payload::NoOpSelfValidatingPayloadBuilder,
+    };
     use ic_logger::replica_logger::no_op_logger;
     use ic_test_artifact_pool::ingress_pool::TestIngressPool;
     use ic_test_utilities::{
@@ -415,6 +457,7 @@ mod test {
         let xnet_payload_builder =
             FakeXNetPayloadBuilder::make(certified_streams.drain(..).collect());
         let self_validating_payload_builder = NoOpSelfValidatingPayloadBuilder {};
+        let canister_http_payload_builder = NoOpCanisterHttpPayloadBuilder {};
 
         PayloadBuilderImpl::new(
             subnet_test_id(0),
@@ -422,6 +465,7 @@ mod test {
             Arc::new(ingress_selector),
             Arc::new(xnet_payload_builder),
             Arc::new(self_validating_payload_builder),
+            Arc::new(canister_http_payload_builder),
             MetricsRegistry::new(),
             no_op_logger(),
         )
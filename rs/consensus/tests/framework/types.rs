@@ -5,6 +5,7 @@ use ic_artifact_pool::{
 use ic_config::artifact_pool::ArtifactPoolConfig;
 use ic_consensus::{consensus::ConsensusImpl, dkg};
 use ic_interfaces::{
+    canister_http::{CanisterHttpPayloadBuilder, NoOpCanisterHttpPayloadBuilder},
     certification::Certifier,
     certified_stream_store::CertifiedStreamStore,
     ingress_manager::IngressSelector,
@@ -138,6 +139,7 @@ pub struct ConsensusDependencies {
     pub(crate) xnet_payload_builder: Arc<dyn XNetPayloadBuilder>,
     pub(crate) ingress_selector: Arc<dyn IngressSelector>,
     pub(crate) self_validating_payload_builder: Arc<dyn SelfValidatingPayloadBuilder>,
+    pub(crate) canister_http_payload_builder: Arc<dyn CanisterHttpPayloadBuilder>,
     pub consensus_pool: Arc<RwLock<ConsensusPoolImpl>>,
     pub dkg_pool: Arc<RwLock<dkg_pool::DkgPoolImpl>>,
     pub message_routing: Arc<dyn MessageRouting>,
@@ -178,6 +180,7 @@ impl ConsensusDependencies {
             ingress_selector: Arc::new(FakeIngressSelector::new()),
             xnet_payload_builder: Arc::new(xnet_payload_builder),
             self_validating_payload_builder: Arc::new(FakeSelfValidatingPayloadBuilder::new()),
+            canister_http_payload_builder: Arc::new(NoOpCanisterHttpPayloadBuilder {}),
             state_manager,
             metrics_registry,
             replica_config,
@@ -4,7 +4,9 @@ use crate::framework::ConsensusDriver;
 use ic_artifact_pool::{consensus_pool, dkg_pool};
 use ic_consensus::consensus::dkg_key_manager::DkgKeyManager;
 use ic_consensus::{certification::CertifierImpl, consensus::ConsensusImpl, dkg};
-use ic_interfaces::{state_manager::Labeled, time_source::TimeSource};
+use ic_interfaces::{
+    canister_http::NoOpCanisterHttpPayloadBuilder, state_manager::Labeled, time_source::TimeSource,
+};
 use ic_logger::replica_logger::no_op_logger;
 use ic_metrics::MetricsRegistry;
 use ic_test_utilities::{
@@ -124,6 +126,7 @@ fn consensus_produces_expected_batches() {
             Arc::clone(&ingress_selector) as Arc<_>,
             Arc::clone(&xnet_payload_builder) as Arc<_>,
             Arc::clone(&self_validating_payload_builder) as Arc<_>,
+            Arc::new(NoOpCanisterHttpPayloadBuilder {}),
             Arc::clone(&dkg_pool) as Arc<_>,
             dkg_key_manager.clone(),
             Arc::clone(&router) as Arc<_>,
@@ -39,8 +39,25 @@ pub fn parse_threshold_sig_key(pem_file: &Path) -> Result<ThresholdSigPublicKey>
     let decoded = base64::decode(&lines[1..n - 1].join(""))
         .map_err(|err| invalid_data_err(format!("failed to decode base64: {}", err)))?;
 
-    let pubkey_bytes = bls12_381::api::public_key_from_der(&decoded)
-        .map_err(|err| invalid_data_err(format!("failed to decode public key: {}", err)))?;
+    parse_threshold_sig_key_from_der(&decoded)
+}
+
+/// Parse a DER format threshold signature public key, e.g. the root key
+/// reported by `/api/v2/status` or a subnet public key in the state tree.
+///
+/// # Arguments
+/// * `der_bytes` is the DER encoding of the key.
+/// # Returns
+/// The decoded `ThresholdSigPublicKey`
+/// # Error
+/// * `std::io::Error` if the encoded key is not BLS12-381.
+pub fn parse_threshold_sig_key_from_der(der_bytes: &[u8]) -> Result<ThresholdSigPublicKey> {
+    let pubkey_bytes = bls12_381::api::public_key_from_der(der_bytes).map_err(|err| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("failed to decode public key: {}", err),
+        )
+    })?;
 
     Ok(ThresholdSigPublicKey::from(pubkey_bytes))
 }
@@ -70,6 +87,22 @@ GY4Bb/WQ5wfxhiUca4hbVIRfOkPlNtXSg/AHff5QIckWPifeyRB/S9A1jjg1XdKP
     );
     }
 
+    #[test]
+    fn can_parse_der_key() {
+        let der = base64::decode(
+            "MIGCMB0GDSsGAQQBgtx8BQMBAgEGDCsGAQQBgtx8BQMCAQNhAKOY3Qk9qTesCRaL\
+             GY4Bb/WQ5wfxhiUca4hbVIRfOkPlNtXSg/AHff5QIckWPifeyRB/S9A1jjg1XdKP\
+             5lSemYM6VVTrGhjShUwHqVmdOBJ8ofpb2+qV/2ppvxc+3OFBvA==",
+        )
+        .unwrap();
+        let pk = parse_threshold_sig_key_from_der(&der).unwrap();
+        assert_eq!(
+            hex::encode(&pk.into_bytes()[..]),
+            "a398dd093da937ac09168b198e016ff590e707f186251c6b885b54845f3a43e536d5d283f0077dfe5021c9163e27dec9107f4bd0358e38355dd28fe6549e99833a5554eb1a18d2854c07a9599d38127ca1fa5bdbea95ff6a69bf173edce141bc"
+        );
+        assert!(parse_threshold_sig_key_from_der(&der[1..]).is_err());
+    }
+
     #[test]
     fn base64_decode_fails() {
         use std::io::Write;
@@ -20,7 +20,8 @@ use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{CanisterState, SystemState};
 use ic_types::{
     ic00::{
-        CanisterIdRecord, InstallCodeArgs, Method, Payload, SetControllerArgs, UpdateSettingsArgs,
+        CanisterIdRecord, CanisterSnapshotArgs, InstallCodeArgs, Method, Payload,
+        SetControllerArgs, TakeCanisterSnapshotArgs, UpdateSettingsArgs,
     },
     messages::{
         is_subnet_message, Request, Response, SignedIngressContent,
@@ -51,6 +52,18 @@ impl std::fmt::Display for CyclesAccountManagerError {
     }
 }
 
+/// Errors returned by [`CyclesAccountManager::reserve_cycles`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReservationError {
+    /// The main balance of the canister does not have the cycles to reserve.
+    InsufficientCycles {
+        requested: Cycles,
+        available: Cycles,
+    },
+    /// The reserved balance would exceed the limit of the canister.
+    ReservedLimitExceeded { requested: Cycles, limit: Cycles },
+}
+
 /// Handles any operation related to cycles accounting, such as charging (due to
 /// using system resources) or refunding unused cycles.
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -134,6 +147,13 @@ impl CyclesAccountManager {
         self.config.xnet_byte_transmission_fee * Cycles::from(payload_size.get())
     }
 
+    /// Returns the fee for a canister HTTP request of `request_size` bytes in
+    /// [`Cycles`].
+    pub fn http_request_fee(&self, request_size: NumBytes) -> Cycles {
+        self.config.http_request_fee
+            + self.config.http_request_byte_fee * Cycles::from(request_size.get())
+    }
+
     /// Returns the freezing threshold for this canister in Cycles.
     pub fn freeze_threshold_cycles(
         &self,
@@ -263,7 +283,8 @@ impl CyclesAccountManager {
         self.refund_cycles(system_state, cycles_to_refund);
     }
 
-    /// Charges the canister for its compute allocation
+    /// Charges the canister for its compute allocation. The cycles are taken
+    /// from the reserved balance first.
     ///
     /// # Errors
     ///
@@ -276,9 +297,7 @@ impl CyclesAccountManager {
         duration: Duration,
     ) -> Result<(), CanisterOutOfCyclesError> {
         let cycles = self.compute_allocation_cost(compute_allocation, duration);
-
-        // Can charge all the way to the empty account (zero cycles)
-        self.consume_with_threshold(system_state, cycles, Cycles::from(0))
+        self.consume_allocation_fee(system_state, cycles)
     }
 
     /// The cost of compute allocation, per round
@@ -315,10 +334,13 @@ impl CyclesAccountManager {
                 | Ok(Method::CanisterStatus)
                 | Ok(Method::DeleteCanister)
                 | Ok(Method::UninstallCode)
-                | Ok(Method::StopCanister) => match CanisterIdRecord::decode(ingress.arg()) {
-                    Ok(record) => Some(record.get_canister_id()),
-                    Err(_) => return Err(IngressInductionCostError::InvalidSubnetPayload),
-                },
+                | Ok(Method::StopCanister)
+                | Ok(Method::ListCanisterSnapshots) => {
+                    match CanisterIdRecord::decode(ingress.arg()) {
+                        Ok(record) => Some(record.get_canister_id()),
+                        Err(_) => return Err(IngressInductionCostError::InvalidSubnetPayload),
+                    }
+                }
                 Ok(Method::UpdateSettings) => match UpdateSettingsArgs::decode(ingress.arg()) {
                     Ok(record) => Some(record.get_canister_id()),
                     Err(_) => return Err(IngressInductionCostError::InvalidSubnetPayload),
@@ -331,9 +353,23 @@ impl CyclesAccountManager {
                     Ok(record) => Some(record.get_canister_id()),
                     Err(_) => return Err(IngressInductionCostError::InvalidSubnetPayload),
                 },
+                Ok(Method::TakeCanisterSnapshot) => {
+                    match TakeCanisterSnapshotArgs::decode(ingress.arg()) {
+                        Ok(record) => Some(record.get_canister_id()),
+                        Err(_) => return Err(IngressInductionCostError::InvalidSubnetPayload),
+                    }
+                }
+                Ok(Method::LoadCanisterSnapshot) | Ok(Method::DeleteCanisterSnapshot) => {
+                    match CanisterSnapshotArgs::decode(ingress.arg()) {
+                        Ok(record) => Some(record.get_canister_id()),
+                        Err(_) => return Err(IngressInductionCostError::InvalidSubnetPayload),
+                    }
+                }
                 Ok(Method::CreateCanister)
                 | Ok(Method::SetupInitialDKG)
                 | Ok(Method::DepositCycles)
+                | Ok(Method::FetchCanisterLogs)
+                | Ok(Method::HttpRequest)
                 | Ok(Method::RawRand)
                 | Ok(Method::SignWithECDSA)
                 | Ok(Method::GetMockECDSAPublicKey)
@@ -374,7 +410,8 @@ impl CyclesAccountManager {
     //
     ////////////////////////////////////////////////////////////////////////////
 
-    /// Subtracts the cycles cost of using a `bytes` amount of memory.
+    /// Subtracts the cycles cost of using a `bytes` amount of memory. The
+    /// cycles are taken from the reserved balance first.
     ///
     /// Note: The following charges for memory taken by the canister. It
     /// currently takes into account all the pages in the canister's heap and
@@ -392,9 +429,7 @@ impl CyclesAccountManager {
         duration: Duration,
     ) -> Result<(), CanisterOutOfCyclesError> {
         let cycles_amount = self.memory_cost(bytes, duration);
-
-        // Can charge all the way to the empty account (zero cycles)
-        self.consume_with_threshold(system_state, cycles_amount, Cycles::from(0))
+        self.consume_allocation_fee(system_state, cycles_amount)
     }
 
     /// The cost of using `bytes` worth of memory.
@@ -409,6 +444,57 @@ impl CyclesAccountManager {
         )
     }
 
+    ////////////////////////////////////////////////////////////////////////////
+    //
+    // Reservation
+    //
+    ////////////////////////////////////////////////////////////////////////////
+
+    /// Returns the amount of cycles a canister has to reserve up front when
+    /// it increases its memory allocation by `memory_bytes` and its compute
+    /// allocation by `compute_allocation` while the subnet is close to its
+    /// capacity.
+    pub fn allocation_reservation_cycles(
+        &self,
+        memory_bytes: NumBytes,
+        compute_allocation: ComputeAllocation,
+    ) -> Cycles {
+        let duration = self.config.allocation_reservation_duration;
+        self.memory_cost(memory_bytes, duration)
+            + self.compute_allocation_cost(compute_allocation, duration)
+    }
+
+    /// Moves `cycles` from the main balance of the canister to its reserved
+    /// balance, provided that the reserved balance stays within `limit`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ReservationError` if the main balance does not have enough
+    /// cycles or if the reserved balance would exceed `limit`. The balances
+    /// are unchanged in that case.
+    pub fn reserve_cycles(
+        &self,
+        system_state: &mut SystemState,
+        cycles: Cycles,
+        limit: Cycles,
+    ) -> Result<(), ReservationError> {
+        if system_state.reserved_balance + cycles > limit {
+            return Err(ReservationError::ReservedLimitExceeded {
+                requested: system_state.reserved_balance + cycles,
+                limit,
+            });
+        }
+        if cycles > system_state.cycles_balance {
+            return Err(ReservationError::InsufficientCycles {
+                requested: cycles,
+                available: system_state.cycles_balance,
+            });
+        }
+        system_state.cycles_balance -= cycles;
+        system_state.reserved_balance += cycles;
+        Ok(())
+    }
+
     ////////////////////////////////////////////////////////////////////////////
     //
     // Request
@@ -507,6 +593,25 @@ impl CyclesAccountManager {
         }
     }
 
+    /// Consumes the fee for memory or compute allocation, taking as much as
+    /// possible from the reserved balance and the rest from the main balance.
+    /// Can charge all the way to the empty account (zero cycles).
+    fn consume_allocation_fee(
+        &self,
+        system_state: &mut SystemState,
+        cycles: Cycles,
+    ) -> Result<(), CanisterOutOfCyclesError> {
+        let from_reserved_balance = std::cmp::min(cycles, system_state.reserved_balance);
+        self.withdraw_with_threshold(
+            system_state,
+            cycles - from_reserved_balance,
+            Cycles::from(0),
+        )?;
+        system_state.reserved_balance -= from_reserved_balance;
+        self.observe_consumed_cycles(system_state, cycles);
+        Ok(())
+    }
+
     /// Note that this function is made public only for the tests.
     #[doc(hidden)]
     pub fn refund_cycles(&self, system_state: &mut SystemState, cycles: Cycles) {
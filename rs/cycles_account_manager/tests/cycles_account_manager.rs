@@ -1,6 +1,8 @@
 use ic_base_types::NumSeconds;
 use ic_config::subnet_config::SubnetConfigs;
-use ic_cycles_account_manager::{IngressInductionCost, IngressInductionCostError};
+use ic_cycles_account_manager::{
+    IngressInductionCost, IngressInductionCostError, ReservationError,
+};
 use ic_interfaces::execution_environment::CanisterOutOfCyclesError;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::SystemState;
@@ -769,3 +771,108 @@ fn verify_refund() {
         initial_consumed_cycles - NominalCycles::from(cycles)
     );
 }
+
+#[test]
+fn memory_cost_scales_linearly_with_large_stable_memory() {
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+    let duration = Duration::from_secs(30 * 24 * 60 * 60);
+    let cost_per_gib = cycles_account_manager.memory_cost(NumBytes::from(1 << 30), duration);
+    for gibs in &[8u64, 16, 32, 64] {
+        assert_eq!(
+            cycles_account_manager.memory_cost(NumBytes::from(gibs << 30), duration),
+            Cycles::from(cost_per_gib.get() * *gibs as u128)
+        );
+    }
+}
+
+#[test]
+fn reserve_cycles_moves_cycles_to_reserved_balance() {
+    let mut system_state = SystemStateBuilder::new().build();
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+
+    cycles_account_manager
+        .reserve_cycles(&mut system_state, Cycles::from(1_000u64), INITIAL_CYCLES)
+        .unwrap();
+    assert_eq!(system_state.reserved_balance, Cycles::from(1_000u64));
+    assert_eq!(
+        system_state.cycles_balance,
+        INITIAL_CYCLES - Cycles::from(1_000u64)
+    );
+    // Reserving cycles does not consume them.
+    assert_eq!(
+        system_state
+            .canister_metrics
+            .consumed_cycles_since_replica_started,
+        NominalCycles::from(0)
+    );
+}
+
+#[test]
+fn reserve_cycles_respects_limit_and_balance() {
+    let mut system_state = SystemStateBuilder::new().build();
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+
+    assert_eq!(
+        cycles_account_manager.reserve_cycles(
+            &mut system_state,
+            Cycles::from(1_000u64),
+            Cycles::from(999u64)
+        ),
+        Err(ReservationError::ReservedLimitExceeded {
+            requested: Cycles::from(1_000u64),
+            limit: Cycles::from(999u64),
+        })
+    );
+    assert_eq!(
+        cycles_account_manager.reserve_cycles(
+            &mut system_state,
+            INITIAL_CYCLES + Cycles::from(1u64),
+            Cycles::from(u128::MAX)
+        ),
+        Err(ReservationError::InsufficientCycles {
+            requested: INITIAL_CYCLES + Cycles::from(1u64),
+            available: INITIAL_CYCLES,
+        })
+    );
+    assert_eq!(system_state.reserved_balance, Cycles::zero());
+    assert_eq!(system_state.cycles_balance, INITIAL_CYCLES);
+}
+
+#[test]
+fn allocation_charges_use_reserved_balance_first() {
+    let mut system_state = SystemStateBuilder::new().build();
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+    let duration = Duration::from_secs(1);
+    let bytes = NumBytes::from(1 << 30);
+    let memory_fee = cycles_account_manager.memory_cost(bytes, duration);
+    let compute_allocation = ComputeAllocation::try_from(10).unwrap();
+    let compute_fee = cycles_account_manager.compute_allocation_cost(compute_allocation, duration);
+
+    // Reserve enough for the memory fee and half of the compute fee.
+    let reserved = memory_fee + Cycles::from(compute_fee.get() / 2);
+    cycles_account_manager
+        .reserve_cycles(&mut system_state, reserved, INITIAL_CYCLES)
+        .unwrap();
+    let balance = system_state.cycles_balance;
+
+    cycles_account_manager
+        .charge_for_memory(&mut system_state, bytes, duration)
+        .unwrap();
+    assert_eq!(system_state.cycles_balance, balance);
+    assert_eq!(system_state.reserved_balance, reserved - memory_fee);
+
+    cycles_account_manager
+        .charge_for_compute_allocation(&mut system_state, compute_allocation, duration)
+        .unwrap();
+    assert_eq!(system_state.reserved_balance, Cycles::zero());
+    assert_eq!(
+        system_state.cycles_balance,
+        balance - (compute_fee - Cycles::from(compute_fee.get() / 2))
+    );
+    assert_eq!(
+        system_state
+            .canister_metrics
+            .consumed_cycles_since_replica_started,
+        NominalCycles::from_cycles(memory_fee + compute_fee)
+    );
+}
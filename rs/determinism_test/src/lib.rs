@@ -17,7 +17,10 @@ use ic_state_manager::StateManagerImpl;
 use ic_test_utilities::types::messages::SignedIngressBuilder;
 use ic_types::{
     artifact::SignedIngress,
-    batch::{Batch, BatchPayload, IngressPayload, SelfValidatingPayload, XNetPayload},
+    batch::{
+        Batch, BatchPayload, CanisterHttpPayload, IngressPayload, SelfValidatingPayload,
+        XNetPayload,
+    },
     ingress::{IngressStatus, WasmResult},
     messages::{CanisterInstallMode, MessageId},
     time::UNIX_EPOCH,
@@ -25,7 +28,7 @@ use ic_types::{
 };
 use setup::setup;
 use std::{convert::TryFrom, sync::Arc, thread::sleep, time::Duration};
-use wabt::wat2wasm;
+use wabt::{wat2wasm_with_features, Features};
 
 fn build_batch(message_routing: &dyn MessageRouting, msgs: Vec<SignedIngress>) -> Batch {
     Batch {
@@ -37,6 +40,7 @@ fn build_batch(message_routing: &dyn MessageRouting, msgs: Vec<SignedIngress>) -
                 stream_slices: Default::default(),
             },
             self_validating: SelfValidatingPayload::default(),
+            canister_http: CanisterHttpPayload::default(),
         },
         randomness: Randomness::from([0; 32]),
         registry_version: RegistryVersion::from(1),
@@ -55,6 +59,7 @@ fn build_batch_with_full_state_hash(message_routing: &dyn MessageRouting) -> Bat
                 stream_slices: Default::default(),
             },
             self_validating: SelfValidatingPayload::default(),
+            canister_http: CanisterHttpPayload::default(),
         },
         randomness: Randomness::from([0; 32]),
         registry_version: RegistryVersion::from(1),
@@ -171,7 +176,10 @@ fn install_canister(
         Err(err) => panic!("{}", err),
     };
 
-    let wasm = wat2wasm(wasm).unwrap();
+    let mut features = Features::new();
+    features.enable_simd();
+    features.enable_bulk_memory();
+    let wasm = wat2wasm_with_features(wasm, features).unwrap();
     let signed_ingress = SignedIngressBuilder::new()
         .canister_id(IC_00)
         .expiry_time(UNIX_EPOCH + Duration::from_secs(60))
@@ -223,9 +231,27 @@ const WASM: &str = r#"(module
   (call $msg_reply)
 )
 
+(func $simd
+  ;; The square root of a negative number is a NaN whose bit pattern must
+  ;; be canonicalized to be deterministic.
+  (v128.store (i32.const 8192)
+    (f32x4.sqrt (f32x4.splat (f32.const -1))))
+  (v128.store (i32.const 8208)
+    (f64x2.div (f64x2.splat (f64.const 0)) (f64x2.splat (f64.const 0))))
+  (call $msg_reply)
+)
+
+(func $bulk
+  (memory.fill (i32.const 16384) (i32.const 42) (i32.const 8192))
+  (memory.copy (i32.const 0) (i32.const 16384) (i32.const 4096))
+  (call $msg_reply)
+)
+
 (memory $memory 1)
 (export "canister_update dirty1" (func $dirty1))
 (export "canister_update dirty2" (func $dirty2))
+(export "canister_update simd" (func $simd))
+(export "canister_update bulk" (func $bulk))
 (export "memory" (memory $memory)))
 "#;
 
@@ -11,6 +11,7 @@ use ic_metrics_exporter::MetricsRuntimeImpl;
 use ic_protobuf::registry::{
     provisional_whitelist::v1::ProvisionalWhitelist as PbProvisionalWhitelist,
     routing_table::v1::RoutingTable as PbRoutingTable,
+    subnet::v1::SubnetFeatures as PbSubnetFeatures,
 };
 use ic_protobuf::types::v1::PrincipalId as PrincipalIdIdProto;
 use ic_protobuf::types::v1::SubnetId as SubnetIdProto;
@@ -75,6 +76,11 @@ fn get_registry(
     // Set subnetwork list(needed for filling network_topology.nns_subnet_id)
     let mut record = SubnetRecordBuilder::from(node_ids).build();
     record.subnet_type = i32::from(subnet_type);
+    // Exercise the Wasm features that are behind subnet features as well.
+    record.features = Some(PbSubnetFeatures {
+        wasm_simd: true,
+        ..PbSubnetFeatures::default()
+    });
 
     insert_initial_dkg_transcript(registry_version.get(), subnet_id, &record, &data_provider);
     add_subnet_record(&data_provider, registry_version.get(), subnet_id, record);
@@ -6,3 +6,8 @@ fn test_process_batches_deterministically() {
         "dirty1", "dirty2", "dirty1", "dirty2", "dirty1", "dirty2",
     ]);
 }
+
+#[test]
+fn test_process_simd_and_bulk_memory_batches_deterministically() {
+    determinism_test(vec!["simd", "bulk", "dirty1", "simd", "bulk", "dirty2"]);
+}
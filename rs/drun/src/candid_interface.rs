@@ -0,0 +1,114 @@
+//! Candid support: payloads given as textual Candid values and, if the
+//! interface of the canisters is provided as a `.did` file, typed encoding of
+//! arguments and decoding of replies.
+use candid::{check_prog, types::Type, IDLArgs, IDLProg, TypeEnv};
+use std::path::Path;
+
+/// The interface of a canister as described by a `.did` file.
+pub(crate) struct CandidInterface {
+    env: TypeEnv,
+    actor: Option<Type>,
+}
+
+/// The method that textual Candid arguments are passed to.
+#[derive(Clone, Copy)]
+pub(crate) enum CandidMethod<'a> {
+    /// The arguments of `canister_init`, i.e. the arguments of the service
+    /// constructor in the `.did` file.
+    Init,
+    Call(&'a str),
+}
+
+impl CandidInterface {
+    pub(crate) fn from_file(path: &Path) -> Result<Self, String> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|err| format!("Could not read {}: {}", path.display(), err))?;
+        let prog = source
+            .parse::<IDLProg>()
+            .map_err(|err| format!("Failed to parse {}: {}", path.display(), err))?;
+        let mut env = TypeEnv::new();
+        let actor = check_prog(&mut env, &prog)
+            .map_err(|err| format!("Failed to type check {}: {}", path.display(), err))?;
+        Ok(Self { env, actor })
+    }
+
+    /// Returns the argument types of the given method, if the interface
+    /// declares it.
+    fn arg_types(&self, method: CandidMethod<'_>) -> Option<&[Type]> {
+        match (method, &self.actor) {
+            (CandidMethod::Init, Some(Type::Class(args, _))) => Some(&args[..]),
+            (CandidMethod::Init, _) => None,
+            (CandidMethod::Call(method_name), _) => {
+                self.method(method_name).map(|function| &function.args[..])
+            }
+        }
+    }
+
+    fn method(&self, method_name: &str) -> Option<&candid::types::Function> {
+        self.actor
+            .as_ref()
+            .and_then(|actor| self.env.get_method(actor, method_name).ok())
+    }
+
+    /// Decodes the reply of `method_name`, if the interface declares the
+    /// method and the reply matches its return types.
+    pub(crate) fn decode_reply(&self, method_name: &str, reply: &[u8]) -> Option<IDLArgs> {
+        let function = self.method(method_name)?;
+        IDLArgs::from_bytes_with_types(reply, &self.env, &function.rets).ok()
+    }
+
+    /// Gives the textual values the return types of `method_name`, so that
+    /// they can be compared with decoded replies.
+    fn annotate_reply(&self, method_name: &str, args: IDLArgs) -> IDLArgs {
+        match self.method(method_name) {
+            Some(function) => args
+                .clone()
+                .annotate_types(true, &self.env, &function.rets)
+                .unwrap_or(args),
+            None => args,
+        }
+    }
+}
+
+pub(crate) fn parse_args(text: &str) -> Result<IDLArgs, String> {
+    text.parse::<IDLArgs>()
+        .map_err(|err| format!("Failed to parse Candid value {} with {}", text, err))
+}
+
+/// Encodes textual Candid arguments. The arguments are typed according to the
+/// interface if it declares `method`, otherwise their types are inferred from
+/// the values.
+pub(crate) fn encode_args(
+    text: &str,
+    interface: Option<&CandidInterface>,
+    method: CandidMethod<'_>,
+) -> Result<Vec<u8>, String> {
+    let args = parse_args(text)?;
+    match interface.and_then(|interface| interface.arg_types(method).map(|t| (interface, t))) {
+        Some((interface, types)) => args.to_bytes_with_types(&interface.env, types),
+        None => args.to_bytes(),
+    }
+    .map_err(|err| format!("Failed to encode Candid value {} with {}", text, err))
+}
+
+/// Checks whether `reply` encodes the same values as `expected`, after typing
+/// both according to the return types of `method_name` if the interface
+/// declares it.
+pub(crate) fn reply_matches(
+    expected: &IDLArgs,
+    reply: &[u8],
+    interface: Option<&CandidInterface>,
+    method_name: Option<&str>,
+) -> bool {
+    let (actual, expected) = match (interface, method_name) {
+        (Some(interface), Some(method_name)) => (
+            interface.decode_reply(method_name, reply),
+            interface.annotate_reply(method_name, expected.clone()),
+        ),
+        _ => (None, expected.clone()),
+    };
+    match actual.or_else(|| IDLArgs::from_bytes(reply).ok()) {
+        Some(actual) => actual.to_string() == expected.to_string(),
+        None => false,
+    }
+}
@@ -1,52 +1,53 @@
 //! Standalone interface for testing application canisters.
 
+use crate::candid_interface::{reply_matches, CandidInterface};
 use crate::message::{msg_stream_from_file, Message};
 use hex::encode;
-use ic_config::{subnet_config::SubnetConfigs, Config};
+use ic_config::{
+    state_manager::Config as StateManagerConfig, subnet_config::SubnetConfigs, Config,
+};
 use ic_cycles_account_manager::CyclesAccountManager;
 use ic_execution_environment::setup_execution;
 use ic_interfaces::{
-    execution_environment::IngressHistoryReader, messaging::MessageRouting,
+    execution_environment::{IngressHistoryReader, QueryHandler},
+    messaging::MessageRouting,
     state_manager::StateReader,
 };
 use ic_messaging::MessageRoutingImpl;
 use ic_metrics::MetricsRegistry;
 use ic_metrics_exporter::MetricsRuntimeImpl;
-use ic_protobuf::registry::{
-    provisional_whitelist::v1::ProvisionalWhitelist as PbProvisionalWhitelist,
-    routing_table::v1::RoutingTable as PbRoutingTable,
-};
-use ic_protobuf::types::v1::PrincipalId as PrincipalIdIdProto;
-use ic_protobuf::types::v1::SubnetId as SubnetIdProto;
 use ic_registry_client::client::RegistryClientImpl;
-use ic_registry_common::proto_registry_data_provider::ProtoRegistryDataProvider;
-use ic_registry_keys::{
-    make_provisional_whitelist_record_key, make_routing_table_record_key, ROOT_SUBNET_ID_KEY,
-};
-use ic_registry_provisional_whitelist::ProvisionalWhitelist;
-use ic_registry_routing_table::{routing_table_insert_subnet, RoutingTable};
+use ic_registry_routing_table::RoutingTable;
 use ic_registry_subnet_type::SubnetType;
+use ic_replicated_state::ReplicatedState;
 use ic_state_manager::StateManagerImpl;
 use ic_test_utilities::{
+    certified_stream_store::stream_slice_from,
     consensus::fake::FakeVerifier,
     mock_time,
-    registry::{add_subnet_record, insert_initial_dkg_transcript, SubnetRecordBuilder},
+    registry::{multi_subnet_registry, routing_table_for_subnets},
+    state_manager::certify_states_with_fake_signature,
 };
 use ic_types::{
-    batch::{Batch, BatchPayload, IngressPayload, SelfValidatingPayload, XNetPayload},
+    batch::{
+        Batch, BatchPayload, CanisterHttpPayload, IngressPayload, SelfValidatingPayload,
+        XNetPayload,
+    },
+    ic00,
     ingress::{IngressStatus, WasmResult},
     messages::{MessageId, SignedIngress},
-    replica_config::ReplicaConfig,
-    user_error::UserError,
+    time::Time,
+    user_error::{RejectCode, UserError},
     CanisterId, NodeId, PrincipalId, Randomness, RegistryVersion, SubnetId,
 };
 use slog::{Drain, Logger};
-use std::collections::BTreeMap;
+use std::cell::Cell;
 use std::fs::OpenOptions;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::{thread::sleep, time::Duration};
 
+mod candid_interface;
 mod message;
 
 // drun will panic if it takes more than this many batches
@@ -60,22 +61,50 @@ pub struct DrunOptions {
     pub cfg: Config,
     pub extra_batches: u64,
     pub log_file: Option<PathBuf>,
+    /// A `.did` file describing the interface of the canisters, used to type
+    /// Candid arguments and to decode replies.
+    pub did_file: Option<PathBuf>,
+    /// The number of subnets to run in-process. Canisters on different
+    /// subnets communicate through XNet streams.
+    pub subnets: usize,
 }
 
-/// Deliver a single message to the Message Routing layer
+/// The components that drun drives for every subnet it runs.
+struct Subnet {
+    subnet_id: SubnetId,
+    state_manager: Arc<StateManagerImpl>,
+    message_routing: MessageRoutingImpl,
+    ingress_hist_reader: Box<dyn IngressHistoryReader>,
+    query_handler: Arc<dyn QueryHandler<State = ReplicatedState>>,
+    /// The time of the next batch, only moved by `advance_time`.
+    time: Cell<Time>,
+}
+
+/// The interface and the method to decode a reply with, if any.
+type ReplyDecoder<'a> = Option<(&'a CandidInterface, &'a str)>;
+
+/// Deliver a single message to the Message Routing layer of the subnet at
+/// index `target` and return its result.
 fn deliver_message(
     msg: SignedIngress,
-    message_routing: &dyn MessageRouting,
-    ingress_hist_reader: &dyn IngressHistoryReader,
+    subnets: &[Subnet],
+    target: usize,
     extra_batches: u64,
-) {
+    interface: Option<&CandidInterface>,
+) -> Result<WasmResult, UserError> {
     let message_id = msg.id();
+    let method_name = msg.method_name();
 
-    let _ = execute_ingress_message(message_routing, msg, &message_id, ingress_hist_reader);
+    let _ = execute_ingress_message(subnets, target, msg, &message_id);
     // print result after waiting, to not interleave the result
     // with debug.print messages from subsequent calls. revise after DFN-1269.
-    wait_extra_batches(message_routing, extra_batches);
-    print_ingress_result(&message_id, ingress_hist_reader);
+    wait_extra_batches(subnets, extra_batches);
+    print_subnet_prefix(subnets, target);
+    print_ingress_result(
+        &message_id,
+        subnets[target].ingress_hist_reader.as_ref(),
+        interface.map(|interface| (interface, method_name.as_str())),
+    )
 }
 
 fn setup_logger(log_file: PathBuf) -> Logger {
@@ -96,93 +125,253 @@ fn setup_logger(log_file: PathBuf) -> Logger {
     slog::Logger::root(drain, slog::o!())
 }
 
-fn get_registry(
-    metrics_registry: &MetricsRegistry,
-    subnet_id: SubnetId,
-    root_subnet_id: SubnetId,
-    subnet_type: SubnetType,
-    node_ids: &[NodeId],
-) -> Arc<RegistryClientImpl> {
-    let registry_version = RegistryVersion::from(1);
-    let data_provider = Arc::new(ProtoRegistryDataProvider::new());
-    let root_subnet_id_proto = SubnetIdProto {
-        principal_id: Some(PrincipalIdIdProto {
-            raw: root_subnet_id.get_ref().to_vec(),
-        }),
-    };
-    data_provider
-        .add(
-            ROOT_SUBNET_ID_KEY,
-            registry_version,
-            Some(root_subnet_id_proto),
-        )
-        .unwrap();
-    let mut routing_table = RoutingTable::new(BTreeMap::new());
-    routing_table_insert_subnet(&mut routing_table, subnet_id).unwrap();
-    let pb_routing_table = PbRoutingTable::from(routing_table);
-    data_provider
-        .add(
-            &make_routing_table_record_key(),
-            registry_version,
-            Some(pb_routing_table),
-        )
-        .unwrap();
-    let pb_whitelist = PbProvisionalWhitelist::from(ProvisionalWhitelist::All);
-    data_provider
-        .add(
-            &make_provisional_whitelist_record_key(),
-            registry_version,
-            Some(pb_whitelist),
-        )
-        .unwrap();
-
-    // Set subnetwork list(needed for filling network_topology.nns_subnet_id)
-    let mut record = SubnetRecordBuilder::from(node_ids).build();
-    record.subnet_type = i32::from(subnet_type);
-
-    insert_initial_dkg_transcript(registry_version.get(), subnet_id, &record, &data_provider);
-    add_subnet_record(&data_provider, registry_version.get(), subnet_id, record);
-
-    let registry_client = Arc::new(RegistryClientImpl::new(
-        data_provider,
-        Some(metrics_registry),
-    ));
-    registry_client.fetch_and_start_polling().unwrap();
-    registry_client
-}
-
 pub fn run_drun(uo: DrunOptions) -> Result<(), String> {
     let DrunOptions {
         msg_filename,
         cfg,
         extra_batches,
         log_file,
+        subnets: num_subnets,
+        did_file,
     } = uo;
-    // Hardcoded magic values to create a ReplicaConfig that parses.
+    if num_subnets == 0 {
+        return Err("drun needs at least one subnet.".to_string());
+    }
+    // Hardcoded magic values to create subnets that parse. The root subnet
+    // is not one of the subnets run by drun.
     let subnet_type = SubnetType::System;
-    let subnet_config = SubnetConfigs::default().own_subnet_config(subnet_type);
-    let subnet_id = SubnetId::from(PrincipalId::new_subnet_test_id(0));
-    let root_subnet_id = SubnetId::from(PrincipalId::new_subnet_test_id(1));
-    let replica_config = ReplicaConfig {
-        node_id: NodeId::from(PrincipalId::new_node_test_id(27)),
-        subnet_id,
-    };
+    let subnet_ids: Vec<_> = (0..num_subnets as u64)
+        .map(|idx| SubnetId::from(PrincipalId::new_subnet_test_id(idx)))
+        .collect();
+    let root_subnet_id = SubnetId::from(PrincipalId::new_subnet_test_id(num_subnets as u64));
+    let node_ids: Vec<_> = (0..num_subnets as u64)
+        .map(|idx| NodeId::from(PrincipalId::new_node_test_id(27 + idx)))
+        .collect();
 
-    let mut msg_stream = msg_stream_from_file(&msg_filename)?;
+    let interface = did_file
+        .map(|did_file| CandidInterface::from_file(&did_file))
+        .transpose()?;
+    let mut msg_stream = msg_stream_from_file(&msg_filename, interface.as_ref())?;
     let log = match log_file {
         Some(log_file) => setup_logger(log_file),
         None => slog::Logger::root(slog::Discard, slog::o!()),
     };
 
     let metrics_registry = MetricsRegistry::global();
-    let registry = get_registry(
+    let routing_table = routing_table_for_subnets(&subnet_ids);
+    let registry = multi_subnet_registry(
         &metrics_registry,
-        subnet_id,
+        &subnet_ids
+            .iter()
+            .copied()
+            .zip(node_ids.iter().copied())
+            .collect::<Vec<_>>(),
         root_subnet_id,
         subnet_type,
-        &[replica_config.node_id],
     );
 
+    let subnets: Vec<_> = subnet_ids
+        .iter()
+        .enumerate()
+        .map(|(idx, subnet_id)| {
+            // Metrics can only be registered once per registry, so only the
+            // first subnet reports to the exported global registry.
+            let metrics_registry = if idx == 0 {
+                metrics_registry.clone()
+            } else {
+                MetricsRegistry::new()
+            };
+            setup_subnet(
+                *subnet_id,
+                subnet_type,
+                StateManagerConfig::new(
+                    cfg.state_manager
+                        .state_root()
+                        .join(format!("subnet_{}", idx)),
+                ),
+                &cfg,
+                &metrics_registry,
+                &log,
+                Arc::clone(&registry),
+            )
+        })
+        .collect();
+
+    let _metrics_runtime = MetricsRuntimeImpl::new_insecure(
+        tokio::runtime::Handle::current(),
+        cfg.metrics,
+        metrics_registry.clone(),
+        &log,
+    );
+
+    // The result of the last call, which assertions are checked against, and
+    // the canister method it called, if any.
+    let mut last_result = None;
+    let mut last_method = None;
+    let mut failed_assertions = 0;
+    msg_stream.try_for_each(|parse_result| {
+        parse_result.and_then(|msg| {
+            let target = target_subnet(&msg, &routing_table, &subnet_ids)?;
+            match msg {
+                Message::Install(msg, _)
+                | Message::Management(msg, _)
+                | Message::Create(msg, _) => {
+                    last_result = Some(deliver_message(
+                        msg,
+                        &subnets,
+                        target.unwrap(),
+                        extra_batches,
+                        None,
+                    ));
+                    last_method = None;
+                }
+
+                Message::Query(q) => {
+                    let target = target.unwrap();
+                    let subnet = &subnets[target];
+                    let method_name = q.method_name.clone();
+                    // NOTE: Data certificates aren't supported in drun yet.
+                    // To support them, we'd need to do something similar to
+                    // http_handler::get_latest_certified_state_and_data_certificate
+                    let result = subnet.query_handler.query(
+                        q,
+                        subnet.state_manager.get_latest_state().take(),
+                        Vec::new(),
+                    );
+                    print_subnet_prefix(&subnets, target);
+                    print_query_result(
+                        &result,
+                        interface
+                            .as_ref()
+                            .map(|interface| (interface, method_name.as_str())),
+                    );
+                    last_result = Some(result);
+                    last_method = Some(method_name);
+                }
+
+                Message::Ingress(msg) => {
+                    last_method = Some(msg.method_name());
+                    last_result = Some(deliver_message(
+                        msg,
+                        &subnets,
+                        target.unwrap(),
+                        extra_batches,
+                        interface.as_ref(),
+                    ));
+                }
+                Message::AssertReply(expected) => {
+                    let replied_as_expected = matches!(
+                        &last_result,
+                        Some(Ok(WasmResult::Reply(payload))) if *payload == expected
+                    );
+                    if !replied_as_expected {
+                        failed_assertions += 1;
+                        println!(
+                            "Assertion failed: expected Reply: 0x{}, got {}",
+                            encode(expected),
+                            describe_result(&last_result)
+                        );
+                    }
+                }
+                Message::AssertCandidReply(expected) => {
+                    let replied_as_expected = matches!(
+                        &last_result,
+                        Some(Ok(WasmResult::Reply(payload))) if reply_matches(
+                            &expected,
+                            payload,
+                            interface.as_ref(),
+                            last_method.as_deref(),
+                        )
+                    );
+                    if !replied_as_expected {
+                        failed_assertions += 1;
+                        println!(
+                            "Assertion failed: expected Reply: {}, got {}",
+                            expected,
+                            describe_result(&last_result)
+                        );
+                    }
+                }
+                Message::AssertReject(expected) => {
+                    if reject_code(&last_result) != Some(expected) {
+                        failed_assertions += 1;
+                        println!(
+                            "Assertion failed: expected reject code {}, got {}",
+                            expected as u64,
+                            describe_result(&last_result)
+                        );
+                    }
+                }
+                Message::AdvanceTime(duration) => {
+                    for subnet in subnets.iter() {
+                        subnet.time.set(subnet.time.get() + duration);
+                    }
+                }
+            }
+            Ok(())
+        })
+    })?;
+
+    if failed_assertions > 0 {
+        return Err(format!("{} assertion(s) failed.", failed_assertions));
+    }
+    Ok(())
+}
+
+/// Returns the index of the subnet that the given message is delivered to, or
+/// `None` for directives that are not delivered to any subnet.
+///
+/// Code installation and canister management messages are addressed to the
+/// management canister, but are delivered to the subnet that hosts the
+/// canister they target. Canisters are created on the subnet chosen in the
+/// directive. Any other message to the management canister is delivered to
+/// subnet 0.
+fn target_subnet(
+    msg: &Message,
+    routing_table: &RoutingTable,
+    subnet_ids: &[SubnetId],
+) -> Result<Option<usize>, String> {
+    let subnet_of = |canister_id: CanisterId| -> Result<usize, String> {
+        if canister_id == ic00::IC_00 {
+            return Ok(0);
+        }
+        routing_table
+            .route(canister_id.get())
+            .and_then(|subnet_id| subnet_ids.iter().position(|id| *id == subnet_id))
+            .ok_or_else(|| format!("Canister {} is not hosted on any subnet.", canister_id))
+    };
+    match msg {
+        Message::Install(_, canister_id) | Message::Management(_, canister_id) => {
+            subnet_of(*canister_id).map(Some)
+        }
+        Message::Query(q) => subnet_of(q.receiver).map(Some),
+        Message::Ingress(msg) => subnet_of(msg.canister_id()).map(Some),
+        Message::Create(_, target) if *target >= subnet_ids.len() => Err(format!(
+            "Cannot create a canister on subnet {}, drun runs {} subnet(s).",
+            target,
+            subnet_ids.len()
+        )),
+        Message::Create(_, target) => Ok(Some(*target)),
+        Message::AssertReply(_)
+        | Message::AssertCandidReply(_)
+        | Message::AssertReject(_)
+        | Message::AdvanceTime(_) => Ok(None),
+    }
+}
+
+/// Sets up the state manager, execution environment and message routing of a
+/// single subnet.
+fn setup_subnet(
+    subnet_id: SubnetId,
+    subnet_type: SubnetType,
+    state_manager_config: StateManagerConfig,
+    cfg: &Config,
+    metrics_registry: &MetricsRegistry,
+    log: &Logger,
+    registry: Arc<RegistryClientImpl>,
+) -> Subnet {
+    let subnet_config = SubnetConfigs::default().own_subnet_config(subnet_type);
+
     let cycles_account_manager = Arc::new(CyclesAccountManager::new(
         subnet_config.scheduler_config.max_instructions_per_message,
         cfg.hypervisor.max_cycles_per_canister,
@@ -193,157 +382,217 @@ pub fn run_drun(uo: DrunOptions) -> Result<(), String> {
 
     let state_manager = Arc::new(StateManagerImpl::new(
         Arc::new(FakeVerifier::new()),
-        replica_config.subnet_id,
+        subnet_id,
         subnet_type,
         log.clone().into(),
-        &metrics_registry,
-        &cfg.state_manager,
+        metrics_registry,
+        &state_manager_config,
         ic_types::malicious_flags::MaliciousFlags::default(),
     ));
     let (_, ingress_history_writer, ingress_hist_reader, query_handler, _, scheduler) =
         setup_execution(
             log.clone().into(),
-            &metrics_registry,
-            replica_config.subnet_id,
+            metrics_registry,
+            subnet_id,
             subnet_type,
             subnet_config.scheduler_config,
             cfg.hypervisor.clone(),
             Arc::clone(&cycles_account_manager),
             Arc::clone(&state_manager) as Arc<_>,
         );
-    let _metrics_runtime = MetricsRuntimeImpl::new_insecure(
-        tokio::runtime::Handle::current(),
-        cfg.metrics,
-        metrics_registry.clone(),
-        &log,
-    );
 
     let message_routing = MessageRoutingImpl::new(
         Arc::clone(&state_manager) as _,
         Arc::clone(&state_manager) as _,
         Arc::clone(&ingress_history_writer) as _,
         scheduler,
-        cfg.hypervisor,
+        cfg.hypervisor.clone(),
         cycles_account_manager,
-        replica_config.subnet_id,
-        &metrics_registry,
+        subnet_id,
+        metrics_registry,
         log.clone().into(),
-        Arc::clone(&registry) as _,
+        registry as _,
     );
 
-    msg_stream.try_for_each(|parse_result| {
-        parse_result.map(|msg| match msg {
-            Message::Install(msg) => {
-                deliver_message(
-                    msg,
-                    &message_routing,
-                    ingress_hist_reader.as_ref(),
-                    extra_batches,
-                );
-            }
-
-            Message::Query(q) => {
-                // NOTE: Data certificates aren't supported in drun yet.
-                // To support them, we'd need to do something similar to
-                // http_handler::get_latest_certified_state_and_data_certificate
-                print_query_result(query_handler.query(
-                    q,
-                    state_manager.get_latest_state().take(),
-                    Vec::new(),
-                ));
-            }
+    Subnet {
+        subnet_id,
+        state_manager,
+        message_routing,
+        ingress_hist_reader,
+        query_handler,
+        time: Cell::new(mock_time()),
+    }
+}
 
-            Message::Ingress(msg) => {
-                deliver_message(
-                    msg,
-                    &message_routing,
-                    ingress_hist_reader.as_ref(),
-                    extra_batches,
-                );
-            }
-            Message::Create(msg) => {
-                deliver_message(
-                    msg,
-                    &message_routing,
-                    ingress_hist_reader.as_ref(),
-                    extra_batches,
-                );
-            }
-        })
-    })
+/// Results are only attributed to subnets if drun runs more than one.
+fn print_subnet_prefix(subnets: &[Subnet], idx: usize) {
+    if subnets.len() > 1 {
+        print!("subnet {}: ", idx);
+    }
 }
 
-fn print_query_result(res: Result<WasmResult, UserError>) {
+fn print_query_result(res: &Result<WasmResult, UserError>, decoder: ReplyDecoder<'_>) {
     match res {
         Ok(payload) => {
             print!("Ok: ");
-            print_wasm_result(payload);
+            print_wasm_result(payload, decoder);
         }
         Err(e) => println!("Err: {}", e),
     }
 }
 
-fn print_ingress_result(message_id: &MessageId, ingress_hist_reader: &dyn IngressHistoryReader) {
+fn print_ingress_result(
+    message_id: &MessageId,
+    ingress_hist_reader: &dyn IngressHistoryReader,
+    decoder: ReplyDecoder<'_>,
+) -> Result<WasmResult, UserError> {
     let status = (ingress_hist_reader.get_latest_status())(message_id);
     print!("ingress ");
     match status {
         IngressStatus::Completed { result, .. } => {
             print!("Completed: ");
-            print_wasm_result(result)
+            print_wasm_result(&result, decoder);
+            Ok(result)
+        }
+        IngressStatus::Failed { error, .. } => {
+            println!("Err: {}", error);
+            Err(error)
         }
-        IngressStatus::Failed { error, .. } => println!("Err: {}", error),
         _ => panic!("Ingress message has not finished processing."),
-    };
+    }
 }
 
-fn print_wasm_result(wasm_result: WasmResult) {
+/// Prints a reply as Candid values if it can be decoded with `decoder`, and
+/// as hex otherwise.
+fn print_wasm_result(wasm_result: &WasmResult, decoder: ReplyDecoder<'_>) {
     match wasm_result {
-        WasmResult::Reply(v) => println!("Reply: 0x{}", encode(v)),
+        WasmResult::Reply(v) => match decoder
+            .and_then(|(interface, method_name)| interface.decode_reply(method_name, v))
+        {
+            Some(args) => println!("Reply: {}", args),
+            None => println!("Reply: 0x{}", encode(v)),
+        },
         WasmResult::Reject(e) => println!("Reject: {}", e),
     }
 }
 
-fn build_batch(message_routing: &dyn MessageRouting, msgs: Vec<SignedIngress>) -> Batch {
+/// Returns the reject code of a call result, if the call was rejected.
+fn reject_code(result: &Option<Result<WasmResult, UserError>>) -> Option<RejectCode> {
+    match result {
+        Some(Ok(WasmResult::Reply(_))) | None => None,
+        Some(Ok(WasmResult::Reject(_))) => Some(RejectCode::CanisterReject),
+        Some(Err(error)) => Some(error.reject_code()),
+    }
+}
+
+fn describe_result(result: &Option<Result<WasmResult, UserError>>) -> String {
+    match result {
+        None => "no result".to_string(),
+        Some(Ok(WasmResult::Reply(v))) => format!("Reply: 0x{}", encode(v)),
+        Some(Ok(WasmResult::Reject(e))) => format!("Reject: {}", e),
+        Some(Err(e)) => format!("Err: {} (reject code {})", e, e.reject_code() as u64),
+    }
+}
+
+fn build_batch(
+    message_routing: &dyn MessageRouting,
+    msgs: Vec<SignedIngress>,
+    xnet: XNetPayload,
+    time: Time,
+) -> Batch {
     Batch {
         batch_number: message_routing.expected_batch_height(),
         requires_full_state_hash: !msgs.is_empty(),
         payload: BatchPayload {
             ingress: IngressPayload::from(msgs),
-            xnet: XNetPayload {
-                stream_slices: Default::default(),
-            },
+            xnet,
             self_validating: SelfValidatingPayload::default(),
+            canister_http: CanisterHttpPayload::default(),
         },
         randomness: Randomness::from([0; 32]),
         registry_version: RegistryVersion::from(1),
-        time: mock_time(),
+        time,
         consensus_responses: vec![],
     }
 }
+
+/// Collects the slices of the streams that the other subnets have certified
+/// for the subnet at index `idx`, starting at the first message it has not
+/// inducted yet.
+fn build_xnet_payload(subnets: &[Subnet], idx: usize) -> XNetPayload {
+    let own_subnet = &subnets[idx];
+    let state = own_subnet.state_manager.get_latest_state().take();
+    let stream_slices = subnets
+        .iter()
+        .filter(|remote| remote.subnet_id != own_subnet.subnet_id)
+        .filter_map(|remote| {
+            stream_slice_from(
+                remote.state_manager.as_ref(),
+                remote.subnet_id,
+                own_subnet.subnet_id,
+                &state,
+            )
+            .map(|slice| (remote.subnet_id, slice))
+        })
+        .collect();
+    XNetPayload { stream_slices }
+}
+
+/// Delivers a batch with the given messages to the subnet at index `idx` and
+/// waits until the resulting state has been committed and certified. Returns
+/// `false` if Message Routing did not accept the batch.
+fn deliver_batch(subnets: &[Subnet], idx: usize, msgs: Vec<SignedIngress>) -> bool {
+    let subnet = &subnets[idx];
+    let batch = build_batch(
+        &subnet.message_routing,
+        msgs,
+        build_xnet_payload(subnets, idx),
+        subnet.time.get(),
+    );
+    let height = batch.batch_number;
+    if subnet.message_routing.deliver_batch(batch).is_err() {
+        sleep(WAIT_PER_BATCH);
+        return false;
+    }
+    // The next XNet payload for this subnet must start where the streams
+    // inducted by this batch end, so wait for its state.
+    while subnet.state_manager.latest_state_height() < height {
+        sleep(WAIT_PER_BATCH);
+    }
+    certify_states_with_fake_signature(subnet.state_manager.as_ref());
+    true
+}
+
 /// Block till the given ingress message has finished executing and
 /// then return the result.  To ensure that this function does not
 /// block forever (in case of bugs), this function will panic if the
 /// process is not finished in some amount of time.
 fn execute_ingress_message(
-    message_routing: &dyn MessageRouting,
+    subnets: &[Subnet],
+    target: usize,
     msg: SignedIngress,
     msg_id: &MessageId,
-    ingress_history: &dyn IngressHistoryReader,
 ) -> Result<WasmResult, UserError> {
-    let mut batch = build_batch(message_routing, vec![msg]);
+    let ingress_history = subnets[target].ingress_hist_reader.as_ref();
+    let mut msgs = vec![msg];
     for _ in 0..MAX_BATCHES_UNTIL_RESPONSE {
         // In the first batch we try to send the ingress message itself. If it fails, we
         // repeat with the same batch.
         //
         // After the batch with a message is delivered, we keep submitting work to
-        // message routing in the form of empty batches till the ingress message has
-        // finished executing. This is necessary to get message routing to process
-        // potential inter-canister messages that the ingress message may have
-        // triggered.
-        if message_routing.deliver_batch(batch.clone()).is_ok() {
-            batch = build_batch(message_routing, vec![])
+        // message routing of all subnets in the form of empty batches till the ingress
+        // message has finished executing. This is necessary to get message routing to
+        // process potential inter-canister messages that the ingress message may have
+        // triggered, also across subnets.
+        for idx in 0..subnets.len() {
+            if idx == target {
+                if deliver_batch(subnets, idx, msgs.clone()) {
+                    msgs.clear();
+                }
+            } else {
+                deliver_batch(subnets, idx, vec![]);
+            }
         }
-        sleep(WAIT_PER_BATCH);
 
         let ingress_result = (ingress_history.get_latest_status())(msg_id);
         match ingress_result {
@@ -380,15 +629,120 @@ fn execute_ingress_message(
 ///
 /// This is a temporary measure until DFN-1269 is resolved. In that ticket, we
 /// will actually try to wait until all messages have been executed.
-fn wait_extra_batches(message_routing: &dyn MessageRouting, extra_batches: u64) {
+fn wait_extra_batches(subnets: &[Subnet], extra_batches: u64) {
     for _ in 0..extra_batches {
-        loop {
-            let batch = build_batch(message_routing, vec![]);
-            let ok = message_routing.deliver_batch(batch).is_ok();
-            sleep(WAIT_PER_BATCH);
-            if ok {
-                break;
-            };
+        for idx in 0..subnets.len() {
+            while !deliver_batch(subnets, idx, vec![]) {}
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_test_utilities::types::messages::SignedIngressBuilder;
+    use ic_types::{messages::UserQuery, UserId};
+
+    fn subnet_ids() -> Vec<SubnetId> {
+        (0..2)
+            .map(|idx| SubnetId::from(PrincipalId::new_subnet_test_id(idx)))
+            .collect()
+    }
+
+    fn management_call() -> SignedIngress {
+        SignedIngressBuilder::new().canister_id(ic00::IC_00).build()
+    }
+
+    #[test]
+    fn test_target_subnet_routes_management_messages_to_their_canister() {
+        let subnet_ids = subnet_ids();
+        let routing_table = routing_table_for_subnets(&subnet_ids);
+        let on_subnet_1 = CanisterId::from(1 << 20);
+
+        assert_eq!(
+            target_subnet(
+                &Message::Install(management_call(), on_subnet_1),
+                &routing_table,
+                &subnet_ids
+            ),
+            Ok(Some(1))
+        );
+        assert_eq!(
+            target_subnet(
+                &Message::Management(management_call(), on_subnet_1),
+                &routing_table,
+                &subnet_ids
+            ),
+            Ok(Some(1))
+        );
+        assert_eq!(
+            target_subnet(
+                &Message::Create(management_call(), 1),
+                &routing_table,
+                &subnet_ids
+            ),
+            Ok(Some(1))
+        );
+        assert!(target_subnet(
+            &Message::Create(management_call(), 2),
+            &routing_table,
+            &subnet_ids
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_target_subnet_routes_other_management_canister_messages_to_subnet_0() {
+        let subnet_ids = subnet_ids();
+        let routing_table = routing_table_for_subnets(&subnet_ids);
+
+        assert_eq!(
+            target_subnet(
+                &Message::Ingress(management_call()),
+                &routing_table,
+                &subnet_ids
+            ),
+            Ok(Some(0))
+        );
+        let query = UserQuery {
+            source: UserId::from(PrincipalId::new_anonymous()),
+            receiver: ic00::IC_00,
+            method_name: "raw_rand".to_string(),
+            method_payload: vec![],
+            ingress_expiry: 0,
+            nonce: None,
+        };
+        assert_eq!(
+            target_subnet(&Message::Query(query), &routing_table, &subnet_ids),
+            Ok(Some(0))
+        );
+    }
+
+    #[test]
+    fn test_target_subnet_routes_canister_messages_to_the_hosting_subnet() {
+        let subnet_ids = subnet_ids();
+        let routing_table = routing_table_for_subnets(&subnet_ids);
+
+        for (canister_id, subnet) in &[(CanisterId::from(0), 0), (CanisterId::from(1 << 20), 1)] {
+            let msg = SignedIngressBuilder::new()
+                .canister_id(*canister_id)
+                .build();
+            assert_eq!(
+                target_subnet(&Message::Ingress(msg), &routing_table, &subnet_ids),
+                Ok(Some(*subnet))
+            );
+        }
+        let unhosted = SignedIngressBuilder::new()
+            .canister_id(CanisterId::from(2 << 20))
+            .build();
+        assert!(target_subnet(&Message::Ingress(unhosted), &routing_table, &subnet_ids).is_err());
+        assert_eq!(
+            target_subnet(
+                &Message::AdvanceTime(Duration::from_secs(1)),
+                &routing_table,
+                &subnet_ids
+            ),
+            Ok(None)
+        );
+    }
+}
@@ -5,10 +5,13 @@ use std::path::PathBuf;
 
 const DEFAULT_CONFIG_FILE: &str = "ic.toml";
 const DEFAULT_EXTRA_BATCHES: u64 = 0;
+const DEFAULT_SUBNETS: usize = 1;
 const ARG_CONF: &str = "config";
 const ARG_LOG_FILE: &str = "log-file";
 const ARG_MESSAGES: &str = "messages";
 const ARG_EXTRA_BATCHES: &str = "extra-batches";
+const ARG_SUBNETS: &str = "subnets";
+const ARG_DID_FILE: &str = "did";
 
 #[tokio::main]
 async fn main() -> Result<(), String> {
@@ -24,6 +27,7 @@ async fn main() -> Result<(), String> {
         });
 
         let log_file = matches.value_of(ARG_LOG_FILE).map(PathBuf::from);
+        let did_file = matches.value_of(ARG_DID_FILE).map(PathBuf::from);
 
         let extra_batches = matches
             .value_of(ARG_EXTRA_BATCHES)
@@ -35,11 +39,23 @@ async fn main() -> Result<(), String> {
             })
             .unwrap_or(DEFAULT_EXTRA_BATCHES);
 
+        let subnets = matches
+            .value_of(ARG_SUBNETS)
+            .map(|arg| {
+                arg.parse().unwrap_or_else(|err| {
+                    eprintln!("Failed to parse ARG_SUBNETS\n  {}", err);
+                    std::process::exit(1);
+                })
+            })
+            .unwrap_or(DEFAULT_SUBNETS);
+
         let uo = DrunOptions {
             msg_filename: matches.value_of(ARG_MESSAGES).unwrap().to_string(),
             cfg,
             extra_batches,
             log_file,
+            subnets,
+            did_file,
         };
         run_drun(uo)
     })
@@ -58,6 +74,16 @@ fn get_arg_matches() -> ArgMatches<'static> {
                 ))
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name(ARG_SUBNETS)
+                .long(ARG_SUBNETS)
+                .value_name("INT")
+                .help(&format!(
+                    "Number of subnets to run, connected via XNet streams (default: {}).",
+                    DEFAULT_SUBNETS
+                ))
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name(ARG_CONF)
                 .short("c")
@@ -75,6 +101,13 @@ fn get_arg_matches() -> ArgMatches<'static> {
                 .value_name("Query/Ingress Messages")
                 .help("Text file containing one message per line."),
         )
+        .arg(
+            Arg::with_name(ARG_DID_FILE)
+                .long(ARG_DID_FILE)
+                .value_name("did_file")
+                .help("Candid interface of the canisters, used to type arguments and decode replies (default: None).")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name(ARG_LOG_FILE)
                 .long(ARG_LOG_FILE)
@@ -1,11 +1,15 @@
 use super::CanisterId;
 
+use crate::candid_interface::{encode_args, parse_args, CandidInterface, CandidMethod};
+use candid::IDLArgs;
 use hex::decode;
+use ic_test_utilities::types::messages::SignedIngressBuilder;
 use ic_types::{
     ic00,
-    ic00::Payload,
+    ic00::{CanisterSettingsArgs, Payload},
     messages::{CanisterInstallMode, SignedIngress, UserQuery},
     time::current_time_and_expiry_time,
+    user_error::RejectCode,
     PrincipalId, UserId,
 };
 
@@ -14,16 +18,32 @@ use std::{
     fmt,
     fs::File,
     io::{self, Read},
-    str::Chars,
+    str::{Chars, FromStr},
     string::FromUtf8Error,
+    time::Duration,
 };
 
 #[derive(Debug, PartialEq)]
 pub(crate) enum Message {
     Ingress(SignedIngress),
     Query(UserQuery),
-    Install(SignedIngress),
-    Create(SignedIngress),
+    /// An `install_code` call together with the canister it installs to.
+    Install(SignedIngress, CanisterId),
+    /// A canister creation together with the index of the subnet to create
+    /// the canister on.
+    Create(SignedIngress, usize),
+    /// Any other call to the management canister together with the canister
+    /// it targets.
+    Management(SignedIngress, CanisterId),
+    /// Checks that the previous message was replied to with this payload.
+    AssertReply(Vec<u8>),
+    /// Checks that the previous message was replied to with these Candid
+    /// values.
+    AssertCandidReply(IDLArgs),
+    /// Checks that the previous message was rejected with this reject code.
+    AssertReject(RejectCode),
+    /// Moves the time of all subsequent batches forward.
+    AdvanceTime(Duration),
 }
 
 #[derive(Debug)]
@@ -124,9 +144,10 @@ impl<R: Read> Iterator for LineIterator<R> {
     }
 }
 
-pub(crate) fn msg_stream_from_file(
+pub(crate) fn msg_stream_from_file<'a>(
     filename: &str,
-) -> Result<impl Iterator<Item = Result<Message, String>>, String> {
+    interface: Option<&'a CandidInterface>,
+) -> Result<impl Iterator<Item = Result<Message, String>> + 'a, String> {
     let f = File::open(filename).map_err(|e| e.to_string())?;
     let line_iterator = LineIterator::new(f);
 
@@ -137,55 +158,126 @@ pub(crate) fn msg_stream_from_file(
             Ok(s) => !s.is_empty() && !s.starts_with('#'),
             _ => true,
         })
-        .map(|(i, line)| match line {
-            Ok(line) => {
-                parse_message(&line, i as u64).map_err(|e| format!("Line {}: {}", i + 1, e))
-            }
+        .map(move |(i, line)| match line {
+            Ok(line) => parse_message(&line, i as u64, interface)
+                .map_err(|e| format!("Line {}: {}", i + 1, e)),
             Err(e) => Err(format!("Error while reading line {}: {}", i, e)),
         }))
 }
 
-fn parse_message(s: &str, nonce: u64) -> Result<Message, String> {
+fn parse_message(
+    s: &str,
+    nonce: u64,
+    interface: Option<&CandidInterface>,
+) -> Result<Message, String> {
     let s = s.trim_end();
+    // A message can be sent on behalf of a chosen principal by prefixing it
+    // with `as <principal>`.
+    let tokens: Vec<&str> = s.splitn(3, char::is_whitespace).collect();
+    match &tokens[..] {
+        ["as", sender, message] => {
+            let sender = UserId::from(parse_principal_id(sender)?);
+            match parse_directive(message, nonce, Some(sender), interface)? {
+                Message::AssertReply(_)
+                | Message::AssertCandidReply(_)
+                | Message::AssertReject(_)
+                | Message::AdvanceTime(_) => Err(format!("Only calls can be sent as {}.", sender)),
+                msg => Ok(msg),
+            }
+        }
+        _ => parse_directive(s, nonce, None, interface),
+    }
+}
+
+fn parse_directive(
+    s: &str,
+    nonce: u64,
+    sender: Option<UserId>,
+    interface: Option<&CandidInterface>,
+) -> Result<Message, String> {
     let tokens: Vec<&str> = s.splitn(4, char::is_whitespace).collect();
 
     match &tokens[..] {
         [] => Err("Too few arguments.".to_string()),
         ["ingress", canister_id, method_name, payload] => {
-            use ic_test_utilities::types::messages::SignedIngressBuilder;
-
             let canister_id = parse_canister_id(canister_id)?;
             let method_name = validate_method_name(method_name)?;
-            let method_payload = parse_octet_string(payload)?;
+            let method_payload =
+                parse_payload(payload, interface, CandidMethod::Call(&method_name))?;
 
-            let signed_ingress = SignedIngressBuilder::new()
+            let signed_ingress = ingress_builder(nonce, sender)
                 // `source` should become a self-authenticating id according
                 // to https://sdk.dfinity.org/docs/interface-spec/index.html#id-classes
                 .canister_id(canister_id)
                 .method_name(method_name)
                 .method_payload(method_payload)
-                .nonce(nonce)
                 .build();
             Ok(Message::Ingress(signed_ingress))
         }
         ["query", canister_id, method_name, payload] => Ok(Message::Query(UserQuery {
-            source: UserId::from(PrincipalId::new_anonymous()),
+            source: sender.unwrap_or_else(|| UserId::from(PrincipalId::new_anonymous())),
             receiver: parse_canister_id(canister_id)?,
             method_name: validate_method_name(method_name)?,
-            method_payload: parse_octet_string(payload)?,
+            method_payload: parse_payload(payload, interface, CandidMethod::Call(*method_name))?,
             ingress_expiry: current_time_and_expiry_time().1.as_nanos_since_unix_epoch(),
             nonce: Some(nonce.to_le_bytes().to_vec()),
         })),
-        ["create"] => parse_create(nonce),
-        ["install", canister_id, wasm_file, payload] => {
-            parse_install(nonce, canister_id, payload, wasm_file, "install")
+        ["create"] => parse_create(nonce, sender, 0),
+        ["create", subnet] => parse_create(nonce, sender, parse_subnet_index(subnet)?),
+        ["install", canister_id, wasm_file, payload] => parse_install(
+            nonce,
+            sender,
+            interface,
+            canister_id,
+            payload,
+            wasm_file,
+            "install",
+        ),
+        ["reinstall", canister_id, wasm_file, payload] => parse_install(
+            nonce,
+            sender,
+            interface,
+            canister_id,
+            payload,
+            wasm_file,
+            "reinstall",
+        ),
+        ["upgrade", canister_id, wasm_file, payload] => parse_install(
+            nonce,
+            sender,
+            interface,
+            canister_id,
+            payload,
+            wasm_file,
+            "upgrade",
+        ),
+        ["stop", canister_id] => {
+            parse_canister_id_call(nonce, sender, canister_id, ic00::Method::StopCanister)
+        }
+        ["start", canister_id] => {
+            parse_canister_id_call(nonce, sender, canister_id, ic00::Method::StartCanister)
+        }
+        ["delete", canister_id] => {
+            parse_canister_id_call(nonce, sender, canister_id, ic00::Method::DeleteCanister)
         }
-        ["reinstall", canister_id, wasm_file, payload] => {
-            parse_install(nonce, canister_id, payload, wasm_file, "reinstall")
+        ["cycles", canister_id, amount] => parse_cycles(nonce, sender, canister_id, amount),
+        ["update_settings", canister_id, settings @ ..] if !settings.is_empty() => {
+            parse_update_settings(nonce, sender, canister_id, settings)
         }
-        ["upgrade", canister_id, wasm_file, payload] => {
-            parse_install(nonce, canister_id, payload, wasm_file, "upgrade")
+        ["assert_reply", ..] => {
+            // The payload may contain whitespace, so take the rest of the line.
+            let payload = s["assert_reply".len()..].trim_start();
+            if payload.starts_with('(') {
+                Ok(Message::AssertCandidReply(parse_args(payload)?))
+            } else {
+                Ok(Message::AssertReply(parse_octet_string(payload)?))
+            }
         }
+        ["assert_reject", code] => Ok(Message::AssertReject(parse_reject_code(code)?)),
+        ["advance_time", seconds] => seconds
+            .parse()
+            .map(|seconds| Message::AdvanceTime(Duration::from_secs(seconds)))
+            .map_err(|err| format!("Failed to parse number of seconds {} with {}", seconds, err)),
         _ => Err(format!(
             "Failed to parse line {}, don't have a pattern to match this with",
             s
@@ -193,45 +285,158 @@ fn parse_message(s: &str, nonce: u64) -> Result<Message, String> {
     }
 }
 
-fn parse_canister_id(canister_id: &str) -> Result<CanisterId, String> {
-    use std::str::FromStr;
-    match PrincipalId::from_str(canister_id) {
-        Ok(id) => match CanisterId::new(id) {
-            Ok(id) => Ok(id),
-            Err(err) => Err(format!(
-                "Failed to convert {} to canister id with {}",
-                canister_id, err
-            )),
-        },
-        Err(err) => Err(format!(
+/// Returns a builder for an ingress message with the given nonce that is sent
+/// by `sender`, if one was chosen.
+fn ingress_builder(nonce: u64, sender: Option<UserId>) -> SignedIngressBuilder {
+    let builder = SignedIngressBuilder::new().nonce(nonce);
+    match sender {
+        Some(sender) => builder.sender(sender),
+        None => builder,
+    }
+}
+
+fn parse_principal_id(principal_id: &str) -> Result<PrincipalId, String> {
+    PrincipalId::from_str(principal_id).map_err(|err| {
+        format!(
             "Failed to convert {} to principal id with {}",
+            principal_id, err
+        )
+    })
+}
+
+fn parse_canister_id(canister_id: &str) -> Result<CanisterId, String> {
+    CanisterId::new(parse_principal_id(canister_id)?).map_err(|err| {
+        format!(
+            "Failed to convert {} to canister id with {}",
             canister_id, err
-        )),
-    }
+        )
+    })
 }
 
-fn parse_create(nonce: u64) -> Result<Message, String> {
-    use ic_test_utilities::types::messages::SignedIngressBuilder;
+fn parse_reject_code(code: &str) -> Result<RejectCode, String> {
+    code.parse::<u64>()
+        .map_err(|err| err.to_string())
+        .and_then(|code| RejectCode::try_from(code).map_err(|err| err.to_string()))
+        .map_err(|err| format!("Failed to parse reject code {} with {}", code, err))
+}
+
+fn parse_subnet_index(subnet: &str) -> Result<usize, String> {
+    subnet
+        .parse()
+        .map_err(|err| format!("Failed to parse subnet index {} with {}", subnet, err))
+}
 
-    let signed_ingress = SignedIngressBuilder::new()
+fn parse_create(nonce: u64, sender: Option<UserId>, subnet: usize) -> Result<Message, String> {
+    let signed_ingress = ingress_builder(nonce, sender)
         .method_name(ic00::Method::ProvisionalCreateCanisterWithCycles)
         .canister_id(ic00::IC_00)
         .method_payload(ic00::ProvisionalCreateCanisterWithCyclesArgs::new(None).encode())
-        .nonce(nonce)
         .build();
 
-    Ok(Message::Create(signed_ingress))
+    Ok(Message::Create(signed_ingress, subnet))
+}
+
+/// Parses a management canister call whose only argument is the canister it
+/// targets.
+fn parse_canister_id_call(
+    nonce: u64,
+    sender: Option<UserId>,
+    canister_id: &str,
+    method: ic00::Method,
+) -> Result<Message, String> {
+    let canister_id = parse_canister_id(canister_id)?;
+    let signed_ingress = ingress_builder(nonce, sender)
+        .canister_id(ic00::IC_00)
+        .method_name(method)
+        .method_payload(ic00::CanisterIdRecord::from(canister_id).encode())
+        .build();
+    Ok(Message::Management(signed_ingress, canister_id))
+}
+
+fn parse_cycles(
+    nonce: u64,
+    sender: Option<UserId>,
+    canister_id: &str,
+    amount: &str,
+) -> Result<Message, String> {
+    let canister_id = parse_canister_id(canister_id)?;
+    let amount = amount
+        .parse()
+        .map_err(|err| format!("Failed to parse amount of cycles {} with {}", amount, err))?;
+    let signed_ingress = ingress_builder(nonce, sender)
+        .canister_id(ic00::IC_00)
+        .method_name(ic00::Method::ProvisionalTopUpCanister)
+        .method_payload(ic00::ProvisionalTopUpCanisterArgs::new(canister_id, amount).encode())
+        .build();
+    Ok(Message::Management(signed_ingress, canister_id))
+}
+
+/// Parses the settings of an `update_settings` call, given as `<name>=<value>`
+/// pairs separated by whitespace. Controllers are separated by commas.
+fn parse_update_settings(
+    nonce: u64,
+    sender: Option<UserId>,
+    canister_id: &str,
+    settings: &[&str],
+) -> Result<Message, String> {
+    fn parse_nat(name: &str, value: &str) -> Result<Option<candid::Nat>, String> {
+        value
+            .parse::<u128>()
+            .map(|value| Some(candid::Nat::from(value)))
+            .map_err(|err| format!("Failed to parse {} {} with {}", name, value, err))
+    }
+
+    let canister_id = parse_canister_id(canister_id)?;
+    let mut args = CanisterSettingsArgs::default();
+    for setting in settings.iter().flat_map(|s| s.split_whitespace()) {
+        match setting.splitn(2, '=').collect::<Vec<_>>()[..] {
+            ["controllers", value] => {
+                args.controllers = Some(
+                    value
+                        .split(',')
+                        .filter(|id| !id.is_empty())
+                        .map(parse_principal_id)
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            ["compute_allocation", value] => {
+                args.compute_allocation = parse_nat("compute_allocation", value)?
+            }
+            ["memory_allocation", value] => {
+                args.memory_allocation = parse_nat("memory_allocation", value)?
+            }
+            ["freezing_threshold", value] => {
+                args.freezing_threshold = parse_nat("freezing_threshold", value)?
+            }
+            ["reserved_cycles_limit", value] => {
+                args.reserved_cycles_limit = parse_nat("reserved_cycles_limit", value)?
+            }
+            _ => return Err(format!("Unknown canister setting {}", setting)),
+        }
+    }
+    let signed_ingress = ingress_builder(nonce, sender)
+        .canister_id(ic00::IC_00)
+        .method_name(ic00::Method::UpdateSettings)
+        .method_payload(
+            ic00::UpdateSettingsArgs {
+                canister_id: canister_id.get(),
+                settings: args,
+            }
+            .encode(),
+        )
+        .build();
+    Ok(Message::Management(signed_ingress, canister_id))
 }
 
 fn parse_install(
     nonce: u64,
+    sender: Option<UserId>,
+    interface: Option<&CandidInterface>,
     canister_id: &str,
     payload: &str,
     wasm_file: &str,
     mode: &str,
 ) -> Result<Message, String> {
-    use ic_test_utilities::types::messages::SignedIngressBuilder;
-
     let mut wasm_data = Vec::new();
     let mut wasm_file = File::open(wasm_file)
         .map_err(|e| format!("Could not open wasm file: {} - Error: {}", wasm_file, e))?;
@@ -240,9 +445,9 @@ fn parse_install(
         .map_err(|e| e.to_string())?;
 
     let canister_id = parse_canister_id(canister_id)?;
-    let payload = parse_octet_string(payload)?;
+    let payload = parse_payload(payload, interface, CandidMethod::Init)?;
 
-    let signed_ingress = SignedIngressBuilder::new()
+    let signed_ingress = ingress_builder(nonce, sender)
         // `source` should become a self-authenticating id according
         // to https://sdk.dfinity.org/docs/interface-spec/index.html#id-classes
         .canister_id(ic00::IC_00)
@@ -259,9 +464,8 @@ fn parse_install(
             )
             .encode(),
         )
-        .nonce(nonce)
         .build();
-    Ok(Message::Install(signed_ingress))
+    Ok(Message::Install(signed_ingress, canister_id))
 }
 
 fn validate_method_name(method_name: &str) -> Result<String, String> {
@@ -284,6 +488,20 @@ fn validate_method_name(method_name: &str) -> Result<String, String> {
     }
 }
 
+/// Parses the argument of a call, which is either a textual Candid value or an
+/// octet string.
+fn parse_payload(
+    input_str: &str,
+    interface: Option<&CandidInterface>,
+    method: CandidMethod<'_>,
+) -> Result<Vec<u8>, String> {
+    if input_str.starts_with('(') {
+        encode_args(input_str, interface, method)
+    } else {
+        parse_octet_string(input_str)
+    }
+}
+
 fn parse_octet_string(input_str: &str) -> Result<Vec<u8>, String> {
     if input_str.starts_with('"') {
         parse_quoted(input_str)
@@ -372,7 +590,7 @@ enum Radix {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use ic_test_utilities::types::{ids::canister_test_id, messages::SignedIngressBuilder};
+    use ic_test_utilities::types::ids::canister_test_id;
     use std::io::Cursor;
 
     const APP_CANISTER_URL: &str = "ryjl3-tyaaa-aaaaa-aaaba-cai";
@@ -384,7 +602,7 @@ mod tests {
             "ingress {} write \"payload \\x0a\\b00010001\"",
             APP_CANISTER_URL
         );
-        let parsed_message = parse_message(s, 0).unwrap();
+        let parsed_message = parse_message(s, 0, None).unwrap();
         let expiry_time = match &parsed_message {
             Message::Ingress(signed_ingress) => signed_ingress.expiry_time(),
             _ => panic!(
@@ -407,7 +625,7 @@ mod tests {
     #[test]
     fn test_parse_message_hex_payload_succeeds() {
         let s = &format!("ingress {} write 0x010203", APP_CANISTER_URL);
-        let parsed_message = parse_message(s, 0).unwrap();
+        let parsed_message = parse_message(s, 0, None).unwrap();
         let expiry_time = match &parsed_message {
             Message::Ingress(signed_ingress) => signed_ingress.expiry_time(),
             _ => panic!(
@@ -428,7 +646,7 @@ mod tests {
 
         let s = &format!("query {} read 0x010203", APP_CANISTER_URL);
         let nonce: u64 = 0;
-        let parsed_message = parse_message(s, 0).unwrap();
+        let parsed_message = parse_message(s, 0, None).unwrap();
         let ingress_expiry = match &parsed_message {
             Message::Query(query) => query.ingress_expiry,
             _ => panic!(
@@ -447,28 +665,143 @@ mod tests {
         assert_eq!(expected, parsed_message);
     }
 
+    #[test]
+    fn test_parse_message_candid_payload() {
+        let s = &format!(
+            "ingress {} write (record {{ a = 1 }}, \"b\")",
+            APP_CANISTER_URL
+        );
+        match parse_message(s, 0, None).unwrap() {
+            Message::Ingress(msg) => assert_eq!(
+                msg.method_arg(),
+                &"(record { a = 1 }, \"b\")"
+                    .parse::<IDLArgs>()
+                    .unwrap()
+                    .to_bytes()
+                    .unwrap()[..]
+            ),
+            msg => panic!(
+                "parse_message() returned an unexpected message type: {:?}",
+                msg
+            ),
+        }
+        assert_eq!(
+            parse_message("assert_reply (42 : nat)", 0, None).unwrap(),
+            Message::AssertCandidReply("(42 : nat)".parse().unwrap())
+        );
+
+        let s = &format!("query {} read (record {{ a = }})", APP_CANISTER_URL);
+        assert!(parse_message(s, 0, None).is_err());
+    }
+
     #[test]
     fn test_parse_message_invalid_escapes_fails() {
         let s = &format!("query {} read \"\\xzz\"", APP_CANISTER_URL);
-        assert!(parse_message(s, 0).is_err());
+        assert!(parse_message(s, 0, None).is_err());
 
         let s = &format!("query {} read \"\\b01\"", APP_CANISTER_URL);
-        assert!(parse_message(s, 0).is_err());
+        assert!(parse_message(s, 0, None).is_err());
 
         let s = &format!("query {} read \"\\x1\"", APP_CANISTER_URL);
-        assert!(parse_message(s, 0).is_err());
+        assert!(parse_message(s, 0, None).is_err());
 
         let s = &format!("query {} read \"\\b2\"", APP_CANISTER_URL);
-        assert!(parse_message(s, 0).is_err());
+        assert!(parse_message(s, 0, None).is_err());
     }
 
     #[test]
     fn test_illegal_method_name_must_fail() {
         let s = &format!("query {} 0read \"\\xzz\"", APP_CANISTER_URL);
-        assert!(parse_message(s, 0).is_err());
+        assert!(parse_message(s, 0, None).is_err());
 
         let s = &format!("query {} üread \"\\xzz\"", APP_CANISTER_URL);
-        assert!(parse_message(s, 0).is_err());
+        assert!(parse_message(s, 0, None).is_err());
+    }
+
+    #[test]
+    fn test_parse_create_on_subnet() {
+        match parse_message("create", 0, None).unwrap() {
+            Message::Create(_, subnet) => assert_eq!(subnet, 0),
+            msg => panic!(
+                "parse_message() returned an unexpected message type: {:?}",
+                msg
+            ),
+        }
+        match parse_message("create 2", 0, None).unwrap() {
+            Message::Create(_, subnet) => assert_eq!(subnet, 2),
+            msg => panic!(
+                "parse_message() returned an unexpected message type: {:?}",
+                msg
+            ),
+        }
+        assert!(parse_message("create -1", 0, None).is_err());
+    }
+
+    #[test]
+    fn test_parse_assertions_and_time() {
+        assert_eq!(
+            parse_message("assert_reply \"a b\"", 0, None).unwrap(),
+            Message::AssertReply(b"a b".to_vec())
+        );
+        assert_eq!(
+            parse_message("assert_reject 4", 0, None).unwrap(),
+            Message::AssertReject(RejectCode::CanisterReject)
+        );
+        assert!(parse_message("assert_reject 6", 0, None).is_err());
+        assert_eq!(
+            parse_message("advance_time 60", 0, None).unwrap(),
+            Message::AdvanceTime(Duration::from_secs(60))
+        );
+    }
+
+    #[test]
+    fn test_parse_management_calls_target_canister() {
+        for line in &[
+            format!("stop {}", APP_CANISTER_URL),
+            format!("start {}", APP_CANISTER_URL),
+            format!("delete {}", APP_CANISTER_URL),
+            format!("cycles {} 1000", APP_CANISTER_URL),
+            format!(
+                "update_settings {} compute_allocation=10 freezing_threshold=0",
+                APP_CANISTER_URL
+            ),
+        ] {
+            match parse_message(line, 0, None).unwrap() {
+                Message::Management(msg, canister_id) => {
+                    assert_eq!(msg.canister_id(), ic00::IC_00);
+                    assert_eq!(canister_id, canister_test_id(APP_CANISTER_ID));
+                }
+                msg => panic!(
+                    "parse_message() returned an unexpected message type: {:?}",
+                    msg
+                ),
+            }
+        }
+        let s = &format!("update_settings {} unknown=1", APP_CANISTER_URL);
+        assert!(parse_message(s, 0, None).is_err());
+    }
+
+    #[test]
+    fn test_parse_message_with_sender() {
+        let sender = PrincipalId::new_user_test_id(7);
+        let s = &format!("as {} ingress {} write 0x01", sender, APP_CANISTER_URL);
+        match parse_message(s, 0, None).unwrap() {
+            Message::Ingress(msg) => assert_eq!(msg.sender(), UserId::from(sender)),
+            msg => panic!(
+                "parse_message() returned an unexpected message type: {:?}",
+                msg
+            ),
+        }
+        let s = &format!("as {} query {} read 0x01", sender, APP_CANISTER_URL);
+        match parse_message(s, 0, None).unwrap() {
+            Message::Query(query) => assert_eq!(query.source, UserId::from(sender)),
+            msg => panic!(
+                "parse_message() returned an unexpected message type: {:?}",
+                msg
+            ),
+        }
+        let s = &format!("as {} advance_time 1", sender);
+        assert!(parse_message(s, 0, None).is_err());
     }
 
     #[test]
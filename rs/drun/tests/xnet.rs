@@ -0,0 +1,117 @@
+use ic_config::Config;
+use ic_drun::{run_drun, DrunOptions};
+use ic_types::CanisterId;
+use std::fs;
+use std::path::Path;
+
+/// A canister whose "ping" method replies with "pong".
+const CALLEE_CANISTER: &str = r#"
+            (module
+              (import "ic0" "msg_reply" (func $msg_reply))
+              (import "ic0" "msg_reply_data_append"
+                (func $msg_reply_data_append (param i32 i32)))
+
+              (func $ping
+                (call $msg_reply_data_append (i32.const 0) (i32.const 4))
+                (call $msg_reply)
+              )
+
+              (memory $memory 1)
+              (export "memory" (memory $memory))
+              (data (i32.const 0) "pong")
+              (export "canister_update ping" (func $ping)))"#;
+
+/// A canister that calls "ping" on the canister whose ID is passed as the
+/// argument of its "forward" method and replies with the reply of the callee.
+const PROXY_CANISTER: &str = r#"
+            (module
+              (import "ic0" "msg_arg_data_size" (func $msg_arg_data_size (result i32)))
+              (import "ic0" "msg_arg_data_copy"
+                (func $msg_arg_data_copy (param $dst i32) (param $offset i32) (param $size i32)))
+              (import "ic0" "msg_reply" (func $msg_reply))
+              (import "ic0" "msg_reply_data_append"
+                (func $msg_reply_data_append (param i32 i32)))
+              (import "ic0" "msg_reject" (func $msg_reject (param i32 i32)))
+              (import "ic0" "call_new"
+                (func $call_new
+                  (param $callee_src i32) (param $callee_size i32)
+                  (param $name_src i32) (param $name_size i32)
+                  (param $reply_fun i32) (param $reply_env i32)
+                  (param $reject_fun i32) (param $reject_env i32)))
+              (import "ic0" "call_perform" (func $call_perform (result i32)))
+
+              (func $forward
+                (call $msg_arg_data_copy (i32.const 100) (i32.const 0) (call $msg_arg_data_size))
+                (call $call_new
+                  (i32.const 100) (call $msg_arg_data_size)
+                  (i32.const 0) (i32.const 4)
+                  (i32.const 0) (i32.const 0)
+                  (i32.const 1) (i32.const 0))
+                (drop (call $call_perform))
+              )
+
+              (func $on_reply (param i32)
+                (call $msg_arg_data_copy (i32.const 200) (i32.const 0) (call $msg_arg_data_size))
+                (call $msg_reply_data_append (i32.const 200) (call $msg_arg_data_size))
+                (call $msg_reply)
+              )
+
+              (func $on_reject (param i32)
+                (call $msg_reject (i32.const 4) (i32.const 8))
+              )
+
+              (table funcref (elem $on_reply $on_reject))
+              (memory $memory 1)
+              (export "memory" (memory $memory))
+              (data (i32.const 0) "pingrejected")
+              (export "canister_update forward" (func $forward)))"#;
+
+fn write_wasm(dir: &Path, name: &str, wat: &str) -> String {
+    let path = dir.join(name);
+    fs::write(&path, wabt::wat2wasm(wat).unwrap()).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+/// Runs drun with two subnets and checks that a call from a canister on
+/// subnet 0 to a canister on subnet 1 is answered through the XNet streams.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_call_from_subnet_0_to_subnet_1_is_replied() {
+    let tmpdir = tempfile::Builder::new()
+        .prefix("drun_xnet_test")
+        .tempdir()
+        .unwrap();
+    let proxy_wasm = write_wasm(tmpdir.path(), "proxy.wasm", PROXY_CANISTER);
+    let callee_wasm = write_wasm(tmpdir.path(), "callee.wasm", CALLEE_CANISTER);
+
+    // Every subnet gets a range of 2^20 canister IDs, so the first canister
+    // on subnet 1 has the ID 2^20.
+    let proxy = CanisterId::from(0);
+    let callee = CanisterId::from(1 << 20);
+    let messages = format!(
+        "create\n\
+         create 1\n\
+         install {proxy} {proxy_wasm} \"\"\n\
+         install {callee} {callee_wasm} \"\"\n\
+         ingress {proxy} forward 0x{callee_bytes}\n\
+         assert_reply \"pong\"\n",
+        proxy = proxy,
+        proxy_wasm = proxy_wasm,
+        callee = callee,
+        callee_wasm = callee_wasm,
+        callee_bytes = hex::encode(callee.get().as_slice()),
+    );
+    let msg_file = tmpdir.path().join("messages.txt");
+    fs::write(&msg_file, messages).unwrap();
+
+    Config::run_with_temp_config(|cfg| {
+        run_drun(DrunOptions {
+            msg_filename: msg_file.to_str().unwrap().to_string(),
+            cfg,
+            extra_batches: 0,
+            log_file: None,
+            did_file: None,
+            subnets: 2,
+        })
+    })
+    .unwrap();
+}
@@ -5,7 +5,9 @@ pub mod wasm_utils;
 pub mod wasmtime_embedder;
 
 use ic_cycles_account_manager::CyclesAccountManager;
-use ic_interfaces::execution_environment::{ExecutionParameters, HypervisorError, InstanceStats};
+use ic_interfaces::execution_environment::{
+    ExecutionParameters, HypervisorError, InstanceStats, OutOfInstructionsHandler,
+};
 use ic_replicated_state::{
     canister_state::system_state::SystemState, ExecutionState, Global, NumWasmPages, PageIndex,
 };
@@ -23,6 +25,7 @@ pub struct WasmExecutionInput {
     pub func_ref: FuncRef,
     pub execution_state: ExecutionState,
     pub cycles_account_manager: Arc<CyclesAccountManager>,
+    pub out_of_instructions_handler: Arc<dyn OutOfInstructionsHandler>,
 }
 
 pub struct WasmExecutionOutput {
@@ -10,7 +10,9 @@ use crate::{
     wasmtime_embedder::WasmtimeInstance,
     WasmExecutionInput, WasmExecutionOutput, WasmtimeEmbedder,
 };
-use ic_config::{embedders::Config as EmbeddersConfig, embedders::PersistenceType};
+use ic_config::{
+    embedders::Config as EmbeddersConfig, embedders::PersistenceType, feature_status::FeatureStatus,
+};
 use ic_cow_state::{CowMemoryManager, MappedState};
 use ic_interfaces::execution_environment::{
     HypervisorError, HypervisorResult, InstanceStats, SystemApi,
@@ -196,6 +198,7 @@ impl WasmExecutor {
             func_ref,
             mut execution_state,
             cycles_account_manager,
+            out_of_instructions_handler,
         }: WasmExecutionInput,
     ) -> WasmExecutionOutput {
         let static_system_state =
@@ -245,8 +248,7 @@ impl WasmExecutor {
         let commit_dirty_pages = func_ref.to_commit();
         let modification_tracking = api_type.modification_tracking();
 
-        let instruction_limit = execution_parameters.instruction_limit;
-        let system_api = SystemApiImpl::new(
+        let mut system_api = SystemApiImpl::new(
             api_type,
             system_state_accessor,
             static_system_state,
@@ -255,6 +257,8 @@ impl WasmExecutor {
             execution_state.stable_memory.clone(),
             self.log.clone(),
         );
+        system_api.set_out_of_instructions_handler(out_of_instructions_handler);
+        let instruction_limit = system_api.slice_instruction_limit();
 
         let mut instance = match self.wasm_embedder.new_instance(
             canister_id,
@@ -321,9 +325,11 @@ impl WasmExecutor {
                         .set_execution_error(err);
                 }
             };
-            let num_instructions = instance.get_num_instructions();
+            let instruction_counter = instance.get_num_instructions();
             let stats = instance.get_stats();
             let mut system_api = instance.into_store_data().system_api;
+            let num_instructions =
+                system_api.num_instructions_left(instruction_counter.get() as i64);
             let execution_result = system_api.take_execution_result();
             let system_state_accessor = system_api.release_system_state_accessor();
             let system_state = system_state_accessor.release_system_state();
@@ -344,11 +350,17 @@ impl WasmExecutor {
         wasm_binary: Vec<u8>,
         canister_root: PathBuf,
         canister_id: CanisterId,
+        wasm_simd: FeatureStatus,
     ) -> HypervisorResult<ExecutionState> {
+        // New code is validated against the Wasm features that are currently
+        // enabled on the subnet.
+        let mut config = self.config.clone();
+        config.feature_flags.wasm_simd = wasm_simd;
+
         // Get new ExecutionState not fully initialized.
         let mut execution_state =
             self.wasm_embedder
-                .create_execution_state(wasm_binary, canister_root, &self.config)?;
+                .create_execution_state(wasm_binary, canister_root, &config)?;
 
         let memory_creator = if execution_state.mapped_state.is_some() {
             let mapped_state = Arc::as_ref(execution_state.mapped_state.as_ref().unwrap());
@@ -410,6 +410,16 @@ fn get_valid_system_apis(
                 },
             )],
         ),
+        (
+            "performance_counter",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![ValueType::I32],
+                    return_type: vec![ValueType::I64],
+                },
+            )],
+        ),
         (
             "trap",
             vec![(
@@ -460,6 +470,16 @@ fn get_valid_system_apis(
                 },
             )],
         ),
+        (
+            "global_timer_set",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![ValueType::I64],
+                    return_type: vec![ValueType::I64],
+                },
+            )],
+        ),
         (
             "certified_data_set",
             vec![(
@@ -618,6 +638,13 @@ fn get_valid_exported_functions() -> HashMap<String, FunctionSignature> {
                 return_type: vec![],
             },
         ),
+        (
+            "canister_composite_query",
+            FunctionSignature {
+                param_types: vec![],
+                return_type: vec![],
+            },
+        ),
         (
             "canister_pre_upgrade",
             FunctionSignature {
@@ -646,6 +673,13 @@ fn get_valid_exported_functions() -> HashMap<String, FunctionSignature> {
                 return_type: vec![],
             },
         ),
+        (
+            "canister_global_timer",
+            FunctionSignature {
+                param_types: vec![],
+                return_type: vec![],
+            },
+        ),
     ];
 
     valid_exported_functions
@@ -770,8 +804,8 @@ fn validate_import_section(
 }
 
 // Performs the following checks:
-// * Validates signatures of exported canister_update and canister_query
-//   methods.
+// * Validates signatures of exported canister_update, canister_query and
+//   canister_composite_query methods.
 // * Validates the signatures of other allowed exported functions (like
 //   `canister_init` or `canister_pre_upgrade`) if present.
 // * Validates that the canister doesn't export any reserved symbols
@@ -795,15 +829,17 @@ fn validate_export_section(module: &Module) -> Result<usize, WasmValidationError
                 let mut func_name = export.field();
                 // func_name holds either:
                 // - the entire exported non-IC function names, or
-                // - canister_query or canister_update part in case of the IC functions.
+                // - canister_query, canister_composite_query or canister_update part in
+                //   case of the IC functions.
                 if func_name.starts_with("canister_query ")
+                    || func_name.starts_with("canister_composite_query ")
                     || func_name.starts_with("canister_update ")
                 {
                     let parts: Vec<&str> = func_name.splitn(2, ' ').collect();
                     let unmangled_func_name = parts[1];
                     if seen_funcs.contains(unmangled_func_name) {
                         return Err(WasmValidationError::InvalidExportSection(format!(
-                            "Duplicate function '{}' exported for both update calls and queries.",
+                            "Duplicate function '{}' exported for more than one of update calls, queries and composite queries.",
                             unmangled_func_name
                         )));
                     }
@@ -907,16 +943,28 @@ fn validate_function_section(
 }
 
 /// Sets Wasmtime flags to ensure deterministic execution.
-pub fn ensure_determinism(config: &mut Config) {
+///
+/// SIMD and bulk memory instructions are enabled only if the corresponding
+/// feature flag is set. SIMD instructions are deterministic because the NaN
+/// canonicalization also covers the floating-point vector instructions.
+/// Reference types are disabled because they require bulk memory and the
+/// validation rejects them anyway.
+pub fn ensure_determinism(config: &mut Config, feature_flags: &FeatureFlags) {
+    let wasm_simd = feature_flags.wasm_simd == FeatureStatus::Enabled;
     config
         .wasm_threads(false)
-        .wasm_simd(false)
+        .wasm_simd(wasm_simd)
+        .wasm_bulk_memory(wasm_simd)
+        .wasm_reference_types(false)
         .cranelift_nan_canonicalization(true);
 }
 
-fn can_compile(wasm: &BinaryEncodedWasm) -> Result<(), WasmValidationError> {
+fn can_compile(
+    wasm: &BinaryEncodedWasm,
+    feature_flags: &FeatureFlags,
+) -> Result<(), WasmValidationError> {
     let mut config = wasmtime::Config::default();
-    ensure_determinism(&mut config);
+    ensure_determinism(&mut config, feature_flags);
     let engine = wasmtime::Engine::new(&config).map_err(|_| {
         WasmValidationError::WasmtimeValidation(String::from("Failed to initialize Wasm engine"))
     })?;
@@ -969,7 +1017,7 @@ pub fn validate_wasm_binary(
     wasm: &BinaryEncodedWasm,
     config: &EmbeddersConfig,
 ) -> Result<WasmValidationDetails, WasmValidationError> {
-    can_compile(wasm)?;
+    can_compile(wasm, &config.feature_flags)?;
     let module = parity_wasm::deserialize_buffer::<Module>(wasm.as_slice())
         .map_err(|err| WasmValidationError::ParityDeserializeError(into_parity_wasm_error(err)))?;
     let imports_details = validate_import_section(&module, &config.feature_flags)?;
@@ -15,7 +15,7 @@ use wasmtime::{unix::StoreExt, Memory, Mutability, Store, Val, ValType};
 
 use host_memory::MmapMemoryCreator;
 pub use host_memory::WasmtimeMemoryCreator;
-use ic_config::embedders::{Config as EmbeddersConfig, PersistenceType};
+use ic_config::embedders::{Config as EmbeddersConfig, FeatureFlags, PersistenceType};
 use ic_interfaces::execution_environment::{
     HypervisorError, HypervisorResult, InstanceStats, SystemApi, TrapCode,
 };
@@ -82,6 +82,7 @@ fn trap_to_error(err: anyhow::Error) -> HypervisorError {
 pub struct WasmtimeEmbedder {
     log: ReplicaLogger,
     max_wasm_stack_size: usize,
+    feature_flags: FeatureFlags,
     // Each time a new memory is created it is added to this map.  Each time a
     // `SigsegvMemoryTracker` is created it will look up the corresponding memory in the map
     // and remove it. So memories will only be in this map for the time between module
@@ -93,12 +94,14 @@ impl WasmtimeEmbedder {
     pub fn new(config: EmbeddersConfig, log: ReplicaLogger) -> Self {
         let EmbeddersConfig {
             max_wasm_stack_size,
+            feature_flags,
             ..
         } = config;
 
         WasmtimeEmbedder {
             log,
             max_wasm_stack_size,
+            feature_flags,
             created_memories: Arc::new(Mutex::new(HashMap::new())),
         }
     }
@@ -109,7 +112,7 @@ impl WasmtimeEmbedder {
         wasm_binary: &BinaryEncodedWasm,
     ) -> HypervisorResult<EmbedderCache> {
         let mut config = wasmtime::Config::default();
-        ensure_determinism(&mut config);
+        ensure_determinism(&mut config, &self.feature_flags);
         let cached_mem_creator = match persistence_type {
             PersistenceType::Sigsegv => {
                 let raw_creator = MmapMemoryCreator {};
@@ -2,7 +2,7 @@ use crate::wasmtime_embedder::{system_api_charges, StoreData};
 
 use ic_interfaces::execution_environment::{HypervisorError, HypervisorResult, SystemApi};
 use ic_logger::{error, info, ReplicaLogger};
-use ic_types::{CanisterId, Cycles, NumBytes, NumInstructions};
+use ic_types::{CanisterId, Cycles, NumBytes, NumInstructions, Time};
 
 use wasmtime::{AsContextMut, Caller, Linker, Store, Trap, Val};
 
@@ -22,9 +22,9 @@ fn process_err<S: SystemApi>(
 }
 
 /// Charges a canister (in instructions) for system API call overhead (exit,
-/// accessing state, etc) and for using `num_bytes` bytes of memory. If
-/// the canister has run out instructions or there are unexpected bugs, return
-/// an error.
+/// accessing state, etc) and for using `num_bytes` bytes of memory. Returns
+/// the value of the instruction counter after the charge. If the canister has
+/// run out instructions or there are unexpected bugs, return an error.
 ///
 /// There are a number of scenarios that this function must handle where due
 /// to potential bugs, the expected information is not available. In more
@@ -40,7 +40,7 @@ fn charge_for_system_api_call<S: SystemApi>(
     mut caller: &mut Caller<'_, StoreData<S>>,
     system_api_charge: NumInstructions,
     num_bytes: u64,
-) -> Result<(), Trap> {
+) -> Result<i64, Trap> {
     let num_instructions_global = match caller.data().num_instructions_global {
         None => {
             error!(
@@ -64,20 +64,31 @@ fn charge_for_system_api_call<S: SystemApi>(
                 .get_num_instructions_from_bytes(NumBytes::from(num_bytes))
                 .get() as i64
                 + system_api_charge.get() as i64;
+            let mut updated_instructions = current_instructions - fee;
             if current_instructions < fee {
-                info!(
-                    log,
-                    "Canister {}: ran out of instructions.  Current {}, fee {}",
-                    canister_id,
-                    current_instructions,
-                    fee
-                );
-                return Err(process_err(
-                    caller,
-                    HypervisorError::InstructionLimitExceeded,
-                ));
+                // The current slice is exhausted; the execution may continue
+                // only if a new slice is started.
+                match caller
+                    .as_context_mut()
+                    .data_mut()
+                    .system_api
+                    .out_of_instructions(updated_instructions)
+                {
+                    Ok(new_slice_instructions) => {
+                        updated_instructions = new_slice_instructions;
+                    }
+                    Err(err) => {
+                        info!(
+                            log,
+                            "Canister {}: ran out of instructions.  Current {}, fee {}",
+                            canister_id,
+                            current_instructions,
+                            fee
+                        );
+                        return Err(process_err(caller, err));
+                    }
+                }
             }
-            let updated_instructions = current_instructions - fee;
             if let Err(err) =
                 num_instructions_global.set(&mut caller, Val::I64(updated_instructions))
             {
@@ -94,7 +105,7 @@ fn charge_for_system_api_call<S: SystemApi>(
                     HypervisorError::InstructionLimitExceeded,
                 ));
             }
-            Ok(())
+            Ok(updated_instructions)
         }
         others => {
             error!(
@@ -676,6 +687,7 @@ pub(crate) fn syscalls<S: SystemApi>(
 
     linker
         .func_wrap("ic0", "stable64_write", {
+            let log = log.clone();
             move |mut caller: Caller<'_, StoreData<S>>, offset: i64, src: i64, size: i64| {
                 charge_for_system_api_call(
                     &log,
@@ -701,6 +713,26 @@ pub(crate) fn syscalls<S: SystemApi>(
         })
         .unwrap();
 
+    linker
+        .func_wrap("ic0", "performance_counter", {
+            let log = log.clone();
+            move |mut caller: Caller<'_, StoreData<S>>, counter_type: u32| {
+                let instruction_counter = charge_for_system_api_call(
+                    &log,
+                    canister_id,
+                    &mut caller,
+                    system_api_charges::PERFORMANCE_COUNTER,
+                    0,
+                )?;
+                with_system_api(&mut caller, |s| {
+                    s.ic0_performance_counter(counter_type, instruction_counter)
+                })
+                .map_err(|e| process_err(caller, e))
+                .map(|counter| counter as i64)
+            }
+        })
+        .unwrap();
+
     linker
         .func_wrap("ic0", "canister_cycle_balance", {
             move |mut caller: Caller<'_, StoreData<S>>| {
@@ -798,9 +830,63 @@ pub(crate) fn syscalls<S: SystemApi>(
 
     linker
         .func_wrap("__", "out_of_instructions", {
+            let log = log.clone();
             move |mut caller: Caller<'_, StoreData<S>>| -> Result<(), _> {
-                let err = with_system_api(&mut caller, |s| s.out_of_instructions());
-                Err(process_err(caller, err))
+                let num_instructions_global = match caller.data().num_instructions_global {
+                    Some(global) => global,
+                    None => {
+                        error!(
+                            log,
+                            "[EXC-BUG] Canister {}: instructions counter is set to None.",
+                            canister_id,
+                        );
+                        return Err(process_err(
+                            caller,
+                            HypervisorError::InstructionLimitExceeded,
+                        ));
+                    }
+                };
+                let instruction_counter = match num_instructions_global.get(&mut caller) {
+                    Val::I64(instruction_counter) => instruction_counter,
+                    others => {
+                        error!(
+                            log,
+                            "[EXC-BUG] Canister {}: expected value of type I64 instead got {:?}",
+                            canister_id,
+                            others,
+                        );
+                        return Err(process_err(
+                            caller,
+                            HypervisorError::InstructionLimitExceeded,
+                        ));
+                    }
+                };
+                let result = caller
+                    .as_context_mut()
+                    .data_mut()
+                    .system_api
+                    .out_of_instructions(instruction_counter);
+                match result {
+                    Ok(new_slice_instructions) => {
+                        if let Err(err) = num_instructions_global
+                            .set(&mut caller, Val::I64(new_slice_instructions))
+                        {
+                            error!(
+                                log,
+                                "[EXC-BUG] Canister {}: Setting instructions to {} failed with {}",
+                                canister_id,
+                                new_slice_instructions,
+                                err
+                            );
+                            return Err(process_err(
+                                caller,
+                                HypervisorError::InstructionLimitExceeded,
+                            ));
+                        }
+                        Ok(())
+                    }
+                    Err(err) => Err(process_err(caller, err)),
+                }
             }
         })
         .unwrap();
@@ -837,6 +923,18 @@ pub(crate) fn syscalls<S: SystemApi>(
         })
         .unwrap();
 
+    linker
+        .func_wrap("ic0", "global_timer_set", {
+            move |mut caller: Caller<'_, StoreData<S>>, time: i64| {
+                with_system_api(&mut caller, |s| {
+                    s.ic0_global_timer_set(Time::from_nanos_since_unix_epoch(time as u64))
+                })
+                .map_err(|e| process_err(caller, e))
+                .map(|s| s.as_nanos_since_unix_epoch() as i64)
+            }
+        })
+        .unwrap();
+
     linker
         .func_wrap("ic0", "data_certificate_present", {
             move |mut caller: Caller<'_, StoreData<S>>| {
@@ -29,3 +29,5 @@ pub const STABLE_WRITE: NumInstructions = NumInstructions::new(20);
 pub const STABLE64_READ: NumInstructions = NumInstructions::new(20);
 // Benchmark results: +463 for 1B, -7K for 8KiB
 pub const STABLE64_WRITE: NumInstructions = NumInstructions::new(20);
+// Not tested
+pub const PERFORMANCE_COUNTER: NumInstructions = NumInstructions::new(200);
@@ -8,7 +8,7 @@ use ic_system_api::{ApiType, StaticSystemState, SystemApiImpl};
 use ic_test_utilities::{
     cycles_account_manager::CyclesAccountManagerBuilder, types::ids::canister_test_id,
 };
-use ic_types::{ComputeAllocation, NumBytes, NumInstructions};
+use ic_types::{ComputeAllocation, NumBytes, NumInstructions, MAX_STABLE_MEMORY_IN_BYTES};
 use ic_wasm_types::BinaryEncodedWasm;
 
 use lazy_static::lazy_static;
@@ -41,6 +41,7 @@ fn test_wasmtime_system_api() {
         ExecutionParameters {
             instruction_limit: MAX_NUM_INSTRUCTIONS,
             canister_memory_limit,
+            max_stable_memory_size: NumBytes::new(MAX_STABLE_MEMORY_IN_BYTES),
             subnet_available_memory: MAX_SUBNET_AVAILABLE_MEMORY.clone(),
             compute_allocation: ComputeAllocation::default(),
             subnet_type: SubnetType::Application,
@@ -30,8 +30,10 @@ fn can_extract_exported_functions() {
                         (module
                           (func $write)
                           (func $read)
+                          (func $aggregate)
                           (export "canister_update write" (func $write))
                           (export "canister_query read" (func $read))
+                          (export "canister_composite_query aggregate" (func $aggregate))
                           (memory (;0;) 2)
                           (export "memory" (memory 0))
                         )
@@ -45,6 +47,7 @@ fn can_extract_exported_functions() {
     let mut expected_exports = BTreeSet::new();
     expected_exports.insert(WasmMethod::Update("write".to_string()));
     expected_exports.insert(WasmMethod::Query("read".to_string()));
+    expected_exports.insert(WasmMethod::CompositeQuery("aggregate".to_string()));
     assert_eq!(
         execution_state.exports,
         ExportedFunctions::new(expected_exports)
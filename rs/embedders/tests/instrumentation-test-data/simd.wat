@@ -0,0 +1,7 @@
+(module
+  (func $simd (param i32 i32) (result i32)
+    (i32x4.extract_lane 0
+      (i32x4.add
+        (i32x4.splat (local.get 0))
+        (i32x4.splat (local.get 1)))))
+  (export "simd" (func $simd)))
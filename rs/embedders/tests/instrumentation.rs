@@ -25,6 +25,7 @@ fn inject_and_cmp(testname: &str, conf: &InstructionCostTable) {
     let content = fs::read_to_string(filename).expect("couldn't read the input file");
     let mut features = Features::new();
     features.enable_bulk_memory();
+    features.enable_simd();
     let buff = wabt::wat2wasm_with_features(content, features.clone())
         .expect("couldn't convert the input wat to Wasm");
     let output =
@@ -44,6 +45,11 @@ fn metering_basic() {
     inject_and_cmp("basic", &InstructionCostTable::new());
 }
 
+#[test]
+fn metering_simd() {
+    inject_and_cmp("simd", &InstructionCostTable::new());
+}
+
 #[test]
 fn metering_basic_import() {
     inject_and_cmp("basic_import", &InstructionCostTable::new());
@@ -1,5 +1,5 @@
 use assert_matches::assert_matches;
-use ic_config::embedders::Config as EmbeddersConfig;
+use ic_config::{embedders::Config as EmbeddersConfig, feature_status::FeatureStatus};
 use ic_embedders::wasm_utils::validation::{
     validate_wasm_binary, WasmImportsDetails, WasmValidationDetails, RESERVED_SYMBOLS,
 };
@@ -8,6 +8,8 @@ use ic_wasm_types::{BinaryEncodedWasm, WasmValidationError};
 fn wat2wasm(wat: &str) -> Result<BinaryEncodedWasm, wabt::Error> {
     let mut features = wabt::Features::new();
     features.enable_multi_value();
+    features.enable_simd();
+    features.enable_bulk_memory();
     wabt::wat2wasm_with_features(wat, features).map(BinaryEncodedWasm::new)
 }
 
@@ -244,6 +246,52 @@ fn can_validate_duplicate_method_for_canister_query_and_canister_update() {
     );
 }
 
+#[test]
+fn can_validate_valid_canister_composite_query() {
+    let wasm = wat2wasm(
+        r#"(module
+                    (func $read)
+                    (export "canister_composite_query read" (func $read)))"#,
+    )
+    .unwrap();
+    assert_eq!(
+        validate_wasm_binary(&wasm, &EmbeddersConfig::default()),
+        Ok(WasmValidationDetails {
+            reserved_exports: 0,
+            imports_details: WasmImportsDetails::default(),
+        })
+    );
+}
+
+#[test]
+fn can_validate_invalid_canister_composite_query() {
+    let wasm = wat2wasm(
+        r#"(module
+                    (func $read (param i64 i32) (result i32) (local.get 1))
+                    (export "canister_composite_query read" (func $read)))"#,
+    )
+    .unwrap();
+    assert_matches!(
+        validate_wasm_binary(&wasm, &EmbeddersConfig::default()),
+        Err(WasmValidationError::InvalidFunctionSignature(_))
+    );
+}
+
+#[test]
+fn can_validate_duplicate_method_for_canister_query_and_canister_composite_query() {
+    let wasm = wat2wasm(
+        r#"(module
+                    (func $read)
+                    (export "canister_query read" (func $read))
+                    (export "canister_composite_query read" (func $read)))"#,
+    )
+    .unwrap();
+    assert_matches!(
+        validate_wasm_binary(&wasm, &EmbeddersConfig::default()),
+        Err(WasmValidationError::InvalidExportSection(_))
+    );
+}
+
 #[test]
 fn can_validate_canister_query_update_method_name_with_whitespace() {
     let wasm = wat2wasm(
@@ -543,3 +591,52 @@ fn can_validate_module_cycles_u128_related_imports() {
         })
     );
 }
+
+const SIMD_MODULE: &str = r#"(module
+    (func (export "canister_query simd") (result i32)
+        (i32x4.extract_lane 0
+            (i32x4.add (i32x4.splat (i32.const 1)) (i32x4.splat (i32.const 2))))))"#;
+
+#[test]
+fn can_validate_simd_only_when_enabled() {
+    let wasm = wat2wasm(SIMD_MODULE).unwrap();
+    assert_matches!(
+        validate_wasm_binary(&wasm, &EmbeddersConfig::default()),
+        Err(WasmValidationError::WasmtimeValidation(_))
+    );
+
+    let mut config = EmbeddersConfig::default();
+    config.feature_flags.wasm_simd = FeatureStatus::Enabled;
+    assert_eq!(
+        validate_wasm_binary(&wasm, &config),
+        Ok(WasmValidationDetails {
+            reserved_exports: 0,
+            imports_details: WasmImportsDetails::default(),
+        })
+    );
+}
+
+#[test]
+fn can_validate_bulk_memory_only_when_enabled() {
+    let wasm = wat2wasm(
+        r#"(module
+            (memory 1)
+            (func (export "canister_update fill")
+                (memory.fill (i32.const 0) (i32.const 42) (i32.const 16))))"#,
+    )
+    .unwrap();
+    assert_matches!(
+        validate_wasm_binary(&wasm, &EmbeddersConfig::default()),
+        Err(WasmValidationError::WasmtimeValidation(_))
+    );
+
+    let mut config = EmbeddersConfig::default();
+    config.feature_flags.wasm_simd = FeatureStatus::Enabled;
+    assert_eq!(
+        validate_wasm_binary(&wasm, &config),
+        Ok(WasmValidationDetails {
+            reserved_exports: 0,
+            imports_details: WasmImportsDetails::default(),
+        })
+    );
+}
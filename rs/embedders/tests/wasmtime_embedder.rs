@@ -12,7 +12,7 @@ use ic_test_utilities::{
 };
 use ic_types::{
     methods::{FuncRef, WasmMethod},
-    ComputeAllocation, NumInstructions,
+    ComputeAllocation, NumInstructions, MAX_STABLE_MEMORY_IN_BYTES,
 };
 use ic_wasm_types::BinaryEncodedWasm;
 use std::sync::Arc;
@@ -21,6 +21,7 @@ fn execution_parameters() -> ExecutionParameters {
     ExecutionParameters {
         instruction_limit: NumInstructions::new(5_000_000_000),
         canister_memory_limit: ic_types::NumBytes::from(4 << 30),
+        max_stable_memory_size: ic_types::NumBytes::new(MAX_STABLE_MEMORY_IN_BYTES),
         subnet_available_memory: SubnetAvailableMemory::new(i64::MAX / 2),
         compute_allocation: ComputeAllocation::default(),
         subnet_type: SubnetType::Application,
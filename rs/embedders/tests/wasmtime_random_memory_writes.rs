@@ -18,6 +18,7 @@ use ic_test_utilities::{
 use ic_types::{
     methods::{FuncRef, WasmMethod},
     CanisterId, ComputeAllocation, Cycles, NumBytes, NumInstructions, PrincipalId,
+    MAX_STABLE_MEMORY_IN_BYTES,
 };
 use ic_wasm_types::BinaryEncodedWasm;
 use lazy_static::lazy_static;
@@ -83,6 +84,7 @@ fn test_api_for_update(
         ExecutionParameters {
             instruction_limit: MAX_NUM_INSTRUCTIONS,
             canister_memory_limit,
+            max_stable_memory_size: NumBytes::new(MAX_STABLE_MEMORY_IN_BYTES),
             subnet_available_memory: MAX_SUBNET_AVAILABLE_MEMORY.clone(),
             compute_allocation: ComputeAllocation::default(),
             subnet_type: SubnetType::Application,
@@ -18,7 +18,7 @@ use ic_test_utilities::{
 };
 use ic_types::{
     CanisterId, Cycles, MemoryAllocation, NumBytes, NumInstructions, PrincipalId, SubnetId, Time,
-    UserId,
+    UserId, MAX_STABLE_MEMORY_IN_BYTES,
 };
 use lazy_static::lazy_static;
 use maplit::btreemap;
@@ -104,6 +104,7 @@ where
     let execution_parameters = ExecutionParameters {
         instruction_limit: MAX_NUM_INSTRUCTIONS,
         canister_memory_limit: canister_state.memory_limit(NumBytes::new(std::u64::MAX)),
+        max_stable_memory_size: NumBytes::new(MAX_STABLE_MEMORY_IN_BYTES),
         subnet_available_memory: MAX_SUBNET_AVAILABLE_MEMORY.clone(),
         compute_allocation: canister_state.scheduler_state.compute_allocation,
         subnet_type: SubnetType::Application,
@@ -6,20 +6,24 @@ use crate::{
 };
 use candid::Decode;
 use ic_base_types::NumSeconds;
+use ic_config::feature_status::FeatureStatus;
 use ic_cow_state::CowMemoryManager;
-use ic_cycles_account_manager::CyclesAccountManager;
+use ic_cycles_account_manager::{CyclesAccountManager, ReservationError};
 use ic_ic00_types::{
-    CanisterIdRecord, CanisterStatusResultV2, InstallCodeArgs, Method as Ic00Method,
-    SetControllerArgs, UpdateSettingsArgs,
+    CanisterIdRecord, CanisterSnapshotArgs, CanisterSnapshotResponse, CanisterStatusResultV2,
+    InstallCodeArgs, Method as Ic00Method, SetControllerArgs, TakeCanisterSnapshotArgs,
+    UpdateSettingsArgs,
 };
 use ic_interfaces::execution_environment::{
     CanisterOutOfCyclesError, ExecutionParameters, HypervisorError, IngressHistoryWriter,
+    OutOfInstructionsHandler,
 };
 use ic_logger::{error, fatal, info, ReplicaLogger};
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
-    CallOrigin, CanisterState, CanisterStatus, Memory, ReplicatedState, SchedulerState, SystemState,
+    CallOrigin, CanisterSnapshot, CanisterState, CanisterStatus, CanisterTimer, LogVisibility,
+    Memory, ReplicatedState, SchedulerState, SnapshotId, SystemState, MAX_SNAPSHOTS_PER_CANISTER,
 };
 use ic_state_layout::{CanisterLayout, CheckpointLayout, RwPolicy};
 use ic_types::{
@@ -63,10 +67,13 @@ pub(crate) enum StopCanisterResult {
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
 pub(crate) struct CanisterMgrConfig {
     pub(crate) subnet_memory_capacity: NumBytes,
+    pub(crate) subnet_memory_reservation_threshold: NumBytes,
     pub(crate) max_cycles_per_canister: Option<Cycles>,
     pub(crate) default_provisional_cycles_balance: Cycles,
     pub(crate) default_freeze_threshold: NumSeconds,
     pub(crate) compute_capacity: u64,
+    pub(crate) compute_reservation_threshold: u64,
+    pub(crate) default_reserved_balance_limit: Cycles,
     pub(crate) own_subnet_id: SubnetId,
     pub(crate) own_subnet_type: SubnetType,
     pub(crate) max_controllers: usize,
@@ -83,21 +90,33 @@ impl CanisterMgrConfig {
         own_subnet_type: SubnetType,
         max_controllers: usize,
         num_cores: usize,
+        subnet_reservation_threshold_percent: u64,
+        default_reserved_balance_limit: Cycles,
     ) -> Self {
+        let compute_capacity = 100 * num_cores as u64;
         Self {
             subnet_memory_capacity,
+            subnet_memory_reservation_threshold: NumBytes::new(
+                (subnet_memory_capacity.get() as u128
+                    * subnet_reservation_threshold_percent as u128
+                    / 100) as u64,
+            ),
             max_cycles_per_canister,
             default_provisional_cycles_balance,
             default_freeze_threshold,
             own_subnet_id,
             own_subnet_type,
             max_controllers,
-            compute_capacity: 100 * num_cores as u64,
+            compute_capacity,
+            compute_reservation_threshold: compute_capacity * subnet_reservation_threshold_percent
+                / 100,
+            default_reserved_balance_limit,
         }
     }
 }
 
 /// The entity responsible for managing canisters (creation, installing, etc.)
+#[derive(Clone)]
 pub(crate) struct CanisterManager {
     hypervisor: Arc<Hypervisor>,
     log: ReplicaLogger,
@@ -123,6 +142,21 @@ impl CanisterManager {
         }
     }
 
+    /// Returns a copy of the canister manager that runs all executions with
+    /// the given `OutOfInstructionsHandler`.
+    pub(crate) fn with_out_of_instructions_handler(
+        &self,
+        out_of_instructions_handler: Arc<dyn OutOfInstructionsHandler>,
+    ) -> Self {
+        Self {
+            hypervisor: Arc::new(
+                self.hypervisor
+                    .with_out_of_instructions_handler(out_of_instructions_handler),
+            ),
+            ..self.clone()
+        }
+    }
+
     /// Checks if a given ingress message directed to the management canister
     /// should be accepted or not.
     pub(crate) fn should_accept_ingress_message(
@@ -167,6 +201,7 @@ impl CanisterManager {
             | Ok(Ic00Method::SignWithECDSA)
             | Ok(Ic00Method::GetMockECDSAPublicKey)
             | Ok(Ic00Method::SignWithMockECDSA)
+            | Ok(Ic00Method::HttpRequest)
             // "DepositCycles" can be called by anyone however as ingress message
             // cannot carry cycles, it does not make sense to allow them from users.
             | Ok(Ic00Method::DepositCycles) => rejected_canister_err,
@@ -178,7 +213,8 @@ impl CanisterManager {
             | Ok(Ic00Method::StartCanister)
             | Ok(Ic00Method::UninstallCode)
             | Ok(Ic00Method::StopCanister)
-            | Ok(Ic00Method::DeleteCanister) => match Decode!(payload, CanisterIdRecord) {
+            | Ok(Ic00Method::DeleteCanister)
+            | Ok(Ic00Method::ListCanisterSnapshots) => match Decode!(payload, CanisterIdRecord) {
                 Err(_) => rejected_canister_err,
                 Ok(args) => is_sender_controller(args.get_canister_id(), sender, state),
             },
@@ -194,10 +230,25 @@ impl CanisterManager {
                 Err(_) => rejected_canister_err,
                 Ok(args) => is_sender_controller(args.get_canister_id(), sender, state),
             },
+            Ok(Ic00Method::TakeCanisterSnapshot) => {
+                match Decode!(payload, TakeCanisterSnapshotArgs) {
+                    Err(_) => rejected_canister_err,
+                    Ok(args) => is_sender_controller(args.get_canister_id(), sender, state),
+                }
+            }
+            Ok(Ic00Method::LoadCanisterSnapshot) | Ok(Ic00Method::DeleteCanisterSnapshot) => {
+                match Decode!(payload, CanisterSnapshotArgs) {
+                    Err(_) => rejected_canister_err,
+                    Ok(args) => is_sender_controller(args.get_canister_id(), sender, state),
+                }
+            }
 
             // Nobody pays for `raw_rand`, so this cannot be used via ingress messages
             Ok(Ic00Method::RawRand) => rejected_canister_err,
 
+            // Canister logs can only be fetched via query calls.
+            Ok(Ic00Method::FetchCanisterLogs) => rejected_canister_err,
+
             Ok(Ic00Method::ProvisionalCreateCanisterWithCycles)
             | Ok(Ic00Method::ProvisionalTopUpCanister) => {
                 if provisional_whitelist.contains(sender.get_ref()) {
@@ -288,6 +339,105 @@ impl CanisterManager {
         if let Some(freezing_threshold) = settings.freezing_threshold {
             canister.system_state.freeze_threshold = freezing_threshold;
         }
+        if let Some(log_visibility) = settings.log_visibility {
+            canister.system_state.log_visibility = log_visibility;
+        }
+        if let Some(reserved_cycles_limit) = settings.reserved_cycles_limit {
+            canister.system_state.reserved_balance_limit = Some(reserved_cycles_limit);
+        }
+    }
+
+    /// Returns the maximum amount of cycles the canister can have in its
+    /// reserved balance.
+    fn reserved_balance_limit(&self, canister: &CanisterState) -> Cycles {
+        canister
+            .system_state
+            .reserved_balance_limit
+            .unwrap_or(self.config.default_reserved_balance_limit)
+    }
+
+    /// Moves cycles from the main balance of the canister to its reserved
+    /// balance to pay for the increase of its memory and compute allocations
+    /// requested in `settings`. Cycles are only reserved for the part of an
+    /// increase that happens while the subnet is above its reservation
+    /// threshold, so canisters on subnets with spare capacity are unaffected.
+    ///
+    /// Note: Called only after validating the settings and before applying
+    /// them.
+    fn reserve_cycles_for_allocations(
+        &self,
+        settings: &ValidatedCanisterSettings,
+        canister: &mut CanisterState,
+        total_subnet_compute_allocation_used: u64,
+        total_subnet_memory_taken: NumBytes,
+    ) -> Result<(), CanisterManagerError> {
+        let limit = match settings.reserved_cycles_limit {
+            Some(limit) => {
+                if limit < canister.system_state.reserved_balance {
+                    return Err(CanisterManagerError::ReservedCyclesLimitIsTooLow {
+                        canister_id: canister.canister_id(),
+                        reserved_balance: canister.system_state.reserved_balance,
+                        limit,
+                    });
+                }
+                limit
+            }
+            None => self.reserved_balance_limit(canister),
+        };
+
+        // Only the part of an increase that ends up above the threshold is
+        // paid up front.
+        let memory_increase = match settings.memory_allocation {
+            Some(MemoryAllocation::Reserved(requested)) => {
+                let current = match canister.memory_allocation() {
+                    MemoryAllocation::Reserved(bytes) => bytes,
+                    MemoryAllocation::BestEffort => {
+                        canister.memory_usage(self.config.own_subnet_type)
+                    }
+                };
+                let increase = requested.get().saturating_sub(current.get());
+                (total_subnet_memory_taken.get() + increase)
+                    .saturating_sub(self.config.subnet_memory_reservation_threshold.get())
+                    .min(increase)
+            }
+            Some(MemoryAllocation::BestEffort) | None => 0,
+        };
+        let compute_increase = match settings.compute_allocation {
+            Some(requested) => {
+                let increase = requested
+                    .as_percent()
+                    .saturating_sub(canister.scheduler_state.compute_allocation.as_percent());
+                (total_subnet_compute_allocation_used + increase)
+                    .saturating_sub(self.config.compute_reservation_threshold)
+                    .min(increase)
+            }
+            None => 0,
+        };
+
+        let cycles = self.cycles_account_manager.allocation_reservation_cycles(
+            NumBytes::from(memory_increase),
+            ComputeAllocation::try_from(compute_increase)
+                .expect("An increase of a compute allocation is a valid compute allocation."),
+        );
+        self.cycles_account_manager
+            .reserve_cycles(&mut canister.system_state, cycles, limit)
+            .map_err(|err| match err {
+                ReservationError::InsufficientCycles {
+                    requested,
+                    available,
+                } => CanisterManagerError::InsufficientCyclesInAllocationReservation {
+                    canister_id: canister.canister_id(),
+                    requested,
+                    available,
+                },
+                ReservationError::ReservedLimitExceeded { requested, limit } => {
+                    CanisterManagerError::ReservedCyclesLimitExceeded {
+                        canister_id: canister.canister_id(),
+                        requested,
+                        limit,
+                    }
+                }
+            })
     }
 
     /// Tries to apply the requested settings on the canister identified by
@@ -315,6 +465,12 @@ impl CanisterManager {
 
         let validated_settings =
             ValidatedCanisterSettings::try_from((settings, self.config.max_controllers))?;
+        self.reserve_cycles_for_allocations(
+            &validated_settings,
+            canister,
+            total_subnet_compute_allocation_used,
+            total_subnet_memory_taken,
+        )?;
         self.do_update_settings(validated_settings, canister);
 
         Ok(())
@@ -402,52 +558,75 @@ impl CanisterManager {
         &self,
         context: InstallCodeContext,
         state: &mut ReplicatedState,
-        mut execution_parameters: ExecutionParameters,
+        execution_parameters: ExecutionParameters,
     ) -> (
         NumInstructions,
         Result<InstallCodeResult, CanisterManagerError>,
     ) {
+        let instruction_limit = execution_parameters.instruction_limit;
+        let execution_parameters =
+            match self.prepare_install_code(&context, state, execution_parameters) {
+                Ok(execution_parameters) => execution_parameters,
+                Err(err) => return (instruction_limit, Err(err)),
+            };
+
+        // Copy bits out of context as the calls below are going to consume it.
+        let canister_id = context.canister_id;
+        let mode = context.mode;
+
+        let old_canister = state
+            .canister_state(&canister_id)
+            .expect("The canister was found in `prepare_install_code`.");
+        let (instructions_left, result) = self.install_or_upgrade(
+            context,
+            old_canister,
+            state.time(),
+            state.path().to_path_buf(),
+            execution_parameters,
+            state.metadata.own_subnet_features.wasm_simd.into(),
+        );
+
+        let result = self.finish_install_code(canister_id, mode, state, instructions_left, result);
+        (instructions_left, result)
+    }
+
+    /// Validates an `install_code` request and reserves cycles on the canister
+    /// for executing the various hooks such as `start`, `pre_upgrade`,
+    /// `post_upgrade`.
+    ///
+    /// Returns the execution parameters that should be used for installing the
+    /// code. In case of an error, the state is unmodified.
+    pub(crate) fn prepare_install_code(
+        &self,
+        context: &InstallCodeContext,
+        state: &mut ReplicatedState,
+        execution_parameters: ExecutionParameters,
+    ) -> Result<ExecutionParameters, CanisterManagerError> {
         // Copy necessary bits out of the `ReplicatedState`. This is because further
         // below, we take a mutable reference to the old canister state while it
         // is held inside state. Then Rust's borrow checker prevents us from
         // calling further methods on the state.
-        let time = state.time();
-        let canister_layout_path = state.path().to_path_buf();
         let compute_allocation_used = state.total_compute_allocation();
         let memory_taken = state.total_memory_taken();
 
         // Perform a battery of validation checks.
         let old_canister = match state.canister_state_mut(&context.canister_id) {
             None => {
-                return (
-                    execution_parameters.instruction_limit,
-                    Err(CanisterManagerError::CanisterNotFound(context.canister_id)),
-                );
+                return Err(CanisterManagerError::CanisterNotFound(context.canister_id));
             }
             Some(canister) => canister,
         };
-        if let Err(err) = self.validate_compute_allocation(
+        self.validate_compute_allocation(
             compute_allocation_used,
             old_canister,
             context.compute_allocation,
-        ) {
-            return (execution_parameters.instruction_limit, Err(err));
-        }
-        if let Err(err) =
-            self.validate_memory_allocation(memory_taken, old_canister, context.memory_allocation)
-        {
-            return (execution_parameters.instruction_limit, Err(err));
-        }
-        if let Err(err) = self.validate_controller(old_canister, &context.sender) {
-            return (execution_parameters.instruction_limit, Err(err));
-        }
+        )?;
+        self.validate_memory_allocation(memory_taken, old_canister, context.memory_allocation)?;
+        self.validate_controller(old_canister, &context.sender)?;
         match context.mode {
             CanisterInstallMode::Install => {
                 if old_canister.execution_state.is_some() {
-                    return (
-                        execution_parameters.instruction_limit,
-                        Err(CanisterManagerError::CanisterNonEmpty(context.canister_id)),
-                    );
+                    return Err(CanisterManagerError::CanisterNonEmpty(context.canister_id));
                 }
             }
             CanisterInstallMode::Reinstall | CanisterInstallMode::Upgrade => {}
@@ -457,35 +636,60 @@ impl CanisterManager {
         // for executing the various hooks such as `start`, `pre_upgrade`,
         // `post_upgrade`.
         let memory_usage = old_canister.memory_usage(self.config.own_subnet_type);
-        let compute_allocation = old_canister.scheduler_state.compute_allocation;
-        if let MemoryAllocation::Reserved(bytes) = old_canister.memory_allocation() {
-            execution_parameters.canister_memory_limit = bytes;
-        }
-        execution_parameters.compute_allocation = compute_allocation;
+        let execution_parameters =
+            self.install_code_execution_parameters(old_canister, execution_parameters);
 
-        if let Err(err) = self.cycles_account_manager.withdraw_execution_cycles(
-            &mut old_canister.system_state,
-            memory_usage,
-            compute_allocation,
-            execution_parameters.instruction_limit,
-        ) {
-            return (
+        self.cycles_account_manager
+            .withdraw_execution_cycles(
+                &mut old_canister.system_state,
+                memory_usage,
+                execution_parameters.compute_allocation,
                 execution_parameters.instruction_limit,
-                Err(CanisterManagerError::InstallCodeNotEnoughCycles(err)),
-            );
-        }
+            )
+            .map_err(CanisterManagerError::InstallCodeNotEnoughCycles)?;
 
-        // Copy bits out of context as the calls below are going to consume it.
-        let canister_id = context.canister_id;
-        let mode = context.mode;
+        Ok(execution_parameters)
+    }
 
-        let (instructions_left, result) = match context.mode {
+    /// Returns the execution parameters of an `install_code` message for the
+    /// given canister, i.e. applies its memory and compute allocation to the
+    /// given parameters.
+    pub(crate) fn install_code_execution_parameters(
+        &self,
+        old_canister: &CanisterState,
+        mut execution_parameters: ExecutionParameters,
+    ) -> ExecutionParameters {
+        if let MemoryAllocation::Reserved(bytes) = old_canister.memory_allocation() {
+            execution_parameters.canister_memory_limit = bytes;
+        }
+        execution_parameters.compute_allocation = old_canister.scheduler_state.compute_allocation;
+        execution_parameters
+    }
+
+    /// Installs or upgrades the code of the given canister depending on the
+    /// mode in `context`. Returns the new state of the canister without
+    /// modifying the old one. The new code may use SIMD instructions only if
+    /// `wasm_simd` is enabled.
+    pub(crate) fn install_or_upgrade(
+        &self,
+        context: InstallCodeContext,
+        old_canister: &CanisterState,
+        time: Time,
+        canister_layout_path: PathBuf,
+        execution_parameters: ExecutionParameters,
+        wasm_simd: FeatureStatus,
+    ) -> (
+        NumInstructions,
+        Result<(NumBytes, CanisterState), CanisterManagerError>,
+    ) {
+        match context.mode {
             CanisterInstallMode::Install | CanisterInstallMode::Reinstall => self.install(
                 context,
                 old_canister,
                 time,
                 canister_layout_path,
                 execution_parameters,
+                wasm_simd,
             ),
             CanisterInstallMode::Upgrade => self.upgrade(
                 context,
@@ -493,10 +697,26 @@ impl CanisterManager {
                 time,
                 canister_layout_path,
                 execution_parameters,
+                wasm_simd,
             ),
-        };
+        }
+    }
 
-        let result = match result {
+    /// Applies the result of `install_or_upgrade` to the state: on success the
+    /// old canister is replaced with the new one, otherwise the old canister
+    /// is kept. In both cases the left over execution cycles are refunded.
+    pub(crate) fn finish_install_code(
+        &self,
+        canister_id: CanisterId,
+        mode: CanisterInstallMode,
+        state: &mut ReplicatedState,
+        instructions_left: NumInstructions,
+        result: Result<(NumBytes, CanisterState), CanisterManagerError>,
+    ) -> Result<InstallCodeResult, CanisterManagerError> {
+        let old_canister = state
+            .canister_state_mut(&canister_id)
+            .expect("The canister of an install_code message must exist.");
+        match result {
             Ok((heap_delta, mut new_canister)) => {
                 // Refund the left over execution cycles to the new canister and
                 // replace the old canister with the new one.
@@ -529,8 +749,7 @@ impl CanisterManager {
                     .refund_execution_cycles(&mut old_canister.system_state, instructions_left);
                 Err(err)
             }
-        };
-        (instructions_left, result)
+        }
     }
 
     /// Uninstalls code from a canister.
@@ -568,6 +787,193 @@ impl CanisterManager {
         Ok(())
     }
 
+    /// Takes a snapshot of the Wasm module, the memories and the certified
+    /// data of a canister. If `replace_snapshot` is given, the new snapshot
+    /// replaces that existing snapshot of the canister.
+    ///
+    /// The snapshot counts towards the memory usage of the canister, so it
+    /// must fit into the memory allocation of the canister or, if the canister
+    /// has no memory allocation, into the remaining memory capacity of the
+    /// subnet.
+    pub(crate) fn take_canister_snapshot(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        replace_snapshot: Option<Vec<u8>>,
+        state: &mut ReplicatedState,
+    ) -> Result<CanisterSnapshotResponse, CanisterManagerError> {
+        let time = state.time();
+        let path = state.path().to_owned();
+        let memory_taken = state.total_memory_taken();
+        let canister = state
+            .canister_state_mut(&canister_id)
+            .ok_or(CanisterManagerError::CanisterNotFound(canister_id))?;
+        self.validate_controller(canister, &sender)?;
+
+        let replaced_snapshot_id = match replace_snapshot {
+            Some(snapshot_id) => Some(self.validate_snapshot_exists(canister, &snapshot_id)?),
+            None => {
+                if canister.system_state.snapshots.len() >= MAX_SNAPSHOTS_PER_CANISTER {
+                    return Err(CanisterManagerError::CanisterSnapshotLimitExceeded {
+                        canister_id,
+                        limit: MAX_SNAPSHOTS_PER_CANISTER,
+                    });
+                }
+                None
+            }
+        };
+
+        let execution_state = canister
+            .execution_state
+            .as_ref()
+            .ok_or((canister_id, HypervisorError::WasmModuleNotFound))?;
+        let snapshot = CanisterSnapshot::from_execution_state(
+            execution_state,
+            canister.system_state.certified_data.clone(),
+            time,
+        );
+        let replaced_snapshot_size = replaced_snapshot_id
+            .and_then(|snapshot_id| canister.system_state.snapshots.get(snapshot_id))
+            .map_or(NumBytes::from(0), |snapshot| snapshot.size());
+        self.validate_memory_growth(
+            memory_taken,
+            canister,
+            snapshot.size(),
+            replaced_snapshot_size,
+        )?;
+
+        if let Some(snapshot_id) = replaced_snapshot_id {
+            self.remove_snapshot(&path, canister, snapshot_id)?;
+        }
+        let total_size = snapshot.size().get();
+        let snapshot_id = canister.system_state.snapshots.push(snapshot);
+        Ok(CanisterSnapshotResponse {
+            id: encode_snapshot_id(snapshot_id),
+            taken_at_timestamp: time.as_nanos_since_unix_epoch(),
+            total_size,
+        })
+    }
+
+    /// Restores the Wasm module, the memories and the certified data of a
+    /// canister from one of its snapshots. The snapshot itself is kept.
+    ///
+    /// The canister must be stopped, so that no call context of the canister
+    /// expects a different state when its responses arrive.
+    pub(crate) fn load_canister_snapshot(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        snapshot_id: Vec<u8>,
+        state: &mut ReplicatedState,
+    ) -> Result<(), CanisterManagerError> {
+        let path = state.path().to_owned();
+        let memory_taken = state.total_memory_taken();
+        let wasm_simd = state.metadata.own_subnet_features.wasm_simd.into();
+        let canister = state
+            .canister_state_mut(&canister_id)
+            .ok_or(CanisterManagerError::CanisterNotFound(canister_id))?;
+        self.validate_controller(canister, &sender)?;
+        if canister.status() != CanisterStatusType::Stopped {
+            return Err(CanisterManagerError::LoadCanisterSnapshotNotStopped(
+                canister_id,
+            ));
+        }
+        let snapshot_id = self.validate_snapshot_exists(canister, &snapshot_id)?;
+        let snapshot = canister.system_state.snapshots.get(snapshot_id).unwrap();
+
+        let layout = canister_layout(&path, &canister_id);
+        let mut execution_state = self
+            .hypervisor
+            .create_execution_state(
+                snapshot.wasm_binary.as_slice().to_vec(),
+                layout.raw_path(),
+                canister_id,
+                wasm_simd,
+            )
+            .map_err(|err| (canister_id, err))?;
+        // The memories of the canister are persisted independently of the
+        // snapshot, so they must not share any files with it.
+        execution_state.wasm_memory = snapshot.wasm_memory.detached_copy();
+        execution_state.stable_memory = snapshot.stable_memory.detached_copy();
+        execution_state.exported_globals = snapshot.exported_globals.clone();
+        let certified_data = snapshot.certified_data.clone();
+
+        let current_size = canister
+            .execution_state
+            .as_ref()
+            .map_or(NumBytes::from(0), |es| es.memory_usage());
+        self.validate_memory_growth(
+            memory_taken,
+            canister,
+            execution_state.memory_usage(),
+            current_size,
+        )?;
+
+        truncate_canister_heap(&self.log, &path, canister_id);
+        truncate_canister_stable_memory(&self.log, &path, canister_id);
+        canister.execution_state = Some(execution_state);
+        canister.system_state.certified_data = certified_data;
+        Ok(())
+    }
+
+    /// Lists the snapshots of a canister, from the oldest to the newest.
+    pub(crate) fn list_canister_snapshots(
+        &self,
+        sender: PrincipalId,
+        canister: &CanisterState,
+    ) -> Result<Vec<CanisterSnapshotResponse>, CanisterManagerError> {
+        self.validate_controller(canister, &sender)?;
+        Ok(canister
+            .system_state
+            .snapshots
+            .iter()
+            .map(|(snapshot_id, snapshot)| CanisterSnapshotResponse {
+                id: encode_snapshot_id(*snapshot_id),
+                taken_at_timestamp: snapshot.taken_at_timestamp.as_nanos_since_unix_epoch(),
+                total_size: snapshot.size().get(),
+            })
+            .collect())
+    }
+
+    /// Deletes a snapshot of a canister and releases the memory it takes.
+    pub(crate) fn delete_canister_snapshot(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        snapshot_id: Vec<u8>,
+        state: &mut ReplicatedState,
+    ) -> Result<(), CanisterManagerError> {
+        let path = state.path().to_owned();
+        let canister = state
+            .canister_state_mut(&canister_id)
+            .ok_or(CanisterManagerError::CanisterNotFound(canister_id))?;
+        self.validate_controller(canister, &sender)?;
+        let snapshot_id = self.validate_snapshot_exists(canister, &snapshot_id)?;
+        self.remove_snapshot(&path, canister, snapshot_id)
+    }
+
+    // Marks the files of the snapshot as deleted, so that they are not carried
+    // over to the next checkpoint, and removes the snapshot from the canister.
+    // The canister is left unmodified if the files cannot be marked.
+    fn remove_snapshot(
+        &self,
+        state_path: &Path,
+        canister: &mut CanisterState,
+        snapshot_id: SnapshotId,
+    ) -> Result<(), CanisterManagerError> {
+        let canister_id = canister.canister_id();
+        canister_layout(state_path, &canister_id)
+            .snapshot(snapshot_id)
+            .and_then(|layout| layout.mark_deleted())
+            .map_err(|err| CanisterManagerError::CanisterSnapshotNotDeleted {
+                canister_id,
+                snapshot_id: encode_snapshot_id(snapshot_id),
+                message: err.to_string(),
+            })?;
+        canister.system_state.snapshots.remove(snapshot_id);
+        Ok(())
+    }
+
     /// Signals a canister to stop.
     ///
     /// If the canister is running, then the canister is marked as "stopping".
@@ -707,6 +1113,8 @@ impl CanisterManager {
             canister.scheduler_state.compute_allocation.as_percent(),
             Some(canister.memory_allocation().bytes().get()),
             canister.system_state.freeze_threshold.get(),
+            canister.system_state.reserved_balance.get(),
+            self.reserved_balance_limit(canister).get(),
         ))
     }
 
@@ -725,7 +1133,8 @@ impl CanisterManager {
             .canister_state_mut(&canister_id)
             .ok_or(CanisterManagerError::CanisterNotFound(canister_id))?;
 
-        let settings = CanisterSettings::new(Some(new_controller), None, None, None, None);
+        let settings =
+            CanisterSettings::new(Some(new_controller), None, None, None, None, None, None);
         self.update_settings(
             sender,
             settings,
@@ -833,6 +1242,7 @@ impl CanisterManager {
         time: Time,
         canister_layout_path: PathBuf,
         mut execution_parameters: ExecutionParameters,
+        wasm_simd: FeatureStatus,
     ) -> (
         NumInstructions,
         Result<(NumBytes, CanisterState), CanisterManagerError>,
@@ -840,11 +1250,15 @@ impl CanisterManager {
         let canister_id = context.canister_id;
         let layout = canister_layout(&canister_layout_path, &canister_id);
 
-        let system_state = old_canister.system_state.clone();
+        let mut system_state = old_canister.system_state.clone();
+        // A fresh install starts without a global timer. Note that upgrades
+        // keep the global timer of the canister.
+        system_state.global_timer = CanisterTimer::Inactive;
         let execution_state = match self.hypervisor.create_execution_state(
             context.wasm_module,
             layout.raw_path(),
             canister_id,
+            wasm_simd,
         ) {
             Ok(execution_state) => Some(execution_state),
             Err(err) => {
@@ -941,6 +1355,7 @@ impl CanisterManager {
         time: Time,
         canister_layout_path: PathBuf,
         mut execution_parameters: ExecutionParameters,
+        wasm_simd: FeatureStatus,
     ) -> (
         NumInstructions,
         Result<(NumBytes, CanisterState), CanisterManagerError>,
@@ -987,6 +1402,7 @@ impl CanisterManager {
             context.wasm_module,
             layout.raw_path(),
             canister_id,
+            wasm_simd,
         ) {
             Err(err) => return (instructions_limit, Err((canister_id, err).into())),
             Ok(mut execution_state) => {
@@ -1156,6 +1572,14 @@ impl CanisterManager {
         let scheduler_state = SchedulerState::default();
         let mut new_canister = CanisterState::new(system_state, None, scheduler_state);
 
+        // The allocations of a new canister are paid out of its initial
+        // balance if the subnet is close to its capacity.
+        self.reserve_cycles_for_allocations(
+            &settings,
+            &mut new_canister,
+            state.total_compute_allocation(),
+            state.total_memory_taken(),
+        )?;
         self.do_update_settings(settings, &mut new_canister);
 
         // Add new canister to the replicated state.
@@ -1285,6 +1709,63 @@ impl CanisterManager {
         Ok(())
     }
 
+    // Ensures that the canister can take `new_bytes` more memory after
+    // releasing `freed_bytes`: the new memory usage must fit into the memory
+    // allocation of the canister or, if it has no memory allocation, the
+    // additional memory must fit into the remaining capacity of the subnet.
+    fn validate_memory_growth(
+        &self,
+        total_subnet_memory_taken: NumBytes,
+        canister: &CanisterState,
+        new_bytes: NumBytes,
+        freed_bytes: NumBytes,
+    ) -> Result<(), CanisterManagerError> {
+        let memory_usage_needed = NumBytes::from(
+            (canister.memory_usage(self.config.own_subnet_type).get() + new_bytes.get())
+                .saturating_sub(freed_bytes.get()),
+        );
+        match canister.memory_allocation() {
+            MemoryAllocation::Reserved(bytes) => {
+                if memory_usage_needed > bytes {
+                    return Err(CanisterManagerError::NotEnoughMemoryAllocationGiven {
+                        canister_id: canister.canister_id(),
+                        memory_allocation_given: canister.memory_allocation(),
+                        memory_usage_needed,
+                    });
+                }
+            }
+            MemoryAllocation::BestEffort => {
+                let requested = NumBytes::from(new_bytes.get().saturating_sub(freed_bytes.get()));
+                let available = NumBytes::from(
+                    self.config
+                        .subnet_memory_capacity
+                        .get()
+                        .saturating_sub(total_subnet_memory_taken.get()),
+                );
+                if requested > available {
+                    return Err(CanisterManagerError::SubnetMemoryCapacityOverSubscribed {
+                        requested,
+                        available,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_snapshot_exists(
+        &self,
+        canister: &CanisterState,
+        snapshot_id: &[u8],
+    ) -> Result<SnapshotId, CanisterManagerError> {
+        decode_snapshot_id(snapshot_id)
+            .filter(|id| canister.system_state.snapshots.get(*id).is_some())
+            .ok_or_else(|| CanisterManagerError::CanisterSnapshotNotFound {
+                canister_id: canister.canister_id(),
+                snapshot_id: snapshot_id.to_vec(),
+            })
+    }
+
     fn validate_canister_is_stopped(
         &self,
         canister: &CanisterState,
@@ -1342,6 +1823,19 @@ impl CanisterManager {
             .map(|execution_state| execution_state.wasm_binary.binary.hash_sha256())
     }
 }
+
+// Snapshot ids are exposed to users as the big-endian encoding of the
+// per-canister snapshot counter.
+fn encode_snapshot_id(snapshot_id: SnapshotId) -> Vec<u8> {
+    snapshot_id.to_be_bytes().to_vec()
+}
+
+fn decode_snapshot_id(bytes: &[u8]) -> Option<SnapshotId> {
+    <[u8; 8]>::try_from(bytes)
+        .ok()
+        .map(SnapshotId::from_be_bytes)
+}
+
 #[doc(hidden)] // pub for usage in tests
 pub(crate) fn canister_layout(
     state_path: &Path,
@@ -1396,6 +1890,35 @@ pub(crate) enum CanisterManagerError {
         subnet_id: SubnetId,
         max_number_of_canisters: u64,
     },
+    CanisterSnapshotNotFound {
+        canister_id: CanisterId,
+        snapshot_id: Vec<u8>,
+    },
+    CanisterSnapshotLimitExceeded {
+        canister_id: CanisterId,
+        limit: usize,
+    },
+    CanisterSnapshotNotDeleted {
+        canister_id: CanisterId,
+        snapshot_id: Vec<u8>,
+        message: String,
+    },
+    LoadCanisterSnapshotNotStopped(CanisterId),
+    InsufficientCyclesInAllocationReservation {
+        canister_id: CanisterId,
+        requested: Cycles,
+        available: Cycles,
+    },
+    ReservedCyclesLimitExceeded {
+        canister_id: CanisterId,
+        requested: Cycles,
+        limit: Cycles,
+    },
+    ReservedCyclesLimitIsTooLow {
+        canister_id: CanisterId,
+        reserved_balance: Cycles,
+        limit: Cycles,
+    },
 }
 
 impl From<CanisterManagerError> for UserError {
@@ -1533,6 +2056,72 @@ impl From<CanisterManagerError> for UserError {
                     format!("Subnet {} has reached the allowed canister limit of {} canisters. Retry creating the canister.", subnet_id, max_number_of_canisters),
                 )
             }
+            CanisterSnapshotNotFound { canister_id, snapshot_id } => {
+                Self::new(
+                    ErrorCode::CanisterSnapshotNotFound,
+                    format!(
+                        "Could not find the snapshot {} of canister {}.",
+                        hex::encode(snapshot_id),
+                        canister_id,
+                    ),
+                )
+            }
+            CanisterSnapshotLimitExceeded { canister_id, limit } => {
+                Self::new(
+                    ErrorCode::CanisterContractViolation,
+                    format!(
+                        "Canister {} already has the maximum of {} snapshots. Replace or delete an existing snapshot first.",
+                        canister_id, limit,
+                    ),
+                )
+            }
+            CanisterSnapshotNotDeleted { canister_id, snapshot_id, message } => {
+                Self::new(
+                    ErrorCode::CanisterContractViolation,
+                    format!(
+                        "Could not delete the snapshot {} of canister {}: {}",
+                        hex::encode(snapshot_id),
+                        canister_id,
+                        message,
+                    ),
+                )
+            }
+            LoadCanisterSnapshotNotStopped(canister_id) => {
+                Self::new(
+                    ErrorCode::CanisterNotStopped,
+                    format!(
+                        "Canister {} must be stopped before a snapshot is loaded.",
+                        canister_id,
+                    ),
+                )
+            }
+            InsufficientCyclesInAllocationReservation { canister_id, requested, available } => {
+                Self::new(
+                    ErrorCode::InsufficientCyclesInAllocationReservation,
+                    format!(
+                        "Canister {} cannot increase its allocations because the subnet is close to its capacity and {} cycles need to be reserved up front, but only {} cycles are available.",
+                        canister_id, requested, available,
+                    ),
+                )
+            }
+            ReservedCyclesLimitExceeded { canister_id, requested, limit } => {
+                Self::new(
+                    ErrorCode::ReservedCyclesLimitExceeded,
+                    format!(
+                        "Canister {} cannot increase its allocations because its reserved balance would grow to {} cycles, which exceeds its reserved cycles limit of {} cycles.",
+                        canister_id, requested, limit,
+                    ),
+                )
+            }
+            ReservedCyclesLimitIsTooLow { canister_id, reserved_balance, limit } => {
+                Self::new(
+                    ErrorCode::ReservedCyclesLimitIsTooLow,
+                    format!(
+                        "Canister {} has {} reserved cycles, which is more than the requested reserved cycles limit of {} cycles.",
+                        canister_id, reserved_balance, limit,
+                    ),
+                )
+            }
         }
     }
 }
@@ -1610,6 +2199,9 @@ pub fn uninstall_canister(
     // Drop its certified data.
     canister.system_state.certified_data = Vec::new();
 
+    // Deactivate its global timer.
+    canister.system_state.global_timer = CanisterTimer::Inactive;
+
     truncate_canister_heap(log, state_path, canister.canister_id());
     truncate_canister_stable_memory(log, state_path, canister.canister_id());
 
@@ -1679,6 +2271,8 @@ struct ValidatedCanisterSettings {
     pub compute_allocation: Option<ComputeAllocation>,
     pub memory_allocation: Option<MemoryAllocation>,
     pub freezing_threshold: Option<NumSeconds>,
+    pub log_visibility: Option<LogVisibility>,
+    pub reserved_cycles_limit: Option<Cycles>,
 }
 
 impl TryFrom<(CanisterSettings, usize)> for ValidatedCanisterSettings {
@@ -1714,6 +2308,8 @@ impl TryFrom<(CanisterSettings, usize)> for ValidatedCanisterSettings {
             compute_allocation: settings.compute_allocation(),
             memory_allocation: settings.memory_allocation(),
             freezing_threshold: settings.freezing_threshold(),
+            log_visibility: settings.log_visibility(),
+            reserved_cycles_limit: settings.reserved_cycles_limit(),
         })
     }
 }
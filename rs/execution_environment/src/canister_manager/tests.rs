@@ -22,7 +22,7 @@ use ic_registry_routing_table::{CanisterIdRange, RoutingTable};
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
     page_map, testing::CanisterQueuesTesting, CallContextManager, CallOrigin, CanisterStatus,
-    NumWasmPages, PageMap, ReplicatedState,
+    LogVisibility, NumWasmPages, PageMap, ReplicatedState, MAX_SNAPSHOTS_PER_CANISTER,
 };
 use ic_test_utilities::{
     cycles_account_manager::CyclesAccountManagerBuilder,
@@ -47,6 +47,7 @@ use ic_types::{
     user_error::{ErrorCode, UserError},
     CanisterId, CanisterStatusType, ComputeAllocation, Cycles, InstallCodeContext,
     MemoryAllocation, NumBytes, NumInstructions, QueryAllocation, SubnetId,
+    MAX_STABLE_MEMORY_IN_BYTES,
 };
 use ic_wasm_types::WasmValidationError;
 use lazy_static::lazy_static;
@@ -63,6 +64,7 @@ const MEMORY_CAPACITY: NumBytes = NumBytes::new(8 * 1024 * 1024 * 1024); // 8GiB
 const MAX_CONTROLLERS: usize = 10;
 const WASM_PAGE_SIZE_IN_BYTES: u64 = 64 * 1024; // 64KiB
 const MAX_NUMBER_OF_CANISTERS: u64 = 0;
+const DEFAULT_RESERVED_BALANCE_LIMIT: Cycles = Cycles::new(5_000_000_000_000);
 
 lazy_static! {
     static ref MAX_SUBNET_AVAILABLE_MEMORY: SubnetAvailableMemory =
@@ -72,6 +74,7 @@ lazy_static! {
     static ref EXECUTION_PARAMETERS: ExecutionParameters = ExecutionParameters {
         instruction_limit: MAX_NUM_INSTRUCTIONS,
         canister_memory_limit: NumBytes::new(u64::MAX / 2),
+        max_stable_memory_size: NumBytes::new(MAX_STABLE_MEMORY_IN_BYTES),
         subnet_available_memory: MAX_SUBNET_AVAILABLE_MEMORY.clone(),
         compute_allocation: ComputeAllocation::default(),
         subnet_type: SubnetType::Application,
@@ -81,6 +84,7 @@ lazy_static! {
 struct CanisterManagerBuilder {
     cycles_account_manager: CyclesAccountManager,
     subnet_id: SubnetId,
+    subnet_reservation_threshold_percent: u64,
 }
 
 impl CanisterManagerBuilder {
@@ -94,6 +98,11 @@ impl CanisterManagerBuilder {
         self
     }
 
+    fn with_subnet_reservation_threshold_percent(mut self, percent: u64) -> Self {
+        self.subnet_reservation_threshold_percent = percent;
+        self
+    }
+
     fn build(self) -> CanisterManager {
         let subnet_type = SubnetType::Application;
         let metrics_registry = MetricsRegistry::new();
@@ -115,7 +124,11 @@ impl CanisterManagerBuilder {
         CanisterManager::new(
             hypervisor,
             no_op_logger(),
-            canister_manager_config(self.subnet_id, subnet_type),
+            canister_manager_config_with_reservation_threshold(
+                self.subnet_id,
+                subnet_type,
+                self.subnet_reservation_threshold_percent,
+            ),
             cycles_account_manager,
             ingress_history_writer,
         )
@@ -127,11 +140,21 @@ impl Default for CanisterManagerBuilder {
         Self {
             cycles_account_manager: CyclesAccountManagerBuilder::new().build(),
             subnet_id: subnet_test_id(1),
+            // Cycles are never reserved for allocations unless a test opts in.
+            subnet_reservation_threshold_percent: 100,
         }
     }
 }
 
 fn canister_manager_config(subnet_id: SubnetId, subnet_type: SubnetType) -> CanisterMgrConfig {
+    canister_manager_config_with_reservation_threshold(subnet_id, subnet_type, 100)
+}
+
+fn canister_manager_config_with_reservation_threshold(
+    subnet_id: SubnetId,
+    subnet_type: SubnetType,
+    subnet_reservation_threshold_percent: u64,
+) -> CanisterMgrConfig {
     CanisterMgrConfig::new(
         MEMORY_CAPACITY,
         Some(CYCLES_LIMIT_PER_CANISTER),
@@ -141,6 +164,8 @@ fn canister_manager_config(subnet_id: SubnetId, subnet_type: SubnetType) -> Cani
         subnet_type,
         MAX_CONTROLLERS,
         1,
+        subnet_reservation_threshold_percent,
+        DEFAULT_RESERVED_BALANCE_LIMIT,
     )
 }
 
@@ -1305,6 +1330,57 @@ fn install_puts_canister_back_after_invalid_wasm() {
     });
 }
 
+#[test]
+fn install_accepts_simd_only_if_the_subnet_feature_is_enabled() {
+    with_setup(|canister_manager, mut state, _| {
+        let mut features = wabt::Features::new();
+        features.enable_simd();
+        let wasm = wabt::wat2wasm_with_features(
+            r#"(module
+                (func (export "canister_query simd") (result i32)
+                    (i32x4.extract_lane 0 (i32x4.splat (i32.const 1)))))"#,
+            features,
+        )
+        .unwrap();
+
+        let sender = canister_test_id(1).get();
+        let canister_id = canister_manager
+            .create_canister(
+                sender,
+                subnet_test_id(1),
+                *INITIAL_CYCLES,
+                CanisterSettings::default(),
+                MAX_NUMBER_OF_CANISTERS,
+                &mut state,
+            )
+            .0
+            .unwrap();
+        let context = InstallCodeContextBuilder::default()
+            .sender(sender)
+            .canister_id(canister_id)
+            .wasm_module(wasm)
+            .build();
+
+        assert_matches!(
+            canister_manager
+                .install_code(context.clone(), &mut state, EXECUTION_PARAMETERS.clone())
+                .1,
+            Err(CanisterManagerError::Hypervisor(
+                _,
+                HypervisorError::InvalidWasm(WasmValidationError::WasmtimeValidation(_))
+            ))
+        );
+
+        state.metadata.own_subnet_features.wasm_simd = true;
+        assert_matches!(
+            canister_manager
+                .install_code(context, &mut state, EXECUTION_PARAMETERS.clone())
+                .1,
+            Ok(_)
+        );
+    });
+}
+
 #[test]
 fn reinstall_clears_stable_memory() {
     with_setup(|canister_manager, mut state, _| {
@@ -2831,6 +2907,8 @@ fn lower_memory_allocation_than_usage_fails() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(2)).unwrap()),
             None,
+            None,
+            None,
         );
 
         let compute_allocation_used = state.total_compute_allocation();
@@ -2862,6 +2940,8 @@ fn test_install_when_updating_memory_allocation_via_canister_settings() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(2)).unwrap()),
             None,
+            None,
+            None,
         );
         let canister_id = canister_manager
             .create_canister(
@@ -2904,6 +2984,8 @@ fn test_install_when_updating_memory_allocation_via_canister_settings() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(MEMORY_CAPACITY.get() / 2)).unwrap()),
             None,
+            None,
+            None,
         );
 
         let compute_allocation_used = state.total_compute_allocation();
@@ -2952,6 +3034,8 @@ fn test_upgrade_when_updating_memory_allocation_via_canister_settings() {
                 MemoryAllocation::try_from(NumBytes::from(WASM_PAGE_SIZE_IN_BYTES + 100)).unwrap(),
             ),
             None,
+            None,
+            None,
         );
         let wat = r#"
         (module
@@ -3027,6 +3111,8 @@ fn test_upgrade_when_updating_memory_allocation_via_canister_settings() {
                     .unwrap(),
             ),
             None,
+            None,
+            None,
         );
 
         let compute_allocation_used = state.total_compute_allocation();
@@ -3103,13 +3189,51 @@ fn uninstall_code_can_be_invoked_by_governance_canister() {
     );
 }
 
+#[test]
+fn update_settings_changes_log_visibility() {
+    with_setup(|canister_manager, mut state, subnet_id| {
+        let sender = canister_test_id(100).get();
+        let canister_id = canister_manager
+            .create_canister(
+                sender,
+                subnet_id,
+                *INITIAL_CYCLES,
+                CanisterSettings::default(),
+                MAX_NUMBER_OF_CANISTERS,
+                &mut state,
+            )
+            .0
+            .unwrap();
+
+        let mut canister = state.canister_state_mut(&canister_id).unwrap();
+        assert_eq!(
+            canister.system_state.log_visibility,
+            LogVisibility::Controllers
+        );
+
+        let settings = CanisterSettings::new(
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(LogVisibility::Public),
+            None,
+        );
+        canister_manager
+            .update_settings(sender, settings, &mut canister, 0, NumBytes::from(0))
+            .unwrap();
+        assert_eq!(canister.system_state.log_visibility, LogVisibility::Public);
+    });
+}
+
 #[test]
 fn test_install_when_setting_memory_allocation_to_zero() {
     with_setup(|canister_manager, mut state, subnet_id| {
         let wasm = ic_test_utilities::universal_canister::UNIVERSAL_CANISTER_WASM.to_vec();
 
         let sender = canister_test_id(100).get();
-        let settings = CanisterSettings::new(None, None, None, None, None);
+        let settings = CanisterSettings::new(None, None, None, None, None, None, None);
         let canister_id = canister_manager
             .create_canister(
                 sender,
@@ -3129,6 +3253,8 @@ fn test_install_when_setting_memory_allocation_to_zero() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(0)).unwrap()),
             None,
+            None,
+            None,
         );
 
         let compute_allocation_used = state.total_compute_allocation();
@@ -3177,6 +3303,8 @@ fn test_upgrade_when_setting_memory_allocation_to_zero() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(MEMORY_CAPACITY.get() / 2)).unwrap()),
             None,
+            None,
+            None,
         );
         let canister_id = canister_manager
             .create_canister(
@@ -3215,6 +3343,8 @@ fn test_upgrade_when_setting_memory_allocation_to_zero() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(0)).unwrap()),
             None,
+            None,
+            None,
         );
 
         let compute_allocation_used = state.total_compute_allocation();
@@ -3430,7 +3560,8 @@ fn hypervisor_sends_new_controller_to_canister() {
         // Change to a new controller with a different length.
         let new_controller = PrincipalId::try_from(&[1, 2, 3][..]).unwrap();
         assert!(controller.to_vec().len() != new_controller.to_vec().len());
-        let new_settings = CanisterSettings::new(Some(new_controller), None, None, None, None);
+        let new_settings =
+            CanisterSettings::new(Some(new_controller), None, None, None, None, None, None);
         canister_manager
             .update_settings(
                 controller,
@@ -3487,3 +3618,558 @@ proptest! {
         });
     }
 }
+
+fn create_canister_with_code(
+    canister_manager: &CanisterManager,
+    state: &mut ReplicatedState,
+    sender: PrincipalId,
+    subnet_id: SubnetId,
+) -> CanisterId {
+    let canister_id = canister_manager
+        .create_canister(
+            sender,
+            subnet_id,
+            *INITIAL_CYCLES,
+            CanisterSettings::default(),
+            MAX_NUMBER_OF_CANISTERS,
+            state,
+        )
+        .0
+        .unwrap();
+    canister_manager
+        .install_code(
+            InstallCodeContextBuilder::default()
+                .sender(sender)
+                .canister_id(canister_id)
+                .build(),
+            state,
+            EXECUTION_PARAMETERS.clone(),
+        )
+        .1
+        .unwrap();
+    canister_id
+}
+
+fn write_stable_memory(state: &mut ReplicatedState, canister_id: CanisterId, bytes: &[u8]) {
+    let execution_state = state
+        .canister_state_mut(&canister_id)
+        .unwrap()
+        .execution_state
+        .as_mut()
+        .unwrap();
+    execution_state.stable_memory.size = NumWasmPages::new(1);
+    let mut buf = page_map::Buffer::new(PageMap::default());
+    buf.write(bytes, 0);
+    execution_state
+        .stable_memory
+        .page_map
+        .update(&buf.dirty_pages().collect::<Vec<_>>());
+}
+
+fn read_stable_memory(state: &ReplicatedState, canister_id: CanisterId, len: usize) -> Vec<u8> {
+    let execution_state = state
+        .canister_state(&canister_id)
+        .unwrap()
+        .execution_state
+        .as_ref()
+        .unwrap();
+    let mut bytes = vec![0; len];
+    page_map::Buffer::new(execution_state.stable_memory.page_map.clone()).read(&mut bytes, 0);
+    bytes
+}
+
+#[test]
+fn load_canister_snapshot_restores_memory_and_certified_data() {
+    with_setup(|canister_manager, mut state, subnet_id| {
+        let sender = canister_test_id(42).get();
+        let canister_id =
+            create_canister_with_code(&canister_manager, &mut state, sender, subnet_id);
+        write_stable_memory(&mut state, canister_id, &[1; 10]);
+        state
+            .canister_state_mut(&canister_id)
+            .unwrap()
+            .system_state
+            .certified_data = vec![1, 2, 3];
+
+        let snapshot = canister_manager
+            .take_canister_snapshot(sender, canister_id, None, &mut state)
+            .unwrap();
+        assert_eq!(
+            snapshot.taken_at_timestamp,
+            state.time().as_nanos_since_unix_epoch()
+        );
+
+        write_stable_memory(&mut state, canister_id, &[2; 10]);
+        state
+            .canister_state_mut(&canister_id)
+            .unwrap()
+            .system_state
+            .certified_data = vec![4, 5, 6];
+
+        state
+            .canister_state_mut(&canister_id)
+            .unwrap()
+            .system_state
+            .status = CanisterStatus::Stopped;
+        canister_manager
+            .load_canister_snapshot(sender, canister_id, snapshot.id.clone(), &mut state)
+            .unwrap();
+
+        assert_eq!(read_stable_memory(&state, canister_id, 10), vec![1; 10]);
+        let canister = state.canister_state(&canister_id).unwrap();
+        assert_eq!(canister.system_state.certified_data, vec![1, 2, 3]);
+        // The snapshot is kept after it has been loaded.
+        assert_eq!(
+            canister_manager
+                .list_canister_snapshots(sender, canister)
+                .unwrap(),
+            vec![snapshot]
+        );
+    });
+}
+
+#[test]
+fn load_canister_snapshot_fails_if_canister_is_not_stopped() {
+    with_setup(|canister_manager, mut state, subnet_id| {
+        let sender = canister_test_id(42).get();
+        let canister_id =
+            create_canister_with_code(&canister_manager, &mut state, sender, subnet_id);
+        let snapshot = canister_manager
+            .take_canister_snapshot(sender, canister_id, None, &mut state)
+            .unwrap();
+        write_stable_memory(&mut state, canister_id, &[2; 10]);
+
+        assert_eq!(
+            canister_manager.load_canister_snapshot(sender, canister_id, snapshot.id, &mut state),
+            Err(CanisterManagerError::LoadCanisterSnapshotNotStopped(
+                canister_id
+            ))
+        );
+        assert_eq!(read_stable_memory(&state, canister_id, 10), vec![2; 10]);
+    });
+}
+
+#[test]
+fn take_canister_snapshot_fails_if_limit_is_reached() {
+    with_setup(|canister_manager, mut state, subnet_id| {
+        let sender = canister_test_id(42).get();
+        let canister_id =
+            create_canister_with_code(&canister_manager, &mut state, sender, subnet_id);
+
+        let mut snapshots = vec![];
+        for _ in 0..MAX_SNAPSHOTS_PER_CANISTER {
+            snapshots.push(
+                canister_manager
+                    .take_canister_snapshot(sender, canister_id, None, &mut state)
+                    .unwrap(),
+            );
+        }
+        assert_eq!(
+            canister_manager.take_canister_snapshot(sender, canister_id, None, &mut state),
+            Err(CanisterManagerError::CanisterSnapshotLimitExceeded {
+                canister_id,
+                limit: MAX_SNAPSHOTS_PER_CANISTER,
+            })
+        );
+
+        // Replacing an existing snapshot is still possible.
+        let replaced = snapshots.remove(0);
+        let snapshot = canister_manager
+            .take_canister_snapshot(sender, canister_id, Some(replaced.id.clone()), &mut state)
+            .unwrap();
+        assert_ne!(snapshot.id, replaced.id);
+        snapshots.push(snapshot);
+        assert_eq!(
+            canister_manager
+                .list_canister_snapshots(sender, state.canister_state(&canister_id).unwrap())
+                .unwrap(),
+            snapshots
+        );
+    });
+}
+
+#[test]
+fn delete_canister_snapshot_releases_memory() {
+    with_setup(|canister_manager, mut state, subnet_id| {
+        let sender = canister_test_id(42).get();
+        let canister_id =
+            create_canister_with_code(&canister_manager, &mut state, sender, subnet_id);
+        let memory_usage = |state: &ReplicatedState| {
+            state
+                .canister_state(&canister_id)
+                .unwrap()
+                .memory_usage(SubnetType::Application)
+        };
+        let initial_memory_usage = memory_usage(&state);
+
+        let snapshot = canister_manager
+            .take_canister_snapshot(sender, canister_id, None, &mut state)
+            .unwrap();
+        assert_eq!(
+            memory_usage(&state),
+            initial_memory_usage + NumBytes::from(snapshot.total_size)
+        );
+
+        canister_manager
+            .delete_canister_snapshot(sender, canister_id, snapshot.id.clone(), &mut state)
+            .unwrap();
+        assert_eq!(memory_usage(&state), initial_memory_usage);
+        assert!(canister_layout(state.path(), &canister_id)
+            .snapshot(0)
+            .unwrap()
+            .is_marked_deleted());
+
+        assert_eq!(
+            canister_manager.delete_canister_snapshot(
+                sender,
+                canister_id,
+                snapshot.id.clone(),
+                &mut state
+            ),
+            Err(CanisterManagerError::CanisterSnapshotNotFound {
+                canister_id,
+                snapshot_id: snapshot.id,
+            })
+        );
+    });
+}
+
+#[test]
+fn take_canister_snapshot_fails_if_memory_allocation_is_too_small() {
+    with_setup(|canister_manager, mut state, subnet_id| {
+        let sender = canister_test_id(42).get();
+        let canister_id =
+            create_canister_with_code(&canister_manager, &mut state, sender, subnet_id);
+        let canister = state.canister_state_mut(&canister_id).unwrap();
+        let memory_usage = canister.memory_usage(SubnetType::Application);
+        canister.system_state.memory_allocation = MemoryAllocation::try_from(memory_usage).unwrap();
+
+        assert_matches!(
+            canister_manager.take_canister_snapshot(sender, canister_id, None, &mut state),
+            Err(CanisterManagerError::NotEnoughMemoryAllocationGiven { .. })
+        );
+        assert!(state
+            .canister_state(&canister_id)
+            .unwrap()
+            .system_state
+            .snapshots
+            .is_empty());
+    });
+}
+
+#[test]
+fn canister_snapshots_require_a_controller() {
+    with_setup(|canister_manager, mut state, subnet_id| {
+        let sender = canister_test_id(42).get();
+        let other = canister_test_id(43).get();
+        let canister_id =
+            create_canister_with_code(&canister_manager, &mut state, sender, subnet_id);
+        let snapshot = canister_manager
+            .take_canister_snapshot(sender, canister_id, None, &mut state)
+            .unwrap();
+
+        assert_matches!(
+            canister_manager.take_canister_snapshot(other, canister_id, None, &mut state),
+            Err(CanisterManagerError::CanisterInvalidController { .. })
+        );
+        assert_matches!(
+            canister_manager.load_canister_snapshot(
+                other,
+                canister_id,
+                snapshot.id.clone(),
+                &mut state
+            ),
+            Err(CanisterManagerError::CanisterInvalidController { .. })
+        );
+        assert_matches!(
+            canister_manager
+                .list_canister_snapshots(other, state.canister_state(&canister_id).unwrap()),
+            Err(CanisterManagerError::CanisterInvalidController { .. })
+        );
+        assert_matches!(
+            canister_manager.delete_canister_snapshot(other, canister_id, snapshot.id, &mut state),
+            Err(CanisterManagerError::CanisterInvalidController { .. })
+        );
+    });
+}
+
+fn with_reservation_setup<F>(subnet_reservation_threshold_percent: u64, f: F)
+where
+    F: FnOnce(CanisterManager, ReplicatedState, SubnetId),
+{
+    let subnet_id = subnet_test_id(1);
+    let canister_manager = CanisterManagerBuilder::default()
+        .with_subnet_id(subnet_id)
+        .with_subnet_reservation_threshold_percent(subnet_reservation_threshold_percent)
+        .build();
+    let tmpdir = tempfile::Builder::new().prefix("test").tempdir().unwrap();
+    f(
+        canister_manager,
+        initial_state(tmpdir.path(), subnet_id),
+        subnet_id,
+    )
+}
+
+fn update_compute_allocation(
+    canister_manager: &CanisterManager,
+    state: &mut ReplicatedState,
+    sender: PrincipalId,
+    canister_id: CanisterId,
+    compute_allocation: u64,
+    reserved_cycles_limit: Option<Cycles>,
+) -> Result<(), CanisterManagerError> {
+    let settings = CanisterSettings::new(
+        None,
+        None,
+        Some(ComputeAllocation::try_from(compute_allocation).unwrap()),
+        None,
+        None,
+        None,
+        reserved_cycles_limit,
+    );
+    let compute_allocation_used = state.total_compute_allocation();
+    let memory_taken = state.total_memory_taken();
+    let canister = state.canister_state_mut(&canister_id).unwrap();
+    canister_manager.update_settings(
+        sender,
+        settings,
+        canister,
+        compute_allocation_used,
+        memory_taken,
+    )
+}
+
+#[test]
+fn update_settings_does_not_reserve_cycles_below_threshold() {
+    with_setup(|canister_manager, mut state, subnet_id| {
+        let sender = canister_test_id(42).get();
+        let canister_id = canister_manager
+            .create_canister(
+                sender,
+                subnet_id,
+                *INITIAL_CYCLES,
+                CanisterSettings::default(),
+                MAX_NUMBER_OF_CANISTERS,
+                &mut state,
+            )
+            .0
+            .unwrap();
+        let balance = state
+            .canister_state(&canister_id)
+            .unwrap()
+            .system_state
+            .cycles_balance;
+
+        update_compute_allocation(&canister_manager, &mut state, sender, canister_id, 10, None)
+            .unwrap();
+
+        let canister = state.canister_state(&canister_id).unwrap();
+        assert_eq!(canister.system_state.reserved_balance, Cycles::zero());
+        assert_eq!(canister.system_state.cycles_balance, balance);
+    });
+}
+
+#[test]
+fn update_settings_reserves_cycles_for_compute_allocation_above_threshold() {
+    // Any increase of an allocation is above the threshold.
+    with_reservation_setup(0, |canister_manager, mut state, subnet_id| {
+        let sender = canister_test_id(42).get();
+        let canister_id = canister_manager
+            .create_canister(
+                sender,
+                subnet_id,
+                *INITIAL_CYCLES,
+                CanisterSettings::default(),
+                MAX_NUMBER_OF_CANISTERS,
+                &mut state,
+            )
+            .0
+            .unwrap();
+        let balance = state
+            .canister_state(&canister_id)
+            .unwrap()
+            .system_state
+            .cycles_balance;
+
+        update_compute_allocation(&canister_manager, &mut state, sender, canister_id, 10, None)
+            .unwrap();
+
+        let expected_reservation = CyclesAccountManagerBuilder::new()
+            .build()
+            .allocation_reservation_cycles(
+                NumBytes::from(0),
+                ComputeAllocation::try_from(10).unwrap(),
+            );
+        assert!(expected_reservation > Cycles::zero());
+        let mut canister = state.canister_state_mut(&canister_id).unwrap();
+        assert_eq!(canister.system_state.reserved_balance, expected_reservation);
+        assert_eq!(
+            canister.system_state.cycles_balance,
+            balance - expected_reservation
+        );
+
+        let status = canister_manager
+            .get_canister_status(sender, &mut canister)
+            .unwrap();
+        assert_eq!(status.reserved_cycles(), expected_reservation.get());
+        assert_eq!(
+            status.settings().reserved_cycles_limit(),
+            DEFAULT_RESERVED_BALANCE_LIMIT.get()
+        );
+
+        // Lowering the compute allocation does not reserve more cycles.
+        update_compute_allocation(&canister_manager, &mut state, sender, canister_id, 5, None)
+            .unwrap();
+        assert_eq!(
+            state
+                .canister_state(&canister_id)
+                .unwrap()
+                .system_state
+                .reserved_balance,
+            expected_reservation
+        );
+    });
+}
+
+#[test]
+fn update_settings_fails_if_reserved_cycles_limit_is_exceeded() {
+    with_reservation_setup(0, |canister_manager, mut state, subnet_id| {
+        let sender = canister_test_id(42).get();
+        let canister_id = canister_manager
+            .create_canister(
+                sender,
+                subnet_id,
+                *INITIAL_CYCLES,
+                CanisterSettings::default(),
+                MAX_NUMBER_OF_CANISTERS,
+                &mut state,
+            )
+            .0
+            .unwrap();
+        let balance = state
+            .canister_state(&canister_id)
+            .unwrap()
+            .system_state
+            .cycles_balance;
+
+        assert_matches!(
+            update_compute_allocation(
+                &canister_manager,
+                &mut state,
+                sender,
+                canister_id,
+                10,
+                Some(Cycles::new(1))
+            ),
+            Err(CanisterManagerError::ReservedCyclesLimitExceeded { .. })
+        );
+
+        // The settings are applied atomically.
+        let canister = state.canister_state(&canister_id).unwrap();
+        assert_eq!(canister.scheduler_state.compute_allocation.as_percent(), 0);
+        assert_eq!(canister.system_state.reserved_balance_limit, None);
+        assert_eq!(canister.system_state.reserved_balance, Cycles::zero());
+        assert_eq!(canister.system_state.cycles_balance, balance);
+    });
+}
+
+#[test]
+fn update_settings_rejects_reserved_cycles_limit_below_reserved_balance() {
+    with_reservation_setup(0, |canister_manager, mut state, subnet_id| {
+        let sender = canister_test_id(42).get();
+        let canister_id = canister_manager
+            .create_canister(
+                sender,
+                subnet_id,
+                *INITIAL_CYCLES,
+                CanisterSettings::default(),
+                MAX_NUMBER_OF_CANISTERS,
+                &mut state,
+            )
+            .0
+            .unwrap();
+        update_compute_allocation(&canister_manager, &mut state, sender, canister_id, 10, None)
+            .unwrap();
+
+        assert_matches!(
+            update_compute_allocation(
+                &canister_manager,
+                &mut state,
+                sender,
+                canister_id,
+                10,
+                Some(Cycles::new(1))
+            ),
+            Err(CanisterManagerError::ReservedCyclesLimitIsTooLow { .. })
+        );
+    });
+}
+
+#[test]
+fn create_canister_reserves_cycles_for_memory_allocation_above_threshold() {
+    with_reservation_setup(0, |canister_manager, mut state, subnet_id| {
+        let sender = canister_test_id(42).get();
+        let memory_allocation = NumBytes::from(1 << 30);
+        let settings = CanisterSettings::new(
+            None,
+            None,
+            None,
+            Some(MemoryAllocation::try_from(memory_allocation).unwrap()),
+            None,
+            None,
+            None,
+        );
+        let canister_id = canister_manager
+            .create_canister(
+                sender,
+                subnet_id,
+                *INITIAL_CYCLES,
+                settings,
+                MAX_NUMBER_OF_CANISTERS,
+                &mut state,
+            )
+            .0
+            .unwrap();
+
+        let expected_reservation = CyclesAccountManagerBuilder::new()
+            .build()
+            .allocation_reservation_cycles(memory_allocation, ComputeAllocation::default());
+        let canister = state.canister_state(&canister_id).unwrap();
+        assert_eq!(canister.system_state.reserved_balance, expected_reservation);
+        assert_eq!(
+            canister.system_state.cycles_balance,
+            *INITIAL_CYCLES - CANISTER_CREATION_FEE - expected_reservation
+        );
+    });
+}
+
+#[test]
+fn create_canister_fails_without_cycles_for_reservation() {
+    with_reservation_setup(0, |canister_manager, mut state, subnet_id| {
+        let sender = canister_test_id(42).get();
+        let settings = CanisterSettings::new(
+            None,
+            None,
+            None,
+            Some(MemoryAllocation::try_from(NumBytes::from(1 << 30)).unwrap()),
+            None,
+            None,
+            None,
+        );
+        let cycles = CANISTER_CREATION_FEE + Cycles::new(1_000);
+        let (result, cycles_to_return) = canister_manager.create_canister(
+            sender,
+            subnet_id,
+            cycles,
+            settings,
+            MAX_NUMBER_OF_CANISTERS,
+            &mut state,
+        );
+        assert_matches!(
+            result,
+            Err(CanisterManagerError::InsufficientCyclesInAllocationReservation { .. })
+        );
+        assert_eq!(cycles_to_return, cycles);
+        assert_eq!(state.num_canisters(), 0);
+    });
+}
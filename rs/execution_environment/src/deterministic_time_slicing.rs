@@ -0,0 +1,280 @@
+//! Support for deterministic time slicing (DTS).
+//!
+//! A long-running execution is split into slices. When a slice runs out of
+//! instructions, the execution is paused and the control is returned to the
+//! caller, which can either resume the execution with a new slice in a later
+//! round or abort it.
+//!
+//! An execution runs on a dedicated thread that blocks in the
+//! `OutOfInstructionsHandler` while it is paused. Slices are cut only when
+//! the instruction counter reaches the slice limit, so the result of an
+//! execution does not depend on the number or the timing of its slices.
+//!
+//! The Wasm stack of a paused execution lives only in memory. An execution
+//! whose thread is gone, e.g. after a restart from a checkpoint, is recreated
+//! by starting it again with the limits of its completed slices: these slices
+//! run back to back and the execution pauses where it paused before.
+use ic_interfaces::execution_environment::{
+    HypervisorError, HypervisorResult, OutOfInstructionsHandler,
+};
+use ic_types::NumInstructions;
+use std::collections::VecDeque;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    mpsc::{channel, Receiver, Sender},
+    Arc, Mutex,
+};
+
+/// The stack size of the threads that run sliced executions. It matches the
+/// stack size of the query execution threads.
+const SLICED_EXECUTION_THREAD_STACK_SIZE: usize = 8_192_000;
+
+/// Commands sent to a paused execution.
+enum Command {
+    Resume(NumInstructions),
+    Abort,
+}
+
+/// Events sent by an execution to its owner.
+enum SliceEvent<R> {
+    Paused(NumInstructions),
+    Finished(R),
+}
+
+/// The handler that pauses the execution at the end of each slice and waits
+/// for the owner of the execution to resume or abort it. The slices in
+/// `next_slice_instruction_limits` are started without pausing.
+struct PausingOutOfInstructionsHandler<R> {
+    slice_instruction_limit: AtomicU64,
+    next_slice_instruction_limits: Mutex<VecDeque<NumInstructions>>,
+    events: Mutex<Sender<SliceEvent<R>>>,
+    commands: Mutex<Receiver<Command>>,
+}
+
+impl<R: Send> OutOfInstructionsHandler for PausingOutOfInstructionsHandler<R> {
+    fn slice_instruction_limit(&self) -> NumInstructions {
+        NumInstructions::from(self.slice_instruction_limit.load(Ordering::SeqCst))
+    }
+
+    fn out_of_instructions(&self, instructions_executed: NumInstructions) -> HypervisorResult<()> {
+        if let Some(limit) = self
+            .next_slice_instruction_limits
+            .lock()
+            .unwrap()
+            .pop_front()
+        {
+            self.slice_instruction_limit
+                .store(limit.get(), Ordering::SeqCst);
+            return Ok(());
+        }
+        if self
+            .events
+            .lock()
+            .unwrap()
+            .send(SliceEvent::Paused(instructions_executed))
+            .is_err()
+        {
+            return Err(HypervisorError::InstructionLimitExceeded);
+        }
+        match self.commands.lock().unwrap().recv() {
+            Ok(Command::Resume(slice_instruction_limit)) => {
+                self.slice_instruction_limit
+                    .store(slice_instruction_limit.get(), Ordering::SeqCst);
+                Ok(())
+            }
+            // The owner has dropped the execution, which is equivalent to
+            // aborting it.
+            Ok(Command::Abort) | Err(_) => Err(HypervisorError::InstructionLimitExceeded),
+        }
+    }
+}
+
+/// The outcome of running a slice of an execution.
+pub(crate) enum SliceResult<R> {
+    /// The execution has reached the end of the slice.
+    Paused(PausedExecution<R>),
+    /// The execution has completed with the given result.
+    Finished(R),
+}
+
+/// An execution that is waiting for a new slice.
+pub(crate) struct PausedExecution<R> {
+    instructions_executed: NumInstructions,
+    commands: Sender<Command>,
+    events: Receiver<SliceEvent<R>>,
+    thread: std::thread::JoinHandle<()>,
+}
+
+impl<R> PausedExecution<R> {
+    /// The total number of instructions executed in all completed slices.
+    pub(crate) fn instructions_executed(&self) -> NumInstructions {
+        self.instructions_executed
+    }
+
+    /// Runs the next slice of the execution with the given instruction limit.
+    pub(crate) fn resume(self, slice_instruction_limit: NumInstructions) -> SliceResult<R> {
+        self.commands
+            .send(Command::Resume(slice_instruction_limit))
+            .expect("The thread of a paused execution terminated unexpectedly.");
+        wait_for_slice(self.commands, self.events, self.thread)
+    }
+
+    /// Aborts the execution and waits until its thread terminates. The
+    /// result of the aborted execution is discarded.
+    pub(crate) fn abort(self) {
+        // The thread may have already terminated, so errors can be ignored.
+        let _ = self.commands.send(Command::Abort);
+        while let Ok(event) = self.events.recv() {
+            if let SliceEvent::Paused(_) = event {
+                let _ = self.commands.send(Command::Abort);
+            }
+        }
+        let _ = self.thread.join();
+    }
+}
+
+/// Runs the given job on a new thread until the end of the last of the given
+/// slices. All slices but the last one are started without pausing, which is
+/// how a paused execution is recreated from the limits of its completed
+/// slices. The job receives the `OutOfInstructionsHandler` that must be
+/// installed in the hypervisor for the execution to be sliced.
+pub(crate) fn execute_sliced<R, F>(
+    slice_instruction_limits: Vec<NumInstructions>,
+    job: F,
+) -> SliceResult<R>
+where
+    R: Send + 'static,
+    F: FnOnce(Arc<dyn OutOfInstructionsHandler>) -> R + Send + 'static,
+{
+    let mut slice_instruction_limits = VecDeque::from(slice_instruction_limits);
+    let first_slice_instruction_limit = slice_instruction_limits
+        .pop_front()
+        .expect("An execution needs at least one slice.");
+    let (events_tx, events_rx) = channel();
+    let (commands_tx, commands_rx) = channel();
+    let handler = Arc::new(PausingOutOfInstructionsHandler {
+        slice_instruction_limit: AtomicU64::new(first_slice_instruction_limit.get()),
+        next_slice_instruction_limits: Mutex::new(slice_instruction_limits),
+        events: Mutex::new(events_tx.clone()),
+        commands: Mutex::new(commands_rx),
+    });
+    let thread = std::thread::Builder::new()
+        .name("SlicedExecution".to_string())
+        .stack_size(SLICED_EXECUTION_THREAD_STACK_SIZE)
+        .spawn(move || {
+            let result = job(handler);
+            // The owner may have aborted the execution in the meantime.
+            let _ = events_tx.send(SliceEvent::Finished(result));
+        })
+        .expect("Failed to spawn a thread for a sliced execution.");
+    wait_for_slice(commands_tx, events_rx, thread)
+}
+
+fn wait_for_slice<R>(
+    commands: Sender<Command>,
+    events: Receiver<SliceEvent<R>>,
+    thread: std::thread::JoinHandle<()>,
+) -> SliceResult<R> {
+    match events.recv() {
+        Ok(SliceEvent::Paused(instructions_executed)) => SliceResult::Paused(PausedExecution {
+            instructions_executed,
+            commands,
+            events,
+            thread,
+        }),
+        Ok(SliceEvent::Finished(result)) => {
+            thread
+                .join()
+                .expect("The thread of a sliced execution panicked.");
+            SliceResult::Finished(result)
+        }
+        Err(_) => panic!("The thread of a sliced execution panicked."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A job that "executes" `total` instructions and asks for a new slice
+    // every time its current slice is exhausted.
+    fn count_to(
+        total: u64,
+    ) -> impl FnOnce(Arc<dyn OutOfInstructionsHandler>) -> HypervisorResult<u64> + Send + 'static
+    {
+        move |handler| {
+            let mut executed = 0;
+            let mut slices = 1;
+            loop {
+                let slice = handler.slice_instruction_limit().get();
+                if total - executed <= slice {
+                    return Ok(slices);
+                }
+                executed += slice;
+                handler.out_of_instructions(NumInstructions::from(executed))?;
+                slices += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn execution_within_first_slice_finishes() {
+        match execute_sliced(vec![NumInstructions::from(100)], count_to(50)) {
+            SliceResult::Finished(result) => assert_eq!(result, Ok(1)),
+            SliceResult::Paused(_) => panic!("Expected the execution to finish."),
+        }
+    }
+
+    #[test]
+    fn execution_is_paused_and_resumed() {
+        let mut result = execute_sliced(vec![NumInstructions::from(100)], count_to(250));
+        let mut pauses = vec![];
+        let result = loop {
+            match result {
+                SliceResult::Paused(paused) => {
+                    pauses.push(paused.instructions_executed().get());
+                    result = paused.resume(NumInstructions::from(100));
+                }
+                SliceResult::Finished(result) => break result,
+            }
+        };
+        assert_eq!(pauses, vec![100, 200]);
+        assert_eq!(result, Ok(3));
+    }
+
+    #[test]
+    fn slice_limit_can_change_on_resume() {
+        let paused = match execute_sliced(vec![NumInstructions::from(100)], count_to(250)) {
+            SliceResult::Paused(paused) => paused,
+            SliceResult::Finished(_) => panic!("Expected the execution to pause."),
+        };
+        match paused.resume(NumInstructions::from(1000)) {
+            SliceResult::Finished(result) => assert_eq!(result, Ok(2)),
+            SliceResult::Paused(_) => panic!("Expected the execution to finish."),
+        }
+    }
+
+    #[test]
+    fn recreated_execution_pauses_after_the_given_slices() {
+        let paused = match execute_sliced(
+            vec![NumInstructions::from(100), NumInstructions::from(60)],
+            count_to(250),
+        ) {
+            SliceResult::Paused(paused) => paused,
+            SliceResult::Finished(_) => panic!("Expected the execution to pause."),
+        };
+        assert_eq!(paused.instructions_executed(), NumInstructions::from(160));
+        match paused.resume(NumInstructions::from(100)) {
+            SliceResult::Finished(result) => assert_eq!(result, Ok(3)),
+            SliceResult::Paused(_) => panic!("Expected the execution to finish."),
+        }
+    }
+
+    #[test]
+    fn aborted_execution_terminates() {
+        match execute_sliced(vec![NumInstructions::from(100)], count_to(250)) {
+            SliceResult::Paused(paused) => paused.abort(),
+            SliceResult::Finished(_) => panic!("Expected the execution to pause."),
+        }
+    }
+}
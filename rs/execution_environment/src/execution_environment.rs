@@ -1,24 +1,28 @@
 use crate::{
-    canister_manager::{CanisterManager, CanisterMgrConfig, StopCanisterResult},
+    canister_manager::{
+        CanisterManager, CanisterManagerError, CanisterMgrConfig, InstallCodeResult,
+        StopCanisterResult,
+    },
     canister_settings::CanisterSettings,
+    deterministic_time_slicing::{execute_sliced, PausedExecution, SliceResult},
     execution_environment_metrics::ExecutionEnvironmentMetrics,
     hypervisor::Hypervisor,
     QueryExecutionType,
 };
 use candid::Encode;
 use ic_base_types::PrincipalId;
-use ic_config::execution_environment::Config as ExecutionConfig;
+use ic_config::{execution_environment::Config as ExecutionConfig, feature_status::FeatureStatus};
 use ic_cycles_account_manager::{CyclesAccountManager, IngressInductionCost};
 use ic_ic00_types::{
-    CanisterIdRecord, CanisterSettingsArgs, CreateCanisterArgs, EmptyBlob, InstallCodeArgs,
-    Method as Ic00Method, Payload as Ic00Payload, ProvisionalCreateCanisterWithCyclesArgs,
-    ProvisionalTopUpCanisterArgs, SetControllerArgs, SetupInitialDKGArgs, SignWithECDSAArgs,
-    UpdateSettingsArgs, IC_00,
+    CanisterHttpRequestArgs, CanisterIdRecord, CanisterSettingsArgs, CanisterSnapshotArgs,
+    CreateCanisterArgs, EmptyBlob, InstallCodeArgs, Method as Ic00Method, Payload as Ic00Payload,
+    ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs, SetControllerArgs,
+    SetupInitialDKGArgs, SignWithECDSAArgs, TakeCanisterSnapshotArgs, UpdateSettingsArgs, IC_00,
 };
 use ic_interfaces::{
     execution_environment::{
-        CanisterHeartbeatError, ExecuteMessageResult, ExecutionParameters, HypervisorError,
-        IngressHistoryWriter, SubnetAvailableMemory,
+        CanisterHeartbeatError, CanisterOutOfCyclesError, ExecuteMessageResult,
+        ExecutionParameters, HypervisorError, IngressHistoryWriter, SubnetAvailableMemory,
     },
     messages::{CanisterInputMessage, RequestOrIngress},
 };
@@ -28,17 +32,25 @@ use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_routing_table::RoutingTable;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
-    metadata_state::subnet_call_context_manager::{SetupInitialDkgContext, SignWithEcdsaContext},
-    CallContextAction, CallOrigin, CanisterState, ReplicatedState,
+    metadata_state::subnet_call_context_manager::{
+        CanisterHttpRequestContext, SetupInitialDkgContext, SignWithEcdsaContext,
+    },
+    CallContextAction, CallOrigin, CanisterState, CanisterTimer, ExecutionTask, PausedTask,
+    ReplicatedState, SystemState,
 };
 use ic_types::{
+    canister_http::{
+        MAX_CANISTER_HTTP_REQUESTS_IN_FLIGHT, MAX_CANISTER_HTTP_REQUEST_BYTES,
+        MAX_CANISTER_HTTP_URL_SIZE,
+    },
     canonical_error::{not_found_error, permission_denied_error, CanonicalError},
     crypto::threshold_sig::ni_dkg::NiDkgTargetId,
     ingress::{IngressStatus, WasmResult},
     messages::{
-        is_subnet_message, CallbackId, Ingress, MessageId, Payload, RejectContext, Request,
-        Response, SignedIngressContent, StopCanisterContext,
+        is_subnet_message, CallbackId, CanisterInstallMode, Ingress, MessageId, Payload,
+        RejectContext, Request, Response, SignedIngressContent, StopCanisterContext,
     },
+    methods::SystemMethod,
     user_error::{ErrorCode, RejectCode, UserError},
     CanisterId, CanisterStatusType, ComputeAllocation, Cycles, InstallCodeContext, NumBytes,
     NumInstructions, SubnetId, Time, UserId,
@@ -47,7 +59,13 @@ use ic_types::{
 use mockall::automock;
 use rand::RngCore;
 use std::str::FromStr;
-use std::{collections::BTreeMap, convert::Into, convert::TryFrom, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    convert::Into,
+    convert::TryFrom,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
 use strum::ParseError;
 
 /// ExecutionEnvironment is the component responsible for executing messages
@@ -109,6 +127,54 @@ pub trait ExecutionEnvironment: Sync + Send {
         Result<NumBytes, CanisterHeartbeatError>,
     );
 
+    /// Executes the global timer of a given canister. The timer is deactivated
+    /// once the execution starts, so a canister that is not running or cannot
+    /// pay for the execution keeps its timer.
+    #[allow(clippy::too_many_arguments)]
+    fn execute_canister_global_timer(
+        &self,
+        canister_state: CanisterState,
+        instructions_limit: NumInstructions,
+        routing_table: Arc<RoutingTable>,
+        subnet_records: Arc<BTreeMap<SubnetId, SubnetType>>,
+        time: Time,
+        subnet_available_memory: SubnetAvailableMemory,
+        nns_subnet_id: SubnetId,
+    ) -> (
+        CanisterState,
+        NumInstructions,
+        Result<NumBytes, CanisterHeartbeatError>,
+    );
+
+    /// Resumes the paused execution at the front of the task queue of the
+    /// given canister and runs it for another slice of at most
+    /// `instructions_limit` instructions.
+    fn resume_paused_execution(
+        &self,
+        canister_state: CanisterState,
+        instructions_limit: NumInstructions,
+        routing_table: Arc<RoutingTable>,
+        subnet_records: Arc<BTreeMap<SubnetId, SubnetType>>,
+        nns_subnet_id: SubnetId,
+    ) -> ExecuteMessageResult<CanisterState>;
+
+    /// Resumes the paused `install_code` execution at the front of the task
+    /// queue of the given canister and runs it for another slice of at most
+    /// `instructions_limit` instructions.
+    //
+    // Returns the new replicated state and the number of left instructions.
+    fn resume_install_code(
+        &self,
+        canister_id: CanisterId,
+        state: ReplicatedState,
+        instructions_limit: NumInstructions,
+    ) -> (ReplicatedState, NumInstructions);
+
+    /// Aborts the paused execution of the given canister, if there is one.
+    /// The message of the aborted execution stays in the task queue of the
+    /// canister and is executed again from scratch later.
+    fn abort_paused_execution(&self, canister_state: &mut CanisterState);
+
     /// Look up the current amount of memory available on the subnet.
     /// EXC-185 will make this method obsolete.
     fn subnet_available_memory(&self, state: &ReplicatedState) -> i64;
@@ -139,6 +205,42 @@ pub struct ExecutionEnvironmentImpl {
     cycles_account_manager: Arc<CyclesAccountManager>,
     own_subnet_id: SubnetId,
     own_subnet_type: SubnetType,
+    max_stable_memory_size: NumBytes,
+    paused_executions: Mutex<BTreeMap<CanisterId, (PausedTask, PausedState)>>,
+}
+
+/// The output of `Hypervisor::execute_update()`.
+type UpdateOutput = (CanisterState, NumInstructions, CallContextAction, NumBytes);
+
+/// The output of `CanisterManager::install_or_upgrade()`.
+type InstallCodeOutput = (
+    NumInstructions,
+    Result<(NumBytes, CanisterState), CanisterManagerError>,
+);
+
+/// The part of a paused execution that lives only in memory: the thread that
+/// runs the execution and the state the execution started from. The part
+/// that is persisted is the `PausedTask` in the task queue of the canister.
+enum PausedState {
+    Update {
+        execution: PausedExecution<UpdateOutput>,
+        initial_canister: CanisterState,
+        routing_table: Arc<RoutingTable>,
+        subnet_records: Arc<BTreeMap<SubnetId, SubnetType>>,
+    },
+    InstallCode {
+        execution: PausedExecution<InstallCodeOutput>,
+        initial_canister: CanisterState,
+    },
+}
+
+impl PausedState {
+    fn abort(self) {
+        match self {
+            PausedState::Update { execution, .. } => execution.abort(),
+            PausedState::InstallCode { execution, .. } => execution.abort(),
+        }
+    }
 }
 
 impl ExecutionEnvironment for ExecutionEnvironmentImpl {
@@ -161,6 +263,17 @@ impl ExecutionEnvironment for ExecutionEnvironmentImpl {
 
         let mut msg = match msg {
             CanisterInputMessage::Response(response) => {
+                let transform = state
+                    .metadata
+                    .subnet_call_context_manager
+                    .canister_http_request_contexts
+                    .get(&response.originator_reply_callback)
+                    .and_then(|context| {
+                        context
+                            .transform_method_name
+                            .clone()
+                            .map(|method_name| (context.request.sender, method_name))
+                    });
                 let request = state
                     .metadata
                     .subnet_call_context_manager
@@ -168,14 +281,25 @@ impl ExecutionEnvironment for ExecutionEnvironmentImpl {
                 return match request {
                     None => (state, instructions_limit),
                     Some(request) => {
+                        let (response_payload, instructions_left) = match transform {
+                            None => (response.response_payload, instructions_limit),
+                            Some((canister_id, method_name)) => self
+                                .transform_canister_http_response(
+                                    canister_id,
+                                    &method_name,
+                                    response.response_payload,
+                                    &state,
+                                    instructions_limit,
+                                ),
+                        };
                         state.push_subnet_output_response(Response {
                             originator: request.sender,
                             respondent: CanisterId::from(self.own_subnet_id),
                             originator_reply_callback: request.sender_reply_callback,
                             refund: request.payment,
-                            response_payload: response.response_payload,
+                            response_payload,
                         });
-                        (state, instructions_limit)
+                        (state, instructions_left)
                     }
                 };
             }
@@ -186,6 +310,18 @@ impl ExecutionEnvironment for ExecutionEnvironmentImpl {
 
         let method = Ic00Method::from_str(msg.method_name());
         let payload = msg.method_payload();
+
+        // Some subnet messages modify the canister they operate on, so a
+        // paused execution of that canister has to be aborted first. Only
+        // the controllers of the canister can send these messages, so they
+        // cannot starve its long-running executions.
+        if interrupts_paused_execution(&method) {
+            if let Some(canister_id) = target_canister_id(&method, payload) {
+                if let Some(canister) = state.canister_state_mut(&canister_id) {
+                    self.abort_paused_execution(canister);
+                }
+            }
+        }
         let (result, instructions_left) = match method {
             Ok(Ic00Method::CreateCanister) => {
                 match &mut msg { RequestOrIngress::Ingress(_) =>
@@ -227,9 +363,9 @@ impl ExecutionEnvironment for ExecutionEnvironmentImpl {
 
             Ok(Ic00Method::InstallCode) => {
                 let (res, instructions_left) = match InstallCodeArgs::decode(payload) {
-                    Err(err) => (Err(err.into()), instructions_limit),
+                    Err(err) => (Some(Err(err.into())), instructions_limit),
                     Ok(args) => match InstallCodeContext::try_from((*msg.sender(), args)) {
-                        Err(err) => (Err(err.into()), instructions_limit),
+                        Err(err) => (Some(Err(err.into())), instructions_limit),
                         Ok(install_context) => {
                             let canister_id = install_context.canister_id;
                             info!(
@@ -245,51 +381,46 @@ impl ExecutionEnvironment for ExecutionEnvironmentImpl {
                             let execution_parameters = ExecutionParameters {
                                 instruction_limit: instructions_limit,
                                 canister_memory_limit: self.config.max_canister_memory_size,
+                                max_stable_memory_size: self.max_stable_memory_size,
                                 subnet_available_memory,
                                 compute_allocation: ComputeAllocation::default(),
                                 subnet_type: state.metadata.own_subnet_type,
                             };
 
-                            let (instructions_left, result) = self.canister_manager.install_code(
-                                install_context,
-                                &mut state,
-                                execution_parameters,
-                            );
-
-                            let execution_duration = timer.elapsed();
-
-                            let result = match result {
-                                Ok(result) => {
-                                    state.metadata.heap_delta_estimate += result.heap_delta;
-
-                                    info!(
-                                        self.log,
-                                        "Finished executing install_code message on canister {:?} after {:?}, old wasm hash {:?}, new wasm hash {:?}",
-                                        canister_id,
-                                        execution_duration,
-                                        result.old_wasm_hash,
-                                        result.new_wasm_hash,
-                                    );
-
-                                    (Ok(EmptyBlob::encode()), instructions_left)
-                                }
-                                Err(err) => {
-                                    info!(
-                                        self.log,
-                                        "Finished executing install_code message on canister {:?} after {:?} with error: {:?}",
-                                        canister_id,
-                                        execution_duration,
-                                        err
-                                    );
-                                    (Err(err.into()), instructions_left)
-                                }
-                            };
+                            let (result, instructions_left) =
+                                if self.config.deterministic_time_slicing == FeatureStatus::Enabled
+                                {
+                                    self.execute_install_code_sliced(
+                                        install_context,
+                                        &msg,
+                                        &mut state,
+                                        execution_parameters,
+                                        instructions_limit,
+                                    )
+                                } else {
+                                    let (instructions_left, result) =
+                                        self.canister_manager.install_code(
+                                            install_context,
+                                            &mut state,
+                                            execution_parameters,
+                                        );
+                                    (Some(result), instructions_left)
+                                };
 
-                            result
+                            // The result is `None` if the execution was paused.
+                            let res = result.map(|result| {
+                                self.install_code_response(
+                                    canister_id,
+                                    result,
+                                    timer.elapsed(),
+                                    &mut state,
+                                )
+                            });
+                            (res, instructions_left)
                         }
                     },
                 };
-                (Some((res, msg.take_cycles())), instructions_left)
+                (res.map(|res| (res, msg.take_cycles())), instructions_left)
             }
 
             Ok(Ic00Method::UninstallCode) => {
@@ -409,6 +540,75 @@ impl ExecutionEnvironment for ExecutionEnvironmentImpl {
                 (Some((res, msg.take_cycles())), instructions_limit)
             }
 
+            Ok(Ic00Method::TakeCanisterSnapshot) => {
+                let res = match TakeCanisterSnapshotArgs::decode(payload) {
+                    Err(err) => Err(err.into()),
+                    Ok(args) => {
+                        let canister_id = args.get_canister_id();
+                        self.canister_manager
+                            .take_canister_snapshot(
+                                *msg.sender(),
+                                canister_id,
+                                args.replace_snapshot,
+                                &mut state,
+                            )
+                            .map(|response| response.encode())
+                            .map_err(|err| err.into())
+                    }
+                };
+                (Some((res, msg.take_cycles())), instructions_limit)
+            }
+
+            Ok(Ic00Method::LoadCanisterSnapshot) => {
+                let res = match CanisterSnapshotArgs::decode(payload) {
+                    Err(err) => Err(err.into()),
+                    Ok(args) => {
+                        let canister_id = args.get_canister_id();
+                        self.canister_manager
+                            .load_canister_snapshot(
+                                *msg.sender(),
+                                canister_id,
+                                args.snapshot_id,
+                                &mut state,
+                            )
+                            .map(|()| EmptyBlob::encode())
+                            .map_err(|err| err.into())
+                    }
+                };
+                (Some((res, msg.take_cycles())), instructions_limit)
+            }
+
+            Ok(Ic00Method::ListCanisterSnapshots) => {
+                let res = match CanisterIdRecord::decode(payload) {
+                    Err(err) => Err(err.into()),
+                    Ok(args) => self.list_canister_snapshots(
+                        *msg.sender(),
+                        args.get_canister_id(),
+                        &mut state,
+                    ),
+                };
+                (Some((res, msg.take_cycles())), instructions_limit)
+            }
+
+            Ok(Ic00Method::DeleteCanisterSnapshot) => {
+                let res = match CanisterSnapshotArgs::decode(payload) {
+                    Err(err) => Err(err.into()),
+                    Ok(args) => {
+                        let canister_id = args.get_canister_id();
+                        self.canister_manager
+                            .delete_canister_snapshot(
+                                *msg.sender(),
+                                canister_id,
+                                args.snapshot_id,
+                                &mut state,
+                            )
+                            .map(|()| EmptyBlob::encode())
+                            .map_err(|err| err.into())
+                    }
+                };
+                (Some((res, msg.take_cycles())), instructions_limit)
+            }
+
             Ok(Ic00Method::RawRand) => {
                 let res = match EmptyBlob::decode(payload) {
                     Err(err) => Err(err.into()),
@@ -421,6 +621,17 @@ impl ExecutionEnvironment for ExecutionEnvironmentImpl {
                 (Some((res, msg.take_cycles())), instructions_limit)
             }
 
+            Ok(Ic00Method::FetchCanisterLogs) => {
+                let res = Err(UserError::new(
+                    ErrorCode::CanisterContractViolation,
+                    format!(
+                        "{} API is only accessible via query calls",
+                        Ic00Method::FetchCanisterLogs
+                    ),
+                ));
+                (Some((res, msg.take_cycles())), instructions_limit)
+            }
+
             Ok(Ic00Method::DepositCycles) => match CanisterIdRecord::decode(payload) {
                 Err(err) => (
                     Some((Err(err.into()), msg.take_cycles())),
@@ -572,6 +783,29 @@ impl ExecutionEnvironment for ExecutionEnvironmentImpl {
                 (res, instructions_limit)
             }
 
+            Ok(Ic00Method::HttpRequest) => match &msg {
+                RequestOrIngress::Request(request) => {
+                    let res = match CanisterHttpRequestArgs::decode(payload) {
+                        Err(err) => Some((Err(err.into()), msg.take_cycles())),
+                        Ok(args) => self
+                            .http_request(request, args, &mut state)
+                            .map_or_else(|err| Some((Err(err), msg.take_cycles())), |()| None),
+                    };
+                    (res, instructions_limit)
+                }
+                RequestOrIngress::Ingress(_) => {
+                    error!(self.log, "[EXC-BUG] Ingress messages to HttpRequest should've been filtered earlier.");
+                    let error_string = format!(
+                        "HttpRequest is called by user {}. It can only be called by a canister.",
+                        msg.sender()
+                    );
+                    let user_error =
+                        UserError::new(ErrorCode::CanisterContractViolation, error_string);
+                    let res = Some((Err(user_error), msg.take_cycles()));
+                    (res, instructions_limit)
+                }
+            },
+
             Ok(Ic00Method::ProvisionalCreateCanisterWithCycles) => {
                 let res = match ProvisionalCreateCanisterWithCyclesArgs::decode(payload) {
                     Err(err) => Err(err.into()),
@@ -657,6 +891,42 @@ impl ExecutionEnvironment for ExecutionEnvironmentImpl {
         subnet_available_memory: SubnetAvailableMemory,
         nns_subnet_id: SubnetId,
     ) -> ExecuteMessageResult<CanisterState> {
+        // Calls of update methods of running canisters are executed in slices
+        // if deterministic time slicing is enabled. All other messages,
+        // including the ones that are going to be rejected, are handled below.
+        let msg = match msg {
+            CanisterInputMessage::Request(request)
+                if self.should_slice(&canister, &request.method_name) =>
+            {
+                return self.execute_update_sliced(
+                    canister,
+                    instructions_limit,
+                    RequestOrIngress::Request(request),
+                    time,
+                    routing_table,
+                    subnet_records,
+                    subnet_available_memory,
+                    nns_subnet_id,
+                );
+            }
+            CanisterInputMessage::Ingress(ingress)
+                if ingress.expiry_time >= time
+                    && self.should_slice(&canister, &ingress.method_name) =>
+            {
+                return self.execute_update_sliced(
+                    canister,
+                    instructions_limit,
+                    RequestOrIngress::Ingress(ingress),
+                    time,
+                    routing_table,
+                    subnet_records,
+                    subnet_available_memory,
+                    nns_subnet_id,
+                );
+            }
+            msg => msg,
+        };
+
         let (should_refund_remaining_cycles, mut res) = match msg {
             CanisterInputMessage::Request(request) => {
                 let memory_usage = canister.memory_usage(self.own_subnet_type);
@@ -668,15 +938,12 @@ impl ExecutionEnvironment for ExecutionEnvironmentImpl {
                     instructions_limit,
                 ) {
                     // Canister is out of cycles. Reject the request.
-                    return self.reject_request(
+                    return self.reject_due_to_out_of_cycles(
                         canister,
                         instructions_limit,
-                        request,
-                        RejectContext {
-                            code: RejectCode::SysTransient,
-                            message: err.to_string(),
-                        },
-                        NumBytes::from(0),
+                        RequestOrIngress::Request(request),
+                        err,
+                        time,
                     );
                 }
                 (
@@ -704,24 +971,13 @@ impl ExecutionEnvironment for ExecutionEnvironmentImpl {
                     instructions_limit,
                 ) {
                     // Canister is out of cycles. Reject the request.
-                    let canister_id = canister.canister_id();
-                    return ExecuteMessageResult {
+                    return self.reject_due_to_out_of_cycles(
                         canister,
-                        num_instructions_left: instructions_limit,
-                        ingress_status: Some((
-                            ingress.message_id,
-                            IngressStatus::Failed {
-                                receiver: canister_id.get(),
-                                user_id: ingress.source,
-                                error: UserError::new(
-                                    ErrorCode::CanisterOutOfCycles,
-                                    err.to_string(),
-                                ),
-                                time,
-                            },
-                        )),
-                        heap_delta: NumBytes::from(0),
-                    };
+                        instructions_limit,
+                        RequestOrIngress::Ingress(ingress),
+                        err,
+                        time,
+                    );
                 }
                 (
                     true,
@@ -764,7 +1020,7 @@ impl ExecutionEnvironment for ExecutionEnvironmentImpl {
 
     fn execute_canister_heartbeat(
         &self,
-        mut canister: CanisterState,
+        canister: CanisterState,
         instructions_limit: NumInstructions,
         routing_table: Arc<RoutingTable>,
         subnet_records: Arc<BTreeMap<SubnetId, SubnetType>>,
@@ -776,56 +1032,42 @@ impl ExecutionEnvironment for ExecutionEnvironmentImpl {
         NumInstructions,
         Result<NumBytes, CanisterHeartbeatError>,
     ) {
-        if canister.status() != CanisterStatusType::Running {
-            let status = canister.status();
-            return (
-                canister,
-                instructions_limit,
-                Err(CanisterHeartbeatError::CanisterNotRunning { status }),
-            );
-        }
-
-        let memory_usage = canister.memory_usage(self.own_subnet_type);
-        let compute_allocation = canister.scheduler_state.compute_allocation;
-        if let Err(err) = self.cycles_account_manager.withdraw_execution_cycles(
-            &mut canister.system_state,
-            memory_usage,
-            compute_allocation,
+        self.execute_canister_system_task(
+            SystemMethod::CanisterHeartbeat,
+            canister,
             instructions_limit,
-        ) {
-            return (
-                canister,
-                instructions_limit,
-                Err(CanisterHeartbeatError::OutOfCycles(err)),
-            );
-        }
-
-        let execution_parameters =
-            self.execution_parameters(&canister, instructions_limit, subnet_available_memory);
-
-        let (mut canister, num_instructions_left, result) =
-            self.hypervisor.execute_canister_heartbeat(
-                canister,
-                routing_table,
-                subnet_records,
-                time,
-                execution_parameters,
-                nns_subnet_id,
-            );
-
-        // Clone the `cycles_account_manager` to avoid having to require 'static
-        // lifetime bound on `self`.
-        let cycles_account_manager = Arc::clone(&self.cycles_account_manager);
-
-        // Refund the canister with any cycles left after message execution.
-        cycles_account_manager
-            .refund_execution_cycles(&mut canister.system_state, num_instructions_left);
-        let result = match result {
-            Ok(heap_delta) => Ok(heap_delta),
-            Err(err) => Err(CanisterHeartbeatError::CanisterExecutionFailed(err)),
-        };
+            routing_table,
+            subnet_records,
+            time,
+            subnet_available_memory,
+            nns_subnet_id,
+        )
+    }
 
-        (canister, num_instructions_left, result)
+    fn execute_canister_global_timer(
+        &self,
+        canister: CanisterState,
+        instructions_limit: NumInstructions,
+        routing_table: Arc<RoutingTable>,
+        subnet_records: Arc<BTreeMap<SubnetId, SubnetType>>,
+        time: Time,
+        subnet_available_memory: SubnetAvailableMemory,
+        nns_subnet_id: SubnetId,
+    ) -> (
+        CanisterState,
+        NumInstructions,
+        Result<NumBytes, CanisterHeartbeatError>,
+    ) {
+        self.execute_canister_system_task(
+            SystemMethod::CanisterGlobalTimer,
+            canister,
+            instructions_limit,
+            routing_table,
+            subnet_records,
+            time,
+            subnet_available_memory,
+            nns_subnet_id,
+        )
     }
 
     fn max_canister_memory_size(&self) -> NumBytes {
@@ -841,35 +1083,221 @@ impl ExecutionEnvironment for ExecutionEnvironmentImpl {
         ExecutionParameters {
             instruction_limit,
             canister_memory_limit: canister.memory_limit(self.config.max_canister_memory_size),
+            max_stable_memory_size: self.max_stable_memory_size,
             subnet_available_memory,
             compute_allocation: canister.scheduler_state.compute_allocation,
             subnet_type: self.own_subnet_type,
         }
     }
-}
 
-impl ExecutionEnvironmentImpl {
-    #[allow(clippy::too_many_arguments)]
-    pub fn new(
-        log: ReplicaLogger,
-        hypervisor: Arc<Hypervisor>,
-        ingress_history_writer: Arc<dyn IngressHistoryWriter<State = ReplicatedState>>,
-        metrics_registry: &MetricsRegistry,
-        own_subnet_id: SubnetId,
-        own_subnet_type: SubnetType,
-        num_cores: usize,
-        config: ExecutionConfig,
-        cycles_account_manager: Arc<CyclesAccountManager>,
-    ) -> Self {
-        let canister_manager_config: CanisterMgrConfig = CanisterMgrConfig::new(
-            config.subnet_memory_capacity,
-            config.max_cycles_per_canister,
-            config.default_provisional_cycles_balance,
-            config.default_freeze_threshold,
-            own_subnet_id,
+    fn resume_paused_execution(
+        &self,
+        mut canister: CanisterState,
+        instructions_limit: NumInstructions,
+        routing_table: Arc<RoutingTable>,
+        subnet_records: Arc<BTreeMap<SubnetId, SubnetType>>,
+        nns_subnet_id: SubnetId,
+    ) -> ExecuteMessageResult<CanisterState> {
+        let task = match canister.system_state.task_queue.pop_front() {
+            Some(ExecutionTask::PausedExecution(task)) => task,
+            task => fatal!(
+                self.log,
+                "Expected a paused execution of canister {}, found {:?}.",
+                canister.canister_id(),
+                task
+            ),
+        };
+        let slice_instruction_limit = self.slice_instruction_limit(instructions_limit);
+        let (result, initial_canister) =
+            match self.take_paused_execution(canister.canister_id(), &task) {
+                Some(PausedState::Update {
+                    execution,
+                    initial_canister,
+                    routing_table: initial_routing_table,
+                    subnet_records: initial_subnet_records,
+                }) if initial_routing_table == routing_table
+                    && initial_subnet_records == subnet_records =>
+                {
+                    (execution.resume(slice_instruction_limit), initial_canister)
+                }
+                paused => {
+                    // The execution is not in memory, e.g. because the replica
+                    // restarted from a checkpoint, or it observed a network
+                    // topology that has changed since. It is recreated, so
+                    // that it continues exactly like on every other replica.
+                    if let Some(paused) = paused {
+                        paused.abort();
+                    }
+                    let initial_canister = sliced_execution_initial_canister(&canister, &task);
+                    let result = self.execute_update_slices(
+                        initial_canister.clone(),
+                        &task,
+                        slice_instruction_limit,
+                        Arc::clone(&routing_table),
+                        Arc::clone(&subnet_records),
+                        nns_subnet_id,
+                    );
+                    (result, initial_canister)
+                }
+            };
+        self.process_update_slice(
+            result,
+            task,
+            slice_instruction_limit,
+            initial_canister,
+            routing_table,
+            subnet_records,
+            canister,
+            instructions_limit,
+        )
+    }
+
+    fn resume_install_code(
+        &self,
+        canister_id: CanisterId,
+        mut state: ReplicatedState,
+        instructions_limit: NumInstructions,
+    ) -> (ReplicatedState, NumInstructions) {
+        let timer = Timer::start();
+        let task = state
+            .canister_state_mut(&canister_id)
+            .and_then(|canister| canister.system_state.task_queue.pop_front());
+        let task = match task {
+            Some(ExecutionTask::PausedInstallCode(task)) => task,
+            task => fatal!(
+                self.log,
+                "Expected a paused install_code execution of canister {}, found {:?}.",
+                canister_id,
+                task
+            ),
+        };
+        let install_context = match install_code_context(&task.message) {
+            Ok(install_context) => install_context,
+            Err(err) => fatal!(
+                self.log,
+                "The paused install_code message of canister {} cannot be decoded: {}",
+                canister_id,
+                err
+            ),
+        };
+        let mode = install_context.mode;
+        let slice_instruction_limit = self.slice_instruction_limit(instructions_limit);
+        let (result, initial_canister) = match self.take_paused_execution(canister_id, &task) {
+            Some(PausedState::InstallCode {
+                execution,
+                initial_canister,
+            }) => (execution.resume(slice_instruction_limit), initial_canister),
+            paused => {
+                // The execution is not in memory, e.g. because the replica
+                // restarted from a checkpoint, so it is recreated.
+                if let Some(paused) = paused {
+                    paused.abort();
+                }
+                let canister = state
+                    .canister_state(&canister_id)
+                    .expect("A canister with a paused execution must exist.");
+                let initial_canister = sliced_execution_initial_canister(canister, &task);
+                let execution_parameters = self.canister_manager.install_code_execution_parameters(
+                    &initial_canister,
+                    ExecutionParameters {
+                        instruction_limit: self.config.max_instructions_per_sliced_install_code,
+                        canister_memory_limit: self.config.max_canister_memory_size,
+                        max_stable_memory_size: self.max_stable_memory_size,
+                        subnet_available_memory: SubnetAvailableMemory::new(
+                            task.subnet_available_memory,
+                        ),
+                        compute_allocation: ComputeAllocation::default(),
+                        subnet_type: state.metadata.own_subnet_type,
+                    },
+                );
+                let result = self.execute_install_code_slices(
+                    install_context,
+                    initial_canister.clone(),
+                    &task,
+                    slice_instruction_limit,
+                    execution_parameters,
+                    state.path().to_path_buf(),
+                );
+                (result, initial_canister)
+            }
+        };
+        let mut msg = task.message.clone();
+        let (result, instructions_left) = self.process_install_code_slice(
+            result,
+            task,
+            slice_instruction_limit,
+            initial_canister,
+            mode,
+            &mut state,
+            instructions_limit,
+        );
+        match result {
+            Some(result) => {
+                let res =
+                    self.install_code_response(canister_id, result, timer.elapsed(), &mut state);
+                self.metrics
+                    .observe_subnet_message(msg.method_name(), timer, &res);
+                let refund = msg.take_cycles();
+                let state = self.output_subnet_response(msg, state, res, refund);
+                (state, instructions_left)
+            }
+            None => (state, instructions_left),
+        }
+    }
+
+    fn abort_paused_execution(&self, canister: &mut CanisterState) {
+        let (task, instruction_limit, aborted_task) = match canister.system_state.task_queue.front()
+        {
+            Some(ExecutionTask::PausedExecution(task)) => (
+                task.clone(),
+                self.config.max_instructions_per_sliced_message,
+                ExecutionTask::AbortedExecution(task.message.clone()),
+            ),
+            Some(ExecutionTask::PausedInstallCode(task)) => (
+                task.clone(),
+                self.config.max_instructions_per_sliced_install_code,
+                ExecutionTask::AbortedInstallCode(task.message.clone()),
+            ),
+            _ => return,
+        };
+        if let Some(paused) = self.take_paused_execution(canister.canister_id(), &task) {
+            paused.abort();
+        }
+        // The instructions executed so far are lost, but the canister still
+        // pays for them.
+        self.cycles_account_manager.refund_execution_cycles(
+            &mut canister.system_state,
+            instruction_limit - task.instructions_executed,
+        );
+        canister.system_state.task_queue[0] = aborted_task;
+    }
+}
+
+impl ExecutionEnvironmentImpl {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        log: ReplicaLogger,
+        hypervisor: Arc<Hypervisor>,
+        ingress_history_writer: Arc<dyn IngressHistoryWriter<State = ReplicatedState>>,
+        metrics_registry: &MetricsRegistry,
+        own_subnet_id: SubnetId,
+        own_subnet_type: SubnetType,
+        num_cores: usize,
+        max_stable_memory_size: NumBytes,
+        config: ExecutionConfig,
+        cycles_account_manager: Arc<CyclesAccountManager>,
+    ) -> Self {
+        let canister_manager_config: CanisterMgrConfig = CanisterMgrConfig::new(
+            config.subnet_memory_capacity,
+            config.max_cycles_per_canister,
+            config.default_provisional_cycles_balance,
+            config.default_freeze_threshold,
+            own_subnet_id,
             own_subnet_type,
             config.max_controllers,
             num_cores,
+            config.subnet_reservation_threshold_percent,
+            config.default_reserved_balance_limit,
         );
         let canister_manager = CanisterManager::new(
             Arc::clone(&hypervisor),
@@ -888,9 +1316,88 @@ impl ExecutionEnvironmentImpl {
             cycles_account_manager,
             own_subnet_id,
             own_subnet_type,
+            max_stable_memory_size,
+            paused_executions: Mutex::new(BTreeMap::new()),
         }
     }
 
+    // Executes `canister_heartbeat` or `canister_global_timer` after charging
+    // the canister for the execution.
+    #[allow(clippy::too_many_arguments)]
+    fn execute_canister_system_task(
+        &self,
+        system_task: SystemMethod,
+        mut canister: CanisterState,
+        instructions_limit: NumInstructions,
+        routing_table: Arc<RoutingTable>,
+        subnet_records: Arc<BTreeMap<SubnetId, SubnetType>>,
+        time: Time,
+        subnet_available_memory: SubnetAvailableMemory,
+        nns_subnet_id: SubnetId,
+    ) -> (
+        CanisterState,
+        NumInstructions,
+        Result<NumBytes, CanisterHeartbeatError>,
+    ) {
+        if canister.status() != CanisterStatusType::Running {
+            let status = canister.status();
+            return (
+                canister,
+                instructions_limit,
+                Err(CanisterHeartbeatError::CanisterNotRunning { status }),
+            );
+        }
+
+        let memory_usage = canister.memory_usage(self.own_subnet_type);
+        let compute_allocation = canister.scheduler_state.compute_allocation;
+        if let Err(err) = self.cycles_account_manager.withdraw_execution_cycles(
+            &mut canister.system_state,
+            memory_usage,
+            compute_allocation,
+            instructions_limit,
+        ) {
+            return (
+                canister,
+                instructions_limit,
+                Err(CanisterHeartbeatError::OutOfCycles(err)),
+            );
+        }
+
+        // The timer is deactivated when its execution starts, so that it fires
+        // only once even if the execution fails.
+        if system_task == SystemMethod::CanisterGlobalTimer {
+            canister.system_state.global_timer = CanisterTimer::Inactive;
+        }
+
+        let execution_parameters =
+            self.execution_parameters(&canister, instructions_limit, subnet_available_memory);
+
+        let (mut canister, num_instructions_left, result) =
+            self.hypervisor.execute_canister_system_task(
+                system_task,
+                canister,
+                routing_table,
+                subnet_records,
+                time,
+                execution_parameters,
+                nns_subnet_id,
+            );
+
+        // Clone the `cycles_account_manager` to avoid having to require 'static
+        // lifetime bound on `self`.
+        let cycles_account_manager = Arc::clone(&self.cycles_account_manager);
+
+        // Refund the canister with any cycles left after message execution.
+        cycles_account_manager
+            .refund_execution_cycles(&mut canister.system_state, num_instructions_left);
+        let result = match result {
+            Ok(heap_delta) => Ok(heap_delta),
+            Err(err) => Err(CanisterHeartbeatError::CanisterExecutionFailed(err)),
+        };
+
+        (canister, num_instructions_left, result)
+    }
+
     fn create_canister(
         &self,
         sender: PrincipalId,
@@ -1000,6 +1507,20 @@ impl ExecutionEnvironmentImpl {
             .map_err(|err| err.into())
     }
 
+    fn list_canister_snapshots(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        state: &mut ReplicatedState,
+    ) -> Result<Vec<u8>, UserError> {
+        let canister = get_canister_mut(canister_id, state)?;
+
+        self.canister_manager
+            .list_canister_snapshots(sender, canister)
+            .map(|snapshots| Encode!(&snapshots).unwrap())
+            .map_err(|err| err.into())
+    }
+
     fn stop_canister(
         &self,
         canister_id: CanisterId,
@@ -1318,6 +1839,47 @@ impl ExecutionEnvironmentImpl {
         }
     }
 
+    // Helper function to produce the result of a message that cannot be
+    // executed because the canister cannot pay for its execution.
+    fn reject_due_to_out_of_cycles(
+        &self,
+        canister: CanisterState,
+        num_instructions_left: NumInstructions,
+        msg: RequestOrIngress,
+        err: CanisterOutOfCyclesError,
+        time: Time,
+    ) -> ExecuteMessageResult<CanisterState> {
+        match msg {
+            RequestOrIngress::Request(request) => self.reject_request(
+                canister,
+                num_instructions_left,
+                request,
+                RejectContext {
+                    code: RejectCode::SysTransient,
+                    message: err.to_string(),
+                },
+                NumBytes::from(0),
+            ),
+            RequestOrIngress::Ingress(ingress) => {
+                let canister_id = canister.canister_id();
+                ExecuteMessageResult {
+                    canister,
+                    num_instructions_left,
+                    ingress_status: Some((
+                        ingress.message_id,
+                        IngressStatus::Failed {
+                            receiver: canister_id.get(),
+                            user_id: ingress.source,
+                            error: UserError::new(ErrorCode::CanisterOutOfCycles, err.to_string()),
+                            time,
+                        },
+                    )),
+                    heap_delta: NumBytes::from(0),
+                }
+            }
+        }
+    }
+
     // Execute an update method from an inter-canister request.
     #[allow(clippy::too_many_arguments)]
     fn execute_update_method_for_request(
@@ -1844,6 +2406,159 @@ impl ExecutionEnvironmentImpl {
         Ok(())
     }
 
+    fn http_request(
+        &self,
+        request: &Request,
+        args: CanisterHttpRequestArgs,
+        state: &mut ReplicatedState,
+    ) -> Result<(), UserError> {
+        if !state.metadata.own_subnet_features.http_requests {
+            return Err(UserError::new(
+                ErrorCode::CanisterContractViolation,
+                "This API is not enabled on this subnet",
+            ));
+        }
+        if !args.url.starts_with("https://") {
+            return Err(UserError::new(
+                ErrorCode::InvalidManagementPayload,
+                format!("Only HTTPS URLs are supported, got {}", args.url),
+            ));
+        }
+        if args.url.len() > MAX_CANISTER_HTTP_URL_SIZE {
+            return Err(UserError::new(
+                ErrorCode::InvalidManagementPayload,
+                format!(
+                    "The URL of a HTTP request must not be longer than {} bytes",
+                    MAX_CANISTER_HTTP_URL_SIZE
+                ),
+            ));
+        }
+        let request_size = args.url.len()
+            + args
+                .headers
+                .iter()
+                .map(|header| header.name.len() + header.value.len())
+                .sum::<usize>()
+            + args.body.as_ref().map_or(0, |body| body.len());
+        if request_size > MAX_CANISTER_HTTP_REQUEST_BYTES {
+            return Err(UserError::new(
+                ErrorCode::InvalidManagementPayload,
+                format!(
+                    "The HTTP request is {} bytes, which exceeds the limit of {} bytes",
+                    request_size, MAX_CANISTER_HTTP_REQUEST_BYTES
+                ),
+            ));
+        }
+        let fee = self
+            .cycles_account_manager
+            .http_request_fee(NumBytes::from(request_size as u64));
+        if request.payment < fee {
+            return Err(UserError::new(
+                ErrorCode::CanisterRejectedMessage,
+                format!(
+                    "The HTTP request was sent with {} cycles, but {} cycles are required",
+                    request.payment, fee
+                ),
+            ));
+        }
+        let open_requests = state
+            .metadata
+            .subnet_call_context_manager
+            .canister_http_request_contexts
+            .len();
+        if open_requests >= MAX_CANISTER_HTTP_REQUESTS_IN_FLIGHT {
+            return Err(UserError::new(
+                ErrorCode::SubnetOversubscribed,
+                format!(
+                    "The subnet already has {} open HTTP requests, try again later",
+                    open_requests
+                ),
+            ));
+        }
+        // The transform method is executed on this subnet when the response
+        // arrives, so the calling canister has to live here as well.
+        if args.transform_method_name.is_some() && state.canister_state(&request.sender).is_none() {
+            return Err(UserError::new(
+                ErrorCode::CanisterContractViolation,
+                format!(
+                    "HTTP requests with a transform method can only be made by canisters on subnet {}, but {} is not on it",
+                    self.own_subnet_id,
+                    request.sender
+                ),
+            ));
+        }
+
+        info!(
+            self.log,
+            "Received a HttpRequest for {} from {:?}", args.url, request.sender
+        );
+        // The fee is kept, only the rest of the payment is refunded with the
+        // response.
+        let mut request = request.clone();
+        request.payment -= fee;
+        let context = CanisterHttpRequestContext {
+            request,
+            url: args.url,
+            http_method: args.method.into(),
+            headers: args.headers.into_iter().map(Into::into).collect(),
+            body: args.body,
+            transform_method_name: args.transform_method_name,
+            time: state.time(),
+        };
+        state
+            .metadata
+            .subnet_call_context_manager
+            .push_http_request(context);
+        Ok(())
+    }
+
+    /// Passes the response to a HTTP request through the transform method of
+    /// the canister that made the request. The method is executed as a
+    /// replicated query, so it cannot modify the state of the canister.
+    /// Rejects are delivered as they are.
+    fn transform_canister_http_response(
+        &self,
+        canister_id: CanisterId,
+        transform_method_name: &str,
+        response_payload: Payload,
+        state: &ReplicatedState,
+        instructions_limit: NumInstructions,
+    ) -> (Payload, NumInstructions) {
+        let data = match response_payload {
+            Payload::Data(data) => data,
+            Payload::Reject(_) => return (response_payload, instructions_limit),
+        };
+        let canister = match state.canister_state(&canister_id) {
+            Some(canister) => canister.clone(),
+            None => {
+                let reject = RejectContext::new(
+                    RejectCode::DestinationInvalid,
+                    format!("Canister {} not found", canister_id),
+                );
+                return (Payload::Reject(reject), instructions_limit);
+            }
+        };
+        // Letting the canister grow arbitrarily when executing the
+        // query is fine as we do not persist state modifications.
+        let subnet_available_memory =
+            SubnetAvailableMemory::new(self.config.subnet_memory_capacity.get() as i64);
+        let execution_parameters =
+            self.execution_parameters(&canister, instructions_limit, subnet_available_memory);
+        let (canister, instructions_left, result) = self.hypervisor.execute_query(
+            QueryExecutionType::Replicated,
+            transform_method_name,
+            &data,
+            IC_00.get(),
+            canister,
+            None,
+            state.time(),
+            execution_parameters,
+        );
+        let result = result
+            .map_err(|err| self.log_and_transform_to_user_error(err, &canister.canister_id()));
+        (Payload::from(result), instructions_left)
+    }
+
     fn get_ingress_status(
         &self,
         canister: &mut CanisterState,
@@ -1929,6 +2644,465 @@ impl ExecutionEnvironmentImpl {
         ingress_status.map(|status| (message_id, status))
     }
 
+    // Returns true if a call of the given method should be executed in slices.
+    // Only update methods of running canisters are sliced.
+    fn should_slice(&self, canister: &CanisterState, method_name: &str) -> bool {
+        self.config.deterministic_time_slicing == FeatureStatus::Enabled
+            && canister.status() == CanisterStatusType::Running
+            && !canister.exports_query_method(method_name.to_string())
+    }
+
+    // Returns the instruction limit of the next slice of an execution given
+    // the number of instructions the scheduler allows to execute.
+    fn slice_instruction_limit(&self, instructions_limit: NumInstructions) -> NumInstructions {
+        std::cmp::min(self.config.max_instructions_per_slice, instructions_limit)
+    }
+
+    // Keeps the given paused execution in memory, so that the next slice
+    // can continue it without executing its completed slices again.
+    fn store_paused_execution(
+        &self,
+        canister_id: CanisterId,
+        task: PausedTask,
+        paused: PausedState,
+    ) {
+        let previous = self
+            .paused_executions
+            .lock()
+            .unwrap()
+            .insert(canister_id, (task, paused));
+        if let Some((_, paused)) = previous {
+            paused.abort();
+        }
+    }
+
+    // Returns the paused execution of the given canister if it is the one
+    // described by `task`. A paused execution that does not match the task,
+    // e.g. because the state was reloaded from a checkpoint, is aborted.
+    fn take_paused_execution(
+        &self,
+        canister_id: CanisterId,
+        task: &PausedTask,
+    ) -> Option<PausedState> {
+        let entry = self.paused_executions.lock().unwrap().remove(&canister_id);
+        match entry {
+            Some((stored_task, paused)) if stored_task == *task => Some(paused),
+            Some((_, paused)) => {
+                paused.abort();
+                None
+            }
+            None => None,
+        }
+    }
+
+    // Executes the first slice of an update method. The execution as a whole
+    // may consume up to `max_instructions_per_sliced_message` instructions,
+    // which are reserved upfront, but this call executes at most
+    // `instructions_limit` of them. If the execution does not finish, the
+    // canister gets a `PausedExecution` task that is resumed by the scheduler
+    // in the following rounds.
+    #[allow(clippy::too_many_arguments)]
+    fn execute_update_sliced(
+        &self,
+        mut canister: CanisterState,
+        instructions_limit: NumInstructions,
+        msg: RequestOrIngress,
+        time: Time,
+        routing_table: Arc<RoutingTable>,
+        subnet_records: Arc<BTreeMap<SubnetId, SubnetType>>,
+        subnet_available_memory: SubnetAvailableMemory,
+        nns_subnet_id: SubnetId,
+    ) -> ExecuteMessageResult<CanisterState> {
+        let memory_usage = canister.memory_usage(self.own_subnet_type);
+        let compute_allocation = canister.scheduler_state.compute_allocation;
+        if let Err(err) = self.cycles_account_manager.withdraw_execution_cycles(
+            &mut canister.system_state,
+            memory_usage,
+            compute_allocation,
+            self.config.max_instructions_per_sliced_message,
+        ) {
+            // Canister is out of cycles. Reject the request.
+            return self.reject_due_to_out_of_cycles(canister, instructions_limit, msg, err, time);
+        }
+
+        let task = PausedTask {
+            message: msg,
+            time,
+            initial_cycles_balance: canister.system_state.cycles_balance,
+            subnet_available_memory: subnet_available_memory.get(),
+            slice_instruction_limits: vec![],
+            instructions_executed: NumInstructions::from(0),
+            wasm_simd: false,
+        };
+        let initial_canister = sliced_execution_initial_canister(&canister, &task);
+        let slice_instruction_limit = self.slice_instruction_limit(instructions_limit);
+        let result = self.execute_update_slices(
+            initial_canister.clone(),
+            &task,
+            slice_instruction_limit,
+            Arc::clone(&routing_table),
+            Arc::clone(&subnet_records),
+            nns_subnet_id,
+        );
+        self.process_update_slice(
+            result,
+            task,
+            slice_instruction_limit,
+            initial_canister,
+            routing_table,
+            subnet_records,
+            canister,
+            instructions_limit,
+        )
+    }
+
+    // Starts the update execution described by `task` from `initial_canister`
+    // and runs its completed slices followed by a new slice of
+    // `slice_instruction_limit` instructions.
+    fn execute_update_slices(
+        &self,
+        initial_canister: CanisterState,
+        task: &PausedTask,
+        slice_instruction_limit: NumInstructions,
+        routing_table: Arc<RoutingTable>,
+        subnet_records: Arc<BTreeMap<SubnetId, SubnetType>>,
+        nns_subnet_id: SubnetId,
+    ) -> SliceResult<UpdateOutput> {
+        let execution_parameters = self.execution_parameters(
+            &initial_canister,
+            self.config.max_instructions_per_sliced_message,
+            SubnetAvailableMemory::new(task.subnet_available_memory),
+        );
+        let mut slice_instruction_limits = task.slice_instruction_limits.clone();
+        slice_instruction_limits.push(slice_instruction_limit);
+        let msg = task.message.clone();
+        let time = task.time;
+        let hypervisor = Arc::clone(&self.hypervisor);
+        execute_sliced(
+            slice_instruction_limits,
+            move |out_of_instructions_handler| {
+                hypervisor
+                    .with_out_of_instructions_handler(out_of_instructions_handler)
+                    .execute_update(
+                        initial_canister,
+                        msg,
+                        time,
+                        routing_table,
+                        subnet_records,
+                        execution_parameters,
+                        nns_subnet_id,
+                    )
+            },
+        )
+    }
+
+    // Handles the outcome of a slice of an update method: either pauses the
+    // execution or applies its result to the live state of the canister.
+    #[allow(clippy::too_many_arguments)]
+    fn process_update_slice(
+        &self,
+        result: SliceResult<UpdateOutput>,
+        mut task: PausedTask,
+        slice_instruction_limit: NumInstructions,
+        initial_canister: CanisterState,
+        routing_table: Arc<RoutingTable>,
+        subnet_records: Arc<BTreeMap<SubnetId, SubnetType>>,
+        mut canister: CanisterState,
+        instructions_limit: NumInstructions,
+    ) -> ExecuteMessageResult<CanisterState> {
+        match result {
+            SliceResult::Paused(execution) => {
+                let instructions_consumed =
+                    execution.instructions_executed() - task.instructions_executed;
+                task.slice_instruction_limits.push(slice_instruction_limit);
+                task.instructions_executed = execution.instructions_executed();
+                canister
+                    .system_state
+                    .task_queue
+                    .push_front(ExecutionTask::PausedExecution(task.clone()));
+                self.store_paused_execution(
+                    canister.canister_id(),
+                    task,
+                    PausedState::Update {
+                        execution,
+                        initial_canister,
+                        routing_table,
+                        subnet_records,
+                    },
+                );
+                ExecuteMessageResult {
+                    canister,
+                    num_instructions_left: instructions_left(
+                        instructions_limit,
+                        instructions_consumed,
+                    ),
+                    ingress_status: None,
+                    heap_delta: NumBytes::from(0),
+                }
+            }
+            SliceResult::Finished((new_canister, num_instructions_left, action, heap_delta)) => {
+                let instructions_consumed = instructions_left(
+                    self.config.max_instructions_per_sliced_message,
+                    num_instructions_left + task.instructions_executed,
+                );
+                let (action, heap_delta) =
+                    match merge_sliced_update(&mut canister, &initial_canister, new_canister) {
+                        Ok(()) => (action, heap_delta),
+                        Err(err) => (
+                            CallContextAction::Fail {
+                                error: err,
+                                refund: task.message.clone().take_cycles(),
+                            },
+                            NumBytes::from(0),
+                        ),
+                    };
+                let ingress_status = match task.message {
+                    RequestOrIngress::Request(request) => {
+                        produce_inter_canister_response(
+                            &mut canister,
+                            action,
+                            request.sender,
+                            request.sender_reply_callback,
+                        );
+                        None
+                    }
+                    RequestOrIngress::Ingress(ingress) => self.get_ingress_status(
+                        &mut canister,
+                        ingress.source,
+                        action,
+                        ingress.message_id,
+                        task.time,
+                    ),
+                };
+                // Refund the canister with any cycles left after message execution.
+                self.cycles_account_manager
+                    .refund_execution_cycles(&mut canister.system_state, num_instructions_left);
+                ExecuteMessageResult {
+                    canister,
+                    num_instructions_left: instructions_left(
+                        instructions_limit,
+                        instructions_consumed,
+                    ),
+                    ingress_status,
+                    heap_delta,
+                }
+            }
+        }
+    }
+
+    // Executes the first slice of an `install_code` message. Behaves like
+    // `CanisterManager::install_code()`, except that the execution as a whole
+    // may consume up to `max_instructions_per_sliced_install_code`
+    // instructions and that it is paused if it does not finish within
+    // `instructions_limit` instructions.
+    //
+    // Returns `None` if the execution is paused, i.e. if there is no result
+    // yet.
+    fn execute_install_code_sliced(
+        &self,
+        install_context: InstallCodeContext,
+        msg: &RequestOrIngress,
+        state: &mut ReplicatedState,
+        execution_parameters: ExecutionParameters,
+        instructions_limit: NumInstructions,
+    ) -> (
+        Option<Result<InstallCodeResult, CanisterManagerError>>,
+        NumInstructions,
+    ) {
+        let execution_parameters = match self.canister_manager.prepare_install_code(
+            &install_context,
+            state,
+            ExecutionParameters {
+                instruction_limit: self.config.max_instructions_per_sliced_install_code,
+                ..execution_parameters
+            },
+        ) {
+            Ok(execution_parameters) => execution_parameters,
+            Err(err) => return (Some(Err(err)), instructions_limit),
+        };
+
+        let canister_id = install_context.canister_id;
+        let mode = install_context.mode;
+        let canister = state
+            .canister_state(&canister_id)
+            .expect("The canister was found in `prepare_install_code`.");
+        let task = PausedTask {
+            message: msg.clone(),
+            time: state.time(),
+            initial_cycles_balance: canister.system_state.cycles_balance,
+            subnet_available_memory: execution_parameters.subnet_available_memory.get(),
+            slice_instruction_limits: vec![],
+            instructions_executed: NumInstructions::from(0),
+            wasm_simd: state.metadata.own_subnet_features.wasm_simd,
+        };
+        let initial_canister = sliced_execution_initial_canister(canister, &task);
+        let execution_parameters = ExecutionParameters {
+            subnet_available_memory: SubnetAvailableMemory::new(task.subnet_available_memory),
+            ..execution_parameters
+        };
+        let slice_instruction_limit = self.slice_instruction_limit(instructions_limit);
+        let result = self.execute_install_code_slices(
+            install_context,
+            initial_canister.clone(),
+            &task,
+            slice_instruction_limit,
+            execution_parameters,
+            state.path().to_path_buf(),
+        );
+        self.process_install_code_slice(
+            result,
+            task,
+            slice_instruction_limit,
+            initial_canister,
+            mode,
+            state,
+            instructions_limit,
+        )
+    }
+
+    // Starts the `install_code` execution described by `task` from
+    // `initial_canister` and runs its completed slices followed by a new
+    // slice of `slice_instruction_limit` instructions.
+    fn execute_install_code_slices(
+        &self,
+        install_context: InstallCodeContext,
+        initial_canister: CanisterState,
+        task: &PausedTask,
+        slice_instruction_limit: NumInstructions,
+        execution_parameters: ExecutionParameters,
+        canister_layout_path: PathBuf,
+    ) -> SliceResult<InstallCodeOutput> {
+        let mut slice_instruction_limits = task.slice_instruction_limits.clone();
+        slice_instruction_limits.push(slice_instruction_limit);
+        let time = task.time;
+        // Replayed slices must validate the code against the same subnet
+        // features as the original execution.
+        let wasm_simd = task.wasm_simd.into();
+        let canister_manager = self.canister_manager.clone();
+        execute_sliced(
+            slice_instruction_limits,
+            move |out_of_instructions_handler| {
+                canister_manager
+                    .with_out_of_instructions_handler(out_of_instructions_handler)
+                    .install_or_upgrade(
+                        install_context,
+                        &initial_canister,
+                        time,
+                        canister_layout_path,
+                        execution_parameters,
+                        wasm_simd,
+                    )
+            },
+        )
+    }
+
+    // Handles the outcome of a slice of an `install_code` message: either
+    // pauses the execution or applies its result to the state.
+    #[allow(clippy::too_many_arguments)]
+    fn process_install_code_slice(
+        &self,
+        result: SliceResult<InstallCodeOutput>,
+        mut task: PausedTask,
+        slice_instruction_limit: NumInstructions,
+        initial_canister: CanisterState,
+        mode: CanisterInstallMode,
+        state: &mut ReplicatedState,
+        instructions_limit: NumInstructions,
+    ) -> (
+        Option<Result<InstallCodeResult, CanisterManagerError>>,
+        NumInstructions,
+    ) {
+        let canister_id = initial_canister.canister_id();
+        match result {
+            SliceResult::Paused(execution) => {
+                let instructions_consumed =
+                    execution.instructions_executed() - task.instructions_executed;
+                task.slice_instruction_limits.push(slice_instruction_limit);
+                task.instructions_executed = execution.instructions_executed();
+                state
+                    .canister_state_mut(&canister_id)
+                    .expect("A canister with a paused execution must exist.")
+                    .system_state
+                    .task_queue
+                    .push_front(ExecutionTask::PausedInstallCode(task.clone()));
+                self.store_paused_execution(
+                    canister_id,
+                    task,
+                    PausedState::InstallCode {
+                        execution,
+                        initial_canister,
+                    },
+                );
+                (
+                    None,
+                    instructions_left(instructions_limit, instructions_consumed),
+                )
+            }
+            SliceResult::Finished((num_instructions_left, result)) => {
+                let instructions_consumed = instructions_left(
+                    self.config.max_instructions_per_sliced_install_code,
+                    num_instructions_left + task.instructions_executed,
+                );
+                let result = result.map(|(heap_delta, new_canister)| {
+                    let canister = state
+                        .canister_state(&canister_id)
+                        .expect("A canister with a paused execution must exist.");
+                    (
+                        heap_delta,
+                        merge_sliced_install_code(canister, &initial_canister, new_canister),
+                    )
+                });
+                let result = self.canister_manager.finish_install_code(
+                    canister_id,
+                    mode,
+                    state,
+                    num_instructions_left,
+                    result,
+                );
+                (
+                    Some(result),
+                    instructions_left(instructions_limit, instructions_consumed),
+                )
+            }
+        }
+    }
+
+    // Produces the response to an `install_code` message from its result.
+    fn install_code_response(
+        &self,
+        canister_id: CanisterId,
+        result: Result<InstallCodeResult, CanisterManagerError>,
+        execution_duration: f64,
+        state: &mut ReplicatedState,
+    ) -> Result<Vec<u8>, UserError> {
+        match result {
+            Ok(result) => {
+                state.metadata.heap_delta_estimate += result.heap_delta;
+
+                info!(
+                    self.log,
+                    "Finished executing install_code message on canister {:?} after {:?}, old wasm hash {:?}, new wasm hash {:?}",
+                    canister_id,
+                    execution_duration,
+                    result.old_wasm_hash,
+                    result.new_wasm_hash,
+                );
+
+                Ok(EmptyBlob::encode())
+            }
+            Err(err) => {
+                info!(
+                    self.log,
+                    "Finished executing install_code message on canister {:?} after {:?} with error: {:?}",
+                    canister_id,
+                    execution_duration,
+                    err
+                );
+                Err(err.into())
+            }
+        }
+    }
+
     fn log_and_transform_to_user_error(
         &self,
         hypervisor_err: HypervisorError,
@@ -1956,6 +3130,202 @@ fn get_canister_mut(
     }
 }
 
+// Returns the number of instructions left of `instructions_limit` after
+// consuming `instructions_consumed` instructions.
+fn instructions_left(
+    instructions_limit: NumInstructions,
+    instructions_consumed: NumInstructions,
+) -> NumInstructions {
+    NumInstructions::from(
+        instructions_limit
+            .get()
+            .saturating_sub(instructions_consumed.get()),
+    )
+}
+
+// Returns the state from which the sliced execution described by `task`
+// starts. The input and output queues of the live canister keep changing
+// while the execution is paused, so the execution starts with empty queues
+// and the requests it makes are moved to the live queues when it finishes.
+// This way, an execution that is recreated from its task starts from the
+// same state as the original one.
+fn sliced_execution_initial_canister(canister: &CanisterState, task: &PausedTask) -> CanisterState {
+    let mut initial_canister = canister.clone();
+    initial_canister.system_state.cycles_balance = task.initial_cycles_balance;
+    initial_canister.system_state.clear_queues();
+    initial_canister
+}
+
+// Decodes the context of an `install_code` message.
+fn install_code_context(msg: &RequestOrIngress) -> Result<InstallCodeContext, UserError> {
+    let args = InstallCodeArgs::decode(msg.method_payload())?;
+    InstallCodeContext::try_from((*msg.sender(), args)).map_err(|err| err.into())
+}
+
+// Returns true if the given subnet message modifies the canister it operates
+// on in a way that a paused execution of the canister cannot take into
+// account, i.e. if the paused execution has to be aborted.
+fn interrupts_paused_execution(method: &Result<Ic00Method, ParseError>) -> bool {
+    matches!(
+        method,
+        Ok(Ic00Method::InstallCode
+            | Ic00Method::UninstallCode
+            | Ic00Method::UpdateSettings
+            | Ic00Method::SetController
+            | Ic00Method::StopCanister
+            | Ic00Method::LoadCanisterSnapshot)
+    )
+}
+
+// Returns the ID of the canister that a subnet message operates on, if any.
+fn target_canister_id(
+    method: &Result<Ic00Method, ParseError>,
+    payload: &[u8],
+) -> Option<CanisterId> {
+    match method {
+        Ok(Ic00Method::InstallCode) => InstallCodeArgs::decode(payload)
+            .ok()
+            .map(|args| args.get_canister_id()),
+        Ok(Ic00Method::UpdateSettings) => UpdateSettingsArgs::decode(payload)
+            .ok()
+            .map(|args| args.get_canister_id()),
+        Ok(Ic00Method::SetController) => SetControllerArgs::decode(payload)
+            .ok()
+            .map(|args| args.get_canister_id()),
+        Ok(Ic00Method::ProvisionalTopUpCanister) => ProvisionalTopUpCanisterArgs::decode(payload)
+            .ok()
+            .map(|args| args.get_canister_id()),
+        Ok(Ic00Method::TakeCanisterSnapshot) => TakeCanisterSnapshotArgs::decode(payload)
+            .ok()
+            .map(|args| args.get_canister_id()),
+        Ok(Ic00Method::LoadCanisterSnapshot | Ic00Method::DeleteCanisterSnapshot) => {
+            CanisterSnapshotArgs::decode(payload)
+                .ok()
+                .map(|args| args.get_canister_id())
+        }
+        Ok(
+            Ic00Method::CanisterStatus
+            | Ic00Method::DeleteCanister
+            | Ic00Method::DepositCycles
+            | Ic00Method::StartCanister
+            | Ic00Method::StopCanister
+            | Ic00Method::UninstallCode
+            | Ic00Method::ListCanisterSnapshots,
+        ) => CanisterIdRecord::decode(payload)
+            .ok()
+            .map(|args| args.get_canister_id()),
+        Ok(
+            Ic00Method::CreateCanister
+            | Ic00Method::FetchCanisterLogs
+            | Ic00Method::HttpRequest
+            | Ic00Method::RawRand
+            | Ic00Method::SetupInitialDKG
+            | Ic00Method::SignWithECDSA
+            | Ic00Method::ProvisionalCreateCanisterWithCycles
+            | Ic00Method::GetMockECDSAPublicKey
+            | Ic00Method::SignWithMockECDSA,
+        )
+        | Err(_) => None,
+    }
+}
+
+// Applies the result of a sliced update execution that started from
+// `initial_canister` to the live state of the canister.
+//
+// Between slices the canister only executes the paused execution, so the
+// only changes to its live state come from the outside: messages are
+// inducted into and routed out of its queues and its cycles balance may be
+// charged. The changes made by the execution are applied on top of them.
+// Log records added by the execution are appended to the live log.
+//
+// Returns an error and leaves the canister unmodified if the output queues of
+// the canister have no room for the requests made by the execution.
+fn merge_sliced_update(
+    canister: &mut CanisterState,
+    initial_canister: &CanisterState,
+    new_canister: CanisterState,
+) -> Result<(), HypervisorError> {
+    let mut system_state = canister.system_state.clone();
+    for request in new_canister
+        .system_state
+        .queues()
+        .output_requests_pushed_since(initial_canister.system_state.queues())
+    {
+        if let Err((err, _)) = system_state.push_output_request(request) {
+            return Err(HypervisorError::ContractViolation(format!(
+                "Failed to enqueue the requests of a sliced execution: {}",
+                err
+            )));
+        }
+    }
+    system_state.cycles_balance = system_state.cycles_balance
+        + new_canister.system_state.cycles_balance
+        - initial_canister.system_state.cycles_balance;
+    system_state
+        .canister_metrics
+        .consumed_cycles_since_replica_started += new_canister
+        .system_state
+        .canister_metrics
+        .consumed_cycles_since_replica_started;
+    system_state
+        .canister_metrics
+        .consumed_cycles_since_replica_started -= initial_canister
+        .system_state
+        .canister_metrics
+        .consumed_cycles_since_replica_started;
+    system_state.certified_data = new_canister.system_state.certified_data;
+    system_state.status = new_canister.system_state.status;
+    system_state.global_timer = new_canister.system_state.global_timer;
+    append_log_records(&mut system_state, initial_canister, &new_canister);
+    canister.system_state = system_state;
+    canister.execution_state = new_canister.execution_state;
+    Ok(())
+}
+
+// Applies the result of a sliced `install_code` execution that started from
+// `initial_canister` to the live state of the canister. See
+// `merge_sliced_update()` for the changes the live state may have undergone.
+fn merge_sliced_install_code(
+    canister: &CanisterState,
+    initial_canister: &CanisterState,
+    new_canister: CanisterState,
+) -> CanisterState {
+    let mut merged_canister = canister.clone();
+    merged_canister.system_state.cycles_balance = canister.system_state.cycles_balance
+        + new_canister.system_state.cycles_balance
+        - initial_canister.system_state.cycles_balance;
+    merged_canister.system_state.memory_allocation = new_canister.system_state.memory_allocation;
+    merged_canister.system_state.certified_data = new_canister.system_state.certified_data;
+    merged_canister.system_state.global_timer = new_canister.system_state.global_timer;
+    append_log_records(
+        &mut merged_canister.system_state,
+        initial_canister,
+        &new_canister,
+    );
+    merged_canister.scheduler_state.compute_allocation =
+        new_canister.scheduler_state.compute_allocation;
+    merged_canister.execution_state = new_canister.execution_state;
+    merged_canister
+}
+
+// Appends the log records added by a sliced execution that started from
+// `initial_canister` and resulted in `new_canister` to `system_state`.
+fn append_log_records(
+    system_state: &mut SystemState,
+    initial_canister: &CanisterState,
+    new_canister: &CanisterState,
+) {
+    for record in new_canister
+        .system_state
+        .canister_log
+        .records_added_since(&initial_canister.system_state.canister_log)
+    {
+        system_state
+            .canister_log
+            .add_record(record.timestamp_nanos, record.content.clone());
+    }
+}
+
 fn produce_inter_canister_response(
     canister: &mut CanisterState,
     action: CallContextAction,
@@ -230,6 +230,7 @@ fn dashboard_label_value_from(code: ErrorCode) -> &'static str {
         CanisterWasmModuleNotFound => "Canister WASM Module Not Found",
         CanisterNonEmpty => "Canister Non-Empty",
         CanisterEmpty => "Canister Empty",
+        CanisterSnapshotNotFound => "Canister Snapshot Not Found",
         CanisterOutOfCycles => "Canister Out Of Cycles",
         CanisterTrapped => "Canister Trapped",
         CanisterCalledTrap => "Canister Called Trap",
@@ -257,5 +258,13 @@ fn dashboard_label_value_from(code: ErrorCode) -> &'static str {
         CanisterCyclesLimitExceeded => {
             "Canister Cycles Limit for Single Message Execution Exceeded"
         }
+        InsufficientCyclesInAllocationReservation => {
+            "Insufficient Cycles to Reserve for Resource Allocation"
+        }
+        ReservedCyclesLimitExceeded => "Reserved Cycles Limit Exceeded",
+        ReservedCyclesLimitIsTooLow => "Reserved Cycles Limit Is Too Low",
+        QueryCallGraphTotalInstructionLimitExceeded => {
+            "Query Call Graph Total Instruction Limit Exceeded"
+        }
     }
 }
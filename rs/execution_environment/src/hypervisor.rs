@@ -8,7 +8,8 @@ use ic_embedders::{
     wasm_executor::WasmExecutor, WasmExecutionInput, WasmExecutionOutput, WasmtimeEmbedder,
 };
 use ic_interfaces::execution_environment::{
-    ExecutionParameters, HypervisorError, HypervisorResult,
+    DefaultOutOfInstructionsHandler, ExecutionParameters, HypervisorError, HypervisorResult,
+    OutOfInstructionsHandler,
 };
 use ic_interfaces::messages::RequestOrIngress;
 use ic_logger::{debug, fatal, ReplicaLogger};
@@ -86,6 +87,7 @@ impl HypervisorMetrics {
 }
 
 #[doc(hidden)]
+#[derive(Clone)]
 pub struct Hypervisor {
     wasm_executor: Arc<WasmExecutor>,
     sandbox_executor: Option<Arc<SandboxedExecutionController>>,
@@ -94,6 +96,7 @@ pub struct Hypervisor {
     own_subnet_type: SubnetType,
     log: ReplicaLogger,
     cycles_account_manager: Arc<CyclesAccountManager>,
+    out_of_instructions_handler: Arc<dyn OutOfInstructionsHandler>,
 }
 
 impl Hypervisor {
@@ -201,6 +204,7 @@ impl Hypervisor {
             Arc::clone(&self.metrics),
             Arc::clone(&self.wasm_executor),
             self.sandbox_executor.clone(),
+            Arc::clone(&self.out_of_instructions_handler),
         );
 
         let (mut system_state, heap_delta) = if output.wasm_result.is_ok() {
@@ -211,6 +215,7 @@ impl Hypervisor {
         } else {
             // In contrast to other methods, an update methods ignores the
             // Wasm execution error and returns 0 as the heap delta.
+            system_state.canister_log = output.system_state.canister_log;
             (system_state, NumBytes::from(0))
         };
 
@@ -255,7 +260,6 @@ impl Hypervisor {
             );
         }
 
-        let method = WasmMethod::Query(method.to_string());
         let memory_usage = canister.memory_usage(self.own_subnet_type);
         let (execution_state, system_state, scheduler_state) = canister.into_parts();
 
@@ -271,6 +275,18 @@ impl Hypervisor {
             Some(state) => state,
         };
 
+        // Composite queries can call other canisters, so they can only be
+        // executed in non-replicated mode.
+        let method = match query_execution_type {
+            QueryExecutionType::NonReplicated { .. }
+                if execution_state
+                    .exports_method(&WasmMethod::CompositeQuery(method.to_string())) =>
+            {
+                WasmMethod::CompositeQuery(method.to_string())
+            }
+            _ => WasmMethod::Query(method.to_string()),
+        };
+
         // Validate that the Wasm module exports the method.
         if !execution_state.exports_method(&method) {
             return (
@@ -307,6 +323,7 @@ impl Hypervisor {
                     Arc::clone(&self.metrics),
                     Arc::clone(&self.wasm_executor),
                     self.sandbox_executor.clone(),
+                    Arc::clone(&self.out_of_instructions_handler),
                 );
 
                 let canister =
@@ -356,6 +373,7 @@ impl Hypervisor {
                     Arc::clone(&self.metrics),
                     Arc::clone(&self.wasm_executor),
                     self.sandbox_executor.clone(),
+                    Arc::clone(&self.out_of_instructions_handler),
                 );
 
                 let new_execution_state = match query_kind {
@@ -489,6 +507,7 @@ impl Hypervisor {
             Arc::clone(&self.metrics),
             Arc::clone(&self.wasm_executor),
             self.sandbox_executor.clone(),
+            Arc::clone(&self.out_of_instructions_handler),
         );
 
         let cycles_account_manager = Arc::clone(&self.cycles_account_manager);
@@ -507,7 +526,9 @@ impl Hypervisor {
             }
             Err(callback_err) => {
                 // A trap has occurred when executing the reply/reject closure.
-                // Execute the cleanup if it exists.
+                // Keep the log of the failed execution and execute the
+                // cleanup if it exists.
+                canister.system_state.canister_log = output.system_state.canister_log;
                 match callback.on_cleanup {
                     None => {
                         // No cleanup closure present. Return the callback error as-is.
@@ -541,6 +562,7 @@ impl Hypervisor {
                             metrics,
                             Arc::clone(&self.wasm_executor),
                             self.sandbox_executor.clone(),
+                            Arc::clone(&self.out_of_instructions_handler),
                         );
 
                         canister.execution_state = Some(cleanup_output.execution_state);
@@ -563,6 +585,8 @@ impl Hypervisor {
                             }
                             Err(cleanup_err) => {
                                 // Executing the cleanup call back failed.
+                                canister.system_state.canister_log =
+                                    cleanup_output.system_state.canister_log;
                                 (
                                     canister,
                                     cleanup_output.num_instructions_left,
@@ -635,6 +659,7 @@ impl Hypervisor {
             Arc::clone(&self.metrics),
             Arc::clone(&self.wasm_executor),
             self.sandbox_executor.clone(),
+            Arc::clone(&self.out_of_instructions_handler),
         );
 
         self.system_execution_result_with_old_system_state(output, system_state, scheduler_state)
@@ -696,6 +721,7 @@ impl Hypervisor {
             Arc::clone(&self.metrics),
             Arc::clone(&self.wasm_executor),
             self.sandbox_executor.clone(),
+            Arc::clone(&self.out_of_instructions_handler),
         );
         self.system_execution_result(output, system_state, scheduler_state)
     }
@@ -757,6 +783,7 @@ impl Hypervisor {
             Arc::clone(&self.metrics),
             Arc::clone(&self.wasm_executor),
             self.sandbox_executor.clone(),
+            Arc::clone(&self.out_of_instructions_handler),
         );
         self.system_execution_result(output, system_state, scheduler_state)
     }
@@ -818,6 +845,7 @@ impl Hypervisor {
             Arc::clone(&self.metrics),
             Arc::clone(&self.wasm_executor),
             self.sandbox_executor.clone(),
+            Arc::clone(&self.out_of_instructions_handler),
         );
         self.system_execution_result(output, system_state, scheduler_state)
     }
@@ -864,6 +892,7 @@ impl Hypervisor {
             Arc::clone(&self.metrics),
             Arc::clone(&self.wasm_executor),
             self.sandbox_executor.clone(),
+            Arc::clone(&self.out_of_instructions_handler),
         );
         match output.wasm_result {
             Ok(maybe_wasm_result) => match maybe_wasm_result {
@@ -916,7 +945,58 @@ impl Hypervisor {
         execution_parameters: ExecutionParameters,
         nns_subnet_id: SubnetId,
     ) -> (CanisterState, NumInstructions, HypervisorResult<NumBytes>) {
-        let method = WasmMethod::System(SystemMethod::CanisterHeartbeat);
+        self.execute_canister_system_task(
+            SystemMethod::CanisterHeartbeat,
+            canister,
+            routing_table,
+            subnet_records,
+            time,
+            execution_parameters,
+            nns_subnet_id,
+        )
+    }
+
+    /// Executes the `canister_global_timer` system method.
+    ///
+    /// The caller is responsible for deactivating the global timer of the
+    /// canister before the execution, so that the timer fires only once.
+    /// The returned values are the same as in `execute_canister_heartbeat`.
+    #[allow(clippy::type_complexity)]
+    pub fn execute_canister_global_timer(
+        &self,
+        canister: CanisterState,
+        routing_table: Arc<RoutingTable>,
+        subnet_records: Arc<BTreeMap<SubnetId, SubnetType>>,
+        time: Time,
+        execution_parameters: ExecutionParameters,
+        nns_subnet_id: SubnetId,
+    ) -> (CanisterState, NumInstructions, HypervisorResult<NumBytes>) {
+        self.execute_canister_system_task(
+            SystemMethod::CanisterGlobalTimer,
+            canister,
+            routing_table,
+            subnet_records,
+            time,
+            execution_parameters,
+            nns_subnet_id,
+        )
+    }
+
+    /// Executes a system task, i.e. `canister_heartbeat` or
+    /// `canister_global_timer`, that is triggered by the system rather than
+    /// by a message.
+    #[allow(clippy::type_complexity, clippy::too_many_arguments)]
+    pub fn execute_canister_system_task(
+        &self,
+        system_task: SystemMethod,
+        canister: CanisterState,
+        routing_table: Arc<RoutingTable>,
+        subnet_records: Arc<BTreeMap<SubnetId, SubnetType>>,
+        time: Time,
+        execution_parameters: ExecutionParameters,
+        nns_subnet_id: SubnetId,
+    ) -> (CanisterState, NumInstructions, HypervisorResult<NumBytes>) {
+        let method = WasmMethod::System(system_task.clone());
         let memory_usage = canister.memory_usage(self.own_subnet_type);
         let (execution_state, mut system_state, scheduler_state) = canister.into_parts();
 
@@ -947,7 +1027,8 @@ impl Hypervisor {
             .unwrap()
             .new_call_context(CallOrigin::Heartbeat, Cycles::from(0));
 
-        let api_type = ApiType::heartbeat(
+        let api_type = ApiType::system_task(
+            system_task,
             time,
             call_context_id,
             self.own_subnet_id,
@@ -968,6 +1049,7 @@ impl Hypervisor {
             Arc::clone(&self.metrics),
             Arc::clone(&self.wasm_executor),
             self.sandbox_executor.clone(),
+            Arc::clone(&self.out_of_instructions_handler),
         );
 
         {
@@ -991,7 +1073,8 @@ impl Hypervisor {
     // - `execution_state` is taken from the Wasm output.
     // - `scheduler_state` is taken from the corresponding argument.
     // - `system_state` is taken from the Wasm output if the execution succeeded;
-    //   otherwise, it is taken from the corresponding argument.
+    //   otherwise, it is taken from the corresponding argument, except for
+    //   the canister log, which keeps the records of the failed execution.
     fn system_execution_result(
         &self,
         output: WasmExecutionOutput,
@@ -1006,7 +1089,11 @@ impl Hypervisor {
                 let bytes = NumBytes::from((output.instance_stats.dirty_pages * PAGE_SIZE) as u64);
                 (output.system_state, Ok(bytes))
             }
-            Err(err) => (old_system_state, Err(err)),
+            Err(err) => {
+                let mut system_state = old_system_state;
+                system_state.canister_log = output.system_state.canister_log;
+                (system_state, Err(err))
+            }
         };
         let canister =
             CanisterState::from_parts(Some(output.execution_state), system_state, scheduler_state);
@@ -1040,17 +1127,30 @@ impl Hypervisor {
         (canister, output.num_instructions_left, heap_delta)
     }
 
+    /// Validates the given Wasm module and creates the execution state for
+    /// it. `wasm_simd` is the `wasm_simd` feature of the subnet in the
+    /// replicated state.
     pub fn create_execution_state(
         &self,
         wasm_binary: Vec<u8>,
         canister_root: PathBuf,
         canister_id: CanisterId,
+        wasm_simd: FeatureStatus,
     ) -> HypervisorResult<ExecutionState> {
         if let Some(sandbox_executor) = self.sandbox_executor.as_ref() {
-            sandbox_executor.create_execution_state(wasm_binary, canister_root, canister_id)
+            sandbox_executor.create_execution_state(
+                wasm_binary,
+                canister_root,
+                canister_id,
+                wasm_simd == FeatureStatus::Enabled,
+            )
         } else {
-            self.wasm_executor
-                .create_execution_state(wasm_binary, canister_root, canister_id)
+            self.wasm_executor.create_execution_state(
+                wasm_binary,
+                canister_root,
+                canister_id,
+                wasm_simd,
+            )
         }
     }
 
@@ -1068,6 +1168,11 @@ impl Hypervisor {
         embedder_config.persistence_type = config.persistence_type;
         embedder_config.num_runtime_generic_threads = num_runtime_threads;
         embedder_config.num_runtime_query_threads = std::cmp::min(num_runtime_threads, 4);
+        // Installed code was validated against the subnet features when it
+        // was installed, so compiling it must accept all Wasm features that
+        // a subnet can enable. `create_execution_state()` restricts new code
+        // to the features enabled in the replicated state.
+        embedder_config.feature_flags.wasm_simd = FeatureStatus::Enabled;
 
         let wasm_embedder = WasmtimeEmbedder::new(embedder_config.clone(), log.clone());
         let wasm_executor = WasmExecutor::new(
@@ -1079,6 +1184,14 @@ impl Hypervisor {
 
         let sandbox_executor = match config.canister_sandboxing_flag {
             FeatureStatus::Enabled => {
+                // Sandboxed executions run in another process and cannot be
+                // paused at the end of a slice.
+                if config.deterministic_time_slicing == FeatureStatus::Enabled {
+                    fatal!(
+                        log,
+                        "Deterministic time slicing is not supported with canister sandboxing."
+                    );
+                }
                 Some(Arc::new(SandboxedExecutionController::new(log.clone())))
             }
             FeatureStatus::Disabled => None,
@@ -1092,6 +1205,20 @@ impl Hypervisor {
             own_subnet_type,
             log,
             cycles_account_manager,
+            out_of_instructions_handler: Arc::new(DefaultOutOfInstructionsHandler),
+        }
+    }
+
+    /// Returns a copy of the hypervisor that runs all executions with the
+    /// given `OutOfInstructionsHandler`. This is how executions are split
+    /// into slices without changing the signatures of the execution methods.
+    pub fn with_out_of_instructions_handler(
+        &self,
+        out_of_instructions_handler: Arc<dyn OutOfInstructionsHandler>,
+    ) -> Self {
+        Self {
+            out_of_instructions_handler,
+            ..self.clone()
         }
     }
 
@@ -1127,10 +1254,12 @@ pub fn execute(
     metrics: Arc<HypervisorMetrics>,
     wasm_executor: Arc<WasmExecutor>,
     sandbox_executor: Option<Arc<SandboxedExecutionController>>,
+    out_of_instructions_handler: Arc<dyn OutOfInstructionsHandler>,
 ) -> WasmExecutionOutput {
     let api_type_str = api_type.as_str();
+    let canister_log_time = api_type.canister_log_time();
 
-    let result = if let Some(sandbox_executor) = sandbox_executor {
+    let mut result = if let Some(sandbox_executor) = sandbox_executor {
         sandbox_executor.process(WasmExecutionInput {
             api_type: api_type.clone(),
             system_state,
@@ -1139,6 +1268,7 @@ pub fn execute(
             func_ref,
             execution_state,
             cycles_account_manager,
+            out_of_instructions_handler,
         })
     } else {
         wasm_executor.process(WasmExecutionInput {
@@ -1149,9 +1279,17 @@ pub fn execute(
             func_ref,
             execution_state,
             cycles_account_manager,
+            out_of_instructions_handler,
         })
     };
 
     metrics.observe(api_type_str, &result);
+
+    if let (Err(err), Some(time)) = (&result.wasm_result, canister_log_time) {
+        result.system_state.canister_log.add_record(
+            time.as_nanos_since_unix_epoch(),
+            format!("[TRAP]: {}", err).into_bytes(),
+        );
+    }
     result
 }
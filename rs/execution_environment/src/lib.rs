@@ -1,6 +1,7 @@
 mod canister_manager;
 mod canister_settings;
 mod common;
+mod deterministic_time_slicing;
 mod execution_environment;
 mod execution_environment_metrics;
 mod history;
@@ -105,6 +106,7 @@ pub fn setup_execution(
         own_subnet_id,
         own_subnet_type,
         scheduler_config.scheduler_cores,
+        scheduler_config.max_stable_memory_size,
         config.clone(),
         Arc::clone(&cycles_account_manager),
     ));
@@ -116,6 +118,7 @@ pub fn setup_execution(
         config,
         metrics_registry,
         scheduler_config.max_instructions_per_message,
+        scheduler_config.max_stable_memory_size,
     ));
     let threadpool = threadpool::Builder::new()
         .num_threads(QUERY_EXECUTION_THREADS)
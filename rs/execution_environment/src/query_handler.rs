@@ -13,6 +13,10 @@ use crate::{
 };
 use ic_config::execution_environment::Config;
 use ic_crypto_tree_hash::{flatmap, Label, LabeledTree, LabeledTree::SubTree};
+use ic_ic00_types::{
+    CanisterLogRecord, FetchCanisterLogsRequest, FetchCanisterLogsResponse, Method as Ic00Method,
+    Payload as Ic00Payload, IC_00,
+};
 use ic_interfaces::{
     execution_environment::{QueryExecutionService, QueryHandler, SubnetAvailableMemory},
     state_manager::StateReader,
@@ -20,7 +24,7 @@ use ic_interfaces::{
 use ic_logger::ReplicaLogger;
 use ic_metrics::MetricsRegistry;
 use ic_registry_subnet_type::SubnetType;
-use ic_replicated_state::ReplicatedState;
+use ic_replicated_state::{LogVisibility, ReplicatedState};
 use ic_types::{
     ingress::WasmResult,
     messages::{
@@ -28,7 +32,7 @@ use ic_types::{
         UserQuery,
     },
     user_error::{ErrorCode, RejectCode, UserError},
-    CanisterId, NumInstructions, SubnetId,
+    CanisterId, NumBytes, NumInstructions, PrincipalId, SubnetId,
 };
 use query_allocations::QueryAllocationsUsed;
 use serde::Serialize;
@@ -36,6 +40,7 @@ use std::{
     convert::Infallible,
     future::Future,
     pin::Pin,
+    str::FromStr,
     sync::{Arc, Mutex, RwLock},
     task::{Context, Poll},
 };
@@ -86,6 +91,71 @@ fn label<T: Into<Label>>(t: T) -> Label {
     t.into()
 }
 
+// Returns the canister whose certified data is attached to the response of the
+// given query. Queries to the management canister are answered on behalf of the
+// canister that they refer to.
+fn effective_canister_id(query: &UserQuery) -> CanisterId {
+    if query.receiver == IC_00 {
+        if let Ok(Ic00Method::FetchCanisterLogs) = Ic00Method::from_str(&query.method_name) {
+            if let Ok(args) = FetchCanisterLogsRequest::decode(&query.method_payload) {
+                return args.get_canister_id();
+            }
+        }
+    }
+    query.receiver
+}
+
+// Answers a `fetch_canister_logs` query from the given state. Depending on the
+// log visibility of the canister, the logs are readable either by its
+// controllers only or by anyone.
+fn fetch_canister_logs(
+    sender: PrincipalId,
+    state: &ReplicatedState,
+    payload: &[u8],
+) -> Result<WasmResult, UserError> {
+    let args = FetchCanisterLogsRequest::decode(payload)?;
+    let canister_id = args.get_canister_id();
+    let canister = state.canister_state(&canister_id).ok_or_else(|| {
+        UserError::new(
+            ErrorCode::CanisterNotFound,
+            format!("Canister {} not found", canister_id),
+        )
+    })?;
+
+    match canister.system_state.log_visibility {
+        LogVisibility::Public => {}
+        LogVisibility::Controllers => {
+            if !canister.controllers().contains(&sender) {
+                return Err(UserError::new(
+                    ErrorCode::CanisterInvalidController,
+                    format!(
+                        "Caller {} is not allowed to fetch the logs of canister {}",
+                        sender, canister_id
+                    ),
+                ));
+            }
+        }
+    }
+
+    let canister_log_records = canister
+        .system_state
+        .canister_log
+        .records()
+        .iter()
+        .map(|record| CanisterLogRecord {
+            idx: record.idx,
+            timestamp_nanos: record.timestamp_nanos,
+            content: record.content.clone(),
+        })
+        .collect();
+    Ok(WasmResult::Reply(
+        FetchCanisterLogsResponse {
+            canister_log_records,
+        }
+        .encode(),
+    ))
+}
+
 pub(crate) struct InternalHttpQueryHandler {
     log: ReplicaLogger,
     hypervisor: Arc<Hypervisor>,
@@ -95,6 +165,7 @@ pub(crate) struct InternalHttpQueryHandler {
     config: Config,
     metrics: QueryHandlerMetrics,
     max_instructions_per_message: NumInstructions,
+    max_stable_memory_size: NumBytes,
 }
 
 /// Struct that is responsible for handling queries sent by user.
@@ -105,6 +176,7 @@ pub(crate) struct HttpQueryHandler {
 }
 
 impl InternalHttpQueryHandler {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         log: ReplicaLogger,
         hypervisor: Arc<Hypervisor>,
@@ -113,6 +185,7 @@ impl InternalHttpQueryHandler {
         config: Config,
         metrics_registry: &MetricsRegistry,
         max_instructions_per_message: NumInstructions,
+        max_stable_memory_size: NumBytes,
     ) -> Self {
         Self {
             log,
@@ -123,6 +196,7 @@ impl InternalHttpQueryHandler {
             config,
             metrics: QueryHandlerMetrics::new(metrics_registry),
             max_instructions_per_message,
+            max_stable_memory_size,
         }
     }
 }
@@ -137,6 +211,20 @@ impl QueryHandler for InternalHttpQueryHandler {
         data_certificate: Vec<u8>,
     ) -> Result<WasmResult, UserError> {
         let measurement_scope = MeasurementScope::root(&self.metrics.query);
+        if query.receiver == IC_00 {
+            return match Ic00Method::from_str(&query.method_name) {
+                Ok(Ic00Method::FetchCanisterLogs) => {
+                    fetch_canister_logs(query.source.get(), &state, &query.method_payload)
+                }
+                _ => Err(UserError::new(
+                    ErrorCode::CanisterMethodNotFound,
+                    format!(
+                        "Query method {} not found in the management canister",
+                        query.method_name
+                    ),
+                )),
+            };
+        }
         // Note that This assumes that the QueryHandler is always called with the
         // "latest" state.  If and when we start supporting queries against older
         // versions of the state, we will need the caller of the QueryHandler to
@@ -163,7 +251,9 @@ impl QueryHandler for InternalHttpQueryHandler {
             self.query_allocations_used.clone(),
             subnet_available_memory,
             max_canister_memory_size,
+            self.max_stable_memory_size,
             self.max_instructions_per_message,
+            self.config.max_query_call_graph_instructions,
         );
         context.run(query, &self.metrics, &measurement_scope)
     }
@@ -247,7 +337,7 @@ impl Service<(UserQuery, Option<CertificateDelegation>)> for HttpQueryHandler {
                 let result = match get_latest_certified_state_and_data_certificate(
                     state_reader,
                     certificate_delegation,
-                    query.receiver,
+                    effective_canister_id(&query),
                 ) {
                     Some((state, cert)) => internal.query(query, state, cert),
                     None => Err(UserError::new(
@@ -1,5 +1,6 @@
-//! This module implements an MVP version of inter-canister queries. This
-//! implementation has the following restrictions:
+//! This module implements composite queries, i.e. inter-canister queries
+//! issued by `canister_composite_query` methods. This implementation has the
+//! following restrictions:
 //!
 //! - A canister can only query other canisters on the same subnet.
 //!
@@ -7,9 +8,17 @@
 //! execution, i.e. the originator of the processing is a Query from an end-user
 //! and not an Ingress message.
 //!
+//! - Only composite query methods can query other canisters. On system and
+//! verified application subnets regular query methods can do so as well, as
+//! they could before composite queries existed.
+//!
 //! - Loops are not allowed. E.g. call graphs like A -> B -> C -> A are not
 //! supported.
 //!
+//! - All executions in the call graph share a total instruction limit on top
+//! of the per-message limit. Queries are not charged cycles, neither for the
+//! executions nor for the calls.
+//!
 //! Some interesting factoids about inter-canister query execution to keep in
 //! mind:
 //!
@@ -112,7 +121,12 @@ pub(super) struct QueryContext<'a> {
     query_allocations_used: Arc<RwLock<QueryAllocationsUsed>>,
     subnet_available_memory: SubnetAvailableMemory,
     max_canister_memory_size: NumBytes,
+    max_stable_memory_size: NumBytes,
     max_instructions_per_message: NumInstructions,
+    // The total number of instructions that all executions in the call graph
+    // can consume and the number of instructions they consumed so far.
+    max_query_call_graph_instructions: NumInstructions,
+    total_instructions_executed: NumInstructions,
 }
 
 impl<'a> QueryContext<'a> {
@@ -127,7 +141,9 @@ impl<'a> QueryContext<'a> {
         query_allocations_used: Arc<RwLock<QueryAllocationsUsed>>,
         subnet_available_memory: SubnetAvailableMemory,
         max_canister_memory_size: NumBytes,
+        max_stable_memory_size: NumBytes,
         max_instructions_per_message: NumInstructions,
+        max_query_call_graph_instructions: NumInstructions,
     ) -> Self {
         let routing_table = Arc::clone(&state.metadata.network_topology.routing_table);
         Self {
@@ -144,7 +160,10 @@ impl<'a> QueryContext<'a> {
             routing_table,
             subnet_available_memory,
             max_canister_memory_size,
+            max_stable_memory_size,
             max_instructions_per_message,
+            max_query_call_graph_instructions,
+            total_instructions_executed: NumInstructions::from(0),
         }
     }
 
@@ -168,12 +187,10 @@ impl<'a> QueryContext<'a> {
         debug!(self.log, "Executing query for {}", canister_id);
         let old_canister = self.get_canister_from_state(&canister_id)?;
         let call_origin = CallOrigin::Query(query.source);
-        // EXC-500: Contain the usage of inter-canister query calls to the subnets
-        // that currently use it until we decide on the future of this feature and
-        // get a proper spec for it.
-        let cross_canister_query_calls_enabled = self.own_subnet_type == SubnetType::System
-            || self.own_subnet_type == SubnetType::VerifiedApplication;
-        let query_kind = if ENABLE_QUERY_OPTIMIZATION || !cross_canister_query_calls_enabled {
+        let cross_canister_query_calls_enabled = self.query_calls_from_queries_enabled();
+        let query_kind = if old_canister.exports_composite_query_method(query.method_name.clone()) {
+            NonReplicatedQueryKind::Stateful
+        } else if ENABLE_QUERY_OPTIMIZATION || !cross_canister_query_calls_enabled {
             NonReplicatedQueryKind::Pure
         } else {
             NonReplicatedQueryKind::Stateful
@@ -260,6 +277,21 @@ impl<'a> QueryContext<'a> {
         let measurement_scope =
             MeasurementScope::nested(&metrics.query_spawned_calls, measurement_scope);
         loop {
+            // Every outstanding request or response requires another
+            // execution, which is not possible once the call graph has used up
+            // its instructions.
+            if (self.outstanding_response.is_some() || !self.outstanding_requests.is_empty())
+                && self.call_graph_instructions_left() == NumInstructions::from(0)
+            {
+                return Err(UserError::new(
+                    ErrorCode::QueryCallGraphTotalInstructionLimitExceeded,
+                    format!(
+                        "Query call graph of canister {} exceeded the total instruction limit of {}",
+                        starting_canister_id, self.max_query_call_graph_instructions
+                    ),
+                ));
+            }
+
             if let Some(response) = self.outstanding_response.take() {
                 debug!(self.log, "Executing response for {}", response.originator);
                 // Any result returned by `handle_response` is a query context
@@ -294,6 +326,56 @@ impl<'a> QueryContext<'a> {
         }
     }
 
+    // EXC-500: Regular queries can call other canisters only on the subnets
+    // that used inter-canister queries before composite queries existed.
+    fn query_calls_from_queries_enabled(&self) -> bool {
+        self.own_subnet_type == SubnetType::System
+            || self.own_subnet_type == SubnetType::VerifiedApplication
+    }
+
+    // Returns the number of instructions that the executions in the call
+    // graph can still consume.
+    fn call_graph_instructions_left(&self) -> NumInstructions {
+        NumInstructions::from(
+            self.max_query_call_graph_instructions
+                .get()
+                .saturating_sub(self.total_instructions_executed.get()),
+        )
+    }
+
+    // Returns the instruction limit of the next execution on the canister. It
+    // is bounded by the per-message limit, by the query allocation of the
+    // canister and by the instructions left in the call graph.
+    fn instruction_limit(&self, canister: &CanisterState) -> NumInstructions {
+        let query_allocation: NumInstructions = self
+            .query_allocations_used
+            .write()
+            .unwrap()
+            .allocation_before_execution(canister)
+            .into();
+        self.max_instructions_per_message
+            .min(query_allocation)
+            .min(self.call_graph_instructions_left())
+    }
+
+    // Records the instructions that an execution on the canister consumed.
+    fn add_instructions_executed(
+        &mut self,
+        canister: &CanisterState,
+        instructions_executed: NumInstructions,
+        measurement_scope: &MeasurementScope,
+    ) {
+        measurement_scope.add(instructions_executed, NumMessages::from(1));
+        self.total_instructions_executed += instructions_executed;
+        self.query_allocations_used
+            .write()
+            .unwrap()
+            .update_allocation_after_execution(
+                canister,
+                QueryAllocation::from(instructions_executed),
+            );
+    }
+
     // A helper function to lookup the CallContextManager and create a new
     // CallContext in it.
     fn new_call_context(
@@ -404,13 +486,7 @@ impl<'a> QueryContext<'a> {
         measurement_scope: &MeasurementScope,
     ) -> (CanisterState, HypervisorResult<Option<WasmResult>>) {
         let call_context_id = self.new_call_context(&mut canister, call_origin);
-        let instruction_limit = self.max_instructions_per_message.min(
-            self.query_allocations_used
-                .write()
-                .unwrap()
-                .allocation_before_execution(&canister)
-                .into(),
-        );
+        let instruction_limit = self.instruction_limit(&canister);
         let execution_parameters = self.execution_parameters(&canister, instruction_limit);
         let (canister, instructions_left, result) = self.hypervisor.execute_query(
             QueryExecutionType::NonReplicated {
@@ -427,14 +503,7 @@ impl<'a> QueryContext<'a> {
             execution_parameters,
         );
         let instructions_executed = instruction_limit - instructions_left;
-        measurement_scope.add(instructions_executed, NumMessages::from(1));
-        self.query_allocations_used
-            .write()
-            .unwrap()
-            .update_allocation_after_execution(
-                &canister,
-                QueryAllocation::from(instructions_executed),
-            );
+        self.add_instructions_executed(&canister, instructions_executed, measurement_scope);
         (canister, result)
     }
 
@@ -480,13 +549,7 @@ impl<'a> QueryContext<'a> {
         subnet_records.insert(self.own_subnet_id, self.own_subnet_type);
         let subnet_records = Arc::new(subnet_records);
 
-        let instruction_limit = self.max_instructions_per_message.min(
-            self.query_allocations_used
-                .write()
-                .unwrap()
-                .allocation_before_execution(&canister)
-                .into(),
-        );
+        let instruction_limit = self.instruction_limit(&canister);
         let execution_parameters = self.execution_parameters(&canister, instruction_limit);
         let (canister, instructions_left, _heap_delta, execution_result) =
             self.hypervisor.execute_callback(
@@ -503,14 +566,7 @@ impl<'a> QueryContext<'a> {
                 self.state.metadata.network_topology.nns_subnet_id,
             );
         let instructions_executed = instruction_limit - instructions_left;
-        measurement_scope.add(instructions_executed, NumMessages::from(1));
-        self.query_allocations_used
-            .write()
-            .unwrap()
-            .update_allocation_after_execution(
-                &canister,
-                QueryAllocation::from(instructions_executed),
-            );
+        self.add_instructions_executed(&canister, instructions_executed, measurement_scope);
         (canister, call_context_id, call_origin, execution_result)
     }
 
@@ -573,13 +629,20 @@ impl<'a> QueryContext<'a> {
         };
 
         let call_origin = CallOrigin::CanisterQuery(request.sender, request.sender_reply_callback);
+        let query_kind = if canister.exports_composite_query_method(request.method_name.clone())
+            || self.query_calls_from_queries_enabled()
+        {
+            NonReplicatedQueryKind::Stateful
+        } else {
+            NonReplicatedQueryKind::Pure
+        };
         let (mut canister, result) = self.execute_query(
             canister,
             call_origin,
             request.method_name.as_str(),
             request.method_payload.as_slice(),
             request.sender.get(),
-            NonReplicatedQueryKind::Stateful,
+            query_kind,
             measurement_scope,
         );
 
@@ -925,6 +988,7 @@ impl<'a> QueryContext<'a> {
         ExecutionParameters {
             instruction_limit,
             canister_memory_limit: canister.memory_limit(self.max_canister_memory_size),
+            max_stable_memory_size: self.max_stable_memory_size,
             subnet_available_memory: self.subnet_available_memory.clone(),
             compute_allocation: canister.scheduler_state.compute_allocation,
             subnet_type: self.own_subnet_type,
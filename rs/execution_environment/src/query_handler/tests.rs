@@ -6,13 +6,17 @@ use crate::{
 };
 use ic_base_types::NumSeconds;
 use ic_config::execution_environment::Config;
+use ic_ic00_types::{
+    CanisterLogRecord, FetchCanisterLogsRequest, FetchCanisterLogsResponse, Method as Ic00Method,
+    Payload, IC_00,
+};
 use ic_interfaces::execution_environment::{
     ExecutionParameters, QueryHandler, SubnetAvailableMemory,
 };
 use ic_metrics::MetricsRegistry;
 use ic_registry_routing_table::{CanisterIdRange, RoutingTable};
 use ic_registry_subnet_type::SubnetType;
-use ic_replicated_state::ReplicatedState;
+use ic_replicated_state::{LogVisibility, ReplicatedState};
 use ic_test_utilities::{
     cycles_account_manager::CyclesAccountManagerBuilder,
     types::{
@@ -25,7 +29,9 @@ use ic_test_utilities::{
 use ic_types::{
     ingress::WasmResult, messages::UserQuery, user_error::ErrorCode, ComputeAllocation,
 };
-use ic_types::{CanisterId, Cycles, NumBytes, NumInstructions, SubnetId};
+use ic_types::{
+    CanisterId, Cycles, NumBytes, NumInstructions, SubnetId, UserId, MAX_STABLE_MEMORY_IN_BYTES,
+};
 use maplit::btreemap;
 use std::{path::Path, sync::Arc};
 
@@ -48,6 +54,8 @@ where
             subnet_type,
             1000,
             1,
+            100,
+            CYCLE_BALANCE,
         )
     }
 
@@ -96,6 +104,7 @@ where
             Config::default(),
             &metrics_registry,
             INSTRUCTION_LIMIT,
+            NumBytes::new(MAX_STABLE_MEMORY_IN_BYTES),
         );
         f(query_handler, canister_manager, state);
     });
@@ -130,6 +139,7 @@ fn universal_canister(
             ExecutionParameters {
                 instruction_limit: INSTRUCTION_LIMIT,
                 canister_memory_limit: MEMORY_CAPACITY,
+                max_stable_memory_size: NumBytes::new(MAX_STABLE_MEMORY_IN_BYTES),
                 subnet_available_memory: SubnetAvailableMemory::new(MEMORY_CAPACITY.get() as i64),
                 compute_allocation: ComputeAllocation::default(),
                 subnet_type: SubnetType::Application,
@@ -421,3 +431,83 @@ fn query_compilied_once() {
         },
     );
 }
+
+fn fetch_canister_logs_query(source: UserId, canister_id: CanisterId) -> UserQuery {
+    UserQuery {
+        source,
+        receiver: IC_00,
+        method_name: Ic00Method::FetchCanisterLogs.to_string(),
+        method_payload: FetchCanisterLogsRequest::new(canister_id).encode(),
+        ingress_expiry: 0,
+        nonce: None,
+    }
+}
+
+#[test]
+fn fetch_canister_logs_is_restricted_to_controllers_by_default() {
+    with_setup(
+        SubnetType::Application,
+        |query_handler, canister_manager, mut state| {
+            let canister_id = universal_canister(&canister_manager, &mut state);
+            let controller = user_test_id(1);
+            let canister = state.canister_state_mut(&canister_id).unwrap();
+            canister.system_state.controllers.insert(controller.get());
+            canister
+                .system_state
+                .canister_log
+                .add_record(42, b"hello".to_vec());
+            let state = Arc::new(state);
+
+            let output = query_handler.query(
+                fetch_canister_logs_query(controller, canister_id),
+                Arc::clone(&state),
+                vec![],
+            );
+            let expected = FetchCanisterLogsResponse {
+                canister_log_records: vec![CanisterLogRecord {
+                    idx: 0,
+                    timestamp_nanos: 42,
+                    content: b"hello".to_vec(),
+                }],
+            };
+            assert_eq!(output, Ok(WasmResult::Reply(expected.encode())));
+
+            let err = query_handler
+                .query(
+                    fetch_canister_logs_query(user_test_id(2), canister_id),
+                    state,
+                    vec![],
+                )
+                .unwrap_err();
+            assert_eq!(err.code(), ErrorCode::CanisterInvalidController);
+        },
+    );
+}
+
+#[test]
+fn fetch_canister_logs_with_public_visibility_is_allowed_for_anyone() {
+    with_setup(
+        SubnetType::Application,
+        |query_handler, canister_manager, mut state| {
+            let canister_id = universal_canister(&canister_manager, &mut state);
+            let canister = state.canister_state_mut(&canister_id).unwrap();
+            canister.system_state.log_visibility = LogVisibility::Public;
+            canister
+                .system_state
+                .canister_log
+                .add_record(42, b"hello".to_vec());
+
+            let output = query_handler.query(
+                fetch_canister_logs_query(user_test_id(2), canister_id),
+                Arc::new(state),
+                vec![],
+            );
+            let response = match output {
+                Ok(WasmResult::Reply(bytes)) => FetchCanisterLogsResponse::decode(&bytes).unwrap(),
+                other => panic!("Unexpected result: {:?}", other),
+            };
+            assert_eq!(response.canister_log_records.len(), 1);
+            assert_eq!(response.canister_log_records[0].content, b"hello".to_vec());
+        },
+    );
+}
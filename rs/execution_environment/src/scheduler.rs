@@ -16,13 +16,14 @@ use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_routing_table::RoutingTable;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
-    canister_state::QUEUE_INDEX_NONE, CanisterState, CanisterStatus, InputQueueType,
+    canister_state::QUEUE_INDEX_NONE, CanisterState, CanisterStatus, ExecutionTask, InputQueueType,
     ReplicatedState,
 };
 use ic_types::{
     ic00::{EmptyBlob, InstallCodeArgs, Payload as _, IC_00},
     ingress::{IngressStatus, WasmResult},
     messages::{Ingress, MessageId, Payload, Response, StopCanisterContext},
+    methods::SystemMethod,
     user_error::{ErrorCode, UserError},
     AccumulatedPriority, CanisterId, CanisterStatusType, ComputeAllocation, ExecutionRound,
     InstallCodeContext, MemoryAllocation, NumBytes, NumInstructions, Randomness, SubnetId, Time,
@@ -94,20 +95,32 @@ pub(crate) struct SchedulerImpl {
 // how errors should be tracked.
 //
 // An execution round consists of multiple iterations. The heartbeat should
-// run only in the first iteration.
+// run only in the first iteration, while expired global timers run in every
+// iteration.
 // Additionally, all errors should be tracked on system subnets, but on other
 // subnets only system errors should be tracked.
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 enum HeartbeatHandling {
     Execute { only_track_system_errors: bool },
-    Skip,
+    Skip { only_track_system_errors: bool },
 }
 
 impl HeartbeatHandling {
     pub fn should_execute_heartbeat(&self) -> bool {
         match self {
             Self::Execute { .. } => true,
-            Self::Skip => false,
+            Self::Skip { .. } => false,
+        }
+    }
+
+    pub fn only_track_system_errors(&self) -> bool {
+        match self {
+            Self::Execute {
+                only_track_system_errors,
+            }
+            | Self::Skip {
+                only_track_system_errors,
+            } => *only_track_system_errors,
         }
     }
 }
@@ -222,6 +235,7 @@ fn filter_idle_canisters(
     all_canister_states: &BTreeMap<CanisterId, CanisterState>,
     heartbeat_handling: HeartbeatHandling,
     heap_delta_rate_limit: NumBytes,
+    time: Time,
 ) -> (Vec<CanisterId>, BTreeSet<CanisterId>) {
     let mut rate_limited_canisters = BTreeSet::new();
     // Consider only canisters with some input messages for execution.
@@ -233,9 +247,18 @@ fn filter_idle_canisters(
             if !is_under_limit {
                 rate_limited_canisters.insert(**canister_id);
             }
-            (canister.has_input()
+            // Executions of `install_code` are continued together with the
+            // subnet messages, so the canister has to wait until they finish.
+            let has_install_code_task = canister
+                .system_state
+                .task_queue
+                .front()
+                .map_or(false, ExecutionTask::is_install_code);
+            (canister.has_input_or_task()
                 || (heartbeat_handling.should_execute_heartbeat()
-                    && canister.exports_heartbeat_method()))
+                    && canister.exports_heartbeat_method())
+                || has_expired_global_timer(canister, time))
+                && !has_install_code_task
                 && is_under_limit
         })
         .cloned()
@@ -243,6 +266,18 @@ fn filter_idle_canisters(
     (runnable_canisters, rate_limited_canisters)
 }
 
+// Returns true if the canister is running, exports the global timer method and
+// its global timer has reached the deadline. A canister that is not running
+// keeps its expired timer until it is started again.
+fn has_expired_global_timer(canister: &CanisterState, time: Time) -> bool {
+    canister.status() == CanisterStatusType::Running
+        && canister.exports_global_timer_method()
+        && canister
+            .system_state
+            .global_timer
+            .has_reached_deadline(time)
+}
+
 // Partitions the executable canisters to the available cores for execution.
 //
 // Returns the executable canisters partitioned by cores and the
@@ -338,7 +373,9 @@ impl SchedulerImpl {
                     only_track_system_errors: self.config.only_track_system_heartbeat_errors,
                 }
             } else {
-                HeartbeatHandling::Skip
+                HeartbeatHandling::Skip {
+                    only_track_system_errors: self.config.only_track_system_heartbeat_errors,
+                }
             };
 
             // Record subnet available memory before taking out the canisters.
@@ -351,6 +388,7 @@ impl SchedulerImpl {
                     &canisters,
                     heartbeat_handling,
                     self.config.heap_delta_rate_limit,
+                    state.time(),
                 );
             rate_limited_canister_ids.extend(new_rate_limited_canister_ids);
 
@@ -715,6 +753,9 @@ impl SchedulerImpl {
                 )
                 .is_err()
             {
+                if canister.has_paused_execution() {
+                    self.exec_env.abort_paused_execution(canister);
+                }
                 all_rejects.push(uninstall_canister(
                     &self.log,
                     canister,
@@ -914,6 +955,67 @@ impl Scheduler for SchedulerImpl {
                 self.config.max_instructions_per_round / 4;
             let mut total_instructions_consumed = NumInstructions::from(0);
 
+            // Executions of `install_code` messages that were paused or aborted
+            // in earlier rounds are continued before any new subnet message is
+            // executed.
+            let canister_ids_with_install_code_tasks: Vec<CanisterId> = state
+                .canisters_iter()
+                .filter(|canister| {
+                    canister
+                        .system_state
+                        .task_queue
+                        .front()
+                        .map_or(false, ExecutionTask::is_install_code)
+                })
+                .map(|canister| canister.canister_id())
+                .collect();
+            for canister_id in canister_ids_with_install_code_tasks {
+                if total_instructions_consumed >= max_instructions_per_round_for_subnet_messages {
+                    break;
+                }
+                let aborted_message = state.canister_state_mut(&canister_id).and_then(|canister| {
+                    match canister.system_state.task_queue.pop_front() {
+                        Some(ExecutionTask::AbortedInstallCode(msg)) => Some(msg),
+                        Some(task) => {
+                            canister.system_state.task_queue.push_front(task);
+                            None
+                        }
+                        None => None,
+                    }
+                });
+                let (new_state, instructions_left, instructions_limit) = match aborted_message {
+                    Some(msg) => {
+                        let msg = CanisterInputMessage::from(msg);
+                        let instructions_limit =
+                            get_instructions_limit_for_subnet_message(&self.config, &msg);
+                        let (new_state, instructions_left) = self.exec_env.execute_subnet_message(
+                            msg,
+                            state,
+                            instructions_limit,
+                            &mut csprng,
+                            &provisional_whitelist,
+                            subnet_available_memory.clone(),
+                            max_number_of_canisters,
+                        );
+                        (new_state, instructions_left, instructions_limit)
+                    }
+                    None => {
+                        let instructions_limit = self.config.max_instructions_per_install_code;
+                        let (new_state, instructions_left) = self.exec_env.resume_install_code(
+                            canister_id,
+                            state,
+                            instructions_limit,
+                        );
+                        (new_state, instructions_left, instructions_limit)
+                    }
+                };
+
+                state = new_state;
+                let instructions_consumed = instructions_limit - instructions_left;
+                total_instructions_consumed += instructions_consumed;
+                measurement_scope.add(instructions_consumed, NumMessages::from(1));
+            }
+
             while let Some(msg) = state.pop_subnet_input() {
                 let instructions_limit_per_message =
                     get_instructions_limit_for_subnet_message(&self.config, &msg);
@@ -957,7 +1059,7 @@ impl Scheduler for SchedulerImpl {
 
                 for canister_id in &ordered_canister_ids {
                     let canister_state = canisters.get_mut(canister_id).unwrap();
-                    if !canister_state.has_input() {
+                    if !canister_state.has_input_or_task() {
                         canister_state
                             .system_state
                             .canister_metrics
@@ -1090,70 +1192,101 @@ fn execute_canisters_on_thread(
             continue;
         }
 
-        // Run heartbeat before processing the messages. Otherwise, if there are many
-        // messages, we may reach the instruction limit before running heartbeat.
-        if let HeartbeatHandling::Execute {
-            only_track_system_errors,
-        } = heartbeat_handling
-        {
-            if canister.exports_heartbeat_method() {
-                let measurement_scope = MeasurementScope::nested(
-                    &metrics.round_inner_iteration_thread_heartbeat,
-                    &measurement_scope,
-                );
-                let timer = metrics.msg_execution_duration.start_timer();
-                let (new_canister, num_instructions_left, result) = exec_env
-                    .execute_canister_heartbeat(
-                        canister,
-                        canister_execution_limits.instruction_limit_per_message,
-                        Arc::clone(&routing_table),
-                        Arc::clone(&subnet_records),
-                        time,
-                        subnet_available_memory.clone(),
-                        nns_subnet_id,
-                    );
-                let heap_delta = match result {
-                    Ok(heap_delta) => heap_delta,
-                    Err(err) => {
-                        if only_track_system_errors || err.is_system_error() {
-                            let log_count = HEARTBEAT_ERROR_COUNT.fetch_add(1, Ordering::SeqCst);
-                            if log_count % LOG_ONE_HEARTBEAT_OUT_OF == 0 {
-                                info!(
-                                    logger,
-                                    "Error executing heartbeat on canister {} with failure `{}`",
-                                    new_canister.canister_id(),
-                                    err;
-                                    messaging.canister_id => new_canister.canister_id().to_string(),
-                                );
+        // Run heartbeat and the expired global timer before processing the
+        // messages. Otherwise, if there are many messages, we may reach the
+        // instruction limit before running them.
+        // System tasks of a canister with a paused execution wait until the
+        // execution completes.
+        for system_task in &[
+            SystemMethod::CanisterHeartbeat,
+            SystemMethod::CanisterGlobalTimer,
+        ] {
+            if canister.has_paused_execution() {
+                break;
+            }
+            let should_execute = match system_task {
+                SystemMethod::CanisterGlobalTimer => has_expired_global_timer(&canister, time),
+                _ => {
+                    heartbeat_handling.should_execute_heartbeat()
+                        && canister.exports_heartbeat_method()
+                }
+            };
+            if !should_execute {
+                continue;
+            }
+            let measurement_scope = MeasurementScope::nested(
+                &metrics.round_inner_iteration_thread_heartbeat,
+                &measurement_scope,
+            );
+            let timer = metrics.msg_execution_duration.start_timer();
+            let (new_canister, num_instructions_left, result) = match system_task {
+                SystemMethod::CanisterGlobalTimer => exec_env.execute_canister_global_timer(
+                    canister,
+                    canister_execution_limits.instruction_limit_per_message,
+                    Arc::clone(&routing_table),
+                    Arc::clone(&subnet_records),
+                    time,
+                    subnet_available_memory.clone(),
+                    nns_subnet_id,
+                ),
+                _ => exec_env.execute_canister_heartbeat(
+                    canister,
+                    canister_execution_limits.instruction_limit_per_message,
+                    Arc::clone(&routing_table),
+                    Arc::clone(&subnet_records),
+                    time,
+                    subnet_available_memory.clone(),
+                    nns_subnet_id,
+                ),
+            };
+            let heap_delta = match result {
+                Ok(heap_delta) => heap_delta,
+                Err(err) => {
+                    if heartbeat_handling.only_track_system_errors() || err.is_system_error() {
+                        let log_count = HEARTBEAT_ERROR_COUNT.fetch_add(1, Ordering::SeqCst);
+                        if log_count % LOG_ONE_HEARTBEAT_OUT_OF == 0 {
+                            info!(
+                                logger,
+                                "Error executing {} on canister {} with failure `{}`",
+                                system_task,
+                                new_canister.canister_id(),
+                                err;
+                                messaging.canister_id => new_canister.canister_id().to_string(),
+                            );
+                        }
+                        match system_task {
+                            SystemMethod::CanisterGlobalTimer => {
+                                metrics.execution_round_failed_global_timer_executions.inc()
                             }
-                            metrics.execution_round_failed_heartbeat_executions.inc();
+                            _ => metrics.execution_round_failed_heartbeat_executions.inc(),
                         }
-                        NumBytes::from(0)
                     }
-                };
-                let instructions_consumed =
-                    canister_execution_limits.instruction_limit_per_message - num_instructions_left;
-                measurement_scope.add(instructions_consumed, NumMessages::from(1));
-                observe_instructions_consumed_per_message(
-                    &logger,
-                    &metrics,
-                    &new_canister,
-                    instructions_consumed,
-                    canister_execution_limits.instruction_limit_per_message,
-                );
-                canister = new_canister;
-                total_instructions_executed += instructions_consumed;
-                total_messages_executed.inc_assign();
-                total_heap_delta += heap_delta;
-                canister.scheduler_state.heap_delta_debit += heap_delta;
-                drop(timer);
-            }
+                    NumBytes::from(0)
+                }
+            };
+            let instructions_consumed =
+                canister_execution_limits.instruction_limit_per_message - num_instructions_left;
+            measurement_scope.add(instructions_consumed, NumMessages::from(1));
+            observe_instructions_consumed_per_message(
+                &logger,
+                &metrics,
+                &new_canister,
+                instructions_consumed,
+                canister_execution_limits.instruction_limit_per_message,
+            );
+            canister = new_canister;
+            total_instructions_executed += instructions_consumed;
+            total_messages_executed.inc_assign();
+            total_heap_delta += heap_delta;
+            canister.scheduler_state.heap_delta_debit += heap_delta;
+            drop(timer);
         }
 
-        // Process all messages of the canister until
-        // - either its input queue is empty.
+        // Process all tasks and messages of the canister until
+        // - either its task queue and input queue are empty.
         // - or the instruction limit is reached.
-        while canister.has_input() {
+        // - or an execution is paused at the end of its slice.
+        while canister.has_input_or_task() {
             if total_instructions_executed + canister_execution_limits.instruction_limit_per_message
                 > canister_execution_limits.total_instruction_limit
             {
@@ -1167,19 +1300,33 @@ fn execute_canisters_on_thread(
                 &metrics.round_inner_iteration_thread_message,
                 &measurement_scope,
             );
-            let message = canister.pop_input().unwrap();
-            let msg_info = message.to_string();
             let timer = metrics.msg_execution_duration.start_timer();
-            let result = exec_env.execute_canister_message(
-                canister,
-                canister_execution_limits.instruction_limit_per_message,
-                message,
-                time,
-                Arc::clone(&routing_table),
-                Arc::clone(&subnet_records),
-                subnet_available_memory.clone(),
-                nns_subnet_id,
-            );
+            let (msg_info, result) = match next_execution(&mut canister) {
+                NextExecution::ContinuePaused => (
+                    "paused execution".to_string(),
+                    exec_env.resume_paused_execution(
+                        canister,
+                        canister_execution_limits.instruction_limit_per_message,
+                        Arc::clone(&routing_table),
+                        Arc::clone(&subnet_records),
+                        nns_subnet_id,
+                    ),
+                ),
+                NextExecution::StartNew(message) => (
+                    message.to_string(),
+                    exec_env.execute_canister_message(
+                        canister,
+                        canister_execution_limits.instruction_limit_per_message,
+                        message,
+                        time,
+                        Arc::clone(&routing_table),
+                        Arc::clone(&subnet_records),
+                        subnet_available_memory.clone(),
+                        nns_subnet_id,
+                    ),
+                ),
+                NextExecution::None => break,
+            };
             let instructions_consumed = canister_execution_limits.instruction_limit_per_message
                 - result.num_instructions_left;
             measurement_scope.add(instructions_consumed, NumMessages::from(1));
@@ -1209,14 +1356,16 @@ fn execute_canisters_on_thread(
                     messaging.canister_id => canister.canister_id().to_string(),
                 );
             }
-            if total_heap_delta >= canister_execution_limits.max_heap_delta_per_iteration {
+            if total_heap_delta >= canister_execution_limits.max_heap_delta_per_iteration
+                || canister.has_paused_execution()
+            {
                 break;
             }
         }
         if let Some(es) = &mut canister.execution_state {
             es.last_executed_round = round_id;
         }
-        if !canister.has_input() || rank == 0 {
+        if !canister.has_input_or_task() || rank == 0 {
             // The very first canister is considered to have a full execution round for
             // scheduling purposes even if it did not complete within the round.
             canister.scheduler_state.last_full_execution_round = round_id;
@@ -1239,6 +1388,40 @@ fn execute_canisters_on_thread(
     }
 }
 
+// The next execution of a canister on an execution thread.
+enum NextExecution {
+    // The paused execution at the front of the task queue is resumed.
+    ContinuePaused,
+    // A new execution of the message is started.
+    StartNew(CanisterInputMessage),
+    // Nothing can be executed on an execution thread.
+    None,
+}
+
+// Determines the next execution of the canister: tasks take precedence over
+// the messages in the input queues. Paused executions stay in the task queue
+// because `ExecutionEnvironment::resume_paused_execution()` pops them itself.
+// Tasks of `install_code` messages are handled by the subnet message
+// execution, so they block the canister on execution threads.
+fn next_execution(canister: &mut CanisterState) -> NextExecution {
+    match canister.system_state.task_queue.front() {
+        Some(ExecutionTask::PausedExecution(_)) => NextExecution::ContinuePaused,
+        Some(ExecutionTask::AbortedExecution(_)) => {
+            match canister.system_state.task_queue.pop_front() {
+                Some(ExecutionTask::AbortedExecution(msg)) => NextExecution::StartNew(msg.into()),
+                _ => unreachable!("The front task of the canister changed unexpectedly."),
+            }
+        }
+        Some(ExecutionTask::PausedInstallCode(_)) | Some(ExecutionTask::AbortedInstallCode(_)) => {
+            NextExecution::None
+        }
+        None => match canister.pop_input() {
+            Some(message) => NextExecution::StartNew(message),
+            None => NextExecution::None,
+        },
+    }
+}
+
 fn observe_replicated_state_metrics(state: &ReplicatedState, metrics: &SchedulerMetrics) {
     // Observe the number of registered canisters keyed by their status.
     let mut num_running_canisters = 0;
@@ -1340,7 +1523,12 @@ fn get_instructions_limit_for_subnet_message(
             CanisterStatus
             | CreateCanister
             | DeleteCanister
+            | DeleteCanisterSnapshot
             | DepositCycles
+            | FetchCanisterLogs
+            | HttpRequest
+            | ListCanisterSnapshots
+            | LoadCanisterSnapshot
             | RawRand
             | SetController
             | SetupInitialDKG
@@ -1349,6 +1537,7 @@ fn get_instructions_limit_for_subnet_message(
             | SignWithMockECDSA
             | StartCanister
             | StopCanister
+            | TakeCanisterSnapshot
             | UninstallCode
             | UpdateSettings
             | ProvisionalCreateCanisterWithCycles
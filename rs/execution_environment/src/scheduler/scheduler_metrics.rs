@@ -53,6 +53,7 @@ pub(super) struct SchedulerMetrics {
     pub(super) round_finalization_ingress: Histogram,
     pub(super) round_finalization_charge: Histogram,
     pub(super) execution_round_failed_heartbeat_executions: IntCounter,
+    pub(super) execution_round_failed_global_timer_executions: IntCounter,
     pub(super) canister_heap_delta_debits: Histogram,
     pub(super) heap_delta_rate_limited_canisters_per_round: Histogram,
 }
@@ -400,6 +401,10 @@ impl SchedulerMetrics {
                 "execution_round_failed_heartbeat_executions",
                 "Total number of heartbeat executions that completed in error",
             ),
+            execution_round_failed_global_timer_executions: metrics_registry.int_counter(
+                "execution_round_failed_global_timer_executions",
+                "Total number of global timer executions that completed in error",
+            ),
             canister_heap_delta_debits: metrics_registry.histogram(
                 "scheduler_canister_heap_delta_debits",
                 "The heap delta debit of a canister at the end of the round, before \
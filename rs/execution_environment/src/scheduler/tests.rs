@@ -1,9 +1,15 @@
 use super::*;
 #[cfg(test)]
 use crate::execution_environment::MockExecutionEnvironment;
+use crate::{ExecutionEnvironmentImpl, Hypervisor, IngressHistoryWriterImpl};
+use assert_matches::assert_matches;
 use candid::Encode;
 use ic_base_types::NumSeconds;
-use ic_config::subnet_config::{CyclesAccountManagerConfig, SchedulerConfig};
+use ic_config::{
+    execution_environment::Config as ExecutionConfig,
+    feature_status::FeatureStatus,
+    subnet_config::{CyclesAccountManagerConfig, SchedulerConfig},
+};
 use ic_ic00_types::{CanisterIdRecord, Method};
 use ic_interfaces::execution_environment::{
     CanisterHeartbeatError, ExecuteMessageResult, HypervisorError,
@@ -15,10 +21,11 @@ use ic_registry_routing_table::{CanisterIdRange, RoutingTable};
 use ic_replicated_state::{
     canister_state::{ENFORCE_MESSAGE_MEMORY_USAGE, QUEUE_INDEX_NONE},
     testing::{CanisterQueuesTesting, ReplicatedStateTesting},
-    CallOrigin, ExportedFunctions,
+    CallOrigin, CanisterTimer, ExportedFunctions, PausedTask,
 };
 use ic_test_utilities::{
     cycles_account_manager::CyclesAccountManagerBuilder,
+    execution_state::ExecutionStateBuilder,
     history::MockIngressHistory,
     metrics::{fetch_histogram_stats, fetch_int_counter, fetch_int_gauge_vec, metric_vec},
     mock_time,
@@ -29,18 +36,20 @@ use ic_test_utilities::{
     },
     types::{
         ids::{canister_test_id, message_test_id, subnet_test_id, user_test_id},
-        messages::{RequestBuilder, SignedIngressBuilder},
+        messages::{IngressBuilder, RequestBuilder, SignedIngressBuilder},
     },
     with_test_replica_logger,
 };
-use ic_types::messages::{CallbackId, RequestOrResponse, MAX_RESPONSE_COUNT_BYTES};
+use ic_types::messages::{
+    CallbackId, RequestOrIngress, RequestOrResponse, MAX_RESPONSE_COUNT_BYTES,
+};
 use ic_types::methods::SystemMethod;
 use ic_types::{
     ingress::WasmResult,
     methods::WasmMethod,
     time::UNIX_EPOCH,
     user_error::{ErrorCode, UserError},
-    ComputeAllocation, Cycles, NumBytes,
+    ComputeAllocation, Cycles, NumBytes, MAX_STABLE_MEMORY_IN_BYTES,
 };
 use ic_wasm_types::WasmEngineError;
 use lazy_static::lazy_static;
@@ -3239,7 +3248,7 @@ proptest! {
                     state.clone(),
                     Randomness::from([0; 32]),
                     ExecutionRound::from(LAST_ROUND_MAX + 1),
-                    ProvisionalWhitelist::Set(BTreeSet::new()),
+                        ProvisionalWhitelist::Set(BTreeSet::new()),
                     MAX_NUMBER_OF_CANISTERS,
                 );
             },
@@ -3287,14 +3296,14 @@ proptest! {
                     state.clone(),
                     Randomness::from([0; 32]),
                     ExecutionRound::from(LAST_ROUND_MAX + 1),
-                    ProvisionalWhitelist::Set(BTreeSet::new()),
+                        ProvisionalWhitelist::Set(BTreeSet::new()),
                     MAX_NUMBER_OF_CANISTERS,
                 );
                 let new_state2 = scheduler.execute_round(
                     state.clone(),
                     Randomness::from([0; 32]),
                     ExecutionRound::from(LAST_ROUND_MAX + 1),
-                    ProvisionalWhitelist::Set(BTreeSet::new()),
+                        ProvisionalWhitelist::Set(BTreeSet::new()),
                     MAX_NUMBER_OF_CANISTERS,
                 );
                 assert_eq!(new_state1, new_state2);
@@ -3352,7 +3361,7 @@ proptest! {
                             state,
                             Randomness::from([0; 32]),
                             ExecutionRound::from(round),
-                            ProvisionalWhitelist::Set(BTreeSet::new()),
+                                        ProvisionalWhitelist::Set(BTreeSet::new()),
                             MAX_NUMBER_OF_CANISTERS,
                         );
                 }
@@ -3406,7 +3415,7 @@ proptest! {
                     state.clone(),
                     Randomness::from([0; 32]),
                     ExecutionRound::from(LAST_ROUND_MAX + 1),
-                    ProvisionalWhitelist::Set(BTreeSet::new()),
+                        ProvisionalWhitelist::Set(BTreeSet::new()),
                     MAX_NUMBER_OF_CANISTERS,
                 );
                 assert_eq!(state.canisters_iter().count(), original_canister_count);
@@ -3488,6 +3497,470 @@ proptest! {
     }
 }
 
+/// A paused execution is resumed before any new message of the canister is
+/// executed.
+#[test]
+fn paused_execution_is_resumed_before_new_messages() {
+    let scheduler_test_fixture = SchedulerTestFixture {
+        scheduler_config: SchedulerConfig {
+            scheduler_cores: 1,
+            max_instructions_per_round: NumInstructions::from(100),
+            max_instructions_per_message: NumInstructions::from(10),
+            ..SchedulerConfig::application_subnet()
+        },
+        metrics_registry: MetricsRegistry::new(),
+        canister_num: 1,
+        message_num_per_canister: 1,
+    };
+    let mut exec_env = default_exec_env_mock(
+        &scheduler_test_fixture,
+        1,
+        NumInstructions::from(1),
+        NumBytes::new(0),
+    );
+    exec_env
+        .expect_resume_paused_execution()
+        .times(1)
+        .returning(|mut canister, _, _, _, _| {
+            // The new message has not been executed yet.
+            assert_eq!(canister.system_state.queues().ingress_queue_size(), 1);
+            assert_matches!(
+                canister.system_state.task_queue.pop_front(),
+                Some(ExecutionTask::PausedExecution(_))
+            );
+            ExecuteMessageResult {
+                canister,
+                num_instructions_left: NumInstructions::from(5),
+                ingress_status: None,
+                heap_delta: NumBytes::new(0),
+            }
+        });
+    let exec_env = Arc::new(exec_env);
+    let ingress_history_writer = Arc::new(default_ingress_history_writer_mock(1));
+
+    scheduler_test(
+        &scheduler_test_fixture,
+        |scheduler| {
+            let mut state = get_initial_state(1, 1);
+            let canister_id = canister_test_id(0);
+            state
+                .canister_state_mut(&canister_id)
+                .unwrap()
+                .system_state
+                .task_queue
+                .push_back(ExecutionTask::PausedExecution(PausedTask {
+                    message: RequestOrIngress::Ingress(
+                        IngressBuilder::new().receiver(canister_id).build(),
+                    ),
+                    time: mock_time(),
+                    initial_cycles_balance: *INITIAL_CYCLES,
+                    subnet_available_memory: SUBNET_AVAILABLE_MEMORY,
+                    slice_instruction_limits: vec![NumInstructions::from(10)],
+                    instructions_executed: NumInstructions::from(10),
+                    wasm_simd: false,
+                }));
+
+            let state = scheduler.execute_round(
+                state,
+                Randomness::from([0; 32]),
+                ExecutionRound::from(1),
+                ProvisionalWhitelist::Set(BTreeSet::new()),
+                MAX_NUMBER_OF_CANISTERS,
+            );
+
+            let canister = state.canister_state(&canister_id).unwrap();
+            assert!(canister.system_state.task_queue.is_empty());
+            assert_eq!(canister.system_state.queues().ingress_queue_size(), 0);
+        },
+        ingress_history_writer,
+        exec_env,
+    );
+}
+
+/// An aborted execution is restarted from scratch before any new message of
+/// the canister is executed.
+#[test]
+fn aborted_execution_is_restarted() {
+    let scheduler_test_fixture = SchedulerTestFixture {
+        scheduler_config: SchedulerConfig {
+            scheduler_cores: 1,
+            max_instructions_per_round: NumInstructions::from(100),
+            max_instructions_per_message: NumInstructions::from(10),
+            ..SchedulerConfig::application_subnet()
+        },
+        metrics_registry: MetricsRegistry::new(),
+        canister_num: 1,
+        message_num_per_canister: 0,
+    };
+    let exec_env = default_exec_env_mock(
+        &scheduler_test_fixture,
+        1,
+        NumInstructions::from(1),
+        NumBytes::new(0),
+    );
+    let exec_env = Arc::new(exec_env);
+    let ingress_history_writer = Arc::new(default_ingress_history_writer_mock(1));
+
+    scheduler_test(
+        &scheduler_test_fixture,
+        |scheduler| {
+            let mut state = get_initial_state(1, 0);
+            let canister_id = canister_test_id(0);
+            let ingress = IngressBuilder::new().receiver(canister_id).build();
+            state
+                .canister_state_mut(&canister_id)
+                .unwrap()
+                .system_state
+                .task_queue
+                .push_back(ExecutionTask::AbortedExecution(RequestOrIngress::Ingress(
+                    ingress,
+                )));
+
+            let state = scheduler.execute_round(
+                state,
+                Randomness::from([0; 32]),
+                ExecutionRound::from(1),
+                ProvisionalWhitelist::Set(BTreeSet::new()),
+                MAX_NUMBER_OF_CANISTERS,
+            );
+
+            let canister = state.canister_state(&canister_id).unwrap();
+            assert!(canister.system_state.task_queue.is_empty());
+        },
+        ingress_history_writer,
+        exec_env,
+    );
+}
+
+// A Wasm module with an update method that runs a long loop writing to the
+// memory and replies with the entire contents of the memory.
+const LONG_RUNNING_UPDATE_WAT: &str = r#"(module
+    (import "ic0" "msg_reply" (func $msg_reply))
+    (import "ic0" "msg_reply_data_append"
+        (func $msg_reply_data_append (param i32 i32)))
+    (func $work
+        (local $i i32)
+        (loop $loop
+            (i32.store
+                (i32.and (i32.mul (local.get $i) (i32.const 4)) (i32.const 0xfffc))
+                (i32.add (global.get $acc) (local.get $i)))
+            (global.set $acc
+                (i32.add (global.get $acc) (i32.mul (local.get $i) (i32.const 7))))
+            (local.set $i (i32.add (local.get $i) (i32.const 1)))
+            (br_if $loop (i32.lt_u (local.get $i) (i32.const 50000))))
+        (call $msg_reply_data_append (i32.const 0) (i32.const 65536))
+        (call $msg_reply))
+    (memory $memory 1)
+    (global $acc (mut i32) (i32.const 0))
+    (export "canister_update work" (func $work)))"#;
+
+// Executes the update method of `LONG_RUNNING_UPDATE_WAT`, see
+// `execute_long_running_message()`. Returns the final ingress status, the
+// final balance of the canister and the number of rounds the execution took.
+fn execute_long_running_update(
+    scheduler_config: SchedulerConfig,
+    execution_config: ExecutionConfig,
+    restart_every_rounds: Option<u64>,
+) -> (IngressStatus, Cycles, u64) {
+    let (status, canister, rounds) = execute_long_running_message(
+        LONG_RUNNING_UPDATE_WAT,
+        scheduler_config,
+        execution_config,
+        restart_every_rounds,
+    );
+    (status, canister.system_state.cycles_balance, rounds)
+}
+
+// Executes the `work` update method of the canister `wat` with a real
+// execution environment until the ingress message completes. If
+// `restart_every_rounds` is set, the execution environment and the scheduler
+// are recreated every that many rounds, which drops the paused executions
+// kept in memory just like a restart of the replica. Returns the final
+// ingress status, the final state of the canister and the number of rounds
+// the execution took.
+fn execute_long_running_message(
+    wat: &str,
+    scheduler_config: SchedulerConfig,
+    execution_config: ExecutionConfig,
+    restart_every_rounds: Option<u64>,
+) -> (IngressStatus, CanisterState, u64) {
+    with_test_replica_logger(|log| {
+        let tmpdir = tempfile::Builder::new().prefix("test").tempdir().unwrap();
+        let subnet_id = subnet_test_id(1);
+        let subnet_type = SubnetType::Application;
+        let new_scheduler = || {
+            let metrics_registry = MetricsRegistry::new();
+            let cycles_account_manager = Arc::new(
+                CyclesAccountManagerBuilder::new()
+                    .with_max_num_instructions(MAX_INSTRUCTIONS_PER_MESSAGE)
+                    .build(),
+            );
+            let hypervisor = Arc::new(Hypervisor::new(
+                execution_config.clone(),
+                1,
+                &metrics_registry,
+                subnet_id,
+                subnet_type,
+                log.clone(),
+                Arc::clone(&cycles_account_manager),
+            ));
+            let ingress_history_writer = Arc::new(IngressHistoryWriterImpl::new(
+                log.clone(),
+                &metrics_registry,
+            ));
+            let exec_env = Arc::new(ExecutionEnvironmentImpl::new(
+                log.clone(),
+                hypervisor,
+                Arc::clone(&ingress_history_writer) as Arc<_>,
+                &metrics_registry,
+                subnet_id,
+                subnet_type,
+                1,
+                NumBytes::new(MAX_STABLE_MEMORY_IN_BYTES),
+                execution_config.clone(),
+                Arc::clone(&cycles_account_manager),
+            ));
+            SchedulerImpl::new(
+                scheduler_config.clone(),
+                subnet_id,
+                ingress_history_writer,
+                exec_env,
+                cycles_account_manager,
+                &metrics_registry,
+                log.clone(),
+            )
+        };
+        let mut scheduler = new_scheduler();
+
+        let mut state =
+            ReplicatedState::new_rooted_at(subnet_id, subnet_type, tmpdir.path().to_path_buf());
+        let canister_id = canister_test_id(0);
+        let mut canister = new_canister_state(
+            canister_id,
+            user_test_id(24).get(),
+            *INITIAL_CYCLES,
+            NumSeconds::from(100_000),
+        );
+        let wasm_binary = wabt::wat2wasm(wat).unwrap();
+        canister.execution_state =
+            Some(ExecutionStateBuilder::new(wasm_binary, tmpdir.path().into()).build());
+        let ingress = SignedIngressBuilder::new()
+            .canister_id(canister_id)
+            .method_name("work")
+            .build();
+        let message_id = ingress.id();
+        canister.push_ingress(ingress.into());
+        state.put_canister_state(canister);
+
+        for round in 1..=1000 {
+            if let Some(restart_every_rounds) = restart_every_rounds {
+                if round % restart_every_rounds == 0 {
+                    scheduler = new_scheduler();
+                }
+            }
+            state = scheduler.execute_round(
+                state,
+                Randomness::from([0; 32]),
+                ExecutionRound::from(round),
+                ProvisionalWhitelist::Set(BTreeSet::new()),
+                MAX_NUMBER_OF_CANISTERS,
+            );
+            let status = state.get_ingress_status(&message_id);
+            if let IngressStatus::Completed { .. } | IngressStatus::Failed { .. } = status {
+                let canister = state.canister_state(&canister_id).unwrap().clone();
+                return (status, canister, round);
+            }
+        }
+        panic!("The long-running update did not complete within 1000 rounds.");
+    })
+}
+
+/// A message executed in many slices across multiple rounds produces the
+/// same result and charges the same amount of cycles as the same message
+/// executed in one go.
+#[test]
+fn sliced_execution_is_identical_to_single_shot_execution() {
+    let (single_shot_status, single_shot_balance, single_shot_rounds) = execute_long_running_update(
+        SchedulerConfig {
+            scheduler_cores: 1,
+            max_instructions_per_round: NumInstructions::from(1_000_000_000),
+            max_instructions_per_message: NumInstructions::from(1_000_000_000),
+            ..SchedulerConfig::application_subnet()
+        },
+        ExecutionConfig::default(),
+        None,
+    );
+    assert_eq!(single_shot_rounds, 1);
+    assert_matches!(
+        single_shot_status,
+        IngressStatus::Completed {
+            result: WasmResult::Reply(_),
+            ..
+        }
+    );
+
+    let (sliced_status, sliced_balance, sliced_rounds) = execute_long_running_update(
+        SchedulerConfig {
+            scheduler_cores: 1,
+            max_instructions_per_round: NumInstructions::from(50_000),
+            max_instructions_per_message: NumInstructions::from(10_000),
+            ..SchedulerConfig::application_subnet()
+        },
+        ExecutionConfig {
+            deterministic_time_slicing: FeatureStatus::Enabled,
+            max_instructions_per_slice: NumInstructions::from(10_000),
+            max_instructions_per_sliced_message: NumInstructions::from(1_000_000_000),
+            ..ExecutionConfig::default()
+        },
+        None,
+    );
+    assert!(sliced_rounds > 1);
+    assert_eq!(sliced_status, single_shot_status);
+    assert_eq!(sliced_balance, single_shot_balance);
+}
+
+/// A paused execution is part of the state: after a restart it is recreated
+/// from its task and completes exactly like an uninterrupted execution.
+#[test]
+fn paused_execution_survives_restarts() {
+    let scheduler_config = SchedulerConfig {
+        scheduler_cores: 1,
+        max_instructions_per_round: NumInstructions::from(50_000),
+        max_instructions_per_message: NumInstructions::from(10_000),
+        ..SchedulerConfig::application_subnet()
+    };
+    let execution_config = ExecutionConfig {
+        deterministic_time_slicing: FeatureStatus::Enabled,
+        max_instructions_per_slice: NumInstructions::from(10_000),
+        max_instructions_per_sliced_message: NumInstructions::from(1_000_000_000),
+        ..ExecutionConfig::default()
+    };
+    let (status, balance, rounds) =
+        execute_long_running_update(scheduler_config.clone(), execution_config.clone(), None);
+    let (restarted_status, restarted_balance, restarted_rounds) =
+        execute_long_running_update(scheduler_config, execution_config, Some(3));
+    assert!(rounds > 3);
+    assert_eq!(restarted_status, status);
+    assert_eq!(restarted_balance, balance);
+    assert_eq!(restarted_rounds, rounds);
+}
+
+fn sliced_scheduler_config() -> SchedulerConfig {
+    SchedulerConfig {
+        scheduler_cores: 1,
+        max_instructions_per_round: NumInstructions::from(50_000),
+        max_instructions_per_message: NumInstructions::from(10_000),
+        ..SchedulerConfig::application_subnet()
+    }
+}
+
+fn sliced_execution_config() -> ExecutionConfig {
+    ExecutionConfig {
+        deterministic_time_slicing: FeatureStatus::Enabled,
+        max_instructions_per_slice: NumInstructions::from(10_000),
+        max_instructions_per_sliced_message: NumInstructions::from(1_000_000_000),
+        ..ExecutionConfig::default()
+    }
+}
+
+/// The records a sliced execution adds to the canister log are kept once the
+/// execution completes.
+#[test]
+fn sliced_execution_keeps_log_records() {
+    let wat = r#"
+        (module
+            (import "ic0" "debug_print" (func $debug_print (param i32 i32)))
+            (import "ic0" "msg_reply" (func $msg_reply))
+            (func $work
+                (local $i i32)
+                (loop $loop
+                    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                    (br_if $loop (i32.lt_u (local.get $i) (i32.const 50000))))
+                (call $debug_print (i32.const 0) (i32.const 4))
+                (call $msg_reply))
+            (memory 1)
+            (data (i32.const 0) "done")
+            (export "canister_update work" (func $work)))"#;
+    let (status, canister, rounds) = execute_long_running_message(
+        wat,
+        sliced_scheduler_config(),
+        sliced_execution_config(),
+        None,
+    );
+    assert!(rounds > 1);
+    assert_matches!(
+        status,
+        IngressStatus::Completed {
+            result: WasmResult::Reply(_),
+            ..
+        }
+    );
+    let contents: Vec<_> = canister
+        .system_state
+        .canister_log
+        .records()
+        .iter()
+        .map(|record| record.content.clone())
+        .collect();
+    assert_eq!(contents, vec![b"done".to_vec()]);
+}
+
+/// A timer set by a sliced execution is kept once the execution completes.
+#[test]
+fn sliced_execution_keeps_global_timer() {
+    let wat = r#"
+        (module
+            (import "ic0" "global_timer_set" (func $global_timer_set (param i64) (result i64)))
+            (import "ic0" "msg_reply" (func $msg_reply))
+            (func $work
+                (local $i i32)
+                (loop $loop
+                    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                    (br_if $loop (i32.lt_u (local.get $i) (i32.const 50000))))
+                (drop (call $global_timer_set (i64.const 1000000000000000000)))
+                (call $msg_reply))
+            (export "canister_update work" (func $work)))"#;
+    let (status, canister, rounds) = execute_long_running_message(
+        wat,
+        sliced_scheduler_config(),
+        sliced_execution_config(),
+        None,
+    );
+    assert!(rounds > 1);
+    assert_matches!(
+        status,
+        IngressStatus::Completed {
+            result: WasmResult::Reply(_),
+            ..
+        }
+    );
+    assert_eq!(
+        canister.system_state.global_timer,
+        CanisterTimer::from_nanos_since_unix_epoch(1_000_000_000_000_000_000)
+    );
+}
+
+/// Canister sandboxing cannot pause executions, so it cannot be combined with
+/// deterministic time slicing.
+#[test]
+#[should_panic(expected = "Deterministic time slicing is not supported with canister sandboxing")]
+fn sandboxing_with_deterministic_time_slicing_is_rejected() {
+    let cycles_account_manager = Arc::new(CyclesAccountManagerBuilder::new().build());
+    Hypervisor::new(
+        ExecutionConfig {
+            canister_sandboxing_flag: FeatureStatus::Enabled,
+            deterministic_time_slicing: FeatureStatus::Enabled,
+            ..ExecutionConfig::default()
+        },
+        1,
+        &MetricsRegistry::new(),
+        subnet_test_id(1),
+        SubnetType::Application,
+        no_op_logger(),
+        cycles_account_manager,
+    );
+}
+
 struct SchedulerTestFixture {
     pub scheduler_config: SchedulerConfig,
     pub metrics_registry: MetricsRegistry,
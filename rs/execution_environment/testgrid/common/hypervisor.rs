@@ -15,8 +15,8 @@ use ic_registry_routing_table::{CanisterIdRange, RoutingTable};
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::page_map::MemoryRegion;
 use ic_replicated_state::{
-    testing::CanisterQueuesTesting, CallContextAction, CallOrigin, CanisterState, Global,
-    NumWasmPages, SystemState,
+    testing::CanisterQueuesTesting, CallContextAction, CallOrigin, CanisterState, CanisterTimer,
+    Global, NumWasmPages, SystemState,
 };
 use ic_replicated_state::{PageIndex, PageMap};
 use ic_sys::PAGE_SIZE;
@@ -40,7 +40,7 @@ use ic_types::{
     methods::{Callback, FuncRef, SystemMethod, WasmClosure, WasmMethod},
     user_error::RejectCode,
     CanisterId, ComputeAllocation, Cycles, MemoryAllocation, NumBytes, NumInstructions,
-    PrincipalId, SubnetId, Time, UserId,
+    PrincipalId, SubnetId, Time, UserId, MAX_STABLE_MEMORY_IN_BYTES,
 };
 use ic_utils::ic_features::cow_state_feature;
 use lazy_static::lazy_static;
@@ -67,6 +67,7 @@ fn execution_parameters(
     ExecutionParameters {
         instruction_limit,
         canister_memory_limit: canister.memory_limit(NumBytes::new(u64::MAX / 2)),
+        max_stable_memory_size: NumBytes::new(MAX_STABLE_MEMORY_IN_BYTES),
         subnet_available_memory: MAX_SUBNET_AVAILABLE_MEMORY.clone(),
         compute_allocation: canister.scheduler_state.compute_allocation,
         subnet_type: SubnetType::Application,
@@ -330,6 +331,7 @@ fn execute(
     let execution_parameters = ExecutionParameters {
         instruction_limit: MAX_NUM_INSTRUCTIONS,
         canister_memory_limit: NumBytes::from(4 << 30),
+        max_stable_memory_size: NumBytes::new(MAX_STABLE_MEMORY_IN_BYTES),
         subnet_available_memory: MAX_SUBNET_AVAILABLE_MEMORY.clone(),
         compute_allocation: ComputeAllocation::default(),
         subnet_type: SubnetType::Application,
@@ -3058,6 +3060,60 @@ fn sys_api_call_ic_trap() {
     });
 }
 
+#[test]
+fn debug_print_and_trap_message_are_kept_in_canister_log() {
+    with_hypervisor(|hypervisor, tmp_path| {
+        let (canister, _, action, _) = execute_update(
+            &hypervisor,
+            r#"(module
+                  (import "ic0" "debug_print" (func $debug_print (param i32) (param i32)))
+                  (import "ic0" "trap" (func $ic_trap (param i32) (param i32)))
+                  (func $test
+                    (call $debug_print (i32.const 0) (i32.const 5))
+                    (call $ic_trap (i32.const 5) (i32.const 3)))
+
+                  (memory $memory 1)
+                  (export "memory" (memory $memory))
+                  (export "canister_update test" (func $test))
+                  (data (i32.const 0) "debugHi!")
+            )"#,
+            "test",
+            EMPTY_PAYLOAD,
+            None,
+            tmp_path,
+        );
+        let error = HypervisorError::CalledTrap("Hi!".to_string());
+        assert_eq!(
+            action,
+            CallContextAction::Fail {
+                error: error.clone(),
+                refund: Cycles::from(0),
+            }
+        );
+
+        // The log survives the rollback of the failed execution.
+        let records: Vec<_> = canister
+            .system_state
+            .canister_log
+            .records()
+            .iter()
+            .cloned()
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].content, b"debug".to_vec());
+        assert_eq!(
+            records[1].content,
+            format!("[TRAP]: {}", error).into_bytes()
+        );
+        for record in records {
+            assert_eq!(
+                record.timestamp_nanos,
+                mock_time().as_nanos_since_unix_epoch()
+            );
+        }
+    });
+}
+
 #[test]
 fn globals_are_updated_in_execution_state_after_message_execution() {
     with_hypervisor(|hypervisor, tmp_path| {
@@ -3589,6 +3645,7 @@ fn test_system_method_is_callable(system_method: SystemMethod) {
             SystemMethod::CanisterInspectMessage => unimplemented!(),
             SystemMethod::Empty => unimplemented!(),
             SystemMethod::CanisterHeartbeat => unimplemented!("We don't need this test."),
+            SystemMethod::CanisterGlobalTimer => unimplemented!("We don't need this test."),
         };
 
         assert!(
@@ -3681,6 +3738,14 @@ fn test_non_existing_system_method(system_method: SystemMethod) {
                 execution_parameters,
                 subnet_test_id(0x101), // NNS subnet
             ),
+            SystemMethod::CanisterGlobalTimer => hypervisor.execute_canister_global_timer(
+                canister,
+                routing_table,
+                subnet_records,
+                mock_time(),
+                execution_parameters,
+                subnet_test_id(0x101), // NNS subnet
+            ),
         };
 
         assert!(
@@ -3721,6 +3786,11 @@ fn test_non_existing_canister_heartbeat() {
     test_non_existing_system_method(SystemMethod::CanisterHeartbeat);
 }
 
+#[test]
+fn test_non_existing_canister_global_timer() {
+    test_non_existing_system_method(SystemMethod::CanisterGlobalTimer);
+}
+
 #[test]
 fn canister_init_can_set_mutable_globals() {
     with_hypervisor(|hypervisor, tmp_path| {
@@ -4203,6 +4273,42 @@ fn canister_heartbeat() {
     });
 }
 
+// Tests that the global timer method can set a new global timer.
+#[test]
+fn canister_global_timer_can_set_global_timer() {
+    with_hypervisor(|hypervisor, tmp_path| {
+        let wasm = wabt::wat2wasm(
+            r#"
+            (module
+              (import "ic0" "global_timer_set"
+                (func $global_timer_set (param i64) (result i64)))
+              (func (export "canister_global_timer")
+                (drop (call $global_timer_set (i64.const 1000))))
+              (memory (export "memory") 1))"#,
+        )
+        .unwrap();
+
+        let execution_state = ExecutionStateBuilder::new(wasm, tmp_path).build();
+        let canister = canister_from_exec_state(execution_state);
+        let (_, _, _, routing_table, subnet_records) = setup();
+        let execution_parameters = execution_parameters(&canister, MAX_NUM_INSTRUCTIONS);
+
+        let (canister, _, result) = hypervisor.execute_canister_global_timer(
+            canister,
+            routing_table,
+            subnet_records,
+            mock_time(),
+            execution_parameters,
+            subnet_test_id(0x101), // NNS subnet
+        );
+        assert!(result.is_ok());
+        assert_eq!(
+            canister.system_state.global_timer,
+            CanisterTimer::Active(Time::from_nanos_since_unix_epoch(1000))
+        );
+    });
+}
+
 // Tests that execute_canister_heartbeat produces a heap delta.
 #[test]
 fn execute_canister_heartbeat_produces_heap_delta() {
@@ -48,6 +48,10 @@ use ic_test_utilities::{
     with_test_replica_logger,
 };
 use ic_types::{
+    canister_http::{
+        MAX_CANISTER_HTTP_REQUESTS_IN_FLIGHT, MAX_CANISTER_HTTP_REQUEST_BYTES,
+        MAX_CANISTER_HTTP_URL_SIZE,
+    },
     canonical_error::{not_found_error, permission_denied_error},
     ic00,
     ic00::{
@@ -63,6 +67,7 @@ use ic_types::{
     user_error::{ErrorCode, RejectCode, UserError},
     CanisterId, CanisterStatusType, ComputeAllocation, Cycles, MemoryAllocation, NumBytes,
     NumInstructions, PrincipalId, QueueIndex, RegistryVersion, SubnetId,
+    MAX_STABLE_MEMORY_IN_BYTES,
 };
 use lazy_static::lazy_static;
 use maplit::btreemap;
@@ -152,6 +157,7 @@ where
             subnet_id,
             subnet_type,
             1,
+            NumBytes::new(MAX_STABLE_MEMORY_IN_BYTES),
             execution_environment::Config::default(),
             cycles_account_manager,
         );
@@ -195,6 +201,7 @@ fn test_outgoing_messages(
             subnet_id,
             subnet_type,
             1,
+            NumBytes::new(MAX_STABLE_MEMORY_IN_BYTES),
             execution_environment::Config::default(),
             cycles_account_manager,
         );
@@ -1409,6 +1416,10 @@ fn get_running_canister_status_from_another_canister() {
             ComputeAllocation::default().as_percent(),
             None,
             123,
+            0,
+            execution_environment::Config::default()
+                .default_reserved_balance_limit
+                .get(),
         ),
     )
 }
@@ -1433,6 +1444,10 @@ fn get_stopped_canister_status_from_another_canister() {
             ComputeAllocation::default().as_percent(),
             None,
             123,
+            0,
+            execution_environment::Config::default()
+                .default_reserved_balance_limit
+                .get(),
         ),
     );
 }
@@ -1457,6 +1472,10 @@ fn get_stopping_canister_status_from_another_canister() {
             ComputeAllocation::default().as_percent(),
             None,
             123,
+            0,
+            execution_environment::Config::default()
+                .default_reserved_balance_limit
+                .get(),
         ),
     );
 }
@@ -1934,6 +1953,7 @@ fn get_execution_environment(
         own_subnet_id,
         subnet_type,
         1,
+        NumBytes::new(MAX_STABLE_MEMORY_IN_BYTES),
         execution_environment::Config::default(),
         cycles_account_manager,
     );
@@ -2367,7 +2387,7 @@ fn install_code_fails_on_invalid_memory_allocation() {
                 user_id: sender,
                 error: UserError::new(
                     ErrorCode::CanisterContractViolation,
-                    "MemoryAllocation expected to be in the range [0..12_884_901_888], got 18_446_744_073_709_551_615"
+                    "MemoryAllocation expected to be in the range [0..73_014_444_032], got 18_446_744_073_709_551_615"
                 ),
                 time: mock_time(),
             });
@@ -2406,6 +2426,7 @@ fn metrics_are_observed_for_subnet_messages() {
             subnet_id,
             subnet_type,
             1,
+            NumBytes::new(MAX_STABLE_MEMORY_IN_BYTES),
             execution_environment::Config::default(),
             cycles_account_manager,
         );
@@ -3119,3 +3140,332 @@ fn test_allocating_memory_reduces_subnet_available_memory() {
         },
     );
 }
+
+/// Returns the arguments of an HTTP request to `url` without headers, body
+/// or transform method.
+fn http_request_args(url: &str) -> ic00::CanisterHttpRequestArgs {
+    ic00::CanisterHttpRequestArgs {
+        url: url.to_string(),
+        method: ic00::HttpMethod::Get,
+        headers: vec![],
+        body: None,
+        transform_method_name: None,
+    }
+}
+
+/// Returns the fee that an application subnet charges for the given request.
+fn http_request_fee(args: &ic00::CanisterHttpRequestArgs) -> Cycles {
+    let config = CyclesAccountManagerConfig::application_subnet();
+    let request_size = args.url.len() + args.body.as_ref().map_or(0, |body| body.len());
+    config.http_request_fee + config.http_request_byte_fee * Cycles::from(request_size as u64)
+}
+
+/// Sends an HTTP request with the given payment from `sender` to the
+/// management canister.
+fn execute_http_request(
+    exec_env: &ExecutionEnvironmentImpl,
+    mut state: ReplicatedState,
+    sender: CanisterId,
+    args: ic00::CanisterHttpRequestArgs,
+    payment: Cycles,
+) -> ReplicatedState {
+    state
+        .subnet_queues_mut()
+        .push_input(
+            QUEUE_INDEX_NONE,
+            RequestOrResponse::Request(
+                RequestBuilder::new()
+                    .sender(sender)
+                    .receiver(IC_00)
+                    .method_name(Method::HttpRequest)
+                    .method_payload(args.encode())
+                    .payment(payment)
+                    .build(),
+            ),
+            InputQueueType::RemoteSubnet,
+        )
+        .unwrap();
+    exec_env
+        .execute_subnet_message(
+            state.subnet_queues_mut().pop_input().unwrap(),
+            state,
+            MAX_NUM_INSTRUCTIONS,
+            &mut mock_random_number_generator(),
+            &ProvisionalWhitelist::Set(BTreeSet::new()),
+            MAX_SUBNET_AVAILABLE_MEMORY.clone(),
+            MAX_NUMBER_OF_CANISTERS,
+        )
+        .0
+}
+
+/// Asserts that the request of `sender` was rejected with the given message
+/// and its payment was refunded in full.
+fn assert_http_request_rejected(
+    state: &mut ReplicatedState,
+    sender: CanisterId,
+    payment: Cycles,
+    reject_code: RejectCode,
+    message: &str,
+) {
+    assert!(state
+        .metadata
+        .subnet_call_context_manager
+        .canister_http_request_contexts
+        .is_empty());
+    match state
+        .subnet_queues_mut()
+        .pop_canister_output(&sender)
+        .unwrap()
+        .1
+    {
+        RequestOrResponse::Response(response) => {
+            assert_eq!(response.refund, payment);
+            match response.response_payload {
+                Payload::Reject(context) => {
+                    assert_eq!(context.code, reject_code);
+                    assert!(
+                        context.message.contains(message),
+                        "Unexpected reject message: {}",
+                        context.message
+                    );
+                }
+                payload => panic!("Expected a reject, got {:?}", payload),
+            }
+        }
+        msg => panic!("Expected a response, got {:?}", msg),
+    }
+}
+
+#[test]
+fn http_request_fails_if_the_feature_is_not_enabled() {
+    with_setup(SubnetType::Application, |exec_env, state, _, _, _| {
+        let sender = canister_test_id(1);
+        let args = http_request_args("https://example.com");
+        let payment = http_request_fee(&args);
+
+        let mut state = execute_http_request(&exec_env, state, sender, args, payment);
+        assert_http_request_rejected(
+            &mut state,
+            sender,
+            payment,
+            RejectCode::CanisterError,
+            "This API is not enabled on this subnet",
+        );
+    });
+}
+
+#[test]
+fn http_request_rejects_invalid_urls_and_oversized_requests() {
+    with_setup(SubnetType::Application, |exec_env, mut state, _, _, _| {
+        state.metadata.own_subnet_features.http_requests = true;
+        let sender = canister_test_id(1);
+        let payment = Cycles::new(1_000_000_000_000_000);
+
+        let args = http_request_args("http://example.com");
+        let mut state = execute_http_request(&exec_env, state, sender, args, payment);
+        assert_http_request_rejected(
+            &mut state,
+            sender,
+            payment,
+            RejectCode::CanisterReject,
+            "Only HTTPS URLs are supported",
+        );
+
+        let url = format!("https://{}", "a".repeat(MAX_CANISTER_HTTP_URL_SIZE));
+        let args = http_request_args(&url);
+        let mut state = execute_http_request(&exec_env, state, sender, args, payment);
+        assert_http_request_rejected(
+            &mut state,
+            sender,
+            payment,
+            RejectCode::CanisterReject,
+            "The URL of a HTTP request must not be longer than",
+        );
+
+        let mut args = http_request_args("https://example.com");
+        args.body = Some(vec![0; MAX_CANISTER_HTTP_REQUEST_BYTES]);
+        let mut state = execute_http_request(&exec_env, state, sender, args, payment);
+        assert_http_request_rejected(
+            &mut state,
+            sender,
+            payment,
+            RejectCode::CanisterReject,
+            "which exceeds the limit of",
+        );
+    });
+}
+
+#[test]
+fn http_request_charges_a_fee() {
+    with_setup(SubnetType::Application, |exec_env, mut state, _, _, _| {
+        state.metadata.own_subnet_features.http_requests = true;
+        let sender = canister_test_id(1);
+        let args = http_request_args("https://example.com");
+        let fee = http_request_fee(&args);
+
+        // Requests that do not pay the fee are rejected.
+        let payment = fee - Cycles::new(1);
+        let mut state = execute_http_request(&exec_env, state, sender, args.clone(), payment);
+        assert_http_request_rejected(
+            &mut state,
+            sender,
+            payment,
+            RejectCode::CanisterReject,
+            "cycles are required",
+        );
+
+        // Only the payment exceeding the fee is kept for the refund.
+        let payment = fee + Cycles::new(1_000);
+        let state = execute_http_request(&exec_env, state, sender, args, payment);
+        let contexts = &state
+            .metadata
+            .subnet_call_context_manager
+            .canister_http_request_contexts;
+        assert_eq!(contexts.len(), 1);
+        assert_eq!(
+            contexts.values().next().unwrap().request.payment,
+            Cycles::new(1_000)
+        );
+    });
+}
+
+#[test]
+fn http_request_fails_if_too_many_requests_are_open() {
+    with_setup(SubnetType::Application, |exec_env, mut state, _, _, _| {
+        state.metadata.own_subnet_features.http_requests = true;
+        let sender = canister_test_id(1);
+        let args = http_request_args("https://example.com");
+        let payment = http_request_fee(&args);
+
+        for _ in 0..MAX_CANISTER_HTTP_REQUESTS_IN_FLIGHT {
+            state = execute_http_request(&exec_env, state, sender, args.clone(), payment);
+        }
+        assert_eq!(
+            state
+                .metadata
+                .subnet_call_context_manager
+                .canister_http_request_contexts
+                .len(),
+            MAX_CANISTER_HTTP_REQUESTS_IN_FLIGHT
+        );
+
+        let mut state = execute_http_request(&exec_env, state, sender, args, payment);
+        assert_eq!(
+            state
+                .metadata
+                .subnet_call_context_manager
+                .canister_http_request_contexts
+                .len(),
+            MAX_CANISTER_HTTP_REQUESTS_IN_FLIGHT
+        );
+        match state
+            .subnet_queues_mut()
+            .pop_canister_output(&sender)
+            .unwrap()
+            .1
+        {
+            RequestOrResponse::Response(response) => {
+                assert_eq!(response.refund, payment);
+                assert_matches!(
+                    response.response_payload,
+                    Payload::Reject(RejectContext {
+                        code: RejectCode::SysFatal,
+                        ..
+                    })
+                );
+            }
+            msg => panic!("Expected a response, got {:?}", msg),
+        }
+    });
+}
+
+// A canister whose `transform` query replies with "transformed".
+const TRANSFORM_WAT: &str = r#"(module
+                  (import "ic0" "msg_reply" (func $msg_reply))
+                  (import "ic0" "msg_reply_data_append"
+                    (func $msg_reply_data_append (param i32) (param i32)))
+                  (func $transform
+                    (call $msg_reply_data_append
+                        (i32.const 0) (i32.const 11))    ;; refers to "transformed"
+                    (call $msg_reply))
+                  (export "canister_query transform" (func $transform))
+                  (memory $memory 1)
+                  (export "memory" (memory $memory))
+                  (data (i32.const 0) "transformed")
+            )"#;
+
+#[test]
+fn http_response_is_passed_through_the_transform_method() {
+    with_setup(SubnetType::Application, |exec_env, mut state, _, _, _| {
+        state.metadata.own_subnet_features.http_requests = true;
+        let sender = canister_test_id(1);
+
+        let wasm_binary = wabt::wat2wasm(TRANSFORM_WAT).unwrap();
+        let tmpdir = tempfile::Builder::new().prefix("test").tempdir().unwrap();
+        let wasm_embedder = WasmtimeEmbedder::new(EmbeddersConfig::new(), no_op_logger());
+        let execution_state = wasm_embedder
+            .create_execution_state(
+                wasm_binary,
+                tmpdir.path().into(),
+                &EmbeddersConfig::default(),
+            )
+            .unwrap();
+        let mut canister = get_running_canister(sender);
+        canister.execution_state = Some(execution_state);
+        state.put_canister_state(canister);
+
+        let mut args = http_request_args("https://example.com");
+        args.transform_method_name = Some("transform".to_string());
+        let payment = http_request_fee(&args) + Cycles::new(1_000);
+        let state = execute_http_request(&exec_env, state, sender, args, payment);
+        let (callback_id, context) = state
+            .metadata
+            .subnet_call_context_manager
+            .canister_http_request_contexts
+            .iter()
+            .next()
+            .unwrap();
+        let callback_id = *callback_id;
+        let sender_reply_callback = context.request.sender_reply_callback;
+
+        // The response delivered by consensus goes through the transform
+        // method before it is sent back to the canister.
+        let response = ResponseBuilder::new()
+            .originator(IC_00)
+            .respondent(IC_00)
+            .originator_reply_callback(callback_id)
+            .response_payload(Payload::Data(b"response".to_vec()))
+            .build();
+        let mut state = exec_env
+            .execute_subnet_message(
+                CanisterInputMessage::Response(response),
+                state,
+                MAX_NUM_INSTRUCTIONS,
+                &mut mock_random_number_generator(),
+                &ProvisionalWhitelist::Set(BTreeSet::new()),
+                MAX_SUBNET_AVAILABLE_MEMORY.clone(),
+                MAX_NUMBER_OF_CANISTERS,
+            )
+            .0;
+        assert!(state
+            .metadata
+            .subnet_call_context_manager
+            .canister_http_request_contexts
+            .is_empty());
+        let own_subnet_id = state.metadata.own_subnet_id;
+        assert_eq!(
+            state
+                .subnet_queues_mut()
+                .pop_canister_output(&sender)
+                .unwrap()
+                .1,
+            RequestOrResponse::Response(Response {
+                originator: sender,
+                respondent: CanisterId::from(own_subnet_id),
+                originator_reply_callback: sender_reply_callback,
+                refund: Cycles::new(1_000),
+                response_payload: Payload::Data(b"transformed".to_vec()),
+            })
+        );
+    });
+}
@@ -15,7 +15,8 @@ use ic_test_utilities::{
     types::messages::IngressBuilder, with_test_replica_logger,
 };
 use ic_types::{
-    ingress::WasmResult, CanisterId, ComputeAllocation, Cycles, NumBytes, NumInstructions, SubnetId,
+    ingress::WasmResult, CanisterId, ComputeAllocation, Cycles, NumBytes, NumInstructions,
+    SubnetId, MAX_STABLE_MEMORY_IN_BYTES,
 };
 use maplit::btreemap;
 use proptest::prelude::*;
@@ -25,6 +26,7 @@ fn execution_parameters() -> ExecutionParameters {
     ExecutionParameters {
         instruction_limit: NumInstructions::new(1_000_000_000),
         canister_memory_limit: NumBytes::new(u64::MAX / 2),
+        max_stable_memory_size: NumBytes::new(MAX_STABLE_MEMORY_IN_BYTES),
         subnet_available_memory: SubnetAvailableMemory::new(i64::MAX / 2),
         compute_allocation: ComputeAllocation::default(),
         subnet_type: SubnetType::Application,
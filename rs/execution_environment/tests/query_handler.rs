@@ -1,17 +1,25 @@
+use ic_config::embedders::Config as EmbeddersConfig;
 use ic_config::execution_environment::Config;
 use ic_config::subnet_config::SubnetConfigs;
+use ic_embedders::WasmtimeEmbedder;
 use ic_execution_environment::setup_execution;
+use ic_interfaces::execution_environment::QueryHandler;
+use ic_logger::replica_logger::no_op_logger;
 use ic_metrics::MetricsRegistry;
 use ic_registry_routing_table::{CanisterIdRange, RoutingTable};
 use ic_registry_subnet_type::SubnetType;
-use ic_replicated_state::ReplicatedState;
+use ic_replicated_state::{CanisterState, ReplicatedState, SchedulerState};
 use ic_test_utilities::{
     cycles_account_manager::CyclesAccountManagerBuilder,
+    state::SystemStateBuilder,
     state_manager::FakeStateManager,
     types::ids::{subnet_test_id, user_test_id},
     with_test_replica_logger,
 };
-use ic_types::{messages::UserQuery, user_error::ErrorCode, CanisterId, SubnetId};
+use ic_types::{
+    ingress::WasmResult, messages::UserQuery, user_error::ErrorCode, CanisterId, Cycles,
+    NumInstructions, SubnetId,
+};
 use maplit::btreemap;
 use std::{path::Path, sync::Arc};
 
@@ -66,3 +74,247 @@ async fn query_non_existent() {
         }
     });
 }
+
+// A composite query that calls `forward` on the canister whose id is in the
+// first 10 bytes of its argument and passes the rest of the argument along.
+// It replies with the reply of the callee and rejects with its reject message.
+const FORWARDER_WAT: &str = r#"(module
+    (import "ic0" "msg_arg_data_size" (func $msg_arg_data_size (result i32)))
+    (import "ic0" "msg_arg_data_copy" (func $msg_arg_data_copy (param i32 i32 i32)))
+    (import "ic0" "msg_reject_msg_size" (func $msg_reject_msg_size (result i32)))
+    (import "ic0" "msg_reject_msg_copy" (func $msg_reject_msg_copy (param i32 i32 i32)))
+    (import "ic0" "msg_reply_data_append" (func $msg_reply_data_append (param i32 i32)))
+    (import "ic0" "msg_reply" (func $msg_reply))
+    (import "ic0" "msg_reject" (func $msg_reject (param i32 i32)))
+    (import "ic0" "call_new"
+        (func $call_new
+            (param i32 i32)
+            (param $method_name_src i32)    (param $method_name_len i32)
+            (param $reply_fun i32)          (param $reply_env i32)
+            (param $reject_fun i32)         (param $reject_env i32)
+    ))
+    (import "ic0" "call_data_append" (func $call_data_append (param i32 i32)))
+    (import "ic0" "call_perform" (func $call_perform (result i32)))
+    (func $forward
+        (call $msg_arg_data_copy (i32.const 100) (i32.const 0) (call $msg_arg_data_size))
+        (call $call_new
+            (i32.const 100) (i32.const 10)  ;; callee id from the argument
+            (i32.const 0) (i32.const 7)     ;; refers to "forward" on the heap
+            (i32.const 0) (i32.const 0)     ;; on_reply closure
+            (i32.const 1) (i32.const 0)     ;; on_reject closure
+        )
+        (call $call_data_append
+            (i32.const 110) (i32.sub (call $msg_arg_data_size) (i32.const 10)))
+        (drop (call $call_perform)))
+    (func $on_reply (param i32)
+        (call $msg_arg_data_copy (i32.const 100) (i32.const 0) (call $msg_arg_data_size))
+        (call $msg_reply_data_append (i32.const 100) (call $msg_arg_data_size))
+        (call $msg_reply))
+    (func $on_reject (param i32)
+        (call $msg_reject_msg_copy (i32.const 100) (i32.const 0) (call $msg_reject_msg_size))
+        (call $msg_reject (i32.const 100) (call $msg_reject_msg_size)))
+    (table funcref (elem $on_reply $on_reject))
+    (memory $memory 1)
+    (export "memory" (memory $memory))
+    (data (i32.const 0) "forward")
+    (export "canister_composite_query forward" (func $forward)))"#;
+
+// A query that replies with "pong".
+const PONG_WAT: &str = r#"(module
+    (import "ic0" "msg_reply_data_append" (func $msg_reply_data_append (param i32 i32)))
+    (import "ic0" "msg_reply" (func $msg_reply))
+    (func $forward
+        (call $msg_reply_data_append (i32.const 0) (i32.const 4))
+        (call $msg_reply))
+    (memory $memory 1)
+    (export "memory" (memory $memory))
+    (data (i32.const 0) "pong")
+    (export "canister_query forward" (func $forward)))"#;
+
+// A query that traps.
+const TRAP_WAT: &str = r#"(module
+    (import "ic0" "trap" (func $trap (param i32 i32)))
+    (func $forward
+        (call $trap (i32.const 0) (i32.const 4)))
+    (memory $memory 1)
+    (export "memory" (memory $memory))
+    (data (i32.const 0) "boom")
+    (export "canister_query forward" (func $forward)))"#;
+
+// A query that loops until it runs out of instructions.
+const BUSY_WAT: &str = r#"(module
+    (func $forward
+        (loop $loop (br $loop)))
+    (memory $memory 1)
+    (export "memory" (memory $memory))
+    (export "canister_query forward" (func $forward)))"#;
+
+fn canister_from_wat(path: &Path, canister_id: CanisterId, wat: &str) -> CanisterState {
+    let canister_root = path.join(canister_id.to_string());
+    std::fs::create_dir_all(&canister_root).unwrap();
+    let wasm_embedder = WasmtimeEmbedder::new(EmbeddersConfig::new(), no_op_logger());
+    let execution_state = wasm_embedder
+        .create_execution_state(
+            wabt::wat2wasm(wat).unwrap(),
+            canister_root,
+            &EmbeddersConfig::default(),
+        )
+        .unwrap();
+    CanisterState {
+        system_state: SystemStateBuilder::new().canister_id(canister_id).build(),
+        execution_state: Some(execution_state),
+        scheduler_state: SchedulerState::default(),
+    }
+}
+
+// Installs the given canisters on an application subnet and runs `forward` on
+// the first one with the ids of the others as argument.
+fn run_composite_query(
+    max_instructions_per_message: NumInstructions,
+    max_query_call_graph_instructions: NumInstructions,
+    canisters: Vec<(&str, Cycles)>,
+) -> Result<WasmResult, ic_types::user_error::UserError> {
+    with_test_replica_logger(|log| {
+        let subnet_id = subnet_test_id(1);
+        let subnet_type = SubnetType::Application;
+        let mut subnet_config = SubnetConfigs::default().own_subnet_config(subnet_type);
+        subnet_config.scheduler_config.max_instructions_per_message = max_instructions_per_message;
+        let config = Config {
+            max_query_call_graph_instructions,
+            ..Config::default()
+        };
+        let tmpdir = tempfile::Builder::new().prefix("test").tempdir().unwrap();
+        let mut state = initial_state(tmpdir.path(), subnet_id);
+        let metrics_registry = MetricsRegistry::new();
+        let cycles_account_manager = Arc::new(CyclesAccountManagerBuilder::new().build());
+        let state_manager = Arc::new(FakeStateManager::new());
+
+        let (_, _, _, query_handler, _, _) = setup_execution(
+            log,
+            &metrics_registry,
+            subnet_id,
+            subnet_type,
+            subnet_config.scheduler_config,
+            config,
+            cycles_account_manager,
+            state_manager,
+        );
+
+        let mut canister_ids = vec![];
+        for (i, (wat, cycles)) in canisters.into_iter().enumerate() {
+            let canister_id = CanisterId::from(i as u64 + 1);
+            let mut canister = canister_from_wat(tmpdir.path(), canister_id, wat);
+            canister.system_state.cycles_balance = cycles;
+            state.put_canister_state(canister);
+            canister_ids.push(canister_id);
+        }
+
+        query_handler.query(
+            UserQuery {
+                source: user_test_id(2),
+                receiver: canister_ids[0],
+                method_name: "forward".to_string(),
+                method_payload: canister_ids[1..]
+                    .iter()
+                    .flat_map(|id| id.get_ref().as_slice().to_vec())
+                    .collect(),
+                ingress_expiry: 0,
+                nonce: None,
+            },
+            Arc::new(state),
+            vec![],
+        )
+    })
+}
+
+const MAX_INSTRUCTIONS_PER_MESSAGE: NumInstructions = NumInstructions::new(10_000_000);
+const MAX_QUERY_CALL_GRAPH_INSTRUCTIONS: NumInstructions = NumInstructions::new(50_000_000);
+const CYCLES: Cycles = Cycles::new(1_000_000_000_000);
+
+#[tokio::test]
+async fn composite_query_nested_calls() {
+    let result = run_composite_query(
+        MAX_INSTRUCTIONS_PER_MESSAGE,
+        MAX_QUERY_CALL_GRAPH_INSTRUCTIONS,
+        vec![
+            (FORWARDER_WAT, CYCLES),
+            (FORWARDER_WAT, CYCLES),
+            (PONG_WAT, CYCLES),
+        ],
+    );
+    assert_eq!(result, Ok(WasmResult::Reply(b"pong".to_vec())));
+}
+
+#[tokio::test]
+async fn composite_query_does_not_need_cycles() {
+    let result = run_composite_query(
+        MAX_INSTRUCTIONS_PER_MESSAGE,
+        MAX_QUERY_CALL_GRAPH_INSTRUCTIONS,
+        vec![(FORWARDER_WAT, Cycles::zero()), (PONG_WAT, Cycles::zero())],
+    );
+    assert_eq!(result, Ok(WasmResult::Reply(b"pong".to_vec())));
+}
+
+#[tokio::test]
+async fn composite_query_callee_trap_is_rejected_to_caller() {
+    let result = run_composite_query(
+        MAX_INSTRUCTIONS_PER_MESSAGE,
+        MAX_QUERY_CALL_GRAPH_INSTRUCTIONS,
+        vec![(FORWARDER_WAT, CYCLES), (TRAP_WAT, CYCLES)],
+    );
+    match result {
+        Ok(WasmResult::Reject(msg)) => assert!(msg.contains("trapped explicitly: boom"), "{}", msg),
+        other => panic!("Expected a reject, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn composite_query_callee_out_of_instructions_is_rejected_to_caller() {
+    let result = run_composite_query(
+        MAX_INSTRUCTIONS_PER_MESSAGE,
+        MAX_QUERY_CALL_GRAPH_INSTRUCTIONS,
+        vec![(FORWARDER_WAT, CYCLES), (BUSY_WAT, CYCLES)],
+    );
+    match result {
+        Ok(WasmResult::Reject(msg)) => {
+            assert!(msg.contains("exceeded the cycles limit"), "{}", msg)
+        }
+        other => panic!("Expected a reject, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn composite_query_fails_when_call_graph_runs_out_of_instructions() {
+    // The call graph cannot afford a single full execution of the callee.
+    let result = run_composite_query(
+        MAX_INSTRUCTIONS_PER_MESSAGE,
+        NumInstructions::new(MAX_INSTRUCTIONS_PER_MESSAGE.get() / 2),
+        vec![(FORWARDER_WAT, CYCLES), (BUSY_WAT, CYCLES)],
+    );
+    assert_eq!(
+        result.unwrap_err().code(),
+        ErrorCode::QueryCallGraphTotalInstructionLimitExceeded
+    );
+}
+
+#[tokio::test]
+async fn regular_query_cannot_call_other_canisters_on_application_subnet() {
+    // Only the composite query of the first canister may call the second
+    // one; the second canister's regular query must not call the third.
+    let result = run_composite_query(
+        MAX_INSTRUCTIONS_PER_MESSAGE,
+        MAX_QUERY_CALL_GRAPH_INSTRUCTIONS,
+        vec![
+            (FORWARDER_WAT, CYCLES),
+            (
+                &FORWARDER_WAT.replace("canister_composite_query", "canister_query"),
+                CYCLES,
+            ),
+            (PONG_WAT, CYCLES),
+        ],
+    );
+    match result {
+        Ok(WasmResult::Reject(msg)) => assert!(msg.contains("violated contract"), "{}", msg),
+        other => panic!("Expected a reject, got {:?}", other),
+    }
+}
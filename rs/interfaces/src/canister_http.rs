@@ -0,0 +1,149 @@
+//! The interfaces used to make HTTP requests on behalf of canisters and to
+//! agree on their responses through consensus.
+use crate::validation::ValidationError;
+use ic_types::{
+    batch::{CanisterHttpPayload, ValidationContext},
+    canister_http::{CanisterHttpRequest, CanisterHttpRequestId, CanisterHttpResponse},
+    crypto::CryptoError,
+    Height, NodeId, NumBytes, RegistryVersion,
+};
+
+/// The error returned when a request can not be handed over to the adapter.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CanisterHttpSendError {
+    /// The adapter is busy and the request should be retried later.
+    Full(CanisterHttpRequest),
+    /// The adapter is gone and no more requests can be sent.
+    Disconnected(CanisterHttpRequest),
+}
+
+/// The client of the adapter that performs the actual HTTP requests.
+///
+/// Requests are sent and responses are received asynchronously: a response
+/// to a request that was sent earlier becomes available via `try_receive()`
+/// once the adapter is done with it.
+pub trait CanisterHttpAdapterClient: Send + Sync {
+    /// Hands over a request to the adapter without blocking.
+    fn send(&self, request: CanisterHttpRequest) -> Result<(), CanisterHttpSendError>;
+
+    /// Returns the next available response, if any, without blocking.
+    fn try_receive(&self) -> Option<CanisterHttpResponse>;
+}
+
+/// A CanisterHttpPayload error from which it is not possible to recover.
+#[derive(Debug, PartialEq, Eq)]
+pub enum InvalidCanisterHttpPayload {
+    /// The payload contains a response to a request that is not known at
+    /// the certified height.
+    UnknownRequest(CanisterHttpRequestId),
+    /// The payload contains a response to a request that was already
+    /// answered, either in this payload or in a past one.
+    DuplicateResponse(CanisterHttpRequestId),
+    /// The payload exceeds the maximal payload size.
+    PayloadTooBig {
+        expected: NumBytes,
+        received: NumBytes,
+    },
+    /// A share in the proof of a response is for a different response.
+    ContentHashMismatch(CanisterHttpRequestId),
+    /// The payload contains more than one share of the same node for the
+    /// same request.
+    DuplicateShare {
+        id: CanisterHttpRequestId,
+        signer: NodeId,
+    },
+    /// The proof of a response does not contain enough shares.
+    NotEnoughShares {
+        id: CanisterHttpRequestId,
+        expected: usize,
+        received: usize,
+    },
+    /// A share was signed at a different registry version than the one of
+    /// the validation context.
+    RegistryVersionMismatch {
+        expected: RegistryVersion,
+        received: RegistryVersion,
+    },
+    /// A share was signed by a node that is not a member of the subnet.
+    SignerNotInSubnet(NodeId),
+    /// The signature of a share is invalid.
+    InvalidSignature(CryptoError),
+}
+
+/// A CanisterHttpPayload error from which it may be possible to recover.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CanisterHttpTransientValidationError {
+    /// The state at the certified height is not available (yet).
+    StateUnavailable(Height),
+    /// The subnet membership at the given registry version is not available
+    /// (yet).
+    RegistryUnavailable(RegistryVersion),
+    /// The signature of a share could not be verified (yet).
+    CryptoError(CryptoError),
+}
+
+/// A CanisterHttpPayload error that results from payload validation.
+pub type CanisterHttpPayloadValidationError =
+    ValidationError<InvalidCanisterHttpPayload, CanisterHttpTransientValidationError>;
+
+pub trait CanisterHttpPayloadBuilder: Send + Sync {
+    /// Produces a `CanisterHttpPayload` of maximum byte size `byte_limit`
+    /// that is valid given a `ValidationContext` (certified height plus
+    /// registry version) and `past_payloads` (the `CanisterHttpPayloads`
+    /// from all blocks above the certified height, in descending block
+    /// height order).
+    fn get_canister_http_payload(
+        &self,
+        validation_context: &ValidationContext,
+        past_payloads: &[&CanisterHttpPayload],
+        byte_limit: NumBytes,
+    ) -> CanisterHttpPayload;
+
+    /// Checks whether the provided `CanisterHttpPayload` is valid given a
+    /// `ValidationContext` (certified height and registry version) and
+    /// `past_payloads` (the `CanisterHttpPayloads` from all blocks above the
+    /// certified height, in descending block height order).
+    ///
+    /// If valid, returns the payload's `CountBytes` byte size; else returns a
+    /// permanent or transient `ValidationError`.
+    fn validate_canister_http_payload(
+        &self,
+        payload: &CanisterHttpPayload,
+        validation_context: &ValidationContext,
+        past_payloads: &[&CanisterHttpPayload],
+    ) -> Result<NumBytes, CanisterHttpPayloadValidationError>;
+}
+
+/// A `CanisterHttpPayloadBuilder` that never includes any responses, for
+/// subnets where canister HTTP requests are not served.
+pub struct NoOpCanisterHttpPayloadBuilder {}
+
+impl CanisterHttpPayloadBuilder for NoOpCanisterHttpPayloadBuilder {
+    fn get_canister_http_payload(
+        &self,
+        _validation_context: &ValidationContext,
+        _past_payloads: &[&CanisterHttpPayload],
+        _byte_limit: NumBytes,
+    ) -> CanisterHttpPayload {
+        CanisterHttpPayload::default()
+    }
+
+    fn validate_canister_http_payload(
+        &self,
+        payload: &CanisterHttpPayload,
+        _validation_context: &ValidationContext,
+        _past_payloads: &[&CanisterHttpPayload],
+    ) -> Result<NumBytes, CanisterHttpPayloadValidationError> {
+        let first_id = payload
+            .responses
+            .first()
+            .map(|response| response.content.id)
+            .or_else(|| payload.shares.first().map(|share| share.content.id));
+        match first_id {
+            None => Ok(0.into()),
+            Some(id) => Err(ValidationError::Permanent(
+                InvalidCanisterHttpPayload::UnknownRequest(id),
+            )),
+        }
+    }
+}
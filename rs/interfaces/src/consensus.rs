@@ -1,5 +1,9 @@
 //! The consensus public interface.
 use crate::{
+    canister_http::{
+        CanisterHttpPayloadValidationError, CanisterHttpTransientValidationError,
+        InvalidCanisterHttpPayload,
+    },
     consensus_pool::{ChangeSet, ConsensusPool},
     ingress_manager::{
         IngressPayloadValidationError, IngressPermanentError, IngressTransientError,
@@ -68,6 +72,7 @@ pub enum PayloadPermanentError {
         received: NumBytes,
     },
     SelfValidatingPayloadValidationError(InvalidSelfValidatingPayload),
+    CanisterHttpPayloadValidationError(InvalidCanisterHttpPayload),
 }
 
 #[derive(Debug)]
@@ -76,6 +81,7 @@ pub enum PayloadTransientError {
     IngressPayloadValidationError(IngressTransientError),
     RegistryUnavailable,
     SelfValidatingPayloadValidationError(SelfValidatingTransientValidationError),
+    CanisterHttpPayloadValidationError(CanisterHttpTransientValidationError),
 }
 
 /// Payload validation error
@@ -107,3 +113,12 @@ impl From<SelfValidatingPayloadValidationError> for PayloadValidationError {
         )
     }
 }
+
+impl From<CanisterHttpPayloadValidationError> for PayloadValidationError {
+    fn from(err: CanisterHttpPayloadValidationError) -> Self {
+        err.map(
+            PayloadPermanentError::CanisterHttpPayloadValidationError,
+            PayloadTransientError::CanisterHttpPayloadValidationError,
+        )
+    }
+}
@@ -32,6 +32,7 @@ pub use sign::{Signable, SignableMock};
 
 pub use sign::canister_threshold_sig::*;
 
+use ic_types::canister_http::CanisterHttpResponseMetadata;
 use ic_types::consensus::certification::CertificationContent;
 use ic_types::consensus::dkg as consensus_dkg;
 use ic_types::consensus::{
@@ -49,6 +50,9 @@ pub trait Crypto:
     // Dealing
     + BasicSigner<consensus_dkg::DealingContent>
     + BasicSigVerifier<consensus_dkg::DealingContent>
+    // CanisterHttpResponseMetadata
+    + BasicSigner<CanisterHttpResponseMetadata>
+    + BasicSigVerifier<CanisterHttpResponseMetadata>
     // DKG
     + NiDkgAlgorithm
     // CertificationContent
@@ -104,6 +108,8 @@ impl<T> Crypto for T where
         + BasicSigVerifier<Block>
         + BasicSigner<consensus_dkg::DealingContent>
         + BasicSigVerifier<consensus_dkg::DealingContent>
+        + BasicSigner<CanisterHttpResponseMetadata>
+        + BasicSigVerifier<CanisterHttpResponseMetadata>
         + NiDkgAlgorithm
         + ThresholdSigner<CertificationContent>
         + ThresholdSigVerifier<CertificationContent>
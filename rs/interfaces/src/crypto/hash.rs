@@ -1,4 +1,5 @@
 use ic_types::artifact::StateSyncMessage;
+use ic_types::canister_http::CanisterHttpResponse;
 use ic_types::consensus::certification::CertificationMessage;
 use ic_types::consensus::dkg as consensus_dkg;
 use ic_types::consensus::{
@@ -69,6 +70,10 @@ const DOMAIN_ECDSA_VERIFIED_DEALING: &str = "ic-threshold-ecdsa-verified-dealing
 const DOMAIN_ECDSA_TRANSCRIPT: &str = "ic-idkg-transcript-domain";
 const DOMAIN_ECDSA_SIG_SHARE: &str = "ic-threshold-ecdsa-sig-share-domain";
 
+const DOMAIN_CANISTER_HTTP_RESPONSE: &str = "ic-canister-http-response-domain";
+pub(crate) const DOMAIN_CANISTER_HTTP_RESPONSE_METADATA: &str =
+    "ic-canister-http-response-metadata-domain";
+
 /// A cryptographically hashable type.
 pub trait CryptoHashable: CryptoHashDomain + Hash {}
 impl<T> CryptoHashable for T where T: CryptoHashDomain + Hash {}
@@ -153,6 +158,8 @@ mod private {
     impl CryptoHashDomainSeal for EcdsaTranscript {}
     impl CryptoHashDomainSeal for EcdsaSigShare {}
 
+    impl CryptoHashDomainSeal for CanisterHttpResponse {}
+
     impl CryptoHashDomainSeal for CryptoHashableTestDummy {}
 }
 
@@ -380,6 +387,12 @@ impl CryptoHashDomain for EcdsaSigShare {
     }
 }
 
+impl CryptoHashDomain for CanisterHttpResponse {
+    fn domain(&self) -> String {
+        DOMAIN_CANISTER_HTTP_RESPONSE.to_string()
+    }
+}
+
 impl CryptoHashDomain for CryptoHashableTestDummy {
     fn domain(&self) -> String {
         "test_struct_domain".to_string()
@@ -89,6 +89,7 @@ impl SubnetAvailableMemory {
 pub struct ExecutionParameters {
     pub instruction_limit: NumInstructions,
     pub canister_memory_limit: NumBytes,
+    pub max_stable_memory_size: NumBytes,
     pub subnet_available_memory: SubnetAvailableMemory,
     pub compute_allocation: ComputeAllocation,
     pub subnet_type: SubnetType,
@@ -111,6 +112,38 @@ pub struct ExecuteMessageResult<CanisterState> {
 
 pub type HypervisorResult<T> = Result<T, HypervisorError>;
 
+/// Decides what happens when a Wasm execution has consumed all instructions
+/// of its current slice.
+///
+/// With deterministic time slicing a long-running execution is split into
+/// slices of at most `slice_instruction_limit()` instructions. At the end of
+/// each slice the execution calls `out_of_instructions()`, which may block
+/// until the execution is allowed to continue in a later round.
+pub trait OutOfInstructionsHandler: Send + Sync {
+    /// Returns the maximum number of instructions that can be executed in a
+    /// single slice.
+    fn slice_instruction_limit(&self) -> NumInstructions;
+
+    /// Called at the end of a slice with the total number of instructions
+    /// that the execution has consumed so far. Returns `Ok(())` if the
+    /// execution may continue with a new slice and an error otherwise.
+    fn out_of_instructions(&self, instructions_executed: NumInstructions) -> HypervisorResult<()>;
+}
+
+/// The handler used by executions that are not sliced: the only slice covers
+/// the whole instruction limit, so running out of instructions is an error.
+pub struct DefaultOutOfInstructionsHandler;
+
+impl OutOfInstructionsHandler for DefaultOutOfInstructionsHandler {
+    fn slice_instruction_limit(&self) -> NumInstructions {
+        NumInstructions::from(u64::MAX)
+    }
+
+    fn out_of_instructions(&self, _instructions_executed: NumInstructions) -> HypervisorResult<()> {
+        Err(HypervisorError::InstructionLimitExceeded)
+    }
+}
+
 /// Interface for the component to filter out ingress messages that
 /// the canister is not willing to accept.
 // Since this service will be shared across many connections we must
@@ -514,9 +547,28 @@ pub trait SystemApi {
 
     fn ic0_time(&self) -> HypervisorResult<Time>;
 
+    /// Returns the current value of the performance counter of the given
+    /// type. The only supported type is `0`, which counts the instructions
+    /// executed in the current message so far, including the ones executed
+    /// in earlier slices of the message.
+    ///
+    /// `instruction_counter` is the current value of the instruction counter
+    /// that is maintained by the hypervisor.
+    fn ic0_performance_counter(
+        &self,
+        counter_type: u32,
+        instruction_counter: i64,
+    ) -> HypervisorResult<u64>;
+
     /// This system call is not part of the public spec and used by the
-    /// hypervisor, when execution runs out of instructions.
-    fn out_of_instructions(&self) -> HypervisorError;
+    /// hypervisor, when execution runs out of instructions in the current
+    /// slice. `instruction_counter` is the (non-positive) value of the
+    /// instruction counter at that point.
+    ///
+    /// Returns the new value of the instruction counter if the execution
+    /// continues with the next slice and an error if the execution has to
+    /// stop.
+    fn out_of_instructions(&mut self, instruction_counter: i64) -> HypervisorResult<i64>;
 
     /// This system call is not part of the public spec. It's called after a
     /// native `memory.grow` has been called to check whether there's enough
@@ -627,6 +679,11 @@ pub trait SystemApi {
     /// See: https://sdk.dfinity.org/docs/interface-spec/index.html#system-api-certified-data
     fn ic0_certified_data_set(&mut self, src: u32, size: u32, heap: &[u8]) -> HypervisorResult<()>;
 
+    /// Sets the global timer of the canister to the given time and returns
+    /// the previous value. A time of zero deactivates the timer and is also
+    /// returned if the timer was not active.
+    fn ic0_global_timer_set(&mut self, time: Time) -> HypervisorResult<Time>;
+
     /// If run in non-replicated execution (i.e. query),
     /// returns 1 if the data certificate is present, 0 otherwise.
     /// If run in replicated execution (i.e. an update call or a certified
@@ -709,6 +766,12 @@ pub trait Scheduler: Send {
     ///   many instructions is left which is used to update the limit for the
     ///   next `pulse` and if the above constraint is satisfied, we can start
     ///   the `pulse`. And so on.
+    ///
+    /// # Paused executions
+    ///
+    /// A long-running execution may be paused at the end of a round and
+    /// resumed in one of the following rounds. Paused executions are part of
+    /// the state, so they survive checkpoints and restarts.
     fn execute_round(
         &self,
         state: Self::State,
@@ -84,6 +84,15 @@ impl From<RequestOrIngress> for StopCanisterContext {
     }
 }
 
+impl From<RequestOrIngress> for CanisterInputMessage {
+    fn from(msg: RequestOrIngress) -> Self {
+        match msg {
+            RequestOrIngress::Request(msg) => CanisterInputMessage::Request(msg),
+            RequestOrIngress::Ingress(msg) => CanisterInputMessage::Ingress(msg),
+        }
+    }
+}
+
 impl TryFrom<CanisterInputMessage> for RequestOrIngress {
     type Error = ();
 
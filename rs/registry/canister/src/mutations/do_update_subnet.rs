@@ -310,6 +310,8 @@ mod tests {
             features: Some(SubnetFeatures {
                 ecdsa_signatures: false,
                 canister_sandboxing: false,
+                http_requests: false,
+                wasm_simd: false,
             }),
             ecdsa_config: Some(EcdsaConfig {
                 quadruples_to_create_in_advance: 10,
@@ -355,6 +357,8 @@ mod tests {
                     SubnetFeatures {
                         ecdsa_signatures: false,
                         canister_sandboxing: false,
+                        http_requests: false,
+                        wasm_simd: false,
                     }
                     .into()
                 ),
@@ -3,8 +3,9 @@ mod proto;
 use candid::Decode;
 use ic_base_types::{CanisterId, PrincipalId, SubnetId};
 use ic_ic00_types::{
-    CanisterIdRecord, InstallCodeArgs, Method as Ic00Method, Payload, ProvisionalTopUpCanisterArgs,
-    SetControllerArgs, UpdateSettingsArgs,
+    CanisterIdRecord, CanisterSnapshotArgs, FetchCanisterLogsRequest, InstallCodeArgs,
+    Method as Ic00Method, Payload, ProvisionalTopUpCanisterArgs, SetControllerArgs,
+    TakeCanisterSnapshotArgs, UpdateSettingsArgs,
 };
 use serde::{Deserialize, Serialize};
 use std::{collections::BTreeMap, convert::TryFrom, str::FromStr, sync::Arc};
@@ -37,7 +38,8 @@ pub fn resolve_destination(
         | Ok(Ic00Method::ProvisionalCreateCanisterWithCycles)
         | Ok(Ic00Method::GetMockECDSAPublicKey)
         | Ok(Ic00Method::SignWithMockECDSA)
-        | Ok(Ic00Method::SignWithECDSA) => Ok(own_subnet),
+        | Ok(Ic00Method::SignWithECDSA)
+        | Ok(Ic00Method::HttpRequest) => Ok(own_subnet),
         // This message needs to be routed to the NNS subnet.  We assume that
         // this message can only be sent by canisters on the NNS subnet hence
         // returning `own_subnet` here is fine.
@@ -73,13 +75,38 @@ pub fn resolve_destination(
         | Ok(Ic00Method::StopCanister)
         | Ok(Ic00Method::DeleteCanister)
         | Ok(Ic00Method::UninstallCode)
-        | Ok(Ic00Method::DepositCycles) => {
+        | Ok(Ic00Method::DepositCycles)
+        | Ok(Ic00Method::ListCanisterSnapshots) => {
             let args = Decode!(payload, CanisterIdRecord)?;
             let canister_id = args.get_canister_id();
             routing_table.route(canister_id.get()).ok_or_else(|| {
                 ResolveDestinationError::SubnetNotFound(canister_id, method.unwrap())
             })
         }
+        Ok(Ic00Method::TakeCanisterSnapshot) => {
+            let args = TakeCanisterSnapshotArgs::decode(payload)?;
+            let canister_id = args.get_canister_id();
+            routing_table.route(canister_id.get()).ok_or({
+                ResolveDestinationError::SubnetNotFound(
+                    canister_id,
+                    Ic00Method::TakeCanisterSnapshot,
+                )
+            })
+        }
+        Ok(Ic00Method::LoadCanisterSnapshot) | Ok(Ic00Method::DeleteCanisterSnapshot) => {
+            let args = CanisterSnapshotArgs::decode(payload)?;
+            let canister_id = args.get_canister_id();
+            routing_table.route(canister_id.get()).ok_or_else(|| {
+                ResolveDestinationError::SubnetNotFound(canister_id, method.unwrap())
+            })
+        }
+        Ok(Ic00Method::FetchCanisterLogs) => {
+            let args = FetchCanisterLogsRequest::decode(payload)?;
+            let canister_id = args.get_canister_id();
+            routing_table.route(canister_id.get()).ok_or({
+                ResolveDestinationError::SubnetNotFound(canister_id, Ic00Method::FetchCanisterLogs)
+            })
+        }
         Ok(Ic00Method::ProvisionalTopUpCanister) => {
             let args = ProvisionalTopUpCanisterArgs::decode(payload)?;
             let canister_id = args.get_canister_id();
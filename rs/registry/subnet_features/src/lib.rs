@@ -10,6 +10,14 @@ pub struct SubnetFeatures {
     /// This feature flag controls whether canister execution happens
     /// in sandboxed process or not. It is disabled by default.
     pub canister_sandboxing: bool,
+    /// This feature flag controls whether canisters of this subnet are
+    /// allowed to make HTTP requests to the outside world. It is disabled by
+    /// default.
+    pub http_requests: bool,
+    /// This feature flag controls whether canisters of this subnet may use
+    /// the deterministic subset of Wasm SIMD and bulk memory instructions. It
+    /// is disabled by default.
+    pub wasm_simd: bool,
 }
 
 impl From<SubnetFeatures> for pb::SubnetFeatures {
@@ -17,6 +25,8 @@ impl From<SubnetFeatures> for pb::SubnetFeatures {
         Self {
             ecdsa_signatures: features.ecdsa_signatures,
             canister_sandboxing: features.canister_sandboxing,
+            http_requests: features.http_requests,
+            wasm_simd: features.wasm_simd,
         }
     }
 }
@@ -26,6 +36,8 @@ impl From<pb::SubnetFeatures> for SubnetFeatures {
         Self {
             ecdsa_signatures: features.ecdsa_signatures,
             canister_sandboxing: features.canister_sandboxing,
+            http_requests: features.http_requests,
+            wasm_simd: features.wasm_simd,
         }
     }
 }
@@ -45,6 +57,8 @@ impl FromStr for SubnetFeatures {
             match feature {
                 "ecdsa_signatures" => features.ecdsa_signatures = true,
                 "canister_sandboxing" => features.canister_sandboxing = true,
+                "http_requests" => features.http_requests = true,
+                "wasm_simd" => features.wasm_simd = true,
                 _ => return Err(format!("Unknown feature {:?} in {:?}", feature, string)),
             }
         }
@@ -78,12 +92,17 @@ mod tests {
 
     #[test]
     fn test_all_can_be_set_true() {
-        let result = SubnetFeatures::from_str("ecdsa_signatures,canister_sandboxing").unwrap();
+        let result = SubnetFeatures::from_str(
+            "ecdsa_signatures,canister_sandboxing,http_requests,wasm_simd",
+        )
+        .unwrap();
         assert_eq!(
             result,
             SubnetFeatures {
                 ecdsa_signatures: true,
                 canister_sandboxing: true,
+                http_requests: true,
+                wasm_simd: true,
             }
         );
     }
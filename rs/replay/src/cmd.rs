@@ -35,6 +35,16 @@ pub struct CliArgs {
     #[clap(long)]
     /// The replay will stop at this height and make a checkpoint.
     pub replay_until_height: Option<u64>,
+
+    /// Print the ingress messages executed at every replayed height together
+    /// with their results.
+    #[clap(long)]
+    pub ingress_summary: bool,
+
+    /// Comma-separated list of heights. The replay prints the canonical state
+    /// diffs between consecutive heights of the list.
+    #[clap(long, use_delimiter = true)]
+    pub diff_heights: Vec<u64>,
 }
 
 #[derive(Clap)]
@@ -12,6 +12,10 @@
 //! state (after all past blocks have been executed). All of them are meant to
 //! help recover NNS subnet where the registry canister resides.
 //!
+//! To root-cause divergences offline, the replay can print the ingress
+//! messages executed at every height and the canonical state diffs between
+//! selected heights.
+//!
 //! Use `ic-replay --help` to find out more.
 use ic_artifact_pool::{
     certification_pool::CertificationPoolImpl,
@@ -75,6 +79,7 @@ use tempfile::TempDir;
 mod backup;
 pub mod cmd;
 pub mod ingress;
+mod trace;
 
 // Amount of time we are waiting for execution, after batches are delivered.
 const WAIT_DURATION: Duration = Duration::from_millis(200);
@@ -99,6 +104,7 @@ pub struct Player {
     // The target height until which the state will be replayed.
     // None means finalized height.
     replay_target_height: Option<u64>,
+    tracer: trace::Tracer,
 }
 
 impl Player {
@@ -343,6 +349,7 @@ impl Player {
             _log: log,
             tmp_dir: None,
             replay_target_height: None,
+            tracer: trace::Tracer::default(),
         }
     }
 
@@ -352,6 +359,21 @@ impl Player {
         self
     }
 
+    /// Print the ingress messages executed at every replayed height together
+    /// with their results.
+    pub fn with_ingress_summary(mut self, ingress_summary: bool) -> Self {
+        self.tracer.ingress_summary = ingress_summary;
+        self
+    }
+
+    /// Print the canonical state diffs between consecutive heights of
+    /// `diff_heights`. Only the heights replayed by this run and the height
+    /// the replay starts from can be diffed.
+    pub fn with_diff_heights(mut self, diff_heights: Vec<u64>) -> Self {
+        self.tracer.diff_heights = diff_heights.into_iter().map(Height::from).collect();
+        self
+    }
+
     /// Replay past finalized but un-executed blocks by delivering ingress
     /// messages for execution, and make a full checkpoint of the latest
     /// state when they all finish.
@@ -484,7 +506,29 @@ impl Player {
         replay_target_height: Option<Height>,
     ) -> Height {
         let expected_batch_height = message_routing.expected_batch_height();
-        let last_batch_height = loop {
+        let last_batch_height = if self.tracer.is_enabled() {
+            self.deliver_and_trace_batches(message_routing, pool, replay_target_height)
+        } else {
+            self.deliver_batches_up_to(message_routing, pool, replay_target_height)
+        };
+        println!(
+            "latest_batch_height = {}, batches = {}",
+            last_batch_height,
+            last_batch_height - expected_batch_height.decrement()
+        );
+        println!("Delivered batches up to the height {}", last_batch_height);
+        last_batch_height
+    }
+
+    /// Deliver finalized batches up to the target height (or the finalized
+    /// height) at once.
+    fn deliver_batches_up_to(
+        &self,
+        message_routing: &dyn MessageRouting,
+        pool: &PoolReader<'_>,
+        replay_target_height: Option<Height>,
+    ) -> Height {
+        loop {
             match deliver_batches(
                 message_routing,
                 pool,
@@ -502,13 +546,54 @@ impl Player {
                     unreachable!();
                 }
             }
-        };
-        println!(
-            "latest_batch_height = {}, batches = {}",
+        }
+    }
+
+    /// Deliver finalized batches up to the target height (or the finalized
+    /// height) one at a time, and trace the state after each of them before
+    /// the next one executes.
+    fn deliver_and_trace_batches(
+        &self,
+        message_routing: &dyn MessageRouting,
+        pool: &PoolReader<'_>,
+        replay_target_height: Option<Height>,
+    ) -> Height {
+        let finalized_height = pool.get_finalized_height();
+        let target_height = replay_target_height
+            .unwrap_or(finalized_height)
+            .min(finalized_height);
+
+        let mut last_batch_height = message_routing.expected_batch_height().decrement();
+        self.tracer.trace_tree(
             last_batch_height,
-            last_batch_height - expected_batch_height.decrement()
+            &self.state_manager.get_latest_state().take(),
         );
-        println!("Delivered batches up to the height {}", last_batch_height);
+        while last_batch_height < target_height {
+            let height = self.deliver_batches_up_to(
+                message_routing,
+                pool,
+                Some(last_batch_height.increment()),
+            );
+            if height == last_batch_height {
+                // The pool has no batch to deliver at the next height.
+                break;
+            }
+            while self.state_manager.latest_state_height() < height {
+                std::thread::sleep(WAIT_DURATION);
+            }
+            let before = self
+                .state_manager
+                .get_state_at(last_batch_height)
+                .ok()
+                .map(|state| state.take());
+            let after = self
+                .state_manager
+                .get_state_at(height)
+                .unwrap_or_else(|err| panic!("State at height {} is missing: {:?}", height, err))
+                .take();
+            self.tracer.trace_height(height, before.as_deref(), &after);
+            last_batch_height = height;
+        }
         last_batch_height
     }
 
@@ -24,6 +24,8 @@ fn main() {
 
         let subcmd = &args.subcmd;
         let target_height = args.replay_until_height;
+        let ingress_summary = args.ingress_summary;
+        let diff_heights = args.diff_heights.clone();
         if let Some(h) = target_height {
             let question = format!("The checkpoint created at height {} ", h)
                 + "cannot be used for deterministic state computation if it is not a CUP height.\n"
@@ -45,7 +47,9 @@ fn main() {
                     cmd.start_height,
                 )
                 .await
-                .with_replay_target_height(target_height);
+                .with_replay_target_height(target_height)
+                .with_ingress_summary(ingress_summary)
+                .with_diff_heights(diff_heights.clone());
                 player.restore(cmd.start_height + 1);
             });
             return;
@@ -91,7 +95,11 @@ fn main() {
                     return;
                 },
                 (_, target_height) => {
-                    Player::new(cfg, subnet_id).await.with_replay_target_height(target_height)
+                    Player::new(cfg, subnet_id)
+                        .await
+                        .with_replay_target_height(target_height)
+                        .with_ingress_summary(ingress_summary)
+                        .with_diff_heights(diff_heights)
                 },
             };
             player.replay(extra);
@@ -0,0 +1,125 @@
+//! Reports what the replayed heights did: the ingress messages executed at
+//! every height and the differences between the canonical state trees of
+//! selected heights.
+use ic_crypto_tree_hash::HashTree;
+use ic_replicated_state::ReplicatedState;
+use ic_state_manager::{
+    tree_diff::{diff, PrettyPrintedChanges},
+    tree_hash::hash_state,
+};
+use ic_types::{
+    ingress::{IngressStatus, WasmResult},
+    Height,
+};
+use std::cell::RefCell;
+
+/// Options that make the replay report what every height did. Tracing
+/// requires executing the batches one at a time.
+#[derive(Default)]
+pub(crate) struct Tracer {
+    /// Whether to print the ingress messages executed at every height.
+    pub(crate) ingress_summary: bool,
+    /// The heights between which to print the canonical tree diffs.
+    pub(crate) diff_heights: Vec<Height>,
+    /// The canonical tree of the last height in `diff_heights` that was
+    /// traced.
+    last_tree: RefCell<Option<(Height, HashTree)>>,
+}
+
+impl Tracer {
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.ingress_summary || !self.diff_heights.is_empty()
+    }
+
+    /// Reports what the batch at `height` did, given the states before and
+    /// after its execution.
+    pub(crate) fn trace_height(
+        &self,
+        height: Height,
+        before: Option<&ReplicatedState>,
+        after: &ReplicatedState,
+    ) {
+        if self.ingress_summary {
+            match before {
+                Some(before) => print_ingress_summary(height, before, after),
+                None => println!(
+                    "Height {}: the previous state is not available, skipping the ingress summary",
+                    height
+                ),
+            }
+        }
+        self.trace_tree(height, after);
+    }
+
+    /// Records the canonical tree of `state` if `height` is one of the
+    /// selected heights and prints its difference from the tree of the
+    /// previous selected height.
+    pub(crate) fn trace_tree(&self, height: Height, state: &ReplicatedState) {
+        if !self.diff_heights.contains(&height) {
+            return;
+        }
+        let tree = hash_state(state);
+        let mut last_tree = self.last_tree.borrow_mut();
+        if let Some((last_height, last)) = last_tree.as_ref() {
+            let changes = diff(last, &tree);
+            println!(
+                "Canonical state diff between heights {} and {}:",
+                last_height, height
+            );
+            if changes.is_empty() {
+                println!("(no changes)");
+            } else {
+                print!("{}", PrettyPrintedChanges(&changes));
+            }
+        }
+        *last_tree = Some((height, tree));
+    }
+}
+
+/// Prints the ingress messages that completed or failed at `height`, i.e. the
+/// ones whose status changed to one of these between `before` and `after`.
+fn print_ingress_summary(height: Height, before: &ReplicatedState, after: &ReplicatedState) {
+    let executed: Vec<_> = after
+        .metadata
+        .ingress_history
+        .statuses()
+        .filter(|(id, status)| {
+            matches!(
+                status,
+                IngressStatus::Completed { .. } | IngressStatus::Failed { .. }
+            ) && before.get_ingress_status(id) != **status
+        })
+        .collect();
+
+    println!(
+        "Height {}: {} ingress message(s) executed",
+        height,
+        executed.len()
+    );
+    for (id, status) in executed {
+        match status {
+            IngressStatus::Completed {
+                receiver,
+                user_id,
+                result,
+                ..
+            } => {
+                let result = match result {
+                    WasmResult::Reply(bytes) => format!("reply {}", hex::encode(bytes)),
+                    WasmResult::Reject(message) => format!("reject \"{}\"", message),
+                };
+                println!("  {} from {} to {}: {}", id, user_id, receiver, result);
+            }
+            IngressStatus::Failed {
+                receiver,
+                user_id,
+                error,
+                ..
+            } => println!(
+                "  {} from {} to {}: failed with {}",
+                id, user_id, receiver, error
+            ),
+            _ => unreachable!(),
+        }
+    }
+}
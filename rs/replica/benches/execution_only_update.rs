@@ -16,7 +16,10 @@ use ic_test_utilities::{
     types::messages::SignedIngressBuilder,
 };
 use ic_types::{
-    batch::{Batch, BatchPayload, IngressPayload, SelfValidatingPayload, XNetPayload},
+    batch::{
+        Batch, BatchPayload, CanisterHttpPayload, IngressPayload, SelfValidatingPayload,
+        XNetPayload,
+    },
     ic00,
     ic00::Payload,
     ingress::{IngressStatus, WasmResult},
@@ -117,6 +120,7 @@ fn build_batch(message_routing: &dyn MessageRouting, msgs: Vec<SignedIngress>) -
                 stream_slices: Default::default(),
             },
             self_validating: SelfValidatingPayload::default(),
+            canister_http: CanisterHttpPayload::default(),
         },
         randomness: Randomness::from([0; 32]),
         registry_version: RegistryVersion::from(1),
@@ -1,4 +1,5 @@
 use ic_config::{artifact_pool::ArtifactPoolConfig, subnet_config::SubnetConfig, Config};
+use ic_consensus::canister_http::{CanisterHttpPayloadBuilderImpl, StubCanisterHttpAdapterClient};
 use ic_consensus::certification::VerifierImpl;
 use ic_crypto::CryptoComponent;
 use ic_cycles_account_manager::CyclesAccountManager;
@@ -143,6 +144,19 @@ pub fn construct_ic_stack(
     let self_validating_payload_builder = NoOpSelfValidatingPayloadBuilder {};
     let self_validating_payload_builder = Arc::new(self_validating_payload_builder);
 
+    // No HTTP adapter is available yet, so all canister HTTP requests are
+    // rejected by consensus.
+    let canister_http_payload_builder = CanisterHttpPayloadBuilderImpl::new(
+        Arc::new(StubCanisterHttpAdapterClient::reject_all()),
+        Arc::clone(&state_manager) as Arc<_>,
+        Arc::clone(&crypto) as Arc<_>,
+        Arc::clone(&registry) as Arc<_>,
+        node_id,
+        subnet_id,
+        replica_logger.clone(),
+    );
+    let canister_http_payload_builder = Arc::new(canister_http_payload_builder);
+
     let artifact_pool_config = ArtifactPoolConfig::from(config.artifact_pool);
 
     // Determine the correct catch-up package.
@@ -229,6 +243,7 @@ pub fn construct_ic_stack(
         P2PStateSyncClient::Client(Arc::clone(&state_manager) as Arc<_>),
         xnet_payload_builder as Arc<_>,
         self_validating_payload_builder as Arc<_>,
+        canister_http_payload_builder as Arc<_>,
         message_router as Arc<_>,
         // TODO(SCL-213)
         Arc::clone(&crypto) as Arc<_>,
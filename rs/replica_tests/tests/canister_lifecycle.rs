@@ -694,7 +694,11 @@ fn can_get_canister_information() {
                 num_cycles.get(),
                 ComputeAllocation::default().as_percent(),
                 None,
-                2592000
+                2592000,
+                0,
+                ic_config::execution_environment::Config::default()
+                    .default_reserved_balance_limit
+                    .get(),
             )
         );
 
@@ -744,7 +748,11 @@ fn can_get_canister_information() {
                     num_cycles.get(),
                     ComputeAllocation::default().as_percent(),
                     None,
-                    2592000
+                    2592000,
+                    0,
+                    ic_config::execution_environment::Config::default()
+                        .default_reserved_balance_limit
+                        .get(),
                 ),
                 CanisterStatusResultV2::decode(&res).unwrap(),
                 2 * BALANCE_EPSILON,
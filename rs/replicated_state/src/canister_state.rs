@@ -145,6 +145,17 @@ impl CanisterState {
         self.system_state.has_input()
     }
 
+    /// See `SystemState::has_paused_execution` for documentation.
+    pub fn has_paused_execution(&self) -> bool {
+        self.system_state.has_paused_execution()
+    }
+
+    /// Returns true if the canister has a task in its execution queue or a
+    /// message in its input queues, false otherwise.
+    pub fn has_input_or_task(&self) -> bool {
+        !self.system_state.task_queue.is_empty() || self.has_input()
+    }
+
     /// Returns true if there is at least one message in the canister's output
     /// queues, false otherwise.
     pub fn has_output(&self) -> bool {
@@ -222,12 +233,14 @@ impl CanisterState {
         }
     }
 
-    /// The amount of memory currently being used by the canister.
+    /// The amount of memory currently being used by the canister, including
+    /// the memory taken by its snapshots.
     pub fn memory_usage(&self, own_subnet_type: SubnetType) -> NumBytes {
         self.execution_state
             .as_ref()
             .map_or(NumBytes::from(0), |es| es.memory_usage())
             + self.system_state.memory_usage(own_subnet_type)
+            + self.system_state.snapshots.memory_usage()
     }
 
     /// Hack to get the dashboard templating working.
@@ -270,6 +283,16 @@ impl CanisterState {
         }
     }
 
+    /// Returns true if the canister exports the `canister_global_timer`
+    /// system method.
+    pub fn exports_global_timer_method(&self) -> bool {
+        match &self.execution_state {
+            Some(execution_state) => execution_state
+                .exports_method(&WasmMethod::System(SystemMethod::CanisterGlobalTimer)),
+            None => false,
+        }
+    }
+
     /// Returns true if the canister contains an exported query method with the
     /// name provided, false otherwise.
     pub fn exports_query_method(&self, method_name: String) -> bool {
@@ -281,6 +304,17 @@ impl CanisterState {
         }
     }
 
+    /// Returns true if the canister contains an exported composite query
+    /// method with the name provided, false otherwise.
+    pub fn exports_composite_query_method(&self, method_name: String) -> bool {
+        match &self.execution_state {
+            Some(execution_state) => {
+                execution_state.exports_method(&WasmMethod::CompositeQuery(method_name))
+            }
+            None => false,
+        }
+    }
+
     /// Returns the number of global variables in the Wasm module.
     pub fn num_wasm_globals(&self) -> usize {
         match &self.execution_state {
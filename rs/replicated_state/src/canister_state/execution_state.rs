@@ -200,6 +200,18 @@ impl Memory {
     pub fn new(page_map: PageMap, size: NumWasmPages) -> Self {
         Memory { page_map, size }
     }
+
+    /// Returns a copy of this memory that does not depend on any checkpoint
+    /// file: all pages are kept in the page delta of the copy, so persisting
+    /// the delta writes out the complete contents of the memory.
+    pub fn detached_copy(&self) -> Self {
+        let mut page_map = PageMap::default();
+        page_map.update(&self.page_map.host_pages_iter().collect::<Vec<_>>());
+        Memory {
+            page_map,
+            size: self.size,
+        }
+    }
 }
 
 impl Default for Memory {
@@ -468,6 +468,32 @@ impl CanisterQueues {
         debug_assert!(self.stats_ok());
     }
 
+    /// Returns the requests that were pushed into the output queues of `self`
+    /// after it was cloned from `snapshot`, in the order they were pushed
+    /// into each output queue.
+    ///
+    /// Assumes that no output messages were popped from `self` since the
+    /// snapshot was taken, i.e. that `self` was only modified by executing
+    /// messages.
+    pub fn output_requests_pushed_since(&self, snapshot: &CanisterQueues) -> Vec<Request> {
+        self.output_queues
+            .iter()
+            .flat_map(|(receiver, queue)| {
+                let num_old_messages = snapshot
+                    .output_queues
+                    .get(receiver)
+                    .map_or(0, OutputQueue::num_messages);
+                queue
+                    .iter()
+                    .skip(num_old_messages)
+                    .filter_map(|msg| match msg {
+                        RequestOrResponse::Request(request) => Some(request.clone()),
+                        RequestOrResponse::Response(_) => None,
+                    })
+            })
+            .collect()
+    }
+
     /// Returns a reference to the message at the head of the respective output
     /// queue, if any.
     pub(super) fn peek_output(&self, canister_id: &CanisterId) -> Option<Arc<RequestOrResponse>> {
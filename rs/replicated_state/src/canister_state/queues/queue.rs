@@ -118,6 +118,11 @@ impl<T: std::clone::Clone + CountBytes> QueueWithReservation<T> {
         self.num_slots_reserved
     }
 
+    /// Returns an iterator over the items in the queue, from head to tail.
+    fn iter(&self) -> impl Iterator<Item = &T> {
+        self.queue.iter().map(|msg| msg.as_ref())
+    }
+
     /// Calculates the sum of the given stat across all enqueued messages.
     ///
     /// Time complexity: O(num_messages).
@@ -327,6 +332,11 @@ impl OutputQueue {
         self.queue.num_messages()
     }
 
+    /// Returns an iterator over the messages in the queue, from head to tail.
+    pub(super) fn iter(&self) -> impl Iterator<Item = &RequestOrResponse> {
+        self.queue.iter()
+    }
+
     /// Returns the number of reserved slots in the queue.
     pub(super) fn reserved_slots(&self) -> usize {
         self.queue.reserved_slots()
@@ -11,7 +11,7 @@ use ic_test_utilities::{
         messages::{IngressBuilder, RequestBuilder, ResponseBuilder},
     },
 };
-use ic_types::time::current_time_and_expiry_time;
+use ic_types::{time::current_time_and_expiry_time, Cycles};
 use proptest::prelude::*;
 use std::convert::TryInto;
 
@@ -35,6 +35,33 @@ fn cannot_push_output_response_without_input_request() {
     queues.push_output_response(ResponseBuilder::default().respondent(this).build());
 }
 
+#[test]
+/// Only the requests pushed after the snapshot was taken are returned.
+fn output_requests_pushed_since_snapshot() {
+    let this = canister_test_id(13);
+    let other = canister_test_id(14);
+    let request = |receiver: CanisterId, payment: u64| {
+        RequestBuilder::default()
+            .sender(this)
+            .receiver(receiver)
+            .payment(Cycles::from(payment))
+            .build()
+    };
+    let mut queues = CanisterQueues::default();
+    queues.push_output_request(request(other, 1)).unwrap();
+    let snapshot = queues.clone();
+
+    queues.push_output_request(request(other, 2)).unwrap();
+    queues.push_output_request(request(this, 3)).unwrap();
+    queues.push_output_request(request(other, 4)).unwrap();
+
+    assert_eq!(
+        queues.output_requests_pushed_since(&snapshot),
+        vec![request(this, 3), request(other, 2), request(other, 4)]
+    );
+    assert!(snapshot.output_requests_pushed_since(&snapshot).is_empty());
+}
+
 #[test]
 fn enqueuing_unexpected_response_does_not_panic() {
     let other = canister_test_id(14);
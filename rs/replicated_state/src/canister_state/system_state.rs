@@ -1,12 +1,20 @@
 mod call_context_manager;
+mod canister_log;
+mod canister_snapshots;
 
 pub use super::queues::memory_required_to_push_request;
 use super::{queues::can_push, ENFORCE_MESSAGE_MEMORY_USAGE};
 pub use crate::canister_state::queues::CanisterOutputQueuesIterator;
 use crate::{CanisterQueues, InputQueueType, StateError};
 pub use call_context_manager::{CallContext, CallContextAction, CallContextManager, CallOrigin};
+pub use canister_log::{
+    CanisterLog, CanisterLogRecord, LogVisibility, MAX_CANISTER_LOG_SIZE_BYTES,
+};
+pub use canister_snapshots::{
+    CanisterSnapshot, CanisterSnapshots, SnapshotId, MAX_SNAPSHOTS_PER_CANISTER,
+};
 use ic_base_types::NumSeconds;
-use ic_interfaces::messages::CanisterInputMessage;
+use ic_interfaces::messages::{CanisterInputMessage, RequestOrIngress};
 use ic_protobuf::{
     proxy::{try_from_option_field, ProxyDecodeError},
     state::canister_state_bits::v1 as pb,
@@ -15,14 +23,17 @@ use ic_registry_subnet_type::SubnetType;
 use ic_types::{
     messages::{Ingress, Request, RequestOrResponse, Response, StopCanisterContext},
     nominal_cycles::NominalCycles,
-    CanisterId, Cycles, MemoryAllocation, NumBytes, PrincipalId, QueueIndex,
+    CanisterId, Cycles, MemoryAllocation, NumBytes, NumInstructions, PrincipalId, QueueIndex, Time,
 };
 use lazy_static::lazy_static;
 use maplit::btreeset;
 use serde::{Deserialize, Serialize};
 use std::convert::{TryFrom, TryInto};
 use std::str::FromStr;
-use std::{collections::BTreeSet, sync::Arc};
+use std::{
+    collections::{BTreeSet, VecDeque},
+    sync::Arc,
+};
 
 lazy_static! {
     static ref DEFAULT_PRINCIPAL_MULTIPLE_CONTROLLERS: PrincipalId =
@@ -87,6 +98,34 @@ pub struct SystemState {
     ///     2. executing the operation and return `cycles_spent`
     ///     3. reimburse the canister with `cycles_reserved` - `cycles_spent`
     pub cycles_balance: Cycles,
+
+    /// Queue of executions that have to be completed before the canister
+    /// executes any new message. Paused executions are always at the front.
+    pub task_queue: VecDeque<ExecutionTask>,
+
+    /// The most recent debug prints and trap messages of the canister.
+    pub canister_log: CanisterLog,
+
+    /// Determines who is allowed to fetch `canister_log`.
+    pub log_visibility: LogVisibility,
+
+    /// Snapshots of the canister taken via the management canister. Their
+    /// memory is accounted against the memory allocation of the canister.
+    pub snapshots: CanisterSnapshots,
+
+    /// The timer set by the canister via `ic0.global_timer_set`. It is kept
+    /// across upgrades, but cleared when the code of the canister is
+    /// (re)installed or uninstalled.
+    pub global_timer: CanisterTimer,
+
+    /// Cycles that were set aside to pay for the memory and compute
+    /// allocations of the canister. Periodic allocation charges are taken
+    /// from here before touching `cycles_balance`.
+    pub reserved_balance: Cycles,
+
+    /// The maximum amount of cycles that can be set aside in
+    /// `reserved_balance`. If not set, a subnet-wide default applies.
+    pub reserved_balance_limit: Option<Cycles>,
 }
 
 /// A wrapper around the different canister statuses.
@@ -170,6 +209,231 @@ impl TryFrom<pb::canister_state_bits::CanisterStatus> for CanisterStatus {
     }
 }
 
+/// The global timer of a canister.
+///
+/// Once the time of the subnet reaches the deadline of an active timer, the
+/// timer is deactivated and the `canister_global_timer` method of the canister
+/// is executed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CanisterTimer {
+    /// The timer is not set.
+    Inactive,
+    /// The timer expires at the given time.
+    Active(Time),
+}
+
+impl Default for CanisterTimer {
+    fn default() -> Self {
+        Self::Inactive
+    }
+}
+
+impl CanisterTimer {
+    /// Converts the value passed to `ic0.global_timer_set` into a timer: `0`
+    /// deactivates the timer, any other value is the deadline in nanoseconds
+    /// since the Unix epoch.
+    pub fn from_nanos_since_unix_epoch(nanos: u64) -> Self {
+        match nanos {
+            0 => Self::Inactive,
+            nanos => Self::Active(Time::from_nanos_since_unix_epoch(nanos)),
+        }
+    }
+
+    /// The inverse of `from_nanos_since_unix_epoch()`.
+    pub fn to_nanos_since_unix_epoch(self) -> u64 {
+        match self {
+            Self::Inactive => 0,
+            Self::Active(deadline) => deadline.as_nanos_since_unix_epoch(),
+        }
+    }
+
+    /// Returns true if the timer is active and its deadline is not after
+    /// `now`.
+    pub fn has_reached_deadline(&self, now: Time) -> bool {
+        match self {
+            Self::Inactive => false,
+            Self::Active(deadline) => *deadline <= now,
+        }
+    }
+}
+
+/// The persistent part of an execution that ran out of instructions in its
+/// current slice and is waiting to be resumed in a later round.
+///
+/// The Wasm stack of the execution only lives in the memory of the execution
+/// environment. If it is lost, e.g. because the replica restarted from a
+/// checkpoint, the execution is started again from the initial state
+/// described here and runs the completed slices back to back. Since slices
+/// end only when the instruction counter reaches the slice limit, the
+/// execution is paused at exactly the same point as before.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PausedTask {
+    /// The message that is being executed.
+    pub message: RequestOrIngress,
+
+    /// The batch time of the round in which the execution started.
+    pub time: Time,
+
+    /// The cycles balance of the canister when the execution started, i.e.
+    /// after the cycles for the execution were withdrawn.
+    pub initial_cycles_balance: Cycles,
+
+    /// The subnet memory that was available to the execution when it started.
+    pub subnet_available_memory: i64,
+
+    /// The instruction limits of the slices that have completed so far.
+    pub slice_instruction_limits: Vec<NumInstructions>,
+
+    /// The number of instructions executed in the completed slices.
+    pub instructions_executed: NumInstructions,
+
+    /// Whether the `wasm_simd` subnet feature was enabled when the execution
+    /// started. Only `install_code` executions depend on it.
+    pub wasm_simd: bool,
+}
+
+/// A task in the execution queue of a canister. Tasks take precedence over
+/// the messages in the input queues: as long as the queue is not empty, the
+/// scheduler executes the task at its front instead of a new message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExecutionTask {
+    /// An update message execution that ran out of instructions in its
+    /// current slice and is waiting to be resumed in a later round.
+    PausedExecution(PausedTask),
+
+    /// Like `PausedExecution`, but for an `install_code` message.
+    PausedInstallCode(PausedTask),
+
+    /// A paused update message execution that was aborted, e.g. because a
+    /// subnet message modified the canister. The message is executed again
+    /// from scratch.
+    AbortedExecution(RequestOrIngress),
+
+    /// Like `AbortedExecution`, but for an `install_code` message.
+    AbortedInstallCode(RequestOrIngress),
+}
+
+impl ExecutionTask {
+    /// Returns true if the task refers to an execution that is paused.
+    pub fn is_paused(&self) -> bool {
+        match self {
+            ExecutionTask::PausedExecution(_) | ExecutionTask::PausedInstallCode(_) => true,
+            ExecutionTask::AbortedExecution(_) | ExecutionTask::AbortedInstallCode(_) => false,
+        }
+    }
+
+    /// Returns true if the task refers to an `install_code` message.
+    pub fn is_install_code(&self) -> bool {
+        match self {
+            ExecutionTask::PausedInstallCode(_) | ExecutionTask::AbortedInstallCode(_) => true,
+            ExecutionTask::PausedExecution(_) | ExecutionTask::AbortedExecution(_) => false,
+        }
+    }
+}
+
+impl From<&RequestOrIngress> for pb::execution_task::AbortedExecution {
+    fn from(item: &RequestOrIngress) -> Self {
+        use pb::execution_task::aborted_execution::Input;
+        let input = match item {
+            RequestOrIngress::Request(request) => Input::Request(request.into()),
+            RequestOrIngress::Ingress(ingress) => Input::Ingress(ingress.into()),
+        };
+        Self { input: Some(input) }
+    }
+}
+
+impl TryFrom<pb::execution_task::AbortedExecution> for RequestOrIngress {
+    type Error = ProxyDecodeError;
+    fn try_from(value: pb::execution_task::AbortedExecution) -> Result<Self, Self::Error> {
+        use pb::execution_task::aborted_execution::Input;
+        match value.input {
+            Some(Input::Request(request)) => Ok(RequestOrIngress::Request(request.try_into()?)),
+            Some(Input::Ingress(ingress)) => Ok(RequestOrIngress::Ingress(ingress.try_into()?)),
+            None => Err(ProxyDecodeError::MissingField(
+                "ExecutionTask::AbortedExecution::input",
+            )),
+        }
+    }
+}
+
+impl From<&PausedTask> for pb::execution_task::PausedExecution {
+    fn from(item: &PausedTask) -> Self {
+        Self {
+            message: Some((&item.message).into()),
+            time_nanos: item.time.as_nanos_since_unix_epoch(),
+            initial_cycles_balance: Some(item.initial_cycles_balance.into()),
+            subnet_available_memory: item.subnet_available_memory,
+            slice_instruction_limits: item
+                .slice_instruction_limits
+                .iter()
+                .map(|limit| limit.get())
+                .collect(),
+            instructions_executed: item.instructions_executed.get(),
+            wasm_simd: item.wasm_simd,
+        }
+    }
+}
+
+impl TryFrom<pb::execution_task::PausedExecution> for PausedTask {
+    type Error = ProxyDecodeError;
+    fn try_from(value: pb::execution_task::PausedExecution) -> Result<Self, Self::Error> {
+        Ok(Self {
+            message: try_from_option_field(
+                value.message,
+                "ExecutionTask::PausedExecution::message",
+            )?,
+            time: Time::from_nanos_since_unix_epoch(value.time_nanos),
+            initial_cycles_balance: try_from_option_field(
+                value.initial_cycles_balance,
+                "ExecutionTask::PausedExecution::initial_cycles_balance",
+            )?,
+            subnet_available_memory: value.subnet_available_memory,
+            slice_instruction_limits: value
+                .slice_instruction_limits
+                .into_iter()
+                .map(NumInstructions::from)
+                .collect(),
+            instructions_executed: NumInstructions::from(value.instructions_executed),
+            wasm_simd: value.wasm_simd,
+        })
+    }
+}
+
+impl From<&ExecutionTask> for pb::ExecutionTask {
+    fn from(item: &ExecutionTask) -> Self {
+        use pb::execution_task::Task;
+        let task = match item {
+            ExecutionTask::PausedExecution(task) => Task::PausedExecution(task.into()),
+            ExecutionTask::PausedInstallCode(task) => Task::PausedInstallCode(task.into()),
+            ExecutionTask::AbortedExecution(msg) => Task::AbortedExecution(msg.into()),
+            ExecutionTask::AbortedInstallCode(msg) => Task::AbortedInstallCode(msg.into()),
+        };
+        Self { task: Some(task) }
+    }
+}
+
+impl TryFrom<pb::ExecutionTask> for ExecutionTask {
+    type Error = ProxyDecodeError;
+    fn try_from(value: pb::ExecutionTask) -> Result<Self, Self::Error> {
+        use pb::execution_task::Task;
+        match value.task {
+            Some(Task::PausedExecution(task)) => {
+                Ok(ExecutionTask::PausedExecution(task.try_into()?))
+            }
+            Some(Task::PausedInstallCode(task)) => {
+                Ok(ExecutionTask::PausedInstallCode(task.try_into()?))
+            }
+            Some(Task::AbortedExecution(msg)) => {
+                Ok(ExecutionTask::AbortedExecution(msg.try_into()?))
+            }
+            Some(Task::AbortedInstallCode(msg)) => {
+                Ok(ExecutionTask::AbortedInstallCode(msg.try_into()?))
+            }
+            None => Err(ProxyDecodeError::MissingField("ExecutionTask::task")),
+        }
+    }
+}
+
 impl SystemState {
     pub fn new_running(
         canister_id: CanisterId,
@@ -236,6 +500,13 @@ impl SystemState {
             status,
             certified_data: Default::default(),
             canister_metrics: CanisterMetrics::default(),
+            task_queue: VecDeque::new(),
+            canister_log: CanisterLog::default(),
+            log_visibility: LogVisibility::default(),
+            snapshots: CanisterSnapshots::default(),
+            global_timer: CanisterTimer::default(),
+            reserved_balance: Cycles::zero(),
+            reserved_balance_limit: None,
         }
     }
 
@@ -266,6 +537,13 @@ impl SystemState {
         certified_data: Vec<u8>,
         canister_metrics: CanisterMetrics,
         cycles_balance: Cycles,
+        task_queue: VecDeque<ExecutionTask>,
+        canister_log: CanisterLog,
+        log_visibility: LogVisibility,
+        snapshots: CanisterSnapshots,
+        global_timer: CanisterTimer,
+        reserved_balance: Cycles,
+        reserved_balance_limit: Option<Cycles>,
     ) -> Self {
         Self {
             controllers,
@@ -277,6 +555,13 @@ impl SystemState {
             certified_data,
             canister_metrics,
             cycles_balance,
+            task_queue,
+            canister_log,
+            log_visibility,
+            snapshots,
+            global_timer,
+            reserved_balance,
+            reserved_balance_limit,
         }
     }
 
@@ -378,6 +663,14 @@ impl SystemState {
         self.queues.has_input()
     }
 
+    /// Returns true if the canister has an execution that is paused, false
+    /// otherwise.
+    pub fn has_paused_execution(&self) -> bool {
+        self.task_queue
+            .front()
+            .map_or(false, ExecutionTask::is_paused)
+    }
+
     /// Pushes a `RequestOrResponse` into the induction pool.
     ///
     /// If the message is a `Request`, reserves a slot in the corresponding
@@ -480,6 +773,13 @@ impl SystemState {
         &self.queues
     }
 
+    /// Removes all messages from the canister queues. Used to start a sliced
+    /// execution from a state that does not depend on the messages that are
+    /// inducted or routed while the execution is paused.
+    pub fn clear_queues(&mut self) {
+        self.queues = CanisterQueues::default();
+    }
+
     /// Returns a boolean whether the system state is ready to be `Stopped`.
     /// Only relevant for a `Stopping` system state.
     pub fn ready_to_stop(&self) -> bool {
@@ -491,6 +791,7 @@ impl SystemState {
             } => {
                 call_context_manager.callbacks().is_empty()
                     && call_context_manager.call_contexts().is_empty()
+                    && self.task_queue.is_empty()
             }
             CanisterStatus::Stopped => true,
         }
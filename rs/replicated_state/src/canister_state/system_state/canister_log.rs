@@ -0,0 +1,165 @@
+#[cfg(test)]
+mod tests;
+
+use ic_protobuf::state::canister_state_bits::v1 as pb;
+use std::collections::VecDeque;
+
+/// The upper bound on the total size of the records kept in the log of a
+/// single canister. Once the bound is reached, the oldest records are evicted
+/// to make room for new ones.
+pub const MAX_CANISTER_LOG_SIZE_BYTES: usize = 4 * 1024;
+
+/// The number of bytes a record occupies in the log in addition to its
+/// content: the index and the timestamp.
+const RECORD_OVERHEAD_BYTES: usize = 2 * std::mem::size_of::<u64>();
+
+/// Determines who is allowed to fetch the log of a canister.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogVisibility {
+    /// Only the controllers of the canister.
+    Controllers,
+    /// Anyone.
+    Public,
+}
+
+impl Default for LogVisibility {
+    fn default() -> Self {
+        LogVisibility::Controllers
+    }
+}
+
+/// A single entry of a canister log: either a message printed by the canister
+/// via `ic0.debug_print` or the message of a trap.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CanisterLogRecord {
+    /// The position of the record in the log. Indices are assigned in
+    /// increasing order and are never reused, even after eviction.
+    pub idx: u64,
+    /// The time of the execution that produced the record.
+    pub timestamp_nanos: u64,
+    pub content: Vec<u8>,
+}
+
+impl CanisterLogRecord {
+    fn size_bytes(&self) -> usize {
+        RECORD_OVERHEAD_BYTES + self.content.len()
+    }
+}
+
+/// A bounded buffer of the most recent log records of a canister.
+///
+/// The log is part of the replicated state, so records must only be added
+/// deterministically during message execution.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CanisterLog {
+    next_idx: u64,
+    records: VecDeque<CanisterLogRecord>,
+    size_bytes: usize,
+}
+
+impl CanisterLog {
+    /// Creates a log from the given records, e.g. when loading a checkpoint.
+    pub fn new(next_idx: u64, records: Vec<CanisterLogRecord>) -> Self {
+        let mut log = Self {
+            next_idx,
+            records: VecDeque::with_capacity(records.len()),
+            size_bytes: 0,
+        };
+        for record in records.into_iter() {
+            log.push_record(record);
+        }
+        log
+    }
+
+    /// Appends a new record with the given content, evicting the oldest
+    /// records if the log would otherwise exceed its size bound. Content that
+    /// does not fit the log on its own is truncated.
+    pub fn add_record(&mut self, timestamp_nanos: u64, mut content: Vec<u8>) {
+        content.truncate(MAX_CANISTER_LOG_SIZE_BYTES - RECORD_OVERHEAD_BYTES);
+        let record = CanisterLogRecord {
+            idx: self.next_idx,
+            timestamp_nanos,
+            content,
+        };
+        self.next_idx += 1;
+        self.push_record(record);
+    }
+
+    fn push_record(&mut self, record: CanisterLogRecord) {
+        self.size_bytes += record.size_bytes();
+        self.records.push_back(record);
+        while self.size_bytes > MAX_CANISTER_LOG_SIZE_BYTES {
+            match self.records.pop_front() {
+                Some(evicted) => self.size_bytes -= evicted.size_bytes(),
+                None => break,
+            }
+        }
+    }
+
+    /// Returns the records in the log, from the oldest to the newest.
+    pub fn records(&self) -> &VecDeque<CanisterLogRecord> {
+        &self.records
+    }
+
+    /// Returns the records that were added to the log after it was in the
+    /// state `initial`, e.g. by an execution that started from `initial`.
+    pub fn records_added_since<'a>(
+        &'a self,
+        initial: &CanisterLog,
+    ) -> impl Iterator<Item = &'a CanisterLogRecord> {
+        let next_idx = initial.next_idx;
+        self.records
+            .iter()
+            .filter(move |record| record.idx >= next_idx)
+    }
+
+    /// Returns the index that will be assigned to the next record.
+    pub fn next_idx(&self) -> u64 {
+        self.next_idx
+    }
+
+    /// Returns the total size of the records in the log.
+    pub fn size_bytes(&self) -> usize {
+        self.size_bytes
+    }
+}
+
+impl From<LogVisibility> for pb::LogVisibility {
+    fn from(item: LogVisibility) -> Self {
+        match item {
+            LogVisibility::Controllers => pb::LogVisibility::Controllers,
+            LogVisibility::Public => pb::LogVisibility::Public,
+        }
+    }
+}
+
+impl From<pb::LogVisibility> for LogVisibility {
+    fn from(item: pb::LogVisibility) -> Self {
+        match item {
+            // Checkpoints created before log visibility was introduced.
+            pb::LogVisibility::Unspecified => LogVisibility::default(),
+            pb::LogVisibility::Controllers => LogVisibility::Controllers,
+            pb::LogVisibility::Public => LogVisibility::Public,
+        }
+    }
+}
+
+impl From<&CanisterLogRecord> for pb::CanisterLogRecord {
+    fn from(item: &CanisterLogRecord) -> Self {
+        Self {
+            idx: item.idx,
+            timestamp_nanos: item.timestamp_nanos,
+            content: item.content.clone(),
+        }
+    }
+}
+
+impl From<pb::CanisterLogRecord> for CanisterLogRecord {
+    fn from(item: pb::CanisterLogRecord) -> Self {
+        Self {
+            idx: item.idx,
+            timestamp_nanos: item.timestamp_nanos,
+            content: item.content,
+        }
+    }
+}
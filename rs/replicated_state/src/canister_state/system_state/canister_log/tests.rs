@@ -0,0 +1,76 @@
+use super::*;
+
+#[test]
+fn records_get_increasing_indices() {
+    let mut log = CanisterLog::default();
+    log.add_record(1, b"first".to_vec());
+    log.add_record(2, b"second".to_vec());
+
+    let records: Vec<_> = log.records().iter().cloned().collect();
+    assert_eq!(
+        records,
+        vec![
+            CanisterLogRecord {
+                idx: 0,
+                timestamp_nanos: 1,
+                content: b"first".to_vec(),
+            },
+            CanisterLogRecord {
+                idx: 1,
+                timestamp_nanos: 2,
+                content: b"second".to_vec(),
+            },
+        ]
+    );
+    assert_eq!(log.next_idx(), 2);
+}
+
+#[test]
+fn oldest_records_are_evicted_when_log_is_full() {
+    let mut log = CanisterLog::default();
+    let content = vec![0; 1000];
+    for i in 0..10 {
+        log.add_record(i, content.clone());
+    }
+
+    assert!(log.size_bytes() <= MAX_CANISTER_LOG_SIZE_BYTES);
+    let first = log.records().front().unwrap().idx;
+    let last = log.records().back().unwrap().idx;
+    assert!(first > 0);
+    assert_eq!(last, 9);
+    assert_eq!(log.records().len() as u64, last - first + 1);
+}
+
+#[test]
+fn oversized_content_is_truncated() {
+    let mut log = CanisterLog::default();
+    log.add_record(0, vec![1; 2 * MAX_CANISTER_LOG_SIZE_BYTES]);
+
+    assert_eq!(log.records().len(), 1);
+    assert_eq!(log.size_bytes(), MAX_CANISTER_LOG_SIZE_BYTES);
+}
+
+#[test]
+fn new_keeps_indices_of_existing_records() {
+    let mut log = CanisterLog::default();
+    log.add_record(1, b"a".to_vec());
+    log.add_record(2, b"b".to_vec());
+
+    let restored = CanisterLog::new(log.next_idx(), log.records().iter().cloned().collect());
+    assert_eq!(restored, log);
+}
+
+#[test]
+fn records_added_since_skips_initial_records() {
+    let mut initial = CanisterLog::default();
+    initial.add_record(1, b"a".to_vec());
+    let mut log = initial.clone();
+    log.add_record(2, b"b".to_vec());
+    log.add_record(3, b"c".to_vec());
+
+    let added: Vec<_> = log
+        .records_added_since(&initial)
+        .map(|record| record.content.clone())
+        .collect();
+    assert_eq!(added, vec![b"b".to_vec(), b"c".to_vec()]);
+}
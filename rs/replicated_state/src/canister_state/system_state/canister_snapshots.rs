@@ -0,0 +1,138 @@
+#[cfg(test)]
+mod tests;
+
+use crate::canister_state::execution_state::{ExecutionState, ExportedFunctions, Global, Memory};
+use crate::num_bytes_try_from;
+use ic_types::{NumBytes, Time};
+use ic_wasm_types::BinaryEncodedWasm;
+use std::collections::BTreeMap;
+
+/// The maximum number of snapshots a single canister can have at a time.
+/// Taking a new snapshot once the limit is reached requires replacing an
+/// existing one.
+pub const MAX_SNAPSHOTS_PER_CANISTER: usize = 1;
+
+/// The identifier of a snapshot, unique among the snapshots that were ever
+/// taken of the same canister.
+pub type SnapshotId = u64;
+
+/// A copy of the state of a canister that can be restored at a later point.
+///
+/// A snapshot captures everything that is needed to resume the canister from
+/// where it was: its Wasm module, its Wasm and stable memories and its
+/// certified data.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CanisterSnapshot {
+    /// The time at which the snapshot was taken.
+    pub taken_at_timestamp: Time,
+    pub wasm_binary: BinaryEncodedWasm,
+    pub wasm_memory: Memory,
+    pub stable_memory: Memory,
+    pub exported_globals: Vec<Global>,
+    pub exports: ExportedFunctions,
+    pub certified_data: Vec<u8>,
+}
+
+impl CanisterSnapshot {
+    /// Takes a snapshot of the given execution state.
+    ///
+    /// The snapshot does not share any files with the canister, so that the
+    /// canister and the snapshot can be persisted independently.
+    pub fn from_execution_state(
+        execution_state: &ExecutionState,
+        certified_data: Vec<u8>,
+        taken_at_timestamp: Time,
+    ) -> Self {
+        Self {
+            taken_at_timestamp,
+            wasm_binary: BinaryEncodedWasm::new(
+                execution_state.wasm_binary.binary.as_slice().to_vec(),
+            ),
+            wasm_memory: execution_state.wasm_memory.detached_copy(),
+            stable_memory: execution_state.stable_memory.detached_copy(),
+            exported_globals: execution_state.exported_globals.clone(),
+            exports: execution_state.exports.clone(),
+            certified_data,
+        }
+    }
+
+    /// Returns the memory taken by the snapshot. It is accounted in the same
+    /// way as the memory used by an `ExecutionState`.
+    pub fn size(&self) -> NumBytes {
+        // We use 8 bytes per global.
+        let globals_size_bytes = 8 * self.exported_globals.len() as u64;
+        num_bytes_try_from(self.wasm_memory.size)
+            .expect("could not convert from wasm memory number of pages to bytes")
+            + num_bytes_try_from(self.stable_memory.size)
+                .expect("could not convert from stable memory number of pages to bytes")
+            + NumBytes::from(globals_size_bytes)
+            + NumBytes::from(self.wasm_binary.len() as u64)
+            + NumBytes::from(self.certified_data.len() as u64)
+    }
+}
+
+/// The snapshots of a single canister.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CanisterSnapshots {
+    next_snapshot_id: SnapshotId,
+    snapshots: BTreeMap<SnapshotId, CanisterSnapshot>,
+}
+
+impl CanisterSnapshots {
+    /// Creates the snapshots from their parts, e.g. when loading a checkpoint.
+    pub fn new(
+        next_snapshot_id: SnapshotId,
+        snapshots: BTreeMap<SnapshotId, CanisterSnapshot>,
+    ) -> Self {
+        Self {
+            next_snapshot_id,
+            snapshots,
+        }
+    }
+
+    /// Adds a new snapshot and returns the identifier assigned to it.
+    pub fn push(&mut self, snapshot: CanisterSnapshot) -> SnapshotId {
+        let snapshot_id = self.next_snapshot_id;
+        self.next_snapshot_id += 1;
+        self.snapshots.insert(snapshot_id, snapshot);
+        snapshot_id
+    }
+
+    pub fn get(&self, snapshot_id: SnapshotId) -> Option<&CanisterSnapshot> {
+        self.snapshots.get(&snapshot_id)
+    }
+
+    pub fn remove(&mut self, snapshot_id: SnapshotId) -> Option<CanisterSnapshot> {
+        self.snapshots.remove(&snapshot_id)
+    }
+
+    /// Returns the snapshots ordered by their identifiers, i.e. from the
+    /// oldest to the newest.
+    pub fn iter(&self) -> impl Iterator<Item = (&SnapshotId, &CanisterSnapshot)> {
+        self.snapshots.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&SnapshotId, &mut CanisterSnapshot)> {
+        self.snapshots.iter_mut()
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// Returns the identifier that will be assigned to the next snapshot.
+    pub fn next_snapshot_id(&self) -> SnapshotId {
+        self.next_snapshot_id
+    }
+
+    /// Returns the total memory taken by the snapshots.
+    pub fn memory_usage(&self) -> NumBytes {
+        self.snapshots
+            .values()
+            .fold(NumBytes::from(0), |acc, snapshot| acc + snapshot.size())
+    }
+}
@@ -0,0 +1,67 @@
+use super::*;
+use crate::{NumWasmPages, PageIndex, PageMap};
+use ic_sys::PageBytes;
+use ic_types::methods::WasmMethod;
+
+fn snapshot_with_heap(heap: &[(PageIndex, &PageBytes)]) -> CanisterSnapshot {
+    let mut page_map = PageMap::default();
+    page_map.update(heap);
+    CanisterSnapshot {
+        taken_at_timestamp: Time::from_nanos_since_unix_epoch(1),
+        wasm_binary: BinaryEncodedWasm::new(vec![0; 100]),
+        wasm_memory: Memory::new(page_map, NumWasmPages::from(1)),
+        stable_memory: Memory::default(),
+        exported_globals: vec![Global::I32(1), Global::I64(2)],
+        exports: vec![WasmMethod::Update("f".to_string())]
+            .into_iter()
+            .collect(),
+        certified_data: vec![1, 2, 3],
+    }
+}
+
+#[test]
+fn snapshot_ids_are_not_reused() {
+    let mut snapshots = CanisterSnapshots::default();
+    let first = snapshots.push(snapshot_with_heap(&[]));
+    assert!(snapshots.remove(first).is_some());
+    let second = snapshots.push(snapshot_with_heap(&[]));
+
+    assert_ne!(first, second);
+    assert_eq!(snapshots.len(), 1);
+    assert!(snapshots.get(first).is_none());
+    assert!(snapshots.get(second).is_some());
+    assert_eq!(snapshots.next_snapshot_id(), second + 1);
+}
+
+#[test]
+fn snapshot_size_accounts_for_all_parts() {
+    let snapshot = snapshot_with_heap(&[]);
+    // One Wasm page of heap, two globals, the Wasm binary and certified data.
+    assert_eq!(snapshot.size(), NumBytes::from(65536 + 2 * 8 + 100 + 3));
+}
+
+#[test]
+fn memory_usage_sums_up_snapshot_sizes() {
+    let mut snapshots = CanisterSnapshots::default();
+    assert_eq!(snapshots.memory_usage(), NumBytes::from(0));
+
+    let snapshot = snapshot_with_heap(&[]);
+    let size = snapshot.size();
+    snapshots.push(snapshot.clone());
+    snapshots.push(snapshot);
+    assert_eq!(snapshots.memory_usage(), NumBytes::from(2 * size.get()));
+}
+
+#[test]
+fn detached_copy_keeps_memory_contents() {
+    let page = [7; ic_sys::PAGE_SIZE];
+    let snapshot = snapshot_with_heap(&[(PageIndex::from(3), &page)]);
+    let copy = snapshot.wasm_memory.detached_copy();
+
+    assert_eq!(copy.size, snapshot.wasm_memory.size);
+    assert_eq!(copy.page_map.get_page(PageIndex::from(3)), &page);
+    assert_eq!(
+        copy.page_map.get_page_delta_indices().len(),
+        snapshot.wasm_memory.page_map.num_host_pages()
+    );
+}
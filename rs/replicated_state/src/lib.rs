@@ -12,7 +12,9 @@ pub use canister_state::{
     num_bytes_try_from,
     system_state::{
         memory_required_to_push_request, CallContext, CallContextAction, CallContextManager,
-        CallOrigin, CanisterMetrics, CanisterStatus, SystemState,
+        CallOrigin, CanisterLog, CanisterLogRecord, CanisterMetrics, CanisterSnapshot,
+        CanisterSnapshots, CanisterStatus, CanisterTimer, ExecutionTask, LogVisibility, PausedTask,
+        SnapshotId, SystemState, MAX_SNAPSHOTS_PER_CANISTER,
     },
     CanisterQueues, CanisterState, EmbedderCache, ExecutionState, ExportedFunctions, Global,
     NumWasmPages, SchedulerState,
@@ -4,6 +4,7 @@ use ic_protobuf::{
     state::system_metadata::v1 as pb_metadata,
 };
 use ic_types::{
+    canister_http::{CanisterHttpHeader, CanisterHttpMethod, CanisterHttpRequest},
     crypto::threshold_sig::ni_dkg::{id::ni_dkg_target_id, NiDkgTargetId},
     messages::{CallbackId, Request},
     node_id_into_protobuf, node_id_try_from_protobuf, NodeId, RegistryVersion, Time,
@@ -19,6 +20,7 @@ pub struct SubnetCallContextManager {
     pub setup_initial_dkg_contexts: BTreeMap<CallbackId, SetupInitialDkgContext>,
     pub sign_with_ecdsa_contexts: BTreeMap<CallbackId, SignWithEcdsaContext>,
     pub sign_with_mock_ecdsa_contexts: BTreeMap<CallbackId, SignWithEcdsaContext>,
+    pub canister_http_request_contexts: BTreeMap<CallbackId, CanisterHttpRequestContext>,
 }
 
 impl SubnetCallContextManager {
@@ -40,6 +42,14 @@ impl SubnetCallContextManager {
         };
     }
 
+    pub fn push_http_request(&mut self, context: CanisterHttpRequestContext) {
+        let callback_id = CallbackId::new(self.next_callback_id);
+        self.next_callback_id += 1;
+
+        self.canister_http_request_contexts
+            .insert(callback_id, context);
+    }
+
     pub fn retrieve_request(
         &mut self,
         callback_id: CallbackId,
@@ -81,6 +91,19 @@ impl SubnetCallContextManager {
                         context.request
                     })
             })
+            .or_else(|| {
+                self.canister_http_request_contexts
+                    .remove(&callback_id)
+                    .map(|context| {
+                        info!(
+                            logger,
+                            "Received the response for HttpRequest with callback id {:?} from {:?}",
+                            callback_id,
+                            context.request.sender
+                        );
+                        context.request
+                    })
+            })
     }
 }
 
@@ -118,6 +141,16 @@ impl From<&SubnetCallContextManager> for pb_metadata::SubnetCallContextManager {
                     },
                 )
                 .collect(),
+            canister_http_request_contexts: item
+                .canister_http_request_contexts
+                .iter()
+                .map(
+                    |(callback_id, context)| pb_metadata::CanisterHttpRequestContextTree {
+                        callback_id: callback_id.get(),
+                        context: Some(context.into()),
+                    },
+                )
+                .collect(),
         }
     }
 }
@@ -143,11 +176,19 @@ impl TryFrom<pb_metadata::SubnetCallContextManager> for SubnetCallContextManager
                 try_from_option_field(entry.context, "SystemMetadata::SignWithMockEcdsaContext")?;
             sign_with_mock_ecdsa_contexts.insert(CallbackId::new(entry.callback_id), context);
         }
+        let mut canister_http_request_contexts =
+            BTreeMap::<CallbackId, CanisterHttpRequestContext>::new();
+        for entry in item.canister_http_request_contexts {
+            let context: CanisterHttpRequestContext =
+                try_from_option_field(entry.context, "SystemMetadata::CanisterHttpRequestContext")?;
+            canister_http_request_contexts.insert(CallbackId::new(entry.callback_id), context);
+        }
         Ok(Self {
             next_callback_id: item.next_callback_id,
             setup_initial_dkg_contexts,
             sign_with_ecdsa_contexts,
             sign_with_mock_ecdsa_contexts,
+            canister_http_request_contexts,
         })
     }
 }
@@ -238,3 +279,90 @@ impl TryFrom<pb_metadata::SignWithEcdsaContext> for SignWithEcdsaContext {
         })
     }
 }
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CanisterHttpRequestContext {
+    pub request: Request,
+    pub url: String,
+    pub http_method: CanisterHttpMethod,
+    pub headers: Vec<CanisterHttpHeader>,
+    pub body: Option<Vec<u8>>,
+    /// The query method of the calling canister that is applied to the
+    /// response before it is delivered.
+    pub transform_method_name: Option<String>,
+    pub time: Time,
+}
+
+impl CanisterHttpRequestContext {
+    /// Returns the request that the adapter has to send for this context.
+    pub fn to_request(&self, callback_id: CallbackId) -> CanisterHttpRequest {
+        CanisterHttpRequest {
+            id: callback_id,
+            url: self.url.clone(),
+            method: self.http_method,
+            headers: self.headers.clone(),
+            body: self.body.clone(),
+        }
+    }
+}
+
+impl From<&CanisterHttpRequestContext> for pb_metadata::CanisterHttpRequestContext {
+    fn from(context: &CanisterHttpRequestContext) -> Self {
+        let http_method = match context.http_method {
+            CanisterHttpMethod::Get => pb_metadata::HttpMethod::Get,
+            CanisterHttpMethod::Head => pb_metadata::HttpMethod::Head,
+            CanisterHttpMethod::Post => pb_metadata::HttpMethod::Post,
+        };
+        pb_metadata::CanisterHttpRequestContext {
+            request: Some((&context.request).into()),
+            url: context.url.clone(),
+            http_method: http_method as i32,
+            headers: context
+                .headers
+                .iter()
+                .map(|header| pb_metadata::HttpHeader {
+                    name: header.name.clone(),
+                    value: header.value.clone(),
+                })
+                .collect(),
+            body: context.body.clone(),
+            transform_method_name: context.transform_method_name.clone(),
+            time: context.time.as_nanos_since_unix_epoch(),
+        }
+    }
+}
+
+impl TryFrom<pb_metadata::CanisterHttpRequestContext> for CanisterHttpRequestContext {
+    type Error = ProxyDecodeError;
+    fn try_from(context: pb_metadata::CanisterHttpRequestContext) -> Result<Self, Self::Error> {
+        let request: Request =
+            try_from_option_field(context.request, "CanisterHttpRequestContext::request")?;
+        let http_method = match pb_metadata::HttpMethod::from_i32(context.http_method) {
+            Some(pb_metadata::HttpMethod::Get) => CanisterHttpMethod::Get,
+            Some(pb_metadata::HttpMethod::Head) => CanisterHttpMethod::Head,
+            Some(pb_metadata::HttpMethod::Post) => CanisterHttpMethod::Post,
+            Some(pb_metadata::HttpMethod::Unspecified) | None => {
+                return Err(ProxyDecodeError::ValueOutOfRange {
+                    typ: "CanisterHttpRequestContext::http_method",
+                    err: format!("{}", context.http_method),
+                })
+            }
+        };
+        Ok(CanisterHttpRequestContext {
+            request,
+            url: context.url,
+            http_method,
+            headers: context
+                .headers
+                .into_iter()
+                .map(|header| CanisterHttpHeader {
+                    name: header.name,
+                    value: header.value,
+                })
+                .collect(),
+            body: context.body,
+            transform_method_name: context.transform_method_name,
+            time: Time::from_nanos_since_unix_epoch(context.time),
+        })
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{spawn, EncodedBlock};
+use crate::{certification::archived_block_size_bytes, spawn, EncodedBlock};
 use candid::CandidType;
 use ic_types::ic00::{Method, IC_00};
 use ic_types::CanisterId;
@@ -114,12 +114,13 @@ impl Archive {
             // Get the CanisterId and remaining capacity of the node that can
             // accept at least the first block
             let (node_canister_id, node_index, remaining_capacity) = self
-                .node_and_capacity(blocks[0].size_bytes())
+                .node_and_capacity(archived_block_size_bytes(&blocks[0]))
                 .await
                 .map_err(|e| (num_sent_blocks, e))?;
 
             // Take as many blocks as can be sent and send those in
-            let mut first_blocks: VecDeque<_> = take_prefix(&mut blocks, remaining_capacity).into();
+            let mut first_blocks: VecDeque<_> =
+                take_prefix(&mut blocks, remaining_capacity, archived_block_size_bytes).into();
             if first_blocks.is_empty() {
                 return Err((num_sent_blocks, FailedToArchiveBlocks("empty chunk".into())));
             }
@@ -133,7 +134,8 @@ impl Archive {
 
             // Additionally, need to respect the inter-canister message size
             while !first_blocks.is_empty() {
-                let chunk = take_prefix(&mut first_blocks, max_chunk_size);
+                let chunk =
+                    take_prefix(&mut first_blocks, max_chunk_size, EncodedBlock::size_bytes);
                 let chunk_len = chunk.len() as u64;
                 if chunk.is_empty() {
                     return Err((num_sent_blocks, FailedToArchiveBlocks("empty chunk".into())));
@@ -324,14 +326,19 @@ impl Archive {
     }
 }
 
-/// Extract longest prefix from `blocks` which fits in `max_size`
-fn take_prefix(blocks: &mut VecDeque<EncodedBlock>, mut max_size: usize) -> Vec<EncodedBlock> {
+/// Extract longest prefix from `blocks` which fits in `max_size`, where each
+/// block takes `size_bytes(block)` bytes
+fn take_prefix(
+    blocks: &mut VecDeque<EncodedBlock>,
+    mut max_size: usize,
+    size_bytes: impl Fn(&EncodedBlock) -> usize,
+) -> Vec<EncodedBlock> {
     let mut result = vec![];
     while let Some(next) = blocks.front() {
-        if next.size_bytes() > max_size {
+        if size_bytes(next) > max_size {
             break;
         }
-        max_size -= next.size_bytes();
+        max_size -= size_bytes(next);
         result.push(blocks.pop_front().unwrap());
     }
     result
@@ -1,6 +1,10 @@
 use ledger_canister::{
-    metrics_encoder::MetricsEncoder, BlockHeight, BlockRes, EncodedBlock, GetBlocksArgs,
-    IterBlocksArgs,
+    certification::{
+        archive_node_hash_tree, archived_block_size_bytes, BlockHashTree,
+        ARCHIVED_BLOCK_OVERHEAD_BYTES,
+    },
+    metrics_encoder::MetricsEncoder,
+    BlockHeight, BlockRes, EncodedBlock, GetBlocksArgs, HashOf, IterBlocksArgs,
 };
 
 use dfn_core::api::stable_memory_size_in_pages;
@@ -20,10 +24,21 @@ struct ArchiveNodeState {
     pub max_memory_size_bytes: usize,
     pub block_height_offset: u64,
     pub blocks: Vec<EncodedBlock>,
+    /// The memory used by the blocks, their hashes and the hash tree, see
+    /// `archived_block_size_bytes`.
     pub total_block_size: usize,
     pub ledger_canister_id: ic_types::CanisterId,
     #[serde(skip)]
     pub last_upgrade_timestamp: u64,
+    /// The hashes of the stored blocks, in order. They are persisted so that
+    /// the hash tree can be rebuilt after upgrades without hashing the blocks
+    /// again.
+    #[serde(default)]
+    pub block_hashes: Vec<HashOf<EncodedBlock>>,
+    /// The hashes of the stored blocks, which the node certifies. The tree is
+    /// rebuilt from `block_hashes` after upgrades.
+    #[serde(skip)]
+    pub block_hash_tree: BlockHashTree,
 }
 
 const DEFAULT_MAX_MEMORY_SIZE: usize = 1024 * 1024 * 1024;
@@ -39,10 +54,19 @@ impl ArchiveNodeState {
             block_height_offset,
             blocks: Vec::new(),
             total_block_size: 0,
+            block_hashes: Vec::new(),
             ledger_canister_id: archive_main_canister_id,
             last_upgrade_timestamp: 0,
+            block_hash_tree: BlockHashTree::default(),
         }
     }
+
+    /// Certifies the stored blocks, see `ledger_canister::certification`.
+    fn certify_blocks(&self) {
+        dfn_core::api::set_certified_data(
+            &archive_node_hash_tree(&self.block_hash_tree, None).reconstruct(),
+        );
+    }
 }
 
 // Helper to print messages in cyan
@@ -67,13 +91,20 @@ fn append_blocks(mut blocks: Vec<EncodedBlock>) {
         blocks.len()
     ));
     for block in &blocks {
-        archive_state.total_block_size += block.size_bytes();
+        archive_state.total_block_size += archived_block_size_bytes(block);
     }
     assert!(
         archive_state.total_block_size < archive_state.max_memory_size_bytes,
         "No space left"
     );
+    let first_height = archive_state.block_height_offset + archive_state.blocks.len() as u64;
+    for (height, block) in (first_height..).zip(&blocks) {
+        let hash = block.hash();
+        archive_state.block_hash_tree.insert(height, hash);
+        archive_state.block_hashes.push(hash);
+    }
     archive_state.blocks.append(&mut blocks);
+    archive_state.certify_blocks();
     print(format!(
         "[archive node] append_blocks(): done. archive size: {} blocks",
         archive_state.blocks.len()
@@ -83,10 +114,11 @@ fn append_blocks(mut blocks: Vec<EncodedBlock>) {
 // Return the number of bytes the canister can still accommodate
 fn remaining_capacity() -> usize {
     let archive_state = ARCHIVE_STATE.read().unwrap();
+    // The certification overhead can push nodes upgraded from older versions
+    // past their maximum size.
     let remaining_capacity = archive_state
         .max_memory_size_bytes
-        .checked_sub(archive_state.total_block_size)
-        .unwrap();
+        .saturating_sub(archive_state.total_block_size);
     print(format!(
         "[archive node] remaining_capacity: {} bytes",
         remaining_capacity
@@ -115,11 +147,13 @@ fn init(
         }
     }
 
-    *ARCHIVE_STATE.write().unwrap() = ArchiveNodeState::new(
+    let archive_state = ArchiveNodeState::new(
         archive_main_canister_id,
         block_height_offset,
         max_memory_size_bytes,
     );
+    archive_state.certify_blocks();
+    *ARCHIVE_STATE.write().unwrap() = archive_state;
 }
 
 /// Get Block by BlockHeight. If the BlockHeight is outside the range stored in
@@ -184,6 +218,23 @@ fn get_blocks_() {
     });
 }
 
+/// Get multiple Blocks by BlockHeight and length together with a witness that
+/// proves them against the data certified by the node. If the query is outside
+/// the range stored in the Node the result is an error.
+#[export_name = "canister_query get_certified_blocks_pb"]
+fn get_certified_blocks_() {
+    dfn_core::over(protobuf, |GetBlocksArgs { start, length }| {
+        let archive_state = ARCHIVE_STATE.read().unwrap();
+        ledger_canister::get_certified_blocks(
+            &archive_state.blocks,
+            archive_state.block_height_offset,
+            start,
+            length,
+            |range| archive_node_hash_tree(&archive_state.block_hash_tree, range),
+        )
+    });
+}
+
 #[export_name = "canister_post_upgrade"]
 fn post_upgrade() {
     over_init(|_: BytesS| {
@@ -191,6 +242,18 @@ fn post_upgrade() {
         *state = serde_cbor::from_reader(&mut stable::StableReader::new())
             .expect("Decoding stable memory failed");
         state.last_upgrade_timestamp = dfn_core::api::time_nanos();
+        // Nodes upgraded from a version that did not persist the hashes have to
+        // hash their blocks once and account for the certification overhead.
+        let num_hashes = state.block_hashes.len();
+        let missing_hashes: Vec<_> = state.blocks[num_hashes..]
+            .iter()
+            .map(EncodedBlock::hash)
+            .collect();
+        state.total_block_size += missing_hashes.len() * ARCHIVED_BLOCK_OVERHEAD_BYTES;
+        state.block_hashes.extend(missing_hashes);
+        state.block_hash_tree =
+            BlockHashTree::from_hashes(state.block_height_offset, &state.block_hashes);
+        state.certify_blocks();
     });
 }
 
@@ -232,7 +295,7 @@ fn encode_metrics(w: &mut MetricsEncoder<Vec<u8>>) -> std::io::Result<()> {
     w.encode_gauge(
         "archive_node_blocks_bytes",
         state.total_block_size as f64,
-        "Total amount of memory consumed by the blocks stored by this canister.",
+        "Total amount of memory consumed by the blocks stored by this canister and their certification.",
     )?;
     w.encode_gauge(
         "archive_node_stable_memory_pages",
@@ -0,0 +1,180 @@
+//! This module contains the hash trees that the ledger and the archive nodes
+//! certify, so that clients can verify the blocks they fetch.
+//!
+//! The tree certified by the ledger is as follows
+//!
+//! ```text
+//! *
+//! |
+//! +-- archives --+-- [ archive node canister id ] -- [ BE first height ++ BE last height ]
+//! |              |
+//! |              …
+//! |
+//! +-- blocks --+-- [ BE block height ] -- [ block hash ]
+//! |            |
+//! |            …
+//! |
+//! `-- tip -- [ BE tip height ++ tip hash ]
+//! ```
+//!
+//! where "blocks" contains the blocks that are not archived yet and "tip" is
+//! empty as long as the chain is. An archive node certifies the blocks it
+//! stores:
+//!
+//! ```text
+//! *
+//! |
+//! `-- blocks --+-- [ BE block height ] -- [ block hash ]
+//!              |
+//!              …
+//! ```
+//!
+//! A block served by an archive node is thus proven by the certificate of the
+//! node together with the certificate of the ledger, which proves that the
+//! node stores the range of blocks that includes it.
+
+use crate::{BlockHeight, EncodedBlock, HashOf};
+use ic_certified_map::{fork, labeled, labeled_hash, AsHashTree, HashTree, RbTree};
+use ic_types::CanisterId;
+use std::borrow::Cow;
+use std::fmt;
+
+pub const ARCHIVES_LABEL: &[u8] = b"archives";
+pub const BLOCKS_LABEL: &[u8] = b"blocks";
+pub const TIP_LABEL: &[u8] = b"tip";
+
+/// An estimate of the memory, in bytes, that an archive node uses for a
+/// block in addition to the block itself: the persisted hash of the block and
+/// its entry in the [BlockHashTree].
+pub const ARCHIVED_BLOCK_OVERHEAD_BYTES: usize = 160;
+
+/// Returns the memory, in bytes, that an archive node needs to store and
+/// certify `block`.
+pub fn archived_block_size_bytes(block: &EncodedBlock) -> usize {
+    block.size_bytes() + ARCHIVED_BLOCK_OVERHEAD_BYTES
+}
+
+/// A block height encoded in big-endian, so that the order of the labels in
+/// the tree is the order of the heights.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Default)]
+pub struct EncodedBlockHeight([u8; 8]);
+
+impl fmt::Debug for EncodedBlockHeight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", BlockHeight::from_be_bytes(self.0))
+    }
+}
+
+impl From<BlockHeight> for EncodedBlockHeight {
+    fn from(height: BlockHeight) -> Self {
+        Self(height.to_be_bytes())
+    }
+}
+
+impl AsRef<[u8]> for EncodedBlockHeight {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// The hashes of a contiguous range of blocks, indexed by height.
+#[derive(PartialEq, Default, Clone, Debug)]
+pub struct BlockHashTree(RbTree<EncodedBlockHeight, Vec<u8>>);
+
+impl BlockHashTree {
+    /// Builds the tree of `blocks`, the first of which has height
+    /// `first_height`.
+    pub fn new(first_height: BlockHeight, blocks: &[EncodedBlock]) -> Self {
+        let mut tree = Self::default();
+        for (height, block) in (first_height..).zip(blocks) {
+            tree.insert(height, block.hash());
+        }
+        tree
+    }
+
+    /// Builds the tree of the blocks with the given `hashes`, the first of
+    /// which has height `first_height`.
+    pub fn from_hashes(first_height: BlockHeight, hashes: &[HashOf<EncodedBlock>]) -> Self {
+        let mut tree = Self::default();
+        for (height, hash) in (first_height..).zip(hashes) {
+            tree.insert(height, *hash);
+        }
+        tree
+    }
+
+    pub fn insert(&mut self, height: BlockHeight, hash: HashOf<EncodedBlock>) {
+        self.0.insert(height.into(), hash.into_bytes().to_vec());
+    }
+
+    /// Returns the "blocks" subtree with the hashes of the blocks in the
+    /// inclusive range `[first, last]` revealed, or pruned if `range` is
+    /// `None`.
+    pub fn witness(&self, range: Option<(BlockHeight, BlockHeight)>) -> HashTree<'_> {
+        match range {
+            Some((first, last)) => labeled(
+                BLOCKS_LABEL,
+                self.0.value_range(
+                    EncodedBlockHeight::from(first).as_ref(),
+                    EncodedBlockHeight::from(last).as_ref(),
+                ),
+            ),
+            None => HashTree::Pruned(labeled_hash(BLOCKS_LABEL, &self.0.root_hash())),
+        }
+    }
+}
+
+/// The ranges of blocks stored in the archive nodes, indexed by the canister
+/// id of the node.
+#[derive(PartialEq, Default, Clone, Debug)]
+pub struct ArchiveIndexTree(RbTree<Vec<u8>, Vec<u8>>);
+
+impl ArchiveIndexTree {
+    pub fn new(index: &[((BlockHeight, BlockHeight), CanisterId)]) -> Self {
+        let mut tree = RbTree::new();
+        for ((height_from, height_to), canister_id) in index {
+            let mut range = height_from.to_be_bytes().to_vec();
+            range.extend_from_slice(&height_to.to_be_bytes());
+            tree.insert(canister_id.get().into_vec(), range);
+        }
+        Self(tree)
+    }
+}
+
+/// Returns the tree certified by the ledger, revealing the archive index,
+/// the tip and the hashes of the blocks in `range`.
+pub fn ledger_hash_tree<'a>(
+    archives: &'a ArchiveIndexTree,
+    blocks: &'a BlockHashTree,
+    tip: Option<(BlockHeight, HashOf<EncodedBlock>)>,
+    range: Option<(BlockHeight, BlockHeight)>,
+) -> HashTree<'a> {
+    let tip = match tip {
+        Some((height, hash)) => {
+            let mut tip = height.to_be_bytes().to_vec();
+            tip.extend_from_slice(&hash.into_bytes());
+            tip
+        }
+        None => vec![],
+    };
+    fork(
+        fork(
+            labeled(ARCHIVES_LABEL, archives.0.as_hash_tree()),
+            blocks.witness(range),
+        ),
+        labeled(TIP_LABEL, HashTree::Leaf(Cow::Owned(tip))),
+    )
+}
+
+/// Returns the tree certified by an archive node, revealing the hashes of
+/// the blocks in `range`.
+pub fn archive_node_hash_tree(
+    blocks: &BlockHashTree,
+    range: Option<(BlockHeight, BlockHeight)>,
+) -> HashTree<'_> {
+    blocks.witness(range)
+}
+
+/// Encodes a hash tree in CBOR, the representation expected by clients.
+pub fn encode_hash_tree(tree: &HashTree<'_>) -> Vec<u8> {
+    serde_cbor::to_vec(tree).expect("Failed to encode hash tree")
+}
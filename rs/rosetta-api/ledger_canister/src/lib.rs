@@ -1,5 +1,6 @@
 use candid::CandidType;
 use dfn_protobuf::ProtoBuf;
+use ic_certified_map::HashTree;
 use ic_crypto_sha::Sha256;
 use ic_types::{CanisterId, PrincipalId};
 use intmap::IntMap;
@@ -13,7 +14,7 @@ use serde::{
 };
 use std::borrow::Cow;
 use std::collections::hash_map::Entry::{Occupied, Vacant};
-use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::convert::TryFrom;
 use std::convert::TryInto;
 use std::fmt;
@@ -24,6 +25,7 @@ use std::sync::{Arc, RwLock};
 use std::time::{Duration, SystemTime};
 
 pub mod account_identifier;
+pub mod certification;
 pub mod http_request;
 pub mod metrics_encoder;
 pub mod tokens;
@@ -37,6 +39,7 @@ pub mod archive;
 
 use archive::Archive;
 pub use archive::ArchiveOptions;
+use certification::{ArchiveIndexTree, BlockHashTree};
 use dfn_core::api::now;
 
 pub mod spawn;
@@ -297,6 +300,22 @@ impl<S: Default + BalancesStore> Balances<S> {
                 self.credit(to, *amount);
                 self.token_pool -= *amount;
             }
+            Operation::Approve { from, fee, .. } => {
+                self.debit(from, *fee)?;
+                self.token_pool += *fee;
+            }
+            Operation::TransferFrom {
+                from,
+                to,
+                amount,
+                fee,
+                ..
+            } => {
+                let debit_amount = (*amount + *fee).expect("amount + fee failed");
+                self.debit(from, debit_amount)?;
+                self.credit(to, *amount);
+                self.token_pool += *fee;
+            }
         }
         Ok(())
     }
@@ -404,6 +423,140 @@ pub enum Operation {
         amount: Tokens,
         fee: Tokens,
     },
+    /// Sets the amount that `spender` may transfer from `from` with
+    /// `TransferFrom` operations, replacing the previous allowance.
+    Approve {
+        from: AccountIdentifier,
+        spender: AccountIdentifier,
+        allowance: Tokens,
+        /// If set, the operation only succeeds if the current allowance is
+        /// equal to it.
+        expected_allowance: Option<Tokens>,
+        /// The time at which the allowance expires, if any.
+        expires_at: Option<TimeStamp>,
+        fee: Tokens,
+    },
+    /// A transfer made by `spender` on behalf of `from`. The amount and the
+    /// fee are deducted from the allowance of `spender`.
+    TransferFrom {
+        from: AccountIdentifier,
+        to: AccountIdentifier,
+        spender: AccountIdentifier,
+        amount: Tokens,
+        fee: Tokens,
+    },
+}
+
+/// The amount that a spender may transfer on behalf of an account.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct Allowance {
+    pub allowance: Tokens,
+    pub expires_at: Option<TimeStamp>,
+}
+
+/// The allowances set with `Approve` operations, keyed by the approving
+/// account and the spender. Allowances that are zero or expired are not
+/// stored.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Allowances {
+    allowances: BTreeMap<(AccountIdentifier, AccountIdentifier), Allowance>,
+    /// The allowances that have an expiration time, ordered by it.
+    expiration_queue: BTreeSet<(TimeStamp, AccountIdentifier, AccountIdentifier)>,
+}
+
+impl Allowances {
+    /// Returns the allowance of `spender` on `from` at time `now`.
+    pub fn allowance(
+        &self,
+        from: &AccountIdentifier,
+        spender: &AccountIdentifier,
+        now: TimeStamp,
+    ) -> Allowance {
+        match self.allowances.get(&(*from, *spender)) {
+            Some(allowance) if allowance.expires_at.map_or(true, |t| t > now) => allowance.clone(),
+            _ => Allowance {
+                allowance: Tokens::ZERO,
+                expires_at: None,
+            },
+        }
+    }
+
+    /// Updates the allowances according to an operation that has been
+    /// added to the ledger.
+    pub fn apply(&mut self, operation: &Operation) {
+        match operation {
+            Operation::Approve {
+                from,
+                spender,
+                allowance,
+                expires_at,
+                ..
+            } => {
+                self.remove(from, spender);
+                if *allowance != Tokens::ZERO {
+                    if let Some(expires_at) = expires_at {
+                        self.expiration_queue.insert((*expires_at, *from, *spender));
+                    }
+                    self.allowances.insert(
+                        (*from, *spender),
+                        Allowance {
+                            allowance: *allowance,
+                            expires_at: *expires_at,
+                        },
+                    );
+                }
+            }
+            Operation::TransferFrom {
+                from,
+                spender,
+                amount,
+                fee,
+                ..
+            } => {
+                let used = (*amount + *fee).expect("amount + fee failed");
+                let remaining = match self.allowances.get_mut(&(*from, *spender)) {
+                    Some(allowance) => {
+                        allowance.allowance = (allowance.allowance - used).unwrap_or(Tokens::ZERO);
+                        allowance.allowance
+                    }
+                    None => return,
+                };
+                if remaining == Tokens::ZERO {
+                    self.remove(from, spender);
+                }
+            }
+            Operation::Burn { .. } | Operation::Mint { .. } | Operation::Transfer { .. } => {}
+        }
+    }
+
+    /// Removes the allowances that expired at or before `now`.
+    pub fn purge_expired(&mut self, now: TimeStamp) {
+        while let Some((expires_at, from, spender)) = self.expiration_queue.iter().next().cloned() {
+            if expires_at > now {
+                break;
+            }
+            self.expiration_queue.remove(&(expires_at, from, spender));
+            self.allowances.remove(&(from, spender));
+        }
+    }
+
+    fn remove(&mut self, from: &AccountIdentifier, spender: &AccountIdentifier) {
+        if let Some(Allowance {
+            expires_at: Some(expires_at),
+            ..
+        }) = self.allowances.remove(&(*from, *spender))
+        {
+            self.expiration_queue.remove(&(expires_at, *from, *spender));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.allowances.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allowances.is_empty()
+    }
 }
 
 /// An operation with the metadata the client generated attached to it
@@ -524,6 +677,21 @@ pub struct Blockchain {
 
     /// How many blocks have been sent to the archive
     pub num_archived_blocks: u64,
+
+    /// The hashes of `blocks`, so that the hash tree can be rebuilt without
+    /// hashing the blocks again.
+    #[serde(default)]
+    pub block_hashes: Vec<HashOf<EncodedBlock>>,
+
+    /// The hashes of the blocks that are not archived yet, certified together
+    /// with the archive index and the tip, see `certification`. The hash
+    /// trees are rebuilt after upgrades.
+    #[serde(skip)]
+    pub block_hash_tree: BlockHashTree,
+
+    /// The certified ranges of blocks stored in the archive nodes.
+    #[serde(skip)]
+    pub archive_index_tree: ArchiveIndexTree,
 }
 
 impl Default for Blockchain {
@@ -534,6 +702,9 @@ impl Default for Blockchain {
             last_timestamp: SystemTime::UNIX_EPOCH.into(),
             archive: Arc::new(RwLock::new(None)),
             num_archived_blocks: 0,
+            block_hashes: vec![],
+            block_hash_tree: BlockHashTree::default(),
+            archive_index_tree: ArchiveIndexTree::default(),
         }
     }
 }
@@ -558,7 +729,10 @@ impl Blockchain {
                     .to_owned(),
             );
         }
-        self.last_hash = Some(encoded_block.hash());
+        let hash = encoded_block.hash();
+        self.block_hash_tree.insert(self.chain_length(), hash);
+        self.block_hashes.push(hash);
+        self.last_hash = Some(hash);
         self.last_timestamp = block.timestamp;
         self.blocks.push(encoded_block);
         Ok(self.chain_length().checked_sub(1).unwrap())
@@ -600,7 +774,48 @@ impl Blockchain {
             );
         }
         self.blocks = self.blocks.split_off(len);
+        self.block_hashes.drain(..len);
         self.num_archived_blocks += len as u64;
+        self.block_hash_tree =
+            BlockHashTree::from_hashes(self.num_archived_blocks, &self.block_hashes);
+    }
+
+    /// Certifies that the archive nodes store the given ranges of blocks.
+    pub fn set_archive_index(&mut self, index: &[((BlockHeight, BlockHeight), CanisterId)]) {
+        self.archive_index_tree = ArchiveIndexTree::new(index);
+    }
+
+    /// Rebuilds the hash trees, which are not persisted across upgrades.
+    pub fn rebuild_hash_trees(&mut self) {
+        let index = self
+            .archive
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|archive| archive.index())
+            .unwrap_or_default();
+        self.set_archive_index(&index);
+        // Ledgers upgraded from a version that did not store the hashes have
+        // to hash their blocks once.
+        let num_hashes = self.block_hashes.len();
+        self.block_hashes
+            .extend(self.blocks[num_hashes..].iter().map(EncodedBlock::hash));
+        self.block_hash_tree =
+            BlockHashTree::from_hashes(self.num_archived_blocks, &self.block_hashes);
+    }
+
+    /// Returns the tree certified by the ledger, revealing the hashes of the
+    /// blocks in the inclusive `range`.
+    pub fn hash_tree(&self, range: Option<(BlockHeight, BlockHeight)>) -> HashTree<'_> {
+        let tip = self
+            .last_hash
+            .map(|hash| (self.chain_length().checked_sub(1).unwrap(), hash));
+        certification::ledger_hash_tree(&self.archive_index_tree, &self.block_hash_tree, tip, range)
+    }
+
+    /// Returns the data that the ledger certifies.
+    pub fn root_hash(&self) -> [u8; HASH_LENGTH] {
+        self.hash_tree(None).reconstruct()
     }
 
     pub fn get_blocks_for_archiving(
@@ -717,6 +932,10 @@ pub struct Ledger {
     transactions_by_height: VecDeque<TransactionInfo>,
     /// Used to prevent non-whitelisted canisters from sending tokens
     send_whitelist: HashSet<CanisterId>,
+    /// The allowances set with `Approve` operations. They are not derived
+    /// from the blocks, so archiving blocks does not affect them.
+    #[serde(default)]
+    pub allowances: Allowances,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -738,6 +957,7 @@ impl Default for Ledger {
             transactions_by_hash: BTreeMap::new(),
             transactions_by_height: VecDeque::new(),
             send_whitelist: HashSet::new(),
+            allowances: Allowances::default(),
         }
     }
 }
@@ -763,6 +983,7 @@ impl Ledger {
         now: TimeStamp,
     ) -> Result<(BlockHeight, HashOf<EncodedBlock>), TransferError> {
         self.purge_old_transactions(now);
+        self.allowances.purge_expired(now);
 
         let created_at_time = created_at_time.unwrap_or(now);
 
@@ -798,6 +1019,7 @@ impl Ledger {
                 TransferError::InsufficientFunds { balance }
             }
         })?;
+        self.allowances.apply(&payment);
 
         let height = self
             .blockchain
@@ -874,9 +1096,92 @@ impl Ledger {
         self.balances
             .add_payment(&block.transaction.operation)
             .map_err(|e| format!("failed to execute transfer {:?}: {:?}", block, e))?;
+        self.allowances.apply(&block.transaction.operation);
         self.blockchain.add_block(block)
     }
 
+    /// Adds an `Approve` block to the ledger, after checking that the
+    /// allowance does not expire before `now` and that the current allowance
+    /// is the expected one, if specified.
+    pub fn approve(
+        &mut self,
+        memo: Memo,
+        approve: Operation,
+        created_at_time: Option<TimeStamp>,
+    ) -> Result<(BlockHeight, HashOf<EncodedBlock>), ApproveError> {
+        self.approve_with_timestamp(memo, approve, created_at_time, dfn_core::api::now().into())
+    }
+
+    fn approve_with_timestamp(
+        &mut self,
+        memo: Memo,
+        approve: Operation,
+        created_at_time: Option<TimeStamp>,
+        now: TimeStamp,
+    ) -> Result<(BlockHeight, HashOf<EncodedBlock>), ApproveError> {
+        match &approve {
+            Operation::Approve {
+                from,
+                spender,
+                expected_allowance,
+                expires_at,
+                ..
+            } => {
+                if expires_at.map_or(false, |t| t <= now) {
+                    return Err(ApproveError::Expired { ledger_time: now });
+                }
+                let current_allowance = self.allowances.allowance(from, spender, now).allowance;
+                if expected_allowance.map_or(false, |expected| expected != current_allowance) {
+                    return Err(ApproveError::AllowanceChanged { current_allowance });
+                }
+            }
+            _ => panic!("expected an Approve operation, got {:?}", approve),
+        }
+        Ok(self.add_payment_with_timestamp(memo, approve, created_at_time, now)?)
+    }
+
+    /// Adds a `TransferFrom` block to the ledger, after checking that the
+    /// allowance of the spender covers the amount and the fee.
+    pub fn transfer_from(
+        &mut self,
+        memo: Memo,
+        transfer_from: Operation,
+        created_at_time: Option<TimeStamp>,
+    ) -> Result<(BlockHeight, HashOf<EncodedBlock>), TransferFromError> {
+        self.transfer_from_with_timestamp(
+            memo,
+            transfer_from,
+            created_at_time,
+            dfn_core::api::now().into(),
+        )
+    }
+
+    fn transfer_from_with_timestamp(
+        &mut self,
+        memo: Memo,
+        transfer_from: Operation,
+        created_at_time: Option<TimeStamp>,
+        now: TimeStamp,
+    ) -> Result<(BlockHeight, HashOf<EncodedBlock>), TransferFromError> {
+        match &transfer_from {
+            Operation::TransferFrom {
+                from,
+                spender,
+                amount,
+                fee,
+                ..
+            } => {
+                let allowance = self.allowances.allowance(from, spender, now).allowance;
+                let required = (*amount + *fee).expect("amount + fee failed");
+                if allowance < required {
+                    return Err(TransferFromError::InsufficientAllowance { allowance });
+                }
+            }
+            _ => panic!("expected a TransferFrom operation, got {:?}", transfer_from),
+        }
+        Ok(self.add_payment_with_timestamp(memo, transfer_from, created_at_time, now)?)
+    }
+
     pub fn from_init(
         &mut self,
         initial_values: HashMap<AccountIdentifier, Tokens>,
@@ -1536,6 +1841,56 @@ mod tests {
         }
     }
 
+    #[test]
+    fn hash_tree_follows_blocks_and_archive_index() {
+        let mut blockchain = Blockchain::default();
+        let empty_root_hash = blockchain.root_hash();
+        for i in 0..5 {
+            let block = Block {
+                parent_hash: blockchain.last_hash,
+                transaction: Transaction::new(
+                    PrincipalId::new_user_test_id(0).into(),
+                    PrincipalId::new_user_test_id(1).into(),
+                    Tokens::new(1, 0).unwrap(),
+                    TRANSACTION_FEE,
+                    Memo(i),
+                    TimeStamp::new(1, 0),
+                ),
+                timestamp: TimeStamp::new(1, 0),
+            };
+            blockchain.add_block(block).unwrap();
+        }
+        let root_hash = blockchain.root_hash();
+        assert_ne!(root_hash, empty_root_hash);
+        // A witness reveals a part of the certified tree, so it has the same
+        // root hash.
+        for range in vec![Some((0, 0)), Some((1, 3)), Some((0, 4))] {
+            assert_eq!(blockchain.hash_tree(range).reconstruct(), root_hash);
+        }
+
+        blockchain.remove_archived_blocks(2);
+        blockchain.set_archive_index(&[((0, 1), CanisterId::from_u64(1))]);
+        assert_ne!(blockchain.root_hash(), root_hash);
+        assert_eq!(
+            blockchain.block_hash_tree,
+            BlockHashTree::new(2, &blockchain.blocks)
+        );
+        assert_eq!(
+            blockchain.hash_tree(Some((2, 4))).reconstruct(),
+            blockchain.root_hash()
+        );
+
+        // A ledger upgraded from a version that did not store the block
+        // hashes certifies the same tree.
+        let hashes = std::mem::take(&mut blockchain.block_hashes);
+        blockchain.rebuild_hash_trees();
+        assert_eq!(blockchain.block_hashes, hashes);
+        assert_eq!(
+            blockchain.block_hash_tree,
+            BlockHashTree::new(2, &blockchain.blocks)
+        );
+    }
+
     #[test]
     fn test_purge() {
         let mut ledger = Ledger::default();
@@ -1597,6 +1952,187 @@ mod tests {
         assert_eq!(res6, None);
     }
 
+    fn approvals_ledger(genesis: TimeStamp) -> Ledger {
+        let mut ledger = Ledger::default();
+        ledger.from_init(
+            vec![(
+                PrincipalId::new_user_test_id(0).into(),
+                Tokens::new(10, 0).unwrap(),
+            )]
+            .into_iter()
+            .collect(),
+            PrincipalId::new_user_test_id(1000).into(),
+            genesis,
+            None,
+            HashSet::new(),
+        );
+        ledger
+    }
+
+    #[test]
+    fn approve_and_transfer_from() {
+        let genesis: TimeStamp = SystemTime::UNIX_EPOCH.into();
+        let now = genesis + Duration::from_secs(1);
+        let mut ledger = approvals_ledger(genesis);
+        let owner = PrincipalId::new_user_test_id(0).into();
+        let spender = PrincipalId::new_user_test_id(1).into();
+        let recipient = PrincipalId::new_user_test_id(2).into();
+
+        ledger
+            .approve_with_timestamp(
+                Memo(1),
+                Operation::Approve {
+                    from: owner,
+                    spender,
+                    allowance: Tokens::new(3, 0).unwrap(),
+                    expected_allowance: Some(Tokens::ZERO),
+                    expires_at: None,
+                    fee: TRANSACTION_FEE,
+                },
+                None,
+                now,
+            )
+            .unwrap();
+        assert_eq!(
+            ledger.balances.account_balance(&owner),
+            (Tokens::new(10, 0).unwrap() - TRANSACTION_FEE).unwrap()
+        );
+        assert_eq!(
+            ledger.allowances.allowance(&owner, &spender, now).allowance,
+            Tokens::new(3, 0).unwrap()
+        );
+
+        let transfer_from = |amount| Operation::TransferFrom {
+            from: owner,
+            to: recipient,
+            spender,
+            amount,
+            fee: TRANSACTION_FEE,
+        };
+        ledger
+            .transfer_from_with_timestamp(
+                Memo(2),
+                transfer_from(Tokens::new(2, 0).unwrap()),
+                None,
+                now,
+            )
+            .unwrap();
+        assert_eq!(
+            ledger.balances.account_balance(&recipient),
+            Tokens::new(2, 0).unwrap()
+        );
+        assert_eq!(ledger.balances.account_balance(&spender), Tokens::ZERO);
+        let remaining = Tokens::from_e8s(100_000_000 - TRANSACTION_FEE.get_e8s());
+        assert_eq!(
+            ledger.allowances.allowance(&owner, &spender, now).allowance,
+            remaining
+        );
+
+        assert_eq!(
+            ledger.transfer_from_with_timestamp(Memo(3), transfer_from(remaining), None, now),
+            Err(TransferFromError::InsufficientAllowance {
+                allowance: remaining
+            })
+        );
+
+        // Allowances survive upgrades and are not affected by archiving.
+        let state_bytes = serde_cbor::to_vec(&ledger).unwrap();
+        let mut decoded: Ledger = serde_cbor::from_slice(&state_bytes).unwrap();
+        assert_eq!(decoded.allowances, ledger.allowances);
+        decoded.remove_archived_blocks(decoded.blockchain.blocks.len());
+        assert_eq!(decoded.allowances, ledger.allowances);
+
+        // The blocks are decoded to the same operations.
+        for block in ledger.blockchain.blocks.iter() {
+            let decoded = block.decode().unwrap();
+            assert_eq!(decoded.encode().unwrap(), *block);
+        }
+    }
+
+    #[test]
+    fn approve_checks_expiration_and_expected_allowance() {
+        let genesis: TimeStamp = SystemTime::UNIX_EPOCH.into();
+        let now = genesis + Duration::from_secs(1);
+        let mut ledger = approvals_ledger(genesis);
+        let owner = PrincipalId::new_user_test_id(0).into();
+        let spender = PrincipalId::new_user_test_id(1).into();
+        let approve = |allowance, expected_allowance, expires_at| Operation::Approve {
+            from: owner,
+            spender,
+            allowance,
+            expected_allowance,
+            expires_at,
+            fee: TRANSACTION_FEE,
+        };
+
+        assert_eq!(
+            ledger.approve_with_timestamp(
+                Memo(1),
+                approve(Tokens::new(1, 0).unwrap(), None, Some(now)),
+                None,
+                now,
+            ),
+            Err(ApproveError::Expired { ledger_time: now })
+        );
+
+        let expires_at = now + Duration::from_secs(10);
+        ledger
+            .approve_with_timestamp(
+                Memo(2),
+                approve(Tokens::new(1, 0).unwrap(), None, Some(expires_at)),
+                None,
+                now,
+            )
+            .unwrap();
+        assert_eq!(
+            ledger.approve_with_timestamp(
+                Memo(3),
+                approve(Tokens::new(2, 0).unwrap(), Some(Tokens::ZERO), None),
+                None,
+                now,
+            ),
+            Err(ApproveError::AllowanceChanged {
+                current_allowance: Tokens::new(1, 0).unwrap()
+            })
+        );
+
+        // Once expired, the allowance is zero and can no longer be used.
+        let later = expires_at + Duration::from_secs(1);
+        assert_eq!(
+            ledger
+                .allowances
+                .allowance(&owner, &spender, later)
+                .allowance,
+            Tokens::ZERO
+        );
+        assert_eq!(
+            ledger.transfer_from_with_timestamp(
+                Memo(4),
+                Operation::TransferFrom {
+                    from: owner,
+                    to: spender,
+                    spender,
+                    amount: Tokens::from_e8s(1),
+                    fee: TRANSACTION_FEE,
+                },
+                None,
+                later,
+            ),
+            Err(TransferFromError::InsufficientAllowance {
+                allowance: Tokens::ZERO
+            })
+        );
+        ledger
+            .approve_with_timestamp(
+                Memo(5),
+                approve(Tokens::new(2, 0).unwrap(), Some(Tokens::ZERO), None),
+                None,
+                later,
+            )
+            .unwrap();
+        assert_eq!(ledger.allowances.len(), 1);
+    }
+
     /// Verify consistency of transaction hash after renaming transfer to
     /// operation (see NNS1-765).
     #[test]
@@ -1704,6 +2240,162 @@ impl fmt::Display for TransferError {
     }
 }
 
+/// Argument taken by the approve endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct ApproveArgs {
+    pub memo: Memo,
+    pub from_subaccount: Option<Subaccount>,
+    pub spender: AccountIdBlob,
+    pub allowance: Tokens,
+    pub expected_allowance: Option<Tokens>,
+    pub expires_at: Option<TimeStamp>,
+    pub fee: Tokens,
+    pub created_at_time: Option<TimeStamp>,
+}
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub enum ApproveError {
+    BadFee { expected_fee: Tokens },
+    InsufficientFunds { balance: Tokens },
+    AllowanceChanged { current_allowance: Tokens },
+    Expired { ledger_time: TimeStamp },
+    TxTooOld { allowed_window_nanos: u64 },
+    TxCreatedInFuture,
+    TxDuplicate { duplicate_of: BlockHeight },
+}
+
+impl From<TransferError> for ApproveError {
+    fn from(e: TransferError) -> Self {
+        match e {
+            TransferError::BadFee { expected_fee } => Self::BadFee { expected_fee },
+            TransferError::InsufficientFunds { balance } => Self::InsufficientFunds { balance },
+            TransferError::TxTooOld {
+                allowed_window_nanos,
+            } => Self::TxTooOld {
+                allowed_window_nanos,
+            },
+            TransferError::TxCreatedInFuture => Self::TxCreatedInFuture,
+            TransferError::TxDuplicate { duplicate_of } => Self::TxDuplicate { duplicate_of },
+        }
+    }
+}
+
+impl fmt::Display for ApproveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AllowanceChanged { current_allowance } => write!(
+                f,
+                "the current allowance {} is not the expected one",
+                current_allowance
+            ),
+            Self::Expired { ledger_time } => write!(
+                f,
+                "the allowance expires before the ledger time {}",
+                ledger_time.as_nanos_since_unix_epoch()
+            ),
+            Self::BadFee { expected_fee } => write!(
+                f,
+                "{}",
+                TransferError::BadFee {
+                    expected_fee: *expected_fee,
+                }
+            ),
+            Self::InsufficientFunds { balance } => write!(
+                f,
+                "{}",
+                TransferError::InsufficientFunds { balance: *balance }
+            ),
+            Self::TxTooOld {
+                allowed_window_nanos,
+            } => write!(
+                f,
+                "{}",
+                TransferError::TxTooOld {
+                    allowed_window_nanos: *allowed_window_nanos,
+                }
+            ),
+            Self::TxCreatedInFuture => write!(f, "{}", TransferError::TxCreatedInFuture),
+            Self::TxDuplicate { duplicate_of } => write!(
+                f,
+                "{}",
+                TransferError::TxDuplicate {
+                    duplicate_of: *duplicate_of,
+                }
+            ),
+        }
+    }
+}
+
+/// Argument taken by the transfer_from endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct TransferFromArgs {
+    pub memo: Memo,
+    pub spender_subaccount: Option<Subaccount>,
+    pub from: AccountIdBlob,
+    pub to: AccountIdBlob,
+    pub amount: Tokens,
+    pub fee: Tokens,
+    pub created_at_time: Option<TimeStamp>,
+}
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub enum TransferFromError {
+    BadFee { expected_fee: Tokens },
+    InsufficientFunds { balance: Tokens },
+    InsufficientAllowance { allowance: Tokens },
+    TxTooOld { allowed_window_nanos: u64 },
+    TxCreatedInFuture,
+    TxDuplicate { duplicate_of: BlockHeight },
+}
+
+impl From<TransferError> for TransferFromError {
+    fn from(e: TransferError) -> Self {
+        match e {
+            TransferError::BadFee { expected_fee } => Self::BadFee { expected_fee },
+            TransferError::InsufficientFunds { balance } => Self::InsufficientFunds { balance },
+            TransferError::TxTooOld {
+                allowed_window_nanos,
+            } => Self::TxTooOld {
+                allowed_window_nanos,
+            },
+            TransferError::TxCreatedInFuture => Self::TxCreatedInFuture,
+            TransferError::TxDuplicate { duplicate_of } => Self::TxDuplicate { duplicate_of },
+        }
+    }
+}
+
+impl fmt::Display for TransferFromError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InsufficientAllowance { allowance } => write!(
+                f,
+                "the allowance of the spender doesn't cover the amount and the fee, current allowance: {}",
+                allowance
+            ),
+            Self::BadFee { expected_fee } => write!(f, "{}", TransferError::BadFee {
+                expected_fee: *expected_fee,
+            }),
+            Self::InsufficientFunds { balance } => write!(f, "{}", TransferError::InsufficientFunds { balance: *balance }),
+            Self::TxTooOld {
+                allowed_window_nanos,
+            } => write!(f, "{}", TransferError::TxTooOld {
+                allowed_window_nanos: *allowed_window_nanos,
+            }),
+            Self::TxCreatedInFuture => write!(f, "{}", TransferError::TxCreatedInFuture),
+            Self::TxDuplicate { duplicate_of } => write!(f, "{}", TransferError::TxDuplicate {
+                duplicate_of: *duplicate_of,
+            }),
+        }
+    }
+}
+
+/// Argument taken by the allowance endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct AllowanceArgs {
+    pub account: AccountIdBlob,
+    pub spender: AccountIdBlob,
+}
+
 /// Struct sent by the ledger canister when it notifies a recipient of a payment
 #[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
 pub struct TransactionNotification {
@@ -1781,6 +2473,9 @@ pub struct TotalSupplyArgs {}
 pub struct TipOfChainRes {
     pub certification: Option<Vec<u8>>,
     pub tip_index: BlockHeight,
+    /// The CBOR-encoded hash tree certified by the ledger, revealing the tip
+    /// and the archive index.
+    pub hash_tree: Vec<u8>,
 }
 
 pub struct GetBlocksArgs {
@@ -1796,6 +2491,19 @@ impl GetBlocksArgs {
 
 pub struct GetBlocksRes(pub Result<Vec<EncodedBlock>, String>);
 
+/// Blocks together with a witness that proves them against the data certified
+/// by the canister that served them, see `certification`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertifiedBlocks {
+    pub blocks: Vec<EncodedBlock>,
+    /// The CBOR-encoded certified hash tree, revealing the hashes of the
+    /// blocks.
+    pub hash_tree: Vec<u8>,
+    pub certification: Certification,
+}
+
+pub struct CertifiedBlocksRes(pub Result<CertifiedBlocks, String>);
+
 pub struct IterBlocksArgs {
     pub start: usize,
     pub length: usize,
@@ -1837,6 +2545,27 @@ pub fn get_blocks(
     GetBlocksRes(Ok(blocks[offset..offset + length].to_vec()))
 }
 
+// A helper function for ledger/get_certified_blocks and
+// archive_node/get_certified_blocks endpoints. `hash_tree` returns the
+// certified hash tree revealing the hashes of the blocks in the given range.
+pub fn get_certified_blocks<'a>(
+    blocks: &[EncodedBlock],
+    range_from_offset: BlockHeight,
+    range_from: BlockHeight,
+    length: usize,
+    hash_tree: impl FnOnce(Option<(BlockHeight, BlockHeight)>) -> HashTree<'a>,
+) -> CertifiedBlocksRes {
+    let GetBlocksRes(blocks) = get_blocks(blocks, range_from_offset, range_from, length);
+    CertifiedBlocksRes(blocks.map(|blocks| {
+        let range = Some((range_from, range_from + blocks.len() as u64 - 1));
+        CertifiedBlocks {
+            blocks,
+            hash_tree: certification::encode_hash_tree(&hash_tree(range)),
+            certification: dfn_core::api::data_certificate(),
+        }
+    }))
+}
+
 // A helper function for ledger/iter_blocks and archive_node/iter_blocks
 // endpoints
 pub fn iter_blocks(blocks: &[EncodedBlock], offset: usize, length: usize) -> IterBlocksRes {
@@ -77,15 +77,7 @@ fn init(
             ));
         }
     }
-    set_certified_data(
-        &LEDGER
-            .read()
-            .unwrap()
-            .blockchain
-            .last_hash
-            .map(|h| h.into_bytes())
-            .unwrap_or([0u8; 32]),
-    );
+    certify_blocks(&LEDGER.read().unwrap().blockchain);
 
     if let Some(archive_options) = archive_options {
         LEDGER.write().unwrap().blockchain.archive =
@@ -99,10 +91,15 @@ fn add_payment(
     created_at_time: Option<TimeStamp>,
 ) -> (BlockHeight, HashOf<EncodedBlock>) {
     let (height, hash) = ledger_canister::add_payment(memo, operation, created_at_time);
-    set_certified_data(&hash.into_bytes());
+    certify_blocks(&LEDGER.read().unwrap().blockchain);
     (height, hash)
 }
 
+/// Certifies the blocks of the ledger, see `ledger_canister::certification`.
+fn certify_blocks(blockchain: &Blockchain) {
+    set_certified_data(&blockchain.root_hash());
+}
+
 /// This is the only operation that changes the state of the canister blocks and
 /// balances after init. This creates a payment from the caller's account. It
 /// returns the index of the resulting transaction
@@ -168,11 +165,11 @@ async fn send(
             fee,
         }
     };
-    let (height, hash) = LEDGER
+    let (height, _) = LEDGER
         .write()
         .unwrap()
         .add_payment(memo, transfer, created_at_time)?;
-    set_certified_data(&hash.into_bytes());
+    certify_blocks(&LEDGER.read().unwrap().blockchain);
 
     // Don't put anything that could ever trap after this call or people using this
     // endpoint. If something did panic the payment would appear to fail, but would
@@ -181,6 +178,136 @@ async fn send(
     Ok(height)
 }
 
+/// Sets the amount that `spender` may transfer from the caller's account with
+/// `transfer_from`. It returns the index of the resulting transaction.
+///
+/// # Arguments
+///
+/// * `spender` - The account that may transfer the funds.
+/// * `allowance` - The new allowance, replacing the current one. Zero revokes
+///   the current allowance.
+/// * `expected_allowance` - If set, the approval fails unless the current
+///   allowance is equal to it.
+/// * `expires_at` - When the allowance expires, if ever.
+async fn approve(
+    ApproveArgs {
+        memo,
+        from_subaccount,
+        spender,
+        allowance,
+        expected_allowance,
+        expires_at,
+        fee,
+        created_at_time,
+    }: ApproveArgs,
+) -> Result<BlockHeight, ApproveError> {
+    let caller_principal_id = caller();
+
+    if !LEDGER.read().unwrap().can_send(&caller_principal_id) {
+        panic!("Sending from {} is not allowed", caller_principal_id);
+    }
+
+    let from = AccountIdentifier::new(caller_principal_id, from_subaccount);
+    let spender = AccountIdentifier::from_address(spender).unwrap_or_else(|e| {
+        trap_with(&format!("Invalid account identifier: {}", e));
+        unreachable!()
+    });
+    let minting_acc = LEDGER
+        .read()
+        .unwrap()
+        .minting_account_id
+        .expect("Minting canister id not initialized");
+
+    assert_ne!(from, minting_acc, "The minting account cannot approve");
+    assert_ne!(from, spender, "An account cannot approve itself");
+    if fee != TRANSACTION_FEE {
+        return Err(ApproveError::BadFee {
+            expected_fee: TRANSACTION_FEE,
+        });
+    }
+    let operation = Operation::Approve {
+        from,
+        spender,
+        allowance,
+        expected_allowance,
+        expires_at,
+        fee,
+    };
+    let (height, _) = LEDGER
+        .write()
+        .unwrap()
+        .approve(memo, operation, created_at_time)?;
+    certify_blocks(&LEDGER.read().unwrap().blockchain);
+
+    archive_blocks().await;
+    Ok(height)
+}
+
+/// Transfers funds from an account that approved the caller's account as a
+/// spender. The amount and the fee are deducted from the allowance. It returns
+/// the index of the resulting transaction.
+async fn transfer_from(
+    TransferFromArgs {
+        memo,
+        spender_subaccount,
+        from,
+        to,
+        amount,
+        fee,
+        created_at_time,
+    }: TransferFromArgs,
+) -> Result<BlockHeight, TransferFromError> {
+    let caller_principal_id = caller();
+
+    if !LEDGER.read().unwrap().can_send(&caller_principal_id) {
+        panic!("Sending from {} is not allowed", caller_principal_id);
+    }
+
+    let spender = AccountIdentifier::new(caller_principal_id, spender_subaccount);
+    let parse_account = |address| {
+        AccountIdentifier::from_address(address).unwrap_or_else(|e| {
+            trap_with(&format!("Invalid account identifier: {}", e));
+            unreachable!()
+        })
+    };
+    let from = parse_account(from);
+    let to = parse_account(to);
+    let minting_acc = LEDGER
+        .read()
+        .unwrap()
+        .minting_account_id
+        .expect("Minting canister id not initialized");
+
+    assert_ne!(
+        from, minting_acc,
+        "It is illegal to transfer from the minting account"
+    );
+    assert_ne!(
+        to, minting_acc,
+        "It is illegal to transfer to the minting account"
+    );
+    if fee != TRANSACTION_FEE {
+        return Err(TransferFromError::BadFee {
+            expected_fee: TRANSACTION_FEE,
+        });
+    }
+    let operation = Operation::TransferFrom {
+        from,
+        to,
+        spender,
+        amount,
+        fee,
+    };
+    let (height, _) = LEDGER
+        .write()
+        .unwrap()
+        .transfer_from(memo, operation, created_at_time)?;
+    certify_blocks(&LEDGER.read().unwrap().blockchain);
+
+    archive_blocks().await;
+    Ok(height)
+}
+
 /// You can notify a canister that you have made a payment to it. The
 /// payment must have been made to the account of a canister and from the
 /// callers account. You cannot notify a canister about a transaction it has
@@ -350,17 +477,11 @@ pub async fn notify(
 /// This gives you the index of the last block added to the chain
 /// together with certification
 fn tip_of_chain() -> TipOfChainRes {
-    let last_block_idx = &LEDGER
-        .read()
-        .unwrap()
-        .blockchain
-        .chain_length()
-        .checked_sub(1)
-        .unwrap();
-    let certification = data_certificate();
+    let blockchain = &LEDGER.read().unwrap().blockchain;
     TipOfChainRes {
-        certification,
-        tip_index: *last_block_idx,
+        certification: data_certificate(),
+        tip_index: blockchain.chain_length().checked_sub(1).unwrap(),
+        hash_tree: certification::encode_hash_tree(&blockchain.hash_tree(None)),
     }
 }
 
@@ -419,13 +540,8 @@ fn post_upgrade() {
         *ledger = serde_cbor::from_reader(&mut stable::StableReader::new())
             .expect("Decoding stable memory failed");
 
-        set_certified_data(
-            &ledger
-                .blockchain
-                .last_hash
-                .map(|h| h.into_bytes())
-                .unwrap_or([0u8; 32]),
-        );
+        ledger.blockchain.rebuild_hash_trees();
+        certify_blocks(&ledger.blockchain);
     })
 }
 
@@ -495,6 +611,8 @@ async fn archive_blocks() {
             ));
         }
     }
+    ledger.blockchain.set_archive_index(&archive.index());
+    certify_blocks(&ledger.blockchain);
 }
 
 /// Canister endpoints
@@ -582,6 +700,26 @@ fn transfer() {
     over_async(candid_one, transfer_candid)
 }
 
+#[candid_method(update, rename = "approve")]
+async fn approve_candid(arg: ApproveArgs) -> Result<BlockHeight, ApproveError> {
+    approve(arg).await
+}
+
+#[export_name = "canister_update approve"]
+fn approve_() {
+    over_async(candid_one, approve_candid)
+}
+
+#[candid_method(update, rename = "transfer_from")]
+async fn transfer_from_candid(arg: TransferFromArgs) -> Result<BlockHeight, TransferFromError> {
+    transfer_from(arg).await
+}
+
+#[export_name = "canister_update transfer_from"]
+fn transfer_from_() {
+    over_async(candid_one, transfer_from_candid)
+}
+
 /// See caveats of use on send_dfx
 #[export_name = "canister_update notify_dfx"]
 fn notify_dfx_() {
@@ -666,6 +804,26 @@ fn account_balance_candid() {
     over(candid_one, account_balance_candid_)
 }
 
+#[candid_method(query, rename = "allowance")]
+fn allowance_candid_(arg: AllowanceArgs) -> Allowance {
+    let parse_account = |address| {
+        AccountIdentifier::from_address(address).unwrap_or_else(|e| {
+            trap_with(&format!("Invalid account identifier: {}", e));
+            unreachable!()
+        })
+    };
+    LEDGER.read().unwrap().allowances.allowance(
+        &parse_account(arg.account),
+        &parse_account(arg.spender),
+        dfn_core::api::now().into(),
+    )
+}
+
+#[export_name = "canister_query allowance"]
+fn allowance_candid() {
+    over(candid_one, allowance_candid_)
+}
+
 #[candid_method(query, rename = "account_balance_dfx")]
 fn account_balance_dfx_(args: AccountBalanceArgs) -> Tokens {
     account_balance(args.account)
@@ -706,6 +864,24 @@ fn get_blocks_() {
     });
 }
 
+/// Get multiple blocks by BlockHeight and length together with a witness that
+/// proves them against the data certified by the ledger, see
+/// `ledger_canister::certification`.
+#[export_name = "canister_query get_certified_blocks_pb"]
+fn get_certified_blocks_() {
+    over(protobuf, |GetBlocksArgs { start, length }| {
+        let blockchain: &Blockchain = &LEDGER.read().unwrap().blockchain;
+        let start_offset = blockchain.num_archived_blocks();
+        ledger_canister::get_certified_blocks(
+            &blockchain.blocks,
+            start_offset,
+            start,
+            length,
+            |range| blockchain.hash_tree(range),
+        )
+    });
+}
+
 #[export_name = "canister_query get_nodes"]
 fn get_nodes_() {
     over(candid, |()| -> Vec<CanisterId> {
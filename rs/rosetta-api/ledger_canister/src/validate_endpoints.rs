@@ -1,10 +1,11 @@
 use crate::protobuf;
 use crate::protobuf::transaction::Transfer as PTransfer;
 use crate::{
-    AccountBalanceArgs, AccountIdentifier, Block, BlockArg, BlockRes, CyclesResponse, EncodedBlock,
-    GetBlocksArgs, GetBlocksRes, HashOf, IterBlocksArgs, IterBlocksRes, Memo, NotifyCanisterArgs,
-    Operation, SendArgs, Subaccount, TimeStamp, TipOfChainRes, Tokens, TotalSupplyArgs,
-    Transaction, TransactionNotification, HASH_LENGTH, TRANSACTION_FEE,
+    AccountBalanceArgs, AccountIdentifier, Block, BlockArg, BlockRes, CertifiedBlocks,
+    CertifiedBlocksRes, CyclesResponse, EncodedBlock, GetBlocksArgs, GetBlocksRes, HashOf,
+    IterBlocksArgs, IterBlocksRes, Memo, NotifyCanisterArgs, Operation, SendArgs, Subaccount,
+    TimeStamp, TipOfChainRes, Tokens, TotalSupplyArgs, Transaction, TransactionNotification,
+    HASH_LENGTH, TRANSACTION_FEE,
 };
 use dfn_protobuf::ToProto;
 use ic_base_types::{CanisterId, CanisterIdError};
@@ -66,6 +67,7 @@ impl ToProto for TipOfChainRes {
         Ok(TipOfChainRes {
             certification: pb.certification.map(|pb| pb.certification),
             tip_index: chain_length,
+            hash_tree: pb.hash_tree,
         })
     }
 
@@ -77,6 +79,7 @@ impl ToProto for TipOfChainRes {
             chain_length: Some(protobuf::BlockHeight {
                 height: self.tip_index,
             }),
+            hash_tree: self.hash_tree,
         }
     }
 }
@@ -186,6 +189,57 @@ impl ToProto for GetBlocksRes {
     }
 }
 
+impl ToProto for CertifiedBlocksRes {
+    type Proto = protobuf::GetCertifiedBlocksResponse;
+
+    fn from_proto(pb: Self::Proto) -> Result<Self, String> {
+        use protobuf::get_certified_blocks_response::GetCertifiedBlocksContent;
+        match pb
+            .get_certified_blocks_content
+            .ok_or("get_certified_blocks() response with no content")?
+        {
+            GetCertifiedBlocksContent::Blocks(protobuf::CertifiedBlocks {
+                blocks,
+                hash_tree,
+                certification,
+            }) => Ok(CertifiedBlocksRes(Ok(CertifiedBlocks {
+                blocks: blocks
+                    .into_iter()
+                    .map(|protobuf::EncodedBlock { block }| EncodedBlock(block.into_boxed_slice()))
+                    .collect(),
+                hash_tree,
+                certification: certification.map(|pb| pb.certification),
+            }))),
+            GetCertifiedBlocksContent::Error(error) => Ok(CertifiedBlocksRes(Err(error))),
+        }
+    }
+
+    fn into_proto(self) -> Self::Proto {
+        use protobuf::get_certified_blocks_response::GetCertifiedBlocksContent;
+        let content = match self.0 {
+            Ok(CertifiedBlocks {
+                blocks,
+                hash_tree,
+                certification,
+            }) => GetCertifiedBlocksContent::Blocks(protobuf::CertifiedBlocks {
+                blocks: blocks
+                    .into_iter()
+                    .map(|b| protobuf::EncodedBlock {
+                        block: b.0.into_vec(),
+                    })
+                    .collect(),
+                hash_tree,
+                certification: certification
+                    .map(|certification| protobuf::Certification { certification }),
+            }),
+            Err(err) => GetCertifiedBlocksContent::Error(err),
+        };
+        protobuf::GetCertifiedBlocksResponse {
+            get_certified_blocks_content: Some(content),
+        }
+    }
+}
+
 impl ToProto for IterBlocksArgs {
     type Proto = protobuf::IterBlocksRequest;
 
@@ -571,6 +625,34 @@ impl ToProto for Transaction {
                     None => TRANSACTION_FEE,
                 },
             },
+            PTransfer::Approve(protobuf::Approve {
+                from: Some(from),
+                spender: Some(spender),
+                allowance: Some(allowance),
+                fee: Some(fee),
+                expires_at,
+                expected_allowance,
+            }) => Operation::Approve {
+                from: AccountIdentifier::from_proto(from)?,
+                spender: AccountIdentifier::from_proto(spender)?,
+                allowance: Tokens::from_proto(allowance)?,
+                expected_allowance: expected_allowance.map(Tokens::from_proto).transpose()?,
+                expires_at,
+                fee: Tokens::from_proto(fee)?,
+            },
+            PTransfer::TransferFrom(protobuf::TransferFrom {
+                from: Some(from),
+                to: Some(to),
+                spender: Some(spender),
+                amount: Some(amount),
+                fee: Some(fee),
+            }) => Operation::TransferFrom {
+                from: AccountIdentifier::from_proto(from)?,
+                to: AccountIdentifier::from_proto(to)?,
+                spender: AccountIdentifier::from_proto(spender)?,
+                amount: Tokens::from_proto(amount)?,
+                fee: Tokens::from_proto(fee)?,
+            },
             t => return Err(format!("Transaction lacked a required field: {:?}", t)),
         };
         Ok(Transaction {
@@ -608,6 +690,36 @@ impl ToProto for Transaction {
                 from: Some(from.into_proto()),
                 max_fee: Some(fee.into_proto()),
             }),
+
+            Operation::Approve {
+                from,
+                spender,
+                allowance,
+                expected_allowance,
+                expires_at,
+                fee,
+            } => PTransfer::Approve(protobuf::Approve {
+                from: Some(from.into_proto()),
+                spender: Some(spender.into_proto()),
+                allowance: Some(allowance.into_proto()),
+                fee: Some(fee.into_proto()),
+                expires_at,
+                expected_allowance: expected_allowance.map(Tokens::into_proto),
+            }),
+
+            Operation::TransferFrom {
+                from,
+                to,
+                spender,
+                amount,
+                fee,
+            } => PTransfer::TransferFrom(protobuf::TransferFrom {
+                from: Some(from.into_proto()),
+                to: Some(to.into_proto()),
+                spender: Some(spender.into_proto()),
+                amount: Some(amount.into_proto()),
+                fee: Some(fee.into_proto()),
+            }),
         };
         protobuf::Transaction {
             memo: Some(protobuf::Memo { memo: memo.0 }),
@@ -4,10 +4,11 @@ use dfn_protobuf::protobuf;
 use ic_canister_client::Sender;
 use ic_types::{CanisterId, PrincipalId};
 use ledger_canister::{
-    AccountBalanceArgs, AccountIdentifier, ArchiveOptions, BinaryAccountBalanceArgs, Block,
-    BlockArg, BlockHeight, BlockRes, EncodedBlock, GetBlocksArgs, GetBlocksRes, IterBlocksArgs,
-    IterBlocksRes, LedgerCanisterInitPayload, Memo, NotifyCanisterArgs, Operation, SendArgs,
-    Subaccount, TimeStamp, Tokens, TotalSupplyArgs, Transaction, TransferArgs, TransferError,
+    protobuf::TipOfChainRequest, AccountBalanceArgs, AccountIdentifier, ArchiveOptions,
+    BinaryAccountBalanceArgs, Block, BlockArg, BlockHeight, BlockRes, CertifiedBlocksRes,
+    EncodedBlock, GetBlocksArgs, GetBlocksRes, IterBlocksArgs, IterBlocksRes,
+    LedgerCanisterInitPayload, Memo, NotifyCanisterArgs, Operation, SendArgs, Subaccount,
+    TimeStamp, TipOfChainRes, Tokens, TotalSupplyArgs, Transaction, TransferArgs, TransferError,
     MIN_BURN_AMOUNT, TRANSACTION_FEE,
 };
 use on_wire::IntoWire;
@@ -1300,6 +1301,86 @@ fn get_multiple_blocks_test() {
     })
 }
 
+#[test]
+fn get_certified_blocks_test() {
+    local_test_e(|r| async move {
+        let proj = Project::new(env!("CARGO_MANIFEST_DIR"));
+
+        let minting_account = create_sender(0);
+        let num_blocks = 6u64;
+        let accounts = make_accounts(num_blocks, 1);
+
+        let max_message_size_bytes: usize = 1024 * 1024;
+        let archive_options = Some(ArchiveOptions {
+            trigger_threshold: num_blocks as usize,
+            num_blocks_to_archive: 4,
+            node_max_memory_size_bytes: None,
+            max_message_size_bytes: Some(max_message_size_bytes),
+            controller_id: CanisterId::from_u64(876),
+        });
+
+        let ledger: canister_test::Canister = {
+            let payload = LedgerCanisterInitPayload::new(
+                CanisterId::try_from(minting_account.get_principal_id())
+                    .unwrap()
+                    .into(),
+                accounts,
+                archive_options,
+                Some(max_message_size_bytes),
+                None,
+                HashSet::new(),
+            );
+            let mut install = proj.cargo_bin("ledger-canister").install(&r);
+            install.memory_allocation = Some(128 * 1024 * 1024);
+            install.bytes(CandidOne(payload).into_bytes()?).await?
+        };
+
+        // Archive blocks [0 .. 3], blocks [4 .. 6] stay in the ledger
+        simple_send(&ledger, &create_sender(12345), &minting_account, 100, 0).await?;
+        ledger_assert_num_blocks(&ledger, 3).await;
+        let nodes = ledger_assert_num_nodes(&ledger, 1).await;
+        let node = Canister::new(&r, nodes[0]);
+
+        for (canister, start, length) in vec![(&node, 0u64, 4usize), (&node, 1, 2), (&ledger, 4, 3)]
+        {
+            let GetBlocksRes(blocks) = canister
+                .query_("get_blocks_pb", protobuf, GetBlocksArgs::new(start, length))
+                .await?;
+            let CertifiedBlocksRes(certified_blocks) = canister
+                .query_(
+                    "get_certified_blocks_pb",
+                    protobuf,
+                    GetBlocksArgs::new(start, length),
+                )
+                .await?;
+            let certified_blocks = certified_blocks.unwrap();
+            assert_eq!(certified_blocks.blocks, blocks.unwrap());
+            assert!(!certified_blocks.hash_tree.is_empty());
+        }
+
+        // Blocks outside the range stored in the canister are not served
+        for (canister, start, length) in vec![(&node, 3u64, 2usize), (&ledger, 3, 2)] {
+            let CertifiedBlocksRes(certified_blocks) = canister
+                .query_(
+                    "get_certified_blocks_pb",
+                    protobuf,
+                    GetBlocksArgs::new(start, length),
+                )
+                .await?;
+            assert!(certified_blocks.is_err());
+        }
+
+        // The tip comes with the hash tree that certifies it
+        let tip: TipOfChainRes = ledger
+            .query_("tip_of_chain_pb", protobuf, TipOfChainRequest {})
+            .await?;
+        assert_eq!(tip.tip_index, num_blocks);
+        assert!(!tip.hash_tree.is_empty());
+
+        Ok(())
+    })
+}
+
 #[test]
 fn only_ledger_can_append_blocks_to_archive_nodes() {
     local_test_e(|r| async move {
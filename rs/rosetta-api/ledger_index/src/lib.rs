@@ -0,0 +1,385 @@
+//! The index of the ledger: for every account, the heights of the blocks whose
+//! transaction involves the account. It lets clients get the transaction
+//! history of an account without scanning the whole chain.
+use candid::CandidType;
+use ic_types::CanisterId;
+use ledger_canister::{AccountIdBlob, AccountIdentifier, BlockHeight, EncodedBlock, Operation};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The maximum number of block heights returned by a single
+/// `get_account_transactions` call.
+pub const MAX_RESULTS: usize = 1000;
+
+/// The size of an encoded entry of the index, see [encode_entry].
+pub const ENTRY_SIZE: usize = 36;
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct IndexCanisterInitPayload {
+    pub ledger_canister_id: CanisterId,
+}
+
+/// Argument taken by the get_account_transactions endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct GetAccountTransactionsArgs {
+    pub account: AccountIdBlob,
+    /// The largest block height to return. If not set, the most recent
+    /// transactions are returned.
+    pub start: Option<BlockHeight>,
+    /// The maximum number of block heights to return, capped at
+    /// `MAX_RESULTS`.
+    pub max_results: u64,
+}
+
+/// Result of the get_account_transactions endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, Default, PartialEq, Eq)]
+pub struct GetAccountTransactionsResult {
+    /// The heights of the blocks that involve the account, most recent
+    /// first.
+    pub block_heights: Vec<BlockHeight>,
+    /// The `start` of the next page if there are older transactions.
+    pub next_start: Option<BlockHeight>,
+}
+
+/// Result of the status endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct IndexStatus {
+    pub ledger_canister_id: CanisterId,
+    /// The number of blocks that have been indexed, i.e. the height of the
+    /// next block to index.
+    pub num_blocks_synced: BlockHeight,
+    pub num_accounts: u64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Index {
+    pub ledger_canister_id: CanisterId,
+    num_blocks_synced: BlockHeight,
+    /// The heights of the blocks that involve each account, in ascending
+    /// order.
+    accounts: BTreeMap<AccountIdentifier, Vec<BlockHeight>>,
+}
+
+impl Index {
+    pub fn new(ledger_canister_id: CanisterId) -> Self {
+        Self {
+            ledger_canister_id,
+            num_blocks_synced: 0,
+            accounts: BTreeMap::new(),
+        }
+    }
+
+    /// Rebuilds an index from the entries that [Index::add_block] returned
+    /// for the first `num_blocks_synced` blocks, in order.
+    pub fn from_entries(
+        ledger_canister_id: CanisterId,
+        num_blocks_synced: BlockHeight,
+        entries: impl Iterator<Item = (AccountIdentifier, BlockHeight)>,
+    ) -> Self {
+        let mut index = Self::new(ledger_canister_id);
+        for (account, height) in entries {
+            index.accounts.entry(account).or_default().push(height);
+        }
+        index.num_blocks_synced = num_blocks_synced;
+        index
+    }
+
+    pub fn num_blocks_synced(&self) -> BlockHeight {
+        self.num_blocks_synced
+    }
+
+    /// Indexes `block`, which must be the block at height
+    /// `num_blocks_synced`. Returns the accounts that the block was indexed
+    /// under.
+    pub fn add_block(&mut self, block: &EncodedBlock) -> Result<Vec<AccountIdentifier>, String> {
+        let block = block.decode()?;
+        let height = self.num_blocks_synced;
+        let mut indexed = vec![];
+        for account in operation_accounts(&block.transaction.operation) {
+            let heights = self.accounts.entry(account).or_default();
+            // An account can appear more than once in an operation.
+            if heights.last() != Some(&height) {
+                heights.push(height);
+                indexed.push(account);
+            }
+        }
+        self.num_blocks_synced += 1;
+        Ok(indexed)
+    }
+
+    /// Returns up to `max_results` heights of blocks that involve `account`
+    /// and are not higher than `start`, most recent first.
+    pub fn get_account_transactions(
+        &self,
+        account: &AccountIdentifier,
+        start: Option<BlockHeight>,
+        max_results: usize,
+    ) -> GetAccountTransactionsResult {
+        let heights = match self.accounts.get(account) {
+            Some(heights) => heights,
+            None => return GetAccountTransactionsResult::default(),
+        };
+        let end = match start {
+            Some(start) => heights.partition_point(|height| *height <= start),
+            None => heights.len(),
+        };
+        let begin = end.saturating_sub(max_results.min(MAX_RESULTS));
+        GetAccountTransactionsResult {
+            block_heights: heights[begin..end].iter().rev().cloned().collect(),
+            next_start: begin.checked_sub(1).map(|i| heights[i]),
+        }
+    }
+
+    pub fn status(&self) -> IndexStatus {
+        IndexStatus {
+            ledger_canister_id: self.ledger_canister_id,
+            num_blocks_synced: self.num_blocks_synced,
+            num_accounts: self.accounts.len() as u64,
+        }
+    }
+}
+
+/// Encodes the entry of the index stating that the block at `height`
+/// involves `account`: the hash of the account followed by the height in
+/// little endian.
+pub fn encode_entry(account: &AccountIdentifier, height: BlockHeight) -> [u8; ENTRY_SIZE] {
+    let mut bytes = [0; ENTRY_SIZE];
+    bytes[..28].copy_from_slice(&account.hash);
+    bytes[28..].copy_from_slice(&height.to_le_bytes());
+    bytes
+}
+
+/// Decodes an entry encoded with [encode_entry].
+pub fn decode_entry(bytes: &[u8]) -> (AccountIdentifier, BlockHeight) {
+    assert_eq!(bytes.len(), ENTRY_SIZE, "invalid index entry");
+    let account = AccountIdentifier {
+        hash: bytes[..28].try_into().unwrap(),
+    };
+    let height = BlockHeight::from_le_bytes(bytes[28..].try_into().unwrap());
+    (account, height)
+}
+
+/// Returns the accounts that are involved in `operation`.
+fn operation_accounts(operation: &Operation) -> Vec<AccountIdentifier> {
+    match operation {
+        Operation::Burn { from, .. } => vec![*from],
+        Operation::Mint { to, .. } => vec![*to],
+        Operation::Transfer { from, to, .. } => vec![*from, *to],
+        Operation::Approve { from, spender, .. } => vec![*from, *spender],
+        Operation::TransferFrom {
+            from, to, spender, ..
+        } => vec![*from, *to, *spender],
+    }
+}
+
+/// Marks a synchronization of the index as in progress for as long as it is
+/// alive, so that synchronizations don't overlap.
+///
+/// The guard must be owned by the future of the synchronization. If a
+/// callback of the synchronization traps, its state changes are rolled back,
+/// but the future is dropped by the cleanup callback of the call, which
+/// releases the guard.
+pub struct SyncGuard {
+    in_progress: &'static AtomicBool,
+}
+
+impl SyncGuard {
+    /// Returns an error if a synchronization is already in progress.
+    pub fn acquire(in_progress: &'static AtomicBool) -> Result<Self, String> {
+        if in_progress.swap(true, Ordering::SeqCst) {
+            return Err("A synchronization is already in progress".to_string());
+        }
+        Ok(Self { in_progress })
+    }
+}
+
+impl Drop for SyncGuard {
+    fn drop(&mut self) {
+        self.in_progress.store(false, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+    use ic_types::PrincipalId;
+    use ledger_canister::{Block, Memo, TimeStamp, Tokens, Transaction, TRANSACTION_FEE};
+
+    fn account(n: u64) -> AccountIdentifier {
+        PrincipalId::new_user_test_id(n).into()
+    }
+
+    fn encoded_block(operation: Operation) -> EncodedBlock {
+        Block::new_from_transaction(
+            None,
+            Transaction {
+                operation,
+                memo: Memo::default(),
+                created_at_time: TimeStamp::new(1, 0),
+            },
+            TimeStamp::new(1, 0),
+        )
+        .encode()
+        .unwrap()
+    }
+
+    fn index_with_transfers(num_transfers: u64) -> Index {
+        let mut index = Index::new(CanisterId::from_u64(1));
+        index
+            .add_block(&encoded_block(Operation::Mint {
+                to: account(0),
+                amount: Tokens::new(1000, 0).unwrap(),
+            }))
+            .unwrap();
+        for i in 0..num_transfers {
+            index
+                .add_block(&encoded_block(Operation::Transfer {
+                    from: account(0),
+                    to: account(1 + i % 2),
+                    amount: Tokens::new(1, 0).unwrap(),
+                    fee: TRANSACTION_FEE,
+                }))
+                .unwrap();
+        }
+        index
+    }
+
+    #[test]
+    fn index_accounts() {
+        let index = index_with_transfers(4);
+        assert_eq!(index.num_blocks_synced(), 5);
+        assert_eq!(index.status().num_accounts, 3);
+        assert_eq!(
+            index.get_account_transactions(&account(0), None, 10),
+            GetAccountTransactionsResult {
+                block_heights: vec![4, 3, 2, 1, 0],
+                next_start: None,
+            }
+        );
+        assert_eq!(
+            index.get_account_transactions(&account(2), None, 10),
+            GetAccountTransactionsResult {
+                block_heights: vec![4, 2],
+                next_start: None,
+            }
+        );
+        assert_eq!(
+            index.get_account_transactions(&account(3), None, 10),
+            GetAccountTransactionsResult::default()
+        );
+    }
+
+    #[test]
+    fn self_transfer_is_indexed_once() {
+        let mut index = Index::new(CanisterId::from_u64(1));
+        index
+            .add_block(&encoded_block(Operation::Transfer {
+                from: account(0),
+                to: account(0),
+                amount: Tokens::ZERO,
+                fee: TRANSACTION_FEE,
+            }))
+            .unwrap();
+        assert_eq!(
+            index
+                .get_account_transactions(&account(0), None, 10)
+                .block_heights,
+            vec![0]
+        );
+    }
+
+    #[test]
+    fn paginate_account_transactions() {
+        let index = index_with_transfers(10);
+        let mut start = None;
+        let mut pages = vec![];
+        loop {
+            let page = index.get_account_transactions(&account(0), start, 4);
+            pages.push(page.block_heights);
+            match page.next_start {
+                Some(next_start) => start = Some(next_start),
+                None => break,
+            }
+        }
+        assert_eq!(
+            pages,
+            vec![vec![10, 9, 8, 7], vec![6, 5, 4, 3], vec![2, 1, 0]]
+        );
+
+        assert_eq!(
+            index.get_account_transactions(&account(1), Some(6), 2),
+            GetAccountTransactionsResult {
+                block_heights: vec![5, 3],
+                next_start: Some(1),
+            }
+        );
+    }
+
+    #[test]
+    fn rebuild_index_from_entries() {
+        let mut index = Index::new(CanisterId::from_u64(1));
+        let mut entries = vec![];
+        for operation in vec![
+            Operation::Mint {
+                to: account(0),
+                amount: Tokens::new(1000, 0).unwrap(),
+            },
+            Operation::Transfer {
+                from: account(0),
+                to: account(0),
+                amount: Tokens::ZERO,
+                fee: TRANSACTION_FEE,
+            },
+            Operation::Transfer {
+                from: account(0),
+                to: account(1),
+                amount: Tokens::new(1, 0).unwrap(),
+                fee: TRANSACTION_FEE,
+            },
+        ] {
+            let height = index.num_blocks_synced();
+            for account in index.add_block(&encoded_block(operation)).unwrap() {
+                entries.extend_from_slice(&encode_entry(&account, height));
+            }
+        }
+        assert_eq!(entries.len(), 4 * ENTRY_SIZE);
+
+        let rebuilt = Index::from_entries(
+            CanisterId::from_u64(1),
+            index.num_blocks_synced(),
+            entries.chunks(ENTRY_SIZE).map(decode_entry),
+        );
+        assert_eq!(rebuilt, index);
+    }
+
+    #[test]
+    fn sync_guard_prevents_overlapping_synchronizations() {
+        static IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+        let guard = SyncGuard::acquire(&IN_PROGRESS).unwrap();
+        assert!(SyncGuard::acquire(&IN_PROGRESS).is_err());
+        drop(guard);
+        assert!(SyncGuard::acquire(&IN_PROGRESS).is_ok());
+    }
+
+    #[test]
+    fn sync_guard_is_released_when_a_callback_traps() {
+        static IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+        // A synchronization waiting for the reply of a call.
+        let mut sync = Box::pin(async {
+            let _guard = SyncGuard::acquire(&IN_PROGRESS)?;
+            futures::future::pending::<()>().await;
+            Ok::<(), String>(())
+        });
+        assert!((&mut sync).now_or_never().is_none());
+        assert!(IN_PROGRESS.load(Ordering::SeqCst));
+
+        // When the reply callback traps, the cleanup callback of the call
+        // drops the future of the synchronization.
+        drop(sync);
+        assert!(!IN_PROGRESS.load(Ordering::SeqCst));
+        assert!(SyncGuard::acquire(&IN_PROGRESS).is_ok());
+    }
+}
@@ -0,0 +1,329 @@
+use candid::candid_method;
+use dfn_candid::{candid_one, CandidOne};
+use dfn_core::{
+    api::{call_with_cleanup, now, trap_with},
+    over, over_async, over_init, stable, BytesS,
+};
+use dfn_protobuf::protobuf;
+use ic_types::{CanisterId, PrincipalId};
+use ledger_canister::{
+    protobuf::{ArchiveIndexResponse, TipOfChainRequest},
+    AccountIdentifier, BlockHeight, GetBlocksArgs, GetBlocksRes, TipOfChainRes,
+};
+use ledger_index::{
+    decode_entry, encode_entry, GetAccountTransactionsArgs, GetAccountTransactionsResult, Index,
+    IndexCanisterInitPayload, IndexStatus, SyncGuard, ENTRY_SIZE,
+};
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::RwLock;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// The maximum number of blocks requested from the ledger or an archive node
+/// in a single call.
+const MAX_BLOCKS_PER_REQUEST: u64 = 2000;
+
+/// The maximum number of blocks indexed by a single synchronization.
+const MAX_BLOCKS_PER_SYNC: u64 = 10 * MAX_BLOCKS_PER_REQUEST;
+
+/// How often the heartbeat synchronizes the index with the ledger.
+const SYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The entries of the index are appended to stable memory as blocks are
+/// indexed, so upgrades don't need to serialize the index. The stable memory
+/// starts with a header:
+///
+/// 0          8           16       17                   HEADER_SIZE
+/// +----------+-----------+--------+--------------------+---------->
+/// | # blocks | # entries | id len | ledger canister id | entries
+/// +----------+-----------+--------+--------------------+---------->
+const NUM_BLOCKS_OFFSET: u64 = 0;
+const NUM_ENTRIES_OFFSET: u64 = 8;
+const LEDGER_ID_OFFSET: u64 = 16;
+const HEADER_SIZE: u64 = 64;
+
+/// The number of entries read from stable memory at once in post_upgrade.
+const ENTRIES_PER_READ: u64 = 10_000;
+
+const WASM_PAGE_SIZE: u64 = 64 * 1024;
+
+lazy_static::lazy_static! {
+    static ref INDEX: RwLock<Index> =
+        RwLock::new(Index::new(ic_nns_constants::LEDGER_CANISTER_ID));
+}
+
+/// Whether a synchronization is waiting for replies from the ledger or its
+/// archives. Synchronizations must not overlap, as blocks have to be indexed
+/// in order. Only set through `SyncGuard`.
+static SYNC_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+
+/// When the last synchronization started, in nanoseconds since the Unix
+/// epoch.
+static LAST_SYNC_NANOS: AtomicU64 = AtomicU64::new(0);
+
+// Helper to print messages in green
+fn print<S: std::convert::AsRef<str>>(s: S)
+where
+    yansi::Paint<S>: std::string::ToString,
+{
+    dfn_core::api::print(yansi::Paint::green(s).to_string());
+}
+
+#[candid_method(init)]
+fn canister_init(arg: IndexCanisterInitPayload) {
+    print(format!(
+        "[index] init(): indexing the ledger {}",
+        arg.ledger_canister_id
+    ));
+    let ledger_canister_id = arg.ledger_canister_id.get().into_vec();
+    write_stable(LEDGER_ID_OFFSET, &[ledger_canister_id.len() as u8]);
+    write_stable(LEDGER_ID_OFFSET + 1, &ledger_canister_id);
+    write_stable_u64(NUM_BLOCKS_OFFSET, 0);
+    write_stable_u64(NUM_ENTRIES_OFFSET, 0);
+    *INDEX.write().unwrap() = Index::new(arg.ledger_canister_id);
+}
+
+#[export_name = "canister_init"]
+fn main() {
+    over_init(|CandidOne(arg)| canister_init(arg))
+}
+
+#[export_name = "canister_post_upgrade"]
+fn post_upgrade() {
+    over_init(|_: BytesS| {
+        let mut len = [0; 1];
+        stable::stable64_read(&mut len, LEDGER_ID_OFFSET, 1);
+        let mut ledger_canister_id = vec![0; len[0] as usize];
+        stable::stable64_read(&mut ledger_canister_id, LEDGER_ID_OFFSET + 1, len[0] as u64);
+        let ledger_canister_id = PrincipalId::try_from(ledger_canister_id.as_slice())
+            .ok()
+            .and_then(|pid| CanisterId::try_from(pid).ok())
+            .expect("Decoding the ledger canister id from stable memory failed");
+
+        let num_entries = read_stable_u64(NUM_ENTRIES_OFFSET);
+        let entries = (0..num_entries)
+            .step_by(ENTRIES_PER_READ as usize)
+            .flat_map(|first| {
+                let count = ENTRIES_PER_READ.min(num_entries - first);
+                let mut bytes = vec![0; count as usize * ENTRY_SIZE];
+                stable::stable64_read(&mut bytes, entry_offset(first), bytes.len() as u64);
+                bytes
+                    .chunks(ENTRY_SIZE)
+                    .map(decode_entry)
+                    .collect::<Vec<_>>()
+            });
+        *INDEX.write().unwrap() = Index::from_entries(
+            ledger_canister_id,
+            read_stable_u64(NUM_BLOCKS_OFFSET),
+            entries,
+        );
+    })
+}
+
+/// Returns the offset of the entry with the given index in stable memory.
+fn entry_offset(entry_index: u64) -> u64 {
+    HEADER_SIZE + entry_index * ENTRY_SIZE as u64
+}
+
+/// Writes `bytes` to stable memory at `offset`, growing it if needed.
+fn write_stable(offset: u64, bytes: &[u8]) {
+    let end = offset + bytes.len() as u64;
+    let size = stable::stable64_size() * WASM_PAGE_SIZE;
+    if end > size {
+        let additional_pages = (end - size + WASM_PAGE_SIZE - 1) / WASM_PAGE_SIZE;
+        if stable::stable64_grow(additional_pages) < 0 {
+            trap_with("Failed to grow stable memory");
+        }
+    }
+    stable::stable64_write(offset, bytes);
+}
+
+fn write_stable_u64(offset: u64, value: u64) {
+    write_stable(offset, &value.to_le_bytes());
+}
+
+fn read_stable_u64(offset: u64) -> u64 {
+    let mut bytes = [0; 8];
+    stable::stable64_read(&mut bytes, offset, 8);
+    u64::from_le_bytes(bytes)
+}
+
+/// Appends the entries of the block at `height` to stable memory and records
+/// that the block has been indexed.
+fn log_block(height: BlockHeight, accounts: &[AccountIdentifier]) {
+    let num_entries = read_stable_u64(NUM_ENTRIES_OFFSET);
+    let mut bytes = Vec::with_capacity(accounts.len() * ENTRY_SIZE);
+    for account in accounts {
+        bytes.extend_from_slice(&encode_entry(account, height));
+    }
+    write_stable(entry_offset(num_entries), &bytes);
+    write_stable_u64(NUM_ENTRIES_OFFSET, num_entries + accounts.len() as u64);
+    write_stable_u64(NUM_BLOCKS_OFFSET, height + 1);
+}
+
+/// Indexes the blocks that were added to the ledger since the last
+/// synchronization, fetching them from the ledger or from the archive nodes
+/// that store them. Returns the number of indexed blocks.
+async fn sync_blocks() -> Result<BlockHeight, String> {
+    let _guard = SyncGuard::acquire(&SYNC_IN_PROGRESS)?;
+    LAST_SYNC_NANOS.store(now_nanos(), Ordering::SeqCst);
+    fetch_and_index_blocks().await
+}
+
+async fn fetch_and_index_blocks() -> Result<BlockHeight, String> {
+    let ledger_canister_id = INDEX.read().unwrap().ledger_canister_id;
+    let TipOfChainRes { tip_index, .. } = call_with_cleanup(
+        ledger_canister_id,
+        "tip_of_chain_pb",
+        protobuf,
+        TipOfChainRequest {},
+    )
+    .await
+    .map_err(|e| format!("Failed to get the tip of the chain: {:?}", e))?;
+    let archive_index: ArchiveIndexResponse =
+        call_with_cleanup(ledger_canister_id, "get_archive_index_pb", protobuf, ())
+            .await
+            .map_err(|e| format!("Failed to get the archive index: {:?}", e))?;
+
+    let first_height = INDEX.read().unwrap().num_blocks_synced();
+    loop {
+        let start = INDEX.read().unwrap().num_blocks_synced();
+        if start > tip_index || start - first_height >= MAX_BLOCKS_PER_SYNC {
+            return Ok(start);
+        }
+
+        // The blocks that are not in an archive node are in the ledger.
+        let (canister_id, last_height) = match archive_index
+            .entries
+            .iter()
+            .find(|entry| entry.height_from <= start && start <= entry.height_to)
+        {
+            Some(entry) => {
+                let canister_id = entry
+                    .canister_id
+                    .and_then(|pid| CanisterId::try_from(pid).ok())
+                    .ok_or_else(|| format!("Invalid archive index entry {:?}", entry))?;
+                (canister_id, entry.height_to)
+            }
+            None => (ledger_canister_id, tip_index),
+        };
+        let length = (last_height - start + 1).min(MAX_BLOCKS_PER_REQUEST);
+        let GetBlocksRes(blocks) = call_with_cleanup(
+            canister_id,
+            "get_blocks_pb",
+            protobuf,
+            GetBlocksArgs::new(start, length as usize),
+        )
+        .await
+        .map_err(|e| format!("Failed to get blocks from {}: {:?}", canister_id, e))?;
+        let blocks = blocks?;
+        // Asking again would return the same empty response, so the
+        // synchronization would never finish.
+        if blocks.is_empty() {
+            return Err(format!(
+                "{} returned no blocks starting at height {}",
+                canister_id, start
+            ));
+        }
+
+        let mut index = INDEX.write().unwrap();
+        for block in blocks.iter() {
+            let height = index.num_blocks_synced();
+            let accounts = index.add_block(block)?;
+            log_block(height, &accounts);
+        }
+    }
+}
+
+fn now_nanos() -> u64 {
+    now()
+        .duration_since(UNIX_EPOCH)
+        .expect("now before the Unix epoch")
+        .as_nanos() as u64
+}
+
+#[candid_method(update, rename = "sync")]
+async fn sync_candid() -> Result<BlockHeight, String> {
+    sync_blocks().await
+}
+
+/// Synchronizes the index with the ledger. The heartbeat does so
+/// periodically, this lets clients wait until a block is indexed.
+#[export_name = "canister_update sync"]
+fn sync() {
+    over_async(candid_one, |()| sync_candid())
+}
+
+#[export_name = "canister_heartbeat"]
+fn heartbeat() {
+    let last_sync = UNIX_EPOCH + Duration::from_nanos(LAST_SYNC_NANOS.load(Ordering::SeqCst));
+    if SYNC_IN_PROGRESS.load(Ordering::SeqCst) || now() < last_sync + SYNC_INTERVAL {
+        return;
+    }
+    // canister_heartbeat must be synchronous, so we cannot .await the future
+    dfn_core::api::futures::spawn(async {
+        if let Err(e) = sync_blocks().await {
+            print(format!("[index] heartbeat: synchronization failed: {}", e));
+        }
+    });
+}
+
+#[candid_method(query, rename = "get_account_transactions")]
+fn get_account_transactions_(arg: GetAccountTransactionsArgs) -> GetAccountTransactionsResult {
+    let account = AccountIdentifier::from_address(arg.account).unwrap_or_else(|e| {
+        trap_with(&format!("Invalid account identifier: {}", e));
+        unreachable!()
+    });
+    INDEX.read().unwrap().get_account_transactions(
+        &account,
+        arg.start,
+        usize::try_from(arg.max_results).unwrap_or(usize::MAX),
+    )
+}
+
+#[export_name = "canister_query get_account_transactions"]
+fn get_account_transactions() {
+    over(candid_one, get_account_transactions_)
+}
+
+#[candid_method(query, rename = "status")]
+fn status_() -> IndexStatus {
+    INDEX.read().unwrap().status()
+}
+
+#[export_name = "canister_query status"]
+fn status() {
+    over(candid_one, |()| status_())
+}
+
+#[test]
+fn check_candid_interface_compatibility() {
+    use candid::types::subtype::{subtype, Gamma};
+    use candid::types::Type;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    candid::export_service!();
+
+    let actual_interface = __export_service();
+    println!("Generated DID:\n {}", actual_interface);
+    let mut tmp = tempfile::NamedTempFile::new().expect("failed to create a temporary file");
+    write!(tmp, "{}", actual_interface).expect("failed to write interface to a temporary file");
+    let (mut env1, t1) =
+        candid::pretty_check_file(tmp.path()).expect("failed to check generated candid file");
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("index.did");
+    let (env2, t2) =
+        candid::pretty_check_file(path.as_path()).expect("failed to open index.did file");
+
+    let (t1_ref, t2) = match (t1.as_ref().unwrap(), t2.unwrap()) {
+        (Type::Class(_, s1), Type::Class(_, s2)) => (s1.as_ref(), *s2),
+        (Type::Class(_, s1), s2 @ Type::Service(_)) => (s1.as_ref(), s2),
+        (s1 @ Type::Service(_), Type::Class(_, s2)) => (s1, *s2),
+        (t1, t2) => (t1, t2),
+    };
+
+    let mut gamma = Gamma::new();
+    let t2 = env1.merge_type(env2, t2);
+    subtype(&mut gamma, &env1, t1_ref, &t2)
+        .expect("index canister interface is not compatible with the index.did file");
+}
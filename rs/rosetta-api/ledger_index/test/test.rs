@@ -0,0 +1,222 @@
+use canister_test::*;
+use dfn_candid::{candid_one, CandidOne};
+use dfn_protobuf::protobuf;
+use ic_canister_client::Sender;
+use ic_types::CanisterId;
+use ledger_canister::{
+    AccountIdentifier, ArchiveOptions, Block, BlockHeight, LedgerCanisterInitPayload, Memo,
+    SendArgs, TimeStamp, Tokens, Transaction, TRANSACTION_FEE,
+};
+use ledger_index::{
+    GetAccountTransactionsArgs, GetAccountTransactionsResult, IndexCanisterInitPayload, IndexStatus,
+};
+use on_wire::IntoWire;
+use std::collections::{HashMap, HashSet};
+
+fn create_sender(i: u64) -> Sender {
+    use rand_chacha::ChaChaRng;
+    use rand_core::SeedableRng;
+    let keypair = {
+        let mut rng = ChaChaRng::seed_from_u64(i);
+        ed25519_dalek::Keypair::generate(&mut rng)
+    };
+    Sender::from_keypair(&keypair)
+}
+
+// So we can get the size of EncodedBlock
+fn example_block() -> Block {
+    let transaction = Transaction::new(
+        AccountIdentifier::new(CanisterId::from_u64(1).get(), None),
+        AccountIdentifier::new(CanisterId::from_u64(2).get(), None),
+        Tokens::new(10000, 50).unwrap(),
+        TRANSACTION_FEE,
+        Memo(456),
+        TimeStamp::new(2_000_000_000, 123_456_789),
+    );
+    Block::new_from_transaction(None, transaction, TimeStamp::new(1, 1))
+}
+
+fn account(sender: &Sender) -> AccountIdentifier {
+    sender.get_principal_id().into()
+}
+
+async fn simple_send(
+    ledger: &Canister<'_>,
+    to: &Sender,
+    from: &Sender,
+    amount_e8s: u64,
+) -> Result<BlockHeight, String> {
+    ledger
+        .update_from_sender(
+            "send_pb",
+            protobuf,
+            SendArgs {
+                memo: Memo::default(),
+                amount: Tokens::from_e8s(amount_e8s),
+                fee: TRANSACTION_FEE,
+                from_subaccount: None,
+                to: account(to),
+                created_at_time: None,
+            },
+            from,
+        )
+        .await
+}
+
+/// Installs a ledger that archives blocks in archive nodes of two blocks each,
+/// so that the index has to fetch most blocks from the archive nodes.
+async fn install_ledger<'a>(
+    r: &'a Runtime,
+    minting_account: &Sender,
+    initial_values: HashMap<AccountIdentifier, Tokens>,
+) -> Result<Canister<'a>, String> {
+    let max_message_size_bytes = 192;
+    let archive_options = Some(ArchiveOptions {
+        trigger_threshold: 8,
+        num_blocks_to_archive: 4,
+        node_max_memory_size_bytes: Some(example_block().encode().unwrap().size_bytes() * 2),
+        max_message_size_bytes: Some(max_message_size_bytes),
+        controller_id: CanisterId::from_u64(876),
+    });
+    let payload = LedgerCanisterInitPayload {
+        minting_account: account(minting_account),
+        initial_values,
+        max_message_size_bytes: Some(max_message_size_bytes),
+        transaction_window: None,
+        archive_options,
+        send_whitelist: HashSet::new(),
+    };
+    let mut install = Project::cargo_bin_maybe_use_path_relative_to_rs(
+        "rosetta-api/ledger_canister",
+        "ledger-canister",
+    )
+    .install(r);
+    install.memory_allocation = Some(128 * 1024 * 1024);
+    install.bytes(CandidOne(payload).into_bytes()?).await
+}
+
+/// Calls `sync` until the index has indexed `num_blocks` blocks.
+async fn sync_until(index: &Canister<'_>, num_blocks: u64) -> Result<(), String> {
+    for _ in 0..10 {
+        // The heartbeat may be synchronizing the index concurrently, in which
+        // case `sync` fails.
+        let result: Result<BlockHeight, String> = index.update_("sync", candid_one, ()).await?;
+        if result == Ok(num_blocks) {
+            return Ok(());
+        }
+    }
+    Err(format!("The index did not index {} blocks", num_blocks))
+}
+
+async fn get_account_transactions(
+    index: &Canister<'_>,
+    account: &AccountIdentifier,
+    start: Option<BlockHeight>,
+    max_results: u64,
+) -> Result<GetAccountTransactionsResult, String> {
+    index
+        .query_(
+            "get_account_transactions",
+            candid_one,
+            GetAccountTransactionsArgs {
+                account: account.to_address(),
+                start,
+                max_results,
+            },
+        )
+        .await
+}
+
+#[test]
+fn index_ledger_and_archives_test() {
+    local_test_e(|r| async move {
+        let minting_account = create_sender(0);
+        let senders: Vec<Sender> = (1..=3).map(create_sender).collect();
+
+        // Block 0 mints tokens for the first sender, who then sends tokens to
+        // the second and third sender in turn.
+        let mut initial_values = HashMap::new();
+        initial_values.insert(account(&senders[0]), Tokens::new(1000, 0).unwrap());
+        let ledger = install_ledger(&r, &minting_account, initial_values).await?;
+        let num_sends = 15;
+        for i in 0..num_sends {
+            let height =
+                simple_send(&ledger, &senders[1 + i as usize % 2], &senders[0], 100).await?;
+            assert_eq!(height, i + 1);
+        }
+        let num_blocks = num_sends + 1;
+
+        let nodes: Vec<CanisterId> = ledger.query_("get_nodes", dfn_candid::candid, ()).await?;
+        assert!(!nodes.is_empty(), "expected blocks to be archived");
+
+        let mut index = Project::new(env!("CARGO_MANIFEST_DIR"))
+            .cargo_bin("ledger-index-canister")
+            .install_(
+                &r,
+                CandidOne(IndexCanisterInitPayload {
+                    ledger_canister_id: ledger.canister_id(),
+                }),
+            )
+            .await?;
+        sync_until(&index, num_blocks).await?;
+
+        let expected_status = IndexStatus {
+            ledger_canister_id: ledger.canister_id(),
+            num_blocks_synced: num_blocks,
+            num_accounts: 3,
+        };
+        let status: IndexStatus = index.query_("status", candid_one, ()).await?;
+        assert_eq!(status, expected_status);
+
+        let expected_heights = |filter: fn(u64) -> bool| -> Vec<BlockHeight> {
+            (0..num_blocks).rev().filter(|h| filter(*h)).collect()
+        };
+        for (sender, expected) in senders.iter().zip(vec![
+            expected_heights(|_| true),
+            expected_heights(|h| h % 2 == 1),
+            expected_heights(|h| h > 0 && h % 2 == 0),
+        ]) {
+            let result = get_account_transactions(&index, &account(sender), None, 100).await?;
+            assert_eq!(result.block_heights, expected);
+            assert_eq!(result.next_start, None);
+        }
+
+        // Page through the transactions of the first sender.
+        let mut pages = vec![];
+        let mut start = None;
+        loop {
+            let page = get_account_transactions(&index, &account(&senders[0]), start, 6).await?;
+            pages.push(page.block_heights);
+            match page.next_start {
+                Some(next_start) => start = Some(next_start),
+                None => break,
+            }
+        }
+        assert_eq!(
+            pages,
+            vec![
+                (10..16).rev().collect::<Vec<_>>(),
+                (4..10).rev().collect(),
+                (0..4).rev().collect(),
+            ]
+        );
+
+        // The index survives upgrades and keeps following the ledger.
+        index.upgrade_to_self_binary(Vec::new()).await?;
+        let status: IndexStatus = index.query_("status", candid_one, ()).await?;
+        assert_eq!(status, expected_status);
+
+        simple_send(&ledger, &senders[2], &senders[1], 100).await?;
+        sync_until(&index, num_blocks + 1).await?;
+        let result = get_account_transactions(&index, &account(&senders[2]), None, 1).await?;
+        assert_eq!(
+            result,
+            GetAccountTransactionsResult {
+                block_heights: vec![num_blocks],
+                next_start: Some(num_blocks - 2),
+            }
+        );
+
+        Ok(())
+    })
+}
@@ -1,33 +1,35 @@
-use ic_crypto_tree_hash::{Digest, LabeledTree, MixedHashTree};
+use ic_crypto_tree_hash::{lookup_path, Digest, LabeledTree, MixedHashTree};
 use ic_crypto_utils_threshold_sig::verify_combined;
 use ic_types::{
     consensus::certification::CertificationContent,
     crypto::{threshold_sig::ThresholdSigPublicKey, CombinedThresholdSigOf, CryptoHash},
-    CanisterId, CryptoHashOfPartialState, Time,
+    CanisterId, CryptoHashOfPartialState, PrincipalId, Time,
+};
+use ledger_canister::{
+    certification::{ARCHIVES_LABEL, BLOCKS_LABEL, TIP_LABEL},
+    protobuf::{ArchiveIndexEntry, ArchiveIndexResponse},
+    BlockHeight, CertifiedBlocks, EncodedBlock, HashOf, TipOfChainRes,
 };
-use ledger_canister::{EncodedBlock, HashOf};
 use serde::Deserialize;
 use std::collections::BTreeMap;
-use std::convert::TryFrom;
+use std::convert::{TryFrom, TryInto};
 
 use tree_deserializer::{types::Leb128EncodedU64, LabeledTreeDeserializer};
 
+/// Verifies that the ledger certifies the hash tree of `tip` and that the tip
+/// it reveals has hash `hash`.
 pub(crate) fn verify_block_hash(
-    cert: &ledger_canister::Certification,
+    tip: &TipOfChainRes,
     hash: HashOf<EncodedBlock>,
     root_key: &Option<ThresholdSigPublicKey>,
     canister_id: &CanisterId,
 ) -> Result<(), String> {
     match root_key {
         Some(root_key) => {
-            let (from_cert, _) = check_certificate(
-                canister_id,
-                root_key,
-                cert.as_ref()
-                    .ok_or("verify tip failed: no data certificate present")?,
-            )
-            .map_err(|e| format!("Certification error: {:?}", e))?;
-            if from_cert.as_bytes() != hash.into_bytes() {
+            let tree = verify_hash_tree(&tip.certification, &tip.hash_tree, root_key, canister_id)?;
+            let mut expected = tip.tip_index.to_be_bytes().to_vec();
+            expected.extend_from_slice(&hash.into_bytes());
+            if lookup_leaf(&tree, &[TIP_LABEL])? != expected.as_slice() {
                 Err("verify block hash failed".to_string())
             } else {
                 Ok(())
@@ -37,6 +39,113 @@ pub(crate) fn verify_block_hash(
     }
 }
 
+/// Verifies that `canister_id`, the ledger or one of its archive nodes,
+/// certifies the hash tree of `blocks` and that the tree contains the hashes of
+/// the blocks, the first of which has height `start`.
+pub(crate) fn verify_blocks(
+    blocks: &CertifiedBlocks,
+    start: BlockHeight,
+    root_key: &ThresholdSigPublicKey,
+    canister_id: &CanisterId,
+) -> Result<(), String> {
+    let tree = verify_hash_tree(
+        &blocks.certification,
+        &blocks.hash_tree,
+        root_key,
+        canister_id,
+    )?;
+    for (height, block) in (start..).zip(&blocks.blocks) {
+        let certified_hash = lookup_leaf(&tree, &[BLOCKS_LABEL, &height.to_be_bytes()])?;
+        if certified_hash != &block.hash().into_bytes()[..] {
+            return Err(format!(
+                "the hash of block {} does not match the certified hash",
+                height
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the archive index revealed by the hash tree of `tip`, after
+/// verifying that the ledger certifies it.
+pub(crate) fn verify_archive_index(
+    tip: &TipOfChainRes,
+    root_key: &ThresholdSigPublicKey,
+    canister_id: &CanisterId,
+) -> Result<ArchiveIndexResponse, String> {
+    let tree = verify_hash_tree(&tip.certification, &tip.hash_tree, root_key, canister_id)?;
+    let archives = match lookup_path(&tree, &[ARCHIVES_LABEL]) {
+        Some(LabeledTree::SubTree(archives)) => archives,
+        _ => return Err("the certified hash tree does not contain the archive index".to_string()),
+    };
+    let mut entries = archives
+        .iter()
+        .map(|(label, range)| {
+            let canister_id = PrincipalId::try_from(label.as_bytes())
+                .map_err(|err| format!("invalid archive node canister id: {}", err))?;
+            match range {
+                LabeledTree::Leaf(range) if range.len() == 16 => Ok(ArchiveIndexEntry {
+                    height_from: u64::from_be_bytes(range[..8].try_into().unwrap()),
+                    height_to: u64::from_be_bytes(range[8..].try_into().unwrap()),
+                    canister_id: Some(canister_id),
+                }),
+                _ => Err(format!(
+                    "invalid range of blocks of archive node {}",
+                    canister_id
+                )),
+            }
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    entries.sort_by_key(|entry| entry.height_from);
+    Ok(ArchiveIndexResponse { entries })
+}
+
+/// Verifies that `hash_tree` is the CBOR encoding of a tree whose root hash
+/// `canister_id` certified and returns it as a labeled tree.
+fn verify_hash_tree(
+    certification: &ledger_canister::Certification,
+    hash_tree: &[u8],
+    root_key: &ThresholdSigPublicKey,
+    canister_id: &CanisterId,
+) -> Result<LabeledTree<Vec<u8>>, String> {
+    let (certified, _) = check_certificate(
+        canister_id,
+        root_key,
+        certification
+            .as_ref()
+            .ok_or("no data certificate present")?,
+    )
+    .map_err(|e| format!("Certification error: {:?}", e))?;
+    let tree: MixedHashTree = serde_cbor::from_slice(hash_tree)
+        .map_err(|err| format!("failed to decode hash tree: {}", err))?;
+    let computed = tree.digest();
+    if computed != certified {
+        return Err(format!(
+            "Certification error: {:?}",
+            CertificationError::CertifiedDataMismatch {
+                certified,
+                computed
+            }
+        ));
+    }
+    LabeledTree::try_from(tree).map_err(|err| {
+        format!(
+            "Certification error: {:?}",
+            CertificationError::MalformedHashTree(format!("{:?}", err))
+        )
+    })
+}
+
+fn lookup_leaf<'a>(tree: &'a LabeledTree<Vec<u8>>, path: &[&[u8]]) -> Result<&'a [u8], String> {
+    match lookup_path(tree, path) {
+        Some(LabeledTree::Leaf(value)) => Ok(value),
+        _ => Err(format!(
+            "the certified hash tree does not contain the leaf /{}",
+            path.iter().map(hex::encode).collect::<Vec<_>>().join("/")
+        )),
+    }
+}
+
 #[derive(Debug)]
 pub enum CertificationError {
     /// Failed to deserialize some part of the response.
@@ -131,3 +240,156 @@ fn check_certificate(
             ))
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_crypto_internal_threshold_sig_bls12381::{api as bls12_381, types::SecretKeyBytes};
+    use ic_interfaces::crypto::Signable;
+    use ic_types::{
+        messages::{Blob, Certificate},
+        NumberOfNodes, Randomness,
+    };
+    use ledger_canister::{
+        certification::{archive_node_hash_tree, encode_hash_tree, BlockHashTree},
+        Block, Blockchain, Memo, TimeStamp, Tokens, Transaction, TRANSACTION_FEE,
+    };
+
+    fn key_pair() -> (ThresholdSigPublicKey, SecretKeyBytes) {
+        let (public_coefficients, mut secret_keys) =
+            bls12_381::keygen(Randomness::from([1; 32]), NumberOfNodes::from(1), &[true]).unwrap();
+        let public_key = bls12_381::combined_public_key(&public_coefficients).unwrap();
+        (
+            ThresholdSigPublicKey::from(public_key),
+            secret_keys.remove(0).unwrap(),
+        )
+    }
+
+    /// Returns a certificate of the data certified by `canister_id`.
+    fn certify(
+        canister_id: &CanisterId,
+        certified_data: &[u8],
+        secret_key: &SecretKeyBytes,
+    ) -> Option<Vec<u8>> {
+        let tree = MixedHashTree::Fork(Box::new((
+            MixedHashTree::Labeled(
+                "canister".into(),
+                Box::new(MixedHashTree::Labeled(
+                    canister_id.get().into_vec().into(),
+                    Box::new(MixedHashTree::Labeled(
+                        "certified_data".into(),
+                        Box::new(MixedHashTree::Leaf(certified_data.to_vec())),
+                    )),
+                )),
+            ),
+            MixedHashTree::Labeled("time".into(), Box::new(MixedHashTree::Leaf(vec![1]))),
+        )));
+        let digest = CryptoHashOfPartialState::from(CryptoHash(tree.digest().to_vec()));
+        let content = CertificationContent::new(digest);
+        let signature = bls12_381::sign_message(&content.as_signed_bytes(), secret_key).unwrap();
+        let signature =
+            bls12_381::combine_signatures(&[Some(signature)], NumberOfNodes::from(1)).unwrap();
+        let certificate = Certificate {
+            tree,
+            signature: Blob(signature.0.to_vec()),
+            delegation: None,
+        };
+        Some(serde_cbor::to_vec(&certificate).unwrap())
+    }
+
+    fn blockchain(num_blocks: u64) -> Blockchain {
+        let mut blockchain = Blockchain::default();
+        for i in 0..num_blocks {
+            let transaction = Transaction::new(
+                CanisterId::from_u64(1).get().into(),
+                CanisterId::from_u64(2).get().into(),
+                Tokens::from_e8s(1),
+                TRANSACTION_FEE,
+                Memo(i),
+                TimeStamp::new(1, 0),
+            );
+            let block = Block::new_from_transaction(
+                blockchain.last_hash,
+                transaction,
+                TimeStamp::new(1, 0),
+            );
+            blockchain.add_block(block).unwrap();
+        }
+        blockchain
+    }
+
+    #[test]
+    fn verifies_blocks_certified_by_the_ledger() {
+        let (public_key, secret_key) = key_pair();
+        let ledger_id = CanisterId::from_u64(10);
+        let blockchain = blockchain(5);
+        let certified_blocks = CertifiedBlocks {
+            blocks: blockchain.blocks[1..4].to_vec(),
+            hash_tree: encode_hash_tree(&blockchain.hash_tree(Some((1, 3)))),
+            certification: certify(&ledger_id, &blockchain.root_hash(), &secret_key),
+        };
+        assert_eq!(
+            verify_blocks(&certified_blocks, 1, &public_key, &ledger_id),
+            Ok(())
+        );
+        assert!(verify_blocks(&certified_blocks, 2, &public_key, &ledger_id).is_err());
+        assert!(
+            verify_blocks(&certified_blocks, 1, &public_key, &CanisterId::from_u64(11)).is_err()
+        );
+
+        let mut tampered_blocks = certified_blocks;
+        tampered_blocks.blocks.swap(0, 1);
+        assert!(verify_blocks(&tampered_blocks, 1, &public_key, &ledger_id).is_err());
+    }
+
+    #[test]
+    fn verifies_tip_and_archived_blocks() {
+        let (public_key, secret_key) = key_pair();
+        let ledger_id = CanisterId::from_u64(10);
+        let archive_id = CanisterId::from_u64(11);
+        let mut blockchain = blockchain(5);
+        let archived_blocks = blockchain.blocks[..2].to_vec();
+        blockchain.remove_archived_blocks(2);
+        blockchain.set_archive_index(&[((0, 1), archive_id)]);
+
+        let tip = TipOfChainRes {
+            certification: certify(&ledger_id, &blockchain.root_hash(), &secret_key),
+            tip_index: 4,
+            hash_tree: encode_hash_tree(&blockchain.hash_tree(None)),
+        };
+        let root_key = Some(public_key);
+        assert_eq!(
+            verify_block_hash(&tip, blockchain.last_hash.unwrap(), &root_key, &ledger_id),
+            Ok(())
+        );
+        assert!(
+            verify_block_hash(&tip, blockchain.blocks[0].hash(), &root_key, &ledger_id).is_err()
+        );
+        assert_eq!(
+            verify_archive_index(&tip, &public_key, &ledger_id),
+            Ok(ArchiveIndexResponse {
+                entries: vec![ArchiveIndexEntry {
+                    height_from: 0,
+                    height_to: 1,
+                    canister_id: Some(archive_id.get()),
+                }]
+            })
+        );
+
+        let archive_hash_tree = BlockHashTree::new(0, &archived_blocks);
+        let certified_blocks = CertifiedBlocks {
+            blocks: archived_blocks.clone(),
+            hash_tree: encode_hash_tree(&archive_node_hash_tree(&archive_hash_tree, Some((0, 1)))),
+            certification: certify(
+                &archive_id,
+                &archive_node_hash_tree(&archive_hash_tree, None).reconstruct(),
+                &secret_key,
+            ),
+        };
+        assert_eq!(
+            verify_blocks(&certified_blocks, 0, &public_key, &archive_id),
+            Ok(())
+        );
+        assert!(verify_blocks(&certified_blocks, 0, &public_key, &ledger_id).is_err());
+    }
+}
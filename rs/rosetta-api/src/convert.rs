@@ -6,10 +6,14 @@ use crate::models::{
 };
 use crate::request_types::DISBURSE;
 use crate::request_types::{
-    AddHotKey, Disburse, DisburseMetadata, KeyMetadata, NeuronIdentifierMetadata,
-    PublicKeyOrPrincipal, Request, SetDissolveTimestamp, SetDissolveTimestampMetadata, Stake,
-    StartDissolve, StopDissolve, ADD_HOT_KEY, BURN, FEE, MINT, SET_DISSOLVE_TIMESTAMP, STAKE,
-    START_DISSOLVE, STATUS_COMPLETED, STOP_DISSOLVE, TRANSACTION,
+    AddHotKey, ApproveMetadata, Disburse, DisburseMetadata, DisburseToNeuron,
+    DisburseToNeuronMetadata, Follow, FollowMetadata, KeyMetadata, MergeMaturity,
+    MergeMaturityMetadata, NeuronIdentifierMetadata, PublicKeyOrPrincipal, RegisterVote,
+    RegisterVoteMetadata, RemoveHotKey, Request, SetDissolveTimestamp,
+    SetDissolveTimestampMetadata, Spawn, SpawnMetadata, SpenderMetadata, Split, Stake,
+    StartDissolve, StopDissolve, ADD_HOT_KEY, APPROVE, BURN, DISBURSE_TO_NEURON, FEE, FOLLOW,
+    MERGE_MATURITY, MINT, REGISTER_VOTE, REMOVE_HOT_KEY, SET_DISSOLVE_TIMESTAMP, SPAWN, SPLIT,
+    STAKE, START_DISSOLVE, STATUS_COMPLETED, STOP_DISSOLVE, TRANSACTION, TRANSFER_FROM,
 };
 use crate::store::HashedBlock;
 use crate::time::Seconds;
@@ -157,6 +161,87 @@ pub fn requests_to_operations(requests: &[Request]) -> Result<Vec<Operation>, Ap
                     metadata: None,
                 });
             }
+            Request::Transfer(LedgerOperation::Approve {
+                from,
+                spender,
+                allowance,
+                expected_allowance,
+                expires_at,
+                fee,
+            }) => {
+                let from_account = Some(to_model_account_identifier(from));
+
+                ops.push(Operation {
+                    operation_identifier: allocate_op_id(),
+                    _type: APPROVE.to_string(),
+                    status: None,
+                    account: from_account.clone(),
+                    amount: None,
+                    related_operations: None,
+                    coin_change: None,
+                    metadata: Some(
+                        ApproveMetadata {
+                            spender: *spender,
+                            allowance: *allowance,
+                            expected_allowance: *expected_allowance,
+                            expires_at: *expires_at,
+                        }
+                        .into(),
+                    ),
+                });
+                ops.push(Operation {
+                    operation_identifier: allocate_op_id(),
+                    _type: FEE.to_string(),
+                    status: None,
+                    account: from_account,
+                    amount: Some(signed_amount(-(fee.get_e8s() as i128))),
+                    related_operations: None,
+                    coin_change: None,
+                    metadata: None,
+                });
+            }
+            Request::Transfer(LedgerOperation::TransferFrom {
+                from,
+                to,
+                spender,
+                amount,
+                fee,
+            }) => {
+                let from_account = Some(to_model_account_identifier(from));
+                let amount = i128::from(amount.get_e8s());
+                let metadata: Map<String, Value> = SpenderMetadata { spender: *spender }.into();
+
+                ops.push(Operation {
+                    operation_identifier: allocate_op_id(),
+                    _type: TRANSFER_FROM.to_string(),
+                    status: None,
+                    account: from_account.clone(),
+                    amount: Some(signed_amount(-amount)),
+                    related_operations: None,
+                    coin_change: None,
+                    metadata: Some(metadata.clone()),
+                });
+                ops.push(Operation {
+                    operation_identifier: allocate_op_id(),
+                    _type: TRANSFER_FROM.to_string(),
+                    status: None,
+                    account: Some(to_model_account_identifier(to)),
+                    amount: Some(signed_amount(amount)),
+                    related_operations: None,
+                    coin_change: None,
+                    metadata: Some(metadata),
+                });
+                ops.push(Operation {
+                    operation_identifier: allocate_op_id(),
+                    _type: FEE.to_string(),
+                    status: None,
+                    account: from_account,
+                    amount: Some(signed_amount(-(fee.get_e8s() as i128))),
+                    related_operations: None,
+                    coin_change: None,
+                    metadata: None,
+                });
+            }
             Request::Stake(Stake {
                 account,
                 neuron_identifier,
@@ -284,19 +369,187 @@ pub fn requests_to_operations(requests: &[Request]) -> Result<Vec<Operation>, Ap
                     ),
                 });
             }
+            Request::RemoveHotKey(RemoveHotKey {
+                account,
+                neuron_identifier,
+                key,
+            }) => {
+                ops.push(Operation {
+                    operation_identifier: allocate_op_id(),
+                    _type: REMOVE_HOT_KEY.to_string(),
+                    status: None,
+                    account: Some(to_model_account_identifier(account)),
+                    amount: None,
+                    related_operations: None,
+                    coin_change: None,
+                    metadata: Some(
+                        KeyMetadata {
+                            key: key.clone(),
+                            neuron_identifier: *neuron_identifier,
+                        }
+                        .into(),
+                    ),
+                });
+            }
+            Request::Spawn(Spawn {
+                account,
+                neuron_identifier,
+                controller,
+            }) => {
+                ops.push(Operation {
+                    operation_identifier: allocate_op_id(),
+                    _type: SPAWN.to_string(),
+                    status: None,
+                    account: Some(to_model_account_identifier(account)),
+                    amount: None,
+                    related_operations: None,
+                    coin_change: None,
+                    metadata: Some(
+                        SpawnMetadata {
+                            neuron_identifier: *neuron_identifier,
+                            controller: *controller,
+                        }
+                        .into(),
+                    ),
+                });
+            }
+            Request::MergeMaturity(MergeMaturity {
+                account,
+                neuron_identifier,
+                percentage_to_merge,
+            }) => {
+                ops.push(Operation {
+                    operation_identifier: allocate_op_id(),
+                    _type: MERGE_MATURITY.to_string(),
+                    status: None,
+                    account: Some(to_model_account_identifier(account)),
+                    amount: None,
+                    related_operations: None,
+                    coin_change: None,
+                    metadata: Some(
+                        MergeMaturityMetadata {
+                            neuron_identifier: *neuron_identifier,
+                            percentage_to_merge: *percentage_to_merge,
+                        }
+                        .into(),
+                    ),
+                });
+            }
+            Request::Follow(Follow {
+                account,
+                neuron_identifier,
+                topic,
+                followees,
+            }) => {
+                ops.push(Operation {
+                    operation_identifier: allocate_op_id(),
+                    _type: FOLLOW.to_string(),
+                    status: None,
+                    account: Some(to_model_account_identifier(account)),
+                    amount: None,
+                    related_operations: None,
+                    coin_change: None,
+                    metadata: Some(
+                        FollowMetadata {
+                            neuron_identifier: *neuron_identifier,
+                            topic: *topic,
+                            followees: followees.clone(),
+                        }
+                        .into(),
+                    ),
+                });
+            }
+            Request::RegisterVote(RegisterVote {
+                account,
+                neuron_identifier,
+                proposal,
+                vote,
+            }) => {
+                ops.push(Operation {
+                    operation_identifier: allocate_op_id(),
+                    _type: REGISTER_VOTE.to_string(),
+                    status: None,
+                    account: Some(to_model_account_identifier(account)),
+                    amount: None,
+                    related_operations: None,
+                    coin_change: None,
+                    metadata: Some(
+                        RegisterVoteMetadata {
+                            neuron_identifier: *neuron_identifier,
+                            proposal: *proposal,
+                            vote: *vote,
+                        }
+                        .into(),
+                    ),
+                });
+            }
+            Request::Split(Split {
+                account,
+                neuron_identifier,
+                amount,
+            }) => {
+                ops.push(Operation {
+                    operation_identifier: allocate_op_id(),
+                    _type: SPLIT.to_string(),
+                    status: None,
+                    account: Some(to_model_account_identifier(account)),
+                    amount: Some(amount_(*amount).expect("amount_ never fails")),
+                    related_operations: None,
+                    coin_change: None,
+                    metadata: Some(
+                        NeuronIdentifierMetadata {
+                            neuron_identifier: *neuron_identifier,
+                        }
+                        .into(),
+                    ),
+                });
+            }
+            Request::DisburseToNeuron(DisburseToNeuron {
+                account,
+                neuron_identifier,
+                amount,
+                controller,
+                dissolve_delay_seconds,
+                kyc_verified,
+                nonce,
+            }) => {
+                ops.push(Operation {
+                    operation_identifier: allocate_op_id(),
+                    _type: DISBURSE_TO_NEURON.to_string(),
+                    status: None,
+                    account: Some(to_model_account_identifier(account)),
+                    amount: Some(amount_(*amount).expect("amount_ never fails")),
+                    related_operations: None,
+                    coin_change: None,
+                    metadata: Some(
+                        DisburseToNeuronMetadata {
+                            neuron_identifier: *neuron_identifier,
+                            controller: *controller,
+                            dissolve_delay_seconds: *dissolve_delay_seconds,
+                            kyc_verified: *kyc_verified,
+                            nonce: *nonce,
+                        }
+                        .into(),
+                    ),
+                });
+            }
         }
     }
     Ok(ops)
 }
 
-/// Helper for `from_operations` that creates `Transfer`s from related
-/// debit/credit/fee operations.
+/// Helper for `from_operations` that creates `Transfer`s, `TransferFrom`s
+/// and `Approve`s from related debit/credit/approve/fee operations.
 struct State {
     preprocessing: bool,
     actions: Vec<Request>,
     cr: Option<(Tokens, ledger_canister::AccountIdentifier)>,
     db: Option<(Tokens, ledger_canister::AccountIdentifier)>,
     fee: Option<(Tokens, ledger_canister::AccountIdentifier)>,
+    /// The spender of the debit/credit operations seen previously, if they
+    /// are `TRANSFER_FROM` operations.
+    spender: Option<ledger_canister::AccountIdentifier>,
+    approve: Option<(ledger_canister::AccountIdentifier, ApproveMetadata)>,
 }
 
 impl State {
@@ -309,6 +562,32 @@ impl State {
             Err(err)
         };
 
+        if let Some((from, approve)) = self.approve.take() {
+            if self.preprocessing && self.fee.is_none() {
+                self.fee = Some((TRANSACTION_FEE, from));
+            }
+            if self.cr.is_some() || self.db.is_some() || self.fee.is_none() {
+                return trans_err(
+                    "Operations do not combine to make a recognizable approval".to_string(),
+                );
+            }
+            let (fee, fee_acc) = self.fee.take().unwrap();
+            if fee_acc != from {
+                let msg = format!("Fee should be taken from {}", from);
+                return trans_err(msg);
+            }
+            self.actions
+                .push(Request::Transfer(LedgerOperation::Approve {
+                    from,
+                    spender: approve.spender,
+                    allowance: approve.allowance,
+                    expected_allowance: approve.expected_allowance,
+                    expires_at: approve.expires_at,
+                    fee,
+                }));
+            return Ok(());
+        }
+
         if self.cr.is_none() && self.db.is_none() && self.fee.is_none() {
             return Ok(());
         }
@@ -339,13 +618,22 @@ impl State {
             return trans_err("Debit_amount should be equal -credit_amount".to_string());
         }
 
-        self.actions
-            .push(Request::Transfer(LedgerOperation::Transfer {
+        let operation = match self.spender.take() {
+            Some(spender) => LedgerOperation::TransferFrom {
                 from,
                 to,
+                spender,
                 amount: cr_amount,
                 fee: fee_amount,
-            }));
+            },
+            None => LedgerOperation::Transfer {
+                from,
+                to,
+                amount: cr_amount,
+                fee: fee_amount,
+            },
+        };
+        self.actions.push(Request::Transfer(operation));
 
         Ok(())
     }
@@ -354,6 +642,43 @@ impl State {
         &mut self,
         account: ledger_canister::AccountIdentifier,
         amount: i128,
+    ) -> Result<(), ApiError> {
+        if self.spender.is_some() || self.approve.is_some() {
+            self.flush()?;
+        }
+        self.debit_or_credit(account, amount)
+    }
+
+    fn transfer_from(
+        &mut self,
+        account: ledger_canister::AccountIdentifier,
+        amount: i128,
+        spender: ledger_canister::AccountIdentifier,
+    ) -> Result<(), ApiError> {
+        let pending_transfer = self.cr.is_some() || self.db.is_some();
+        if (pending_transfer && self.spender != Some(spender)) || self.approve.is_some() {
+            self.flush()?;
+        }
+        self.spender = Some(spender);
+        self.debit_or_credit(account, amount)
+    }
+
+    fn approve(
+        &mut self,
+        account: ledger_canister::AccountIdentifier,
+        metadata: ApproveMetadata,
+    ) -> Result<(), ApiError> {
+        if self.cr.is_some() || self.db.is_some() || self.approve.is_some() {
+            self.flush()?;
+        }
+        self.approve = Some((account, metadata));
+        Ok(())
+    }
+
+    fn debit_or_credit(
+        &mut self,
+        account: ledger_canister::AccountIdentifier,
+        amount: i128,
     ) -> Result<(), ApiError> {
         if amount > 0 || self.db.is_some() && amount == 0 {
             if self.cr.is_some() {
@@ -474,6 +799,120 @@ impl State {
 
         Ok(())
     }
+
+    fn remove_hot_key(
+        &mut self,
+        account: ledger_canister::AccountIdentifier,
+        neuron_identifier: u64,
+        key: PublicKeyOrPrincipal,
+    ) -> Result<(), ApiError> {
+        self.flush()?;
+
+        self.actions.push(Request::RemoveHotKey(RemoveHotKey {
+            account,
+            neuron_identifier,
+            key,
+        }));
+
+        Ok(())
+    }
+
+    fn spawn(
+        &mut self,
+        account: ledger_canister::AccountIdentifier,
+        neuron_identifier: u64,
+        controller: Option<PrincipalId>,
+    ) -> Result<(), ApiError> {
+        self.flush()?;
+
+        self.actions.push(Request::Spawn(Spawn {
+            account,
+            neuron_identifier,
+            controller,
+        }));
+
+        Ok(())
+    }
+
+    fn merge_maturity(
+        &mut self,
+        account: ledger_canister::AccountIdentifier,
+        neuron_identifier: u64,
+        percentage_to_merge: u32,
+    ) -> Result<(), ApiError> {
+        self.flush()?;
+
+        self.actions.push(Request::MergeMaturity(MergeMaturity {
+            account,
+            neuron_identifier,
+            percentage_to_merge,
+        }));
+
+        Ok(())
+    }
+
+    fn follow(
+        &mut self,
+        account: ledger_canister::AccountIdentifier,
+        neuron_identifier: u64,
+        topic: i32,
+        followees: Vec<u64>,
+    ) -> Result<(), ApiError> {
+        self.flush()?;
+
+        self.actions.push(Request::Follow(Follow {
+            account,
+            neuron_identifier,
+            topic,
+            followees,
+        }));
+
+        Ok(())
+    }
+
+    fn register_vote(
+        &mut self,
+        account: ledger_canister::AccountIdentifier,
+        neuron_identifier: u64,
+        proposal: u64,
+        vote: i32,
+    ) -> Result<(), ApiError> {
+        self.flush()?;
+
+        self.actions.push(Request::RegisterVote(RegisterVote {
+            account,
+            neuron_identifier,
+            proposal,
+            vote,
+        }));
+
+        Ok(())
+    }
+
+    fn split(
+        &mut self,
+        account: ledger_canister::AccountIdentifier,
+        neuron_identifier: u64,
+        amount: Tokens,
+    ) -> Result<(), ApiError> {
+        self.flush()?;
+
+        self.actions.push(Request::Split(Split {
+            account,
+            neuron_identifier,
+            amount,
+        }));
+
+        Ok(())
+    }
+
+    fn disburse_to_neuron(&mut self, disburse: DisburseToNeuron) -> Result<(), ApiError> {
+        self.flush()?;
+
+        self.actions.push(Request::DisburseToNeuron(disburse));
+
+        Ok(())
+    }
 }
 
 pub fn from_operations(ops: &[Operation], preprocessing: bool) -> Result<Vec<Request>, ApiError> {
@@ -488,6 +927,8 @@ pub fn from_operations(ops: &[Operation], preprocessing: bool) -> Result<Vec<Req
         cr: None,
         db: None,
         fee: None,
+        spender: None,
+        approve: None,
     };
 
     for o in ops {
@@ -501,7 +942,11 @@ pub fn from_operations(ops: &[Operation], preprocessing: bool) -> Result<Vec<Req
             .map_err(|e| op_error(o, e))?;
 
         let validate_neuron_management_op = || {
-            if o.amount.is_some() && o._type != DISBURSE {
+            if o.amount.is_some()
+                && o._type != DISBURSE
+                && o._type != SPLIT
+                && o._type != DISBURSE_TO_NEURON
+            {
                 Err(op_error(
                     o,
                     format!(
@@ -522,6 +967,14 @@ pub fn from_operations(ops: &[Operation], preprocessing: bool) -> Result<Vec<Req
             }
         };
 
+        let required_amount = || {
+            let amount = o
+                .amount
+                .as_ref()
+                .ok_or_else(|| op_error(o, "Amount must be populated".into()))?;
+            ledgeramount_from_amount(amount).map_err(|e| op_error(o, e))
+        };
+
         match o._type.as_str() {
             TRANSACTION => {
                 let amount = o
@@ -531,6 +984,25 @@ pub fn from_operations(ops: &[Operation], preprocessing: bool) -> Result<Vec<Req
                 let amount = from_amount(amount).map_err(|e| op_error(o, e))?;
                 state.transaction(account, amount)?;
             }
+            TRANSFER_FROM => {
+                let amount = o
+                    .amount
+                    .as_ref()
+                    .ok_or_else(|| op_error(o, "Amount must be populated".into()))?;
+                let amount = from_amount(amount).map_err(|e| op_error(o, e))?;
+                let SpenderMetadata { spender } = o.metadata.clone().try_into()?;
+                state.transfer_from(account, amount, spender)?;
+            }
+            APPROVE => {
+                if o.amount.is_some() {
+                    return Err(op_error(
+                        o,
+                        "APPROVE operation cannot have an amount".into(),
+                    ));
+                }
+                let metadata: ApproveMetadata = o.metadata.clone().try_into()?;
+                state.approve(account, metadata)?;
+            }
             FEE => {
                 let amount = o
                     .amount
@@ -593,6 +1065,73 @@ pub fn from_operations(ops: &[Operation], preprocessing: bool) -> Result<Vec<Req
                 };
                 state.disburse(account, neuron_identifier, amount, recipient)?;
             }
+            REMOVE_HOT_KEY => {
+                let KeyMetadata {
+                    key,
+                    neuron_identifier,
+                } = o.metadata.clone().try_into()?;
+                validate_neuron_management_op()?;
+                state.remove_hot_key(account, neuron_identifier, key)?;
+            }
+            SPAWN => {
+                validate_neuron_management_op()?;
+                let SpawnMetadata {
+                    neuron_identifier,
+                    controller,
+                } = o.metadata.clone().try_into()?;
+                state.spawn(account, neuron_identifier, controller)?;
+            }
+            MERGE_MATURITY => {
+                validate_neuron_management_op()?;
+                let MergeMaturityMetadata {
+                    neuron_identifier,
+                    percentage_to_merge,
+                } = o.metadata.clone().try_into()?;
+                state.merge_maturity(account, neuron_identifier, percentage_to_merge)?;
+            }
+            FOLLOW => {
+                validate_neuron_management_op()?;
+                let FollowMetadata {
+                    neuron_identifier,
+                    topic,
+                    followees,
+                } = o.metadata.clone().try_into()?;
+                state.follow(account, neuron_identifier, topic, followees)?;
+            }
+            REGISTER_VOTE => {
+                validate_neuron_management_op()?;
+                let RegisterVoteMetadata {
+                    neuron_identifier,
+                    proposal,
+                    vote,
+                } = o.metadata.clone().try_into()?;
+                state.register_vote(account, neuron_identifier, proposal, vote)?;
+            }
+            SPLIT => {
+                validate_neuron_management_op()?;
+                let NeuronIdentifierMetadata { neuron_identifier } =
+                    o.metadata.clone().try_into()?;
+                state.split(account, neuron_identifier, required_amount()?)?;
+            }
+            DISBURSE_TO_NEURON => {
+                validate_neuron_management_op()?;
+                let DisburseToNeuronMetadata {
+                    neuron_identifier,
+                    controller,
+                    dissolve_delay_seconds,
+                    kyc_verified,
+                    nonce,
+                } = o.metadata.clone().try_into()?;
+                state.disburse_to_neuron(DisburseToNeuron {
+                    account,
+                    neuron_identifier,
+                    amount: required_amount()?,
+                    controller,
+                    dissolve_delay_seconds,
+                    kyc_verified,
+                    nonce,
+                })?;
+            }
             _ => {
                 let msg = format!("Unsupported operation type: {}", o._type);
                 return Err(op_error(o, msg));
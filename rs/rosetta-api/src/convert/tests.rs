@@ -3,6 +3,7 @@ use crate::models::OperationIdentifier;
 use super::*;
 use ledger_canister::AccountIdentifier;
 use ledger_canister::Operation as LedgerOperation;
+use ledger_canister::TimeStamp;
 
 struct OperationBuilder(Operation);
 impl OperationBuilder {
@@ -45,6 +46,13 @@ impl OperationBuilder {
         })
     }
 
+    fn metadata(self, metadata: impl Into<Map<String, Value>>) -> Self {
+        Self(Operation {
+            metadata: Some(metadata.into()),
+            ..self.0
+        })
+    }
+
     fn build(self) -> Operation {
         self.0
     }
@@ -118,6 +126,187 @@ fn test_transfer_and_stake_requests_to_operations() {
     );
 }
 
+#[test]
+fn test_approve_and_transfer_from_requests_to_operations() {
+    let requests = [
+        Request::Transfer(LedgerOperation::Approve {
+            from: test_account(1),
+            spender: test_account(2),
+            allowance: Tokens::from_e8s(1000),
+            expected_allowance: None,
+            expires_at: Some(TimeStamp::new(10, 0)),
+            fee: Tokens::from_e8s(10),
+        }),
+        Request::Transfer(LedgerOperation::TransferFrom {
+            from: test_account(1),
+            to: test_account(3),
+            spender: test_account(2),
+            amount: Tokens::from_e8s(100),
+            fee: Tokens::from_e8s(10),
+        }),
+    ];
+    let expected = vec![
+        OperationBuilder::new(0, "APPROVE")
+            .account(test_account(1))
+            .metadata(ApproveMetadata {
+                spender: test_account(2),
+                allowance: Tokens::from_e8s(1000),
+                expected_allowance: None,
+                expires_at: Some(TimeStamp::new(10, 0)),
+            })
+            .build(),
+        OperationBuilder::new(1, "FEE")
+            .account(test_account(1))
+            .amount(-10)
+            .build(),
+        OperationBuilder::new(2, "TRANSFER_FROM")
+            .account(test_account(1))
+            .amount(-100)
+            .metadata(SpenderMetadata {
+                spender: test_account(2),
+            })
+            .build(),
+        OperationBuilder::new(3, "TRANSFER_FROM")
+            .account(test_account(3))
+            .amount(100)
+            .metadata(SpenderMetadata {
+                spender: test_account(2),
+            })
+            .build(),
+        OperationBuilder::new(4, "FEE")
+            .account(test_account(1))
+            .amount(-10)
+            .build(),
+    ];
+    assert_eq!(
+        Request::requests_to_operations(&requests),
+        Ok(expected.clone())
+    );
+    assert_eq!(requests_to_operations(&requests), Ok(expected));
+}
+
+#[test]
+fn test_approve_and_transfer_from_requests_round_trip() {
+    let requests = vec![
+        Request::Transfer(LedgerOperation::Approve {
+            from: test_account(1),
+            spender: test_account(2),
+            allowance: Tokens::from_e8s(1000),
+            expected_allowance: Some(Tokens::from_e8s(500)),
+            expires_at: Some(TimeStamp::new(10, 0)),
+            fee: TRANSACTION_FEE,
+        }),
+        Request::Transfer(LedgerOperation::TransferFrom {
+            from: test_account(1),
+            to: test_account(3),
+            spender: test_account(2),
+            amount: Tokens::from_e8s(100),
+            fee: TRANSACTION_FEE,
+        }),
+        Request::Transfer(LedgerOperation::Transfer {
+            from: test_account(1),
+            to: test_account(3),
+            amount: Tokens::from_e8s(100),
+            fee: TRANSACTION_FEE,
+        }),
+    ];
+    let operations = Request::requests_to_operations(&requests).unwrap();
+    assert_eq!(from_operations(&operations, false), Ok(requests));
+}
+
+#[test]
+fn test_transfer_from_requires_matching_spenders() {
+    let operations = vec![
+        OperationBuilder::new(0, "TRANSFER_FROM")
+            .account(test_account(1))
+            .amount(-100)
+            .metadata(SpenderMetadata {
+                spender: test_account(2),
+            })
+            .build(),
+        OperationBuilder::new(1, "TRANSFER_FROM")
+            .account(test_account(3))
+            .amount(100)
+            .metadata(SpenderMetadata {
+                spender: test_account(4),
+            })
+            .build(),
+        OperationBuilder::new(2, "FEE")
+            .account(test_account(1))
+            .amount(-(TRANSACTION_FEE.get_e8s() as i128))
+            .build(),
+    ];
+    assert!(from_operations(&operations, false).is_err());
+}
+
+#[test]
+fn test_neuron_management_requests_round_trip() {
+    let controller = PrincipalId::new_user_test_id(1);
+    let requests = vec![
+        Request::Spawn(Spawn {
+            account: test_account(1),
+            neuron_identifier: 1,
+            controller: Some(controller),
+        }),
+        Request::MergeMaturity(MergeMaturity {
+            account: test_account(1),
+            neuron_identifier: 1,
+            percentage_to_merge: 100,
+        }),
+        Request::Follow(Follow {
+            account: test_account(1),
+            neuron_identifier: 1,
+            topic: 3,
+            followees: vec![27, 28],
+        }),
+        Request::RegisterVote(RegisterVote {
+            account: test_account(1),
+            neuron_identifier: 1,
+            proposal: 42,
+            vote: 1,
+        }),
+        Request::Split(Split {
+            account: test_account(1),
+            neuron_identifier: 1,
+            amount: Tokens::from_e8s(300),
+        }),
+        Request::DisburseToNeuron(DisburseToNeuron {
+            account: test_account(1),
+            neuron_identifier: 1,
+            amount: Tokens::from_e8s(200),
+            controller,
+            dissolve_delay_seconds: 3600,
+            kyc_verified: true,
+            nonce: 7,
+        }),
+        Request::RemoveHotKey(RemoveHotKey {
+            account: test_account(1),
+            neuron_identifier: 1,
+            key: PublicKeyOrPrincipal::Principal(controller),
+        }),
+    ];
+    let operations = Request::requests_to_operations(&requests).unwrap();
+    assert_eq!(requests_to_operations(&requests), Ok(operations.clone()));
+    assert_eq!(
+        operations[4],
+        OperationBuilder::new(4, "SPLIT")
+            .account(test_account(1))
+            .amount(300)
+            .neuron_identifier(1)
+            .build()
+    );
+    assert_eq!(from_operations(&operations, false), Ok(requests));
+}
+
+#[test]
+fn test_split_requires_amount() {
+    let operations = vec![OperationBuilder::new(0, "SPLIT")
+        .account(test_account(1))
+        .neuron_identifier(1)
+        .build()];
+    assert!(from_operations(&operations, false).is_err());
+}
+
 #[test]
 fn test_can_handle_multiple_transfers() {
     assert_eq!(
@@ -15,7 +15,9 @@ use url::Url;
 use dfn_candid::CandidOne;
 use dfn_protobuf::{ProtoBuf, ToProto};
 use ic_canister_client::{Agent, HttpClient, Sender};
-use ic_nns_governance::pb::v1::manage_neuron_response::DisburseResponse;
+use ic_nns_governance::pb::v1::manage_neuron_response::{
+    DisburseResponse, DisburseToNeuronResponse, SpawnResponse, SplitResponse,
+};
 use ic_nns_governance::pb::v1::{
     claim_or_refresh_neuron_from_account_response::Result as ClaimOrRefreshResult,
     governance_error, manage_neuron::NeuronIdOrSubaccount, manage_neuron_response,
@@ -26,13 +28,14 @@ use ic_types::CanisterId;
 use ic_types::{crypto::threshold_sig::ThresholdSigPublicKey, messages::SignedRequestBytes};
 use ledger_canister::protobuf::{ArchiveIndexEntry, ArchiveIndexResponse};
 use ledger_canister::{
-    protobuf::TipOfChainRequest, AccountIdentifier, BlockArg, BlockHeight, BlockRes, EncodedBlock,
-    GetBlocksArgs, GetBlocksRes, HashOf, TipOfChainRes, Tokens, Transaction,
+    protobuf::TipOfChainRequest, AccountIdentifier, BlockArg, BlockHeight, BlockRes,
+    CertifiedBlocksRes, EncodedBlock, GetBlocksArgs, GetBlocksRes, HashOf, TipOfChainRes, Tokens,
+    Transaction,
 };
 use on_wire::{FromWire, IntoWire};
 
 use crate::balance_book::BalanceBook;
-use crate::certification::verify_block_hash;
+use crate::certification::{verify_archive_index, verify_block_hash, verify_blocks};
 use crate::errors::{ApiError, Details, ICError};
 use crate::models::{EnvelopePair, SignedTransaction};
 use crate::request_types::START_DISSOLVE;
@@ -96,23 +99,21 @@ impl LedgerClient {
                 ic_url.clone(),
                 canister_id,
                 http_client,
+                root_key,
             ));
             Self::verify_store(&blocks, &canister_access).await?;
 
             if root_key.is_some() {
                 // verify if we have the right certificate/we are connecting to the right
                 // canister
-                let TipOfChainRes {
-                    tip_index,
-                    certification,
-                } = canister_access.query_tip().await?;
+                let tip = canister_access.query_tip().await?;
 
                 let tip_block = canister_access
-                    .query_raw_block(tip_index)
+                    .query_raw_block(tip.tip_index)
                     .await?
                     .expect("Blockchain in the ledger canister is empty");
 
-                verify_block_hash(&certification, tip_block.hash(), &root_key, &canister_id)
+                verify_block_hash(&tip, tip_block.hash(), &root_key, &canister_id)
                     .map_err(ApiError::internal_error)?;
             }
 
@@ -265,13 +266,10 @@ impl LedgerAccess for LedgerClient {
             return Err(ApiError::NotAvailableOffline(false, Details::default()));
         }
         let canister = self.canister_access.as_ref().unwrap();
-        let TipOfChainRes {
-            tip_index,
-            certification,
-        } = canister.query_tip().await?;
-        crate::rosetta_server::TARGET_HEIGHT.set(tip_index as i64);
+        let tip = canister.query_tip().await?;
+        crate::rosetta_server::TARGET_HEIGHT.set(tip.tip_index as i64);
 
-        let chain_length = tip_index + 1;
+        let chain_length = tip.tip_index + 1;
 
         if chain_length == 0 {
             return Ok(());
@@ -341,7 +339,7 @@ impl LedgerAccess for LedgerClient {
                 }
                 let hb = HashedBlock::hash_block(raw_block, last_block_hash, i);
                 if i == chain_length - 1 {
-                    verify_block_hash(&certification, hb.hash, &self.root_key, &self.canister_id)
+                    verify_block_hash(&tip, hb.hash, &self.root_key, &self.canister_id)
                         .map_err(ApiError::internal_error)?;
                 }
                 last_block_hash = Some(hb.hash);
@@ -799,6 +797,70 @@ impl LedgerClient {
                                                         ),
                                                     }
                                                 }
+                                                RequestType::RemoveHotKey { .. }
+                                                | RequestType::MergeMaturity { .. }
+                                                | RequestType::Follow { .. }
+                                                | RequestType::RegisterVote { .. } => {
+                                                    let response: ManageNeuronResponse =
+                                                        candid::decode_one(bytes.as_ref())
+                                                            .map_err(|err| {
+                                                                format!(
+                                                                    "Could not decode {} response: {}",
+                                                                    request_type.into_str(),
+                                                                    err
+                                                                )
+                                                            })?;
+                                                    match &response.command {
+                                                        Some(manage_neuron_response::Command::Configure(_))
+                                                        | Some(manage_neuron_response::Command::MergeMaturity(_))
+                                                        | Some(manage_neuron_response::Command::Follow(_))
+                                                        | Some(manage_neuron_response::Command::RegisterVote(_)) => {
+                                                            return Ok(Ok(None));
+                                                        }
+                                                        Some(manage_neuron_response::Command::Error(err)) => {
+                                                            return Ok(Err(ApiError::TransactionRejected(
+                                                                false,
+                                                                format!("Could not {}: {}", request_type.into_str(), err).into(),
+                                                            )));
+                                                        }
+                                                        _ => panic!(
+                                                            "unexpected {} result: {:?}",
+                                                            request_type.into_str(),
+                                                            response.command
+                                                        ),
+                                                    }
+                                                }
+                                                RequestType::Spawn { .. }
+                                                | RequestType::Split { .. }
+                                                | RequestType::DisburseToNeuron { .. } => {
+                                                    let response: ManageNeuronResponse =
+                                                        candid::decode_one(bytes.as_ref())
+                                                            .map_err(|err| {
+                                                                format!(
+                                                                    "Could not decode {} response: {}",
+                                                                    request_type.into_str(),
+                                                                    err
+                                                                )
+                                                            })?;
+                                                    match &response.command {
+                                                        Some(manage_neuron_response::Command::Spawn(SpawnResponse { created_neuron_id }))
+                                                        | Some(manage_neuron_response::Command::Split(SplitResponse { created_neuron_id }))
+                                                        | Some(manage_neuron_response::Command::DisburseToNeuron(DisburseToNeuronResponse { created_neuron_id })) => {
+                                                            return Ok(Ok(created_neuron_id.as_ref().map(|nid| nid.id)));
+                                                        }
+                                                        Some(manage_neuron_response::Command::Error(err)) => {
+                                                            return Ok(Err(ApiError::TransactionRejected(
+                                                                false,
+                                                                format!("Could not {}: {}", request_type.into_str(), err).into(),
+                                                            )));
+                                                        }
+                                                        _ => panic!(
+                                                            "unexpected {} result: {:?}",
+                                                            request_type.into_str(),
+                                                            response.command
+                                                        ),
+                                                    }
+                                                }
                                             }
                                         }
                                         None => {
@@ -861,7 +923,14 @@ impl LedgerClient {
         match wait_for_result().await {
             // Success
             Ok(Ok(id)) => {
-                if let Request::Stake(_) = result._type {
+                // The neuron id of a stake is the id of the claimed neuron,
+                // the one of a spawn, split or disburse to neuron is the id
+                // of the created neuron.
+                if let Request::Stake(_)
+                | Request::Spawn(_)
+                | Request::Split(_)
+                | Request::DisburseToNeuron(_) = result._type
+                {
                     result.neuron_id = id;
                 } else {
                     result.block_index = id;
@@ -887,6 +956,9 @@ impl LedgerClient {
 pub struct CanisterAccess {
     agent: Agent,
     canister_id: CanisterId,
+    /// If set, the blocks and the archive index are verified against the
+    /// data certified by the ledger and its archive nodes.
+    root_key: Option<ThresholdSigPublicKey>,
     archive_list: Arc<tokio::sync::Mutex<Option<ArchiveIndexResponse>>>,
     #[allow(clippy::type_complexity)]
     ongoing_block_queries: tokio::sync::Mutex<
@@ -902,11 +974,17 @@ impl CanisterAccess {
     const BLOCKS_BATCH_LEN: u64 = 2000;
     const MAX_BLOCK_QUERIES: usize = 5;
 
-    pub fn new(url: Url, canister_id: CanisterId, client: HttpClient) -> Self {
+    pub fn new(
+        url: Url,
+        canister_id: CanisterId,
+        client: HttpClient,
+        root_key: Option<ThresholdSigPublicKey>,
+    ) -> Self {
         let agent = Agent::new_with_client(client, url, Sender::Anonymous);
         Self {
             agent,
             canister_id,
+            root_key,
             archive_list: Arc::new(tokio::sync::Mutex::new(None)),
             ongoing_block_queries: Default::default(),
         }
@@ -978,21 +1056,34 @@ impl CanisterAccess {
         start: BlockHeight,
         end: BlockHeight,
     ) -> Result<Vec<EncodedBlock>, ApiError> {
-        let blocks: GetBlocksRes = self
-            .query_canister(
-                can_id,
-                "get_blocks_pb",
-                GetBlocksArgs {
-                    start,
-                    length: (end - start) as usize,
-                },
-            )
+        let args = GetBlocksArgs {
+            start,
+            length: (end - start) as usize,
+        };
+        let root_key = match &self.root_key {
+            Some(root_key) => root_key,
+            None => {
+                let blocks: GetBlocksRes = self
+                    .query_canister(can_id, "get_blocks_pb", args)
+                    .await
+                    .map_err(|e| ApiError::internal_error(format!("In blocks: {}", e)))?;
+
+                return blocks
+                    .0
+                    .map_err(|e| ApiError::internal_error(format!("In blocks response: {}", e)));
+            }
+        };
+
+        let CertifiedBlocksRes(blocks) = self
+            .query_canister(can_id, "get_certified_blocks_pb", args)
             .await
             .map_err(|e| ApiError::internal_error(format!("In blocks: {}", e)))?;
-
-        blocks
-            .0
-            .map_err(|e| ApiError::internal_error(format!("In blocks response: {}", e)))
+        let blocks =
+            blocks.map_err(|e| ApiError::internal_error(format!("In blocks response: {}", e)))?;
+        verify_blocks(&blocks, start, root_key, &can_id).map_err(|e| {
+            ApiError::internal_error(format!("In blocks response verification: {}", e))
+        })?;
+        Ok(blocks.blocks)
     }
 
     pub async fn clear_outstanding_queries(&self) {
@@ -1092,10 +1183,18 @@ impl CanisterAccess {
             let mut alist = self.archive_list.lock().await;
             archive_entry = locate_archive(&*alist, start);
             if archive_entry.is_none() {
-                let al: ArchiveIndexResponse =
-                    self.query("get_archive_index_pb", ()).await.map_err(|e| {
+                let al: ArchiveIndexResponse = match &self.root_key {
+                    // The archive index is certified together with the tip
+                    Some(root_key) => {
+                        let tip = self.query_tip().await?;
+                        verify_archive_index(&tip, root_key, &self.canister_id).map_err(|e| {
+                            ApiError::internal_error(format!("In get archive index: {}", e))
+                        })?
+                    }
+                    None => self.query("get_archive_index_pb", ()).await.map_err(|e| {
                         ApiError::internal_error(format!("In get archive index: {}", e))
-                    })?;
+                    })?,
+                };
                 trace!("updating archive list to: {:?}", al);
                 *alist = Some(al);
                 archive_entry = locate_archive(&*alist, start);
@@ -18,10 +18,11 @@ use crate::convert::{
 };
 use crate::ledger_client::LedgerAccess;
 use crate::request_types::{
-    AddHotKey, Disburse, PublicKeyOrPrincipal, Request, RequestType, SetDissolveTimestamp, Stake,
-    StartDissolve, StopDissolve,
+    AddHotKey, Disburse, DisburseToNeuron, Follow, MergeMaturity, PublicKeyOrPrincipal,
+    RegisterVote, RemoveHotKey, Request, RequestType, SetDissolveTimestamp, Spawn, Split, Stake,
+    StartDissolve, StopDissolve, STATUS_COMPLETED,
 };
-use crate::store::{BlockStore, HashedBlock};
+use crate::store::{BlockStore, HashedBlock, TransactionQuery};
 use crate::time::Seconds;
 
 use convert::to_arg;
@@ -82,6 +83,22 @@ fn verify_network_id(canister_id: &CanisterId, net_id: &NetworkIdentifier) -> Re
     Ok(())
 }
 
+/// Decodes the command of the `manage_neuron` call made by a request of type
+/// `request_type`.
+fn decode_manage_neuron_command(
+    arg: &Blob,
+    request_type: RequestType,
+) -> Result<Option<Command>, ApiError> {
+    let manage: ManageNeuron = candid::decode_one(arg.0.as_ref()).map_err(|e| {
+        ApiError::internal_error(format!(
+            "Could not decode {} argument: {}",
+            request_type.into_str(),
+            e
+        ))
+    })?;
+    Ok(manage.command)
+}
+
 // For the first block, we return the block itself as its parent
 fn create_parent_block_id(
     blocks: &ledger_client::Blocks,
@@ -611,6 +628,131 @@ impl RosettaRequestHandler {
                         ));
                     };
                 }
+                RequestType::RemoveHotKey { neuron_identifier } => {
+                    if let Some(Command::Configure(manage_neuron::Configure {
+                        operation:
+                            Some(manage_neuron::configure::Operation::RemoveHotKey(
+                                manage_neuron::RemoveHotKey {
+                                    hot_key_to_remove: Some(pid),
+                                },
+                            )),
+                    })) = decode_manage_neuron_command(&arg, request_type)?
+                    {
+                        requests.push(Request::RemoveHotKey(RemoveHotKey {
+                            account: from,
+                            neuron_identifier,
+                            key: PublicKeyOrPrincipal::Principal(pid),
+                        }));
+                    } else {
+                        return Err(ApiError::internal_error(
+                            "Incompatible manage_neuron command".to_string(),
+                        ));
+                    };
+                }
+                RequestType::Spawn { neuron_identifier } => {
+                    if let Some(Command::Spawn(manage_neuron::Spawn { new_controller })) =
+                        decode_manage_neuron_command(&arg, request_type)?
+                    {
+                        requests.push(Request::Spawn(Spawn {
+                            account: from,
+                            neuron_identifier,
+                            controller: new_controller,
+                        }));
+                    } else {
+                        return Err(ApiError::internal_error(
+                            "Incompatible manage_neuron command".to_string(),
+                        ));
+                    };
+                }
+                RequestType::MergeMaturity { neuron_identifier } => {
+                    if let Some(Command::MergeMaturity(manage_neuron::MergeMaturity {
+                        percentage_to_merge,
+                    })) = decode_manage_neuron_command(&arg, request_type)?
+                    {
+                        requests.push(Request::MergeMaturity(MergeMaturity {
+                            account: from,
+                            neuron_identifier,
+                            percentage_to_merge,
+                        }));
+                    } else {
+                        return Err(ApiError::internal_error(
+                            "Incompatible manage_neuron command".to_string(),
+                        ));
+                    };
+                }
+                RequestType::Follow { neuron_identifier } => {
+                    if let Some(Command::Follow(manage_neuron::Follow { topic, followees })) =
+                        decode_manage_neuron_command(&arg, request_type)?
+                    {
+                        requests.push(Request::Follow(Follow {
+                            account: from,
+                            neuron_identifier,
+                            topic,
+                            followees: followees.iter().map(|n| n.id).collect(),
+                        }));
+                    } else {
+                        return Err(ApiError::internal_error(
+                            "Incompatible manage_neuron command".to_string(),
+                        ));
+                    };
+                }
+                RequestType::RegisterVote { neuron_identifier } => {
+                    if let Some(Command::RegisterVote(manage_neuron::RegisterVote {
+                        proposal: Some(proposal),
+                        vote,
+                    })) = decode_manage_neuron_command(&arg, request_type)?
+                    {
+                        requests.push(Request::RegisterVote(RegisterVote {
+                            account: from,
+                            neuron_identifier,
+                            proposal: proposal.id,
+                            vote,
+                        }));
+                    } else {
+                        return Err(ApiError::internal_error(
+                            "Incompatible manage_neuron command".to_string(),
+                        ));
+                    };
+                }
+                RequestType::Split { neuron_identifier } => {
+                    if let Some(Command::Split(manage_neuron::Split { amount_e8s })) =
+                        decode_manage_neuron_command(&arg, request_type)?
+                    {
+                        requests.push(Request::Split(Split {
+                            account: from,
+                            neuron_identifier,
+                            amount: ledger_canister::Tokens::from_e8s(amount_e8s),
+                        }));
+                    } else {
+                        return Err(ApiError::internal_error(
+                            "Incompatible manage_neuron command".to_string(),
+                        ));
+                    };
+                }
+                RequestType::DisburseToNeuron { neuron_identifier } => {
+                    if let Some(Command::DisburseToNeuron(manage_neuron::DisburseToNeuron {
+                        new_controller: Some(controller),
+                        amount_e8s,
+                        dissolve_delay_seconds,
+                        kyc_verified,
+                        nonce,
+                    })) = decode_manage_neuron_command(&arg, request_type)?
+                    {
+                        requests.push(Request::DisburseToNeuron(DisburseToNeuron {
+                            account: from,
+                            neuron_identifier,
+                            amount: ledger_canister::Tokens::from_e8s(amount_e8s),
+                            controller,
+                            dissolve_delay_seconds,
+                            kyc_verified,
+                            nonce,
+                        }));
+                    } else {
+                        return Err(ApiError::internal_error(
+                            "Incompatible manage_neuron command".to_string(),
+                        ));
+                    };
+                }
             }
         }
 
@@ -956,6 +1098,151 @@ impl RosettaRequestHandler {
                         &mut updates,
                     )?;
                 }
+                Request::RemoveHotKey(RemoveHotKey {
+                    account,
+                    key,
+                    neuron_identifier,
+                }) => {
+                    let command = Command::Configure(manage_neuron::Configure {
+                        operation: Some(configure::Operation::RemoveHotKey(
+                            manage_neuron::RemoveHotKey {
+                                hot_key_to_remove: Some(PrincipalId::try_from(&key)?),
+                            },
+                        )),
+                    });
+
+                    add_neuron_management_payload(
+                        RequestType::RemoveHotKey { neuron_identifier },
+                        account,
+                        neuron_identifier,
+                        command,
+                        &mut payloads,
+                        &mut updates,
+                    )?;
+                }
+                Request::Spawn(Spawn {
+                    account,
+                    neuron_identifier,
+                    controller,
+                }) => {
+                    let command = Command::Spawn(manage_neuron::Spawn {
+                        new_controller: controller,
+                    });
+
+                    add_neuron_management_payload(
+                        RequestType::Spawn { neuron_identifier },
+                        account,
+                        neuron_identifier,
+                        command,
+                        &mut payloads,
+                        &mut updates,
+                    )?;
+                }
+                Request::MergeMaturity(MergeMaturity {
+                    account,
+                    neuron_identifier,
+                    percentage_to_merge,
+                }) => {
+                    let command = Command::MergeMaturity(manage_neuron::MergeMaturity {
+                        percentage_to_merge,
+                    });
+
+                    add_neuron_management_payload(
+                        RequestType::MergeMaturity { neuron_identifier },
+                        account,
+                        neuron_identifier,
+                        command,
+                        &mut payloads,
+                        &mut updates,
+                    )?;
+                }
+                Request::Follow(Follow {
+                    account,
+                    neuron_identifier,
+                    topic,
+                    followees,
+                }) => {
+                    let command = Command::Follow(manage_neuron::Follow {
+                        topic,
+                        followees: followees
+                            .into_iter()
+                            .map(|id| ic_nns_common::pb::v1::NeuronId { id })
+                            .collect(),
+                    });
+
+                    add_neuron_management_payload(
+                        RequestType::Follow { neuron_identifier },
+                        account,
+                        neuron_identifier,
+                        command,
+                        &mut payloads,
+                        &mut updates,
+                    )?;
+                }
+                Request::RegisterVote(RegisterVote {
+                    account,
+                    neuron_identifier,
+                    proposal,
+                    vote,
+                }) => {
+                    let command = Command::RegisterVote(manage_neuron::RegisterVote {
+                        proposal: Some(ic_nns_common::pb::v1::ProposalId { id: proposal }),
+                        vote,
+                    });
+
+                    add_neuron_management_payload(
+                        RequestType::RegisterVote { neuron_identifier },
+                        account,
+                        neuron_identifier,
+                        command,
+                        &mut payloads,
+                        &mut updates,
+                    )?;
+                }
+                Request::Split(Split {
+                    account,
+                    neuron_identifier,
+                    amount,
+                }) => {
+                    let command = Command::Split(manage_neuron::Split {
+                        amount_e8s: amount.get_e8s(),
+                    });
+
+                    add_neuron_management_payload(
+                        RequestType::Split { neuron_identifier },
+                        account,
+                        neuron_identifier,
+                        command,
+                        &mut payloads,
+                        &mut updates,
+                    )?;
+                }
+                Request::DisburseToNeuron(DisburseToNeuron {
+                    account,
+                    neuron_identifier,
+                    amount,
+                    controller,
+                    dissolve_delay_seconds,
+                    kyc_verified,
+                    nonce,
+                }) => {
+                    let command = Command::DisburseToNeuron(manage_neuron::DisburseToNeuron {
+                        new_controller: Some(controller),
+                        amount_e8s: amount.get_e8s(),
+                        dissolve_delay_seconds,
+                        kyc_verified,
+                        nonce,
+                    });
+
+                    add_neuron_management_payload(
+                        RequestType::DisburseToNeuron { neuron_identifier },
+                        account,
+                        neuron_identifier,
+                        command,
+                        &mut payloads,
+                        &mut updates,
+                    )?;
+                }
                 Request::Disburse(Disburse {
                     account,
                     amount,
@@ -988,6 +1275,16 @@ impl RosettaRequestHandler {
                         "Mint operations are not supported through rosetta",
                     ))
                 }
+                Request::Transfer(Operation::Approve { .. }) => {
+                    return Err(ApiError::invalid_request(
+                        "Approve operations are not supported through rosetta",
+                    ))
+                }
+                Request::Transfer(Operation::TransferFrom { .. }) => {
+                    return Err(ApiError::invalid_request(
+                        "TransferFrom operations are not supported through rosetta",
+                    ))
+                }
             }
         }
 
@@ -1024,13 +1321,28 @@ impl RosettaRequestHandler {
                     | Request::StartDissolve(StartDissolve { account, .. })
                     | Request::StopDissolve(StopDissolve { account, .. })
                     | Request::Disburse(Disburse { account, .. })
-                    | Request::AddHotKey(AddHotKey { account, .. }) => Ok(account),
+                    | Request::AddHotKey(AddHotKey { account, .. })
+                    | Request::RemoveHotKey(RemoveHotKey { account, .. })
+                    | Request::Spawn(Spawn { account, .. })
+                    | Request::MergeMaturity(MergeMaturity { account, .. })
+                    | Request::Follow(Follow { account, .. })
+                    | Request::RegisterVote(RegisterVote { account, .. })
+                    | Request::Split(Split { account, .. })
+                    | Request::DisburseToNeuron(DisburseToNeuron { account, .. }) => Ok(account),
                     Request::Transfer(Operation::Burn { .. }) => Err(ApiError::invalid_request(
                         "Burn operations are not supported through rosetta",
                     )),
                     Request::Transfer(Operation::Mint { .. }) => Err(ApiError::invalid_request(
                         "Mint operations are not supported through rosetta",
                     )),
+                    Request::Transfer(Operation::Approve { .. }) => Err(ApiError::invalid_request(
+                        "Approve operations are not supported through rosetta",
+                    )),
+                    Request::Transfer(Operation::TransferFrom { .. }) => {
+                        Err(ApiError::invalid_request(
+                            "TransferFrom operations are not supported through rosetta",
+                        ))
+                    }
                 })
                 .collect();
 
@@ -1188,6 +1500,8 @@ impl RosettaRequestHandler {
                     "SET_DISSOLVE_TIMESTAMP".to_string(),
                     "START_DISSOLVING".to_string(),
                     "STOP_DISSOLVING".to_string(),
+                    "APPROVE".to_string(),
+                    "TRANSFER_FROM".to_string(),
                 ],
                 {
                     let mut errs = vec![
@@ -1334,41 +1648,18 @@ impl RosettaRequestHandler {
         ))
     }
 
-    /// Search for a transaction given its hash
+    /// Search for transactions by transaction hash, account, operation type,
+    /// currency and status
     pub async fn search_transactions(
         &self,
         msg: models::SearchTransactionsRequest,
     ) -> Result<SearchTransactionsResponse, ApiError> {
         verify_network_id(self.ledger.ledger_canister_id(), &msg.network_identifier)?;
 
-        if let Some(Operator::Or) = msg.operator {
-            return Err(ApiError::invalid_request("Operator OR not supported"));
-        }
-
         if msg.coin_identifier.is_some() {
             return Err(ApiError::invalid_request("coin_identifier not supported"));
         }
 
-        if msg.currency.is_some() {
-            return Err(ApiError::invalid_request("currency not supported"));
-        }
-
-        if msg.status.is_some() {
-            return Err(ApiError::invalid_request("status not supported"));
-        }
-
-        if msg._type.is_some() {
-            return Err(ApiError::invalid_request("type not supported"));
-        }
-
-        if msg.address.is_some() {
-            return Err(ApiError::invalid_request("address not supported"));
-        }
-
-        if msg.success.is_some() {
-            return Err(ApiError::invalid_request("success not supported"));
-        }
-
         let max_block = match msg.max_block {
             Some(x) => Some(
                 u64::try_from(x)
@@ -1390,69 +1681,96 @@ impl RosettaRequestHandler {
         };
         let limit = std::cmp::min(limit, 10_000);
 
-        if msg.transaction_identifier.is_none() && msg.account_identifier.is_none() {
-            return self.get_blocks_range(max_block, offset, limit).await;
-        }
-
         let blocks = self.ledger.read_blocks().await;
 
-        let last_idx = blocks
-            .last_verified()?
-            .ok_or_else(|| ApiError::BlockchainEmpty(true, Default::default()))?
-            .index;
-
-        let mut heights = Vec::new();
-        let mut total_count = 0;
+        let mut query = TransactionQuery {
+            match_any: msg.operator == Some(Operator::Or),
+            max_block,
+            ..Default::default()
+        };
+        // Conditions that either all or none of the transactions satisfy.
+        let mut constant_conditions = Vec::new();
 
         if let Some(tid) = &msg.transaction_identifier {
-            if msg.account_identifier.is_some() {
-                return Err(ApiError::invalid_request(
-                    "Only one of transaction_identitier and account_identifier should be populated",
-                ));
-            }
-
             let tid = ledger_canister::HashOf::try_from(tid)
                 .map_err(|e| ApiError::InvalidTransactionId(false, e.into()))?;
-
-            if let Some(i) = blocks.tx_hash_location.get(&tid) {
-                heights.push(*i);
-                total_count += 1;
+            match blocks.tx_hash_location.get(&tid) {
+                Some(i) => query.block = Some(*i),
+                None => constant_conditions.push(false),
             }
         }
 
-        let mut next_offset = None;
-
         if let Some(aid) = &msg.account_identifier {
-            let acc = from_model_account_identifier(aid)
-                .map_err(|e| ApiError::InvalidAccountId(false, e.into()))?;
+            query.accounts.push(
+                from_model_account_identifier(aid)
+                    .map_err(|e| ApiError::InvalidAccountId(false, e.into()))?,
+            );
+        }
 
-            let hist = blocks.balance_book.store.get_history(&acc, max_block);
-            heights = hist
-                .iter()
-                .rev()
-                .map(|(h, _)| *h)
-                .filter(|h| *h <= last_idx)
-                .skip(offset)
-                .collect();
+        if let Some(address) = &msg.address {
+            query.accounts.push(
+                ledger_canister::AccountIdentifier::from_hex(address)
+                    .map_err(|e| ApiError::InvalidAccountId(false, e.into()))?,
+            );
+        }
 
-            let cnt = offset
-                .checked_add(heights.len())
-                .ok_or_else(|| ApiError::internal_error("total count overflow"))?;
-            total_count = i64::try_from(cnt).map_err(|e| {
-                ApiError::internal_error(format!("Total count does not fit in i64: {}", e))
-            })?;
-
-            if heights.len() > limit {
-                let next = offset
-                    .checked_add(limit)
-                    .ok_or_else(|| ApiError::internal_error("offset + limit overflow"))?;
-                next_offset = Some(i64::try_from(next).map_err(|e| {
-                    ApiError::internal_error(format!("Next offset cannot fit in i64: {}", e))
-                })?);
-            }
-            heights.truncate(limit);
+        query.operation_type = msg._type.clone();
+
+        if let Some(currency) = &msg.currency {
+            constant_conditions.push(*currency == convert::icp());
         }
 
+        // Ledger transactions never fail once they are in a block.
+        if let Some(status) = &msg.status {
+            constant_conditions.push(status == STATUS_COMPLETED);
+        }
+
+        if let Some(success) = msg.success {
+            constant_conditions.push(success);
+        }
+
+        let matches_all = if query.match_any {
+            constant_conditions.contains(&true)
+                || (!query.has_conditions() && constant_conditions.is_empty())
+        } else {
+            !query.has_conditions() && !constant_conditions.contains(&false)
+        };
+        if matches_all {
+            drop(blocks);
+            return self.get_blocks_range(max_block, offset, limit).await;
+        }
+
+        blocks
+            .last_verified()?
+            .ok_or_else(|| ApiError::BlockchainEmpty(true, Default::default()))?;
+
+        let matches_none = if query.match_any {
+            !query.has_conditions()
+        } else {
+            constant_conditions.contains(&false)
+        };
+        if matches_none {
+            return Ok(SearchTransactionsResponse::new(vec![], 0, None));
+        }
+
+        let (heights, total_count) = blocks
+            .block_store
+            .search_transactions(&query, offset, limit)?;
+
+        let next = offset
+            .checked_add(heights.len())
+            .ok_or_else(|| ApiError::internal_error("offset + limit overflow"))?;
+        let next_offset = if next < total_count {
+            Some(i64::try_from(next).map_err(|e| {
+                ApiError::internal_error(format!("Next offset cannot fit in i64: {}", e))
+            })?)
+        } else {
+            None
+        };
+        let total_count = i64::try_from(total_count).map_err(|e| {
+            ApiError::internal_error(format!("Total count does not fit in i64: {}", e))
+        })?;
+
         let mut txs: Vec<BlockTransaction> = Vec::new();
 
         for i in heights {
@@ -10,7 +10,9 @@ use crate::{
 use dfn_candid::CandidOne;
 use ic_nns_governance::pb::v1::manage_neuron::{self, configure, Command, Configure};
 use ic_types::PrincipalId;
-use ledger_canister::{AccountIdentifier, BlockHeight, Operation as LedgerOperation, Tokens};
+use ledger_canister::{
+    AccountIdentifier, BlockHeight, Operation as LedgerOperation, TimeStamp, Tokens,
+};
 use on_wire::FromWire;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
@@ -32,6 +34,15 @@ pub const SET_DISSOLVE_TIMESTAMP: &str = "SET_DISSOLVE_TIMESTAMP";
 pub const DISBURSE: &str = "DISBURSE";
 pub const DISSOLVE_TIME_UTC_SECONDS: &str = "dissolve_time_utc_seconds";
 pub const ADD_HOT_KEY: &str = "ADD_HOT_KEY";
+pub const REMOVE_HOT_KEY: &str = "REMOVE_HOT_KEY";
+pub const SPAWN: &str = "SPAWN";
+pub const MERGE_MATURITY: &str = "MERGE_MATURITY";
+pub const FOLLOW: &str = "FOLLOW";
+pub const REGISTER_VOTE: &str = "REGISTER_VOTE";
+pub const SPLIT: &str = "SPLIT";
+pub const DISBURSE_TO_NEURON: &str = "DISBURSE_TO_NEURON";
+pub const APPROVE: &str = "APPROVE";
+pub const TRANSFER_FROM: &str = "TRANSFER_FROM";
 
 /// `RequestType` contains all supported values of `Operation.type`.
 /// Extra information, such as `neuron_identifier` should only be included
@@ -60,6 +71,20 @@ pub enum RequestType {
     #[serde(rename = "ADD_HOT_KEY")]
     #[serde(alias = "AddHotKey")]
     AddHotKey { neuron_identifier: u64 },
+    #[serde(rename = "REMOVE_HOT_KEY")]
+    RemoveHotKey { neuron_identifier: u64 },
+    #[serde(rename = "SPAWN")]
+    Spawn { neuron_identifier: u64 },
+    #[serde(rename = "MERGE_MATURITY")]
+    MergeMaturity { neuron_identifier: u64 },
+    #[serde(rename = "FOLLOW")]
+    Follow { neuron_identifier: u64 },
+    #[serde(rename = "REGISTER_VOTE")]
+    RegisterVote { neuron_identifier: u64 },
+    #[serde(rename = "SPLIT")]
+    Split { neuron_identifier: u64 },
+    #[serde(rename = "DISBURSE_TO_NEURON")]
+    DisburseToNeuron { neuron_identifier: u64 },
 }
 
 impl RequestType {
@@ -72,6 +97,13 @@ impl RequestType {
             RequestType::StopDissolve { .. } => STOP_DISSOLVE,
             RequestType::Disburse { .. } => DISBURSE,
             RequestType::AddHotKey { .. } => ADD_HOT_KEY,
+            RequestType::RemoveHotKey { .. } => REMOVE_HOT_KEY,
+            RequestType::Spawn { .. } => SPAWN,
+            RequestType::MergeMaturity { .. } => MERGE_MATURITY,
+            RequestType::Follow { .. } => FOLLOW,
+            RequestType::RegisterVote { .. } => REGISTER_VOTE,
+            RequestType::Split { .. } => SPLIT,
+            RequestType::DisburseToNeuron { .. } => DISBURSE_TO_NEURON,
         }
     }
 
@@ -88,6 +120,13 @@ impl RequestType {
                 | RequestType::StopDissolve { .. }
                 | RequestType::Disburse { .. }
                 | RequestType::AddHotKey { .. }
+                | RequestType::RemoveHotKey { .. }
+                | RequestType::Spawn { .. }
+                | RequestType::MergeMaturity { .. }
+                | RequestType::Follow { .. }
+                | RequestType::RegisterVote { .. }
+                | RequestType::Split { .. }
+                | RequestType::DisburseToNeuron { .. }
         )
     }
 }
@@ -300,6 +339,20 @@ pub enum Request {
     Disburse(Disburse),
     #[serde(rename = "ADD_HOT_KEY")]
     AddHotKey(AddHotKey),
+    #[serde(rename = "REMOVE_HOT_KEY")]
+    RemoveHotKey(RemoveHotKey),
+    #[serde(rename = "SPAWN")]
+    Spawn(Spawn),
+    #[serde(rename = "MERGE_MATURITY")]
+    MergeMaturity(MergeMaturity),
+    #[serde(rename = "FOLLOW")]
+    Follow(Follow),
+    #[serde(rename = "REGISTER_VOTE")]
+    RegisterVote(RegisterVote),
+    #[serde(rename = "SPLIT")]
+    Split(Split),
+    #[serde(rename = "DISBURSE_TO_NEURON")]
+    DisburseToNeuron(DisburseToNeuron),
 }
 
 impl Request {
@@ -335,6 +388,41 @@ impl Request {
             }) => Ok(RequestType::AddHotKey {
                 neuron_identifier: *neuron_identifier,
             }),
+            Request::RemoveHotKey(RemoveHotKey {
+                neuron_identifier, ..
+            }) => Ok(RequestType::RemoveHotKey {
+                neuron_identifier: *neuron_identifier,
+            }),
+            Request::Spawn(Spawn {
+                neuron_identifier, ..
+            }) => Ok(RequestType::Spawn {
+                neuron_identifier: *neuron_identifier,
+            }),
+            Request::MergeMaturity(MergeMaturity {
+                neuron_identifier, ..
+            }) => Ok(RequestType::MergeMaturity {
+                neuron_identifier: *neuron_identifier,
+            }),
+            Request::Follow(Follow {
+                neuron_identifier, ..
+            }) => Ok(RequestType::Follow {
+                neuron_identifier: *neuron_identifier,
+            }),
+            Request::RegisterVote(RegisterVote {
+                neuron_identifier, ..
+            }) => Ok(RequestType::RegisterVote {
+                neuron_identifier: *neuron_identifier,
+            }),
+            Request::Split(Split {
+                neuron_identifier, ..
+            }) => Ok(RequestType::Split {
+                neuron_identifier: *neuron_identifier,
+            }),
+            Request::DisburseToNeuron(DisburseToNeuron {
+                neuron_identifier, ..
+            }) => Ok(RequestType::DisburseToNeuron {
+                neuron_identifier: *neuron_identifier,
+            }),
             Request::Transfer(LedgerOperation::Transfer { .. }) => Ok(RequestType::Send),
             Request::Transfer(LedgerOperation::Burn { .. }) => Err(ApiError::invalid_request(
                 "Burn operations are not supported through rosetta",
@@ -342,6 +430,14 @@ impl Request {
             Request::Transfer(LedgerOperation::Mint { .. }) => Err(ApiError::invalid_request(
                 "Mint operations are not supported through rosetta",
             )),
+            Request::Transfer(LedgerOperation::Approve { .. }) => Err(ApiError::invalid_request(
+                "Approve operations are not supported through rosetta",
+            )),
+            Request::Transfer(LedgerOperation::TransferFrom { .. }) => {
+                Err(ApiError::invalid_request(
+                    "TransferFrom operations are not supported through rosetta",
+                ))
+            }
         }
     }
 
@@ -360,6 +456,13 @@ impl Request {
                 Request::StopDissolve(o) => builder.stop_dissolve(o),
                 Request::Disburse(o) => builder.disburse(o),
                 Request::AddHotKey(o) => builder.add_hot_key(o),
+                Request::RemoveHotKey(o) => builder.remove_hot_key(o),
+                Request::Spawn(o) => builder.spawn(o),
+                Request::MergeMaturity(o) => builder.merge_maturity(o),
+                Request::Follow(o) => builder.follow(o),
+                Request::RegisterVote(o) => builder.register_vote(o),
+                Request::Split(o) => builder.split(o),
+                Request::DisburseToNeuron(o) => builder.disburse_to_neuron(o),
             };
         }
         Ok(builder.build())
@@ -378,6 +481,13 @@ impl Request {
                 | Request::StopDissolve(_)
                 | Request::Disburse(_)
                 | Request::AddHotKey(_)
+                | Request::RemoveHotKey(_)
+                | Request::Spawn(_)
+                | Request::MergeMaturity(_)
+                | Request::Follow(_)
+                | Request::RegisterVote(_)
+                | Request::Split(_)
+                | Request::DisburseToNeuron(_)
         )
     }
 }
@@ -510,6 +620,122 @@ impl TryFrom<&models::Request> for Request {
                     Err(ApiError::invalid_request("Request is missing set hotkey."))
                 }
             }
+            RequestType::RemoveHotKey { neuron_identifier } => {
+                if let Some(Command::Configure(Configure {
+                    operation:
+                        Some(configure::Operation::RemoveHotKey(manage_neuron::RemoveHotKey {
+                            hot_key_to_remove: Some(pid),
+                        })),
+                })) = manage_neuron()?
+                {
+                    Ok(Request::RemoveHotKey(RemoveHotKey {
+                        account,
+                        neuron_identifier: *neuron_identifier,
+                        key: PublicKeyOrPrincipal::Principal(pid),
+                    }))
+                } else {
+                    Err(ApiError::invalid_request(
+                        "Request is missing remove hotkey.",
+                    ))
+                }
+            }
+            RequestType::Spawn { neuron_identifier } => {
+                if let Some(Command::Spawn(manage_neuron::Spawn { new_controller })) =
+                    manage_neuron()?
+                {
+                    Ok(Request::Spawn(Spawn {
+                        account,
+                        neuron_identifier: *neuron_identifier,
+                        controller: new_controller,
+                    }))
+                } else {
+                    Err(ApiError::invalid_request("Request is missing spawn."))
+                }
+            }
+            RequestType::MergeMaturity { neuron_identifier } => {
+                if let Some(Command::MergeMaturity(manage_neuron::MergeMaturity {
+                    percentage_to_merge,
+                })) = manage_neuron()?
+                {
+                    Ok(Request::MergeMaturity(MergeMaturity {
+                        account,
+                        neuron_identifier: *neuron_identifier,
+                        percentage_to_merge,
+                    }))
+                } else {
+                    Err(ApiError::invalid_request(
+                        "Request is missing merge maturity.",
+                    ))
+                }
+            }
+            RequestType::Follow { neuron_identifier } => {
+                if let Some(Command::Follow(manage_neuron::Follow { topic, followees })) =
+                    manage_neuron()?
+                {
+                    Ok(Request::Follow(Follow {
+                        account,
+                        neuron_identifier: *neuron_identifier,
+                        topic,
+                        followees: followees.iter().map(|n| n.id).collect(),
+                    }))
+                } else {
+                    Err(ApiError::invalid_request("Request is missing follow."))
+                }
+            }
+            RequestType::RegisterVote { neuron_identifier } => {
+                if let Some(Command::RegisterVote(manage_neuron::RegisterVote {
+                    proposal: Some(proposal),
+                    vote,
+                })) = manage_neuron()?
+                {
+                    Ok(Request::RegisterVote(RegisterVote {
+                        account,
+                        neuron_identifier: *neuron_identifier,
+                        proposal: proposal.id,
+                        vote,
+                    }))
+                } else {
+                    Err(ApiError::invalid_request(
+                        "Request is missing register vote.",
+                    ))
+                }
+            }
+            RequestType::Split { neuron_identifier } => {
+                if let Some(Command::Split(manage_neuron::Split { amount_e8s })) = manage_neuron()?
+                {
+                    Ok(Request::Split(Split {
+                        account,
+                        neuron_identifier: *neuron_identifier,
+                        amount: Tokens::from_e8s(amount_e8s),
+                    }))
+                } else {
+                    Err(ApiError::invalid_request("Request is missing split."))
+                }
+            }
+            RequestType::DisburseToNeuron { neuron_identifier } => {
+                if let Some(Command::DisburseToNeuron(manage_neuron::DisburseToNeuron {
+                    new_controller: Some(controller),
+                    amount_e8s,
+                    dissolve_delay_seconds,
+                    kyc_verified,
+                    nonce,
+                })) = manage_neuron()?
+                {
+                    Ok(Request::DisburseToNeuron(DisburseToNeuron {
+                        account,
+                        neuron_identifier: *neuron_identifier,
+                        amount: Tokens::from_e8s(amount_e8s),
+                        controller,
+                        dissolve_delay_seconds,
+                        kyc_verified,
+                        nonce,
+                    }))
+                } else {
+                    Err(ApiError::invalid_request(
+                        "Request is missing disburse to neuron.",
+                    ))
+                }
+            }
         }
     }
 }
@@ -560,6 +786,12 @@ mod send {
                 LedgerOperation::Mint { .. } => {
                     Err("Mint operations are not supported through rosetta".to_owned())
                 }
+                LedgerOperation::Approve { .. } => {
+                    Err("Approve operations are not supported through rosetta".to_owned())
+                }
+                LedgerOperation::TransferFrom { .. } => {
+                    Err("TransferFrom operations are not supported through rosetta".to_owned())
+                }
             }
         }
     }
@@ -627,6 +859,82 @@ pub struct AddHotKey {
     pub key: PublicKeyOrPrincipal,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct RemoveHotKey {
+    pub account: ledger_canister::AccountIdentifier,
+    #[serde(default)]
+    pub neuron_identifier: u64,
+    pub key: PublicKeyOrPrincipal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Spawn {
+    pub account: ledger_canister::AccountIdentifier,
+    #[serde(default)]
+    pub neuron_identifier: u64,
+    /// The controller of the spawned neuron. If not set, the spawned neuron
+    /// has the same controller as its parent.
+    #[serde(default)]
+    pub controller: Option<PrincipalId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MergeMaturity {
+    pub account: ledger_canister::AccountIdentifier,
+    #[serde(default)]
+    pub neuron_identifier: u64,
+    /// The percentage of the maturity to merge into the stake, from 1 to 100.
+    pub percentage_to_merge: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Follow {
+    pub account: ledger_canister::AccountIdentifier,
+    #[serde(default)]
+    pub neuron_identifier: u64,
+    /// The governance `Topic` of the proposals on which the neuron follows
+    /// `followees`.
+    pub topic: i32,
+    /// The ids of the neurons to follow. An empty list removes the following
+    /// on `topic`.
+    pub followees: Vec<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RegisterVote {
+    pub account: ledger_canister::AccountIdentifier,
+    #[serde(default)]
+    pub neuron_identifier: u64,
+    pub proposal: u64,
+    /// The governance `Vote`.
+    pub vote: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Split {
+    pub account: ledger_canister::AccountIdentifier,
+    #[serde(default)]
+    pub neuron_identifier: u64,
+    /// The stake of the new neuron.
+    pub amount: Tokens,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DisburseToNeuron {
+    pub account: ledger_canister::AccountIdentifier,
+    #[serde(default)]
+    pub neuron_identifier: u64,
+    /// The stake of the new neuron.
+    pub amount: Tokens,
+    /// The controller of the new neuron.
+    pub controller: PrincipalId,
+    pub dissolve_delay_seconds: u64,
+    pub kyc_verified: bool,
+    /// Together with `controller`, determines the subaccount of the new
+    /// neuron.
+    pub nonce: u64,
+}
+
 #[derive(Debug, Clone, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum PublicKeyOrPrincipal {
@@ -783,6 +1091,223 @@ impl From<KeyMetadata> for Object {
     }
 }
 
+/// The metadata of `APPROVE` operations.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ApproveMetadata {
+    pub spender: AccountIdentifier,
+    pub allowance: Tokens,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_allowance: Option<Tokens>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<TimeStamp>,
+}
+
+impl TryFrom<Option<Object>> for ApproveMetadata {
+    type Error = ApiError;
+
+    fn try_from(o: Option<Object>) -> Result<Self, Self::Error> {
+        serde_json::from_value(serde_json::Value::Object(o.unwrap_or_default())).map_err(|e| {
+            ApiError::internal_error(format!(
+                "Could not parse APPROVE operation metadata from metadata JSON object: {}",
+                e
+            ))
+        })
+    }
+}
+
+impl From<ApproveMetadata> for Object {
+    fn from(m: ApproveMetadata) -> Self {
+        match serde_json::to_value(m) {
+            Ok(Value::Object(o)) => o,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// The metadata of `TRANSFER_FROM` operations.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SpenderMetadata {
+    pub spender: AccountIdentifier,
+}
+
+impl TryFrom<Option<Object>> for SpenderMetadata {
+    type Error = ApiError;
+
+    fn try_from(o: Option<Object>) -> Result<Self, Self::Error> {
+        serde_json::from_value(serde_json::Value::Object(o.unwrap_or_default())).map_err(|e| {
+            ApiError::internal_error(format!(
+                "Could not parse a `spender` from metadata JSON object: {}",
+                e
+            ))
+        })
+    }
+}
+
+impl From<SpenderMetadata> for Object {
+    fn from(m: SpenderMetadata) -> Self {
+        match serde_json::to_value(m) {
+            Ok(Value::Object(o)) => o,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// The metadata of `SPAWN` operations.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SpawnMetadata {
+    #[serde(default)]
+    pub neuron_identifier: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub controller: Option<PrincipalId>,
+}
+
+impl TryFrom<Option<Object>> for SpawnMetadata {
+    type Error = ApiError;
+
+    fn try_from(o: Option<Object>) -> Result<Self, Self::Error> {
+        serde_json::from_value(serde_json::Value::Object(o.unwrap_or_default())).map_err(|e| {
+            ApiError::internal_error(format!(
+                "Could not parse SPAWN operation metadata from metadata JSON object: {}",
+                e
+            ))
+        })
+    }
+}
+
+impl From<SpawnMetadata> for Object {
+    fn from(m: SpawnMetadata) -> Self {
+        match serde_json::to_value(m) {
+            Ok(Value::Object(o)) => o,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// The metadata of `MERGE_MATURITY` operations.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MergeMaturityMetadata {
+    #[serde(default)]
+    pub neuron_identifier: u64,
+    pub percentage_to_merge: u32,
+}
+
+impl TryFrom<Option<Object>> for MergeMaturityMetadata {
+    type Error = ApiError;
+
+    fn try_from(o: Option<Object>) -> Result<Self, Self::Error> {
+        serde_json::from_value(serde_json::Value::Object(o.unwrap_or_default())).map_err(|e| {
+            ApiError::internal_error(format!(
+                "Could not parse MERGE_MATURITY operation metadata from metadata JSON object: {}",
+                e
+            ))
+        })
+    }
+}
+
+impl From<MergeMaturityMetadata> for Object {
+    fn from(m: MergeMaturityMetadata) -> Self {
+        match serde_json::to_value(m) {
+            Ok(Value::Object(o)) => o,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// The metadata of `FOLLOW` operations.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FollowMetadata {
+    #[serde(default)]
+    pub neuron_identifier: u64,
+    pub topic: i32,
+    pub followees: Vec<u64>,
+}
+
+impl TryFrom<Option<Object>> for FollowMetadata {
+    type Error = ApiError;
+
+    fn try_from(o: Option<Object>) -> Result<Self, Self::Error> {
+        serde_json::from_value(serde_json::Value::Object(o.unwrap_or_default())).map_err(|e| {
+            ApiError::internal_error(format!(
+                "Could not parse FOLLOW operation metadata from metadata JSON object: {}",
+                e
+            ))
+        })
+    }
+}
+
+impl From<FollowMetadata> for Object {
+    fn from(m: FollowMetadata) -> Self {
+        match serde_json::to_value(m) {
+            Ok(Value::Object(o)) => o,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// The metadata of `REGISTER_VOTE` operations.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RegisterVoteMetadata {
+    #[serde(default)]
+    pub neuron_identifier: u64,
+    pub proposal: u64,
+    pub vote: i32,
+}
+
+impl TryFrom<Option<Object>> for RegisterVoteMetadata {
+    type Error = ApiError;
+
+    fn try_from(o: Option<Object>) -> Result<Self, Self::Error> {
+        serde_json::from_value(serde_json::Value::Object(o.unwrap_or_default())).map_err(|e| {
+            ApiError::internal_error(format!(
+                "Could not parse REGISTER_VOTE operation metadata from metadata JSON object: {}",
+                e
+            ))
+        })
+    }
+}
+
+impl From<RegisterVoteMetadata> for Object {
+    fn from(m: RegisterVoteMetadata) -> Self {
+        match serde_json::to_value(m) {
+            Ok(Value::Object(o)) => o,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// The metadata of `DISBURSE_TO_NEURON` operations.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DisburseToNeuronMetadata {
+    #[serde(default)]
+    pub neuron_identifier: u64,
+    pub controller: PrincipalId,
+    pub dissolve_delay_seconds: u64,
+    pub kyc_verified: bool,
+    pub nonce: u64,
+}
+
+impl TryFrom<Option<Object>> for DisburseToNeuronMetadata {
+    type Error = ApiError;
+
+    fn try_from(o: Option<Object>) -> Result<Self, Self::Error> {
+        serde_json::from_value(serde_json::Value::Object(o.unwrap_or_default())).map_err(|e| {
+            ApiError::internal_error(format!(
+                "Could not parse DISBURSE_TO_NEURON operation metadata from metadata JSON object: {}",
+                e
+            ))
+        })
+    }
+}
+
+impl From<DisburseToNeuronMetadata> for Object {
+    fn from(m: DisburseToNeuronMetadata) -> Self {
+        match serde_json::to_value(m) {
+            Ok(Value::Object(o)) => o,
+            _ => unreachable!(),
+        }
+    }
+}
+
 /// Transaction is a bit of a misnomer, since operations can succeed or fail
 /// independently from a Transaction.
 pub struct TransactionBuilder {
@@ -817,7 +1342,7 @@ impl TransactionBuilder {
     }
 
     /// Add a `Request::Transfer` to the Transaction.
-    /// This handles `Send`, `Mint`, and `Burn`.
+    /// This handles `Send`, `Mint`, `Burn`, `Approve` and `TransferFrom`.
     pub fn transfer(&mut self, operation: &LedgerOperation) -> Result<(), ApiError> {
         match operation {
             LedgerOperation::Burn { from, amount } => {
@@ -889,6 +1414,92 @@ impl TransactionBuilder {
                     metadata: None,
                 });
             }
+            LedgerOperation::Approve {
+                from,
+                spender,
+                allowance,
+                expected_allowance,
+                expires_at,
+                fee,
+            } => {
+                let from_account = Some(to_model_account_identifier(from));
+
+                let operation_identifier = self.allocate_op_id();
+                self.ops.push(Operation {
+                    operation_identifier,
+                    _type: APPROVE.to_string(),
+                    status: None,
+                    account: from_account.clone(),
+                    amount: None,
+                    related_operations: None,
+                    coin_change: None,
+                    metadata: Some(
+                        ApproveMetadata {
+                            spender: *spender,
+                            allowance: *allowance,
+                            expected_allowance: *expected_allowance,
+                            expires_at: *expires_at,
+                        }
+                        .into(),
+                    ),
+                });
+                let operation_identifier = self.allocate_op_id();
+                self.ops.push(Operation {
+                    operation_identifier,
+                    _type: FEE.to_string(),
+                    status: None,
+                    account: from_account,
+                    amount: Some(signed_amount(-(fee.get_e8s() as i128))),
+                    related_operations: None,
+                    coin_change: None,
+                    metadata: None,
+                });
+            }
+            LedgerOperation::TransferFrom {
+                from,
+                to,
+                spender,
+                amount,
+                fee,
+            } => {
+                let from_account = Some(to_model_account_identifier(from));
+                let amount = i128::from(amount.get_e8s());
+                let metadata: Object = SpenderMetadata { spender: *spender }.into();
+
+                let operation_identifier = self.allocate_op_id();
+                self.ops.push(Operation {
+                    operation_identifier,
+                    _type: TRANSFER_FROM.to_string(),
+                    status: None,
+                    account: from_account.clone(),
+                    amount: Some(signed_amount(-amount)),
+                    related_operations: None,
+                    coin_change: None,
+                    metadata: Some(metadata.clone()),
+                });
+                let operation_identifier = self.allocate_op_id();
+                self.ops.push(Operation {
+                    operation_identifier,
+                    _type: TRANSFER_FROM.to_string(),
+                    status: None,
+                    account: Some(to_model_account_identifier(to)),
+                    amount: Some(signed_amount(amount)),
+                    related_operations: None,
+                    coin_change: None,
+                    metadata: Some(metadata),
+                });
+                let operation_identifier = self.allocate_op_id();
+                self.ops.push(Operation {
+                    operation_identifier,
+                    _type: FEE.to_string(),
+                    status: None,
+                    account: from_account,
+                    amount: Some(signed_amount(-(fee.get_e8s() as i128))),
+                    related_operations: None,
+                    coin_change: None,
+                    metadata: None,
+                });
+            }
         };
         Ok(())
     }
@@ -1037,4 +1648,189 @@ impl TransactionBuilder {
             ),
         });
     }
+
+    pub fn remove_hot_key(&mut self, key: &RemoveHotKey) {
+        let RemoveHotKey {
+            account,
+            neuron_identifier,
+            key,
+        } = key;
+        let operation_identifier = self.allocate_op_id();
+        self.ops.push(Operation {
+            operation_identifier,
+            _type: REMOVE_HOT_KEY.to_string(),
+            status: None,
+            account: Some(to_model_account_identifier(account)),
+            amount: None,
+            related_operations: None,
+            coin_change: None,
+            metadata: Some(
+                KeyMetadata {
+                    key: key.clone(),
+                    neuron_identifier: *neuron_identifier,
+                }
+                .into(),
+            ),
+        });
+    }
+
+    pub fn spawn(&mut self, spawn: &Spawn) {
+        let Spawn {
+            account,
+            neuron_identifier,
+            controller,
+        } = spawn;
+        let operation_identifier = self.allocate_op_id();
+        self.ops.push(Operation {
+            operation_identifier,
+            _type: SPAWN.to_string(),
+            status: None,
+            account: Some(to_model_account_identifier(account)),
+            amount: None,
+            related_operations: None,
+            coin_change: None,
+            metadata: Some(
+                SpawnMetadata {
+                    neuron_identifier: *neuron_identifier,
+                    controller: *controller,
+                }
+                .into(),
+            ),
+        });
+    }
+
+    pub fn merge_maturity(&mut self, merge_maturity: &MergeMaturity) {
+        let MergeMaturity {
+            account,
+            neuron_identifier,
+            percentage_to_merge,
+        } = merge_maturity;
+        let operation_identifier = self.allocate_op_id();
+        self.ops.push(Operation {
+            operation_identifier,
+            _type: MERGE_MATURITY.to_string(),
+            status: None,
+            account: Some(to_model_account_identifier(account)),
+            amount: None,
+            related_operations: None,
+            coin_change: None,
+            metadata: Some(
+                MergeMaturityMetadata {
+                    neuron_identifier: *neuron_identifier,
+                    percentage_to_merge: *percentage_to_merge,
+                }
+                .into(),
+            ),
+        });
+    }
+
+    pub fn follow(&mut self, follow: &Follow) {
+        let Follow {
+            account,
+            neuron_identifier,
+            topic,
+            followees,
+        } = follow;
+        let operation_identifier = self.allocate_op_id();
+        self.ops.push(Operation {
+            operation_identifier,
+            _type: FOLLOW.to_string(),
+            status: None,
+            account: Some(to_model_account_identifier(account)),
+            amount: None,
+            related_operations: None,
+            coin_change: None,
+            metadata: Some(
+                FollowMetadata {
+                    neuron_identifier: *neuron_identifier,
+                    topic: *topic,
+                    followees: followees.clone(),
+                }
+                .into(),
+            ),
+        });
+    }
+
+    pub fn register_vote(&mut self, register_vote: &RegisterVote) {
+        let RegisterVote {
+            account,
+            neuron_identifier,
+            proposal,
+            vote,
+        } = register_vote;
+        let operation_identifier = self.allocate_op_id();
+        self.ops.push(Operation {
+            operation_identifier,
+            _type: REGISTER_VOTE.to_string(),
+            status: None,
+            account: Some(to_model_account_identifier(account)),
+            amount: None,
+            related_operations: None,
+            coin_change: None,
+            metadata: Some(
+                RegisterVoteMetadata {
+                    neuron_identifier: *neuron_identifier,
+                    proposal: *proposal,
+                    vote: *vote,
+                }
+                .into(),
+            ),
+        });
+    }
+
+    pub fn split(&mut self, split: &Split) {
+        let Split {
+            account,
+            neuron_identifier,
+            amount,
+        } = split;
+        let operation_identifier = self.allocate_op_id();
+        self.ops.push(Operation {
+            operation_identifier,
+            _type: SPLIT.to_string(),
+            status: None,
+            account: Some(to_model_account_identifier(account)),
+            amount: Some(amount_(*amount).expect("failed to convert amount")),
+            related_operations: None,
+            coin_change: None,
+            metadata: Some(
+                NeuronIdentifierMetadata {
+                    neuron_identifier: *neuron_identifier,
+                }
+                .into(),
+            ),
+        });
+    }
+
+    pub fn disburse_to_neuron(&mut self, disburse: &DisburseToNeuron) {
+        let DisburseToNeuron {
+            account,
+            neuron_identifier,
+            amount,
+            controller,
+            dissolve_delay_seconds,
+            kyc_verified,
+            nonce,
+        } = disburse;
+        let operation_identifier = self.allocate_op_id();
+        self.ops.push(Operation {
+            operation_identifier,
+            _type: DISBURSE_TO_NEURON.to_string(),
+            status: None,
+            account: Some(to_model_account_identifier(account)),
+            amount: Some(amount_(*amount).expect("failed to convert amount")),
+            related_operations: None,
+            coin_change: None,
+            metadata: Some(
+                DisburseToNeuronMetadata {
+                    neuron_identifier: *neuron_identifier,
+                    controller: *controller,
+                    dissolve_delay_seconds: *dissolve_delay_seconds,
+                    kyc_verified: *kyc_verified,
+                    nonce: *nonce,
+                }
+                .into(),
+            ),
+        });
+    }
 }
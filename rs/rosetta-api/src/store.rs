@@ -3,13 +3,14 @@ use std::convert::TryInto;
 use std::path::Path;
 use std::sync::Mutex;
 
-use rusqlite::{params, Connection};
+use rusqlite::{params, params_from_iter, types::Value, Connection};
 use serde::{Deserialize, Serialize};
 
-use ledger_canister::{AccountIdentifier, BlockHeight, EncodedBlock, HashOf, Tokens};
+use ledger_canister::{AccountIdentifier, BlockHeight, EncodedBlock, HashOf, Operation, Tokens};
 
 use crate::balance_book::BalanceBook;
 use crate::errors::ApiError;
+use crate::request_types::{APPROVE, BURN, FEE, MINT, TRANSACTION, TRANSFER_FROM};
 
 #[derive(candid::CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct HashedBlock {
@@ -56,6 +57,32 @@ impl From<BlockStoreError> for ApiError {
     }
 }
 
+/// Conditions on the transactions returned by
+/// [`SQLiteStore::search_transactions`].
+#[derive(Clone, Debug, Default)]
+pub struct TransactionQuery {
+    /// Matches transactions with an operation on the account, one condition
+    /// per account.
+    pub accounts: Vec<AccountIdentifier>,
+    /// Matches transactions with an operation of this type.
+    pub operation_type: Option<String>,
+    /// Matches the transaction in the block at this height.
+    pub block: Option<BlockHeight>,
+    /// Whether transactions have to match any rather than all of the above
+    /// conditions.
+    pub match_any: bool,
+    /// Only transactions in blocks at or above this height are returned.
+    pub min_block: Option<BlockHeight>,
+    /// Only transactions in blocks at or below this height are returned.
+    pub max_block: Option<BlockHeight>,
+}
+
+impl TransactionQuery {
+    pub fn has_conditions(&self) -> bool {
+        !self.accounts.is_empty() || self.operation_type.is_some() || self.block.is_some()
+    }
+}
+
 pub trait BlockStore {
     fn get_at(&self, index: BlockHeight) -> Result<HashedBlock, BlockStoreError>;
     fn get_range(
@@ -128,6 +155,8 @@ impl SQLiteStore {
             })?;
         }
 
+        store.index_missing_operations()?;
+
         // Read last verified index (if any).
         {
             let connection = store.connection.lock().unwrap();
@@ -184,10 +213,32 @@ impl SQLiteStore {
             "#,
             [],
         )?;
+        // Index of the accounts and types of the operations in each block,
+        // used to search transactions.
+        connection.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS operations (
+                block_idx INTEGER NOT NULL,
+                account VARCHAR(64) NOT NULL,
+                op_type VARCHAR(16) NOT NULL,
+                PRIMARY KEY(block_idx,account,op_type),
+                FOREIGN KEY(block_idx) REFERENCES blocks(idx)
+            )
+            "#,
+            [],
+        )?;
+        connection.execute(
+            "CREATE INDEX IF NOT EXISTS operations_account_idx ON operations(account,block_idx)",
+            [],
+        )?;
+        connection.execute(
+            "CREATE INDEX IF NOT EXISTS operations_type_idx ON operations(op_type,block_idx)",
+            [],
+        )?;
         Ok(())
     }
 
-    fn execute_push(connection: &mut Connection, hb: HashedBlock) -> Result<(), BlockStoreError> {
+    fn execute_push(connection: &Connection, hb: HashedBlock) -> Result<(), BlockStoreError> {
         let hash = hb.hash.into_bytes().to_vec();
         let parent_hash = hb.parent_hash.map(|ph| ph.into_bytes().to_vec());
         connection
@@ -196,9 +247,150 @@ impl SQLiteStore {
                 params![hash, hb.block.0, parent_hash, hb.index],
             )
             .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+        Self::execute_index_operations(connection, hb.index, &hb.block)
+    }
+
+    /// Adds the operations of the block at height `index` to the
+    /// `operations` table.
+    fn execute_index_operations(
+        connection: &Connection,
+        index: BlockHeight,
+        block: &EncodedBlock,
+    ) -> Result<(), BlockStoreError> {
+        let block = block
+            .decode()
+            .map_err(|e| BlockStoreError::Other(format!("Cannot decode block {}: {}", index, e)))?;
+        let mut stmt = connection
+            .prepare_cached(
+                "INSERT OR IGNORE INTO operations (block_idx, account, op_type) VALUES (?1, ?2, ?3)",
+            )
+            .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+        for (account, op_type) in operation_accounts(&block.transaction.operation) {
+            stmt.execute(params![index, account.to_hex(), op_type])
+                .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+        }
         Ok(())
     }
 
+    /// Indexes the operations of the blocks that were stored before the
+    /// `operations` table existed.
+    fn index_missing_operations(&self) -> Result<(), BlockStoreError> {
+        let mut connection = self.connection.lock().unwrap();
+        let tx = connection
+            .transaction()
+            .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+        let mut num_indexed = 0;
+        {
+            let last_indexed: Option<BlockHeight> = tx
+                .query_row("SELECT MAX(block_idx) FROM operations", [], |row| {
+                    row.get(0)
+                })
+                .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+            // The operations of the oldest block are dropped from the index
+            // when pruning, see `prune`.
+            let indexed_up_to = last_indexed.max(self.first_block.as_ref().map(|b| b.index));
+            let mut stmt = tx
+                .prepare("SELECT idx, block FROM blocks WHERE idx > ? ORDER BY idx")
+                .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+            let mut rows = stmt
+                .query(params![indexed_up_to.map(|i| i as i64).unwrap_or(-1)])
+                .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+            while let Some(row) = rows
+                .next()
+                .map_err(|e| BlockStoreError::Other(e.to_string()))?
+            {
+                let index: BlockHeight = row
+                    .get(0)
+                    .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+                let block: Vec<u8> = row
+                    .get(1)
+                    .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+                Self::execute_index_operations(
+                    &tx,
+                    index,
+                    &EncodedBlock::from(block.into_boxed_slice()),
+                )?;
+                num_indexed += 1;
+            }
+        }
+        tx.commit()
+            .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+        if num_indexed > 0 {
+            debug!("Indexed the operations of {} blocks", num_indexed);
+        }
+        Ok(())
+    }
+
+    /// Returns the heights of the verified blocks whose transactions match
+    /// `query`, highest first, skipping the first `offset` ones and returning
+    /// at most `limit` of them, together with the total number of matching
+    /// transactions.
+    ///
+    /// The transactions of pruned blocks and of the oldest block kept by
+    /// pruning are not searched.
+    pub fn search_transactions(
+        &self,
+        query: &TransactionQuery,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<BlockHeight>, usize), BlockStoreError> {
+        let max_block = match (self.last_verified_idx, query.max_block) {
+            (None, _) => return Ok((vec![], 0)),
+            (Some(last_verified), None) => last_verified,
+            (Some(last_verified), Some(max_block)) => last_verified.min(max_block),
+        };
+        let mut filter = "block_idx >= ? AND block_idx <= ?".to_string();
+        let mut values = vec![
+            Value::Integer(query.min_block.unwrap_or(0).min(i64::MAX as u64) as i64),
+            Value::Integer(max_block as i64),
+        ];
+
+        let mut conditions = vec![];
+        for account in &query.accounts {
+            conditions.push("block_idx IN (SELECT block_idx FROM operations WHERE account = ?)");
+            values.push(Value::Text(account.to_hex()));
+        }
+        if let Some(op_type) = &query.operation_type {
+            conditions.push("block_idx IN (SELECT block_idx FROM operations WHERE op_type = ?)");
+            values.push(Value::Text(op_type.clone()));
+        }
+        if let Some(block) = query.block {
+            conditions.push("block_idx = ?");
+            values.push(Value::Integer(block.min(i64::MAX as u64) as i64));
+        }
+        if !conditions.is_empty() {
+            let operator = if query.match_any { " OR " } else { " AND " };
+            filter = format!("{} AND ({})", filter, conditions.join(operator));
+        }
+
+        let connection = self.connection.lock().unwrap();
+        let total_count: i64 = connection
+            .query_row(
+                &format!(
+                    "SELECT COUNT(DISTINCT block_idx) FROM operations WHERE {}",
+                    filter
+                ),
+                params_from_iter(values.iter()),
+                |row| row.get(0),
+            )
+            .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+
+        values.push(Value::Integer(limit.min(i64::MAX as usize) as i64));
+        values.push(Value::Integer(offset.min(i64::MAX as usize) as i64));
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT DISTINCT block_idx FROM operations WHERE {} ORDER BY block_idx DESC LIMIT ? OFFSET ?",
+                filter
+            ))
+            .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+        let heights = stmt
+            .query_map(params_from_iter(values.iter()), |row| row.get(0))
+            .map_err(|e| BlockStoreError::Other(e.to_string()))?
+            .collect::<Result<Vec<BlockHeight>, _>>()
+            .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+        Ok((heights, total_count as usize))
+    }
+
     fn read_oldest_block_snapshot(&self) -> Result<Option<(HashedBlock, BalanceBook)>, String> {
         let mut balance_book = BalanceBook::default();
         let last_index;
@@ -335,6 +527,31 @@ impl SQLiteStore {
     }
 }
 
+/// Returns the accounts and types of the Rosetta operations that `operation`
+/// is converted to, see `convert::requests_to_operations`. The spender of
+/// `Approve` and `TransferFrom` operations, which is only part of the
+/// operation metadata, is indexed as well.
+fn operation_accounts(operation: &Operation) -> Vec<(AccountIdentifier, &'static str)> {
+    match operation {
+        Operation::Burn { from, .. } => vec![(*from, BURN)],
+        Operation::Mint { to, .. } => vec![(*to, MINT)],
+        Operation::Transfer { from, to, .. } => {
+            vec![(*from, TRANSACTION), (*to, TRANSACTION), (*from, FEE)]
+        }
+        Operation::Approve { from, spender, .. } => {
+            vec![(*from, APPROVE), (*spender, APPROVE), (*from, FEE)]
+        }
+        Operation::TransferFrom {
+            from, to, spender, ..
+        } => vec![
+            (*from, TRANSFER_FROM),
+            (*to, TRANSFER_FROM),
+            (*spender, TRANSFER_FROM),
+            (*from, FEE),
+        ],
+    }
+}
+
 fn vec_into_array(v: Vec<u8>) -> [u8; 32] {
     let ba: Box<[u8; 32]> = match v.into_boxed_slice().try_into() {
         Ok(ba) => ba,
@@ -407,7 +624,12 @@ impl BlockStore for SQLiteStore {
 
     fn push(&mut self, hb: HashedBlock) -> Result<(), BlockStoreError> {
         let mut connection = self.connection.lock().unwrap();
-        Self::execute_push(&mut *connection, hb)
+        let tx = connection
+            .transaction()
+            .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+        Self::execute_push(&tx, hb)?;
+        tx.commit()
+            .map_err(|e| BlockStoreError::Other(e.to_string()))
     }
 
     fn push_batch(&mut self, batch: Vec<HashedBlock>) -> Result<(), BlockStoreError> {
@@ -418,7 +640,7 @@ impl BlockStore for SQLiteStore {
             .map_err(|e| BlockStoreError::Other(format!("{}", e)))?;
 
         for hb in batch {
-            match Self::execute_push(&mut *connection, hb) {
+            match Self::execute_push(&*connection, hb) {
                 Ok(_) => (),
                 Err(e) => {
                     connection
@@ -446,6 +668,13 @@ impl BlockStore for SQLiteStore {
             params![hb.index],
         )
         .map_err(|e| e.to_string())?;
+        // The oldest block only serves as the balance snapshot, so its
+        // transaction is not searchable anymore either.
+        tx.execute(
+            "DELETE FROM operations WHERE block_idx <= ?",
+            params![hb.index],
+        )
+        .map_err(|e| e.to_string())?;
         tx.execute(
             "DELETE FROM balances WHERE block_idx > 0 AND block_idx < ?",
             params![hb.index],
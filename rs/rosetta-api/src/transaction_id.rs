@@ -67,7 +67,14 @@ impl TransactionIdentifier {
             | RequestType::StopDissolve { .. }
             | RequestType::SetDissolveTimestamp { .. }
             | RequestType::Disburse { .. }
-            | RequestType::AddHotKey { .. } => {
+            | RequestType::AddHotKey { .. }
+            | RequestType::RemoveHotKey { .. }
+            | RequestType::Spawn { .. }
+            | RequestType::MergeMaturity { .. }
+            | RequestType::Follow { .. }
+            | RequestType::RegisterVote { .. }
+            | RequestType::Split { .. }
+            | RequestType::DisburseToNeuron { .. } => {
                 // Unfortunately, staking operations don't really have a transaction ID
                 Ok(TransactionIdentifier {
                     hash: NEURON_MANAGEMEN_PSEUDO_HASH.to_string(),
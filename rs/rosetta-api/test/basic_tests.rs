@@ -454,12 +454,16 @@ async fn verify_account_search(
             ledger_canister::Operation::Mint { to, .. } => {
                 history.entry(to).or_insert_with(Vec::new).push(hb.index);
             }
-            ledger_canister::Operation::Transfer { from, to, .. } => {
+            ledger_canister::Operation::Transfer { from, to, .. }
+            | ledger_canister::Operation::TransferFrom { from, to, .. } => {
                 history.entry(from).or_insert_with(Vec::new).push(hb.index);
                 if from != to {
                     history.entry(to).or_insert_with(Vec::new).push(hb.index);
                 }
             }
+            ledger_canister::Operation::Approve { from, .. } => {
+                history.entry(from).or_insert_with(Vec::new).push(hb.index);
+            }
         }
     }
 
@@ -0,0 +1,162 @@
+use super::*;
+
+use ic_nns_governance::pb::v1::{Topic, Vote};
+use ic_rosetta_api::convert::from_operations;
+use ic_rosetta_api::models::{
+    ConstructionCombineRequest, ConstructionParseRequest, ConstructionPayloadsRequest,
+};
+use ic_rosetta_api::request_types::{
+    DisburseToNeuron, Follow, MergeMaturity, PublicKeyOrPrincipal, RegisterVote, RemoveHotKey,
+    Spawn, Split,
+};
+use ic_rosetta_api::RosettaRequestHandler;
+use ic_rosetta_test_utils::{make_user, sign_payloads};
+
+async fn parse_requests(
+    req_handler: &RosettaRequestHandler,
+    signed: bool,
+    transaction: String,
+) -> (Vec<Request>, Vec<ic_rosetta_api::models::AccountIdentifier>) {
+    let res = req_handler
+        .construction_parse(ConstructionParseRequest::new(
+            req_handler.network_id(),
+            signed,
+            transaction,
+        ))
+        .await
+        .unwrap();
+    (
+        from_operations(&res.operations, false).unwrap(),
+        res.account_identifier_signers.unwrap(),
+    )
+}
+
+#[actix_rt::test]
+async fn neuron_management_construction_round_trip() {
+    init_test_logger();
+
+    let req_handler = RosettaRequestHandler::new(Arc::new(TestLedger::new()));
+    let (account, keypair, public_key, _) = make_user(1);
+    let (_, _, _, other_principal) = make_user(2);
+
+    let requests = vec![
+        Request::Spawn(Spawn {
+            account,
+            neuron_identifier: 1,
+            controller: None,
+        }),
+        Request::Spawn(Spawn {
+            account,
+            neuron_identifier: 2,
+            controller: Some(other_principal),
+        }),
+        Request::MergeMaturity(MergeMaturity {
+            account,
+            neuron_identifier: 1,
+            percentage_to_merge: 40,
+        }),
+        Request::Follow(Follow {
+            account,
+            neuron_identifier: 1,
+            topic: Topic::NetworkEconomics as i32,
+            followees: vec![27, 28],
+        }),
+        Request::Follow(Follow {
+            account,
+            neuron_identifier: 2,
+            topic: Topic::Unspecified as i32,
+            followees: vec![],
+        }),
+        Request::RegisterVote(RegisterVote {
+            account,
+            neuron_identifier: 1,
+            proposal: 1234,
+            vote: Vote::Yes as i32,
+        }),
+        Request::Split(Split {
+            account,
+            neuron_identifier: 1,
+            amount: Tokens::new(5, 0).unwrap(),
+        }),
+        Request::DisburseToNeuron(DisburseToNeuron {
+            account,
+            neuron_identifier: 2,
+            amount: Tokens::new(2, 0).unwrap(),
+            controller: other_principal,
+            dissolve_delay_seconds: 6 * 30 * 24 * 3600,
+            kyc_verified: true,
+            nonce: 3,
+        }),
+        Request::RemoveHotKey(RemoveHotKey {
+            account,
+            neuron_identifier: 1,
+            key: PublicKeyOrPrincipal::Principal(other_principal),
+        }),
+    ];
+
+    let payloads = req_handler
+        .construction_payloads(ConstructionPayloadsRequest {
+            network_identifier: req_handler.network_id(),
+            operations: Request::requests_to_operations(&requests).unwrap(),
+            metadata: None,
+            public_keys: Some(vec![public_key]),
+        })
+        .await
+        .unwrap();
+
+    let signers = vec![to_model_account_identifier(&account)];
+    assert_eq!(
+        parse_requests(&req_handler, false, payloads.unsigned_transaction.clone()).await,
+        (requests.clone(), signers.clone())
+    );
+
+    let signed = req_handler
+        .construction_combine(ConstructionCombineRequest::new(
+            req_handler.network_id(),
+            payloads.unsigned_transaction,
+            sign_payloads(&[Arc::new(keypair)], payloads.payloads),
+        ))
+        .await
+        .unwrap();
+    let signed_transaction = signed.signed_transaction().unwrap();
+    assert_eq!(signed_transaction.len(), requests.len());
+    for ((request_type, _), request) in signed_transaction.iter().zip(requests.iter()) {
+        assert_eq!(*request_type, request.request_type().unwrap());
+    }
+
+    assert_eq!(
+        parse_requests(&req_handler, true, signed.signed_transaction).await,
+        (requests, signers)
+    );
+}
+
+#[actix_rt::test]
+async fn remove_hot_key_by_public_key_round_trip() {
+    init_test_logger();
+
+    let req_handler = RosettaRequestHandler::new(Arc::new(TestLedger::new()));
+    let (account, _, public_key, _) = make_user(1);
+    let (_, _, hot_key, _) = make_user(2);
+
+    let request = Request::RemoveHotKey(RemoveHotKey {
+        account,
+        neuron_identifier: 0,
+        key: PublicKeyOrPrincipal::PublicKey(hot_key),
+    });
+
+    let payloads = req_handler
+        .construction_payloads(ConstructionPayloadsRequest {
+            network_identifier: req_handler.network_id(),
+            operations: Request::requests_to_operations(&[request.clone()]).unwrap(),
+            metadata: None,
+            public_keys: Some(vec![public_key]),
+        })
+        .await
+        .unwrap();
+
+    // The hot key is sent to governance as a principal, which compares equal
+    // to the public key it is derived from.
+    let (parsed, _) =
+        parse_requests(&req_handler, false, payloads.unsigned_transaction.clone()).await;
+    assert_eq!(parsed, vec![request]);
+}
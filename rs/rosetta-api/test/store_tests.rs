@@ -1,5 +1,5 @@
 use super::*;
-use ic_rosetta_api::store::{BlockStore, BlockStoreError, SQLiteStore};
+use ic_rosetta_api::store::{BlockStore, BlockStoreError, SQLiteStore, TransactionQuery};
 use std::path::Path;
 
 pub(crate) fn sqlite_on_disk_store(path: &Path) -> SQLiteStore {
@@ -126,6 +126,193 @@ async fn store_prune_and_load_test() {
     verify_balance_snapshot(&scribe, &mut store, 30);
 }
 
+#[actix_rt::test]
+async fn store_search_transactions_test() {
+    init_test_logger();
+    let tmpdir = create_tmp_dir();
+    let mut store = sqlite_on_disk_store(tmpdir.path());
+    let mut scribe = Scribe::new_with_sample_data(10, 100);
+    // The spenders are only involved through the operation metadata.
+    let (from, to, spender) = (scribe.accounts[0], scribe.accounts[1], scribe.accounts[2]);
+    scribe.approve(from, spender, 1000);
+    scribe.transfer_from(from, to, spender, 100);
+
+    for hb in &scribe.blockchain {
+        store.push(hb.clone()).unwrap();
+    }
+
+    // Only verified blocks are searched.
+    assert_eq!(
+        store
+            .search_transactions(&TransactionQuery::default(), 0, usize::MAX)
+            .unwrap(),
+        (vec![], 0)
+    );
+
+    store.mark_last_verified(60).unwrap();
+    verify_search(&scribe, &store, 0, 60);
+
+    let last_idx = scribe.blockchain.back().unwrap().index;
+    store.mark_last_verified(last_idx).unwrap();
+    verify_search(&scribe, &store, 0, last_idx);
+}
+
+#[actix_rt::test]
+async fn store_search_transactions_prune_test() {
+    init_test_logger();
+    let tmpdir = create_tmp_dir();
+    let mut store = sqlite_on_disk_store(tmpdir.path());
+    let scribe = Scribe::new_with_sample_data(10, 100);
+
+    for hb in &scribe.blockchain {
+        store.push(hb.clone()).unwrap();
+    }
+    let last_idx = scribe.blockchain.back().unwrap().index;
+    store.mark_last_verified(last_idx).unwrap();
+
+    // The transaction of the oldest block kept by pruning is not searchable.
+    prune(&scribe, &mut store, 10);
+    verify_search(&scribe, &store, 11, last_idx);
+
+    prune(&scribe, &mut store, 20);
+    verify_search(&scribe, &store, 21, last_idx);
+
+    drop(store);
+    // Now reload from disk
+    let mut store = sqlite_on_disk_store(tmpdir.path());
+    verify_search(&scribe, &store, 21, last_idx);
+
+    prune(&scribe, &mut store, 30);
+    verify_search(&scribe, &store, 31, last_idx);
+}
+
+/// Returns the heights of the blocks in `[first_idx, last_idx]` whose
+/// operation satisfies `pred`, highest first.
+fn expected_heights(
+    scribe: &Scribe,
+    first_idx: u64,
+    last_idx: u64,
+    pred: impl Fn(&Operation) -> bool,
+) -> Vec<BlockHeight> {
+    scribe
+        .blockchain
+        .iter()
+        .rev()
+        .filter(|hb| first_idx <= hb.index && hb.index <= last_idx)
+        .filter(|hb| pred(&hb.block.decode().unwrap().transaction.operation))
+        .map(|hb| hb.index)
+        .collect()
+}
+
+fn involves(operation: &Operation, account: &AccountIdentifier) -> bool {
+    match operation {
+        Operation::Burn { from, .. } => from == account,
+        Operation::Mint { to, .. } => to == account,
+        Operation::Transfer { from, to, .. } => from == account || to == account,
+        Operation::Approve { from, spender, .. } => from == account || spender == account,
+        Operation::TransferFrom {
+            from, to, spender, ..
+        } => from == account || to == account || spender == account,
+    }
+}
+
+fn search_all(store: &SQLiteStore, query: &TransactionQuery) -> Vec<BlockHeight> {
+    let (heights, total_count) = store.search_transactions(query, 0, usize::MAX).unwrap();
+    assert_eq!(heights.len(), total_count);
+    heights
+}
+
+fn verify_search(scribe: &Scribe, store: &SQLiteStore, first_idx: u64, last_idx: u64) {
+    assert_eq!(
+        search_all(store, &TransactionQuery::default()),
+        expected_heights(scribe, first_idx, last_idx, |_| true)
+    );
+
+    for acc in &scribe.accounts {
+        let query = TransactionQuery {
+            accounts: vec![*acc],
+            ..Default::default()
+        };
+        let expected = expected_heights(scribe, first_idx, last_idx, |op| involves(op, acc));
+        assert_eq!(search_all(store, &query), expected);
+
+        let (heights, total_count) = store.search_transactions(&query, 2, 3).unwrap();
+        assert_eq!(total_count, expected.len());
+        assert_eq!(
+            heights,
+            expected.iter().skip(2).take(3).cloned().collect::<Vec<_>>()
+        );
+
+        let middle_idx = (first_idx + last_idx) / 2;
+        let query = TransactionQuery {
+            accounts: vec![*acc],
+            min_block: Some(middle_idx),
+            max_block: Some(last_idx - 5),
+            ..Default::default()
+        };
+        assert_eq!(
+            search_all(store, &query),
+            expected_heights(scribe, middle_idx, last_idx - 5, |op| involves(op, acc))
+        );
+    }
+
+    let op_types: [(&str, fn(&Operation) -> bool); 7] = [
+        ("MINT", |op| matches!(op, Operation::Mint { .. })),
+        ("BURN", |op| matches!(op, Operation::Burn { .. })),
+        ("TRANSACTION", |op| matches!(op, Operation::Transfer { .. })),
+        ("FEE", |op| {
+            matches!(
+                op,
+                Operation::Transfer { .. }
+                    | Operation::Approve { .. }
+                    | Operation::TransferFrom { .. }
+            )
+        }),
+        ("APPROVE", |op| matches!(op, Operation::Approve { .. })),
+        ("TRANSFER_FROM", |op| {
+            matches!(op, Operation::TransferFrom { .. })
+        }),
+        ("STAKE", |_| false),
+    ];
+    for (op_type, pred) in op_types.iter() {
+        let query = TransactionQuery {
+            operation_type: Some(op_type.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            search_all(store, &query),
+            expected_heights(scribe, first_idx, last_idx, pred)
+        );
+    }
+
+    let acc1 = scribe.accounts[0];
+    let acc2 = scribe.accounts[1];
+    let mut query = TransactionQuery {
+        accounts: vec![acc1, acc2],
+        ..Default::default()
+    };
+    assert_eq!(
+        search_all(store, &query),
+        expected_heights(scribe, first_idx, last_idx, |op| {
+            involves(op, &acc1) && involves(op, &acc2)
+        })
+    );
+    query.match_any = true;
+    query.block = Some(last_idx);
+    assert_eq!(
+        search_all(store, &query),
+        expected_heights(scribe, first_idx, last_idx, |op| {
+            involves(op, &acc1) || involves(op, &acc2)
+        })
+        .into_iter()
+        .chain(std::iter::once(last_idx))
+        .collect::<std::collections::BTreeSet<_>>()
+        .into_iter()
+        .rev()
+        .collect::<Vec<_>>()
+    );
+}
+
 fn prune(scribe: &Scribe, store: &mut impl BlockStore, prune_at: u64) {
     let oldest_idx = prune_at;
     let oldest_block = scribe.blockchain.get(oldest_idx as usize).unwrap();
@@ -1,4 +1,5 @@
 mod basic_tests;
+mod construction_tests;
 mod rosetta_cli_tests;
 mod store_tests;
 
@@ -6,11 +6,12 @@ use ic_rosetta_api::errors::ApiError;
 use ic_rosetta_api::models::Error as RosettaError;
 use ic_rosetta_api::models::{
     ConstructionCombineResponse, ConstructionPayloadsRequestMetadata, ConstructionPayloadsResponse,
-    CurveType, PublicKey, Signature, SignatureType,
+    CurveType, PublicKey, Signature, SignatureType, SigningPayload,
 };
 use ic_rosetta_api::request_types::{
-    AddHotKey, Disburse, Request, RequestResult, SetDissolveTimestamp, Stake, StartDissolve,
-    StopDissolve, TransactionResults,
+    AddHotKey, Disburse, DisburseToNeuron, Follow, MergeMaturity, RegisterVote, RemoveHotKey,
+    Request, RequestResult, SetDissolveTimestamp, Spawn, Split, Stake, StartDissolve, StopDissolve,
+    TransactionResults,
 };
 use ic_rosetta_api::transaction_id::TransactionIdentifier;
 use ic_types::{messages::Blob, time, PrincipalId};
@@ -111,6 +112,13 @@ pub async fn prepare_multiple_txn(
             | Request::StopDissolve(StopDissolve { account, .. })
             | Request::SetDissolveTimestamp(SetDissolveTimestamp { account, .. })
             | Request::AddHotKey(AddHotKey { account, .. })
+            | Request::RemoveHotKey(RemoveHotKey { account, .. })
+            | Request::Spawn(Spawn { account, .. })
+            | Request::MergeMaturity(MergeMaturity { account, .. })
+            | Request::Follow(Follow { account, .. })
+            | Request::RegisterVote(RegisterVote { account, .. })
+            | Request::Split(Split { account, .. })
+            | Request::DisburseToNeuron(DisburseToNeuron { account, .. })
             | Request::Disburse(Disburse { account, .. }) => {
                 all_sender_account_ids.push(to_model_account_identifier(&account));
             }
@@ -120,6 +128,12 @@ pub async fn prepare_multiple_txn(
             Request::Transfer(Operation::Mint { .. }) => {
                 panic!("Mint operations are supported here")
             }
+            Request::Transfer(Operation::Approve { .. }) => {
+                panic!("Approve operations are not supported here")
+            }
+            Request::Transfer(Operation::TransferFrom { .. }) => {
+                panic!("TransferFrom operations are not supported here")
+            }
         };
 
         all_sender_pks.push(to_public_key(&request.sender_keypair));
@@ -233,6 +247,15 @@ pub async fn sign_txn(
     keypairs: &[Arc<EdKeypair>],
     payloads: ConstructionPayloadsResponse,
 ) -> Result<ConstructionCombineResponse, RosettaError> {
+    let signatures = sign_payloads(keypairs, payloads.payloads);
+    ros.construction_combine(payloads.unsigned_transaction, signatures)
+        .await
+        .unwrap()
+}
+
+/// Signs `payloads` with the key pairs of the accounts they belong to, in
+/// random order.
+pub fn sign_payloads(keypairs: &[Arc<EdKeypair>], payloads: Vec<SigningPayload>) -> Vec<Signature> {
     use ed25519_dalek::Signer;
 
     let mut keypairs_map = HashMap::new();
@@ -243,7 +266,6 @@ pub async fn sign_txn(
     }
 
     let mut signatures: Vec<Signature> = payloads
-        .payloads
         .into_iter()
         .map(|p| {
             // Note: if we can't find the right key pair, just use the first one. This is
@@ -269,10 +291,7 @@ pub async fn sign_txn(
     // The order of signatures shouldn't matter.
     let mut rng = thread_rng();
     signatures.shuffle(&mut rng);
-
-    ros.construction_combine(payloads.unsigned_transaction, signatures)
-        .await
-        .unwrap()
+    signatures
 }
 
 // If accept_suggested_fee is false, then Transfer needs to contain a correct
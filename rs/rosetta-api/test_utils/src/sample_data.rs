@@ -15,6 +15,13 @@ enum Trans {
     Buy(AccountIdentifier, Tokens),
     Sell(AccountIdentifier, Tokens),
     Transfer(AccountIdentifier, AccountIdentifier, Tokens),
+    Approve(AccountIdentifier, AccountIdentifier, Tokens),
+    TransferFrom(
+        AccountIdentifier,
+        AccountIdentifier,
+        AccountIdentifier,
+        Tokens,
+    ),
 }
 
 pub struct Scribe {
@@ -156,6 +163,56 @@ impl Scribe {
         self.add_block(transaction);
     }
 
+    pub fn approve(&mut self, from: AccountIdentifier, spender: AccountIdentifier, allowance: u64) {
+        let allowance = Tokens::from_e8s(allowance);
+        self.transactions
+            .push_back(Trans::Approve(from, spender, allowance));
+        *self.balance_book.get_mut(&from).unwrap() -= TRANSACTION_FEE;
+
+        let transaction = Transaction {
+            operation: Operation::Approve {
+                from,
+                spender,
+                allowance,
+                expected_allowance: None,
+                expires_at: None,
+                fee: TRANSACTION_FEE,
+            },
+            memo: self.next_message(),
+            created_at_time: self.time().into(),
+        };
+        self.balance_history.push_back(self.balance_book.clone());
+        self.add_block(transaction);
+    }
+
+    pub fn transfer_from(
+        &mut self,
+        from: AccountIdentifier,
+        to: AccountIdentifier,
+        spender: AccountIdentifier,
+        amount: u64,
+    ) {
+        let amount = Tokens::from_e8s(amount);
+        self.transactions
+            .push_back(Trans::TransferFrom(from, to, spender, amount));
+        *self.balance_book.get_mut(&from).unwrap() -= (amount + TRANSACTION_FEE).unwrap();
+        *self.balance_book.get_mut(&to).unwrap() += amount;
+
+        let transaction = Transaction {
+            operation: Operation::TransferFrom {
+                from,
+                to,
+                spender,
+                amount,
+                fee: TRANSACTION_FEE,
+            },
+            memo: self.next_message(),
+            created_at_time: self.time().into(),
+        };
+        self.balance_history.push_back(self.balance_book.clone());
+        self.add_block(transaction);
+    }
+
     pub fn get_rand_account(&mut self, min_amount: Tokens) -> AccountIdentifier {
         let mut acc_idx = self.dice_num(self.num_accounts()) as usize;
         let mut acc = self.accounts[acc_idx];
@@ -11,11 +11,12 @@ use ic_protobuf::{
     },
 };
 use ic_replicated_state::{
-    CallContextManager, CanisterStatus, ExportedFunctions, Global, NumWasmPages,
+    CallContextManager, CanisterLog, CanisterStatus, CanisterTimer, ExecutionTask,
+    ExportedFunctions, Global, LogVisibility, NumWasmPages, SnapshotId,
 };
 use ic_types::{
     nominal_cycles::NominalCycles, AccumulatedPriority, CanisterId, ComputeAllocation, Cycles,
-    ExecutionRound, Height, MemoryAllocation, PrincipalId,
+    ExecutionRound, Height, MemoryAllocation, PrincipalId, Time,
 };
 use ic_wasm_types::BinaryEncodedWasm;
 use std::convert::{From, TryFrom, TryInto};
@@ -24,7 +25,10 @@ use std::io::Write;
 use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use std::{collections::BTreeSet, sync::Arc};
+use std::{
+    collections::{BTreeSet, VecDeque},
+    sync::Arc,
+};
 
 pub trait CheckpointManager: Send + Sync {
     /// Returns the base directory path managed by checkpoint manager.
@@ -157,6 +161,18 @@ pub struct ExecutionStateBits {
     pub last_executed_round: ExecutionRound,
 }
 
+/// This struct contains bits of a `CanisterSnapshot` that are not already
+/// covered somewhere else and are too small to be serialized separately.
+#[derive(Debug, PartialEq)]
+pub struct CanisterSnapshotBits {
+    pub taken_at_timestamp: Time,
+    pub heap_size: NumWasmPages,
+    pub stable_memory_size: NumWasmPages,
+    pub exported_globals: Vec<Global>,
+    pub exports: ExportedFunctions,
+    pub certified_data: Vec<u8>,
+}
+
 /// This struct contains bits of the `CanisterState` that are not already
 /// covered somewhere else and are too small to be serialized separately.
 #[derive(Debug)]
@@ -179,6 +195,13 @@ pub struct CanisterStateBits {
     pub consumed_cycles_since_replica_started: NominalCycles,
     pub stable_memory_size: NumWasmPages,
     pub heap_delta_debit: NumBytes,
+    pub task_queue: VecDeque<ExecutionTask>,
+    pub canister_log: CanisterLog,
+    pub log_visibility: LogVisibility,
+    pub next_snapshot_id: SnapshotId,
+    pub global_timer: CanisterTimer,
+    pub reserved_balance: Cycles,
+    pub reserved_balance_limit: Option<Cycles>,
 }
 
 /// `StateLayout` provides convenience functions to construct correct
@@ -203,7 +226,13 @@ pub struct CanisterStateBits {
 /// │           ├── vmemory_0.bin
 /// │           ├── canister.pbuf
 /// │           ├── stable_memory.(pbuf|bin)
-/// │           └── software.wasm
+/// │           ├── software.wasm
+/// │           └── snapshots
+/// │               └── <hex(snapshot_id)>
+/// │                   ├── snapshot.pbuf
+/// │                   ├── vmemory_0.bin
+/// │                   ├── stable_memory.bin
+/// │                   └── software.wasm
 /// │
 /// ├── [checkpoints] {owned and varies by checkpoint manager}
 /// │   └──<hex(round)>
@@ -215,7 +244,10 @@ pub struct CanisterStateBits {
 /// │              ├── vmemory_0.bin
 /// │              ├── canister.pbuf
 /// │              ├── stable_memory.(pbuf|bin)
-/// │              └── software.wasm
+/// │              ├── software.wasm
+/// │              └── snapshots
+/// │                  └── <hex(snapshot_id)>
+/// │                      └── ...
 /// │
 /// └── tmp
 /// ```
@@ -749,6 +781,120 @@ impl<Permissions: AccessPolicy> CanisterLayout<Permissions> {
     pub fn is_marked_deleted(&self) -> bool {
         Path::new(&self.tombstone()).exists()
     }
+
+    /// Returns the identifiers of the snapshots stored for this canister,
+    /// excluding the ones that are marked as deleted.
+    pub fn snapshot_ids(&self) -> Result<Vec<SnapshotId>, LayoutError> {
+        let mut result = Vec::new();
+        for id in self.all_snapshot_ids()? {
+            if !self.snapshot(id)?.is_marked_deleted() {
+                result.push(id);
+            }
+        }
+        result.sort_unstable();
+        Ok(result)
+    }
+
+    fn all_snapshot_ids(&self) -> Result<Vec<SnapshotId>, LayoutError> {
+        collect_subdirs(self.canister_root.join("snapshots").as_path(), |p| {
+            u64::from_str_radix(p, 16).unwrap_or_else(|err| {
+                panic!(
+                    "Failed to convert directory name {} into a snapshot id: {}",
+                    p, err
+                )
+            })
+        })
+    }
+
+    pub fn snapshot(
+        &self,
+        snapshot_id: SnapshotId,
+    ) -> Result<SnapshotLayout<Permissions>, LayoutError> {
+        SnapshotLayout::new(
+            self.canister_root
+                .join("snapshots")
+                .join(format!("{:016x}", snapshot_id)),
+        )
+    }
+}
+
+impl<Permissions: WritePolicy> CanisterLayout<Permissions> {
+    /// Removes the directories of the snapshots that are marked as deleted.
+    /// This must only be done once a checkpoint without these snapshots has
+    /// been created, since the directories are what excludes the snapshots
+    /// from the checkpoint.
+    pub fn remove_deleted_snapshots(&self) -> Result<(), LayoutError> {
+        for id in self.all_snapshot_ids()? {
+            let snapshot_layout = self.snapshot(id)?;
+            if snapshot_layout.is_marked_deleted() {
+                let path = snapshot_layout.raw_path();
+                std::fs::remove_dir_all(&path).map_err(|err| LayoutError::IoError {
+                    path,
+                    message: "Failed to remove a deleted snapshot".to_string(),
+                    io_err: err,
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct SnapshotLayout<Permissions: AccessPolicy> {
+    snapshot_root: PathBuf,
+    permissions_tag: PhantomData<Permissions>,
+}
+
+impl<Permissions: AccessPolicy> SnapshotLayout<Permissions> {
+    pub fn new(snapshot_root: PathBuf) -> Result<Self, LayoutError> {
+        Permissions::check_dir(&snapshot_root)?;
+        Ok(Self {
+            snapshot_root,
+            permissions_tag: PhantomData,
+        })
+    }
+
+    pub fn raw_path(&self) -> PathBuf {
+        self.snapshot_root.clone()
+    }
+
+    pub fn snapshot(
+        &self,
+    ) -> ProtoFileWith<pb_canister_state_bits::CanisterSnapshotBits, Permissions> {
+        self.snapshot_root.join("snapshot.pbuf").into()
+    }
+
+    pub fn wasm(&self) -> WasmFile<Permissions> {
+        self.snapshot_root.join("software.wasm").into()
+    }
+
+    pub fn vmemory_0(&self) -> PathBuf {
+        self.snapshot_root.join("vmemory_0.bin")
+    }
+
+    pub fn stable_memory_blob(&self) -> PathBuf {
+        self.snapshot_root.join("stable_memory.bin")
+    }
+
+    pub fn tombstone(&self) -> PathBuf {
+        self.snapshot_root.join("tombstone")
+    }
+
+    /// Marks this snapshot as deleted by creating a 'tombstone' file in the
+    /// snapshot directory. Such directories will be excluded when a checkpoint
+    /// is created.
+    pub fn mark_deleted(&self) -> Result<(), LayoutError> {
+        let path = self.tombstone();
+        let _ = std::fs::File::create(&path).map_err(|err| LayoutError::IoError {
+            path,
+            message: "Failed to create a file".to_string(),
+            io_err: err,
+        })?;
+        Ok(())
+    }
+
+    pub fn is_marked_deleted(&self) -> bool {
+        Path::new(&self.tombstone()).exists()
+    }
 }
 
 fn open_for_write(path: &Path) -> Result<std::fs::File, LayoutError> {
@@ -995,6 +1141,19 @@ impl From<CanisterStateBits> for pb_canister_state_bits::CanisterStateBits {
             },
             stable_memory_size64: item.stable_memory_size.get() as u64,
             heap_delta_debit: item.heap_delta_debit.get(),
+            task_queue: item.task_queue.iter().map(|task| task.into()).collect(),
+            canister_log_records: item
+                .canister_log
+                .records()
+                .iter()
+                .map(|record| record.into())
+                .collect(),
+            next_canister_log_record_idx: item.canister_log.next_idx(),
+            log_visibility: pb_canister_state_bits::LogVisibility::from(item.log_visibility) as i32,
+            next_snapshot_id: item.next_snapshot_id,
+            global_timer_nanos: item.global_timer.to_nanos_since_unix_epoch(),
+            reserved_balance: Some(item.reserved_balance.into()),
+            reserved_balance_limit: item.reserved_balance_limit.map(|limit| limit.into()),
         }
     }
 }
@@ -1054,6 +1213,23 @@ impl TryFrom<pb_canister_state_bits::CanisterStateBits> for CanisterStateBits {
             value.stable_memory_size as u64
         };
 
+        let mut task_queue = VecDeque::with_capacity(value.task_queue.len());
+        for task in value.task_queue.into_iter() {
+            task_queue.push_back(task.try_into()?);
+        }
+
+        let canister_log = CanisterLog::new(
+            value.next_canister_log_record_idx,
+            value
+                .canister_log_records
+                .into_iter()
+                .map(|record| record.into())
+                .collect(),
+        );
+        let log_visibility = pb_canister_state_bits::LogVisibility::from_i32(value.log_visibility)
+            .unwrap_or(pb_canister_state_bits::LogVisibility::Unspecified)
+            .into();
+
         Ok(Self {
             controllers,
             last_full_execution_round: value.last_full_execution_round.into(),
@@ -1085,6 +1261,18 @@ impl TryFrom<pb_canister_state_bits::CanisterStateBits> for CanisterStateBits {
             consumed_cycles_since_replica_started,
             stable_memory_size: NumWasmPages::from(stable_memory_size as usize),
             heap_delta_debit: NumBytes::from(value.heap_delta_debit),
+            task_queue,
+            canister_log,
+            log_visibility,
+            next_snapshot_id: value.next_snapshot_id,
+            global_timer: CanisterTimer::from_nanos_since_unix_epoch(value.global_timer_nanos),
+            // Checkpoints written before reservations were introduced have no
+            // reserved balance.
+            reserved_balance: value
+                .reserved_balance
+                .map(Cycles::from)
+                .unwrap_or_else(Cycles::zero),
+            reserved_balance_limit: value.reserved_balance_limit.map(Cycles::from),
         })
     }
 }
@@ -1124,6 +1312,41 @@ impl TryFrom<pb_canister_state_bits::ExecutionStateBits> for ExecutionStateBits
     }
 }
 
+impl From<&CanisterSnapshotBits> for pb_canister_state_bits::CanisterSnapshotBits {
+    fn from(item: &CanisterSnapshotBits) -> Self {
+        Self {
+            taken_at_timestamp_nanos: item.taken_at_timestamp.as_nanos_since_unix_epoch(),
+            heap_size: item.heap_size.get() as u64,
+            stable_memory_size: item.stable_memory_size.get() as u64,
+            exported_globals: item
+                .exported_globals
+                .iter()
+                .map(|global| global.into())
+                .collect(),
+            exports: (&item.exports).into(),
+            certified_data: item.certified_data.clone(),
+        }
+    }
+}
+
+impl TryFrom<pb_canister_state_bits::CanisterSnapshotBits> for CanisterSnapshotBits {
+    type Error = ProxyDecodeError;
+    fn try_from(value: pb_canister_state_bits::CanisterSnapshotBits) -> Result<Self, Self::Error> {
+        let mut globals = Vec::with_capacity(value.exported_globals.len());
+        for g in value.exported_globals.into_iter() {
+            globals.push(g.try_into()?);
+        }
+        Ok(Self {
+            taken_at_timestamp: Time::from_nanos_since_unix_epoch(value.taken_at_timestamp_nanos),
+            heap_size: (value.heap_size as usize).into(),
+            stable_memory_size: (value.stable_memory_size as usize).into(),
+            exported_globals: globals,
+            exports: value.exports.try_into()?,
+            certified_data: value.certified_data,
+        })
+    }
+}
+
 // A principal used to indicate that there are no controllers present.
 // Note the "no controller" substring in the principal.
 fn no_controllers_marker() -> PrincipalId {
@@ -1134,8 +1357,13 @@ fn no_controllers_marker() -> PrincipalId {
 mod test {
     use super::*;
 
-    use ic_test_utilities::types::ids::canister_test_id;
-    use ic_types::ic00::IC_00;
+    use ic_interfaces::messages::RequestOrIngress;
+    use ic_replicated_state::PausedTask;
+    use ic_test_utilities::types::{
+        ids::canister_test_id,
+        messages::{IngressBuilder, RequestBuilder},
+    };
+    use ic_types::{ic00::IC_00, NumInstructions};
 
     #[test]
     fn test_encode_decode_empty_controllers() {
@@ -1159,6 +1387,13 @@ mod test {
             consumed_cycles_since_replica_started: NominalCycles::from(0),
             stable_memory_size: NumWasmPages::from(0),
             heap_delta_debit: NumBytes::from(0),
+            task_queue: VecDeque::new(),
+            canister_log: CanisterLog::default(),
+            log_visibility: LogVisibility::default(),
+            next_snapshot_id: 0,
+            global_timer: CanisterTimer::Inactive,
+            reserved_balance: Cycles::zero(),
+            reserved_balance_limit: None,
         };
 
         let pb_bits = pb_canister_state_bits::CanisterStateBits::from(canister_state_bits);
@@ -1198,6 +1433,13 @@ mod test {
             consumed_cycles_since_replica_started: NominalCycles::from(0),
             stable_memory_size: NumWasmPages::from(0),
             heap_delta_debit: NumBytes::from(0),
+            task_queue: VecDeque::new(),
+            canister_log: CanisterLog::default(),
+            log_visibility: LogVisibility::default(),
+            next_snapshot_id: 0,
+            global_timer: CanisterTimer::Inactive,
+            reserved_balance: Cycles::zero(),
+            reserved_balance_limit: None,
         };
 
         let pb_bits = pb_canister_state_bits::CanisterStateBits::from(canister_state_bits);
@@ -1239,6 +1481,13 @@ mod test {
             consumed_cycles_since_replica_started: NominalCycles::from(0),
             stable_memory_size: NumWasmPages::from(0),
             heap_delta_debit: NumBytes::from(0),
+            task_queue: VecDeque::new(),
+            canister_log: CanisterLog::default(),
+            log_visibility: LogVisibility::default(),
+            next_snapshot_id: 0,
+            global_timer: CanisterTimer::Inactive,
+            reserved_balance: Cycles::zero(),
+            reserved_balance_limit: None,
         };
 
         let pb_bits = pb_canister_state_bits::CanisterStateBits::from(canister_state_bits);
@@ -1247,4 +1496,238 @@ mod test {
 
         assert_eq!(canister_state_bits.controllers, controllers)
     }
+
+    #[test]
+    fn test_encode_decode_task_queue() {
+        let mut task_queue = VecDeque::new();
+        task_queue.push_back(ExecutionTask::AbortedInstallCode(
+            RequestOrIngress::Ingress(IngressBuilder::new().method_name("install_code").build()),
+        ));
+        task_queue.push_back(ExecutionTask::AbortedExecution(RequestOrIngress::Request(
+            RequestBuilder::new().method_name("update").build(),
+        )));
+        task_queue.push_front(ExecutionTask::PausedExecution(PausedTask {
+            message: RequestOrIngress::Ingress(IngressBuilder::new().method_name("work").build()),
+            time: Time::from_nanos_since_unix_epoch(1_000),
+            initial_cycles_balance: Cycles::from(123_456),
+            subnet_available_memory: -5,
+            slice_instruction_limits: vec![NumInstructions::from(10), NumInstructions::from(7)],
+            instructions_executed: NumInstructions::from(18),
+            wasm_simd: true,
+        }));
+
+        let canister_state_bits = CanisterStateBits {
+            controllers: BTreeSet::new(),
+            last_full_execution_round: ExecutionRound::from(0),
+            call_context_manager: None,
+            compute_allocation: ComputeAllocation::try_from(0).unwrap(),
+            accumulated_priority: AccumulatedPriority::from(0),
+            execution_state_bits: None,
+            memory_allocation: MemoryAllocation::default(),
+            freeze_threshold: NumSeconds::from(0),
+            cycles_balance: Cycles::from(0),
+            status: CanisterStatus::Stopped,
+            scheduled_as_first: 0,
+            skipped_round_due_to_no_messages: 0,
+            executed: 0,
+            interruped_during_execution: 0,
+            certified_data: vec![],
+            consumed_cycles_since_replica_started: NominalCycles::from(0),
+            stable_memory_size: NumWasmPages::from(0),
+            heap_delta_debit: NumBytes::from(0),
+            task_queue: task_queue.clone(),
+            canister_log: CanisterLog::default(),
+            log_visibility: LogVisibility::default(),
+            next_snapshot_id: 0,
+            global_timer: CanisterTimer::Inactive,
+            reserved_balance: Cycles::zero(),
+            reserved_balance_limit: None,
+        };
+
+        let pb_bits = pb_canister_state_bits::CanisterStateBits::from(canister_state_bits);
+
+        let canister_state_bits = CanisterStateBits::try_from(pb_bits).unwrap();
+
+        assert_eq!(canister_state_bits.task_queue, task_queue);
+    }
+
+    #[test]
+    fn test_encode_decode_canister_log() {
+        let mut canister_log = CanisterLog::default();
+        canister_log.add_record(1, b"debug print".to_vec());
+        canister_log.add_record(2, b"trap message".to_vec());
+
+        let canister_state_bits = CanisterStateBits {
+            controllers: BTreeSet::new(),
+            last_full_execution_round: ExecutionRound::from(0),
+            call_context_manager: None,
+            compute_allocation: ComputeAllocation::try_from(0).unwrap(),
+            accumulated_priority: AccumulatedPriority::from(0),
+            execution_state_bits: None,
+            memory_allocation: MemoryAllocation::default(),
+            freeze_threshold: NumSeconds::from(0),
+            cycles_balance: Cycles::from(0),
+            status: CanisterStatus::Stopped,
+            scheduled_as_first: 0,
+            skipped_round_due_to_no_messages: 0,
+            executed: 0,
+            interruped_during_execution: 0,
+            certified_data: vec![],
+            consumed_cycles_since_replica_started: NominalCycles::from(0),
+            stable_memory_size: NumWasmPages::from(0),
+            heap_delta_debit: NumBytes::from(0),
+            task_queue: VecDeque::new(),
+            canister_log: canister_log.clone(),
+            log_visibility: LogVisibility::Public,
+            next_snapshot_id: 3,
+            global_timer: CanisterTimer::Inactive,
+            reserved_balance: Cycles::zero(),
+            reserved_balance_limit: None,
+        };
+
+        let pb_bits = pb_canister_state_bits::CanisterStateBits::from(canister_state_bits);
+
+        let canister_state_bits = CanisterStateBits::try_from(pb_bits).unwrap();
+
+        assert_eq!(canister_state_bits.canister_log, canister_log);
+        assert_eq!(canister_state_bits.log_visibility, LogVisibility::Public);
+        assert_eq!(canister_state_bits.next_snapshot_id, 3);
+    }
+
+    #[test]
+    fn test_encode_decode_global_timer() {
+        let global_timer = CanisterTimer::Active(Time::from_nanos_since_unix_epoch(1_000));
+        let canister_state_bits = CanisterStateBits {
+            controllers: BTreeSet::new(),
+            last_full_execution_round: ExecutionRound::from(0),
+            call_context_manager: None,
+            compute_allocation: ComputeAllocation::try_from(0).unwrap(),
+            accumulated_priority: AccumulatedPriority::from(0),
+            execution_state_bits: None,
+            memory_allocation: MemoryAllocation::default(),
+            freeze_threshold: NumSeconds::from(0),
+            cycles_balance: Cycles::from(0),
+            status: CanisterStatus::Stopped,
+            scheduled_as_first: 0,
+            skipped_round_due_to_no_messages: 0,
+            executed: 0,
+            interruped_during_execution: 0,
+            certified_data: vec![],
+            consumed_cycles_since_replica_started: NominalCycles::from(0),
+            stable_memory_size: NumWasmPages::from(0),
+            heap_delta_debit: NumBytes::from(0),
+            task_queue: VecDeque::new(),
+            canister_log: CanisterLog::default(),
+            log_visibility: LogVisibility::default(),
+            next_snapshot_id: 0,
+            global_timer,
+            reserved_balance: Cycles::zero(),
+            reserved_balance_limit: None,
+        };
+
+        let pb_bits = pb_canister_state_bits::CanisterStateBits::from(canister_state_bits);
+
+        let canister_state_bits = CanisterStateBits::try_from(pb_bits).unwrap();
+
+        assert_eq!(canister_state_bits.global_timer, global_timer);
+    }
+
+    #[test]
+    fn test_encode_decode_canister_snapshot_bits() {
+        let snapshot_bits = CanisterSnapshotBits {
+            taken_at_timestamp: Time::from_nanos_since_unix_epoch(42),
+            heap_size: NumWasmPages::from(2),
+            stable_memory_size: NumWasmPages::from(1 << 20),
+            exported_globals: vec![Global::I32(1), Global::F64(2.5)],
+            exports: ExportedFunctions::new(BTreeSet::new()),
+            certified_data: vec![1, 2, 3],
+        };
+
+        let pb_bits = pb_canister_state_bits::CanisterSnapshotBits::from(&snapshot_bits);
+
+        assert_eq!(
+            CanisterSnapshotBits::try_from(pb_bits).unwrap(),
+            snapshot_bits
+        );
+    }
+
+    #[test]
+    fn test_snapshot_ids_skip_deleted_snapshots() {
+        let tmpdir = tempfile::Builder::new().prefix("test").tempdir().unwrap();
+        let canister_layout =
+            CanisterLayout::<RwPolicy>::new(tmpdir.path().join("canister")).unwrap();
+        assert_eq!(
+            canister_layout.snapshot_ids().unwrap(),
+            Vec::<SnapshotId>::new()
+        );
+
+        canister_layout.snapshot(17).unwrap();
+        canister_layout.snapshot(3).unwrap();
+        canister_layout.snapshot(5).unwrap().mark_deleted().unwrap();
+
+        assert_eq!(canister_layout.snapshot_ids().unwrap(), vec![3, 17]);
+    }
+
+    #[test]
+    fn test_remove_deleted_snapshots() {
+        let tmpdir = tempfile::Builder::new().prefix("test").tempdir().unwrap();
+        let canister_layout =
+            CanisterLayout::<RwPolicy>::new(tmpdir.path().join("canister")).unwrap();
+        let kept = canister_layout.snapshot(3).unwrap();
+        let deleted = canister_layout.snapshot(5).unwrap();
+        deleted.mark_deleted().unwrap();
+
+        canister_layout.remove_deleted_snapshots().unwrap();
+
+        assert!(kept.raw_path().exists());
+        assert!(!deleted.raw_path().exists());
+        assert_eq!(canister_layout.snapshot_ids().unwrap(), vec![3]);
+    }
+
+    #[test]
+    fn test_encode_decode_reserved_balance() {
+        let canister_state_bits = CanisterStateBits {
+            controllers: BTreeSet::new(),
+            last_full_execution_round: ExecutionRound::from(0),
+            call_context_manager: None,
+            compute_allocation: ComputeAllocation::try_from(0).unwrap(),
+            accumulated_priority: AccumulatedPriority::from(0),
+            execution_state_bits: None,
+            memory_allocation: MemoryAllocation::default(),
+            freeze_threshold: NumSeconds::from(0),
+            cycles_balance: Cycles::from(0),
+            status: CanisterStatus::Stopped,
+            scheduled_as_first: 0,
+            skipped_round_due_to_no_messages: 0,
+            executed: 0,
+            interruped_during_execution: 0,
+            certified_data: vec![],
+            consumed_cycles_since_replica_started: NominalCycles::from(0),
+            stable_memory_size: NumWasmPages::from(0),
+            heap_delta_debit: NumBytes::from(0),
+            task_queue: VecDeque::new(),
+            canister_log: CanisterLog::default(),
+            log_visibility: LogVisibility::default(),
+            next_snapshot_id: 0,
+            global_timer: CanisterTimer::Inactive,
+            reserved_balance: Cycles::from(1_000u64),
+            reserved_balance_limit: Some(Cycles::from(5_000u64)),
+        };
+
+        let mut pb_bits = pb_canister_state_bits::CanisterStateBits::from(canister_state_bits);
+
+        let canister_state_bits = CanisterStateBits::try_from(pb_bits.clone()).unwrap();
+        assert_eq!(canister_state_bits.reserved_balance, Cycles::from(1_000u64));
+        assert_eq!(
+            canister_state_bits.reserved_balance_limit,
+            Some(Cycles::from(5_000u64))
+        );
+
+        // Checkpoints written by older replicas do not have these fields.
+        pb_bits.reserved_balance = None;
+        pb_bits.reserved_balance_limit = None;
+        let canister_state_bits = CanisterStateBits::try_from(pb_bits).unwrap();
+        assert_eq!(canister_state_bits.reserved_balance, Cycles::zero());
+        assert_eq!(canister_state_bits.reserved_balance_limit, None);
+    }
 }
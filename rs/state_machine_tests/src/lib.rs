@@ -3,6 +3,7 @@ use ic_config::subnet_config::SubnetConfigs;
 use ic_cycles_account_manager::CyclesAccountManager;
 use ic_execution_environment::setup_execution;
 use ic_interfaces::{
+    certification::CertificationScope,
     execution_environment::{IngressHistoryReader, QueryHandler},
     messaging::MessageRouting,
     state_manager::{StateHashError, StateManager, StateReader},
@@ -10,39 +11,30 @@ use ic_interfaces::{
 use ic_logger::ReplicaLogger;
 use ic_messaging::MessageRoutingImpl;
 use ic_metrics::MetricsRegistry;
-use ic_protobuf::registry::{
-    provisional_whitelist::v1::ProvisionalWhitelist as PbProvisionalWhitelist,
-    routing_table::v1::RoutingTable as PbRoutingTable,
-};
-use ic_protobuf::types::v1::PrincipalId as PrincipalIdIdProto;
-use ic_protobuf::types::v1::SubnetId as SubnetIdProto;
 use ic_registry_client::client::RegistryClientImpl;
-use ic_registry_common::proto_registry_data_provider::ProtoRegistryDataProvider;
-use ic_registry_keys::{
-    make_provisional_whitelist_record_key, make_routing_table_record_key, ROOT_SUBNET_ID_KEY,
-};
-use ic_registry_provisional_whitelist::ProvisionalWhitelist;
-use ic_registry_routing_table::{routing_table_insert_subnet, RoutingTable};
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::ReplicatedState;
 use ic_state_manager::StateManagerImpl;
 use ic_test_utilities::{
-    consensus::fake::FakeVerifier,
-    mock_time,
-    registry::{add_subnet_record, insert_initial_dkg_transcript, SubnetRecordBuilder},
+    certified_stream_store::stream_slice_from, consensus::fake::FakeVerifier, mock_time,
+    registry::multi_subnet_registry, state_manager::certify_states_with_fake_signature,
     types::messages::SignedIngressBuilder,
 };
-use ic_types::batch::SelfValidatingPayload;
+use ic_types::batch::{CanisterHttpPayload, SelfValidatingPayload};
 use ic_types::{
     batch::{Batch, BatchPayload, IngressPayload, XNetPayload},
     ic00,
-    ic00::{CanisterIdRecord, CanisterSettingsArgs, InstallCodeArgs, Method, Payload},
+    ic00::{
+        CanisterIdRecord, CanisterSettingsArgs, CanisterStatusResultV2, InstallCodeArgs, Method,
+        Payload, UpdateSettingsArgs,
+    },
     ingress::{IngressStatus, WasmResult},
     messages::{CanisterInstallMode, MessageId, SignedIngress, UserQuery},
     time::Time,
     user_error::UserError,
-    CanisterId, CryptoHashOfState, NodeId, PrincipalId, Randomness, RegistryVersion, SubnetId,
-    UserId,
+    xnet::CertifiedStreamSlice,
+    CanisterId, CryptoHashOfState, Cycles, Height, NodeId, PrincipalId, Randomness,
+    RegistryVersion, SubnetId, UserId,
 };
 use std::collections::BTreeMap;
 use std::fmt;
@@ -51,68 +43,18 @@ use std::sync::Arc;
 use std::time::Duration;
 use tempfile::TempDir;
 
-/// Constructs the initial version of the registry containing a subnet with the
-/// specified SUBNET_ID, with the node with the specified NODE_ID assigned to
-/// it.
-fn make_single_node_registry(
-    metrics_registry: &MetricsRegistry,
-    subnet_id: SubnetId,
-    subnet_type: SubnetType,
-    node_id: NodeId,
-) -> Arc<RegistryClientImpl> {
-    let registry_version = RegistryVersion::from(1);
-    let data_provider = Arc::new(ProtoRegistryDataProvider::new());
-
-    let root_subnet_id_proto = SubnetIdProto {
-        principal_id: Some(PrincipalIdIdProto {
-            raw: subnet_id.get_ref().to_vec(),
-        }),
-    };
-    data_provider
-        .add(
-            ROOT_SUBNET_ID_KEY,
-            registry_version,
-            Some(root_subnet_id_proto),
-        )
-        .unwrap();
-
-    let mut routing_table = RoutingTable::new(BTreeMap::new());
-    routing_table_insert_subnet(&mut routing_table, subnet_id).unwrap();
-    let pb_routing_table = PbRoutingTable::from(routing_table);
-    data_provider
-        .add(
-            &make_routing_table_record_key(),
-            registry_version,
-            Some(pb_routing_table),
-        )
-        .unwrap();
-    let pb_whitelist = PbProvisionalWhitelist::from(ProvisionalWhitelist::All);
-    data_provider
-        .add(
-            &make_provisional_whitelist_record_key(),
-            registry_version,
-            Some(pb_whitelist),
-        )
-        .unwrap();
-
-    // Set subnetwork list(needed for filling network_topology.nns_subnet_id)
-    let mut record = SubnetRecordBuilder::from(&[node_id]).build();
-    record.subnet_type = i32::from(subnet_type);
-
-    insert_initial_dkg_transcript(registry_version.get(), subnet_id, &record, &data_provider);
-    add_subnet_record(&data_provider, registry_version.get(), subnet_id, record);
-
-    let registry_client = Arc::new(RegistryClientImpl::new(
-        data_provider,
-        Some(metrics_registry),
-    ));
-    registry_client.fetch_and_start_polling().unwrap();
-    registry_client
-}
+/// The type of all the subnets run by state machines.
+const SUBNET_TYPE: SubnetType = SubnetType::System;
 
 /// Represents a replicated state machine detached from the network layer that
 /// can be used to test this part of the stack in isolation.
+///
+/// Several state machines created with [StateMachine::new_subnets] run one
+/// subnet each and exchange messages through XNet streams, see
+/// [StateMachine::execute_xnet_round].
 pub struct StateMachine {
+    subnet_id: SubnetId,
+    registry: Arc<RegistryClientImpl>,
     state_manager: Arc<StateManagerImpl>,
     message_routing: MessageRoutingImpl,
     ingress_history_reader: Box<dyn IngressHistoryReader>,
@@ -131,6 +73,7 @@ impl Default for StateMachine {
 impl fmt::Debug for StateMachine {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("StateMachine")
+            .field("subnet_id", &self.subnet_id)
             .field("state_dir", &self.state_dir.path().display())
             .field("nonce", &self.nonce.get())
             .finish()
@@ -141,21 +84,46 @@ impl StateMachine {
     /// Constructs a new environment that uses a temporary directory for storing
     /// states.
     pub fn new() -> Self {
-        Self::setup_from_dir(
-            TempDir::new().expect("failed to create a temporary directory"),
-            0,
-            mock_time(),
-            None,
-        )
+        Self::new_with_subnets(1, None).pop().unwrap()
     }
 
     pub fn new_with_config(config: SubnetConfig) -> Self {
-        Self::setup_from_dir(
-            TempDir::new().expect("failed to create a temporary directory"),
-            0,
-            mock_time(),
-            Some(config),
-        )
+        Self::new_with_subnets(1, Some(config)).pop().unwrap()
+    }
+
+    /// Constructs the specified number of state machines, each running its
+    /// own subnet. The state machines share one registry in which every subnet
+    /// owns a canister ID range, so canisters installed on one of them can call
+    /// canisters installed on the others.
+    pub fn new_subnets(count: usize) -> Vec<Self> {
+        Self::new_with_subnets(count, None)
+    }
+
+    fn new_with_subnets(count: usize, subnet_config: Option<SubnetConfig>) -> Vec<Self> {
+        assert!(count > 0, "a state machine needs at least one subnet");
+        let subnets: Vec<_> = (1..=count as u64)
+            .map(|i| {
+                (
+                    SubnetId::from(PrincipalId::new_subnet_test_id(i)),
+                    NodeId::from(PrincipalId::new_node_test_id(i)),
+                )
+            })
+            .collect();
+        let registry =
+            multi_subnet_registry(&MetricsRegistry::new(), &subnets, subnets[0].0, SUBNET_TYPE);
+        subnets
+            .into_iter()
+            .map(|(subnet_id, _)| {
+                Self::setup_from_dir(
+                    TempDir::new().expect("failed to create a temporary directory"),
+                    0,
+                    mock_time(),
+                    subnet_config.clone(),
+                    subnet_id,
+                    Arc::clone(&registry),
+                )
+            })
+            .collect()
     }
 
     /// Constructs and initializes a new state machine that uses the specified
@@ -165,22 +133,19 @@ impl StateMachine {
         nonce: u64,
         time: Time,
         subnet_config: Option<SubnetConfig>,
+        subnet_id: SubnetId,
+        registry: Arc<RegistryClientImpl>,
     ) -> Self {
         let logger = slog::Logger::root(slog::Discard, slog::o!());
         let replica_logger: ReplicaLogger = logger.into();
 
-        let subnet_id = SubnetId::from(PrincipalId::new_subnet_test_id(1));
-        let node_id = NodeId::from(PrincipalId::new_node_test_id(1));
         let metrics_registry = MetricsRegistry::new();
-        let subnet_type = SubnetType::System;
+        let subnet_type = SUBNET_TYPE;
         let subnet_config = match subnet_config {
             Some(subnet_config) => subnet_config,
             None => SubnetConfigs::default().own_subnet_config(subnet_type),
         };
 
-        let registry =
-            make_single_node_registry(&metrics_registry, subnet_id, subnet_type, node_id);
-
         let sm_config = ic_config::state_manager::Config::new(state_dir.path().to_path_buf());
         let hypervisor_config = ic_config::execution_environment::Config::default();
 
@@ -226,6 +191,8 @@ impl StateMachine {
         );
 
         Self {
+            subnet_id,
+            registry,
             state_manager,
             ingress_history_reader,
             message_routing,
@@ -238,7 +205,14 @@ impl StateMachine {
 
     /// Emulates a node restart, including checkpoint recovery.
     pub fn restart_node(self) -> Self {
-        Self::setup_from_dir(self.state_dir, self.nonce.get(), self.time.get(), None)
+        Self::setup_from_dir(
+            self.state_dir,
+            self.nonce.get(),
+            self.time.get(),
+            None,
+            self.subnet_id,
+            self.registry,
+        )
     }
 
     pub fn restart_node_with_config(self, config: SubnetConfig) -> Self {
@@ -247,24 +221,107 @@ impl StateMachine {
             self.nonce.get(),
             self.time.get(),
             Some(config),
+            self.subnet_id,
+            self.registry,
         )
     }
 
-    /// Creates a new batch containing a single ingress message and sends it for
-    /// processing to the replicated state machine.
-    fn send_signed_ingress(&self, msg: SignedIngress) {
+    /// Returns the ID of the subnet run by this state machine.
+    pub fn subnet_id(&self) -> SubnetId {
+        self.subnet_id
+    }
+
+    /// Returns the current time of the state machine, i.e. the time of the
+    /// last executed batch plus whatever [advance_time] added since.
+    pub fn time(&self) -> Time {
+        self.time.get()
+    }
+
+    /// Moves the time of the state machine forward. The new time becomes
+    /// visible to canisters in the next executed round.
+    pub fn advance_time(&self, amount: Duration) {
+        self.time.set(self.time.get() + amount);
+    }
+
+    /// Executes a single round without any messages and blocks until its
+    /// state is committed. Useful to trigger heartbeats and global timers.
+    pub fn tick(&self) {
+        let height = self.deliver_batch(vec![], BTreeMap::new());
+        self.await_height(height);
+    }
+
+    /// Executes the specified number of rounds without any messages, see
+    /// [tick].
+    pub fn run_rounds(&self, rounds: u64) {
+        for _ in 0..rounds {
+            self.tick();
+        }
+    }
+
+    /// Executes a round that inducts the messages that the specified subnets
+    /// sent to this subnet and that were not inducted yet, and blocks until its
+    /// state is committed. The messages this subnet sends in the round are
+    /// inducted by the other subnets in their next XNet round.
+    pub fn execute_xnet_round(&self, remote_subnets: &[&StateMachine]) {
+        self.await_idle();
+        let stream_slices = remote_subnets
+            .iter()
+            .filter(|remote| remote.subnet_id != self.subnet_id)
+            .filter_map(|remote| {
+                self.stream_slice_from(remote)
+                    .map(|slice| (remote.subnet_id, slice))
+            })
+            .collect();
+        let height = self.deliver_batch(vec![], stream_slices);
+        self.await_height(height);
+    }
+
+    /// Executes the specified number of XNet rounds on all the specified
+    /// subnets, one subnet after the other, see [execute_xnet_round]. A call
+    /// from one subnet to another and its response need two rounds.
+    pub fn execute_xnet_rounds(subnets: &[StateMachine], rounds: u64) {
+        let all: Vec<_> = subnets.iter().collect();
+        for _ in 0..rounds {
+            for subnet in subnets {
+                subnet.execute_xnet_round(&all);
+            }
+        }
+    }
+
+    /// Returns the slice of the stream from the specified subnet to this one
+    /// starting at the first message this subnet has not inducted yet, or
+    /// `None` if the remote subnet has not sent anything to this subnet yet.
+    fn stream_slice_from(&self, remote: &StateMachine) -> Option<CertifiedStreamSlice> {
+        remote.await_idle();
+        certify_states_with_fake_signature(remote.state_manager.as_ref());
+        stream_slice_from(
+            remote.state_manager.as_ref(),
+            remote.subnet_id,
+            self.subnet_id,
+            &self.state_manager.get_latest_state().take(),
+        )
+    }
+
+    /// Creates a new batch containing the specified ingress messages and stream
+    /// slices and sends it for processing to the replicated state machine.
+    /// Returns the height of the batch.
+    fn deliver_batch(
+        &self,
+        ingress: Vec<SignedIngress>,
+        stream_slices: BTreeMap<SubnetId, CertifiedStreamSlice>,
+    ) -> Height {
         // Move the block time forward by 1 second.
         self.time.set(self.time.get() + Duration::from_secs(1));
 
+        let batch_number = self.message_routing.expected_batch_height();
         let batch = Batch {
-            batch_number: self.message_routing.expected_batch_height(),
+            batch_number,
             requires_full_state_hash: true,
             payload: BatchPayload {
-                ingress: IngressPayload::from(vec![msg]),
-                xnet: XNetPayload {
-                    stream_slices: Default::default(),
-                },
+                ingress: IngressPayload::from(ingress),
+                xnet: XNetPayload { stream_slices },
                 self_validating: SelfValidatingPayload::default(),
+                canister_http: CanisterHttpPayload::default(),
             },
             randomness: Randomness::from([0; 32]),
             registry_version: RegistryVersion::from(1),
@@ -273,7 +330,37 @@ impl StateMachine {
         };
         self.message_routing
             .deliver_batch(batch)
-            .expect("MR queue overflow")
+            .expect("MR queue overflow");
+        batch_number
+    }
+
+    /// Creates a new batch containing a single ingress message and sends it for
+    /// processing to the replicated state machine.
+    fn send_signed_ingress(&self, msg: SignedIngress) {
+        self.deliver_batch(vec![msg], BTreeMap::new());
+    }
+
+    /// Blocks until all the batches delivered so far are processed, so that
+    /// message routing no longer works on the tip.
+    fn await_idle(&self) {
+        self.await_height(self.message_routing.expected_batch_height().decrement());
+    }
+
+    /// Blocks until the state at the specified height is committed.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the state is not committed within a few
+    /// seconds.
+    fn await_height(&self, height: Height) {
+        let mut tries = 0;
+        while self.state_manager.latest_state_height() < height {
+            tries += 1;
+            if tries > 100 {
+                panic!("state at height {} was not committed in time", height);
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
     }
 
     /// Blocks until the hash of the latest state is computed.
@@ -403,11 +490,28 @@ impl StateMachine {
         receiver: CanisterId,
         method: impl ToString,
         method_payload: Vec<u8>,
+    ) -> Result<WasmResult, UserError> {
+        self.query_as(
+            PrincipalId::new_anonymous(),
+            receiver,
+            method,
+            method_payload,
+        )
+    }
+
+    /// Queries the canister with the specified ID on behalf of the specified
+    /// sender.
+    pub fn query_as(
+        &self,
+        sender: PrincipalId,
+        receiver: CanisterId,
+        method: impl ToString,
+        method_payload: Vec<u8>,
     ) -> Result<WasmResult, UserError> {
         self.query_handler.query(
             UserQuery {
                 receiver,
-                source: UserId::from(PrincipalId::new_anonymous()),
+                source: UserId::from(sender),
                 method_name: method.to_string(),
                 method_payload,
                 ingress_expiry: 0,
@@ -437,6 +541,19 @@ impl StateMachine {
         self.await_ingress(msg_id)
     }
 
+    /// Executes an ingress message sent by the specified principal, see
+    /// [execute_ingress].
+    pub fn execute_ingress_as(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        method: impl ToString,
+        payload: Vec<u8>,
+    ) -> Result<WasmResult, UserError> {
+        let msg_id = self.send_ingress_as(sender, canister_id, method, payload);
+        self.await_ingress(msg_id)
+    }
+
     /// Sends an ingress message to the canister with the specified ID.
     ///
     /// This function is asynchronous. It returns the ID of the ingress message
@@ -446,9 +563,22 @@ impl StateMachine {
         canister_id: CanisterId,
         method: impl ToString,
         payload: Vec<u8>,
+    ) -> MessageId {
+        self.send_ingress_as(PrincipalId::new_anonymous(), canister_id, method, payload)
+    }
+
+    /// Sends an ingress message on behalf of the specified principal, see
+    /// [send_ingress].
+    pub fn send_ingress_as(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        method: impl ToString,
+        payload: Vec<u8>,
     ) -> MessageId {
         self.nonce.set(self.nonce.get() + 1);
         let msg = SignedIngressBuilder::new()
+            .sender(UserId::from(sender))
             .canister_id(canister_id)
             .method_name(method.to_string())
             .method_payload(payload)
@@ -463,4 +593,114 @@ impl StateMachine {
     pub fn ingress_status(&self, msg_id: &MessageId) -> IngressStatus {
         (self.ingress_history_reader.get_latest_status())(msg_id)
     }
+
+    /// Returns the cycle balance of the canister with the specified ID in the
+    /// latest state.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the canister does not exist.
+    pub fn cycle_balance(&self, canister_id: CanisterId) -> Cycles {
+        self.state_manager
+            .get_latest_state()
+            .take()
+            .canister_state(&canister_id)
+            .unwrap_or_else(|| panic!("canister {} does not exist", canister_id))
+            .system_state
+            .cycles_balance
+    }
+
+    /// Sets the cycle balance of the canister with the specified ID. The
+    /// balance is changed directly in the state, without executing a round, and
+    /// is committed as a new checkpoint.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the canister does not exist.
+    pub fn set_cycles_balance(&self, canister_id: CanisterId, amount: Cycles) {
+        self.await_idle();
+
+        let (height, mut state) = self.state_manager.take_tip();
+        state
+            .canister_state_mut(&canister_id)
+            .unwrap_or_else(|| panic!("canister {} does not exist", canister_id))
+            .system_state
+            .cycles_balance = amount;
+        self.state_manager
+            .commit_and_certify(state, height.increment(), CertificationScope::Full);
+    }
+
+    /// Calls a method of the management canister. Management calls are sent by
+    /// the anonymous principal, which controls all the canisters installed with
+    /// [install_canister_wat]. Use [execute_ingress_as] with `ic00::IC_00` to
+    /// manage canisters with other controllers.
+    fn execute_management(&self, method: Method, payload: Vec<u8>) -> Result<Vec<u8>, UserError> {
+        match self.execute_ingress(ic00::IC_00, method, payload)? {
+            WasmResult::Reply(bytes) => Ok(bytes),
+            WasmResult::Reject(reason) => {
+                panic!("{} call rejected: {}", method.to_string(), reason)
+            }
+        }
+    }
+
+    /// Updates the settings of the canister with the specified ID. Only the
+    /// settings that are `Some` are changed.
+    pub fn update_settings(
+        &self,
+        canister_id: CanisterId,
+        settings: CanisterSettingsArgs,
+    ) -> Result<(), UserError> {
+        self.execute_management(
+            Method::UpdateSettings,
+            UpdateSettingsArgs {
+                canister_id: canister_id.get(),
+                settings,
+            }
+            .encode(),
+        )
+        .map(|_| ())
+    }
+
+    /// Returns the status of the canister with the specified ID as reported by
+    /// the management canister.
+    pub fn canister_status(
+        &self,
+        canister_id: CanisterId,
+    ) -> Result<CanisterStatusResultV2, UserError> {
+        self.execute_management(
+            Method::CanisterStatus,
+            CanisterIdRecord::from(canister_id).encode(),
+        )
+        .map(|bytes| {
+            CanisterStatusResultV2::decode(&bytes).expect("failed to decode canister status")
+        })
+    }
+
+    /// Stops the canister with the specified ID.
+    pub fn stop_canister(&self, canister_id: CanisterId) -> Result<(), UserError> {
+        self.execute_management(
+            Method::StopCanister,
+            CanisterIdRecord::from(canister_id).encode(),
+        )
+        .map(|_| ())
+    }
+
+    /// Starts the canister with the specified ID.
+    pub fn start_canister(&self, canister_id: CanisterId) -> Result<(), UserError> {
+        self.execute_management(
+            Method::StartCanister,
+            CanisterIdRecord::from(canister_id).encode(),
+        )
+        .map(|_| ())
+    }
+
+    /// Deletes the canister with the specified ID. The canister has to be
+    /// stopped first.
+    pub fn delete_canister(&self, canister_id: CanisterId) -> Result<(), UserError> {
+        self.execute_management(
+            Method::DeleteCanister,
+            CanisterIdRecord::from(canister_id).encode(),
+        )
+        .map(|_| ())
+    }
 }
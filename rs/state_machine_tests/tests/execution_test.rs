@@ -2,8 +2,9 @@ use ic_config::subnet_config::{CyclesAccountManagerConfig, SubnetConfigs};
 use ic_error_types::ErrorCode;
 use ic_registry_subnet_type::SubnetType;
 use ic_state_machine_tests::StateMachine;
-use ic_types::ic00::CanisterSettingsArgs;
-use ic_types::Cycles;
+use ic_types::ic00::{self, CanisterIdRecord, CanisterSettingsArgs, Payload};
+use ic_types::{Cycles, PrincipalId};
+use std::time::Duration;
 
 /// This is a canister that keeps a counter on the heap and exposes various test
 /// methods. Exposed methods:
@@ -261,6 +262,8 @@ async fn test_canister_out_of_cycles() {
             compute_allocation: Some(candid::Nat::from(1)),
             memory_allocation: None,
             freezing_threshold: None,
+            log_visibility: None,
+            reserved_cycles_limit: None,
         }),
     );
 
@@ -354,3 +357,226 @@ async fn test_manifest_computation_memory_expand() {
     let state_hash_3 = env.await_state_hash();
     assert_ne!(state_hash_2, state_hash_3);
 }
+
+/// This is a canister that counts the executions of its global timer.
+/// Exposed methods:
+///  * "set_timer" set the global timer to 3 seconds from now
+///  * "noop"      do nothing
+///  * "read"      read the number of timer executions
+const TIMER_CANISTER: &str = r#"
+            (module
+              (import "ic0" "msg_reply" (func $msg_reply))
+              (import "ic0" "msg_reply_data_append"
+                (func $msg_reply_data_append (param i32 i32)))
+              (import "ic0" "time" (func $time (result i64)))
+              (import "ic0" "global_timer_set"
+                (func $global_timer_set (param i64) (result i64)))
+
+              (func $set_timer
+                (drop (call $global_timer_set
+                  (i64.add (call $time) (i64.const 3000000000))))
+                (call $msg_reply)
+              )
+
+              (func $noop
+                (call $msg_reply)
+              )
+
+              (func $read
+                (call $msg_reply_data_append (i32.const 0) (i32.const 4))
+                (call $msg_reply)
+              )
+
+              (func $global_timer
+                (i32.store (i32.const 0)
+                  (i32.add (i32.load (i32.const 0)) (i32.const 1)))
+              )
+
+              (memory $memory 1)
+              (export "memory" (memory $memory))
+              (export "canister_query read" (func $read))
+              (export "canister_update set_timer" (func $set_timer))
+              (export "canister_update noop" (func $noop))
+              (export "canister_global_timer" (func $global_timer)))"#;
+
+/// Every ingress message moves the time of the state machine forward by one
+/// second, so the timer set by "set_timer" expires in the third round after it.
+#[tokio::test]
+async fn test_global_timer_fires_once_after_deadline() {
+    let env = StateMachine::new();
+
+    let canister_id = env.install_canister_wat(TIMER_CANISTER, vec![], None);
+    env.execute_ingress(canister_id, "set_timer", vec![])
+        .unwrap();
+
+    for _ in 0..2 {
+        env.execute_ingress(canister_id, "noop", vec![]).unwrap();
+        let val = env.query(canister_id, "read", vec![]).unwrap().bytes();
+        assert_eq!(to_int(val), 0);
+    }
+
+    for _ in 0..3 {
+        env.execute_ingress(canister_id, "noop", vec![]).unwrap();
+        let val = env.query(canister_id, "read", vec![]).unwrap().bytes();
+        assert_eq!(to_int(val), 1);
+    }
+}
+
+#[tokio::test]
+async fn test_global_timer_is_preserved_across_upgrades() {
+    let env = StateMachine::new();
+
+    let canister_id = env.install_canister_wat(TIMER_CANISTER, vec![], None);
+    env.execute_ingress(canister_id, "set_timer", vec![])
+        .unwrap();
+    env.upgrade_canister_wat(canister_id, TIMER_CANISTER, vec![]);
+
+    env.execute_ingress(canister_id, "noop", vec![]).unwrap();
+    let val = env.query(canister_id, "read", vec![]).unwrap().bytes();
+    assert_eq!(to_int(val), 0);
+
+    for _ in 0..3 {
+        env.execute_ingress(canister_id, "noop", vec![]).unwrap();
+        let val = env.query(canister_id, "read", vec![]).unwrap().bytes();
+        assert_eq!(to_int(val), 1);
+    }
+}
+
+#[tokio::test]
+async fn test_global_timer_fires_in_empty_rounds() {
+    let env = StateMachine::new();
+
+    let canister_id = env.install_canister_wat(TIMER_CANISTER, vec![], None);
+    env.execute_ingress(canister_id, "set_timer", vec![])
+        .unwrap();
+
+    env.run_rounds(2);
+    let val = env.query(canister_id, "read", vec![]).unwrap().bytes();
+    assert_eq!(to_int(val), 0);
+
+    env.tick();
+    let val = env.query(canister_id, "read", vec![]).unwrap().bytes();
+    assert_eq!(to_int(val), 1);
+}
+
+#[tokio::test]
+async fn test_advance_time_expires_global_timer() {
+    let env = StateMachine::new();
+
+    let canister_id = env.install_canister_wat(TIMER_CANISTER, vec![], None);
+    env.execute_ingress(canister_id, "set_timer", vec![])
+        .unwrap();
+    let time = env.time();
+
+    env.advance_time(Duration::from_secs(10));
+    assert_eq!(env.time(), time + Duration::from_secs(10));
+
+    env.tick();
+    let val = env.query(canister_id, "read", vec![]).unwrap().bytes();
+    assert_eq!(to_int(val), 1);
+}
+
+#[tokio::test]
+async fn test_set_cycles_balance() {
+    let env = StateMachine::new();
+
+    let canister_id = env.install_canister_wat(TEST_CANISTER, vec![], None);
+    env.execute_ingress(canister_id, "inc", vec![]).unwrap();
+
+    env.set_cycles_balance(canister_id, Cycles::new(1_000_000));
+    assert_eq!(env.cycle_balance(canister_id), Cycles::new(1_000_000));
+    assert_eq!(
+        env.canister_status(canister_id).unwrap().cycles(),
+        1_000_000
+    );
+
+    // The balance survives a restart and the canister keeps its state.
+    let env = env.restart_node();
+    assert_eq!(env.cycle_balance(canister_id), Cycles::new(1_000_000));
+    let val = env.query(canister_id, "read", vec![]).unwrap().bytes();
+    assert_eq!(to_int(val), 1);
+}
+
+#[tokio::test]
+async fn test_update_settings_and_ingress_as_controller() {
+    let env = StateMachine::new();
+    let controller = PrincipalId::new_user_test_id(1);
+
+    let canister_id = env.install_canister_wat(TEST_CANISTER, vec![], None);
+    env.update_settings(
+        canister_id,
+        CanisterSettingsArgs {
+            controllers: Some(vec![controller]),
+            freezing_threshold: Some(candid::Nat::from(1_000)),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    // The anonymous principal is no longer a controller.
+    assert_eq!(
+        env.canister_status(canister_id).unwrap_err().code(),
+        ErrorCode::CanisterInvalidController
+    );
+
+    let reply = env
+        .execute_ingress_as(
+            controller,
+            ic00::IC_00,
+            ic00::Method::CanisterStatus,
+            CanisterIdRecord::from(canister_id).encode(),
+        )
+        .unwrap()
+        .bytes();
+    let status = ic00::CanisterStatusResultV2::decode(&reply).unwrap();
+    assert_eq!(status.settings().controllers(), vec![controller]);
+    assert_eq!(status.freezing_threshold(), 1_000);
+}
+
+#[tokio::test]
+async fn test_stop_start_and_delete_canister() {
+    let env = StateMachine::new();
+
+    let canister_id = env.install_canister_wat(TEST_CANISTER, vec![], None);
+    env.stop_canister(canister_id).unwrap();
+    assert_eq!(
+        env.execute_ingress(canister_id, "inc", vec![])
+            .unwrap_err()
+            .code(),
+        ErrorCode::CanisterStopped
+    );
+
+    env.start_canister(canister_id).unwrap();
+    env.execute_ingress(canister_id, "inc", vec![]).unwrap();
+
+    env.stop_canister(canister_id).unwrap();
+    env.delete_canister(canister_id).unwrap();
+    assert_eq!(
+        env.execute_ingress(canister_id, "inc", vec![])
+            .unwrap_err()
+            .code(),
+        ErrorCode::CanisterNotFound
+    );
+}
+
+#[tokio::test]
+async fn test_global_timer_of_stopped_canister_fires_after_restart() {
+    let env = StateMachine::new();
+
+    let canister_id = env.install_canister_wat(TIMER_CANISTER, vec![], None);
+    env.execute_ingress(canister_id, "set_timer", vec![])
+        .unwrap();
+    env.stop_canister(canister_id).unwrap();
+
+    // The timer expires while the canister is stopped, so it must not fire
+    // and must stay active.
+    env.advance_time(Duration::from_secs(5));
+    for _ in 0..3 {
+        env.tick();
+    }
+
+    env.start_canister(canister_id).unwrap();
+    env.tick();
+    let val = env.query(canister_id, "read", vec![]).unwrap().bytes();
+    assert_eq!(to_int(val), 1);
+}
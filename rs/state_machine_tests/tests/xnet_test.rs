@@ -0,0 +1,143 @@
+use ic_state_machine_tests::StateMachine;
+use ic_types::{ingress::WasmResult, CanisterId, Cycles};
+
+/// This is a canister that keeps a counter on the heap. Exposed methods:
+///  * "inc"  increment the counter
+///  * "read" read the counter value
+const COUNTER_CANISTER: &str = r#"
+            (module
+              (import "ic0" "msg_reply" (func $msg_reply))
+              (import "ic0" "msg_reply_data_append"
+                (func $msg_reply_data_append (param i32 i32)))
+
+              (func $inc
+                (i32.store (i32.const 0)
+                  (i32.add (i32.load (i32.const 0)) (i32.const 1)))
+                (call $msg_reply)
+              )
+
+              (func $read
+                (call $msg_reply_data_append (i32.const 0) (i32.const 4))
+                (call $msg_reply)
+              )
+
+              (memory $memory 1)
+              (export "memory" (memory $memory))
+              (export "canister_query read" (func $read))
+              (export "canister_update inc" (func $inc)))"#;
+
+/// This is a canister that calls "inc" on the canister whose ID is passed as
+/// the argument of its "forward" method, attaching 1000 cycles to the call.
+/// It replies with the reply of the callee, or with the reject code as a
+/// 32-bit integer if the call was rejected.
+const PROXY_CANISTER: &str = r#"
+            (module
+              (import "ic0" "msg_arg_data_size" (func $msg_arg_data_size (result i32)))
+              (import "ic0" "msg_arg_data_copy"
+                (func $msg_arg_data_copy (param $dst i32) (param $offset i32) (param $size i32)))
+              (import "ic0" "msg_reply" (func $msg_reply))
+              (import "ic0" "msg_reply_data_append"
+                (func $msg_reply_data_append (param i32 i32)))
+              (import "ic0" "msg_reject_code" (func $msg_reject_code (result i32)))
+              (import "ic0" "call_new"
+                (func $call_new
+                  (param $callee_src i32) (param $callee_size i32)
+                  (param $name_src i32) (param $name_size i32)
+                  (param $reply_fun i32) (param $reply_env i32)
+                  (param $reject_fun i32) (param $reject_env i32)))
+              (import "ic0" "call_cycles_add" (func $call_cycles_add (param i64)))
+              (import "ic0" "call_perform" (func $call_perform (result i32)))
+
+              (func $forward
+                (call $msg_arg_data_copy (i32.const 100) (i32.const 0) (call $msg_arg_data_size))
+                (call $call_new
+                  (i32.const 100) (call $msg_arg_data_size)
+                  (i32.const 0) (i32.const 3)
+                  (i32.const 0) (i32.const 0)
+                  (i32.const 1) (i32.const 0))
+                (call $call_cycles_add (i64.const 1000))
+                (drop (call $call_perform))
+              )
+
+              (func $on_reply (param i32)
+                (call $msg_arg_data_copy (i32.const 200) (i32.const 0) (call $msg_arg_data_size))
+                (call $msg_reply_data_append (i32.const 200) (call $msg_arg_data_size))
+                (call $msg_reply)
+              )
+
+              (func $on_reject (param i32)
+                (i32.store (i32.const 200) (call $msg_reject_code))
+                (call $msg_reply_data_append (i32.const 200) (i32.const 4))
+                (call $msg_reply)
+              )
+
+              (table funcref (elem $on_reply $on_reject))
+              (memory $memory 1)
+              (export "memory" (memory $memory))
+              (data (i32.const 0) "inc")
+              (export "canister_update forward" (func $forward)))"#;
+
+fn to_int(v: Vec<u8>) -> i32 {
+    use std::convert::TryInto;
+    i32::from_le_bytes(v.try_into().unwrap())
+}
+
+/// Sends a "forward" message to the proxy canister on the first subnet and
+/// pumps the XNet streams until the call to `callee` on the second subnet
+/// and its response have been delivered.
+fn forward(subnets: &[StateMachine], proxy: CanisterId, callee: CanisterId) -> WasmResult {
+    let msg_id = subnets[0].send_ingress(proxy, "forward", callee.get().to_vec());
+    StateMachine::execute_xnet_rounds(subnets, 2);
+    subnets[0].await_ingress(msg_id).unwrap()
+}
+
+#[test]
+fn test_inter_subnet_call() {
+    let subnets = StateMachine::new_subnets(2);
+    assert_ne!(subnets[0].subnet_id(), subnets[1].subnet_id());
+
+    let proxy = subnets[0].install_canister_wat(PROXY_CANISTER, vec![], None);
+    let callee = subnets[1].install_canister_wat(COUNTER_CANISTER, vec![], None);
+
+    assert_eq!(forward(&subnets, proxy, callee), WasmResult::Reply(vec![]));
+    let val = subnets[1].query(callee, "read", vec![]).unwrap().bytes();
+    assert_eq!(to_int(val), 1);
+
+    // The streams are picked up where they were left.
+    forward(&subnets, proxy, callee);
+    let val = subnets[1].query(callee, "read", vec![]).unwrap().bytes();
+    assert_eq!(to_int(val), 2);
+}
+
+#[test]
+fn test_inter_subnet_call_to_stopped_canister_is_rejected() {
+    let subnets = StateMachine::new_subnets(2);
+
+    let proxy = subnets[0].install_canister_wat(PROXY_CANISTER, vec![], None);
+    let callee = subnets[1].install_canister_wat(COUNTER_CANISTER, vec![], None);
+    subnets[1].stop_canister(callee).unwrap();
+
+    // RejectCode::CanisterReject
+    let reply = forward(&subnets, proxy, callee).bytes();
+    assert_eq!(to_int(reply), 4);
+
+    subnets[1].start_canister(callee).unwrap();
+    let val = subnets[1].query(callee, "read", vec![]).unwrap().bytes();
+    assert_eq!(to_int(val), 0);
+}
+
+#[test]
+fn test_unaccepted_cycles_are_refunded_across_subnets() {
+    let subnets = StateMachine::new_subnets(2);
+
+    let proxy = subnets[0].install_canister_wat(PROXY_CANISTER, vec![], None);
+    let callee = subnets[1].install_canister_wat(COUNTER_CANISTER, vec![], None);
+    subnets[0].set_cycles_balance(proxy, Cycles::new(1_000_000));
+
+    forward(&subnets, proxy, callee);
+
+    // Computation is free on system subnets and the callee does not accept
+    // the attached cycles, so they all end up back with the caller.
+    assert_eq!(subnets[0].cycle_balance(proxy), Cycles::new(1_000_000));
+    assert_eq!(subnets[1].cycle_balance(callee), Cycles::new(0));
+}
@@ -5,12 +5,13 @@ use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::canister_state::execution_state::SandboxExecutionState;
 use ic_replicated_state::Memory;
 use ic_replicated_state::{
-    canister_state::execution_state::WasmBinary, page_map::PageMap, CanisterMetrics, CanisterState,
-    ExecutionState, NumWasmPages, ReplicatedState, SchedulerState, SystemState,
+    canister_state::execution_state::WasmBinary, page_map::PageMap, CanisterMetrics,
+    CanisterSnapshot, CanisterSnapshots, CanisterState, ExecutionState, NumWasmPages,
+    ReplicatedState, SchedulerState, SnapshotId, SystemState,
 };
 use ic_state_layout::{
-    CanisterStateBits, CheckpointLayout, ExecutionStateBits, ReadPolicy, ReadWritePolicy, RwPolicy,
-    StateLayout,
+    CanisterLayout, CanisterSnapshotBits, CanisterStateBits, CheckpointLayout, ExecutionStateBits,
+    ReadPolicy, ReadWritePolicy, RwPolicy, StateLayout,
 };
 use ic_types::Height;
 use ic_utils::ic_features::*;
@@ -54,6 +55,16 @@ pub fn make_checkpoint(
         layout.tip_to_checkpoint(tip, height, Some(thread_pool))?
     };
 
+    // The new checkpoint does not contain the snapshots deleted since the
+    // previous one, so their files can be removed from the tip.
+    {
+        let tip = layout.tip().map_err(CheckpointError::from)?;
+        for canister_state in state.canisters_iter() {
+            tip.canister(&canister_state.canister_id())?
+                .remove_deleted_snapshots()?;
+        }
+    }
+
     let state = {
         let _timer = metrics
             .step_duration
@@ -120,6 +131,38 @@ fn serialize_canister_to_tip(
         }
         None => None,
     };
+
+    for (snapshot_id, snapshot) in canister_state.system_state.snapshots.iter() {
+        let snapshot_layout = canister_layout.snapshot(*snapshot_id)?;
+        // Snapshots never change after they are taken, so a snapshot that has
+        // been part of a previous checkpoint is already complete in the tip.
+        // Its memories no longer have a delta either, because
+        // `switch_to_checkpoint()` replaced them with the checkpoint files.
+        if snapshot_layout.snapshot().raw_path().exists() {
+            continue;
+        }
+        snapshot_layout.wasm().serialize(&snapshot.wasm_binary)?;
+        snapshot
+            .wasm_memory
+            .page_map
+            .persist_and_sync_delta(&snapshot_layout.vmemory_0())?;
+        snapshot
+            .stable_memory
+            .page_map
+            .persist_and_sync_delta(&snapshot_layout.stable_memory_blob())?;
+        snapshot_layout.snapshot().serialize(
+            (&CanisterSnapshotBits {
+                taken_at_timestamp: snapshot.taken_at_timestamp,
+                heap_size: snapshot.wasm_memory.size,
+                stable_memory_size: snapshot.stable_memory.size,
+                exported_globals: snapshot.exported_globals.clone(),
+                exports: snapshot.exports.clone(),
+                certified_data: snapshot.certified_data.clone(),
+            })
+                .into(),
+        )?;
+    }
+
     canister_layout
         .canister()
         .serialize(
@@ -158,6 +201,13 @@ fn serialize_canister_to_tip(
                     .map(|es| es.stable_memory.size)
                     .unwrap_or_else(|| NumWasmPages::from(0)),
                 heap_delta_debit: canister_state.scheduler_state.heap_delta_debit,
+                task_queue: canister_state.system_state.task_queue.clone(),
+                canister_log: canister_state.system_state.canister_log.clone(),
+                log_visibility: canister_state.system_state.log_visibility,
+                next_snapshot_id: canister_state.system_state.snapshots.next_snapshot_id(),
+                global_timer: canister_state.system_state.global_timer,
+                reserved_balance: canister_state.system_state.reserved_balance,
+                reserved_balance_limit: canister_state.system_state.reserved_balance_limit,
             }
             .into(),
         )
@@ -296,6 +346,10 @@ fn load_canister_state_from_checkpoint<P: ReadPolicy>(
         consumed_cycles_since_replica_started: canister_state_bits
             .consumed_cycles_since_replica_started,
     };
+    let snapshots = CanisterSnapshots::new(
+        canister_state_bits.next_snapshot_id,
+        load_canister_snapshots_from_checkpoint(checkpoint_layout, &canister_layout, canister_id)?,
+    );
     let system_state = SystemState::new_from_checkpoint(
         canister_state_bits.controllers,
         *canister_id,
@@ -306,6 +360,13 @@ fn load_canister_state_from_checkpoint<P: ReadPolicy>(
         canister_state_bits.certified_data,
         canister_metrics,
         canister_state_bits.cycles_balance,
+        canister_state_bits.task_queue,
+        canister_state_bits.canister_log,
+        canister_state_bits.log_visibility,
+        snapshots,
+        canister_state_bits.global_timer,
+        canister_state_bits.reserved_balance,
+        canister_state_bits.reserved_balance_limit,
     );
 
     Ok(CanisterState {
@@ -320,6 +381,51 @@ fn load_canister_state_from_checkpoint<P: ReadPolicy>(
     })
 }
 
+fn load_canister_snapshots_from_checkpoint<P: ReadPolicy>(
+    checkpoint_layout: &CheckpointLayout<P>,
+    canister_layout: &CanisterLayout<P>,
+    canister_id: &CanisterId,
+) -> Result<BTreeMap<SnapshotId, CanisterSnapshot>, CheckpointError> {
+    let mut snapshots = BTreeMap::new();
+    for snapshot_id in canister_layout.snapshot_ids()? {
+        let snapshot_layout = canister_layout.snapshot(snapshot_id)?;
+        let snapshot_bits = CanisterSnapshotBits::try_from(
+            snapshot_layout.snapshot().deserialize()?,
+        )
+        .map_err(|err| CheckpointError::ProtoError {
+            path: checkpoint_layout.raw_path().into(),
+            field: format!(
+                "canister_states[{}]::snapshots[{}]::snapshot_bits",
+                canister_id, snapshot_id
+            ),
+            proto_err: err.to_string(),
+        })?;
+        let snapshot = CanisterSnapshot {
+            taken_at_timestamp: snapshot_bits.taken_at_timestamp,
+            wasm_binary: snapshot_layout.wasm().deserialize()?,
+            wasm_memory: Memory::new(
+                PageMap::open(
+                    &snapshot_layout.vmemory_0(),
+                    Some(checkpoint_layout.height()),
+                )?,
+                snapshot_bits.heap_size,
+            ),
+            stable_memory: Memory::new(
+                PageMap::open(
+                    &snapshot_layout.stable_memory_blob(),
+                    Some(checkpoint_layout.height()),
+                )?,
+                snapshot_bits.stable_memory_size,
+            ),
+            exported_globals: snapshot_bits.exported_globals,
+            exports: snapshot_bits.exports,
+            certified_data: snapshot_bits.certified_data,
+        };
+        snapshots.insert(snapshot_id, snapshot);
+    }
+    Ok(snapshots)
+}
+
 pub fn handle_disk_format_changes<P: ReadWritePolicy>(
     layout: &CheckpointLayout<P>,
     state: &ReplicatedState,
@@ -729,8 +729,10 @@ fn strip_page_map_deltas(state: &mut ReplicatedState) {
 /// Switches `tip` to the most recent checkpoint file provided by `src`.
 ///
 /// Preconditions:
-/// 1) `tip` and `src` mut have exactly the same set of canisters.
-/// 2) The page deltas must be empty in both states.
+/// 1) `tip` and `src` mut have exactly the same set of canisters and
+///    snapshots.
+/// 2) The page deltas must be empty in both states, except for the deltas of
+///    the snapshots in `tip`, which have been persisted in the checkpoint.
 /// 3) The memory sizes must match.
 fn switch_to_checkpoint(tip: &mut ReplicatedState, src: &ReplicatedState) {
     for (tip_canister, src_canister) in tip.canisters_iter_mut().zip(src.canisters_iter()) {
@@ -761,6 +763,32 @@ fn switch_to_checkpoint(tip: &mut ReplicatedState, src: &ReplicatedState) {
             // execution because the checkpoint file of `tip` has changed.
             tip_state.sandbox_state = SandboxExecutionState::new();
         }
+        assert_eq!(
+            tip_canister.system_state.snapshots.len(),
+            src_canister.system_state.snapshots.len()
+        );
+        for ((tip_id, tip_snapshot), (src_id, src_snapshot)) in tip_canister
+            .system_state
+            .snapshots
+            .iter_mut()
+            .zip(src_canister.system_state.snapshots.iter())
+        {
+            assert_eq!(tip_id, src_id);
+            // The delta of a new snapshot holds its complete memories, which
+            // are now in the checkpoint files. Dropping the delta ensures that
+            // the snapshot is not written again by the next checkpoint.
+            tip_snapshot.wasm_memory.page_map.strip_all_deltas();
+            tip_snapshot
+                .wasm_memory
+                .page_map
+                .switch_to_checkpoint(&src_snapshot.wasm_memory.page_map);
+            tip_snapshot.stable_memory.page_map.strip_all_deltas();
+            tip_snapshot
+                .stable_memory
+                .page_map
+                .switch_to_checkpoint(&src_snapshot.stable_memory.page_map);
+            assert_eq!(tip_snapshot.wasm_memory.size, src_snapshot.wasm_memory.size);
+        }
     }
 }
 
@@ -197,6 +197,36 @@ fn count_chunks(size_bytes: u64, max_chunk_size: u32) -> usize {
     (size_bytes as usize + max_chunk_size as usize - 1) / max_chunk_size as usize
 }
 
+/// Computes the hash of a chunk consisting of `size_bytes` zeros.
+fn zeros_chunk_hash(size_bytes: u32) -> [u8; 32] {
+    let mut hasher = chunk_hasher();
+    let zeros_1kib: [u8; 1024] = [0; 1024];
+    let mut bytes_left = size_bytes as usize;
+    while bytes_left > 0 {
+        let n = bytes_left.min(1024);
+        hasher.write(&zeros_1kib[0..n]);
+        bytes_left -= n;
+    }
+    hasher.finish()
+}
+
+/// Returns the ranges of the file that may contain data. The whole file is
+/// treated as data if the ranges cannot be determined.
+fn file_data_ranges(path: &Path, size_bytes: u64) -> Vec<Range<u64>> {
+    ic_utils::fs::data_ranges(path).unwrap_or_else(|_| vec![0..size_bytes])
+}
+
+/// Checks if the chunk `[offset, offset + size_bytes)` lies entirely within a
+/// hole of a sparse file, given the sorted data ranges of the file. Such
+/// chunks consist of zeros and do not need to be read to be hashed, which
+/// keeps hashing of large and mostly empty memory files cheap.
+fn is_hole(data_ranges: &[Range<u64>], offset: u64, size_bytes: u64) -> bool {
+    let i = data_ranges.partition_point(|range| range.end <= offset);
+    data_ranges
+        .get(i)
+        .map_or(true, |range| range.start >= offset + size_bytes)
+}
+
 /// Checks if the manifest was computed using specified max_chunk_size.
 fn uses_chunk_size(manifest: &Manifest, max_chunk_size: u32) -> bool {
     manifest.chunk_table.iter().all(|chunk| {
@@ -283,6 +313,14 @@ fn build_chunk_table_parallel(
     let file_cache: Arc<Mutex<HashMap<u32, Weak<ScopedMmap>>>> =
         Arc::new(Mutex::new(HashMap::new()));
 
+    let data_ranges: Vec<Vec<Range<u64>>> = file_table
+        .iter()
+        .map(|file_info| {
+            file_data_ranges(&root.join(&file_info.relative_path), file_info.size_bytes)
+        })
+        .collect();
+    let zeros_hash = zeros_chunk_hash(max_chunk_size);
+
     // Compute real chunk hashes in parallel.
     // NB. We must populate hashes of all the chunks in a file before we compute
     // file hashes.
@@ -292,8 +330,14 @@ fn build_chunk_table_parallel(
             let file_path = root.join(&file_table[chunk_info.file_index as usize].relative_path);
             let file_size = file_table[chunk_info.file_index as usize].size_bytes;
             let file_cache = Arc::clone(&file_cache);
+            let data_ranges = &data_ranges[chunk_info.file_index as usize];
             scope.execute(move || {
 		let recompute_chunk_hash = || {
+                    if chunk_info.size_bytes == max_chunk_size
+                        && is_hole(data_ranges, chunk_info.offset, chunk_info.size_bytes as u64)
+                    {
+                        return zeros_hash;
+                    }
 		    let mmap: Arc<ScopedMmap> = if file_size > max_chunk_size as u64 {
 			// We only use the file cache if there is more than one chunk in the file,
 			// otherwise the synchronization cost is unnecessary.
@@ -372,6 +416,7 @@ fn build_chunk_table_sequential(
     let mut chunk_table = Vec::new();
     let mut file_table = Vec::new();
     let mut chunk_index: usize = 0;
+    let zeros_hash = zeros_chunk_hash(max_chunk_size);
 
     for (file_index, FileWithSize(relative_path, size_bytes)) in files.into_iter().enumerate() {
         let mut file_hash = if relative_path.ends_with("state_file") {
@@ -384,6 +429,12 @@ fn build_chunk_table_sequential(
 
         let num_chunks = count_chunks(size_bytes, max_chunk_size);
 
+        let data_ranges = if relative_path.ends_with("state_file") {
+            vec![0..size_bytes]
+        } else {
+            file_data_ranges(&root.join(&relative_path), size_bytes)
+        };
+
         (num_chunks as u32).update_hash(&mut file_hash);
 
         let compute_file_chunk_hashes = |data: &[u8]| {
@@ -394,6 +445,11 @@ fn build_chunk_table_sequential(
                 let offset = size_bytes - bytes_left;
 
                 let recompute_chunk_hash = || {
+                    if chunk_size == max_chunk_size as u64
+                        && is_hole(&data_ranges, offset, chunk_size)
+                    {
+                        return zeros_hash;
+                    }
                     let mut hasher = if relative_path.ends_with("state_file") {
                         cow_chunk_hasher()
                     } else {
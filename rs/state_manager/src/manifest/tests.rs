@@ -495,6 +495,56 @@ fn test_filter_all_zero_chunks() {
     assert_eq!(filter_out_zero_chunks(&manifest), fetch_chunks);
 }
 
+#[test]
+fn sparse_and_dense_files_have_the_same_manifest() {
+    use std::io::{Seek, SeekFrom, Write};
+
+    let metrics_registry = MetricsRegistry::new();
+    let manifest_metrics = ManifestMetrics::new(&metrics_registry);
+    let chunk_size: u64 = 1024 * 1024;
+    let file_size = 64 * chunk_size + 100;
+    let data_offset = 40 * chunk_size + 7;
+
+    // The sparse file only has data in the middle, the rest of it is holes.
+    let sparse_dir = tempfile::TempDir::new().expect("failed to create a temporary directory");
+    let mut file =
+        fs::File::create(sparse_dir.path().join("memory")).expect("failed to create file 'memory'");
+    file.set_len(file_size).unwrap();
+    file.seek(SeekFrom::Start(data_offset)).unwrap();
+    file.write_all(&[1u8; 4096]).unwrap();
+    drop(file);
+
+    // The dense file has the same contents with all zeros written explicitly.
+    let dense_dir = tempfile::TempDir::new().expect("failed to create a temporary directory");
+    let mut contents = vec![0u8; file_size as usize];
+    contents[data_offset as usize..data_offset as usize + 4096].copy_from_slice(&[1u8; 4096]);
+    fs::write(dense_dir.path().join("memory"), contents).expect("failed to create file 'memory'");
+
+    let mut thread_pool = scoped_threadpool::Pool::new(NUM_THREADS);
+    let mut compute = |root: &std::path::Path| {
+        compute_manifest(
+            &mut thread_pool,
+            &manifest_metrics,
+            &no_op_logger(),
+            STATE_SYNC_V1,
+            root,
+            chunk_size as u32,
+            None,
+        )
+        .expect("failed to compute manifest")
+    };
+    let sparse_manifest = compute(sparse_dir.path());
+    let dense_manifest = compute(dense_dir.path());
+
+    assert_eq!(sparse_manifest, dense_manifest);
+    // The last chunk is shorter than the default chunk size, so it is not
+    // recognized as an all-zero chunk.
+    assert_eq!(
+        filter_out_zero_chunks(&sparse_manifest),
+        maplit::hashset! {40, 64}
+    );
+}
+
 #[test]
 fn test_missing_simple_manifest() {
     let (_, manifest_old) = simple_manifest();
@@ -980,11 +980,12 @@ impl IncompleteState {
                 .map(|i| manifest_new.chunk_table[*i].size_bytes as u64)
                 .sum();
 
-            let preallocate_bytes = diff_script.zeros_chunks * crate::manifest::DEFAULT_CHUNK_SIZE;
+            let preallocate_bytes =
+                diff_script.zeros_chunks as u64 * crate::manifest::DEFAULT_CHUNK_SIZE as u64;
 
             state_sync_size_fetch.inc_by(diff_bytes);
-            state_sync_size_preallocate.inc_by(preallocate_bytes as u64);
-            state_sync_size_copy.inc_by(total_bytes - diff_bytes - preallocate_bytes as u64);
+            state_sync_size_preallocate.inc_by(preallocate_bytes);
+            state_sync_size_copy.inc_by(total_bytes - diff_bytes - preallocate_bytes);
 
             fetch_chunks
         } else {
@@ -9,8 +9,8 @@ use ic_interfaces::{
 use ic_logger::replica_logger::no_op_logger;
 use ic_metrics::MetricsRegistry;
 use ic_replicated_state::{
-    page_map::PageIndex, testing::ReplicatedStateTesting, NumWasmPages, PageMap, ReplicatedState,
-    Stream,
+    page_map::PageIndex, testing::ReplicatedStateTesting, CanisterSnapshot, NumWasmPages, PageMap,
+    ReplicatedState, Stream,
 };
 use ic_state_manager::StateManagerImpl;
 use ic_sys::PAGE_SIZE;
@@ -235,6 +235,149 @@ fn stable_memory_is_persisted() {
     });
 }
 
+/// Size of the stable memory used by the big stable memory tests. Only the
+/// first and the last page are ever written, so the checkpoint files stay
+/// sparse.
+const BIG_STABLE_MEMORY_SIZE: u64 = 24 << 30;
+
+fn insert_canister_with_big_stable_memory(state: &mut ReplicatedState, canister_id: CanisterId) {
+    insert_dummy_canister(state, canister_id);
+    let canister_state = state.canister_state_mut(&canister_id).unwrap();
+    let stable_memory = &mut canister_state
+        .execution_state
+        .as_mut()
+        .unwrap()
+        .stable_memory;
+    stable_memory.size = NumWasmPages::new((BIG_STABLE_MEMORY_SIZE >> 16) as usize);
+    stable_memory.page_map.update(&[
+        (PageIndex::new(0), &[1u8; PAGE_SIZE]),
+        (
+            PageIndex::new(BIG_STABLE_MEMORY_SIZE / PAGE_SIZE as u64 - 1),
+            &[2u8; PAGE_SIZE],
+        ),
+    ]);
+}
+
+fn assert_has_big_stable_memory(state: &ReplicatedState, canister_id: CanisterId) {
+    let canister_state = state.canister_state(&canister_id).unwrap();
+    let stable_memory = &canister_state
+        .execution_state
+        .as_ref()
+        .unwrap()
+        .stable_memory;
+    assert_eq!(
+        NumWasmPages::new((BIG_STABLE_MEMORY_SIZE >> 16) as usize),
+        stable_memory.size
+    );
+    let last_page = BIG_STABLE_MEMORY_SIZE / PAGE_SIZE as u64 - 1;
+    let page_map = &stable_memory.page_map;
+    assert_eq!(&[1u8; PAGE_SIZE], page_map.get_page(PageIndex::new(0)));
+    assert_eq!(&[0u8; PAGE_SIZE], page_map.get_page(PageIndex::new(1)));
+    assert_eq!(
+        &[0u8; PAGE_SIZE],
+        page_map.get_page(PageIndex::new(last_page / 2))
+    );
+    assert_eq!(
+        &[2u8; PAGE_SIZE],
+        page_map.get_page(PageIndex::new(last_page))
+    );
+}
+
+#[test]
+fn big_stable_memory_is_persisted() {
+    state_manager_restart_test(|state_manager, restart_fn| {
+        let (_height, mut state) = state_manager.take_tip();
+        insert_canister_with_big_stable_memory(&mut state, canister_test_id(100));
+        state_manager.commit_and_certify(state, height(1), CertificationScope::Full);
+
+        let (_height, state) = state_manager.take_tip();
+        assert_has_big_stable_memory(&state, canister_test_id(100));
+
+        let state_manager = restart_fn(state_manager);
+
+        let recovered = state_manager.get_latest_state();
+        assert_eq!(height(1), recovered.height());
+        assert_has_big_stable_memory(&recovered.take(), canister_test_id(100));
+    });
+}
+
+#[test]
+fn canister_snapshots_are_persisted() {
+    use ic_state_layout::{CheckpointLayout, RwPolicy};
+
+    state_manager_restart_test(|state_manager, restart_fn| {
+        let canister_id = canister_test_id(100);
+        let (_height, mut state) = state_manager.take_tip();
+        insert_dummy_canister(&mut state, canister_id);
+        let canister_state = state.canister_state_mut(&canister_id).unwrap();
+        let execution_state = canister_state.execution_state.as_mut().unwrap();
+        execution_state.stable_memory.size = NumWasmPages::new(2);
+        execution_state.stable_memory.page_map = PageMap::from(&[1; 100][..]);
+        let snapshot =
+            CanisterSnapshot::from_execution_state(execution_state, vec![1, 2, 3], mock_time());
+        let kept_id = canister_state.system_state.snapshots.push(snapshot.clone());
+        let deleted_id = canister_state.system_state.snapshots.push(snapshot.clone());
+        state_manager.commit_and_certify(state, height(1), CertificationScope::Full);
+
+        // Delete one of the snapshots the same way the management canister
+        // does and change the memory of the canister.
+        let (_height, mut state) = state_manager.take_tip();
+        CheckpointLayout::<RwPolicy>::new(state.path().into(), Height::from(0))
+            .and_then(|layout| layout.canister(&canister_id))
+            .and_then(|layout| layout.snapshot(deleted_id))
+            .and_then(|layout| layout.mark_deleted())
+            .unwrap();
+        let canister_state = state.canister_state_mut(&canister_id).unwrap();
+        canister_state.system_state.snapshots.remove(deleted_id);
+        canister_state
+            .execution_state
+            .as_mut()
+            .unwrap()
+            .stable_memory
+            .page_map = PageMap::from(&[2; 100][..]);
+        state_manager.commit_and_certify(state, height(2), CertificationScope::Full);
+
+        // The kept snapshot is backed by the checkpoint files, so it is not
+        // written again by the following checkpoints, and the files of the
+        // deleted snapshot are gone.
+        let (_height, state) = state_manager.take_tip();
+        let kept_snapshot = state
+            .canister_state(&canister_id)
+            .unwrap()
+            .system_state
+            .snapshots
+            .get(kept_id)
+            .unwrap();
+        assert!(kept_snapshot
+            .stable_memory
+            .page_map
+            .get_page_delta_indices()
+            .is_empty());
+        let canister_path = CheckpointLayout::<RwPolicy>::new(state.path().into(), Height::from(0))
+            .and_then(|layout| layout.canister(&canister_id))
+            .unwrap()
+            .raw_path();
+        assert!(!canister_path
+            .join("snapshots")
+            .join(format!("{:016x}", deleted_id))
+            .exists());
+
+        let state_manager = restart_fn(state_manager);
+
+        let recovered = state_manager.get_latest_state();
+        assert_eq!(height(2), recovered.height());
+        let state = recovered.take();
+        let snapshots = &state
+            .canister_state(&canister_id)
+            .unwrap()
+            .system_state
+            .snapshots;
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots.get(kept_id), Some(&snapshot));
+        assert_eq!(snapshots.next_snapshot_id(), deleted_id + 1);
+    });
+}
+
 #[test]
 fn missing_stable_memory_file_is_handled() {
     use ic_state_layout::{CheckpointLayout, RwPolicy};
@@ -1333,6 +1476,50 @@ fn can_do_simple_state_sync_transfer() {
     })
 }
 
+#[test]
+fn can_state_sync_big_stable_memory() {
+    state_manager_test(|src_metrics, src_state_manager| {
+        let (_height, mut state) = src_state_manager.take_tip();
+        insert_canister_with_big_stable_memory(&mut state, canister_test_id(100));
+
+        src_state_manager.commit_and_certify(state, height(1), CertificationScope::Full);
+        let hash = wait_for_checkpoint(&src_state_manager, height(1));
+        let id = StateSyncArtifactId {
+            height: height(1),
+            hash,
+        };
+
+        let msg = src_state_manager
+            .get_validated_by_identifier(&id)
+            .expect("failed to get state sync messages");
+
+        assert_error_counters(src_metrics);
+
+        state_manager_test(|dst_metrics, dst_state_manager| {
+            let chunkable = dst_state_manager.create_chunkable_state(&id);
+
+            let dst_msg = pipe_state_sync(msg, chunkable);
+            dst_state_manager
+                .check_artifact_acceptance(dst_msg, &node_test_id(0))
+                .expect("Failed to process state sync artifact");
+
+            let recovered_state = dst_state_manager
+                .get_state_at(height(1))
+                .expect("Destination state manager didn't receive the state")
+                .take();
+
+            assert_eq!(height(1), dst_state_manager.latest_state_height());
+            assert_has_big_stable_memory(&recovered_state, canister_test_id(100));
+
+            assert_eq!(
+                0,
+                fetch_int_gauge(dst_metrics, "state_sync_remaining_chunks").unwrap()
+            );
+            assert_error_counters(dst_metrics);
+        })
+    })
+}
+
 #[test]
 fn can_state_sync_from_cache() {
     state_manager_test(|src_metrics, src_state_manager| {
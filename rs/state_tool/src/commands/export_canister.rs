@@ -0,0 +1,66 @@
+//! Exports the Wasm module and the memories of a canister to files.
+
+use crate::commands::utils;
+use ic_replicated_state::{num_bytes_try_from, Memory, PageIndex};
+use ic_sys::PAGE_SIZE;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Writes the Wasm module, the heap and the stable memory of the canister
+/// `canister_id` in the checkpoint at `path` to `canister.wasm`, `heap.bin`
+/// and `stable_memory.bin` in the `output` directory.
+pub fn do_export(path: PathBuf, canister_id: String, output: PathBuf) -> Result<(), String> {
+    let state = utils::load_state(path)?;
+    let canister = utils::find_canister(&state, &canister_id)?;
+    let execution_state = canister
+        .execution_state
+        .as_ref()
+        .ok_or_else(|| format!("canister {} has no Wasm module installed", canister_id))?;
+
+    std::fs::create_dir_all(&output)
+        .map_err(|e| format!("failed to create directory {}: {}", output.display(), e))?;
+
+    let wasm_path = output.join("canister.wasm");
+    std::fs::write(&wasm_path, execution_state.wasm_binary.binary.as_slice())
+        .map_err(|e| format!("failed to write {}: {}", wasm_path.display(), e))?;
+    println!("Wasm module:   {}", wasm_path.display());
+
+    let heap_path = output.join("heap.bin");
+    write_memory(&execution_state.wasm_memory, &heap_path)?;
+    println!("Heap:          {}", heap_path.display());
+
+    let stable_memory_path = output.join("stable_memory.bin");
+    write_memory(&execution_state.stable_memory, &stable_memory_path)?;
+    println!("Stable memory: {}", stable_memory_path.display());
+
+    Ok(())
+}
+
+/// Writes the full contents of `memory`, i.e. as many bytes as the canister
+/// can address, to the file at `path`. Pages that are unallocated or contain
+/// only zeros are skipped, so that the file stays sparse.
+fn write_memory(memory: &Memory, path: &Path) -> Result<(), String> {
+    let size = num_bytes_try_from(memory.size)?.get() as usize;
+    let write_error = |e: std::io::Error| format!("failed to write {}: {}", path.display(), e);
+    let file =
+        File::create(path).map_err(|e| format!("failed to create {}: {}", path.display(), e))?;
+    let mut writer = BufWriter::new(file);
+    let num_pages = (size / PAGE_SIZE).min(memory.page_map.num_host_pages());
+    for page in 0..num_pages as u64 {
+        let bytes = memory.page_map.get_page(PageIndex::new(page));
+        if bytes.iter().all(|b| *b == 0) {
+            writer
+                .seek(SeekFrom::Current(PAGE_SIZE as i64))
+                .map_err(write_error)?;
+        } else {
+            writer.write_all(bytes).map_err(write_error)?;
+        }
+    }
+    let file = writer
+        .into_inner()
+        .map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+    // Extends the file with a hole up to the full memory size, which also
+    // covers trailing pages that were skipped above.
+    file.set_len(size as u64).map_err(write_error)
+}
@@ -0,0 +1,73 @@
+//! Inspects the canisters and the ingress history of a checkpoint.
+
+use crate::commands::utils;
+use ic_replicated_state::num_bytes_try_from;
+use ic_types::{ingress::IngressStatus, messages::MessageId, NumBytes};
+use std::convert::TryFrom;
+use std::path::PathBuf;
+
+/// Lists the canisters in the checkpoint at `path` together with their status,
+/// memory sizes and cycle balances.
+pub fn do_list_canisters(path: PathBuf) -> Result<(), String> {
+    let state = utils::load_state(path)?;
+
+    if state.canister_states.is_empty() {
+        println!("No canisters to display");
+        return Ok(());
+    }
+
+    println!(
+        "{:<30}    {:<10}    {:>15}    {:>15}    {:>15}    {:>30}",
+        "CANISTER ID", "STATUS", "WASM MEMORY", "STABLE MEMORY", "MEMORY USAGE", "CYCLES"
+    );
+
+    for canister in state.canisters_iter() {
+        let (wasm_memory, stable_memory) = match &canister.execution_state {
+            Some(execution_state) => (
+                num_bytes_try_from(execution_state.wasm_memory.size)?,
+                num_bytes_try_from(execution_state.stable_memory.size)?,
+            ),
+            None => (NumBytes::from(0), NumBytes::from(0)),
+        };
+
+        println!(
+            "{:<30}    {:<10}    {:>15}    {:>15}    {:>15}    {:>30}",
+            canister.canister_id().to_string(),
+            canister.system_state.status_string(),
+            wasm_memory.get(),
+            stable_memory.get(),
+            canister.memory_usage(state.metadata.own_subnet_type).get(),
+            canister.system_state.cycles_balance.get()
+        );
+    }
+
+    Ok(())
+}
+
+/// Displays the input and output queues of the canister `canister_id` in the
+/// checkpoint at `path`.
+pub fn do_show_queues(path: PathBuf, canister_id: String) -> Result<(), String> {
+    let state = utils::load_state(path)?;
+    let canister = utils::find_canister(&state, &canister_id)?;
+
+    println!("{:#?}", canister.system_state.queues());
+
+    Ok(())
+}
+
+/// Displays the ingress history entry of the message with the hex-encoded
+/// `message_id` in the checkpoint at `path`.
+pub fn do_show_ingress(path: PathBuf, message_id: String) -> Result<(), String> {
+    let bytes = hex::decode(message_id.trim_start_matches("0x"))
+        .map_err(|e| format!("failed to decode message ID {}: {}", message_id, e))?;
+    let message_id = MessageId::try_from(&bytes[..])
+        .map_err(|e| format!("invalid message ID {}: {}", message_id, e))?;
+
+    let state = utils::load_state(path)?;
+    match state.get_ingress_status(&message_id) {
+        IngressStatus::Unknown => println!("No ingress history for message {}", message_id),
+        status => println!("{:#?}", status),
+    }
+
+    Ok(())
+}
@@ -2,8 +2,13 @@
 
 use ic_config::{config_parser::ConfigSource, ConfigOptional};
 use ic_logger::replica_logger::no_op_logger;
-use ic_state_layout::StateLayout;
+use ic_registry_subnet_type::SubnetType;
+use ic_replicated_state::{CanisterState, ReplicatedState};
+use ic_state_layout::{CompleteCheckpointLayout, StateLayout};
+use ic_state_manager::checkpoint::load_checkpoint;
+use ic_types::{CanisterId, Height};
 use std::path::PathBuf;
+use std::str::FromStr;
 
 /// Loads the location of the state root from the given `replica` configuration
 /// file.
@@ -24,3 +29,24 @@ pub fn locate_state_root(config_path: PathBuf) -> Result<StateLayout, String> {
 
     Ok(StateLayout::new(no_op_logger(), state_root))
 }
+
+/// Loads the replicated state from the checkpoint at `path`.
+pub fn load_state(path: PathBuf) -> Result<ReplicatedState, String> {
+    let cp_layout = CompleteCheckpointLayout::new(path.clone(), Height::new(0))
+        .map_err(|e| format!("failed to create checkpoint layout: {}", e))?;
+
+    load_checkpoint(&cp_layout, SubnetType::Application, None)
+        .map_err(|e| format!("failed to load checkpoint at {}: {}", path.display(), e))
+}
+
+/// Looks up the canister with the textual ID `canister_id` in `state`.
+pub fn find_canister<'a>(
+    state: &'a ReplicatedState,
+    canister_id: &str,
+) -> Result<&'a CanisterState, String> {
+    let canister_id = CanisterId::from_str(canister_id)
+        .map_err(|e| format!("failed to parse canister ID {}: {}", canister_id, e))?;
+    state
+        .canister_state(&canister_id)
+        .ok_or_else(|| format!("canister {} does not exist in the checkpoint", canister_id))
+}
@@ -0,0 +1,229 @@
+//! Verifies the integrity of a checkpoint on disk.
+
+use crate::commands::{manifest::compute_checkpoint_manifest, utils};
+use ic_protobuf::state::v1 as pb;
+use ic_state_manager::{
+    manifest::{file_chunk_range, manifest_hash},
+    tree_hash::hash_state,
+};
+use ic_types::state_sync::Manifest;
+use prost::Message;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+
+/// Recomputes the manifest of the checkpoint at `path` and compares it to the
+/// manifest the state manager stored for the checkpoint, if there is one, and
+/// to the hex-encoded `root_hash`, if specified. If `certified_state_hash` is
+/// specified, the partial state hash of the checkpoint is compared to it as
+/// well.
+pub fn do_verify(
+    path: PathBuf,
+    root_hash: Option<String>,
+    certified_state_hash: Option<String>,
+) -> Result<(), String> {
+    let root_hash = root_hash.map(|h| decode_hash(&h)).transpose()?;
+    let certified_state_hash = certified_state_hash.map(|h| decode_hash(&h)).transpose()?;
+    let stored_manifest = load_stored_manifest(&path)?;
+    if root_hash.is_none() && stored_manifest.is_none() {
+        return Err(format!(
+            "no manifest stored for checkpoint {}, a root hash is needed to verify it",
+            path.display()
+        ));
+    }
+
+    let manifest = compute_checkpoint_manifest(path.clone())?;
+    let actual_root_hash = manifest_hash(&manifest);
+    println!("ROOT HASH: {}", hex::encode(actual_root_hash));
+
+    let mut failures = 0;
+
+    if let Some(stored_manifest) = &stored_manifest {
+        let mismatches = diff_manifests(stored_manifest, &manifest);
+        if mismatches.is_empty() {
+            println!("✓ Checkpoint matches the stored manifest");
+        } else {
+            println!("✗ Checkpoint does not match the stored manifest:");
+            for mismatch in mismatches.iter() {
+                println!("\t{}", mismatch);
+            }
+            failures += 1;
+        }
+    }
+
+    if let Some(root_hash) = root_hash {
+        if root_hash == actual_root_hash {
+            println!("✓ Root hash matches {}", hex::encode(root_hash));
+        } else {
+            println!("✗ Root hash does not match {}", hex::encode(root_hash));
+            if let Some(stored_manifest) = &stored_manifest {
+                if manifest_hash(stored_manifest) != root_hash {
+                    println!("\tThe stored manifest does not match the root hash either");
+                }
+            }
+            failures += 1;
+        }
+    }
+
+    if let Some(certified_state_hash) = certified_state_hash {
+        let state = utils::load_state(path)?;
+        let partial_state_hash = hash_state(&state).digest().0;
+        if partial_state_hash == certified_state_hash {
+            println!(
+                "✓ Partial state hash matches {}",
+                hex::encode(certified_state_hash)
+            );
+        } else {
+            println!(
+                "✗ Partial state hash {} does not match {}",
+                hex::encode(partial_state_hash),
+                hex::encode(certified_state_hash)
+            );
+            failures += 1;
+        }
+    }
+
+    if failures > 0 {
+        return Err("✗ Verification FAILED".to_string());
+    }
+    Ok(())
+}
+
+/// Decodes a hex-encoded SHA-256 hash.
+fn decode_hash(hash: &str) -> Result<[u8; 32], String> {
+    let bytes = hex::decode(hash).map_err(|e| format!("failed to decode hash {}: {}", hash, e))?;
+    <[u8; 32]>::try_from(&bytes[..]).map_err(|_| format!("hash {} is not 32 bytes long", hash))
+}
+
+/// Loads the manifest that the state manager stored for the checkpoint at
+/// `path` in the `states_metadata.pbuf` file of its state root. Returns `None`
+/// if `path` is not located in a state root or no manifest is stored for the
+/// checkpoint.
+fn load_stored_manifest(path: &Path) -> Result<Option<Manifest>, String> {
+    let height = match path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| u64::from_str_radix(name, 16).ok())
+    {
+        Some(height) => height,
+        None => return Ok(None),
+    };
+    let metadata_path = match path.parent().and_then(|checkpoints| checkpoints.parent()) {
+        Some(state_root) => state_root.join("states_metadata.pbuf"),
+        None => return Ok(None),
+    };
+    if !metadata_path.exists() {
+        return Ok(None);
+    }
+
+    let buf = std::fs::read(&metadata_path)
+        .map_err(|e| format!("failed to read {}: {}", metadata_path.display(), e))?;
+    let mut metadata = pb::StatesMetadata::decode(&buf[..])
+        .map_err(|e| format!("failed to decode {}: {}", metadata_path.display(), e))?;
+    match metadata
+        .by_height
+        .remove(&height)
+        .and_then(|state_metadata| state_metadata.manifest)
+    {
+        Some(manifest) => Manifest::try_from(manifest).map(Some).map_err(|e| {
+            format!(
+                "failed to decode the manifest in {}: {}",
+                metadata_path.display(),
+                e
+            )
+        }),
+        None => Ok(None),
+    }
+}
+
+/// Lists the files and chunks in which the `actual` manifest differs from the
+/// `expected` one.
+fn diff_manifests(expected: &Manifest, actual: &Manifest) -> Vec<String> {
+    let expected_files: BTreeMap<_, _> = expected
+        .file_table
+        .iter()
+        .enumerate()
+        .map(|(index, file)| (&file.relative_path, index))
+        .collect();
+    let actual_files: BTreeMap<_, _> = actual
+        .file_table
+        .iter()
+        .enumerate()
+        .map(|(index, file)| (&file.relative_path, index))
+        .collect();
+
+    let mut mismatches = vec![];
+    if expected.version != actual.version {
+        mismatches.push(format!(
+            "manifest version {} differs from the stored version {}",
+            actual.version, expected.version
+        ));
+    }
+    for (path, expected_index) in expected_files.iter() {
+        let actual_index = match actual_files.get(path) {
+            Some(index) => *index,
+            None => {
+                mismatches.push(format!("{}: file is missing", path.display()));
+                continue;
+            }
+        };
+        let expected_file = &expected.file_table[*expected_index];
+        let actual_file = &actual.file_table[actual_index];
+        if expected_file.size_bytes != actual_file.size_bytes {
+            mismatches.push(format!(
+                "{}: size is {} bytes, expected {} bytes",
+                path.display(),
+                actual_file.size_bytes,
+                expected_file.size_bytes
+            ));
+        }
+        if expected_file.hash == actual_file.hash {
+            continue;
+        }
+
+        let expected_chunks =
+            &expected.chunk_table[file_chunk_range(&expected.chunk_table, *expected_index)];
+        let actual_chunks =
+            &actual.chunk_table[file_chunk_range(&actual.chunk_table, actual_index)];
+        let mut chunk_mismatch = false;
+        for (chunk, (expected_chunk, actual_chunk)) in
+            expected_chunks.iter().zip(actual_chunks.iter()).enumerate()
+        {
+            if expected_chunk.hash != actual_chunk.hash
+                || expected_chunk.size_bytes != actual_chunk.size_bytes
+            {
+                mismatches.push(format!(
+                    "{}: chunk {} (offset {}, {} bytes) has hash {}, expected {}",
+                    path.display(),
+                    chunk,
+                    actual_chunk.offset,
+                    actual_chunk.size_bytes,
+                    hex::encode(actual_chunk.hash),
+                    hex::encode(expected_chunk.hash)
+                ));
+                chunk_mismatch = true;
+            }
+        }
+        if expected_chunks.len() != actual_chunks.len() {
+            mismatches.push(format!(
+                "{}: has {} chunks, expected {}",
+                path.display(),
+                actual_chunks.len(),
+                expected_chunks.len()
+            ));
+        } else if !chunk_mismatch {
+            mismatches.push(format!(
+                "{}: file hash {} differs from the stored hash {}",
+                path.display(),
+                hex::encode(actual_file.hash),
+                hex::encode(expected_file.hash)
+            ));
+        }
+    }
+    for path in actual_files.keys() {
+        if !expected_files.contains_key(path) {
+            mismatches.push(format!("{}: unexpected file", path.display()));
+        }
+    }
+    mismatches
+}
@@ -2,7 +2,8 @@
 //!
 //! A command-line tool to manage Internet Computer replicated states (decode
 //! persisted state files, diff checkpoints, compute partial state hashes and
-//! checkpoint manifests, import state trees).
+//! checkpoint manifests, import state trees, inspect and export canisters,
+//! verify checkpoints).
 
 use std::path::PathBuf;
 use structopt::StructOpt;
@@ -49,6 +50,26 @@ enum Opt {
         path: PathBuf,
     },
 
+    /// Verifies that a checkpoint is intact by recomputing its manifest and
+    /// comparing it to the stored manifest and the given hashes.
+    #[structopt(name = "verify")]
+    Verify {
+        /// Path to a checkpoint.
+        #[structopt(long = "state")]
+        path: PathBuf,
+
+        /// Hex-encoded expected root hash of the manifest, e.g. the state hash
+        /// of a catch-up package.
+        #[structopt(long = "root_hash")]
+        root_hash: Option<String>,
+
+        /// Hex-encoded certified partial state hash to compare the canonical
+        /// tree hash of the checkpoint with. The signature of the certification
+        /// is not checked.
+        #[structopt(long = "certified_state_hash")]
+        certified_state_hash: Option<String>,
+    },
+
     /// Enumerates persisted states.
     #[structopt(name = "list")]
     ListStates {
@@ -64,6 +85,55 @@ enum Opt {
         #[structopt(long = "file")]
         file: PathBuf,
     },
+
+    /// Lists the canisters in a checkpoint with their status, memory sizes and
+    /// cycle balances.
+    #[structopt(name = "canisters")]
+    ListCanisters {
+        /// Path to a checkpoint.
+        #[structopt(long = "state")]
+        path: PathBuf,
+    },
+
+    /// Writes the Wasm module, heap and stable memory of a canister to files.
+    #[structopt(name = "export_canister")]
+    ExportCanister {
+        /// Path to a checkpoint.
+        #[structopt(long = "state")]
+        path: PathBuf,
+
+        /// ID of the canister to export.
+        #[structopt(long = "canister")]
+        canister_id: String,
+
+        /// Directory to write the files to.
+        #[structopt(long = "output")]
+        output: PathBuf,
+    },
+
+    /// Displays the input and output queues of a canister.
+    #[structopt(name = "queues")]
+    Queues {
+        /// Path to a checkpoint.
+        #[structopt(long = "state")]
+        path: PathBuf,
+
+        /// ID of the canister whose queues to display.
+        #[structopt(long = "canister")]
+        canister_id: String,
+    },
+
+    /// Displays the ingress history entry of a message.
+    #[structopt(name = "ingress")]
+    Ingress {
+        /// Path to a checkpoint.
+        #[structopt(long = "state")]
+        path: PathBuf,
+
+        /// Hex-encoded ID of the ingress message.
+        #[structopt(long = "message_id")]
+        message_id: String,
+    },
 }
 
 fn main() {
@@ -77,8 +147,21 @@ fn main() {
             height,
         } => commands::import_state::do_import(state, config, height),
         Opt::Manifest { path } => commands::manifest::do_compute_manifest(path),
+        Opt::Verify {
+            path,
+            root_hash,
+            certified_state_hash,
+        } => commands::verify::do_verify(path, root_hash, certified_state_hash),
         Opt::ListStates { config } => commands::list::do_list(config),
         Opt::Decode { file } => commands::decode::do_decode(file),
+        Opt::ListCanisters { path } => commands::inspect::do_list_canisters(path),
+        Opt::ExportCanister {
+            path,
+            canister_id,
+            output,
+        } => commands::export_canister::do_export(path, canister_id, output),
+        Opt::Queues { path, canister_id } => commands::inspect::do_show_queues(path, canister_id),
+        Opt::Ingress { path, message_id } => commands::inspect::do_show_ingress(path, message_id),
     };
 
     if let Err(e) = result {
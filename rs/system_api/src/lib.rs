@@ -6,9 +6,9 @@ mod system_state_accessor_direct;
 
 use ic_ic00_types::IC_00;
 use ic_interfaces::execution_environment::{
-    ExecutionParameters,
+    DefaultOutOfInstructionsHandler, ExecutionParameters,
     HypervisorError::{self, *},
-    HypervisorResult, SubnetAvailableMemory, SystemApi,
+    HypervisorResult, OutOfInstructionsHandler, SubnetAvailableMemory, SystemApi,
     TrapCode::CyclesAmountTooBigFor64Bit,
 };
 use ic_logger::{error, info, ReplicaLogger};
@@ -18,13 +18,13 @@ use ic_replicated_state::{
     canister_state::{system_state::CanisterStatus, ENFORCE_MESSAGE_MEMORY_USAGE},
     memory_required_to_push_request,
     page_map::PAGE_SIZE,
-    Memory, NumWasmPages, PageIndex, StateError, SystemState,
+    CanisterTimer, Memory, NumWasmPages, PageIndex, StateError, SystemState,
 };
 use ic_sys::PageBytes;
 use ic_types::{
     ingress::WasmResult,
     messages::{CallContextId, RejectContext, Request, MAX_INTER_CANISTER_PAYLOAD_IN_BYTES},
-    methods::{Callback, WasmClosure},
+    methods::{Callback, SystemMethod, WasmClosure},
     user_error::RejectCode,
     CanisterId, Cycles, NumBytes, NumInstructions, PrincipalId, SubnetId, Time,
 };
@@ -211,8 +211,10 @@ pub enum ApiType {
         message_accepted: bool,
     },
 
-    // For executing the `canister_heartbeat` method
-    Heartbeat {
+    // For executing the `canister_heartbeat` or `canister_global_timer`
+    // method
+    SystemTask {
+        system_task: SystemMethod,
         time: Time,
         call_context_id: CallContextId,
         own_subnet_id: SubnetId,
@@ -253,7 +255,9 @@ impl ApiType {
         }
     }
 
-    pub fn heartbeat(
+    #[allow(clippy::too_many_arguments)]
+    pub fn system_task(
+        system_task: SystemMethod,
         time: Time,
         call_context_id: CallContextId,
         own_subnet_id: SubnetId,
@@ -262,7 +266,8 @@ impl ApiType {
         routing_table: Arc<RoutingTable>,
         subnet_records: Arc<BTreeMap<SubnetId, SubnetType>>,
     ) -> Self {
-        Self::Heartbeat {
+        Self::SystemTask {
+            system_task,
             time,
             call_context_id,
             own_subnet_id,
@@ -456,18 +461,40 @@ impl ApiType {
             | ApiType::ReplyCallback { .. }
             | ApiType::RejectCallback { .. }
             | ApiType::PreUpgrade { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Cleanup { .. } => ModificationTracking::Track,
         }
     }
 
+    /// Returns the time to use for records added to the canister log during
+    /// the execution, or `None` if the execution is not logged: either because
+    /// it does not run in replicated mode or because it has no time.
+    pub fn canister_log_time(&self) -> Option<Time> {
+        match self {
+            ApiType::Start
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::InspectMessage { .. } => None,
+            ApiType::Init { time, .. }
+            | ApiType::SystemTask { time, .. }
+            | ApiType::Update { time, .. }
+            | ApiType::Cleanup { time, .. }
+            | ApiType::ReplicatedQuery { time, .. }
+            | ApiType::PreUpgrade { time, .. }
+            | ApiType::ReplyCallback { time, .. }
+            | ApiType::RejectCallback { time, .. } => Some(*time),
+        }
+    }
+
     /// Returns a string slice representation of the enum variant name for use
     /// e.g. as a metric label.
     pub fn as_str(&self) -> &'static str {
         match self {
             ApiType::Start { .. } => "start",
             ApiType::Init { .. } => "init",
-            ApiType::Heartbeat { .. } => "heartbeat",
+            ApiType::SystemTask { system_task, .. } => match system_task {
+                SystemMethod::CanisterGlobalTimer => "global timer",
+                _ => "heartbeat",
+            },
             ApiType::Update { .. } => "update",
             ApiType::ReplicatedQuery { .. } => "replicated query",
             ApiType::NonReplicatedQuery { .. } => "non replicated query",
@@ -671,6 +698,16 @@ pub struct SystemApiImpl<A: SystemStateAccessor> {
     /// communication between the sandboxed canister process and the main
     /// replica process.
     static_system_state: StaticSystemState,
+
+    /// Decides what happens when the execution runs out of instructions in
+    /// the current slice.
+    out_of_instructions_handler: Arc<dyn OutOfInstructionsHandler>,
+
+    /// The number of instructions executed in all completed slices.
+    instructions_executed_in_previous_slices: NumInstructions,
+
+    /// The instruction limit of the current slice.
+    current_slice_instruction_limit: NumInstructions,
 }
 
 impl<A: SystemStateAccessor> SystemApiImpl<A> {
@@ -690,7 +727,9 @@ impl<A: SystemStateAccessor> SystemApiImpl<A> {
             canister_current_memory_usage,
             execution_parameters.subnet_available_memory.clone(),
         );
-        let stable_memory = StableMemory::new(stable_memory);
+        let stable_memory =
+            StableMemory::new(stable_memory, execution_parameters.max_stable_memory_size);
+        let current_slice_instruction_limit = execution_parameters.instruction_limit;
 
         Self {
             execution_error: None,
@@ -701,9 +740,45 @@ impl<A: SystemStateAccessor> SystemApiImpl<A> {
             stable_memory,
             static_system_state,
             log,
+            out_of_instructions_handler: Arc::new(DefaultOutOfInstructionsHandler),
+            instructions_executed_in_previous_slices: NumInstructions::from(0),
+            current_slice_instruction_limit,
         }
     }
 
+    /// Replaces the handler that is invoked when the execution runs out of
+    /// instructions in the current slice. Must be called before the
+    /// execution starts.
+    pub fn set_out_of_instructions_handler(&mut self, handler: Arc<dyn OutOfInstructionsHandler>) {
+        self.current_slice_instruction_limit = std::cmp::min(
+            self.execution_parameters.instruction_limit,
+            handler.slice_instruction_limit(),
+        );
+        self.out_of_instructions_handler = handler;
+    }
+
+    /// Returns the instruction limit of the current slice, i.e. the value
+    /// the instruction counter should be initialized with.
+    pub fn slice_instruction_limit(&self) -> NumInstructions {
+        self.current_slice_instruction_limit
+    }
+
+    /// Returns the number of instructions left of the whole message
+    /// instruction limit given the current value of the instruction counter.
+    pub fn num_instructions_left(&self, instruction_counter: i64) -> NumInstructions {
+        let executed_in_current_slice = self
+            .current_slice_instruction_limit
+            .get()
+            .saturating_sub(instruction_counter.max(0) as u64);
+        NumInstructions::from(
+            self.execution_parameters
+                .instruction_limit
+                .get()
+                .saturating_sub(self.instructions_executed_in_previous_slices.get())
+                .saturating_sub(executed_in_current_slice),
+        )
+    }
+
     pub fn take_execution_result(&mut self) -> HypervisorResult<Option<WasmResult>> {
         if let Some(err) = self.execution_error.take() {
             // Return allocated memory in case of failed message execution.
@@ -716,7 +791,7 @@ impl<A: SystemStateAccessor> SystemApiImpl<A> {
             | ApiType::Init { .. }
             | ApiType::PreUpgrade { .. }
             | ApiType::Cleanup { .. }
-            | ApiType::Heartbeat { .. } => Ok(None),
+            | ApiType::SystemTask { .. } => Ok(None),
             ApiType::InspectMessage {
                 message_accepted, ..
             } => {
@@ -765,7 +840,7 @@ impl<A: SystemStateAccessor> SystemApiImpl<A> {
             ApiType::Start { .. }
             | ApiType::Init { .. }
             | ApiType::PreUpgrade { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Cleanup { .. }
             | ApiType::InspectMessage { .. } => None,
             ApiType::Update {
@@ -805,7 +880,7 @@ impl<A: SystemStateAccessor> SystemApiImpl<A> {
         match &self.api_type {
             ApiType::Start { .. }
             | ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
             | ApiType::NonReplicatedQuery { .. }
@@ -821,7 +896,7 @@ impl<A: SystemStateAccessor> SystemApiImpl<A> {
         match &self.api_type {
             ApiType::Start { .. }
             | ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
             | ApiType::NonReplicatedQuery { .. }
@@ -849,7 +924,7 @@ impl<A: SystemStateAccessor> SystemApiImpl<A> {
             ApiType::Update {
                 outgoing_request, ..
             }
-            | ApiType::Heartbeat {
+            | ApiType::SystemTask {
                 outgoing_request, ..
             }
             | ApiType::ReplyCallback {
@@ -879,7 +954,7 @@ impl<A: SystemStateAccessor> SystemApiImpl<A> {
         match &self.api_type {
             ApiType::Start {} => Err(self.error_for(method_name)),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -898,7 +973,7 @@ impl<A: SystemStateAccessor> SystemApiImpl<A> {
         match &self.api_type {
             ApiType::Start { .. }
             | ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
             | ApiType::PreUpgrade { .. }
@@ -922,7 +997,7 @@ impl<A: SystemStateAccessor> SystemApiImpl<A> {
         match &self.api_type {
             ApiType::Start { .. }
             | ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
             | ApiType::NonReplicatedQuery { .. }
@@ -946,7 +1021,7 @@ impl<A: SystemStateAccessor> SystemApiImpl<A> {
         match &mut self.api_type {
             ApiType::Start { .. }
             | ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::PreUpgrade { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -970,7 +1045,7 @@ impl<A: SystemStateAccessor> SystemApiImpl<A> {
         match &mut self.api_type {
             ApiType::Start { .. }
             | ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
             | ApiType::PreUpgrade { .. }
@@ -1043,6 +1118,24 @@ impl<A: SystemStateAccessor> SystemApiImpl<A> {
             }
         }
     }
+
+    // Pushes an inter-canister query to the output queue. Queries are free,
+    // so neither cycles are withdrawn nor memory is reserved for the request.
+    //
+    // On queue full returns `Ok(RejectCode::SysTransient as i32)`.
+    fn push_output_query_request(&mut self, req: Request) -> HypervisorResult<i32> {
+        match self.system_state_accessor.push_output_query_request(req) {
+            Ok(()) => Ok(0),
+            Err((StateError::QueueFull { .. }, request)) => {
+                self.system_state_accessor
+                    .unregister_callback(request.sender_reply_callback);
+                Ok(RejectCode::SysTransient as i32)
+            }
+            Err((err, _)) => {
+                unreachable!("Unexpected error while pushing to output queue: {}", err)
+            }
+        }
+    }
 }
 
 impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
@@ -1077,7 +1170,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
     fn ic0_msg_caller_size(&self) -> HypervisorResult<u32> {
         match &self.api_type {
             ApiType::Start { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplyCallback { .. }
             | ApiType::RejectCallback { .. } => Err(self.error_for("ic0_msg_caller_size")),
@@ -1099,7 +1192,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
     ) -> HypervisorResult<()> {
         match &self.api_type {
             ApiType::Start { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplyCallback { .. }
             | ApiType::RejectCallback { .. } => Err(self.error_for("ic0_msg_caller_copy")),
@@ -1123,7 +1216,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
         match &self.api_type {
             ApiType::Start { .. }
             | ApiType::Cleanup { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::RejectCallback { .. }
             | ApiType::PreUpgrade { .. } => Err(self.error_for("ic0_msg_arg_data_size")),
             ApiType::Init {
@@ -1156,7 +1249,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
     ) -> HypervisorResult<()> {
         match &self.api_type {
             ApiType::Start { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Cleanup { .. }
             | ApiType::RejectCallback { .. }
             | ApiType::PreUpgrade { .. } => Err(self.error_for("ic0_msg_arg_data_copy")),
@@ -1200,7 +1293,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
             | ApiType::Cleanup { .. }
             | ApiType::Update { .. }
             | ApiType::ReplyCallback { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::ReplicatedQuery { .. }
             | ApiType::NonReplicatedQuery { .. }
             | ApiType::Init { .. } => Err(self.error_for("ic0_msg_method_name_size")),
@@ -1222,7 +1315,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
             | ApiType::PreUpgrade { .. }
             | ApiType::Update { .. }
             | ApiType::ReplyCallback { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::ReplicatedQuery { .. }
             | ApiType::NonReplicatedQuery { .. }
             | ApiType::Init { .. } => Err(self.error_for("ic0_msg_method_name_copy")),
@@ -1249,7 +1342,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
             | ApiType::Cleanup { .. }
             | ApiType::Update { .. }
             | ApiType::ReplyCallback { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::ReplicatedQuery { .. }
             | ApiType::NonReplicatedQuery { .. }
             | ApiType::Init { .. } => Err(self.error_for("ic0_accept_message")),
@@ -1381,7 +1474,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
         match &self.api_type {
             ApiType::Start { .. } => Err(self.error_for("ic0_canister_self_size")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Cleanup { .. }
             | ApiType::Update { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -1408,7 +1501,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
         match &self.api_type {
             ApiType::Start { .. } => Err(self.error_for("ic0_canister_self_copy")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Cleanup { .. }
             | ApiType::Update { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -1432,7 +1525,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
         match &self.api_type {
             ApiType::Start {} => Err(self.error_for("ic0_controller_size")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -1456,7 +1549,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
         match &self.api_type {
             ApiType::Start {} => Err(self.error_for("ic0_controller_copy")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -1514,7 +1607,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
                 query_kind: NonReplicatedQueryKind::Stateful,
                 ..
             }
-            | ApiType::Heartbeat {
+            | ApiType::SystemTask {
                 call_context_id,
                 own_subnet_id,
                 routing_table,
@@ -1596,7 +1689,11 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
                     sender_reply_callback: callback_id,
                     payment: Cycles::zero(),
                 };
-                self.push_output_request(msg)
+                if matches!(self.api_type, ApiType::NonReplicatedQuery { .. }) {
+                    self.push_output_query_request(msg)
+                } else {
+                    self.push_output_request(msg)
+                }
             }
         }
     }
@@ -1632,7 +1729,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
                 query_kind: NonReplicatedQueryKind::Stateful,
                 ..
             }
-            | ApiType::Heartbeat {
+            | ApiType::SystemTask {
                 outgoing_request, ..
             }
             | ApiType::ReplyCallback {
@@ -1684,7 +1781,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
                 query_kind: NonReplicatedQueryKind::Stateful,
                 ..
             }
-            | ApiType::Heartbeat {
+            | ApiType::SystemTask {
                 outgoing_request, ..
             }
             | ApiType::ReplyCallback {
@@ -1721,7 +1818,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
                 query_kind: NonReplicatedQueryKind::Stateful,
                 ..
             }
-            | ApiType::Heartbeat {
+            | ApiType::SystemTask {
                 outgoing_request, ..
             }
             | ApiType::ReplyCallback {
@@ -1776,7 +1873,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
                 subnet_records,
                 ..
             }
-            | ApiType::Heartbeat {
+            | ApiType::SystemTask {
                 call_context_id,
                 own_subnet_id,
                 own_subnet_type,
@@ -1852,7 +1949,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
                     &self.system_state_accessor,
                     &self.log,
                 )?;
-                self.push_output_request(req)
+                self.push_output_query_request(req)
             }
         }
     }
@@ -1861,7 +1958,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
         match &self.api_type {
             ApiType::Start {} => Err(self.error_for("ic0_stable_size")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -1877,7 +1974,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
         match &self.api_type {
             ApiType::Start {} => Err(self.error_for("ic0_stable_grow")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -1913,7 +2010,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
         match &self.api_type {
             ApiType::Start {} => Err(self.error_for("ic0_stable_read")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -1937,7 +2034,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
         match &self.api_type {
             ApiType::Start {} => Err(self.error_for("ic0_stable_write")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -1959,7 +2056,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
         match &self.api_type {
             ApiType::Start {} => Err(self.error_for("ic0_stable64_size")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -1975,7 +2072,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
         match &self.api_type {
             ApiType::Start {} => Err(self.error_for("ic0_stable64_grow")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -2011,7 +2108,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
         match &self.api_type {
             ApiType::Start {} => Err(self.error_for("ic0_stable64_read")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -2035,7 +2132,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
         match &self.api_type {
             ApiType::Start {} => Err(self.error_for("ic0_stable64_write")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -2057,7 +2154,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
         match &self.api_type {
             ApiType::Start { .. } => Err(self.error_for("ic0_time")),
             ApiType::Init { time, .. }
-            | ApiType::Heartbeat { time, .. }
+            | ApiType::SystemTask { time, .. }
             | ApiType::Update { time, .. }
             | ApiType::Cleanup { time, .. }
             | ApiType::NonReplicatedQuery { time, .. }
@@ -2069,8 +2166,44 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
         }
     }
 
-    fn out_of_instructions(&self) -> HypervisorError {
-        HypervisorError::InstructionLimitExceeded
+    fn ic0_performance_counter(
+        &self,
+        counter_type: u32,
+        instruction_counter: i64,
+    ) -> HypervisorResult<u64> {
+        match counter_type {
+            0 => Ok(self.execution_parameters.instruction_limit.get()
+                - self.num_instructions_left(instruction_counter).get()),
+            _ => Err(ContractViolation(format!(
+                "ic0_performance_counter: unsupported counter type {}",
+                counter_type
+            ))),
+        }
+    }
+
+    fn out_of_instructions(&mut self, instruction_counter: i64) -> HypervisorResult<i64> {
+        let executed_in_current_slice = (self.current_slice_instruction_limit.get() as i128
+            - instruction_counter as i128)
+            .max(0) as u64;
+        let instructions_executed = NumInstructions::from(
+            self.instructions_executed_in_previous_slices
+                .get()
+                .saturating_add(executed_in_current_slice),
+        );
+        let instruction_limit = self.execution_parameters.instruction_limit;
+        if instructions_executed >= instruction_limit {
+            return Err(HypervisorError::InstructionLimitExceeded);
+        }
+        self.out_of_instructions_handler
+            .out_of_instructions(instructions_executed)?;
+        self.instructions_executed_in_previous_slices = instructions_executed;
+        self.current_slice_instruction_limit = std::cmp::min(
+            self.out_of_instructions_handler.slice_instruction_limit(),
+            instruction_limit - instructions_executed,
+        );
+        // The instructions executed beyond the end of the previous slice are
+        // accounted for above, so the new slice starts from a full counter.
+        Ok(self.current_slice_instruction_limit.get() as i64)
     }
 
     fn update_available_memory(
@@ -2166,6 +2299,29 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
         Ok(())
     }
 
+    fn ic0_global_timer_set(&mut self, time: Time) -> HypervisorResult<Time> {
+        match &self.api_type {
+            ApiType::Start { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::InspectMessage { .. } => Err(self.error_for("ic0_global_timer_set")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::PreUpgrade { .. } => {
+                let previous = self.system_state_accessor.global_timer_set(
+                    CanisterTimer::from_nanos_since_unix_epoch(time.as_nanos_since_unix_epoch()),
+                );
+                Ok(Time::from_nanos_since_unix_epoch(
+                    previous.to_nanos_since_unix_epoch(),
+                ))
+            }
+        }
+    }
+
     fn ic0_data_certificate_present(&self) -> HypervisorResult<i32> {
         match &self.api_type {
             ApiType::Start { .. }
@@ -2176,7 +2332,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
             | ApiType::PreUpgrade { .. }
             | ApiType::InspectMessage { .. }
             | ApiType::Update { .. }
-            | ApiType::Heartbeat { .. } => Ok(0),
+            | ApiType::SystemTask { .. } => Ok(0),
             ApiType::ReplicatedQuery {
                 data_certificate, ..
             }
@@ -2193,7 +2349,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
         match &self.api_type {
             ApiType::Start { .. }
             | ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::ReplyCallback { .. }
             | ApiType::RejectCallback { .. }
@@ -2222,7 +2378,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
         match &self.api_type {
             ApiType::Start { .. }
             | ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::ReplyCallback { .. }
             | ApiType::RejectCallback { .. }
@@ -2279,7 +2435,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
             | ApiType::NonReplicatedQuery { .. }
             | ApiType::InspectMessage { .. } => Err(self.error_for("ic0_certified_data_set")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::ReplyCallback { .. }
             | ApiType::RejectCallback { .. }
@@ -2320,7 +2476,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
             | ApiType::NonReplicatedQuery { .. }
             | ApiType::Init { .. }
             | ApiType::Cleanup { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::ReplyCallback { .. }
             | ApiType::RejectCallback { .. }
@@ -2344,7 +2500,7 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
             | ApiType::InspectMessage { .. } => Err(self.error_for("ic0_mint_cycles")),
 
             ApiType::Update { nns_subnet_id, .. }
-            | ApiType::Heartbeat { nns_subnet_id, .. }
+            | ApiType::SystemTask { nns_subnet_id, .. }
             | ApiType::ReplyCallback { nns_subnet_id, .. }
             | ApiType::RejectCallback { nns_subnet_id, .. } => {
                 self.system_state_accessor
@@ -2368,6 +2524,10 @@ impl<A: SystemStateAccessor> SystemApi for SystemApiImpl<A> {
             "[Canister {}] {}",
             self.static_system_state.canister_id, msg
         );
+        if let Some(time) = self.api_type.canister_log_time() {
+            self.system_state_accessor
+                .append_canister_log(time, msg.into_bytes());
+        }
     }
 
     fn ic0_trap(&self, src: u32, size: u32, heap: &[u8]) -> HypervisorError {
@@ -5,10 +5,8 @@ use ic_interfaces::execution_environment::{
     TrapCode::{HeapOutOfBounds, StableMemoryOutOfBounds, StableMemoryTooBigFor32Bit},
 };
 use ic_replicated_state::{canister_state::WASM_PAGE_SIZE_IN_BYTES, page_map, NumWasmPages};
-use ic_types::MAX_STABLE_MEMORY_IN_BYTES;
+use ic_types::{NumBytes, MAX_STABLE_MEMORY_IN_BYTES};
 
-const MAX_64_BIT_STABLE_MEMORY_IN_PAGES: usize =
-    (MAX_STABLE_MEMORY_IN_BYTES / WASM_PAGE_SIZE_IN_BYTES as u64) as usize;
 const MAX_32_BIT_STABLE_MEMORY_IN_PAGES: usize = 64 * 1024; // 4GiB
 
 /// Essentially the same as a `page_map::Memory`, but we use a `Buffer` instead
@@ -23,13 +21,20 @@ pub struct StableMemory {
     pub stable_memory_buffer: page_map::Buffer,
     /// The size of the canister's stable memory.
     pub stable_memory_size: NumWasmPages,
+    /// The maximum size of the stable memory in Wasm pages that the 64-bit
+    /// API can grow it to.
+    max_64_bit_stable_memory_in_pages: u64,
 }
 
 impl StableMemory {
-    pub fn new(stable_memory: ic_replicated_state::Memory) -> Self {
+    /// Creates a stable memory that can grow up to `max_size`, capped at
+    /// `MAX_STABLE_MEMORY_IN_BYTES`.
+    pub fn new(stable_memory: ic_replicated_state::Memory, max_size: NumBytes) -> Self {
+        let max_size = max_size.get().min(MAX_STABLE_MEMORY_IN_BYTES);
         Self {
             stable_memory_buffer: page_map::Buffer::new(stable_memory.page_map),
             stable_memory_size: stable_memory.size,
+            max_64_bit_stable_memory_in_pages: max_size / WASM_PAGE_SIZE_IN_BYTES as u64,
         }
     }
 
@@ -115,7 +120,7 @@ impl StableMemory {
         let initial_page_count = self.stable64_size()?;
 
         let (page_count, overflow) = additional_pages.overflowing_add(initial_page_count);
-        if overflow || page_count > MAX_64_BIT_STABLE_MEMORY_IN_PAGES as u64 {
+        if overflow || page_count > self.max_64_bit_stable_memory_in_pages {
             return Ok(-1);
         }
 
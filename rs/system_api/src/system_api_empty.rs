@@ -182,7 +182,10 @@ impl SystemApi for SystemApiEmpty {
     fn ic0_time(&self) -> HypervisorResult<Time> {
         unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
     }
-    fn out_of_instructions(&self) -> HypervisorError {
+    fn ic0_performance_counter(&self, _: u32, _: i64) -> HypervisorResult<u64> {
+        unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
+    }
+    fn out_of_instructions(&mut self, _: i64) -> HypervisorResult<i64> {
         unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
     }
     fn update_available_memory(&mut self, _: i32, _: u32) -> HypervisorResult<i32> {
@@ -220,6 +223,9 @@ impl SystemApi for SystemApiEmpty {
     fn ic0_certified_data_set(&mut self, _: u32, _: u32, _: &[u8]) -> HypervisorResult<()> {
         unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
     }
+    fn ic0_global_timer_set(&mut self, _: Time) -> HypervisorResult<Time> {
+        unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
+    }
     fn ic0_data_certificate_present(&self) -> HypervisorResult<i32> {
         unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
     }
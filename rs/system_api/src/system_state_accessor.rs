@@ -1,10 +1,10 @@
 use ic_base_types::NumBytes;
 use ic_interfaces::execution_environment::HypervisorResult;
-use ic_replicated_state::StateError;
+use ic_replicated_state::{CanisterTimer, StateError};
 use ic_types::{
     messages::{CallContextId, CallbackId, Request},
     methods::Callback,
-    ComputeAllocation, Cycles, SubnetId,
+    ComputeAllocation, Cycles, SubnetId, Time,
 };
 
 /// The abstract interface through which canister user code can
@@ -40,6 +40,12 @@ pub trait SystemStateAccessor {
     /// Set certified data.
     fn set_certified_data(&self, data: Vec<u8>);
 
+    /// Sets the global timer of the canister and returns the previous one.
+    fn global_timer_set(&self, timer: CanisterTimer) -> CanisterTimer;
+
+    /// Appends a record to the canister log.
+    fn append_canister_log(&self, time: Time, content: Vec<u8>);
+
     /// Registers callback for call return.
     fn register_callback(&self, callback: Callback) -> CallbackId;
 
@@ -53,4 +59,8 @@ pub trait SystemStateAccessor {
         canister_compute_allocation: ComputeAllocation,
         msg: Request,
     ) -> Result<(), (StateError, Request)>;
+
+    /// Pushes an outgoing inter-canister query. Queries are free, so unlike
+    /// `push_output_request()` no cycles are withdrawn for the request.
+    fn push_output_query_request(&self, msg: Request) -> Result<(), (StateError, Request)>;
 }
@@ -1,10 +1,10 @@
 use ic_cycles_account_manager::{CyclesAccountManager, CyclesAccountManagerError};
 use ic_interfaces::execution_environment::{HypervisorError, HypervisorResult};
-use ic_replicated_state::{StateError, SystemState};
+use ic_replicated_state::{CanisterTimer, StateError, SystemState};
 use ic_types::{
     messages::{CallContextId, CallbackId, Request},
     methods::Callback,
-    ComputeAllocation, Cycles, SubnetId,
+    ComputeAllocation, Cycles, SubnetId, Time,
 };
 use std::ops::DerefMut;
 use std::{cell::RefCell, sync::Arc};
@@ -117,6 +117,17 @@ impl SystemStateAccessor for SystemStateAccessorDirect {
         self.system_state.borrow_mut().certified_data = data;
     }
 
+    fn global_timer_set(&self, timer: CanisterTimer) -> CanisterTimer {
+        std::mem::replace(&mut self.system_state.borrow_mut().global_timer, timer)
+    }
+
+    fn append_canister_log(&self, time: Time, content: Vec<u8>) {
+        self.system_state
+            .borrow_mut()
+            .canister_log
+            .add_record(time.as_nanos_since_unix_epoch(), content);
+    }
+
     fn register_callback(&self, callback: Callback) -> CallbackId {
         let mut system_state = self.system_state.borrow_mut();
         // A call context manager exists as the canister is either in
@@ -151,4 +162,8 @@ impl SystemStateAccessor for SystemStateAccessorDirect {
         }
         self.system_state.borrow_mut().push_output_request(msg)
     }
+
+    fn push_output_query_request(&self, msg: Request) -> Result<(), (StateError, Request)> {
+        self.system_state.borrow_mut().push_output_request(msg)
+    }
 }
@@ -14,7 +14,8 @@ use ic_test_utilities::{
 };
 use ic_types::{
     messages::{CallContextId, RejectContext},
-    ComputeAllocation, Cycles, NumInstructions,
+    methods::SystemMethod,
+    ComputeAllocation, Cycles, NumInstructions, MAX_STABLE_MEMORY_IN_BYTES,
 };
 use maplit::btreemap;
 
@@ -25,6 +26,7 @@ pub fn execution_parameters() -> ExecutionParameters {
     ExecutionParameters {
         instruction_limit: NumInstructions::new(5_000_000_000),
         canister_memory_limit: NumBytes::new(4 << 30),
+        max_stable_memory_size: NumBytes::new(MAX_STABLE_MEMORY_IN_BYTES),
         subnet_available_memory: SubnetAvailableMemory::new(i64::MAX / 2),
         compute_allocation: ComputeAllocation::default(),
         subnet_type: SubnetType::Application,
@@ -84,7 +86,21 @@ impl ApiTypeBuilder {
     }
 
     pub fn build_heartbeat_api(self) -> ApiType {
-        ApiType::heartbeat(
+        ApiType::system_task(
+            SystemMethod::CanisterHeartbeat,
+            mock_time(),
+            CallContextId::from(1),
+            self.own_subnet_id,
+            self.own_subnet_type,
+            self.nns_subnet_id,
+            self.routing_table,
+            self.subnet_records,
+        )
+    }
+
+    pub fn build_global_timer_api(self) -> ApiType {
+        ApiType::system_task(
+            SystemMethod::CanisterGlobalTimer,
             mock_time(),
             CallContextId::from(1),
             self.own_subnet_id,
@@ -7,7 +7,7 @@ use ic_logger::replica_logger::no_op_logger;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
     canister_state::ENFORCE_MESSAGE_MEMORY_USAGE, testing::CanisterQueuesTesting, CallOrigin,
-    Memory, NumWasmPages, PageMap, SystemState,
+    CanisterTimer, Memory, NumWasmPages, PageMap, SystemState,
 };
 use ic_system_api::{
     ApiType, NonReplicatedQueryKind, StaticSystemState, SystemApiImpl, SystemStateAccessorDirect,
@@ -24,7 +24,7 @@ use ic_test_utilities::{
 use ic_types::{
     messages::{CallContextId, CallbackId, RejectContext, MAX_RESPONSE_COUNT_BYTES},
     user_error::RejectCode,
-    ComputeAllocation, CountBytes, Cycles, NumBytes, NumInstructions,
+    ComputeAllocation, CountBytes, Cycles, NumBytes, NumInstructions, Time,
 };
 use std::convert::TryInto;
 use std::{convert::From, sync::Arc};
@@ -111,6 +111,7 @@ fn test_canister_init_support() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_performance_counter(0, 0));
     assert_api_supported(api.ic0_canister_cycle_balance());
     assert_api_supported(api.ic0_canister_cycles_balance128(0, &mut []));
     assert_api_not_supported(api.ic0_msg_cycles_available());
@@ -123,6 +124,7 @@ fn test_canister_init_support() {
     assert_api_not_supported(api.ic0_data_certificate_size());
     assert_api_not_supported(api.ic0_data_certificate_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_certified_data_set(0, 0, &[]));
+    assert_api_supported(api.ic0_global_timer_set(mock_time()));
     assert_api_supported(api.ic0_canister_status());
     assert_api_not_supported(api.ic0_mint_cycles(0));
 }
@@ -170,6 +172,7 @@ fn test_canister_update_support() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_performance_counter(0, 0));
     assert_api_supported(api.ic0_canister_cycle_balance());
     assert_api_supported(api.ic0_canister_cycles_balance128(0, &mut []));
     assert_api_supported(api.ic0_msg_cycles_available());
@@ -182,6 +185,7 @@ fn test_canister_update_support() {
     assert_api_not_supported(api.ic0_data_certificate_size());
     assert_api_not_supported(api.ic0_data_certificate_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_certified_data_set(0, 0, &[]));
+    assert_api_supported(api.ic0_global_timer_set(mock_time()));
     assert_api_supported(api.ic0_canister_status());
     assert_api_supported(api.ic0_mint_cycles(0));
 }
@@ -227,6 +231,7 @@ fn test_canister_replicated_query_support() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_performance_counter(0, 0));
     assert_api_supported(api.ic0_canister_cycle_balance());
     assert_api_supported(api.ic0_canister_cycles_balance128(0, &mut []));
     assert_api_not_supported(api.ic0_msg_cycles_available());
@@ -239,6 +244,7 @@ fn test_canister_replicated_query_support() {
     assert_api_not_supported(api.ic0_data_certificate_size());
     assert_api_not_supported(api.ic0_data_certificate_copy(0, 0, 0, &mut []));
     assert_api_not_supported(api.ic0_certified_data_set(0, 0, &[]));
+    assert_api_not_supported(api.ic0_global_timer_set(mock_time()));
     assert_api_supported(api.ic0_canister_status());
     assert_api_not_supported(api.ic0_mint_cycles(0));
 }
@@ -284,6 +290,7 @@ fn test_canister_pure_query_support() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_performance_counter(0, 0));
     assert_api_supported(api.ic0_canister_cycle_balance());
     assert_api_supported(api.ic0_canister_cycles_balance128(0, &mut []));
     assert_api_not_supported(api.ic0_msg_cycles_available());
@@ -296,6 +303,7 @@ fn test_canister_pure_query_support() {
     assert_api_not_supported(api.ic0_data_certificate_size());
     assert_api_not_supported(api.ic0_data_certificate_copy(0, 0, 0, &mut []));
     assert_api_not_supported(api.ic0_certified_data_set(0, 0, &[]));
+    assert_api_not_supported(api.ic0_global_timer_set(mock_time()));
     assert_api_supported(api.ic0_canister_status());
     assert_api_not_supported(api.ic0_mint_cycles(0));
 }
@@ -351,6 +359,7 @@ fn test_canister_stateful_query_support() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_performance_counter(0, 0));
     assert_api_supported(api.ic0_canister_cycle_balance());
     assert_api_supported(api.ic0_canister_cycles_balance128(0, &mut []));
     assert_api_not_supported(api.ic0_msg_cycles_available());
@@ -363,6 +372,7 @@ fn test_canister_stateful_query_support() {
     assert_api_supported(api.ic0_data_certificate_size());
     assert_api_supported(api.ic0_data_certificate_copy(0, 0, 0, &mut []));
     assert_api_not_supported(api.ic0_certified_data_set(0, 0, &[]));
+    assert_api_not_supported(api.ic0_global_timer_set(mock_time()));
     assert_api_supported(api.ic0_canister_status());
     assert_api_not_supported(api.ic0_mint_cycles(0));
 }
@@ -410,6 +420,7 @@ fn test_reply_api_support_on_nns() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_performance_counter(0, 0));
     assert_api_supported(api.ic0_canister_cycle_balance());
     assert_api_supported(api.ic0_canister_cycles_balance128(0, &mut []));
     assert_api_supported(api.ic0_msg_cycles_available());
@@ -422,6 +433,7 @@ fn test_reply_api_support_on_nns() {
     assert_api_not_supported(api.ic0_data_certificate_size());
     assert_api_not_supported(api.ic0_data_certificate_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_certified_data_set(0, 0, &[]));
+    assert_api_supported(api.ic0_global_timer_set(mock_time()));
     assert_api_supported(api.ic0_canister_status());
     assert_api_supported(api.ic0_mint_cycles(0));
 }
@@ -467,6 +479,7 @@ fn test_reply_api_support_non_nns() {
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_performance_counter(0, 0));
     assert_api_supported(api.ic0_canister_cycle_balance());
     assert_api_supported(api.ic0_canister_cycles_balance128(0, &mut []));
     assert_api_supported(api.ic0_msg_cycles_available());
@@ -479,6 +492,7 @@ fn test_reply_api_support_non_nns() {
     assert_api_not_supported(api.ic0_data_certificate_size());
     assert_api_not_supported(api.ic0_data_certificate_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_certified_data_set(0, 0, &[]));
+    assert_api_supported(api.ic0_global_timer_set(mock_time()));
     assert_api_supported(api.ic0_canister_status());
     assert_api_not_supported(api.ic0_mint_cycles(0));
 }
@@ -529,6 +543,7 @@ fn test_reject_api_support_on_nns() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_performance_counter(0, 0));
     assert_api_supported(api.ic0_canister_cycle_balance());
     assert_api_supported(api.ic0_canister_cycles_balance128(0, &mut []));
     assert_api_supported(api.ic0_msg_cycles_available());
@@ -541,6 +556,7 @@ fn test_reject_api_support_on_nns() {
     assert_api_not_supported(api.ic0_data_certificate_size());
     assert_api_not_supported(api.ic0_data_certificate_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_certified_data_set(0, 0, &[]));
+    assert_api_supported(api.ic0_global_timer_set(mock_time()));
     assert_api_supported(api.ic0_canister_status());
     assert_api_supported(api.ic0_mint_cycles(0));
 }
@@ -589,6 +605,7 @@ fn test_reject_api_support_non_nns() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_performance_counter(0, 0));
     assert_api_supported(api.ic0_canister_cycle_balance());
     assert_api_supported(api.ic0_canister_cycles_balance128(0, &mut []));
     assert_api_supported(api.ic0_msg_cycles_available());
@@ -601,6 +618,7 @@ fn test_reject_api_support_non_nns() {
     assert_api_not_supported(api.ic0_data_certificate_size());
     assert_api_not_supported(api.ic0_data_certificate_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_certified_data_set(0, 0, &[]));
+    assert_api_supported(api.ic0_global_timer_set(mock_time()));
     assert_api_supported(api.ic0_canister_status());
     assert_api_not_supported(api.ic0_mint_cycles(0));
 }
@@ -646,6 +664,7 @@ fn test_pre_upgrade_support() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_performance_counter(0, 0));
     assert_api_supported(api.ic0_canister_cycle_balance());
     assert_api_supported(api.ic0_canister_cycles_balance128(0, &mut []));
     assert_api_not_supported(api.ic0_msg_cycles_available());
@@ -658,6 +677,7 @@ fn test_pre_upgrade_support() {
     assert_api_not_supported(api.ic0_data_certificate_size());
     assert_api_not_supported(api.ic0_data_certificate_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_certified_data_set(0, 0, &[]));
+    assert_api_supported(api.ic0_global_timer_set(mock_time()));
     assert_api_supported(api.ic0_canister_status());
     assert_api_not_supported(api.ic0_mint_cycles(0));
 }
@@ -699,6 +719,7 @@ fn test_start_support() {
     assert_api_not_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_not_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_not_supported(api.ic0_time());
+    assert_api_supported(api.ic0_performance_counter(0, 0));
     assert_api_not_supported(api.ic0_canister_cycle_balance());
     assert_api_not_supported(api.ic0_canister_cycles_balance128(0, &mut []));
     assert_api_not_supported(api.ic0_msg_cycles_available());
@@ -711,6 +732,7 @@ fn test_start_support() {
     assert_api_not_supported(api.ic0_data_certificate_size());
     assert_api_not_supported(api.ic0_data_certificate_copy(0, 0, 0, &mut []));
     assert_api_not_supported(api.ic0_certified_data_set(0, 0, &[]));
+    assert_api_not_supported(api.ic0_global_timer_set(mock_time()));
     assert_api_not_supported(api.ic0_canister_status());
     assert_api_not_supported(api.ic0_mint_cycles(0));
 }
@@ -756,6 +778,7 @@ fn test_cleanup_support() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_performance_counter(0, 0));
     assert_api_supported(api.ic0_canister_cycle_balance());
     assert_api_supported(api.ic0_canister_cycles_balance128(0, &mut []));
     assert_api_not_supported(api.ic0_msg_cycles_available());
@@ -768,6 +791,7 @@ fn test_cleanup_support() {
     assert_api_not_supported(api.ic0_data_certificate_size());
     assert_api_not_supported(api.ic0_data_certificate_copy(0, 0, 0, &mut []));
     assert_api_not_supported(api.ic0_certified_data_set(0, 0, &[]));
+    assert_api_supported(api.ic0_global_timer_set(mock_time()));
     assert_api_supported(api.ic0_canister_status());
     assert_api_not_supported(api.ic0_mint_cycles(0));
 }
@@ -818,6 +842,7 @@ fn test_inspect_message_support() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_performance_counter(0, 0));
     assert_api_supported(api.ic0_canister_cycle_balance());
     assert_api_supported(api.ic0_canister_cycles_balance128(0, &mut []));
     assert_api_not_supported(api.ic0_msg_cycles_available());
@@ -830,6 +855,7 @@ fn test_inspect_message_support() {
     assert_api_not_supported(api.ic0_data_certificate_size());
     assert_api_not_supported(api.ic0_data_certificate_copy(0, 0, 0, &mut []));
     assert_api_not_supported(api.ic0_certified_data_set(0, 0, &[]));
+    assert_api_not_supported(api.ic0_global_timer_set(mock_time()));
     assert_api_supported(api.ic0_canister_status());
     assert_api_not_supported(api.ic0_mint_cycles(0));
 }
@@ -876,6 +902,7 @@ fn test_canister_heartbeat_support() {
     assert_api_supported(api.ic0_stable64_grow(1));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_performance_counter(0, 0));
     assert_api_supported(api.ic0_canister_cycle_balance());
     assert_api_supported(api.ic0_canister_cycles_balance128(0, &mut []));
     assert_api_not_supported(api.ic0_msg_cycles_available());
@@ -888,6 +915,67 @@ fn test_canister_heartbeat_support() {
     assert_api_not_supported(api.ic0_data_certificate_size());
     assert_api_not_supported(api.ic0_data_certificate_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_certified_data_set(0, 0, &[]));
+    assert_api_supported(api.ic0_global_timer_set(mock_time()));
+    assert_api_supported(api.ic0_canister_status());
+    assert_api_not_supported(api.ic0_mint_cycles(0));
+}
+
+#[test]
+fn test_canister_global_timer_support() {
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+
+    let mut api = get_system_api(
+        ApiTypeBuilder::new().build_global_timer_api(),
+        get_system_state(),
+        cycles_account_manager,
+    );
+
+    assert_api_not_supported(api.ic0_msg_caller_size());
+    assert_api_not_supported(api.ic0_msg_caller_copy(0, 0, 0, &mut []));
+    assert_api_not_supported(api.ic0_msg_arg_data_size());
+    assert_api_not_supported(api.ic0_msg_arg_data_copy(0, 0, 0, &mut []));
+    assert_api_not_supported(api.ic0_msg_method_name_size());
+    assert_api_not_supported(api.ic0_msg_method_name_copy(0, 0, 0, &mut []));
+    assert_api_not_supported(api.ic0_accept_message());
+    assert_api_not_supported(api.ic0_msg_reply());
+    assert_api_not_supported(api.ic0_msg_reply_data_append(0, 0, &[]));
+    assert_api_not_supported(api.ic0_msg_reject(0, 0, &[]));
+    assert_api_not_supported(api.ic0_msg_reject_code());
+    assert_api_not_supported(api.ic0_msg_reject_msg_size());
+    assert_api_not_supported(api.ic0_msg_reject_msg_copy(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_canister_self_size());
+    assert_api_supported(api.ic0_canister_self_copy(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_controller_size());
+    assert_api_supported(api.ic0_controller_copy(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_call_simple(0, 0, 0, 0, 0, 0, 0, 0, 0, 0, &[]));
+    assert_api_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
+    assert_api_supported(api.ic0_call_data_append(0, 0, &[]));
+    assert_api_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_supported(api.ic0_call_cycles_add(0));
+    assert_api_supported(api.ic0_call_perform());
+    assert_api_supported(api.ic0_stable_size());
+    assert_api_supported(api.ic0_stable_grow(1));
+    assert_api_supported(api.ic0_stable_read(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_stable_write(0, 0, 0, &[]));
+    assert_api_supported(api.ic0_stable64_size());
+    assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_stable64_grow(1));
+    assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
+    assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_performance_counter(0, 0));
+    assert_api_supported(api.ic0_canister_cycle_balance());
+    assert_api_supported(api.ic0_canister_cycles_balance128(0, &mut []));
+    assert_api_not_supported(api.ic0_msg_cycles_available());
+    assert_api_not_supported(api.ic0_msg_cycles_available128(0, &mut []));
+    assert_api_not_supported(api.ic0_msg_cycles_refunded());
+    assert_api_not_supported(api.ic0_msg_cycles_refunded128(0, &mut []));
+    assert_api_not_supported(api.ic0_msg_cycles_accept(0));
+    assert_api_not_supported(api.ic0_msg_cycles_accept128(Cycles::zero(), 0, &mut []));
+    assert_api_supported(api.ic0_data_certificate_present());
+    assert_api_not_supported(api.ic0_data_certificate_size());
+    assert_api_not_supported(api.ic0_data_certificate_copy(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_certified_data_set(0, 0, &[]));
+    assert_api_supported(api.ic0_global_timer_set(mock_time()));
     assert_api_supported(api.ic0_canister_status());
     assert_api_not_supported(api.ic0_mint_cycles(0));
 }
@@ -935,6 +1023,7 @@ fn test_canister_heartbeat_support_nns() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_performance_counter(0, 0));
     assert_api_supported(api.ic0_canister_cycle_balance());
     assert_api_supported(api.ic0_canister_cycles_balance128(0, &mut []));
     assert_api_not_supported(api.ic0_msg_cycles_available());
@@ -947,6 +1036,7 @@ fn test_canister_heartbeat_support_nns() {
     assert_api_not_supported(api.ic0_data_certificate_size());
     assert_api_not_supported(api.ic0_data_certificate_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_certified_data_set(0, 0, &[]));
+    assert_api_supported(api.ic0_global_timer_set(mock_time()));
     assert_api_supported(api.ic0_canister_status());
     // Only supported on NNS.
     assert_api_supported(api.ic0_mint_cycles(0));
@@ -1205,6 +1295,50 @@ fn test_msg_cycles_refunded_traps() {
     assert_eq!(heap, incoming_cycles.get().to_le_bytes());
 }
 
+#[test]
+fn performance_counter_returns_instructions_executed() {
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+    let api = get_system_api(
+        ApiTypeBuilder::new().build_update_api(),
+        get_system_state(),
+        cycles_account_manager,
+    );
+    let instruction_limit = execution_parameters().instruction_limit.get() as i64;
+
+    assert_eq!(api.ic0_performance_counter(0, instruction_limit), Ok(0));
+    assert_eq!(
+        api.ic0_performance_counter(0, instruction_limit - 1234),
+        Ok(1234)
+    );
+    assert!(matches!(
+        api.ic0_performance_counter(1, instruction_limit - 1234),
+        Err(HypervisorError::ContractViolation(_))
+    ));
+}
+
+#[test]
+fn global_timer_set_returns_previous_value() {
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+    let mut api = get_system_api(
+        ApiTypeBuilder::new().build_update_api(),
+        get_system_state(),
+        cycles_account_manager,
+    );
+    let deadline = Time::from_nanos_since_unix_epoch(1);
+
+    assert_eq!(
+        api.ic0_global_timer_set(deadline),
+        Ok(Time::from_nanos_since_unix_epoch(0))
+    );
+    assert_eq!(
+        api.ic0_global_timer_set(Time::from_nanos_since_unix_epoch(0)),
+        Ok(deadline)
+    );
+
+    let system_state = api.release_system_state_accessor().release_system_state();
+    assert_eq!(system_state.global_timer, CanisterTimer::Inactive);
+}
+
 #[test]
 fn certified_data_set() {
     let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
@@ -1555,6 +1689,55 @@ fn stable_grow_returns_allocated_memory_on_error() {
     );
 }
 
+#[test]
+fn stable64_grow_respects_max_stable_memory_size() {
+    let wasm_page_size: u64 = 64 << 10;
+    let max_stable_memory_size = 16 << 30;
+    let system_state = SystemStateBuilder::default().build();
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+    let static_system_state =
+        StaticSystemState::new(&system_state, cycles_account_manager.subnet_type());
+    let system_state_accessor =
+        SystemStateAccessorDirect::new(system_state, Arc::new(cycles_account_manager));
+    let mut api = SystemApiImpl::new(
+        ApiTypeBuilder::new().build_update_api(),
+        system_state_accessor,
+        static_system_state,
+        CANISTER_CURRENT_MEMORY_USAGE,
+        ExecutionParameters {
+            canister_memory_limit: NumBytes::new(32 << 30),
+            max_stable_memory_size: NumBytes::new(max_stable_memory_size),
+            ..execution_parameters()
+        },
+        Memory::default(),
+        no_op_logger(),
+    );
+
+    // Stable memory can grow beyond 8GiB up to the configured maximum.
+    let max_pages = max_stable_memory_size / wasm_page_size;
+    assert_eq!(api.ic0_stable64_grow(max_pages - 1).unwrap(), 0);
+    assert_eq!(api.ic0_stable64_grow(1).unwrap(), (max_pages - 1) as i64);
+    assert_eq!(api.ic0_stable64_size().unwrap(), max_pages);
+    assert_eq!(api.ic0_stable64_grow(1).unwrap(), -1);
+    assert_eq!(api.ic0_stable64_size().unwrap(), max_pages);
+    assert_eq!(
+        api.get_current_memory_usage(),
+        NumBytes::new(max_stable_memory_size)
+    );
+
+    // The last bytes of the stable memory are accessible.
+    let mut heap = vec![0; 16];
+    heap[..8].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+    let offset = max_stable_memory_size - 8;
+    api.ic0_stable64_write(offset, 0, 8, &heap).unwrap();
+    api.ic0_stable64_read(8, offset, 8, &mut heap).unwrap();
+    assert_eq!(heap[8..], [1, 2, 3, 4, 5, 6, 7, 8]);
+    assert_eq!(
+        api.ic0_stable64_read(8, offset + 1, 8, &mut heap),
+        Err(HypervisorError::Trapped(TrapCode::StableMemoryOutOfBounds))
+    );
+}
+
 #[test]
 fn update_available_memory_updates_subnet_available_memory() {
     let wasm_page_size = 64 << 10;
@@ -74,6 +74,38 @@ fn push_output_request_fails_not_enough_cycles_for_request() {
     );
 }
 
+#[test]
+fn push_output_query_request_does_not_withdraw_cycles() {
+    let cycles_account_manager = Arc::new(
+        CyclesAccountManagerBuilder::new()
+            .with_max_num_instructions(MAX_NUM_INSTRUCTIONS)
+            .build(),
+    );
+    // The balance does not cover the cost of a regular request.
+    let system_state = SystemState::new_running(
+        canister_test_id(0),
+        user_test_id(1).get(),
+        Cycles::from(10),
+        NumSeconds::from(100_000),
+    );
+
+    let system_state_accessor =
+        SystemStateAccessorDirect::new(system_state, cycles_account_manager);
+
+    assert_eq!(
+        system_state_accessor.push_output_query_request(
+            RequestBuilder::default()
+                .sender(canister_test_id(0))
+                .build(),
+        ),
+        Ok(())
+    );
+    assert_eq!(
+        system_state_accessor.canister_cycles_balance(),
+        Cycles::from(10)
+    );
+}
+
 #[test]
 fn push_output_request_fails_not_enough_cycles_for_response() {
     let request = RequestBuilder::default()
@@ -1,12 +1,31 @@
 use ic_interfaces::certified_stream_store::{
     CertifiedStreamStore, DecodeStreamError, EncodeStreamError,
 };
+use ic_replicated_state::ReplicatedState;
 use ic_types::{
     xnet::{CertifiedStreamSlice, StreamIndex, StreamSlice},
     RegistryVersion, SubnetId,
 };
 use mockall::*;
 
+/// Encodes the slice of the stream from `remote_subnet` to `own_subnet`
+/// starting at the first message that `own_state` has not inducted yet.
+/// Returns `None` if `remote_store` has no certified stream to `own_subnet`.
+pub fn stream_slice_from(
+    remote_store: &dyn CertifiedStreamStore,
+    remote_subnet: SubnetId,
+    own_subnet: SubnetId,
+    own_state: &ReplicatedState,
+) -> Option<CertifiedStreamSlice> {
+    let begin = own_state
+        .get_stream(&remote_subnet)
+        .map(|stream| stream.signals_end())
+        .unwrap_or_else(|| StreamIndex::from(0));
+    remote_store
+        .encode_certified_stream_slice(own_subnet, Some(begin), Some(begin), None, None)
+        .ok()
+}
+
 mock! {
     pub CertifiedStreamStore {}
 
@@ -5,18 +5,30 @@ use ic_interfaces::registry::{
     RegistryClientVersionedResult,
 };
 use ic_interfaces::time_source::TimeSource;
-use ic_protobuf::registry::subnet::v1::{CatchUpPackageContents, SubnetListRecord, SubnetRecord};
-use ic_registry_client::fake::FakeRegistryClient;
+use ic_metrics::MetricsRegistry;
+use ic_protobuf::registry::{
+    provisional_whitelist::v1::ProvisionalWhitelist as PbProvisionalWhitelist,
+    routing_table::v1::RoutingTable as PbRoutingTable,
+    subnet::v1::{CatchUpPackageContents, SubnetListRecord, SubnetRecord},
+};
+use ic_protobuf::types::v1::PrincipalId as PrincipalIdIdProto;
+use ic_protobuf::types::v1::SubnetId as SubnetIdProto;
+use ic_registry_client::{client::RegistryClientImpl, fake::FakeRegistryClient};
 use ic_registry_common::proto_registry_data_provider::ProtoRegistryDataProvider;
 use ic_registry_keys::{
-    make_catch_up_package_contents_key, make_subnet_list_record_key, make_subnet_record_key,
+    make_catch_up_package_contents_key, make_provisional_whitelist_record_key,
+    make_routing_table_record_key, make_subnet_list_record_key, make_subnet_record_key,
+    ROOT_SUBNET_ID_KEY,
 };
+use ic_registry_provisional_whitelist::ProvisionalWhitelist;
+use ic_registry_routing_table::{routing_table_insert_subnet, RoutingTable};
 use ic_registry_subnet_type::SubnetType;
 use ic_types::crypto::threshold_sig::ni_dkg::NiDkgTag;
 use ic_types::{registry::RegistryClientError, PrincipalId, Time};
 use ic_types::{NodeId, RegistryVersion, ReplicaVersion, SubnetId};
 use mockall::predicate::*;
 use mockall::*;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 mock! {
@@ -145,6 +157,96 @@ pub fn add_subnet_record(
         .unwrap();
 }
 
+/// Builds a routing table that assigns a canister ID range to each of the
+/// given subnets, in order.
+pub fn routing_table_for_subnets(subnet_ids: &[SubnetId]) -> RoutingTable {
+    let mut routing_table = RoutingTable::new(BTreeMap::new());
+    for subnet_id in subnet_ids {
+        routing_table_insert_subnet(&mut routing_table, *subnet_id).unwrap();
+    }
+    routing_table
+}
+
+/// Returns a registry client for in-process test environments that run
+/// several subnets of the given type. The registry contains a record for each
+/// of the given subnets, each consisting of a single node, and a routing table
+/// built with [routing_table_for_subnets]. All canisters are whitelisted.
+pub fn multi_subnet_registry(
+    metrics_registry: &MetricsRegistry,
+    subnets: &[(SubnetId, NodeId)],
+    root_subnet_id: SubnetId,
+    subnet_type: SubnetType,
+) -> Arc<RegistryClientImpl> {
+    let registry_version = RegistryVersion::from(1);
+    let data_provider = Arc::new(ProtoRegistryDataProvider::new());
+
+    let root_subnet_id_proto = SubnetIdProto {
+        principal_id: Some(PrincipalIdIdProto {
+            raw: root_subnet_id.get_ref().to_vec(),
+        }),
+    };
+    data_provider
+        .add(
+            ROOT_SUBNET_ID_KEY,
+            registry_version,
+            Some(root_subnet_id_proto),
+        )
+        .unwrap();
+
+    let subnet_ids: Vec<_> = subnets.iter().map(|(subnet_id, _)| *subnet_id).collect();
+    let pb_routing_table = PbRoutingTable::from(routing_table_for_subnets(&subnet_ids));
+    data_provider
+        .add(
+            &make_routing_table_record_key(),
+            registry_version,
+            Some(pb_routing_table),
+        )
+        .unwrap();
+    let pb_whitelist = PbProvisionalWhitelist::from(ProvisionalWhitelist::All);
+    data_provider
+        .add(
+            &make_provisional_whitelist_record_key(),
+            registry_version,
+            Some(pb_whitelist),
+        )
+        .unwrap();
+
+    for (subnet_id, node_id) in subnets {
+        let mut record = SubnetRecordBuilder::from(&[*node_id]).build();
+        record.subnet_type = i32::from(subnet_type);
+
+        insert_initial_dkg_transcript(registry_version.get(), *subnet_id, &record, &data_provider);
+        data_provider
+            .add(
+                &make_subnet_record_key(*subnet_id),
+                registry_version,
+                Some(record),
+            )
+            .unwrap();
+    }
+    // Set subnetwork list(needed for filling network_topology.nns_subnet_id)
+    let subnet_list_record = SubnetListRecord {
+        subnets: subnet_ids
+            .iter()
+            .map(|subnet_id| subnet_id.get().into_vec())
+            .collect(),
+    };
+    data_provider
+        .add(
+            make_subnet_list_record_key().as_str(),
+            registry_version,
+            Some(subnet_list_record),
+        )
+        .unwrap();
+
+    let registry_client = Arc::new(RegistryClientImpl::new(
+        data_provider,
+        Some(metrics_registry),
+    ));
+    registry_client.fetch_and_start_polling().unwrap();
+    registry_client
+}
+
 /// Provides a `SubnetRecord` to unit tests
 pub fn test_subnet_record() -> SubnetRecord {
     SubnetRecord {
@@ -1,3 +1,4 @@
+use crate::consensus::fake::Fake;
 use crate::types::ids::subnet_test_id;
 use ic_crypto_sha::Sha256;
 use ic_crypto_tree_hash::{LabeledTree, MixedHashTree};
@@ -13,9 +14,13 @@ use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::ReplicatedState;
 use ic_types::crypto::CryptoHash;
 use ic_types::{
-    consensus::certification::Certification,
+    consensus::{
+        certification::{Certification, CertificationContent},
+        ThresholdSignature,
+    },
     crypto::threshold_sig::ni_dkg::{NiDkgId, NiDkgTag, NiDkgTargetSubnet},
     crypto::CryptoHashOf,
+    crypto::Signed,
     xnet::{CertifiedStreamSlice, StreamIndex, StreamSlice},
     CryptoHashOfPartialState, CryptoHashOfState, Height, RegistryVersion, SubnetId,
 };
@@ -23,6 +28,23 @@ use std::sync::{Arc, RwLock};
 
 use mockall::*;
 
+/// Certifies all states that the given state manager has committed but not
+/// certified yet with a fake signature. Useful for test environments without
+/// consensus, as stream slices can only be encoded from certified states.
+pub fn certify_states_with_fake_signature(
+    state_manager: &dyn StateManager<State = ReplicatedState>,
+) {
+    for (height, hash) in state_manager.list_state_hashes_to_certify() {
+        state_manager.deliver_state_certification(Certification {
+            height,
+            signed: Signed {
+                content: CertificationContent::new(hash),
+                signature: ThresholdSignature::fake(),
+            },
+        });
+    }
+}
+
 mock! {
     pub StateManager {}
 
@@ -1,4 +1,6 @@
-use ic_types::batch::{BatchPayload, IngressPayload, SelfValidatingPayload, XNetPayload};
+use ic_types::batch::{
+    BatchPayload, CanisterHttpPayload, IngressPayload, SelfValidatingPayload, XNetPayload,
+};
 
 pub struct PayloadBuilder {
     payload: BatchPayload,
@@ -13,6 +15,7 @@ impl Default for PayloadBuilder {
                 xnet: super::xnet_payload::XNetPayloadBuilder::default().build(),
                 // TODO(MR-70): use payload builder
                 self_validating: SelfValidatingPayload::new(),
+                canister_http: CanisterHttpPayload::default(),
             },
         }
     }
@@ -36,6 +39,12 @@ impl PayloadBuilder {
         self
     }
 
+    /// Set the canister_http field to canister_http_payload.
+    pub fn canister_http(mut self, canister_http_payload: CanisterHttpPayload) -> Self {
+        self.payload.canister_http = canister_http_payload;
+        self
+    }
+
     /// Return the built Payload.
     pub fn build(&self) -> BatchPayload {
         self.payload.clone()
@@ -51,6 +60,7 @@ fn batch_payload_serialize_then_deserialize() {
         ingress: IngressPayload::from(vec![ingress_0]),
         xnet: XNetPayload::default(),
         self_validating: SelfValidatingPayload::default(),
+        canister_http: CanisterHttpPayload::default(),
     };
     let vec = serde_cbor::ser::to_vec(&batch_payload_0).unwrap();
     let batch_payload_1: BatchPayload = serde_cbor::de::from_slice(&vec).unwrap();
@@ -92,6 +102,7 @@ fn payload_serialize_then_deserialize() {
         ingress: IngressPayload::from(vec![ingress_0]),
         xnet: XNetPayload::default(),
         self_validating: SelfValidatingPayload::default(),
+        canister_http: CanisterHttpPayload::default(),
     };
     let payload_0 = Payload::new(
         ic_crypto::crypto_hash,
@@ -45,6 +45,8 @@ pub fn basic_config_with_all_features_enabled() -> InternetComputer {
         SubnetFeatures {
             ecdsa_signatures: true,
             canister_sandboxing: false,
+            http_requests: false,
+            wasm_simd: false,
         },
     ))
 }
@@ -70,6 +70,7 @@ impl From<ErrorCode> for RejectCode {
             CanisterWasmModuleNotFound => DestinationInvalid,
             CanisterAlreadyInstalled => DestinationInvalid,
             CanisterEmpty => DestinationInvalid,
+            CanisterSnapshotNotFound => DestinationInvalid,
             CanisterNonEmpty => CanisterError,
             CanisterOutOfCycles => CanisterError,
             CanisterTrapped => CanisterError,
@@ -96,6 +97,10 @@ impl From<ErrorCode> for RejectCode {
             InsufficientCyclesInCall => CanisterError,
             CanisterWasmEngineError => CanisterError,
             CanisterCyclesLimitExceeded => CanisterError,
+            InsufficientCyclesInAllocationReservation => CanisterError,
+            ReservedCyclesLimitExceeded => CanisterError,
+            ReservedCyclesLimitIsTooLow => CanisterError,
+            QueryCallGraphTotalInstructionLimitExceeded => CanisterError,
         }
     }
 }
@@ -117,6 +122,7 @@ pub enum ErrorCode {
     CanisterAlreadyInstalled = 303,
     CanisterWasmModuleNotFound = 304,
     CanisterEmpty = 305,
+    CanisterSnapshotNotFound = 306,
     InsufficientTransferFunds = 401,
     InsufficientMemoryAllocation = 402,
     InsufficientCyclesForCreateCanister = 403,
@@ -143,6 +149,10 @@ pub enum ErrorCode {
     InsufficientCyclesInCall = 520,
     CanisterWasmEngineError = 521,
     CanisterCyclesLimitExceeded = 522,
+    InsufficientCyclesInAllocationReservation = 523,
+    ReservedCyclesLimitExceeded = 524,
+    ReservedCyclesLimitIsTooLow = 525,
+    QueryCallGraphTotalInstructionLimitExceeded = 526,
 }
 
 impl From<candid::Error> for UserError {
@@ -167,6 +177,7 @@ impl TryFrom<u64> for ErrorCode {
             303 => Ok(ErrorCode::CanisterAlreadyInstalled),
             304 => Ok(ErrorCode::CanisterWasmModuleNotFound),
             305 => Ok(ErrorCode::CanisterEmpty),
+            306 => Ok(ErrorCode::CanisterSnapshotNotFound),
             401 => Ok(ErrorCode::InsufficientTransferFunds),
             402 => Ok(ErrorCode::InsufficientMemoryAllocation),
             403 => Ok(ErrorCode::InsufficientCyclesForCreateCanister),
@@ -193,6 +204,10 @@ impl TryFrom<u64> for ErrorCode {
             520 => Ok(ErrorCode::InsufficientCyclesInCall),
             521 => Ok(ErrorCode::CanisterWasmEngineError),
             522 => Ok(ErrorCode::CanisterCyclesLimitExceeded),
+            523 => Ok(ErrorCode::InsufficientCyclesInAllocationReservation),
+            524 => Ok(ErrorCode::ReservedCyclesLimitExceeded),
+            525 => Ok(ErrorCode::ReservedCyclesLimitIsTooLow),
+            526 => Ok(ErrorCode::QueryCallGraphTotalInstructionLimitExceeded),
             _ => Err(ProxyDecodeError::ValueOutOfRange {
                 typ: "ErrorCode",
                 err: err.to_string(),
@@ -22,14 +22,20 @@ pub enum Method {
     CanisterStatus,
     CreateCanister,
     DeleteCanister,
+    DeleteCanisterSnapshot,
     DepositCycles,
+    FetchCanisterLogs,
+    HttpRequest,
     InstallCode,
+    ListCanisterSnapshots,
+    LoadCanisterSnapshot,
     RawRand,
     SetController,
     SetupInitialDKG,
     SignWithECDSA,
     StartCanister,
     StopCanister,
+    TakeCanisterSnapshot,
     UninstallCode,
     UpdateSettings,
 
@@ -84,6 +90,7 @@ impl From<CanisterId> for CanisterIdRecord {
 ///     controller : principal;
 ///     compute_allocation: nat;
 ///     memory_allocation: opt nat;
+///     reserved_cycles_limit: nat;
 /// })`
 #[derive(CandidType, Deserialize, Debug, Eq, PartialEq)]
 pub struct DefiniteCanisterSettingsArgs {
@@ -92,6 +99,7 @@ pub struct DefiniteCanisterSettingsArgs {
     compute_allocation: candid::Nat,
     memory_allocation: candid::Nat,
     freezing_threshold: candid::Nat,
+    reserved_cycles_limit: candid::Nat,
 }
 
 impl DefiniteCanisterSettingsArgs {
@@ -101,6 +109,7 @@ impl DefiniteCanisterSettingsArgs {
         compute_allocation: u64,
         memory_allocation: Option<u64>,
         freezing_threshold: u64,
+        reserved_cycles_limit: u128,
     ) -> Self {
         let memory_allocation = match memory_allocation {
             None => candid::Nat::from(0),
@@ -112,12 +121,17 @@ impl DefiniteCanisterSettingsArgs {
             compute_allocation: candid::Nat::from(compute_allocation),
             memory_allocation,
             freezing_threshold: candid::Nat::from(freezing_threshold),
+            reserved_cycles_limit: candid::Nat::from(reserved_cycles_limit),
         }
     }
 
     pub fn controllers(&self) -> Vec<PrincipalId> {
         self.controllers.clone()
     }
+
+    pub fn reserved_cycles_limit(&self) -> u128 {
+        self.reserved_cycles_limit.0.to_u128().unwrap()
+    }
 }
 
 impl Payload<'_> for DefiniteCanisterSettingsArgs {}
@@ -186,6 +200,7 @@ impl Payload<'_> for CanisterStatusResult {}
 ///     controller: principal;
 ///     memory_size: nat;
 ///     cycles: nat;
+///     reserved_cycles: nat;
 /// })`
 #[derive(CandidType, Debug, Deserialize, Eq, PartialEq)]
 pub struct CanisterStatusResultV2 {
@@ -198,6 +213,7 @@ pub struct CanisterStatusResultV2 {
     // this is for compat with Spec 0.12/0.13
     balance: Vec<(Vec<u8>, candid::Nat)>,
     freezing_threshold: candid::Nat,
+    reserved_cycles: candid::Nat,
 }
 
 impl CanisterStatusResultV2 {
@@ -212,6 +228,8 @@ impl CanisterStatusResultV2 {
         compute_allocation: u64,
         memory_allocation: Option<u64>,
         freezing_threshold: u64,
+        reserved_cycles: u128,
+        reserved_cycles_limit: u128,
     ) -> Self {
         Self {
             status,
@@ -228,8 +246,10 @@ impl CanisterStatusResultV2 {
                 compute_allocation,
                 memory_allocation,
                 freezing_threshold,
+                reserved_cycles_limit,
             ),
             freezing_threshold: candid::Nat::from(freezing_threshold),
+            reserved_cycles: candid::Nat::from(reserved_cycles),
         }
     }
 
@@ -256,6 +276,14 @@ impl CanisterStatusResultV2 {
     pub fn freezing_threshold(&self) -> u64 {
         self.freezing_threshold.0.to_u64().unwrap()
     }
+
+    pub fn reserved_cycles(&self) -> u128 {
+        self.reserved_cycles.0.to_u128().unwrap()
+    }
+
+    pub fn settings(&self) -> &DefiniteCanisterSettingsArgs {
+        &self.settings
+    }
 }
 
 impl Payload<'_> for CanisterStatusResultV2 {}
@@ -381,12 +409,26 @@ impl UpdateSettingsArgs {
 
 impl Payload<'_> for UpdateSettingsArgs {}
 
+/// Determines who is allowed to fetch the log of a canister.
+///
+/// Struct used for encoding/decoding
+/// `variant { controllers; public }`
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub enum LogVisibility {
+    #[serde(rename = "controllers")]
+    Controllers,
+    #[serde(rename = "public")]
+    Public,
+}
+
 /// Struct used for encoding/decoding
 /// `(record {
 ///     controller : opt principal;
 ///     controllers: opt vec principal;
 ///     compute_allocation: opt nat;
 ///     memory_allocation: opt nat;
+///     log_visibility: opt log_visibility;
+///     reserved_cycles_limit: opt nat;
 /// })`
 #[derive(Default, Clone, CandidType, Deserialize, Debug)]
 pub struct CanisterSettingsArgs {
@@ -395,6 +437,8 @@ pub struct CanisterSettingsArgs {
     pub compute_allocation: Option<candid::Nat>,
     pub memory_allocation: Option<candid::Nat>,
     pub freezing_threshold: Option<candid::Nat>,
+    pub log_visibility: Option<LogVisibility>,
+    pub reserved_cycles_limit: Option<candid::Nat>,
 }
 
 impl Payload<'_> for CanisterSettingsArgs {}
@@ -632,3 +676,188 @@ impl SignWithECDSAArgs {
         }
     }
 }
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     canister_id : principal;
+/// })`
+#[derive(CandidType, Deserialize, Debug)]
+pub struct FetchCanisterLogsRequest {
+    pub canister_id: PrincipalId,
+}
+
+impl FetchCanisterLogsRequest {
+    pub fn new(canister_id: CanisterId) -> Self {
+        Self {
+            canister_id: canister_id.into(),
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        // Safe as this was converted from CanisterId when Self was constructed.
+        CanisterId::new(self.canister_id).unwrap()
+    }
+}
+
+impl Payload<'_> for FetchCanisterLogsRequest {}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     idx : nat64;
+///     timestamp_nanos : nat64;
+///     content : blob;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, Eq, PartialEq)]
+pub struct CanisterLogRecord {
+    pub idx: u64,
+    pub timestamp_nanos: u64,
+    #[serde(with = "serde_bytes")]
+    pub content: Vec<u8>,
+}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     canister_log_records : vec canister_log_record;
+/// })`
+#[derive(CandidType, Deserialize, Debug, Eq, PartialEq)]
+pub struct FetchCanisterLogsResponse {
+    pub canister_log_records: Vec<CanisterLogRecord>,
+}
+
+impl Payload<'_> for FetchCanisterLogsResponse {}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     canister_id : principal;
+///     replace_snapshot : opt blob;
+/// })`
+#[derive(CandidType, Deserialize, Debug)]
+pub struct TakeCanisterSnapshotArgs {
+    pub canister_id: PrincipalId,
+    pub replace_snapshot: Option<Vec<u8>>,
+}
+
+impl TakeCanisterSnapshotArgs {
+    pub fn new(canister_id: CanisterId, replace_snapshot: Option<Vec<u8>>) -> Self {
+        Self {
+            canister_id: canister_id.into(),
+            replace_snapshot,
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        // Safe as this was converted from CanisterId when Self was constructed.
+        CanisterId::new(self.canister_id).unwrap()
+    }
+}
+
+impl Payload<'_> for TakeCanisterSnapshotArgs {}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     canister_id : principal;
+///     snapshot_id : blob;
+/// })`
+///
+/// Used by both `load_canister_snapshot` and `delete_canister_snapshot`.
+#[derive(CandidType, Deserialize, Debug)]
+pub struct CanisterSnapshotArgs {
+    pub canister_id: PrincipalId,
+    #[serde(with = "serde_bytes")]
+    pub snapshot_id: Vec<u8>,
+}
+
+impl CanisterSnapshotArgs {
+    pub fn new(canister_id: CanisterId, snapshot_id: Vec<u8>) -> Self {
+        Self {
+            canister_id: canister_id.into(),
+            snapshot_id,
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        // Safe as this was converted from CanisterId when Self was constructed.
+        CanisterId::new(self.canister_id).unwrap()
+    }
+}
+
+impl Payload<'_> for CanisterSnapshotArgs {}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     id : blob;
+///     taken_at_timestamp : nat64;
+///     total_size : nat64;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, Eq, PartialEq)]
+pub struct CanisterSnapshotResponse {
+    #[serde(with = "serde_bytes")]
+    pub id: Vec<u8>,
+    pub taken_at_timestamp: u64,
+    pub total_size: u64,
+}
+
+impl Payload<'_> for CanisterSnapshotResponse {}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     name : text;
+///     value : text;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, Eq, PartialEq)]
+pub struct HttpHeader {
+    pub name: String,
+    pub value: String,
+}
+
+/// The HTTP methods that canisters can use in `http_request`.
+///
+/// Encoded as `variant { get; head; post }`.
+#[derive(Clone, Copy, CandidType, Deserialize, Debug, Eq, PartialEq)]
+pub enum HttpMethod {
+    #[serde(rename = "get")]
+    Get,
+    #[serde(rename = "head")]
+    Head,
+    #[serde(rename = "post")]
+    Post,
+}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     url : text;
+///     method : variant { get; head; post };
+///     headers : vec http_header;
+///     body : opt blob;
+///     transform_method_name : opt text;
+/// })`
+///
+/// If `transform_method_name` is set, the response is passed to the query
+/// method of the calling canister with that name before it is delivered. The
+/// transform method receives and returns a `CanisterHttpResponsePayload`.
+#[derive(Clone, CandidType, Deserialize, Debug, Eq, PartialEq)]
+pub struct CanisterHttpRequestArgs {
+    pub url: String,
+    pub method: HttpMethod,
+    pub headers: Vec<HttpHeader>,
+    pub body: Option<Vec<u8>>,
+    pub transform_method_name: Option<String>,
+}
+
+impl Payload<'_> for CanisterHttpRequestArgs {}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     status : nat64;
+///     headers : vec http_header;
+///     body : blob;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, Eq, PartialEq)]
+pub struct CanisterHttpResponsePayload {
+    pub status: u64,
+    pub headers: Vec<HttpHeader>,
+    #[serde(with = "serde_bytes")]
+    pub body: Vec<u8>,
+}
+
+impl Payload<'_> for CanisterHttpResponsePayload {}
@@ -2,6 +2,7 @@
 //! Consensus and Message Routing.
 use super::{
     artifact::IngressMessageId,
+    canister_http::{CanisterHttpResponseShare, CanisterHttpResponseWithConsensus},
     messages::{MessageId, Response, SignedIngress, EXPECTED_MESSAGE_ID_LENGTH},
     xnet::CertifiedStreamSlice,
     CountBytes, Height, Randomness, RegistryVersion, SubnetId, Time,
@@ -59,12 +60,14 @@ impl ValidationContext {
 
 /// The payload of a batch.
 ///
-/// Contains ingress and XNet messages.
+/// Contains ingress and XNet messages, as well as the responses to canister
+/// HTTP requests.
 #[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct BatchPayload {
     pub ingress: IngressPayload,
     pub xnet: XNetPayload,
     pub self_validating: SelfValidatingPayload,
+    pub canister_http: CanisterHttpPayload,
 }
 
 /// Return ingress messages, xnet messages, and consensus responses.
@@ -75,11 +78,13 @@ impl BatchPayload {
         ingress: IngressPayload,
         xnet: XNetPayload,
         self_validating: SelfValidatingPayload,
+        canister_http: CanisterHttpPayload,
     ) -> Self {
         BatchPayload {
             ingress,
             xnet,
             self_validating,
+            canister_http,
         }
     }
 
@@ -91,7 +96,9 @@ impl BatchPayload {
     }
 
     pub fn is_empty(&self) -> bool {
-        self.ingress.is_empty() && self.xnet.stream_slices.is_empty()
+        self.ingress.is_empty()
+            && self.xnet.stream_slices.is_empty()
+            && self.canister_http.is_empty()
     }
 }
 
@@ -125,6 +132,63 @@ impl CountBytes for SelfValidatingPayload {
     }
 }
 
+/// Payload that contains the responses to canister HTTP requests, as well as
+/// the shares of the block maker for responses that not enough replicas
+/// agree on yet.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CanisterHttpPayload {
+    pub responses: Vec<CanisterHttpResponseWithConsensus>,
+    pub shares: Vec<CanisterHttpResponseShare>,
+}
+
+impl CanisterHttpPayload {
+    pub fn is_empty(&self) -> bool {
+        self.responses.is_empty() && self.shares.is_empty()
+    }
+}
+
+impl From<&CanisterHttpPayload> for pb::CanisterHttpPayload {
+    fn from(payload: &CanisterHttpPayload) -> Self {
+        Self {
+            responses: payload.responses.iter().map(Into::into).collect(),
+            shares: payload.shares.iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl TryFrom<pb::CanisterHttpPayload> for CanisterHttpPayload {
+    type Error = String;
+
+    fn try_from(payload: pb::CanisterHttpPayload) -> Result<Self, Self::Error> {
+        Ok(Self {
+            responses: payload
+                .responses
+                .into_iter()
+                .map(CanisterHttpResponseWithConsensus::try_from)
+                .collect::<Result<Vec<_>, String>>()?,
+            shares: payload
+                .shares
+                .into_iter()
+                .map(CanisterHttpResponseShare::try_from)
+                .collect::<Result<Vec<_>, String>>()?,
+        })
+    }
+}
+
+impl CountBytes for CanisterHttpPayload {
+    fn count_bytes(&self) -> usize {
+        self.responses
+            .iter()
+            .map(|response| response.count_bytes())
+            .sum::<usize>()
+            + self
+                .shares
+                .iter()
+                .map(|share| share.count_bytes())
+                .sum::<usize>()
+    }
+}
+
 /// Payload that contains XNet messages.
 #[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct XNetPayload {
@@ -0,0 +1,290 @@
+//! Types used for the HTTP requests that canisters make to the outside world
+//! through the `http_request` method of the management canister.
+//!
+//! A request is kept in the replicated state until its response is agreed
+//! upon by consensus: every replica signs a [`CanisterHttpResponseShare`] for
+//! the response it received, and a response is only delivered to execution,
+//! as part of a [`crate::batch::CanisterHttpPayload`], together with the
+//! shares of enough replicas that received the same response.
+use crate::{
+    consensus::BasicSignature,
+    crypto::{
+        BasicSig, BasicSigOf, CryptoHash, CryptoHashOf, Signed, SignedBytesWithoutDomainSeparator,
+    },
+    ic00::{HttpHeader, HttpMethod},
+    messages::CallbackId,
+    CountBytes, NodeId, PrincipalId, RegistryVersion,
+};
+use ic_error_types::RejectCode;
+use ic_protobuf::types::v1 as pb;
+use serde::{Deserialize, Serialize};
+use std::convert::{TryFrom, TryInto};
+
+/// The upper bound on the size of the URL of a canister HTTP request.
+pub const MAX_CANISTER_HTTP_URL_SIZE: usize = 8192;
+
+/// The upper bound on the size of a canister HTTP request, including the URL,
+/// the headers and the body.
+pub const MAX_CANISTER_HTTP_REQUEST_BYTES: usize = 2 * 1024 * 1024;
+
+/// The upper bound on the number of canister HTTP requests that can be open
+/// on a subnet at the same time.
+pub const MAX_CANISTER_HTTP_REQUESTS_IN_FLIGHT: usize = 500;
+
+/// The upper bound on the total size of the responses included in a block.
+pub const MAX_CANISTER_HTTP_PAYLOAD_SIZE: usize = 2 * 1024 * 1024;
+
+/// Canister HTTP requests are identified by the callback id under which the
+/// subnet call context manager keeps track of them.
+pub type CanisterHttpRequestId = CallbackId;
+
+/// The HTTP method of a canister HTTP request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CanisterHttpMethod {
+    Get,
+    Head,
+    Post,
+}
+
+impl From<HttpMethod> for CanisterHttpMethod {
+    fn from(method: HttpMethod) -> Self {
+        match method {
+            HttpMethod::Get => CanisterHttpMethod::Get,
+            HttpMethod::Head => CanisterHttpMethod::Head,
+            HttpMethod::Post => CanisterHttpMethod::Post,
+        }
+    }
+}
+
+/// A single header of a canister HTTP request or response.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CanisterHttpHeader {
+    pub name: String,
+    pub value: String,
+}
+
+impl From<HttpHeader> for CanisterHttpHeader {
+    fn from(header: HttpHeader) -> Self {
+        Self {
+            name: header.name,
+            value: header.value,
+        }
+    }
+}
+
+impl From<&CanisterHttpHeader> for HttpHeader {
+    fn from(header: &CanisterHttpHeader) -> Self {
+        Self {
+            name: header.name.clone(),
+            value: header.value.clone(),
+        }
+    }
+}
+
+/// A request that is handed to the HTTP adapter to be sent to a remote
+/// server on behalf of a canister.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CanisterHttpRequest {
+    pub id: CanisterHttpRequestId,
+    pub url: String,
+    pub method: CanisterHttpMethod,
+    pub headers: Vec<CanisterHttpHeader>,
+    pub body: Option<Vec<u8>>,
+}
+
+/// The response to a canister HTTP request, as agreed upon by consensus.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CanisterHttpResponse {
+    pub id: CanisterHttpRequestId,
+    pub content: CanisterHttpResponseContent,
+}
+
+/// The content of a [`CanisterHttpResponse`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CanisterHttpResponseContent {
+    /// The Candid encoded `CanisterHttpResponsePayload` returned by the
+    /// remote server.
+    Success(Vec<u8>),
+    /// The request could not be completed, e.g. because the server was not
+    /// reachable.
+    Reject(CanisterHttpReject),
+}
+
+/// The reason why a canister HTTP request could not be completed.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CanisterHttpReject {
+    pub reject_code: RejectCode,
+    pub message: String,
+}
+
+/// The metadata of a [`CanisterHttpResponse`] that a replica signs to attest
+/// that it received the response.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CanisterHttpResponseMetadata {
+    pub id: CanisterHttpRequestId,
+    pub content_hash: CryptoHashOf<CanisterHttpResponse>,
+    pub registry_version: RegistryVersion,
+}
+
+impl SignedBytesWithoutDomainSeparator for CanisterHttpResponseMetadata {
+    fn as_signed_bytes_without_domain_separator(&self) -> Vec<u8> {
+        serde_cbor::to_vec(&self).unwrap()
+    }
+}
+
+/// The signature of a single replica on the metadata of the response it
+/// received.
+pub type CanisterHttpResponseShare =
+    Signed<CanisterHttpResponseMetadata, BasicSignature<CanisterHttpResponseMetadata>>;
+
+/// A response together with the shares of the replicas that agree on it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CanisterHttpResponseWithConsensus {
+    pub content: CanisterHttpResponse,
+    pub proof: Vec<CanisterHttpResponseShare>,
+}
+
+impl CountBytes for CanisterHttpResponse {
+    fn count_bytes(&self) -> usize {
+        let content_size = match &self.content {
+            CanisterHttpResponseContent::Success(data) => data.len(),
+            CanisterHttpResponseContent::Reject(reject) => {
+                std::mem::size_of::<RejectCode>() + reject.message.len()
+            }
+        };
+        std::mem::size_of::<CanisterHttpRequestId>() + content_size
+    }
+}
+
+impl From<&CanisterHttpResponse> for pb::CanisterHttpResponse {
+    fn from(response: &CanisterHttpResponse) -> Self {
+        let content = match &response.content {
+            CanisterHttpResponseContent::Success(data) => {
+                pb::canister_http_response::Content::Success(data.clone())
+            }
+            CanisterHttpResponseContent::Reject(reject) => {
+                pb::canister_http_response::Content::Reject(pb::CanisterHttpReject {
+                    reject_code: reject.reject_code as u64,
+                    message: reject.message.clone(),
+                })
+            }
+        };
+        Self {
+            id: response.id.get(),
+            content: Some(content),
+        }
+    }
+}
+
+impl TryFrom<pb::CanisterHttpResponse> for CanisterHttpResponse {
+    type Error = String;
+
+    fn try_from(response: pb::CanisterHttpResponse) -> Result<Self, Self::Error> {
+        let content = match response
+            .content
+            .ok_or_else(|| String::from("Error: CanisterHttpResponse missing content"))?
+        {
+            pb::canister_http_response::Content::Success(data) => {
+                CanisterHttpResponseContent::Success(data)
+            }
+            pb::canister_http_response::Content::Reject(reject) => {
+                CanisterHttpResponseContent::Reject(CanisterHttpReject {
+                    reject_code: reject
+                        .reject_code
+                        .try_into()
+                        .map_err(|e| format!("{:?}", e))?,
+                    message: reject.message,
+                })
+            }
+        };
+        Ok(Self {
+            id: CanisterHttpRequestId::from(response.id),
+            content,
+        })
+    }
+}
+
+impl CountBytes for CanisterHttpResponseMetadata {
+    fn count_bytes(&self) -> usize {
+        std::mem::size_of::<CanisterHttpRequestId>()
+            + self.content_hash.get_ref().0.len()
+            + std::mem::size_of::<RegistryVersion>()
+    }
+}
+
+impl CountBytes for CanisterHttpResponseWithConsensus {
+    fn count_bytes(&self) -> usize {
+        self.content.count_bytes()
+            + self
+                .proof
+                .iter()
+                .map(|share| share.count_bytes())
+                .sum::<usize>()
+    }
+}
+
+impl From<&CanisterHttpResponseShare> for pb::CanisterHttpResponseShare {
+    fn from(share: &CanisterHttpResponseShare) -> Self {
+        Self {
+            metadata: Some(pb::CanisterHttpResponseMetadata {
+                id: share.content.id.get(),
+                content_hash: share.content.content_hash.clone().get().0,
+                registry_version: share.content.registry_version.get(),
+            }),
+            signature: share.signature.signature.clone().get().0,
+            signer: share.signature.signer.get().into_vec(),
+        }
+    }
+}
+
+impl TryFrom<pb::CanisterHttpResponseShare> for CanisterHttpResponseShare {
+    type Error = String;
+
+    fn try_from(share: pb::CanisterHttpResponseShare) -> Result<Self, Self::Error> {
+        let metadata = share
+            .metadata
+            .ok_or_else(|| String::from("Error: CanisterHttpResponseShare missing metadata"))?;
+        Ok(Signed {
+            content: CanisterHttpResponseMetadata {
+                id: CanisterHttpRequestId::from(metadata.id),
+                content_hash: CryptoHashOf::from(CryptoHash(metadata.content_hash)),
+                registry_version: RegistryVersion::from(metadata.registry_version),
+            },
+            signature: BasicSignature {
+                signature: BasicSigOf::from(BasicSig(share.signature)),
+                signer: NodeId::from(
+                    PrincipalId::try_from(share.signer).map_err(|e| format!("{:?}", e))?,
+                ),
+            },
+        })
+    }
+}
+
+impl From<&CanisterHttpResponseWithConsensus> for pb::CanisterHttpResponseWithConsensus {
+    fn from(response: &CanisterHttpResponseWithConsensus) -> Self {
+        Self {
+            response: Some((&response.content).into()),
+            proof: response.proof.iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl TryFrom<pb::CanisterHttpResponseWithConsensus> for CanisterHttpResponseWithConsensus {
+    type Error = String;
+
+    fn try_from(response: pb::CanisterHttpResponseWithConsensus) -> Result<Self, Self::Error> {
+        Ok(Self {
+            content: response
+                .response
+                .ok_or_else(|| {
+                    String::from("Error: CanisterHttpResponseWithConsensus missing response")
+                })?
+                .try_into()?,
+            proof: response
+                .proof
+                .into_iter()
+                .map(CanisterHttpResponseShare::try_from)
+                .collect::<Result<Vec<_>, String>>()?,
+        })
+    }
+}
@@ -1220,23 +1220,30 @@ pub fn get_faults_tolerated(n: usize) -> usize {
 impl From<&Block> for pb::Block {
     fn from(block: &Block) -> Self {
         let payload: &BlockPayload = block.payload.as_ref();
-        let (dkg_payload, xnet_payload, ingress_payload, self_validating_payload) =
-            if payload.is_summary() {
-                (
-                    pb::DkgPayload::from(&payload.as_summary().dkg),
-                    None,
-                    None,
-                    None,
-                )
-            } else {
-                let batch = &payload.as_data().batch;
-                (
-                    pb::DkgPayload::from(&payload.as_data().dealings),
-                    Some(pb::XNetPayload::from(&batch.xnet)),
-                    Some(pb::IngressPayload::from(&batch.ingress)),
-                    Some(pb::SelfValidatingPayload::from(&batch.self_validating)),
-                )
-            };
+        let (
+            dkg_payload,
+            xnet_payload,
+            ingress_payload,
+            self_validating_payload,
+            canister_http_payload,
+        ) = if payload.is_summary() {
+            (
+                pb::DkgPayload::from(&payload.as_summary().dkg),
+                None,
+                None,
+                None,
+                None,
+            )
+        } else {
+            let batch = &payload.as_data().batch;
+            (
+                pb::DkgPayload::from(&payload.as_data().dealings),
+                Some(pb::XNetPayload::from(&batch.xnet)),
+                Some(pb::IngressPayload::from(&batch.ingress)),
+                Some(pb::SelfValidatingPayload::from(&batch.self_validating)),
+                Some(pb::CanisterHttpPayload::from(&batch.canister_http)),
+            )
+        };
         Self {
             version: block.version.to_string(),
             parent: block.parent.clone().get().0,
@@ -1249,6 +1256,7 @@ impl From<&Block> for pb::Block {
             xnet_payload,
             ingress_payload,
             self_validating_payload,
+            canister_http_payload,
             payload_hash: block.payload.get_hash().clone().get().0,
         }
     }
@@ -1278,6 +1286,11 @@ impl TryFrom<pb::Block> for Block {
                 .map(crate::batch::SelfValidatingPayload::try_from)
                 .transpose()?
                 .unwrap_or_default(),
+            block
+                .canister_http_payload
+                .map(crate::batch::CanisterHttpPayload::try_from)
+                .transpose()?
+                .unwrap_or_default(),
         );
         let payload = match dkg_payload {
             dkg::Payload::Summary(summary) => {
@@ -1322,6 +1335,12 @@ impl CountBytes for NiDkgId {
     }
 }
 
+impl<T> CountBytes for BasicSignature<T> {
+    fn count_bytes(&self) -> usize {
+        self.signature.get_ref().0.len() + std::mem::size_of::<NodeId>()
+    }
+}
+
 impl<T> CountBytes for ThresholdSignature<T> {
     fn count_bytes(&self) -> usize {
         self.signature.get_ref().0.len() + self.signer.count_bytes()
@@ -1,7 +1,9 @@
 //! Data types used for encoding/decoding the Candid payloads of ic:00.
 pub use ic_ic00_types::{
-    CanisterIdRecord, CanisterSettingsArgs, CanisterStatusResult, CanisterStatusResultV2,
-    CreateCanisterArgs, EmptyBlob, InstallCodeArgs, Method, Payload,
+    CanisterHttpRequestArgs, CanisterHttpResponsePayload, CanisterIdRecord, CanisterSettingsArgs,
+    CanisterSnapshotArgs, CanisterSnapshotResponse, CanisterStatusResult, CanisterStatusResultV2,
+    CreateCanisterArgs, EmptyBlob, HttpHeader, HttpMethod, InstallCodeArgs, Method, Payload,
     ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs, SetControllerArgs,
-    SetupInitialDKGArgs, SetupInitialDKGResponse, UpdateSettingsArgs, IC_00,
+    SetupInitialDKGArgs, SetupInitialDKGResponse, TakeCanisterSnapshotArgs, UpdateSettingsArgs,
+    IC_00,
 };
@@ -56,6 +56,7 @@
 
 pub mod artifact;
 pub mod batch;
+pub mod canister_http;
 pub mod canonical_error;
 pub mod chunkable;
 pub mod consensus;
@@ -473,10 +474,11 @@ pub struct InvalidMemoryAllocationError {
 
 const GB: u64 = 1024 * 1024 * 1024;
 
-/// The upper limit on the stable memory size.
+/// The upper limit on the stable memory size. The effective limit is
+/// configured per subnet type and may be lower.
 /// This constant is used by other crates to define other constants, that's why
 /// it is public and `u64` (`NumBytes` cannot be used in const expressions).
-pub const MAX_STABLE_MEMORY_IN_BYTES: u64 = 8 * GB;
+pub const MAX_STABLE_MEMORY_IN_BYTES: u64 = 64 * GB;
 
 /// The upper limit on the Wasm memory size.
 /// This constant is used by other crates to define other constants, that's why
@@ -1,4 +1,5 @@
 use std::ffi::{OsStr, OsString};
+use std::ops::Range;
 use std::{fs, fs::File, io, io::Error, io::Write, path::Path, path::PathBuf};
 
 /// The character length of the random string used for temporary file names.
@@ -349,6 +350,40 @@ pub fn copy_file_sparse(from: &Path, to: &Path) -> io::Result<u64> {
     fs::copy(from, to)
 }
 
+#[cfg(target_os = "linux")]
+/// Returns the byte ranges of the file that may contain data in ascending
+/// order. Everything outside of these ranges is a hole and reads as zeros.
+/// File systems that do not track holes report the whole file as data.
+pub fn data_ranges(path: &Path) -> io::Result<Vec<Range<u64>>> {
+    use cvt::cvt;
+    use libc::lseek64;
+    use std::os::unix::io::AsRawFd;
+
+    let file = File::open(path)?;
+    let len = file.metadata()?.len() as i64;
+    let fd = file.as_raw_fd();
+
+    let mut ranges = Vec::new();
+    let mut pos: i64 = 0;
+    while pos < len {
+        let start = match cvt(unsafe { lseek64(fd, pos, libc::SEEK_DATA) }) {
+            Ok(start) => start,
+            // The remaining portion of the file is a hole.
+            Err(err) if err.raw_os_error() == Some(libc::ENXIO) => break,
+            Err(err) => return Err(err),
+        };
+        let end = cvt(unsafe { lseek64(fd, start, libc::SEEK_HOLE) })?;
+        ranges.push(start as u64..end as u64);
+        pos = end;
+    }
+    Ok(ranges)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn data_ranges(path: &Path) -> io::Result<Vec<Range<u64>>> {
+    Ok(vec![0..fs::metadata(path)?.len()])
+}
+
 /// Atomically write to `dst` file, using a random file in the parent directory
 /// of `dst` as the temporary file.
 ///
@@ -471,7 +506,7 @@ fn tmp_name() -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::write_atomically_using_tmp_file;
+    use super::{data_ranges, write_atomically_using_tmp_file};
 
     #[test]
     fn test_write_success() {
@@ -524,4 +559,24 @@ mod tests {
             b"original contents".to_vec()
         );
     }
+
+    #[test]
+    fn test_data_ranges_cover_written_data() {
+        use std::io::{Seek, SeekFrom, Write};
+
+        let tmp_dir = tempfile::TempDir::new().expect("failed to create a temporary directory");
+        let path = tmp_dir.path().join("sparse");
+        let mut file = std::fs::File::create(&path).expect("failed to create a file");
+        file.set_len(64 << 20).expect("failed to truncate the file");
+        file.seek(SeekFrom::Start(32 << 20)).unwrap();
+        file.write_all(&[1; 4096]).unwrap();
+        drop(file);
+
+        let ranges = data_ranges(&path).expect("failed to get data ranges");
+        assert!(ranges.windows(2).all(|w| w[0].end <= w[1].start));
+        assert!(ranges
+            .iter()
+            .any(|r| r.start <= 32 << 20 && (32 << 20) + 4096 <= r.end));
+        assert!(ranges.iter().all(|r| r.end <= 64 << 20));
+    }
 }